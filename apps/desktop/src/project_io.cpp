@@ -86,6 +86,20 @@ bool decodeText(std::string_view value,
   return true;
 }
 
+float gainToDb(float gain) {
+  if (!(gain > 0.0F)) {
+    return FF_GAIN_DB_MIN;
+  }
+  return std::clamp(20.0F * std::log10(gain), FF_GAIN_DB_MIN, FF_GAIN_DB_MAX);
+}
+
+float dbToGain(float gain_db) {
+  if (!(gain_db > FF_GAIN_DB_MIN)) {
+    return 0.0F;
+  }
+  return std::pow(10.0F, std::min(gain_db, FF_GAIN_DB_MAX) / 20.0F);
+}
+
 std::string formatFloat(float value) {
   std::ostringstream stream;
   stream.setf(std::ios::fixed, std::ios::floatfield);
@@ -212,6 +226,7 @@ bool saveProjectToFile(const std::filesystem::path& file_path,
 
   output << "BEGIN_KIT\n";
   output << "name=" << encodeText("Desktop Kit") << "\n";
+  output << "gain_units=db\n";
   for (std::size_t track = 0; track < project.tracks.size(); ++track) {
     const auto& track_state = project.tracks[track];
     if (!track_state.sample_path.empty()) {
@@ -222,7 +237,7 @@ bool saveProjectToFile(const std::filesystem::path& file_path,
                           ? -1
                           : std::min(track_state.parameters.choke_group, 15);
     output << "control|" << track << "|"
-           << formatFloat(track_state.parameters.gain) << "|"
+           << formatFloat(gainToDb(track_state.parameters.gain)) << "|"
            << formatFloat(track_state.parameters.pan) << "|"
            << formatFloat(track_state.parameters.filter_cutoff) << "|"
            << formatFloat(track_state.parameters.envelope_decay) << "|"
@@ -234,8 +249,16 @@ bool saveProjectToFile(const std::filesystem::path& file_path,
   output << "BEGIN_PATTERN\n";
   output << "name=" << encodeText("Desktop Pattern") << "\n";
   output << "swing=" << formatFloat(project.swing) << "\n";
+  const std::size_t step_count = std::clamp<std::size_t>(project.step_count, 1, kMaxPatternSteps);
+  const std::size_t bar_count = (step_count + kStepsPerBar - 1) / kStepsPerBar;
+  if (bar_count > 1) {
+    output << "bars=" << bar_count << "\n";
+  }
+  if (step_count % kStepsPerBar != 0) {
+    output << "length=" << step_count << "\n";
+  }
   for (std::size_t track = 0; track < project.pattern.size(); ++track) {
-    for (std::size_t step = 0; step < project.pattern[track].size(); ++step) {
+    for (std::size_t step = 0; step < step_count; ++step) {
       const auto& cell = project.pattern[track][step];
       output << "step|" << track << "|" << step << "|" << (cell.active ? 1 : 0)
              << "|" << static_cast<int>(cell.velocity) << "\n";
@@ -292,6 +315,7 @@ bool loadProjectFromFile(const std::filesystem::path& file_path,
   std::size_t line_index = 1;
   bool parsed_name = false;
   bool in_kit = false;
+  bool kit_gain_in_db = false;
   bool in_pattern = false;
 
   for (; line_index < lines.size(); ++line_index) {
@@ -302,6 +326,7 @@ bool loadProjectFromFile(const std::filesystem::path& file_path,
 
     if (current == "BEGIN_KIT") {
       in_kit = true;
+      kit_gain_in_db = false;
       continue;
     }
     if (current == "END_KIT") {
@@ -330,6 +355,11 @@ bool loadProjectFromFile(const std::filesystem::path& file_path,
     }
 
     if (in_kit) {
+      if (current == "gain_units=db") {
+        kit_gain_in_db = true;
+        continue;
+      }
+
       if (current.rfind("track|", 0) == 0) {
         std::vector<std::string_view> fields;
         splitFields(current.substr(6), '|', &fields);
@@ -391,6 +421,9 @@ bool loadProjectFromFile(const std::filesystem::path& file_path,
           return false;
         }
         params.choke_group = choke;
+        if (kit_gain_in_db) {
+          params.gain = dbToGain(params.gain);
+        }
         continue;
       }
 
@@ -410,10 +443,42 @@ bool loadProjectFromFile(const std::filesystem::path& file_path,
         continue;
       }
 
+      if (current.rfind("bars=", 0) == 0) {
+        std::size_t bars = 0;
+        if (!parseUnsigned(current.substr(5), &bars) || bars == 0 || bars > kMaxPatternBars) {
+          if (error_message != nullptr) {
+            *error_message = "pattern bar count out of range";
+          }
+          return false;
+        }
+        parsed.step_count = bars * kStepsPerBar;
+        continue;
+      }
+
+      if (current.rfind("length=", 0) == 0) {
+        std::size_t length = 0;
+        if (!parseUnsigned(current.substr(7), &length) || length == 0 ||
+            length > kMaxPatternSteps) {
+          if (error_message != nullptr) {
+            *error_message = "pattern length out of range";
+          }
+          return false;
+        }
+        parsed.step_count = length;
+        continue;
+      }
+
       if (current.rfind("step|", 0) == 0) {
         std::vector<std::string_view> fields;
         splitFields(current.substr(5), '|', &fields);
-        if (fields.size() != 4) {
+        // Fields past velocity (probability=, condition=, accent=, ...) are
+        // written by the Rust tools; the desktop sequencer does not play them.
+        const bool extras_named =
+            std::all_of(fields.begin() + std::min<std::size_t>(fields.size(), 4), fields.end(),
+                        [](std::string_view field) {
+                          return field.find('=') != std::string_view::npos;
+                        });
+        if (fields.size() < 4 || !extras_named) {
           if (error_message != nullptr) {
             *error_message = "invalid step line";
           }
@@ -1,6 +1,7 @@
 #pragma once
 
 #include <array>
+#include <cstddef>
 #include <cstdint>
 #include <filesystem>
 #include <string>
@@ -9,6 +10,10 @@
 
 namespace ff::desktop {
 
+inline constexpr std::size_t kStepsPerBar = 16;
+inline constexpr std::size_t kMaxPatternBars = 4;
+inline constexpr std::size_t kMaxPatternSteps = kStepsPerBar * kMaxPatternBars;
+
 struct ProjectStep final {
   bool active = false;
   std::uint8_t velocity = 100;
@@ -23,8 +28,9 @@ struct ProjectModel final {
   std::string name = "Forest Floor Session";
   float bpm = 120.0F;
   float swing = 0.0F;
+  std::size_t step_count = kStepsPerBar;
   std::array<ProjectTrackState, ff::engine::Engine::kTrackCount> tracks;
-  std::array<std::array<ProjectStep, 16>, ff::engine::Engine::kTrackCount> pattern{};
+  std::array<std::array<ProjectStep, kMaxPatternSteps>, ff::engine::Engine::kTrackCount> pattern{};
 };
 
 bool saveProjectToFile(const std::filesystem::path& file_path,
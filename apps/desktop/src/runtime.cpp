@@ -332,7 +332,7 @@ bool Runtime::setStep(std::size_t track_index,
                       std::size_t step_index,
                       bool active,
                       std::uint8_t velocity) noexcept {
-  if (track_index >= kTrackCount || step_index >= kSteps) {
+  if (track_index >= kTrackCount || step_index >= kMaxSteps) {
     return false;
   }
 
@@ -347,7 +347,7 @@ bool Runtime::setStep(std::size_t track_index,
 
 ProjectStep Runtime::step(std::size_t track_index, std::size_t step_index) const noexcept {
   ProjectStep value{};
-  if (track_index >= kTrackCount || step_index >= kSteps) {
+  if (track_index >= kTrackCount || step_index >= kMaxSteps) {
     return value;
   }
 
@@ -357,6 +357,22 @@ ProjectStep Runtime::step(std::size_t track_index, std::size_t step_index) const
   return value;
 }
 
+bool Runtime::setStepCount(std::size_t step_count) noexcept {
+  if (step_count == 0 || step_count > kMaxSteps) {
+    return false;
+  }
+
+  step_count_.store(step_count, std::memory_order_release);
+
+  std::lock_guard<std::mutex> lock(project_mutex_);
+  project_model_.step_count = step_count;
+  return true;
+}
+
+std::size_t Runtime::stepCount() const noexcept {
+  return step_count_.load(std::memory_order_acquire);
+}
+
 bool Runtime::triggerPad(std::size_t track_index, std::uint8_t velocity) noexcept {
   if (track_index >= kTrackCount || velocity == 0) {
     return false;
@@ -419,6 +435,7 @@ bool Runtime::setTrackSampleFromLoaded(std::size_t track_index,
   {
     std::lock_guard<std::mutex> lock(project_mutex_);
     project_model_.tracks[track_index].sample_path = path.string();
+    unresolved_sample_paths_[track_index].clear();
   }
 
   Command command;
@@ -459,8 +476,10 @@ bool Runtime::loadStarterKit(std::string* error_message) {
     setTempoBpm(shipped_project.bpm);
     setSwing(shipped_project.swing);
 
+    setStepCount(shipped_project.step_count);
+
     for (std::size_t track = 0; track < kTrackCount; ++track) {
-      for (std::size_t step = 0; step < kSteps; ++step) {
+      for (std::size_t step = 0; step < kMaxSteps; ++step) {
         const auto& cell = shipped_project.pattern[track][step];
         setStep(track, step, cell.active, cell.velocity);
       }
@@ -546,14 +565,20 @@ bool Runtime::loadProject(const std::filesystem::path& path,
 
   setTempoBpm(loaded.bpm);
   setSwing(loaded.swing);
+  setStepCount(loaded.step_count);
 
   for (std::size_t track = 0; track < kTrackCount; ++track) {
-    for (std::size_t step_index = 0; step_index < kSteps; ++step_index) {
+    for (std::size_t step_index = 0; step_index < kMaxSteps; ++step_index) {
       const auto& cell = loaded.pattern[track][step_index];
       setStep(track, step_index, cell.active, cell.velocity);
     }
   }
 
+  {
+    std::lock_guard<std::mutex> lock(project_mutex_);
+    unresolved_sample_paths_.fill(std::string{});
+  }
+
   for (std::size_t track = 0; track < kTrackCount; ++track) {
     setTrackParameters(track, loaded.tracks[track].parameters);
 
@@ -561,11 +586,15 @@ bool Runtime::loadProject(const std::filesystem::path& path,
       std::string load_error;
       const std::filesystem::path sample_path(loaded.tracks[track].sample_path);
       if (!setTrackSampleFromFile(track, sample_path, &load_error)) {
-        if (error_message != nullptr) {
-          *error_message = "failed loading track sample: " + sample_path.string() +
-                           " (" + load_error + ")";
+        // Keep the project editable: a silent placeholder holds the slot until the path is fixed.
+        LoadedSample placeholder;
+        placeholder.mono.assign(1, 0.0F);
+        placeholder.source_sample_rate_hz = config_.audio.sample_rate_hz;
+        if (!setTrackSampleFromLoaded(track, placeholder, sample_path, error_message)) {
+          return false;
         }
-        return false;
+        std::lock_guard<std::mutex> lock(project_mutex_);
+        unresolved_sample_paths_[track] = sample_path.string();
       }
     }
   }
@@ -578,6 +607,17 @@ bool Runtime::loadProject(const std::filesystem::path& path,
   return true;
 }
 
+std::vector<std::string> Runtime::unresolvedSamples() const {
+  std::lock_guard<std::mutex> lock(project_mutex_);
+  std::vector<std::string> unresolved;
+  for (const auto& path : unresolved_sample_paths_) {
+    if (!path.empty()) {
+      unresolved.push_back(path);
+    }
+  }
+  return unresolved;
+}
+
 bool Runtime::beginMidiLearn(std::size_t track_index, MidiLearnSlot slot) noexcept {
   if (track_index >= kTrackCount) {
     return false;
@@ -779,7 +819,7 @@ void Runtime::applyPendingCommands(ff::engine::Engine& engine,
 void Runtime::collectStepEvents(std::size_t step_index,
                                 std::size_t block_offset,
                                 std::vector<TriggerEvent>* events) const {
-  if (events == nullptr || step_index >= kSteps) {
+  if (events == nullptr || step_index >= kMaxSteps) {
     return;
   }
 
@@ -820,7 +860,7 @@ void Runtime::processSequencer(std::uint32_t frames,
       consumed += step_advance;
       remaining -= step_advance;
 
-      sequencer_state.current_step = (sequencer_state.current_step + 1U) % kSteps;
+      sequencer_state.current_step = (sequencer_state.current_step + 1U) % stepCount();
       playhead_step_.store(static_cast<std::uint32_t>(sequencer_state.current_step),
                            std::memory_order_release);
 
@@ -49,7 +49,8 @@ struct RuntimeStatus final {
 class Runtime final {
  public:
   static constexpr std::size_t kTrackCount = ff::engine::Engine::kTrackCount;
-  static constexpr std::size_t kSteps = 16;
+  static constexpr std::size_t kSteps = kStepsPerBar;
+  static constexpr std::size_t kMaxSteps = kMaxPatternSteps;
 
   explicit Runtime(ff::diagnostics::Reporter* diagnostics);
   ~Runtime();
@@ -80,6 +81,9 @@ class Runtime final {
   [[nodiscard]] ProjectStep step(std::size_t track_index,
                                  std::size_t step_index) const noexcept;
 
+  bool setStepCount(std::size_t step_count) noexcept;
+  [[nodiscard]] std::size_t stepCount() const noexcept;
+
   bool triggerPad(std::size_t track_index, std::uint8_t velocity) noexcept;
 
   bool setTrackParameters(std::size_t track_index,
@@ -96,6 +100,7 @@ class Runtime final {
                    std::string* error_message = nullptr) const;
   bool loadProject(const std::filesystem::path& path,
                    std::string* error_message = nullptr);
+  [[nodiscard]] std::vector<std::string> unresolvedSamples() const;
 
   bool beginMidiLearn(std::size_t track_index, MidiLearnSlot slot) noexcept;
   void cancelMidiLearn() noexcept;
@@ -188,7 +193,8 @@ class Runtime final {
   ff::engine::Engine engine_;
   SequencerState sequencer_{};
 
-  std::array<std::array<std::atomic<std::uint8_t>, kSteps>, kTrackCount> steps_{};
+  std::array<std::array<std::atomic<std::uint8_t>, kMaxSteps>, kTrackCount> steps_{};
+  std::atomic<std::size_t> step_count_{kSteps};
   std::array<std::atomic<int>, kTrackCount> track_choke_groups_{};
 
   std::atomic<bool> transport_running_{false};
@@ -201,6 +207,7 @@ class Runtime final {
 
   mutable std::mutex project_mutex_;
   ProjectModel project_model_{};
+  std::array<std::string, kTrackCount> unresolved_sample_paths_{};
 
   mutable std::mutex midi_mutex_;
   std::array<std::optional<LearnTarget>, 1> active_learn_{};
@@ -82,10 +82,64 @@ void projectRoundTripPreservesCoreState() {
   std::filesystem::remove(file_path, remove_error);
 }
 
+void rustSavedProjectWithStepFieldsLoads() {
+  const std::filesystem::path fixture_path =
+      std::filesystem::path(FF_SOURCE_ROOT) / "fixtures" / "interop" / "step_fields.ffproject";
+
+  ff::desktop::ProjectModel project;
+  std::string error;
+  assert(ff::desktop::loadProjectFromFile(fixture_path, &project, &error));
+
+  assert(project.name == "Interop Steps");
+  assert(almostEqual(project.bpm, 128.0F));
+  assert(almostEqual(project.swing, 0.1F));
+  assert(project.tracks[1].sample_path == "snare.wav");
+  assert(almostEqual(project.tracks[1].parameters.pan, 0.25F));
+  assert(project.pattern[0][0].active);
+  assert(project.pattern[0][8].active);
+  assert(project.pattern[1][4].active);
+  assert(project.pattern[1][4].velocity == 110);
+  assert(project.pattern[2][12].active);
+  assert(!project.pattern[2][13].active);
+}
+
+void longRustPatternLoadsAndRoundTrips() {
+  const std::filesystem::path fixture_path =
+      std::filesystem::path(FF_SOURCE_ROOT) / "fixtures" / "interop" / "long_pattern.ffproject";
+
+  ff::desktop::ProjectModel loaded;
+  std::string error;
+  assert(ff::desktop::loadProjectFromFile(fixture_path, &loaded, &error));
+  assert(loaded.step_count == 40);
+  assert(loaded.pattern[0][16].active);
+  assert(loaded.pattern[1][20].active);
+  assert(loaded.pattern[2][39].active);
+
+  const auto file_path = std::filesystem::temp_directory_path() /
+                         "forest_floor_desktop_long_pattern.ffproject";
+  std::string save_error;
+  assert(ff::desktop::saveProjectToFile(file_path, loaded, &save_error));
+
+  ff::desktop::ProjectModel restored;
+  std::string load_error;
+  assert(ff::desktop::loadProjectFromFile(file_path, &restored, &load_error));
+  assert(restored.step_count == 40);
+  for (std::size_t track = 0; track < loaded.pattern.size(); ++track) {
+    for (std::size_t step = 0; step < ff::desktop::kMaxPatternSteps; ++step) {
+      assert(restored.pattern[track][step].active == loaded.pattern[track][step].active);
+    }
+  }
+
+  std::error_code remove_error;
+  std::filesystem::remove(file_path, remove_error);
+}
+
 }  // namespace
 
 int main() {
   starterKitSamplesLoad();
   projectRoundTripPreservesCoreState();
+  rustSavedProjectWithStepFieldsLoads();
+  longRustPatternLoadsAndRoundTrips();
   return 0;
 }
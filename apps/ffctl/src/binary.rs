@@ -0,0 +1,265 @@
+use presets_rs::Project;
+
+pub const BINARY_MAGIC: &[u8; 4] = b"FFPB";
+pub const BINARY_VERSION: u16 = 3;
+
+const TEXT_HEADER: &str = "FF_PROJECT_V1";
+
+const MIN_MATCH: usize = 4;
+const MAX_MATCH: usize = 258;
+const WINDOW: usize = 1 << 15;
+const HASH_BITS: u32 = 12;
+const MAX_CHAIN: usize = 32;
+
+// A .ffpb file is a compressed container for the `FF_PROJECT_V1` text format,
+// not a separate schema: the text is split into named blocks (one per kit,
+// pattern or freeze section, and one per run of project-level lines in
+// between), and each block stores its lines LZ77-compressed. Each block
+// carries its byte length so readers can skip it. Decoding rebuilds the exact
+// text and loads it through the text loader, so every field keeps the text
+// format's types and validation.
+pub fn project_to_binary(project: &Project) -> Vec<u8> {
+    let text = presets_rs::save_project_to_text(project);
+    let mut blocks: Vec<(&str, Vec<&str>)> = Vec::new();
+    let mut section: Option<&str> = None;
+    for line in text.lines().skip(1) {
+        if let Some(name) = line.strip_prefix("BEGIN_") {
+            blocks.push((name, Vec::new()));
+            section = Some(name);
+            continue;
+        }
+        if section.is_some_and(|name| line.strip_prefix("END_") == Some(name)) {
+            section = None;
+            continue;
+        }
+        match blocks.last_mut() {
+            Some((name, lines)) if section.is_some() || name.is_empty() => lines.push(line),
+            _ => blocks.push(("", vec![line])),
+        }
+    }
+
+    let mut bytes = Vec::with_capacity(text.len() / 4);
+    bytes.extend_from_slice(BINARY_MAGIC);
+    bytes.extend_from_slice(&BINARY_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(blocks.len() as u32).to_le_bytes());
+    for (name, lines) in blocks {
+        let body = lines.join("\n");
+        let mut payload = Vec::new();
+        write_varint(&mut payload, body.len() as u64);
+        compress(body.as_bytes(), &mut payload);
+        write_text(&mut bytes, name);
+        bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&payload);
+    }
+    bytes
+}
+
+pub fn project_from_binary(bytes: &[u8]) -> Result<Project, String> {
+    if bytes.len() < 6 || &bytes[0..4] != BINARY_MAGIC {
+        return Err("not a ffpb project container".to_string());
+    }
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version != BINARY_VERSION {
+        return Err(format!("unsupported ffpb version: {version}"));
+    }
+
+    let mut reader = Reader { bytes, position: 6 };
+    let block_count = reader.read_u32()?;
+    let mut lines = vec![TEXT_HEADER.to_string()];
+    for _ in 0..block_count {
+        let name = reader.read_text()?;
+        let length = reader.read_u32()? as usize;
+        let mut block = Reader {
+            bytes: reader.take(length)?,
+            position: 0,
+        };
+        let body_length = block.read_length()?;
+        let body = decompress(&block.bytes[block.position..], body_length)
+            .map_err(|error| format!("ffpb block {name:?}: {error}"))?;
+        let body =
+            String::from_utf8(body).map_err(|_| format!("ffpb block {name:?} is not utf8"))?;
+        if !name.is_empty() {
+            lines.push(format!("BEGIN_{name}"));
+        }
+        lines.extend(body.lines().map(str::to_string));
+        if !name.is_empty() {
+            lines.push(format!("END_{name}"));
+        }
+    }
+    if reader.position != bytes.len() {
+        return Err("ffpb container has trailing bytes".to_string());
+    }
+    presets_rs::load_project_from_text(&lines.join("\n"))
+}
+
+// Tokens are a varint tag followed by either `tag >> 1` literal bytes (even
+// tags) or a back-reference of `(tag >> 1) + MIN_MATCH` bytes whose distance
+// follows as a second varint (odd tags).
+fn compress(input: &[u8], output: &mut Vec<u8>) {
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut previous = vec![usize::MAX; input.len()];
+    let mut literal_start = 0;
+    let mut position = 0;
+    while position < input.len() {
+        let (length, distance) = longest_match(input, position, &head, &previous);
+        if length < MIN_MATCH {
+            insert_hash(input, position, &mut head, &mut previous);
+            position += 1;
+            continue;
+        }
+
+        write_literals(output, &input[literal_start..position]);
+        write_varint(output, (((length - MIN_MATCH) as u64) << 1) | 1);
+        write_varint(output, distance as u64);
+        for offset in 0..length {
+            insert_hash(input, position + offset, &mut head, &mut previous);
+        }
+        position += length;
+        literal_start = position;
+    }
+    write_literals(output, &input[literal_start..]);
+}
+
+fn longest_match(
+    input: &[u8],
+    position: usize,
+    head: &[usize],
+    previous: &[usize],
+) -> (usize, usize) {
+    if position + MIN_MATCH > input.len() {
+        return (0, 0);
+    }
+    let limit = (input.len() - position).min(MAX_MATCH);
+    let mut best = (0, 0);
+    let mut candidate = head[hash(&input[position..])];
+    for _ in 0..MAX_CHAIN {
+        if candidate == usize::MAX || position - candidate > WINDOW {
+            break;
+        }
+        let length = (0..limit)
+            .take_while(|&offset| input[candidate + offset] == input[position + offset])
+            .count();
+        if length > best.0 {
+            best = (length, position - candidate);
+            if length == limit {
+                break;
+            }
+        }
+        candidate = previous[candidate];
+    }
+    best
+}
+
+fn insert_hash(input: &[u8], position: usize, head: &mut [usize], previous: &mut [usize]) {
+    if position + MIN_MATCH <= input.len() {
+        let bucket = hash(&input[position..]);
+        previous[position] = head[bucket];
+        head[bucket] = position;
+    }
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let key = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    (key.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+}
+
+fn write_literals(output: &mut Vec<u8>, literals: &[u8]) {
+    if !literals.is_empty() {
+        write_varint(output, (literals.len() as u64) << 1);
+        output.extend_from_slice(literals);
+    }
+}
+
+fn decompress(bytes: &[u8], length: usize) -> Result<Vec<u8>, String> {
+    let mut reader = Reader { bytes, position: 0 };
+    let mut output = Vec::with_capacity(length.min(bytes.len().saturating_mul(MAX_MATCH)));
+    while reader.position < bytes.len() {
+        let tag = reader.read_length()?;
+        let count = if tag & 1 == 0 {
+            tag >> 1
+        } else {
+            (tag >> 1).saturating_add(MIN_MATCH)
+        };
+        if count > length - output.len() {
+            return Err("decompressed data overruns its length".to_string());
+        }
+        if tag & 1 == 0 {
+            output.extend_from_slice(reader.take(count)?);
+            continue;
+        }
+        let distance = reader.read_length()?;
+        if distance == 0 || distance > output.len() {
+            return Err("back-reference outside the decompressed data".to_string());
+        }
+        let start = output.len() - distance;
+        for offset in 0..count {
+            output.push(output[start + offset]);
+        }
+    }
+    if output.len() != length {
+        return Err("decompressed data is shorter than its length".to_string());
+    }
+    Ok(output)
+}
+
+fn write_text(bytes: &mut Vec<u8>, text: &str) {
+    write_varint(bytes, text.len() as u64);
+    bytes.extend_from_slice(text.as_bytes());
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| "truncated ffpb payload".to_string())?;
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn read_varint(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_u8()?;
+            value |= u64::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("ffpb varint is too long".to_string())
+    }
+
+    fn read_length(&mut self) -> Result<usize, String> {
+        usize::try_from(self.read_varint()?).map_err(|_| "ffpb length overflow".to_string())
+    }
+
+    fn read_text(&mut self) -> Result<String, String> {
+        let length = self.read_length()?;
+        String::from_utf8(self.take(length)?.to_vec())
+            .map_err(|_| "ffpb text is not utf8".to_string())
+    }
+}
@@ -0,0 +1,1281 @@
+use presets_rs::{
+    AccentClip, AudioHints, GrooveTemplate, HumanizeDepth, Kit, LockTarget, MasterBus, Metronome,
+    MusicalKey, ParameterLock, Pattern, PatternScale, PatternStep, PatternTempo, PlaybackDirection,
+    ProgramChange, Project, SongEntry, StepResolution, SwingGrid, TempoChange, TrackAssignment,
+    TrackChokeTargets, TrackControls, TrackDucking, TrackFreeze, TrackGroup, TrackHumanize,
+    TrackInputs, TrackLabel, TrackMidiOutput, TrackSampleTrim, TrigCondition, ACCENT_CURVE_POINTS,
+    GROOVE_SLOT_COUNT, MAX_ACCENT_AMOUNT, MAX_FLAM_SPACING_MS, MAX_HINT_BLOCK_SIZE,
+    MAX_HINT_SAMPLE_RATE_HZ, MAX_HUMANIZE_TIMING_SAMPLES, MAX_HUMANIZE_VELOCITY, MAX_OUTPUT_PAIRS,
+    MAX_PATTERN_BARS, MAX_PATTERN_STEPS, MAX_STEP_GATE, MAX_STEP_PROBABILITY, MAX_TRACK_COLOR,
+    MIDI_CHANNEL_COUNT, MIDI_PROGRAM_COUNT, PATTERN_VARIATION_COUNT, STEPS_PER_PATTERN,
+    TRACK_COUNT, TRACK_GROUP_COUNT,
+};
+
+pub const PROJECT_JSON_FORMAT: &str = "ff-project-json-v1";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    fn field(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(fields) => fields
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+pub fn project_to_json(project: &Project) -> String {
+    let kits: Vec<String> = project.kits.iter().map(kit_to_json).collect();
+    let frozen_tracks: Vec<String> = project
+        .frozen_tracks
+        .iter()
+        .map(|freeze| {
+            let steps: Vec<String> = freeze
+                .original_steps
+                .iter()
+                .map(|step| format!("{{{}}}", step_fields_to_json(step)))
+                .collect();
+            format!(
+                "{{\"kit\":{},\"pattern\":{},\"track\":{},\"bounce\":{},\"original\":{},\"steps\":[{}]}}",
+                freeze.kit_index,
+                freeze.pattern_index,
+                freeze.track_index,
+                quote(&freeze.bounce_sample_id),
+                kit_to_json(&freeze.original_kit),
+                steps.join(",")
+            )
+        })
+        .collect();
+
+    let mut patterns = Vec::new();
+    for pattern in &project.patterns {
+        let mut steps = Vec::new();
+        for track_index in 0..TRACK_COUNT {
+            for step_index in 0..pattern.step_count() {
+                let step = pattern.steps[track_index][step_index];
+                if step != PatternStep::default() {
+                    steps.push(format!(
+                        "{{\"track\":{track_index},\"step\":{step_index},{}}}",
+                        step_fields_to_json(&step)
+                    ));
+                }
+            }
+        }
+        let markers: Vec<String> = pattern
+            .markers
+            .iter()
+            .map(|marker| {
+                format!(
+                    "{{\"bar\":{},\"label\":{}}}",
+                    marker.bar,
+                    quote(&marker.label)
+                )
+            })
+            .collect();
+        let tempo = pattern
+            .tempo
+            .map(|tempo| {
+                format!(
+                    "{{\"bpm\":{},\"change\":{}}}",
+                    tempo.bpm,
+                    quote(tempo.change.name())
+                )
+            })
+            .unwrap_or_else(|| "null".to_string());
+        let variations: Vec<String> = pattern
+            .variations
+            .iter()
+            .map(|variation| {
+                format!(
+                    "{{\"variation\":{},\"track\":{},\"step\":{},{}}}",
+                    variation.variation,
+                    variation.track_index,
+                    variation.step_index,
+                    step_fields_to_json(&variation.step)
+                )
+            })
+            .collect();
+        let mutes: Vec<String> = pattern
+            .mutes
+            .iter()
+            .map(|span| {
+                format!(
+                    "{{\"track\":{},\"first_bar\":{},\"last_bar\":{}}}",
+                    span.track_index, span.first_bar, span.last_bar
+                )
+            })
+            .collect();
+        let locks: Vec<String> = pattern
+            .locks
+            .iter()
+            .map(|lock| {
+                format!(
+                    "{{\"track\":{},\"step\":{},\"target\":{},\"value\":{}}}",
+                    lock.track_index,
+                    lock.step_index,
+                    quote(lock.target.name()),
+                    lock.value
+                )
+            })
+            .collect();
+        let directions: Vec<String> = pattern
+            .directions
+            .iter()
+            .map(|direction| quote(direction.name()))
+            .collect();
+        let resolutions: Vec<String> = pattern
+            .resolutions
+            .iter()
+            .map(|resolution| quote(resolution.name()))
+            .collect();
+        let default_steps: Vec<String> = pattern
+            .default_steps
+            .iter()
+            .map(|step| format!("{{{}}}", step_fields_to_json(step)))
+            .collect();
+        let groove = pattern
+            .groove
+            .as_deref()
+            .map(quote)
+            .unwrap_or_else(|| "null".to_string());
+        patterns.push(format!(
+            "{{\"name\":{},\"swing\":{},\"groove\":{},\"accent\":{{\"boosts\":[{}],\"clip\":{}}},\"bars\":{},\"length\":{},\"scale\":{},\"swing_grid\":{},\"tempo\":{},\"markers\":[{}],\"mutes\":[{}],\"locks\":[{}],\"directions\":[{}],\"resolutions\":[{}],\"default_steps\":[{}],\"steps\":[{}],\"variations\":[{}]}}",
+            quote(&pattern.name),
+            pattern.swing,
+            groove,
+            pattern
+                .accent
+                .boosts
+                .map(|boost| boost.to_string())
+                .join(","),
+            quote(pattern.accent.clip.name()),
+            pattern.bar_count,
+            optional_number(pattern.length_steps),
+            quote(pattern.scale.name()),
+            quote(pattern.swing_grid.name()),
+            tempo,
+            markers.join(","),
+            mutes.join(","),
+            locks.join(","),
+            directions.join(","),
+            resolutions.join(","),
+            default_steps.join(","),
+            steps.join(","),
+            variations.join(",")
+        ));
+    }
+
+    let outputs: Vec<String> = (0..TRACK_COUNT)
+        .map(|track_index| {
+            project
+                .output_routing
+                .track_output(track_index)
+                .unwrap_or(0)
+                .to_string()
+        })
+        .collect();
+    let program_changes: Vec<String> = project
+        .program_changes
+        .iter()
+        .map(|change| {
+            format!(
+                "{{\"program\":{},\"pattern\":{},\"kit\":{}}}",
+                change.program,
+                optional_number(change.pattern_index),
+                optional_number(change.kit_index)
+            )
+        })
+        .collect();
+    let grooves: Vec<String> = project
+        .grooves
+        .iter()
+        .map(|groove| {
+            let timing: Vec<String> = groove.timing.iter().map(f32::to_string).collect();
+            let velocity: Vec<String> = groove.velocity.iter().map(i8::to_string).collect();
+            format!(
+                "{{\"name\":{},\"timing\":[{}],\"velocity\":[{}]}}",
+                quote(&groove.name),
+                timing.join(","),
+                velocity.join(",")
+            )
+        })
+        .collect();
+    let song: Vec<String> = project
+        .song
+        .iter()
+        .map(|entry| {
+            format!(
+                "{{\"pattern\":{},\"repeats\":{}}}",
+                entry.pattern_index, entry.repeats
+            )
+        })
+        .collect();
+    let armed: Vec<String> = (0..TRACK_COUNT)
+        .map(|track_index| project.track_inputs.is_armed(track_index).to_string())
+        .collect();
+    let monitoring: Vec<String> = (0..TRACK_COUNT)
+        .map(|track_index| project.track_inputs.is_monitoring(track_index).to_string())
+        .collect();
+
+    format!(
+        "{{\"format\":{},\"name\":{},\"active_kit\":{},\"active_pattern\":{},\"random_seed\":{},\"humanize\":{{\"timing_samples\":{},\"velocity\":{}}},\"audio_hints\":{{\"sample_rate_hz\":{},\"block_size\":{}}},\"key\":{},\"metronome\":{},\"program_changes\":[{}],\"grooves\":[{}],\"song\":{{\"loop\":{},\"entries\":[{}]}},\"outputs\":[{}],\"armed\":[{}],\"monitoring\":[{}],\"kits\":[{}],\"patterns\":[{}],\"frozen_tracks\":[{}]}}",
+        quote(PROJECT_JSON_FORMAT),
+        quote(&project.name),
+        optional_number(project.active_kit),
+        optional_number(project.active_pattern),
+        quote(&project.random_seed.to_string()),
+        project.humanize.timing_samples,
+        project.humanize.velocity,
+        optional_number(project.audio_hints.sample_rate_hz.map(|rate| rate as usize)),
+        optional_number(project.audio_hints.block_size.map(|size| size as usize)),
+        quote(&project.key.to_text()),
+        metronome_to_json(&project.metronome),
+        program_changes.join(","),
+        grooves.join(","),
+        project.song_loop,
+        song.join(","),
+        outputs.join(","),
+        armed.join(","),
+        monitoring.join(","),
+        kits.join(","),
+        patterns.join(","),
+        frozen_tracks.join(",")
+    )
+}
+
+fn metronome_to_json(metronome: &Metronome) -> String {
+    format!(
+        "{{\"enabled\":{},\"accent_sample_id\":{},\"click_sample_id\":{},\"gain_db\":{},\"output_pair\":{},\"include_in_render\":{}}}",
+        metronome.enabled,
+        quote(&metronome.accent_sample_id),
+        quote(&metronome.click_sample_id),
+        metronome.gain_db,
+        metronome.output_pair,
+        metronome.include_in_render
+    )
+}
+
+fn kit_to_json(kit: &Kit) -> String {
+    let tracks: Vec<String> = kit
+        .tracks
+        .iter()
+        .map(|track| {
+            format!(
+                "{{\"track_index\":{},\"sample_id\":{}}}",
+                track.track_index,
+                quote(&track.sample_id)
+            )
+        })
+        .collect();
+    let controls: Vec<String> = kit
+        .controls
+        .iter()
+        .map(|control| {
+            format!(
+                "{{\"track_index\":{},\"gain_db\":{},\"pan\":{},\"filter_cutoff\":{},\"envelope_decay\":{},\"pitch_semitones\":{},\"choke_group\":{}}}",
+                control.track_index,
+                control.controls.gain_db,
+                control.controls.pan,
+                control.controls.filter_cutoff,
+                control.controls.envelope_decay,
+                control.controls.pitch_semitones,
+                optional_number(control.controls.choke_group.map(usize::from)),
+            )
+        })
+        .collect();
+    let regions: Vec<String> = kit
+        .regions
+        .iter()
+        .map(|region| {
+            format!(
+                "{{\"track_index\":{},\"start_frame\":{},\"end_frame\":{}}}",
+                region.track_index, region.start_frame, region.end_frame
+            )
+        })
+        .collect();
+    let trims: Vec<String> = kit
+        .trims
+        .iter()
+        .map(|trim| {
+            format!(
+                "{{\"track_index\":{},\"start\":{},\"end\":{},\"reverse\":{}}}",
+                trim.track_index, trim.start, trim.end, trim.reverse
+            )
+        })
+        .collect();
+    let ducking: Vec<String> = kit
+        .ducking
+        .iter()
+        .map(|duck| {
+            format!(
+                "{{\"track_index\":{},\"source_track\":{},\"depth\":{},\"attack_ms\":{},\"release_ms\":{}}}",
+                duck.track_index, duck.source_track, duck.depth, duck.attack_ms, duck.release_ms
+            )
+        })
+        .collect();
+    let midi_outputs: Vec<String> = kit
+        .midi_outputs
+        .iter()
+        .map(|output| {
+            format!(
+                "{{\"track_index\":{},\"channel\":{},\"note\":{},\"length_steps\":{}}}",
+                output.track_index, output.channel, output.note, output.length_steps
+            )
+        })
+        .collect();
+    let humanize: Vec<String> = kit
+        .humanize
+        .iter()
+        .map(|value| {
+            format!(
+                "{{\"track_index\":{},\"timing_samples\":{},\"velocity\":{}}}",
+                value.track_index, value.timing_samples, value.velocity
+            )
+        })
+        .collect();
+    let labels: Vec<String> = kit
+        .labels
+        .iter()
+        .map(|label| {
+            format!(
+                "{{\"track_index\":{},\"name\":{},\"color\":{}}}",
+                label.track_index,
+                quote(&label.name),
+                label
+                    .color
+                    .map_or_else(|| "null".to_string(), |color| color.to_string())
+            )
+        })
+        .collect();
+    let choke_targets: Vec<String> = kit
+        .choke_targets
+        .iter()
+        .map(|choke| {
+            format!(
+                "{{\"track_index\":{},\"targets\":{}}}",
+                choke.track_index, choke.targets
+            )
+        })
+        .collect();
+    let groups: Vec<String> = kit
+        .groups
+        .iter()
+        .map(|group| {
+            let members: Vec<String> = group.members.iter().map(u8::to_string).collect();
+            format!(
+                "{{\"group_index\":{},\"name\":{},\"members\":[{}],\"gain_db\":{},\"muted\":{},\"soloed\":{},\"choke\":{}}}",
+                group.group_index,
+                quote(&group.name),
+                members.join(","),
+                group.gain_db,
+                group.muted,
+                group.soloed,
+                group.choke
+            )
+        })
+        .collect();
+    let master = format!(
+        "{{\"compressor_amount\":{},\"filter_cutoff\":{},\"limiter_ceiling_db\":{}}}",
+        kit.master.compressor_amount, kit.master.filter_cutoff, kit.master.limiter_ceiling_db
+    );
+    format!(
+        "{{\"name\":{},\"tracks\":[{}],\"controls\":[{}],\"regions\":[{}],\"trims\":[{}],\"ducking\":[{}],\"midi_outputs\":[{}],\"humanize\":[{}],\"labels\":[{}],\"choke_targets\":[{}],\"groups\":[{}],\"master\":{}}}",
+        quote(&kit.name),
+        tracks.join(","),
+        controls.join(","),
+        regions.join(","),
+        trims.join(","),
+        ducking.join(","),
+        midi_outputs.join(","),
+        humanize.join(","),
+        labels.join(","),
+        choke_targets.join(","),
+        groups.join(","),
+        master
+    )
+}
+
+pub fn project_from_json(text: &str) -> Result<Project, String> {
+    let root = parse_json(text)?;
+    let format = root.field("format").and_then(JsonValue::as_str);
+    if format != Some(PROJECT_JSON_FORMAT) {
+        return Err(format!("unexpected json format: {format:?}"));
+    }
+
+    let mut project = Project {
+        name: string_field(&root, "name")?,
+        ..Project::default()
+    };
+
+    if let Some(seed) = root.field("random_seed").and_then(JsonValue::as_str) {
+        project.random_seed = seed
+            .parse()
+            .map_err(|_| format!("invalid random_seed: {seed}"))?;
+    }
+    if let Some(humanize) = root.field("humanize") {
+        project.humanize = HumanizeDepth {
+            timing_samples: index_field(
+                humanize,
+                "timing_samples",
+                MAX_HUMANIZE_TIMING_SAMPLES as usize + 1,
+            )? as u32,
+            velocity: index_field(humanize, "velocity", usize::from(MAX_HUMANIZE_VELOCITY) + 1)?
+                as u8,
+        };
+    }
+    if let Some(hints) = root.field("audio_hints") {
+        project.audio_hints = AudioHints {
+            sample_rate_hz: optional_index_field(
+                hints,
+                "sample_rate_hz",
+                MAX_HINT_SAMPLE_RATE_HZ as usize + 1,
+            )?
+            .map(|rate| rate as u32),
+            block_size: optional_index_field(
+                hints,
+                "block_size",
+                MAX_HINT_BLOCK_SIZE as usize + 1,
+            )?
+            .map(|size| size as u32),
+        };
+        if !project.audio_hints.is_valid() {
+            return Err("invalid audio_hints".to_string());
+        }
+    }
+    if let Some(key) = root.field("key").and_then(JsonValue::as_str) {
+        project.key = MusicalKey::from_text(key)?;
+    }
+    if let Some(metronome) = root.field("metronome") {
+        project.metronome = Metronome {
+            enabled: bool_field(metronome, "enabled")?,
+            accent_sample_id: string_field(metronome, "accent_sample_id")?,
+            click_sample_id: string_field(metronome, "click_sample_id")?,
+            gain_db: number_field(metronome, "gain_db")?,
+            output_pair: index_field(metronome, "output_pair", MAX_OUTPUT_PAIRS)? as u8,
+            include_in_render: bool_field(metronome, "include_in_render")?,
+        };
+        if !project.metronome.is_valid() {
+            return Err("invalid metronome".to_string());
+        }
+    }
+
+    if let Some(outputs) = root.field("outputs").and_then(JsonValue::as_array) {
+        for (track_index, output) in outputs.iter().enumerate() {
+            let output_pair = output
+                .as_f64()
+                .filter(|value| value.fract() == 0.0 && *value >= 0.0)
+                .ok_or_else(|| format!("invalid output pair for track {track_index}"))?;
+            if !project
+                .output_routing
+                .set_track_output(track_index, output_pair as usize)
+            {
+                return Err(format!("output route out of range: track {track_index}"));
+            }
+        }
+    }
+    for (key, set_flag) in [
+        (
+            "armed",
+            TrackInputs::set_armed as fn(&mut TrackInputs, usize, bool) -> bool,
+        ),
+        ("monitoring", TrackInputs::set_monitoring),
+    ] {
+        let Some(flags) = root.field(key).and_then(JsonValue::as_array) else {
+            continue;
+        };
+        for (track_index, flag) in flags.iter().enumerate() {
+            let JsonValue::Bool(flag) = flag else {
+                return Err(format!("{key} must be bools: track {track_index}"));
+            };
+            if !set_flag(&mut project.track_inputs, track_index, *flag) {
+                return Err(format!("{key} track out of range: {track_index}"));
+            }
+        }
+    }
+
+    for kit_value in array_field(&root, "kits")? {
+        project.kits.push(kit_from_json(kit_value)?);
+    }
+
+    for pattern_value in array_field(&root, "patterns")? {
+        let mut pattern = Pattern {
+            name: string_field(pattern_value, "name")?,
+            ..Pattern::default()
+        };
+        pattern.set_swing(number_field(pattern_value, "swing")?);
+        if let Some(groove) = pattern_value.field("groove").and_then(JsonValue::as_str) {
+            pattern.groove = Some(groove.to_string());
+        }
+        if let Some(accent) = pattern_value.field("accent") {
+            let boosts = array_field(accent, "boosts")?;
+            if boosts.len() != ACCENT_CURVE_POINTS {
+                return Err("invalid accent boosts".to_string());
+            }
+            for (slot, boost) in boosts.iter().enumerate() {
+                pattern.accent.boosts[slot] = boost
+                    .as_f64()
+                    .filter(|value| {
+                        value.fract() == 0.0 && (0.0..=f64::from(MAX_ACCENT_AMOUNT)).contains(value)
+                    })
+                    .ok_or_else(|| "invalid accent boost".to_string())?
+                    as u8;
+            }
+            let clip = string_field(accent, "clip")?;
+            pattern.accent.clip = AccentClip::from_name(&clip)
+                .ok_or_else(|| format!("invalid accent clip: {clip}"))?;
+        }
+        if pattern_value.field("bars").is_some() {
+            let bar_count = index_field(pattern_value, "bars", MAX_PATTERN_BARS + 1)?;
+            if !pattern.set_bar_count(bar_count) {
+                return Err(format!("invalid pattern bar count: {bar_count}"));
+            }
+        }
+        if let Some(length_steps) =
+            optional_index_field(pattern_value, "length", MAX_PATTERN_STEPS + 1)?
+        {
+            if length_steps.div_ceil(STEPS_PER_PATTERN) != pattern.bar_count
+                || !pattern.set_step_count(length_steps)
+            {
+                return Err(format!("invalid pattern length: {length_steps}"));
+            }
+        }
+        if let Some(scale) = pattern_value.field("scale").and_then(JsonValue::as_str) {
+            pattern.scale = PatternScale::from_name(scale)
+                .ok_or_else(|| format!("invalid pattern scale: {scale}"))?;
+        }
+        if let Some(swing_grid) = pattern_value
+            .field("swing_grid")
+            .and_then(JsonValue::as_str)
+        {
+            pattern.swing_grid = SwingGrid::from_name(swing_grid)
+                .ok_or_else(|| format!("invalid swing grid: {swing_grid}"))?;
+        }
+        if let Some(tempo) = pattern_value
+            .field("tempo")
+            .filter(|tempo| !matches!(tempo, JsonValue::Null))
+        {
+            let change = string_field(tempo, "change")?;
+            let tempo = PatternTempo {
+                bpm: number_field(tempo, "bpm")?,
+                change: TempoChange::from_name(&change)
+                    .ok_or_else(|| format!("invalid tempo change: {change}"))?,
+            };
+            if !tempo.is_valid() {
+                return Err(format!("pattern tempo out of range: {}", tempo.bpm));
+            }
+            pattern.tempo = Some(tempo);
+        }
+        if let Some(markers) = pattern_value.field("markers").and_then(JsonValue::as_array) {
+            for marker in markers {
+                let bar = index_field(marker, "bar", pattern.bar_count)?;
+                pattern.set_bar_marker(bar, &string_field(marker, "label")?);
+            }
+        }
+        if let Some(mutes) = pattern_value.field("mutes").and_then(JsonValue::as_array) {
+            for span in mutes {
+                if !pattern.set_mute_span(
+                    index_field(span, "track", TRACK_COUNT)?,
+                    index_field(span, "first_bar", pattern.bar_count)?,
+                    index_field(span, "last_bar", pattern.bar_count)?,
+                ) {
+                    return Err("invalid pattern mute span".to_string());
+                }
+            }
+        }
+        if let Some(locks) = pattern_value.field("locks").and_then(JsonValue::as_array) {
+            for lock in locks {
+                let target = string_field(lock, "target")?;
+                if !pattern.set_parameter_lock(ParameterLock {
+                    track_index: index_field(lock, "track", TRACK_COUNT)? as u8,
+                    step_index: index_field(lock, "step", pattern.step_count())? as u8,
+                    target: LockTarget::from_name(&target)
+                        .ok_or_else(|| format!("invalid lock target: {target}"))?,
+                    value: number_field(lock, "value")?,
+                }) {
+                    return Err("invalid pattern parameter lock".to_string());
+                }
+            }
+        }
+        if let Some(directions) = pattern_value
+            .field("directions")
+            .and_then(JsonValue::as_array)
+        {
+            if directions.len() != TRACK_COUNT {
+                return Err(format!(
+                    "pattern.directions must have {TRACK_COUNT} entries"
+                ));
+            }
+            for (track_index, direction) in directions.iter().enumerate() {
+                let name = direction
+                    .as_str()
+                    .ok_or_else(|| "pattern.directions must be strings".to_string())?;
+                pattern.set_track_direction(
+                    track_index,
+                    PlaybackDirection::from_name(name)
+                        .ok_or_else(|| format!("invalid playback direction: {name}"))?,
+                );
+            }
+        }
+        if let Some(resolutions) = pattern_value
+            .field("resolutions")
+            .and_then(JsonValue::as_array)
+        {
+            if resolutions.len() != TRACK_COUNT {
+                return Err(format!(
+                    "pattern.resolutions must have {TRACK_COUNT} entries"
+                ));
+            }
+            for (track_index, resolution) in resolutions.iter().enumerate() {
+                let name = resolution
+                    .as_str()
+                    .ok_or_else(|| "pattern.resolutions must be strings".to_string())?;
+                pattern.set_track_resolution(
+                    track_index,
+                    StepResolution::from_name(name)
+                        .ok_or_else(|| format!("invalid step resolution: {name}"))?,
+                );
+            }
+        }
+        if let Some(default_steps) = pattern_value
+            .field("default_steps")
+            .and_then(JsonValue::as_array)
+        {
+            if default_steps.len() != TRACK_COUNT {
+                return Err(format!(
+                    "pattern.default_steps must have {TRACK_COUNT} entries"
+                ));
+            }
+            for (track_index, step) in default_steps.iter().enumerate() {
+                pattern.set_track_default_step(track_index, step_from_json(step)?);
+            }
+        }
+        for step in array_field(pattern_value, "steps")? {
+            pattern.set_step(
+                index_field(step, "track", TRACK_COUNT)?,
+                index_field(step, "step", pattern.step_count())?,
+                step_from_json(step)?,
+            );
+        }
+        if let Some(variations) = pattern_value
+            .field("variations")
+            .and_then(JsonValue::as_array)
+        {
+            for variation in variations {
+                let step = step_from_json(variation)?;
+                if !pattern.set_variation_step(
+                    index_field(variation, "variation", PATTERN_VARIATION_COUNT)? as u8,
+                    index_field(variation, "track", TRACK_COUNT)?,
+                    index_field(variation, "step", pattern.step_count())?,
+                    Some(step),
+                ) {
+                    return Err("invalid pattern variation".to_string());
+                }
+            }
+        }
+        project.patterns.push(pattern);
+    }
+
+    if let Some(frozen_tracks) = root.field("frozen_tracks").and_then(JsonValue::as_array) {
+        for freeze in frozen_tracks {
+            let mut original_steps = Vec::new();
+            for step in array_field(freeze, "steps")? {
+                original_steps.push(step_from_json(step)?);
+            }
+            if original_steps.len() > MAX_PATTERN_STEPS {
+                return Err(format!("too many frozen steps: {}", original_steps.len()));
+            }
+            project.frozen_tracks.push(TrackFreeze {
+                kit_index: index_field(freeze, "kit", usize::MAX)?,
+                pattern_index: index_field(freeze, "pattern", usize::MAX)?,
+                track_index: index_field(freeze, "track", TRACK_COUNT)? as u8,
+                bounce_sample_id: string_field(freeze, "bounce")?,
+                original_kit: kit_from_json(
+                    freeze
+                        .field("original")
+                        .ok_or_else(|| "missing object field: original".to_string())?,
+                )?,
+                original_steps,
+            });
+        }
+    }
+
+    if let Some(song) = root.field("song") {
+        let mut entries = Vec::new();
+        for entry in array_field(song, "entries")? {
+            entries.push(SongEntry {
+                pattern_index: index_field(entry, "pattern", project.patterns.len())?,
+                repeats: index_field(entry, "repeats", 256)? as u8,
+            });
+        }
+        if !project.set_song(&entries) {
+            return Err("invalid song entry".to_string());
+        }
+        project.song_loop = bool_field(song, "loop")?;
+    }
+
+    if let Some(program_changes) = root.field("program_changes").and_then(JsonValue::as_array) {
+        for change in program_changes {
+            if !project.set_program_change(ProgramChange {
+                program: index_field(change, "program", usize::from(MIDI_PROGRAM_COUNT))? as u8,
+                pattern_index: optional_index_field(change, "pattern", project.patterns.len())?,
+                kit_index: optional_index_field(change, "kit", project.kits.len())?,
+            }) {
+                return Err("invalid program change".to_string());
+            }
+        }
+    }
+
+    if let Some(grooves) = root.field("grooves").and_then(JsonValue::as_array) {
+        for groove_value in grooves {
+            let mut groove = GrooveTemplate::new(&string_field(groove_value, "name")?);
+            let timing = array_field(groove_value, "timing")?;
+            let velocity = array_field(groove_value, "velocity")?;
+            if timing.len() != GROOVE_SLOT_COUNT || velocity.len() != GROOVE_SLOT_COUNT {
+                return Err(format!(
+                    "groove must have {GROOVE_SLOT_COUNT} timing and velocity slots"
+                ));
+            }
+            for slot in 0..GROOVE_SLOT_COUNT {
+                groove.timing[slot] = timing[slot]
+                    .as_f64()
+                    .ok_or_else(|| "groove.timing must be numbers".to_string())?
+                    as f32;
+                groove.velocity[slot] = velocity[slot]
+                    .as_f64()
+                    .filter(|offset| offset.fract() == 0.0 && (-128.0..=127.0).contains(offset))
+                    .ok_or_else(|| "groove.velocity must be small integers".to_string())?
+                    as i8;
+            }
+            let name = groove.name.clone();
+            if !project.set_groove(groove) {
+                return Err(format!("invalid groove: {name}"));
+            }
+        }
+    }
+
+    project.active_kit = optional_index_field(&root, "active_kit", project.kits.len())?;
+    project.active_pattern = optional_index_field(&root, "active_pattern", project.patterns.len())?;
+    Ok(project)
+}
+
+fn kit_from_json(kit_value: &JsonValue) -> Result<Kit, String> {
+    let mut kit = Kit {
+        name: string_field(kit_value, "name")?,
+        ..Kit::default()
+    };
+    for track in array_field(kit_value, "tracks")? {
+        let track_index = index_field(track, "track_index", TRACK_COUNT)? as u8;
+        if !kit.add_assignment(TrackAssignment {
+            track_index,
+            sample_id: string_field(track, "sample_id")?,
+        }) {
+            return Err(format!("duplicate track assignment: {track_index}"));
+        }
+    }
+    for control in array_field(kit_value, "controls")? {
+        let choke_group = match control.field("choke_group") {
+            Some(JsonValue::Null) | None => None,
+            Some(_) => Some(index_field(control, "choke_group", 16)? as u8),
+        };
+        kit.set_track_controls(
+            index_field(control, "track_index", TRACK_COUNT)? as u8,
+            TrackControls {
+                gain_db: number_field(control, "gain_db")?,
+                pan: number_field(control, "pan")?,
+                filter_cutoff: number_field(control, "filter_cutoff")?,
+                envelope_decay: number_field(control, "envelope_decay")?,
+                pitch_semitones: number_field(control, "pitch_semitones")?,
+                choke_group,
+            },
+        );
+    }
+    if let Some(regions) = kit_value.field("regions").and_then(JsonValue::as_array) {
+        for region in regions {
+            let track_index = index_field(region, "track_index", TRACK_COUNT)? as u8;
+            if !kit.set_track_region(
+                track_index,
+                index_field(region, "start_frame", usize::MAX)? as u64,
+                index_field(region, "end_frame", usize::MAX)? as u64,
+            ) {
+                return Err(format!("invalid region for track {track_index}"));
+            }
+        }
+    }
+    if let Some(trims) = kit_value.field("trims").and_then(JsonValue::as_array) {
+        for trim in trims {
+            let track_index = index_field(trim, "track_index", TRACK_COUNT)? as u8;
+            if !kit.set_track_trim(TrackSampleTrim {
+                track_index,
+                start: number_field(trim, "start")?,
+                end: number_field(trim, "end")?,
+                reverse: bool_field(trim, "reverse")?,
+            }) {
+                return Err(format!("invalid trim for track {track_index}"));
+            }
+        }
+    }
+    if let Some(ducking) = kit_value.field("ducking").and_then(JsonValue::as_array) {
+        for duck in ducking {
+            let track_index = index_field(duck, "track_index", TRACK_COUNT)? as u8;
+            if !kit.set_track_ducking(TrackDucking {
+                track_index,
+                source_track: index_field(duck, "source_track", TRACK_COUNT)? as u8,
+                depth: number_field(duck, "depth")?,
+                attack_ms: number_field(duck, "attack_ms")?,
+                release_ms: number_field(duck, "release_ms")?,
+            }) {
+                return Err(format!("invalid ducking for track {track_index}"));
+            }
+        }
+    }
+    if let Some(outputs) = kit_value
+        .field("midi_outputs")
+        .and_then(JsonValue::as_array)
+    {
+        for output in outputs {
+            let track_index = index_field(output, "track_index", TRACK_COUNT)? as u8;
+            if !kit.set_track_midi_output(TrackMidiOutput {
+                track_index,
+                channel: index_field(output, "channel", usize::from(MIDI_CHANNEL_COUNT))? as u8,
+                note: index_field(output, "note", 128)? as u8,
+                length_steps: number_field(output, "length_steps")?,
+            }) {
+                return Err(format!("invalid midi output for track {track_index}"));
+            }
+        }
+    }
+    if let Some(values) = kit_value.field("humanize").and_then(JsonValue::as_array) {
+        for value in values {
+            let track_index = index_field(value, "track_index", TRACK_COUNT)? as u8;
+            if !kit.set_track_humanize(TrackHumanize {
+                track_index,
+                timing_samples: index_field(
+                    value,
+                    "timing_samples",
+                    MAX_HUMANIZE_TIMING_SAMPLES as usize + 1,
+                )? as u32,
+                velocity: index_field(value, "velocity", usize::from(MAX_HUMANIZE_VELOCITY) + 1)?
+                    as u8,
+            }) {
+                return Err(format!("invalid humanize for track {track_index}"));
+            }
+        }
+    }
+    if let Some(labels) = kit_value.field("labels").and_then(JsonValue::as_array) {
+        for label in labels {
+            let track_index = index_field(label, "track_index", TRACK_COUNT)? as u8;
+            let color = match label.field("color") {
+                None | Some(JsonValue::Null) => None,
+                Some(_) => Some(index_field(label, "color", MAX_TRACK_COLOR as usize + 1)? as u32),
+            };
+            if !kit.set_track_label(TrackLabel {
+                track_index,
+                name: string_field(label, "name")?,
+                color,
+            }) {
+                return Err(format!("invalid label for track {track_index}"));
+            }
+        }
+    }
+    if let Some(choke_targets) = kit_value
+        .field("choke_targets")
+        .and_then(JsonValue::as_array)
+    {
+        for choke in choke_targets {
+            let track_index = index_field(choke, "track_index", TRACK_COUNT)? as u8;
+            if !kit.set_track_choke_targets(TrackChokeTargets {
+                track_index,
+                targets: index_field(choke, "targets", 256)? as u8,
+            }) {
+                return Err(format!("invalid choke targets for track {track_index}"));
+            }
+        }
+    }
+    if let Some(groups) = kit_value.field("groups").and_then(JsonValue::as_array) {
+        for group in groups {
+            let group_index = index_field(group, "group_index", TRACK_GROUP_COUNT)? as u8;
+            let members = array_field(group, "members")?
+                .iter()
+                .map(|member| match member.as_f64() {
+                    Some(number)
+                        if number >= 0.0
+                            && number.fract() == 0.0
+                            && number < TRACK_COUNT as f64 =>
+                    {
+                        Ok(number as u8)
+                    }
+                    _ => Err(format!("invalid member for group {group_index}")),
+                })
+                .collect::<Result<Vec<u8>, String>>()?;
+            if !kit.set_track_group(TrackGroup {
+                group_index,
+                name: string_field(group, "name")?,
+                members,
+                gain_db: number_field(group, "gain_db")?,
+                muted: bool_field(group, "muted")?,
+                soloed: bool_field(group, "soloed")?,
+                choke: bool_field(group, "choke")?,
+            }) {
+                return Err(format!("invalid track group: {group_index}"));
+            }
+        }
+    }
+    if let Some(master) = kit_value.field("master") {
+        if !kit.set_master_bus(MasterBus {
+            compressor_amount: number_field(master, "compressor_amount")?,
+            filter_cutoff: number_field(master, "filter_cutoff")?,
+            limiter_ceiling_db: number_field(master, "limiter_ceiling_db")?,
+        }) {
+            return Err("invalid kit master bus".to_string());
+        }
+    }
+    Ok(kit)
+}
+
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for character in value.chars() {
+        match character {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            control if u32::from(control) < 0x20 => {
+                quoted.push_str(&format!("\\u{:04x}", u32::from(control)));
+            }
+            other => quoted.push(other),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn step_fields_to_json(step: &PatternStep) -> String {
+    format!(
+        "\"active\":{},\"velocity\":{},\"probability\":{},\"condition\":{},\"accent\":{},\"flam\":{},\"flam_spacing_ms\":{},\"gate\":{}",
+        step.active,
+        step.velocity,
+        step.probability,
+        quote(&step.condition.to_text()),
+        step.accent,
+        step.flam,
+        step.flam_spacing_ms,
+        step.gate
+    )
+}
+
+// Fields past active and velocity were added later, so older exports that
+// omit them load with the step defaults.
+fn step_from_json(value: &JsonValue) -> Result<PatternStep, String> {
+    let active = match value.field("active") {
+        Some(JsonValue::Bool(value)) => *value,
+        _ => return Err("step.active must be a bool".to_string()),
+    };
+    let mut step = PatternStep {
+        active,
+        velocity: index_field(value, "velocity", 128)? as u8,
+        ..PatternStep::default()
+    };
+    if let Some(probability) =
+        optional_index_field(value, "probability", usize::from(MAX_STEP_PROBABILITY) + 1)?
+    {
+        step.probability = probability as u8;
+    }
+    if let Some(condition) = value.field("condition") {
+        let text = condition
+            .as_str()
+            .ok_or_else(|| "step.condition must be a string".to_string())?;
+        step.condition = TrigCondition::from_text(text)
+            .ok_or_else(|| format!("invalid trig condition: {text}"))?;
+    }
+    if value.field("accent").is_some() {
+        step.accent = bool_field(value, "accent")?;
+    }
+    if value.field("flam").is_some() {
+        step.flam = bool_field(value, "flam")?;
+    }
+    if let Some(spacing) = optional_index_field(
+        value,
+        "flam_spacing_ms",
+        usize::from(MAX_FLAM_SPACING_MS) + 1,
+    )? {
+        step.flam_spacing_ms = spacing as u8;
+    }
+    if let Some(gate) = optional_index_field(value, "gate", usize::from(MAX_STEP_GATE) + 1)? {
+        step.gate = gate as u8;
+    }
+    if !step.is_valid() {
+        return Err("step out of range".to_string());
+    }
+    Ok(step)
+}
+
+fn optional_number(value: Option<usize>) -> String {
+    value
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| "null".to_string())
+}
+
+fn string_field(value: &JsonValue, key: &str) -> Result<String, String> {
+    value
+        .field(key)
+        .and_then(JsonValue::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| format!("missing string field: {key}"))
+}
+
+fn number_field(value: &JsonValue, key: &str) -> Result<f32, String> {
+    value
+        .field(key)
+        .and_then(JsonValue::as_f64)
+        .filter(|number| number.is_finite())
+        .map(|number| number as f32)
+        .ok_or_else(|| format!("missing number field: {key}"))
+}
+
+fn bool_field(value: &JsonValue, key: &str) -> Result<bool, String> {
+    match value.field(key) {
+        Some(JsonValue::Bool(value)) => Ok(*value),
+        _ => Err(format!("missing bool field: {key}")),
+    }
+}
+
+fn index_field(value: &JsonValue, key: &str, limit: usize) -> Result<usize, String> {
+    let number = value
+        .field(key)
+        .and_then(JsonValue::as_f64)
+        .ok_or_else(|| format!("missing index field: {key}"))?;
+    if number < 0.0 || number.fract() != 0.0 || number >= limit as f64 {
+        return Err(format!("{key} out of range: {number}"));
+    }
+    Ok(number as usize)
+}
+
+fn optional_index_field(
+    value: &JsonValue,
+    key: &str,
+    limit: usize,
+) -> Result<Option<usize>, String> {
+    match value.field(key) {
+        Some(JsonValue::Null) | None => Ok(None),
+        Some(_) => index_field(value, key, limit).map(Some),
+    }
+}
+
+fn array_field<'a>(value: &'a JsonValue, key: &str) -> Result<&'a [JsonValue], String> {
+    value
+        .field(key)
+        .and_then(JsonValue::as_array)
+        .ok_or_else(|| format!("missing array field: {key}"))
+}
+
+pub fn parse_json(text: &str) -> Result<JsonValue, String> {
+    let mut parser = JsonParser {
+        bytes: text.as_bytes(),
+        cursor: 0,
+    };
+    let value = parser.parse_value(0)?;
+    parser.skip_whitespace();
+    if parser.cursor != parser.bytes.len() {
+        return Err(format!("trailing json content at byte {}", parser.cursor));
+    }
+    Ok(value)
+}
+
+struct JsonParser<'a> {
+    bytes: &'a [u8],
+    cursor: usize,
+}
+
+impl JsonParser<'_> {
+    fn parse_value(&mut self, depth: usize) -> Result<JsonValue, String> {
+        if depth > 64 {
+            return Err("json nesting too deep".to_string());
+        }
+
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.parse_object(depth),
+            Some(b'[') => self.parse_array(depth),
+            Some(b'"') => self.parse_string().map(JsonValue::String),
+            Some(b't') => self.parse_literal("true", JsonValue::Bool(true)),
+            Some(b'f') => self.parse_literal("false", JsonValue::Bool(false)),
+            Some(b'n') => self.parse_literal("null", JsonValue::Null),
+            Some(_) => self.parse_number(),
+            None => Err("unexpected end of json".to_string()),
+        }
+    }
+
+    fn parse_object(&mut self, depth: usize) -> Result<JsonValue, String> {
+        self.expect(b'{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.cursor += 1;
+            return Ok(JsonValue::Object(fields));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(b':')?;
+            fields.push((key, self.parse_value(depth + 1)?));
+            self.skip_whitespace();
+            match self.next() {
+                Some(b',') => continue,
+                Some(b'}') => return Ok(JsonValue::Object(fields)),
+                _ => return Err(format!("expected ',' or '}}' at byte {}", self.cursor)),
+            }
+        }
+    }
+
+    fn parse_array(&mut self, depth: usize) -> Result<JsonValue, String> {
+        self.expect(b'[')?;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.cursor += 1;
+            return Ok(JsonValue::Array(values));
+        }
+
+        loop {
+            values.push(self.parse_value(depth + 1)?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(b',') => continue,
+                Some(b']') => return Ok(JsonValue::Array(values)),
+                _ => return Err(format!("expected ',' or ']' at byte {}", self.cursor)),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.expect(b'"')?;
+        let mut value = String::new();
+        loop {
+            let start = self.cursor;
+            while let Some(byte) = self.peek() {
+                if byte == b'"' || byte == b'\\' {
+                    break;
+                }
+                self.cursor += 1;
+            }
+            value.push_str(
+                std::str::from_utf8(&self.bytes[start..self.cursor])
+                    .map_err(|_| "invalid utf8 in json string".to_string())?,
+            );
+
+            match self.next() {
+                Some(b'"') => return Ok(value),
+                Some(b'\\') => match self.next() {
+                    Some(b'"') => value.push('"'),
+                    Some(b'\\') => value.push('\\'),
+                    Some(b'/') => value.push('/'),
+                    Some(b'n') => value.push('\n'),
+                    Some(b'r') => value.push('\r'),
+                    Some(b't') => value.push('\t'),
+                    Some(b'b') => value.push('\u{8}'),
+                    Some(b'f') => value.push('\u{c}'),
+                    Some(b'u') => {
+                        let hex = self
+                            .bytes
+                            .get(self.cursor..self.cursor + 4)
+                            .and_then(|digits| std::str::from_utf8(digits).ok())
+                            .ok_or_else(|| "truncated json unicode escape".to_string())?;
+                        let code = u32::from_str_radix(hex, 16)
+                            .map_err(|_| format!("invalid json unicode escape: {hex}"))?;
+                        value.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                        self.cursor += 4;
+                    }
+                    _ => return Err("invalid json escape".to_string()),
+                },
+                _ => return Err("unterminated json string".to_string()),
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<JsonValue, String> {
+        let start = self.cursor;
+        while let Some(byte) = self.peek() {
+            if !matches!(byte, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E') {
+                break;
+            }
+            self.cursor += 1;
+        }
+        let literal = std::str::from_utf8(&self.bytes[start..self.cursor]).unwrap_or_default();
+        literal
+            .parse::<f64>()
+            .map(JsonValue::Number)
+            .map_err(|_| format!("invalid json number at byte {start}"))
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: JsonValue) -> Result<JsonValue, String> {
+        if self.bytes[self.cursor..].starts_with(literal.as_bytes()) {
+            self.cursor += literal.len();
+            Ok(value)
+        } else {
+            Err(format!("invalid json literal at byte {}", self.cursor))
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), String> {
+        match self.next() {
+            Some(byte) if byte == expected => Ok(()),
+            _ => Err(format!(
+                "expected '{}' at byte {}",
+                expected as char, self.cursor
+            )),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\n' | b'\r' | b'\t')) {
+            self.cursor += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.cursor).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
+        self.cursor += 1;
+        Some(byte)
+    }
+}
@@ -0,0 +1,1184 @@
+use std::path::{Path, PathBuf};
+
+use presets_rs::{Project, TRACK_COUNT};
+
+mod binary;
+mod json;
+
+const MIDI_TICKS_PER_QUARTER: u16 = 96;
+const MIDI_DRUM_CHANNEL: u8 = 9;
+const MIDI_BASE_NOTE: u8 = 36;
+const DEFAULT_SAMPLE_RATE_HZ: u32 = 48_000;
+
+const USAGE: &str = "Usage: ffctl <command> [args]
+
+Commands:
+  inspect <project>                    Print a project summary.
+  validate <project> [--samples DIR]   Check that a project loads and recalls.
+  convert <input> <output>             Convert between .ffproject, .json and .ffpb.
+  diff <project-a> <project-b>         List differences between two projects.
+  export-midi <project> <out.mid> [--bars N]
+  render <project> <out.wav> [--bars N] [--sample-rate HZ] [--samples DIR] [--threads N]
+         [--event-log OUT.jsonl]
+  freeze <project> <track> <out.wav> [--sample-rate HZ] [--samples DIR]
+  unfreeze <project> <track>
+  analyze <sample.wav> [--project-bpm BPM] [--stretch]
+  pack <project> <out.ffpack> [--library INDEX]
+  unpack <archive.ffpack> <dir>";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ProjectFormat {
+    Text,
+    Json,
+    Binary,
+}
+
+fn format_for_path(path: &Path) -> ProjectFormat {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => ProjectFormat::Json,
+        Some("ffpb") => ProjectFormat::Binary,
+        _ => ProjectFormat::Text,
+    }
+}
+
+fn decode_project(bytes: &[u8], format: ProjectFormat) -> Result<Project, String> {
+    match format {
+        ProjectFormat::Text => {
+            let text = std::str::from_utf8(bytes).map_err(|_| "project is not utf8".to_string())?;
+            presets_rs::load_project_from_text(text)
+        }
+        ProjectFormat::Json => {
+            let text = std::str::from_utf8(bytes).map_err(|_| "json is not utf8".to_string())?;
+            json::project_from_json(text)
+        }
+        ProjectFormat::Binary => binary::project_from_binary(bytes),
+    }
+}
+
+fn encode_project(project: &Project, format: ProjectFormat) -> Vec<u8> {
+    match format {
+        ProjectFormat::Text => presets_rs::save_project_to_text(project).into_bytes(),
+        ProjectFormat::Json => json::project_to_json(project).into_bytes(),
+        ProjectFormat::Binary => binary::project_to_binary(project),
+    }
+}
+
+fn read_project(path: &Path) -> Result<Project, String> {
+    let bytes = std::fs::read(path)
+        .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+    decode_project(&bytes, format_for_path(path))
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    std::fs::write(path, bytes)
+        .map_err(|error| format!("failed to write {}: {error}", path.display()))
+}
+
+fn inspect_project(project: &Project) -> String {
+    let mut lines = vec![
+        format!("name: {}", project.name),
+        format!(
+            "kits: {} (active {:?})",
+            project.kits.len(),
+            project.active_kit
+        ),
+        format!(
+            "patterns: {} (active {:?})",
+            project.patterns.len(),
+            project.active_pattern
+        ),
+    ];
+
+    for (kit_index, kit) in project.kits.iter().enumerate() {
+        lines.push(format!("kit {kit_index}: {}", kit.name));
+        let mut tracks = kit.tracks.clone();
+        tracks.sort_by_key(|track| track.track_index);
+        for track in tracks {
+            let frozen = project.frozen_tracks.iter().any(|freeze| {
+                freeze.kit_index == kit_index && freeze.track_index == track.track_index
+            });
+            lines.push(format!(
+                "  track {}: {}{}",
+                track.track_index,
+                track.sample_id,
+                if frozen { " (frozen)" } else { "" }
+            ));
+        }
+    }
+
+    for (pattern_index, pattern) in project.patterns.iter().enumerate() {
+        let active_steps = pattern
+            .steps
+            .iter()
+            .flatten()
+            .filter(|step| step.active)
+            .count();
+        lines.push(format!(
+            "pattern {pattern_index}: {} (swing {:.2}, {active_steps} active steps)",
+            pattern.name, pattern.swing
+        ));
+        for track_index in 0..TRACK_COUNT {
+            let row: String = pattern.steps[track_index]
+                .iter()
+                .map(|step| if step.active { 'x' } else { '-' })
+                .collect();
+            lines.push(format!("  {track_index}: {row}"));
+        }
+    }
+
+    lines.join("\n")
+}
+
+fn diff_projects(left: &Project, right: &Project) -> Vec<String> {
+    let mut differences = Vec::new();
+    if left.name != right.name {
+        differences.push(format!("name: {:?} -> {:?}", left.name, right.name));
+    }
+    if left.active_kit != right.active_kit {
+        differences.push(format!(
+            "active_kit: {:?} -> {:?}",
+            left.active_kit, right.active_kit
+        ));
+    }
+    if left.active_pattern != right.active_pattern {
+        differences.push(format!(
+            "active_pattern: {:?} -> {:?}",
+            left.active_pattern, right.active_pattern
+        ));
+    }
+    if left.random_seed != right.random_seed {
+        differences.push(format!(
+            "random_seed: {} -> {}",
+            left.random_seed, right.random_seed
+        ));
+    }
+    if left.humanize != right.humanize {
+        differences.push(format!(
+            "humanize: {:?} -> {:?}",
+            left.humanize, right.humanize
+        ));
+    }
+    if left.audio_hints != right.audio_hints {
+        differences.push(format!(
+            "audio_hints: {:?} -> {:?}",
+            left.audio_hints, right.audio_hints
+        ));
+    }
+    if left.key != right.key {
+        differences.push(format!(
+            "key: {} -> {}",
+            left.key.to_text(),
+            right.key.to_text()
+        ));
+    }
+    if left.metronome != right.metronome {
+        differences.push(format!(
+            "metronome: {:?} -> {:?}",
+            left.metronome, right.metronome
+        ));
+    }
+    if left.song != right.song || left.song_loop != right.song_loop {
+        differences.push(format!(
+            "song: {} entries (loop {}) -> {} entries (loop {})",
+            left.song.len(),
+            left.song_loop,
+            right.song.len(),
+            right.song_loop
+        ));
+    }
+    if left.program_changes != right.program_changes {
+        differences.push(format!(
+            "program_changes: {} -> {} mapped programs",
+            left.program_changes.len(),
+            right.program_changes.len()
+        ));
+    }
+    if left.grooves != right.grooves {
+        differences.push(format!(
+            "grooves: {} -> {} user grooves",
+            left.grooves.len(),
+            right.grooves.len()
+        ));
+    }
+    let frozen = |project: &Project| -> Vec<(usize, u8, String)> {
+        project
+            .frozen_tracks
+            .iter()
+            .map(|freeze| {
+                (
+                    freeze.kit_index,
+                    freeze.track_index,
+                    freeze.bounce_sample_id.clone(),
+                )
+            })
+            .collect()
+    };
+    if left.frozen_tracks != right.frozen_tracks {
+        differences.push(format!(
+            "frozen_tracks: {:?} -> {:?}",
+            frozen(left),
+            frozen(right)
+        ));
+    }
+    for track_index in 0..TRACK_COUNT {
+        let output_a = left.output_routing.track_output(track_index);
+        let output_b = right.output_routing.track_output(track_index);
+        if output_a != output_b {
+            differences.push(format!(
+                "output[{track_index}]: {:?} -> {:?}",
+                output_a, output_b
+            ));
+        }
+        let input = |project: &Project| {
+            (
+                project.track_inputs.is_armed(track_index),
+                project.track_inputs.is_monitoring(track_index),
+            )
+        };
+        if input(left) != input(right) {
+            differences.push(format!(
+                "input[{track_index}] (armed, monitoring): {:?} -> {:?}",
+                input(left),
+                input(right)
+            ));
+        }
+    }
+
+    for kit_index in 0..left.kits.len().max(right.kits.len()) {
+        match (left.kits.get(kit_index), right.kits.get(kit_index)) {
+            (Some(a), Some(b)) => {
+                if a.name != b.name {
+                    differences.push(format!(
+                        "kit[{kit_index}].name: {:?} -> {:?}",
+                        a.name, b.name
+                    ));
+                }
+                for track_index in 0..TRACK_COUNT as u8 {
+                    let sample_a = a
+                        .tracks
+                        .iter()
+                        .find(|track| track.track_index == track_index);
+                    let sample_b = b
+                        .tracks
+                        .iter()
+                        .find(|track| track.track_index == track_index);
+                    if sample_a.map(|track| &track.sample_id)
+                        != sample_b.map(|track| &track.sample_id)
+                    {
+                        differences.push(format!(
+                            "kit[{kit_index}].track[{track_index}].sample: {:?} -> {:?}",
+                            sample_a.map(|track| track.sample_id.as_str()),
+                            sample_b.map(|track| track.sample_id.as_str())
+                        ));
+                    }
+                    if a.track_controls(track_index) != b.track_controls(track_index) {
+                        differences.push(format!(
+                            "kit[{kit_index}].track[{track_index}].controls: {:?} -> {:?}",
+                            a.track_controls(track_index),
+                            b.track_controls(track_index)
+                        ));
+                    }
+                    let region_a = a.track_region(track_index);
+                    let region_b = b.track_region(track_index);
+                    if region_a != region_b {
+                        differences.push(format!(
+                            "kit[{kit_index}].track[{track_index}].region: {:?} -> {:?}",
+                            region_a.map(|region| (region.start_frame, region.end_frame)),
+                            region_b.map(|region| (region.start_frame, region.end_frame))
+                        ));
+                    }
+                    if a.track_trim(track_index) != b.track_trim(track_index) {
+                        differences.push(format!(
+                            "kit[{kit_index}].track[{track_index}].trim: {:?} -> {:?}",
+                            a.track_trim(track_index),
+                            b.track_trim(track_index)
+                        ));
+                    }
+                    if a.track_ducking(track_index) != b.track_ducking(track_index) {
+                        differences.push(format!(
+                            "kit[{kit_index}].track[{track_index}].ducking: {:?} -> {:?}",
+                            a.track_ducking(track_index),
+                            b.track_ducking(track_index)
+                        ));
+                    }
+                    if a.track_midi_output(track_index) != b.track_midi_output(track_index) {
+                        differences.push(format!(
+                            "kit[{kit_index}].track[{track_index}].midi_output: {:?} -> {:?}",
+                            a.track_midi_output(track_index),
+                            b.track_midi_output(track_index)
+                        ));
+                    }
+                    if a.track_label(track_index) != b.track_label(track_index) {
+                        differences.push(format!(
+                            "kit[{kit_index}].track[{track_index}].label: {:?} -> {:?}",
+                            a.track_label(track_index),
+                            b.track_label(track_index)
+                        ));
+                    }
+                    if a.track_choke_targets(track_index) != b.track_choke_targets(track_index) {
+                        differences.push(format!(
+                            "kit[{kit_index}].track[{track_index}].choke_targets: {:?} -> {:?}",
+                            a.track_choke_targets(track_index),
+                            b.track_choke_targets(track_index)
+                        ));
+                    }
+                    if a.track_humanize(track_index) != b.track_humanize(track_index) {
+                        differences.push(format!(
+                            "kit[{kit_index}].track[{track_index}].humanize: {:?} -> {:?}",
+                            a.track_humanize(track_index),
+                            b.track_humanize(track_index)
+                        ));
+                    }
+                }
+                for group_index in 0..presets_rs::TRACK_GROUP_COUNT as u8 {
+                    if a.track_group(group_index) != b.track_group(group_index) {
+                        differences.push(format!(
+                            "kit[{kit_index}].group[{group_index}]: {:?} -> {:?}",
+                            a.track_group(group_index),
+                            b.track_group(group_index)
+                        ));
+                    }
+                }
+                if a.master != b.master {
+                    differences.push(format!(
+                        "kit[{kit_index}].master: {:?} -> {:?}",
+                        a.master, b.master
+                    ));
+                }
+            }
+            (Some(_), None) => differences.push(format!("kit[{kit_index}]: removed")),
+            (None, Some(_)) => differences.push(format!("kit[{kit_index}]: added")),
+            (None, None) => {}
+        }
+    }
+
+    for pattern_index in 0..left.patterns.len().max(right.patterns.len()) {
+        match (
+            left.patterns.get(pattern_index),
+            right.patterns.get(pattern_index),
+        ) {
+            (Some(a), Some(b)) => {
+                if a.name != b.name {
+                    differences.push(format!(
+                        "pattern[{pattern_index}].name: {:?} -> {:?}",
+                        a.name, b.name
+                    ));
+                }
+                if a.swing != b.swing {
+                    differences.push(format!(
+                        "pattern[{pattern_index}].swing: {:.6} -> {:.6}",
+                        a.swing, b.swing
+                    ));
+                }
+                if a.groove != b.groove {
+                    differences.push(format!(
+                        "pattern[{pattern_index}].groove: {:?} -> {:?}",
+                        a.groove, b.groove
+                    ));
+                }
+                if a.bar_count != b.bar_count {
+                    differences.push(format!(
+                        "pattern[{pattern_index}].bars: {} -> {}",
+                        a.bar_count, b.bar_count
+                    ));
+                }
+                if a.accent != b.accent {
+                    differences.push(format!(
+                        "pattern[{pattern_index}].accent: {:?} -> {:?}",
+                        a.accent, b.accent
+                    ));
+                }
+                if a.length_steps != b.length_steps {
+                    differences.push(format!(
+                        "pattern[{pattern_index}].length: {} -> {}",
+                        a.step_count(),
+                        b.step_count()
+                    ));
+                }
+                if a.scale != b.scale {
+                    differences.push(format!(
+                        "pattern[{pattern_index}].scale: {} -> {}",
+                        a.scale.name(),
+                        b.scale.name()
+                    ));
+                }
+                if a.tempo != b.tempo {
+                    differences.push(format!(
+                        "pattern[{pattern_index}].tempo: {:?} -> {:?}",
+                        a.tempo, b.tempo
+                    ));
+                }
+                if a.variations != b.variations {
+                    differences.push(format!(
+                        "pattern[{pattern_index}].variations: {} -> {} overridden steps",
+                        a.variations.len(),
+                        b.variations.len()
+                    ));
+                }
+                if a.mutes != b.mutes {
+                    differences.push(format!(
+                        "pattern[{pattern_index}].mutes: {:?} -> {:?}",
+                        a.mutes, b.mutes
+                    ));
+                }
+                if a.locks != b.locks {
+                    differences.push(format!(
+                        "pattern[{pattern_index}].locks: {} -> {} locked steps",
+                        a.locks.len(),
+                        b.locks.len()
+                    ));
+                }
+                if a.directions != b.directions {
+                    differences.push(format!(
+                        "pattern[{pattern_index}].directions: {:?} -> {:?}",
+                        a.directions, b.directions
+                    ));
+                }
+                if a.resolutions != b.resolutions {
+                    differences.push(format!(
+                        "pattern[{pattern_index}].resolutions: {:?} -> {:?}",
+                        a.resolutions, b.resolutions
+                    ));
+                }
+                if a.swing_grid != b.swing_grid {
+                    differences.push(format!(
+                        "pattern[{pattern_index}].swing_grid: {} -> {}",
+                        a.swing_grid.name(),
+                        b.swing_grid.name()
+                    ));
+                }
+                if a.default_steps != b.default_steps {
+                    differences.push(format!(
+                        "pattern[{pattern_index}].default_steps: {:?} -> {:?}",
+                        a.default_steps, b.default_steps
+                    ));
+                }
+                if a.markers != b.markers {
+                    differences.push(format!(
+                        "pattern[{pattern_index}].markers: {:?} -> {:?}",
+                        a.markers, b.markers
+                    ));
+                }
+                for track_index in 0..TRACK_COUNT {
+                    for step_index in 0..a.step_count().max(b.step_count()) {
+                        let step_a = a.steps[track_index][step_index];
+                        let step_b = b.steps[track_index][step_index];
+                        if step_a != step_b {
+                            differences.push(format!(
+                                "pattern[{pattern_index}].step[{track_index}][{step_index}]: {step_a:?} -> {step_b:?}"
+                            ));
+                        }
+                    }
+                }
+            }
+            (Some(_), None) => differences.push(format!("pattern[{pattern_index}]: removed")),
+            (None, Some(_)) => differences.push(format!("pattern[{pattern_index}]: added")),
+            (None, None) => {}
+        }
+    }
+
+    differences
+}
+
+fn pattern_frames(project: &Project, sample_rate_hz: u32, bars: u32) -> Result<u32, String> {
+    let recall = control_rs::recall_state_from_project(project, sample_rate_hz)?;
+    let bpm = f64::from(recall.sequencer().transport().bpm());
+    let bar_frames = f64::from(sample_rate_hz) * 60.0 / bpm * 4.0;
+    Ok((bar_frames * f64::from(bars)).ceil() as u32)
+}
+
+fn export_midi(project: &Project, bars: u32) -> Result<Vec<u8>, String> {
+    let sample_rate_hz = DEFAULT_SAMPLE_RATE_HZ;
+    let frames = pattern_frames(project, sample_rate_hz, bars)?;
+    let events = control_rs::render_recall_events(project, sample_rate_hz, &[frames])?;
+    let recall = control_rs::recall_state_from_project(project, sample_rate_hz)?;
+    let bpm = f64::from(recall.sequencer().transport().bpm());
+
+    let ticks_per_sample =
+        f64::from(MIDI_TICKS_PER_QUARTER) * bpm / (60.0 * f64::from(sample_rate_hz));
+    let note_length = u32::from(MIDI_TICKS_PER_QUARTER / 8);
+    let mut timed: Vec<(u32, [u8; 3])> = Vec::new();
+    for event in events {
+        if event.timeline_sample >= u64::from(frames) {
+            continue;
+        }
+        let tick = (event.timeline_sample as f64 * ticks_per_sample).round() as u32;
+        let (channel, note, length) = match recall.track_midi_output(usize::from(event.track_index))
+        {
+            Some(output) => (
+                output.channel,
+                output.note,
+                (f64::from(output.length_steps) * f64::from(MIDI_TICKS_PER_QUARTER) / 4.0)
+                    .round()
+                    .max(1.0) as u32,
+            ),
+            None => (
+                MIDI_DRUM_CHANNEL,
+                MIDI_BASE_NOTE + event.track_index,
+                note_length,
+            ),
+        };
+        timed.push((tick, [0x90 | channel, note, event.velocity.max(1)]));
+        timed.push((tick + length, [0x80 | channel, note, 0]));
+    }
+    timed.sort_by_key(|(tick, message)| (*tick, message[0] & 0xF0 == 0x90));
+
+    let mut track = Vec::new();
+    let micros_per_quarter = (60_000_000.0 / bpm).round() as u32;
+    write_variable_length(&mut track, 0);
+    track.extend_from_slice(&[0xFF, 0x51, 0x03]);
+    track.extend_from_slice(&micros_per_quarter.to_be_bytes()[1..]);
+
+    let mut last_tick = 0;
+    for (tick, message) in timed {
+        write_variable_length(&mut track, tick - last_tick);
+        track.extend_from_slice(&message);
+        last_tick = tick;
+    }
+    write_variable_length(&mut track, 0);
+    track.extend_from_slice(&[0xFF, 0x2F, 0x00]);
+
+    let mut bytes = Vec::with_capacity(track.len() + 22);
+    bytes.extend_from_slice(b"MThd");
+    bytes.extend_from_slice(&6u32.to_be_bytes());
+    bytes.extend_from_slice(&0u16.to_be_bytes());
+    bytes.extend_from_slice(&1u16.to_be_bytes());
+    bytes.extend_from_slice(&MIDI_TICKS_PER_QUARTER.to_be_bytes());
+    bytes.extend_from_slice(b"MTrk");
+    bytes.extend_from_slice(&(track.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(&track);
+    Ok(bytes)
+}
+
+fn write_variable_length(output: &mut Vec<u8>, value: u32) {
+    let mut groups = vec![(value & 0x7F) as u8];
+    let mut remaining = value >> 7;
+    while remaining > 0 {
+        groups.push(((remaining & 0x7F) as u8) | 0x80);
+        remaining >>= 7;
+    }
+    output.extend(groups.iter().rev());
+}
+
+fn render_audio(
+    project: &Project,
+    sample_dir: &Path,
+    sample_rate_hz: u32,
+    bars: u32,
+    render_threads: usize,
+) -> Result<(Vec<u8>, render_rs::RenderOutput), String> {
+    let frames = pattern_frames(project, sample_rate_hz, bars)?;
+    let output = render_rs::render_project_threaded(
+        project,
+        sample_rate_hz,
+        frames,
+        render_rs::DEFAULT_RENDER_BLOCK_FRAMES,
+        render_threads,
+        &control_rs::telemetry::EngineMetrics::new(),
+        |sample_id| load_mono_sample(sample_dir, sample_id),
+    )?;
+    Ok((
+        render_rs::wav::write_wav_16(&output.audio, 1, sample_rate_hz),
+        output,
+    ))
+}
+
+fn session_event_log(
+    project: &Project,
+    sample_rate_hz: u32,
+    events: &[control_rs::StepTriggerEvent],
+) -> Result<control_rs::event_log::EventLog, String> {
+    let recall = control_rs::recall_state_from_project(project, sample_rate_hz)?;
+    let mut log = control_rs::event_log::EventLog::new();
+    log.record_parameter_updates(0, &recall.to_engine_recall().parameter_updates);
+    log.record_triggers(events);
+    Ok(log)
+}
+
+fn load_mono_sample(sample_dir: &Path, sample_id: &str) -> Option<Vec<f32>> {
+    let bytes = std::fs::read(sample_dir.join(sample_id)).ok()?;
+    render_rs::wav::read_wav(&bytes)
+        .ok()
+        .map(|wav| wav.to_mono())
+}
+
+fn freeze_project_track(
+    project: &mut Project,
+    track_index: usize,
+    sample_dir: &Path,
+    output: &Path,
+    sample_rate_hz: u32,
+) -> Result<(Vec<u8>, Vec<String>), String> {
+    let bounce_sample_id = output
+        .strip_prefix(sample_dir)
+        .unwrap_or(output)
+        .to_string_lossy()
+        .into_owned();
+    let bounce = render_rs::freeze_track(
+        project,
+        track_index,
+        &bounce_sample_id,
+        sample_rate_hz,
+        |sample_id| load_mono_sample(sample_dir, sample_id),
+    )?;
+    Ok((
+        render_rs::wav::write_wav_16(&bounce.audio, 1, sample_rate_hz),
+        bounce.missing_samples,
+    ))
+}
+
+fn track_argument(args: &[String], index: usize) -> Result<usize, String> {
+    let value = args.get(index).ok_or_else(|| USAGE.to_string())?;
+    value
+        .parse::<usize>()
+        .ok()
+        .filter(|track_index| *track_index < TRACK_COUNT)
+        .ok_or_else(|| format!("invalid track: {value}"))
+}
+
+fn analyze_sample(bytes: &[u8], project_bpm: f32, stretch: bool) -> Result<String, String> {
+    let wav = render_rs::wav::read_wav(bytes)?;
+    let estimate = render_rs::tempo::estimate_tempo(&wav.to_mono(), wav.sample_rate_hz);
+    let mut report = match estimate {
+        Some(estimate) => {
+            let mut line = format!(
+                "tempo: {:.2} bpm (confidence {:.2})",
+                estimate.bpm, estimate.confidence
+            );
+            if let Some(bars) = estimate.bars {
+                line.push_str(&format!("\nbars: {bars}"));
+            }
+            line
+        }
+        None => "tempo: undetected".to_string(),
+    };
+    let action = match render_rs::tempo::suggest_loop_tempo(estimate, project_bpm, stretch) {
+        render_rs::tempo::LoopTempoAction::Matches => "matches project tempo".to_string(),
+        render_rs::tempo::LoopTempoAction::SetProjectTempo(bpm) => {
+            format!("set project tempo to {bpm:.2} bpm")
+        }
+        render_rs::tempo::LoopTempoAction::TimeStretch { ratio } => {
+            format!("time-stretch by {ratio:.4} to fit {project_bpm:.2} bpm")
+        }
+        render_rs::tempo::LoopTempoAction::Undetected => "no tempo action".to_string(),
+    };
+    report.push_str(&format!("\nsuggestion: {action}"));
+    Ok(report)
+}
+
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|index| args.get(index + 1))
+        .map(String::as_str)
+}
+
+fn parse_option<T: std::str::FromStr>(
+    args: &[String],
+    name: &str,
+    default: T,
+) -> Result<T, String> {
+    match option_value(args, name) {
+        Some(value) => value
+            .parse::<T>()
+            .map_err(|_| format!("invalid value for {name}: {value}")),
+        None => Ok(default),
+    }
+}
+
+fn positional(args: &[String], index: usize) -> Result<PathBuf, String> {
+    args.get(index)
+        .filter(|arg| !arg.starts_with("--"))
+        .map(PathBuf::from)
+        .ok_or_else(|| USAGE.to_string())
+}
+
+fn run(args: &[String]) -> Result<String, String> {
+    let command = args
+        .first()
+        .map(String::as_str)
+        .ok_or_else(|| USAGE.to_string())?;
+    match command {
+        "inspect" => Ok(inspect_project(&read_project(&positional(args, 1)?)?)),
+        "validate" => {
+            let path = positional(args, 1)?;
+            let project = read_project(&path)?;
+            let mut recall =
+                control_rs::recall_state_from_project(&project, DEFAULT_SAMPLE_RATE_HZ)?;
+            let sample_dir = option_value(args, "--samples")
+                .map(PathBuf::from)
+                .or_else(|| path.parent().map(Path::to_path_buf))
+                .unwrap_or_default();
+            let mut report = format!("{}: ok", path.display());
+            for sample_id in
+                recall.resolve_samples(|sample_id| sample_dir.join(sample_id).is_file())
+            {
+                report.push_str(&format!("\nunresolved sample: {sample_id}"));
+            }
+            Ok(report)
+        }
+        "convert" => {
+            let input = positional(args, 1)?;
+            let output = positional(args, 2)?;
+            let project = read_project(&input)?;
+            write_file(&output, &encode_project(&project, format_for_path(&output)))?;
+            Ok(format!("wrote {}", output.display()))
+        }
+        "diff" => {
+            let differences = diff_projects(
+                &read_project(&positional(args, 1)?)?,
+                &read_project(&positional(args, 2)?)?,
+            );
+            if differences.is_empty() {
+                Ok("projects are identical".to_string())
+            } else {
+                Ok(differences.join("\n"))
+            }
+        }
+        "export-midi" => {
+            let project = read_project(&positional(args, 1)?)?;
+            let output = positional(args, 2)?;
+            let bars = parse_option(args, "--bars", 1u32)?.max(1);
+            write_file(&output, &export_midi(&project, bars)?)?;
+            Ok(format!("wrote {}", output.display()))
+        }
+        "render" => {
+            let input = positional(args, 1)?;
+            let output = positional(args, 2)?;
+            let project = read_project(&input)?;
+            let bars = parse_option(args, "--bars", 1u32)?.max(1);
+            let sample_rate_hz =
+                parse_option(args, "--sample-rate", DEFAULT_SAMPLE_RATE_HZ)?.max(1);
+            let sample_dir = option_value(args, "--samples")
+                .map(PathBuf::from)
+                .or_else(|| input.parent().map(Path::to_path_buf))
+                .unwrap_or_default();
+            let render_threads = parse_option(args, "--threads", 1usize)?;
+            let (wav, rendered) =
+                render_audio(&project, &sample_dir, sample_rate_hz, bars, render_threads)?;
+            write_file(&output, &wav)?;
+            let mut report = format!("wrote {}", output.display());
+            if let Some(log_path) = option_value(args, "--event-log").map(PathBuf::from) {
+                let log = session_event_log(&project, sample_rate_hz, &rendered.events)?;
+                write_file(&log_path, log.to_jsonl().as_bytes())?;
+                report.push_str(&format!(
+                    "\nwrote {} ({} events)",
+                    log_path.display(),
+                    log.len()
+                ));
+            }
+            for sample_id in rendered.missing_samples {
+                report.push_str(&format!("\nmissing sample: {sample_id}"));
+            }
+            Ok(report)
+        }
+        "freeze" => {
+            let input = positional(args, 1)?;
+            let track_index = track_argument(args, 2)?;
+            let output = positional(args, 3)?;
+            let mut project = read_project(&input)?;
+            let sample_rate_hz =
+                parse_option(args, "--sample-rate", DEFAULT_SAMPLE_RATE_HZ)?.max(1);
+            let sample_dir = option_value(args, "--samples")
+                .map(PathBuf::from)
+                .or_else(|| input.parent().map(Path::to_path_buf))
+                .unwrap_or_default();
+            let (wav, missing) = freeze_project_track(
+                &mut project,
+                track_index,
+                &sample_dir,
+                &output,
+                sample_rate_hz,
+            )?;
+            write_file(&output, &wav)?;
+            write_file(&input, &encode_project(&project, format_for_path(&input)))?;
+            let mut report = format!("froze track {track_index} into {}", output.display());
+            for sample_id in missing {
+                report.push_str(&format!("\nmissing sample: {sample_id}"));
+            }
+            Ok(report)
+        }
+        "unfreeze" => {
+            let input = positional(args, 1)?;
+            let track_index = track_argument(args, 2)?;
+            let mut project = read_project(&input)?;
+            if !project.unfreeze_track(track_index as u8) {
+                return Err(format!("track {track_index} is not frozen"));
+            }
+            write_file(&input, &encode_project(&project, format_for_path(&input)))?;
+            Ok(format!("unfroze track {track_index}"))
+        }
+        "analyze" => {
+            let path = positional(args, 1)?;
+            let bytes = std::fs::read(&path)
+                .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+            let project_bpm = parse_option(args, "--project-bpm", control_rs::DEFAULT_BPM)?;
+            let stretch = args.iter().any(|arg| arg == "--stretch");
+            analyze_sample(&bytes, project_bpm, stretch)
+        }
+        "pack" => {
+            let input = positional(args, 1)?;
+            let output = positional(args, 2)?;
+            let project = read_project(&input)?;
+            let library = option_value(args, "--library")
+                .map(|path| library_rs::LibraryIndex::load(Path::new(path)))
+                .transpose()?;
+            let project_dir = input.parent().map(Path::to_path_buf).unwrap_or_default();
+            let archive =
+                library_rs::pack::pack_loaded_project(&project, &project_dir, library.as_ref())?;
+            write_file(&output, &archive.to_bytes())?;
+            Ok(format!(
+                "wrote {} ({} samples)",
+                output.display(),
+                archive.samples.len()
+            ))
+        }
+        "unpack" => {
+            let archive = positional(args, 1)?;
+            let directory = positional(args, 2)?;
+            let (project_path, _) = library_rs::pack::unpack_project(&archive, &directory)?;
+            Ok(format!("wrote {}", project_path.display()))
+        }
+        "help" | "--help" | "-h" => Ok(USAGE.to_string()),
+        _ => Err(format!("unknown command: {command}\n{USAGE}")),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(output) => println!("{output}"),
+        Err(error) => {
+            eprintln!("ffctl: {error}");
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use presets_rs::{
+        Kit, Pattern, PatternStep, Project, TrackAssignment, TrackChokeTargets, TrackControls,
+        TrackHumanize, TrackLabel, TrackMidiOutput,
+    };
+
+    use super::{
+        decode_project, diff_projects, encode_project, export_midi, session_event_log,
+        ProjectFormat, DEFAULT_SAMPLE_RATE_HZ,
+    };
+
+    const STARTER_PROJECT: &str = include_str!("../../../assets/starter-kit/default.ffproject");
+
+    fn sample_project() -> Project {
+        let mut project = Project {
+            name: "ffctl \"demo\"".to_string(),
+            kits: vec![Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![Pattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        project.kits[0].add_assignment(TrackAssignment {
+            track_index: 1,
+            sample_id: "snare.wav".to_string(),
+        });
+        project.kits[0].set_track_controls(
+            1,
+            TrackControls {
+                pan: -0.25,
+                choke_group: Some(2),
+                ..TrackControls::default()
+            },
+        );
+        project.kits[0].set_track_region(1, 2_400, 9_600);
+        project.kits[0].set_track_trim(presets_rs::TrackSampleTrim {
+            track_index: 1,
+            start: 0.125,
+            end: 0.75,
+            reverse: true,
+        });
+        project.kits[0].set_track_ducking(presets_rs::TrackDucking {
+            track_index: 2,
+            source_track: 0,
+            depth: 0.5,
+            attack_ms: 4.0,
+            release_ms: 150.0,
+        });
+        project.kits[0].set_track_group(presets_rs::TrackGroup {
+            members: vec![0, 1],
+            gain_db: -3.0,
+            soloed: true,
+            ..presets_rs::TrackGroup::new(2, "drums")
+        });
+        project.kits[0].set_master_bus(presets_rs::MasterBus {
+            compressor_amount: 0.25,
+            filter_cutoff: 0.5,
+            limiter_ceiling_db: -1.5,
+        });
+        project.output_routing.set_track_output(1, 3);
+        project.track_inputs.set_armed(1, true);
+        project.track_inputs.set_monitoring(4, false);
+        project.metronome = presets_rs::Metronome {
+            enabled: true,
+            click_sample_id: "rim.wav".to_string(),
+            gain_db: -9.0,
+            output_pair: 2,
+            ..presets_rs::Metronome::default()
+        };
+        project.random_seed = 0xF0F0_0000_1234_5678;
+        project.humanize = presets_rs::HumanizeDepth {
+            timing_samples: 48,
+            velocity: 6,
+        };
+        project.audio_hints = presets_rs::AudioHints {
+            sample_rate_hz: Some(44_100),
+            block_size: None,
+        };
+        project.key = presets_rs::MusicalKey {
+            root: 2,
+            scale: presets_rs::MusicalScale::Dorian,
+        };
+        project.patterns[0].set_swing(0.15);
+        project.patterns[0].set_step(
+            1,
+            4,
+            PatternStep {
+                active: true,
+                velocity: 99,
+                ..PatternStep::default()
+            },
+        );
+        project.patterns[0].set_track_default_step(
+            5,
+            PatternStep {
+                velocity: 64,
+                gate: 75,
+                ..PatternStep::default()
+            },
+        );
+        let mut verse = Pattern::default();
+        verse.set_step_count(28);
+        verse.accent = presets_rs::AccentCurve {
+            boosts: [48, 40, 32, 16, 8],
+            clip: presets_rs::AccentClip::Headroom,
+        };
+        verse.scale = presets_rs::PatternScale::Half;
+        verse.swing_grid = presets_rs::SwingGrid::Sixteenth;
+        verse.tempo = Some(presets_rs::PatternTempo {
+            bpm: 96.5,
+            change: presets_rs::TempoChange::Smooth,
+        });
+        verse.set_bar_marker(1, "turnaround");
+        verse.set_mute_span(2, 1, 1);
+        verse.set_parameter_lock(presets_rs::ParameterLock {
+            track_index: 1,
+            step_index: 20,
+            target: presets_rs::LockTarget::FilterCutoff,
+            value: 0.25,
+        });
+        verse.set_track_direction(3, presets_rs::PlaybackDirection::PingPong);
+        verse.set_track_resolution(5, presets_rs::StepResolution::EighthTriplet);
+        verse.groove = Some("lazy".to_string());
+        verse.set_variation_step(
+            1,
+            2,
+            3,
+            Some(PatternStep {
+                active: true,
+                velocity: 70,
+                condition: presets_rs::TrigCondition::NotFirst,
+                ..PatternStep::default()
+            }),
+        );
+        verse.set_step(
+            3,
+            9,
+            PatternStep {
+                active: true,
+                velocity: 99,
+                probability: 60,
+                condition: presets_rs::TrigCondition::Cycle { hit: 2, of: 3 },
+                accent: true,
+                flam: true,
+                flam_spacing_ms: 12,
+                gate: 40,
+            },
+        );
+        verse.set_step(
+            0,
+            20,
+            PatternStep {
+                active: true,
+                velocity: 80,
+                ..PatternStep::default()
+            },
+        );
+        project.patterns.push(verse);
+        project.set_song(&[
+            presets_rs::SongEntry {
+                pattern_index: 0,
+                repeats: 2,
+            },
+            presets_rs::SongEntry {
+                pattern_index: 1,
+                repeats: 1,
+            },
+        ]);
+        let mut lazy = presets_rs::GrooveTemplate::new("lazy");
+        lazy.timing[3] = 0.125;
+        lazy.velocity[3] = -16;
+        project.set_groove(lazy);
+        project.set_program_change(presets_rs::ProgramChange {
+            program: 12,
+            pattern_index: Some(1),
+            kit_index: None,
+        });
+        project.kits[0].add_assignment(TrackAssignment {
+            track_index: 3,
+            sample_id: "hat.wav".to_string(),
+        });
+        project.patterns[0].set_step(
+            3,
+            2,
+            PatternStep {
+                active: true,
+                velocity: 70,
+                ..PatternStep::default()
+            },
+        );
+        project.freeze_track(3, "bounces/hat.wav");
+        project.kits[0].set_track_midi_output(TrackMidiOutput {
+            track_index: 5,
+            channel: 3,
+            note: 64,
+            length_steps: 2.0,
+        });
+        project.kits[0].set_track_label(TrackLabel {
+            track_index: 1,
+            name: "Snare \"rim\"".to_string(),
+            color: Some(0xFF_8800),
+        });
+        project.kits[0].set_track_humanize(TrackHumanize {
+            track_index: 2,
+            timing_samples: 120,
+            velocity: 8,
+        });
+        project.kits[0].set_track_choke_targets(TrackChokeTargets {
+            track_index: 2,
+            targets: 0b1000,
+        });
+        project.patterns[0].set_step(
+            5,
+            8,
+            PatternStep {
+                active: true,
+                velocity: 110,
+                ..PatternStep::default()
+            },
+        );
+        project
+    }
+
+    #[test]
+    fn every_format_roundtrips_the_project() {
+        let project = sample_project();
+        for format in [
+            ProjectFormat::Text,
+            ProjectFormat::Json,
+            ProjectFormat::Binary,
+        ] {
+            let decoded =
+                decode_project(&encode_project(&project, format), format).expect("decode");
+            assert_eq!(decoded, project, "{format:?} should roundtrip");
+        }
+
+        let starter = presets_rs::load_project_from_text(STARTER_PROJECT).expect("starter");
+        let json = encode_project(&starter, ProjectFormat::Json);
+        assert_eq!(
+            decode_project(&json, ProjectFormat::Json).expect("json"),
+            starter
+        );
+    }
+
+    #[test]
+    fn binary_container_compresses_text_blocks() {
+        let mut starter = presets_rs::load_project_from_text(STARTER_PROJECT).expect("starter");
+        starter.kits[0].name = "1234".to_string();
+        let text = presets_rs::save_project_to_text(&starter);
+        let bytes = encode_project(&starter, ProjectFormat::Binary);
+        assert_eq!(&bytes[0..4], b"FFPB");
+        assert_eq!(u16::from_le_bytes([bytes[4], bytes[5]]), 3);
+        assert_eq!(
+            u32::from_le_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]),
+            3
+        );
+        assert!(bytes.len() * 3 < text.len() * 2);
+        assert_eq!(
+            decode_project(&bytes, ProjectFormat::Binary).expect("binary"),
+            starter
+        );
+
+        let truncated = decode_project(&bytes[..bytes.len() - 1], ProjectFormat::Binary);
+        assert_eq!(truncated, Err("truncated ffpb payload".to_string()));
+        let mut old = bytes.clone();
+        old[4] = 1;
+        assert!(decode_project(&old, ProjectFormat::Binary).is_err());
+        let mut padded = bytes;
+        padded.push(0);
+        assert!(decode_project(&padded, ProjectFormat::Binary).is_err());
+    }
+
+    #[test]
+    fn diff_reports_changed_steps_and_samples() {
+        let left = sample_project();
+        let mut right = sample_project();
+        right.kits[0].tracks[0].sample_id = "snare-02.wav".to_string();
+        right.patterns[0].set_step(1, 4, PatternStep::default());
+
+        let differences = diff_projects(&left, &right);
+        assert_eq!(differences.len(), 2);
+        assert!(differences[0].starts_with("kit[0].track[1].sample"));
+        assert!(differences[1].starts_with("pattern[0].step[1][4]"));
+        assert!(diff_projects(&left, &left).is_empty());
+    }
+
+    #[test]
+    fn midi_export_writes_note_pairs() {
+        let midi = export_midi(&sample_project(), 2).expect("midi export");
+        assert_eq!(&midi[0..4], b"MThd");
+        let note_ons = midi
+            .windows(3)
+            .filter(|window| window[0] == 0x99 && window[1] == 37)
+            .count();
+        assert_eq!(note_ons, 2);
+        let external = midi
+            .windows(3)
+            .filter(|window| window[0] == 0x93 && window[1] == 64)
+            .count();
+        assert_eq!(external, 2);
+    }
+
+    #[test]
+    fn event_log_lists_recall_updates_before_triggers() {
+        let trigger = control_rs::StepTriggerEvent {
+            track_index: 1,
+            step_index: 4,
+            velocity: 110,
+            gate: control_rs::MAX_STEP_GATE,
+            choke_group: None,
+            timeline_sample: 12_000,
+            block_offset: 0,
+            source: control_rs::FfEventSource::Sequencer,
+        };
+        let log = session_event_log(&sample_project(), DEFAULT_SAMPLE_RATE_HZ, &[trigger])
+            .expect("event log");
+        let jsonl = log.to_jsonl();
+        let lines: Vec<&str> = jsonl.lines().collect();
+        assert_eq!(lines.len(), log.len());
+        assert!(lines[0].starts_with("{\"kind\":\"parameter\",\"timeline_sample\":0,"));
+        assert_eq!(
+            lines.last().copied(),
+            Some("{\"kind\":\"trigger\",\"timeline_sample\":12000,\"block_offset\":0,\"source\":1,\"track\":1,\"step\":4,\"velocity\":110,\"choke_group\":null}")
+        );
+    }
+}
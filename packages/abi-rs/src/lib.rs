@@ -1,8 +1,12 @@
 pub const FF_ABI_VERSION_MAJOR: u32 = 1;
-pub const FF_ABI_VERSION_MINOR: u32 = 0;
+pub const FF_ABI_VERSION_MINOR: u32 = 7;
 
+pub const FF_PARAM_GLOBAL_BASE: u32 = 0x0100;
 pub const FF_PARAM_TRACK_BASE: u32 = 0x1000;
 pub const FF_PARAM_TRACK_STRIDE: u32 = 0x10;
+pub const FF_PARAM_GROUP_BASE: u32 = 0x2000;
+pub const FF_PARAM_GROUP_STRIDE: u32 = 0x10;
+pub const FF_TRACK_GROUP_COUNT: u8 = 4;
 
 pub const FF_PARAM_SLOT_GAIN: u32 = 1;
 pub const FF_PARAM_SLOT_PAN: u32 = 2;
@@ -10,6 +14,30 @@ pub const FF_PARAM_SLOT_FILTER_CUTOFF: u32 = 3;
 pub const FF_PARAM_SLOT_ENVELOPE_DECAY: u32 = 4;
 pub const FF_PARAM_SLOT_PITCH: u32 = 5;
 pub const FF_PARAM_SLOT_CHOKE_GROUP: u32 = 6;
+pub const FF_PARAM_SLOT_DUCK_SOURCE: u32 = 7;
+pub const FF_PARAM_SLOT_DUCK_DEPTH: u32 = 8;
+pub const FF_PARAM_SLOT_DUCK_ATTACK: u32 = 9;
+pub const FF_PARAM_SLOT_DUCK_RELEASE: u32 = 10;
+pub const FF_PARAM_SLOT_TRACK_GROUP: u32 = 11;
+pub const FF_PARAM_SLOT_SAMPLE_START: u32 = 12;
+pub const FF_PARAM_SLOT_SAMPLE_END: u32 = 13;
+pub const FF_PARAM_SLOT_REVERSE: u32 = 14;
+pub const FF_PARAM_SLOT_CHOKE_TARGETS: u32 = 15;
+
+pub const FF_PARAM_GROUP_SLOT_GAIN: u32 = 1;
+pub const FF_PARAM_GROUP_SLOT_MUTE: u32 = 2;
+pub const FF_PARAM_GROUP_SLOT_SOLO: u32 = 3;
+pub const FF_PARAM_GROUP_SLOT_CHOKE: u32 = 4;
+
+pub const FF_PARAM_GLOBAL_SWING: u32 = FF_PARAM_GLOBAL_BASE + 1;
+pub const FF_PARAM_GLOBAL_MASTER_COMPRESSOR: u32 = FF_PARAM_GLOBAL_BASE + 2;
+pub const FF_PARAM_GLOBAL_MASTER_FILTER: u32 = FF_PARAM_GLOBAL_BASE + 3;
+pub const FF_PARAM_GLOBAL_LIMITER_CEILING: u32 = FF_PARAM_GLOBAL_BASE + 4;
+
+pub const FF_GAIN_DB_MIN: f32 = -57.5;
+pub const FF_GAIN_DB_MAX: f32 = 6.0;
+pub const FF_SWING_MAX: f32 = 0.45;
+pub const FF_LIMITER_CEILING_DB_MIN: f32 = -12.0;
 
 pub const FF_EVENT_TYPE_NOTE_ON: u32 = 1;
 pub const FF_EVENT_TYPE_NOTE_OFF: u32 = 2;
@@ -17,18 +45,300 @@ pub const FF_EVENT_TYPE_TRIGGER: u32 = 3;
 pub const FF_EVENT_TYPE_TRANSPORT_START: u32 = 4;
 pub const FF_EVENT_TYPE_TRANSPORT_STOP: u32 = 5;
 
+pub const FF_EVENT_PRIORITY_PARAMETER: u32 = 0;
+pub const FF_EVENT_PRIORITY_TRANSPORT: u32 = 1;
+pub const FF_EVENT_PRIORITY_NOTE_OFF: u32 = 2;
+pub const FF_EVENT_PRIORITY_NOTE_ON: u32 = 3;
+
+pub const FF_EVENT_SOURCE_UNKNOWN: u16 = 0;
+pub const FF_EVENT_SOURCE_SEQUENCER: u16 = 1;
+pub const FF_EVENT_SOURCE_LIVE_PAD: u16 = 2;
+pub const FF_EVENT_SOURCE_NOTE_REPEAT: u16 = 3;
+pub const FF_EVENT_SOURCE_SCRIPT: u16 = 4;
+pub const FF_EVENT_SOURCE_AUTOMATION: u16 = 5;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum FfEventSource {
+    #[default]
+    Unknown,
+    Sequencer,
+    LivePad,
+    NoteRepeat,
+    Script,
+    Automation,
+}
+
+impl FfEventSource {
+    pub const ALL: [FfEventSource; 6] = [
+        FfEventSource::Unknown,
+        FfEventSource::Sequencer,
+        FfEventSource::LivePad,
+        FfEventSource::NoteRepeat,
+        FfEventSource::Script,
+        FfEventSource::Automation,
+    ];
+
+    pub fn id(self) -> u16 {
+        match self {
+            FfEventSource::Unknown => FF_EVENT_SOURCE_UNKNOWN,
+            FfEventSource::Sequencer => FF_EVENT_SOURCE_SEQUENCER,
+            FfEventSource::LivePad => FF_EVENT_SOURCE_LIVE_PAD,
+            FfEventSource::NoteRepeat => FF_EVENT_SOURCE_NOTE_REPEAT,
+            FfEventSource::Script => FF_EVENT_SOURCE_SCRIPT,
+            FfEventSource::Automation => FF_EVENT_SOURCE_AUTOMATION,
+        }
+    }
+
+    pub fn from_id(id: u16) -> Option<FfEventSource> {
+        Self::ALL.into_iter().find(|source| source.id() == id)
+    }
+}
+
 pub fn ff_track_parameter_id(track_index: u8, parameter_slot: u32) -> Option<u32> {
     if usize::from(track_index) >= 8 {
         return None;
     }
 
-    if !(FF_PARAM_SLOT_GAIN..=FF_PARAM_SLOT_CHOKE_GROUP).contains(&parameter_slot) {
+    if !(FF_PARAM_SLOT_GAIN..=FF_PARAM_SLOT_CHOKE_TARGETS).contains(&parameter_slot) {
         return None;
     }
 
     Some(FF_PARAM_TRACK_BASE + (u32::from(track_index) * FF_PARAM_TRACK_STRIDE) + parameter_slot)
 }
 
+pub fn ff_group_parameter_id(group_index: u8, parameter_slot: u32) -> Option<u32> {
+    if group_index >= FF_TRACK_GROUP_COUNT {
+        return None;
+    }
+
+    if !(FF_PARAM_GROUP_SLOT_GAIN..=FF_PARAM_GROUP_SLOT_CHOKE).contains(&parameter_slot) {
+        return None;
+    }
+
+    Some(FF_PARAM_GROUP_BASE + (u32::from(group_index) * FF_PARAM_GROUP_STRIDE) + parameter_slot)
+}
+
+pub fn ff_gain_db_from_normalized(normalized: f32) -> f32 {
+    if normalized.is_nan() || normalized <= 0.0 {
+        return f32::NEG_INFINITY;
+    }
+    FF_GAIN_DB_MIN + normalized.min(1.0) * (FF_GAIN_DB_MAX - FF_GAIN_DB_MIN)
+}
+
+pub fn ff_gain_normalized_from_db(gain_db: f32) -> f32 {
+    if gain_db.is_nan() || gain_db <= FF_GAIN_DB_MIN {
+        return 0.0;
+    }
+    ((gain_db - FF_GAIN_DB_MIN) / (FF_GAIN_DB_MAX - FF_GAIN_DB_MIN)).min(1.0)
+}
+
+pub fn ff_gain_db_to_linear(gain_db: f32) -> f32 {
+    if gain_db.is_nan() || gain_db <= FF_GAIN_DB_MIN {
+        return 0.0;
+    }
+    10.0f32.powf(gain_db.min(FF_GAIN_DB_MAX) / 20.0)
+}
+
+pub fn ff_gain_linear_to_db(linear: f32) -> f32 {
+    if linear.is_nan() || linear <= 0.0 {
+        return f32::NEG_INFINITY;
+    }
+    20.0 * linear.log10()
+}
+
+pub fn ff_swing_from_normalized(normalized: f32) -> f32 {
+    if normalized.is_nan() {
+        return 0.0;
+    }
+    normalized.clamp(0.0, 1.0) * FF_SWING_MAX
+}
+
+pub fn ff_swing_normalized_from_swing(swing: f32) -> f32 {
+    if swing.is_nan() {
+        return 0.0;
+    }
+    (swing / FF_SWING_MAX).clamp(0.0, 1.0)
+}
+
+pub fn ff_limiter_ceiling_db_from_normalized(normalized: f32) -> f32 {
+    if normalized.is_nan() {
+        return 0.0;
+    }
+    FF_LIMITER_CEILING_DB_MIN * (1.0 - normalized.clamp(0.0, 1.0))
+}
+
+pub fn ff_limiter_ceiling_normalized_from_db(ceiling_db: f32) -> f32 {
+    if ceiling_db.is_nan() {
+        return 1.0;
+    }
+    (1.0 - ceiling_db / FF_LIMITER_CEILING_DB_MIN).clamp(0.0, 1.0)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FfParameterDescriptor {
+    pub parameter_id: u32,
+    pub track_index: u8,
+    pub parameter_slot: u32,
+    pub name: &'static str,
+    pub default_normalized: f32,
+    pub step_count: u32,
+}
+
+pub fn ff_parameter_slot_name(parameter_slot: u32) -> Option<&'static str> {
+    match parameter_slot {
+        FF_PARAM_SLOT_GAIN => Some("gain"),
+        FF_PARAM_SLOT_PAN => Some("pan"),
+        FF_PARAM_SLOT_FILTER_CUTOFF => Some("filter_cutoff"),
+        FF_PARAM_SLOT_ENVELOPE_DECAY => Some("envelope_decay"),
+        FF_PARAM_SLOT_PITCH => Some("pitch"),
+        FF_PARAM_SLOT_CHOKE_GROUP => Some("choke_group"),
+        FF_PARAM_SLOT_DUCK_SOURCE => Some("duck_source"),
+        FF_PARAM_SLOT_DUCK_DEPTH => Some("duck_depth"),
+        FF_PARAM_SLOT_DUCK_ATTACK => Some("duck_attack"),
+        FF_PARAM_SLOT_DUCK_RELEASE => Some("duck_release"),
+        FF_PARAM_SLOT_TRACK_GROUP => Some("group"),
+        FF_PARAM_SLOT_SAMPLE_START => Some("sample_start"),
+        FF_PARAM_SLOT_SAMPLE_END => Some("sample_end"),
+        FF_PARAM_SLOT_REVERSE => Some("reverse"),
+        FF_PARAM_SLOT_CHOKE_TARGETS => Some("choke_targets"),
+        _ => None,
+    }
+}
+
+pub fn ff_group_parameter_slot_name(parameter_slot: u32) -> Option<&'static str> {
+    match parameter_slot {
+        FF_PARAM_GROUP_SLOT_GAIN => Some("gain"),
+        FF_PARAM_GROUP_SLOT_MUTE => Some("mute"),
+        FF_PARAM_GROUP_SLOT_SOLO => Some("solo"),
+        FF_PARAM_GROUP_SLOT_CHOKE => Some("choke"),
+        _ => None,
+    }
+}
+
+pub fn ff_parameter_descriptor(parameter_id: u32) -> Option<FfParameterDescriptor> {
+    if parameter_id < FF_PARAM_TRACK_BASE {
+        return None;
+    }
+
+    let offset = parameter_id - FF_PARAM_TRACK_BASE;
+    let track_index = u8::try_from(offset / FF_PARAM_TRACK_STRIDE).ok()?;
+    let parameter_slot = offset % FF_PARAM_TRACK_STRIDE;
+    ff_track_parameter_id(track_index, parameter_slot)?;
+
+    let (default_normalized, step_count) = match parameter_slot {
+        FF_PARAM_SLOT_PAN | FF_PARAM_SLOT_PITCH => (0.5, 0),
+        FF_PARAM_SLOT_CHOKE_GROUP => (0.0, 16),
+        FF_PARAM_SLOT_DUCK_SOURCE => (0.0, 8),
+        FF_PARAM_SLOT_TRACK_GROUP => (0.0, u32::from(FF_TRACK_GROUP_COUNT)),
+        FF_PARAM_SLOT_DUCK_DEPTH | FF_PARAM_SLOT_SAMPLE_START => (0.0, 0),
+        FF_PARAM_SLOT_REVERSE => (0.0, 1),
+        FF_PARAM_SLOT_CHOKE_TARGETS => (0.0, 255),
+        FF_PARAM_SLOT_DUCK_ATTACK => (0.05, 0),
+        FF_PARAM_SLOT_DUCK_RELEASE => (0.2, 0),
+        FF_PARAM_SLOT_GAIN => (ff_gain_normalized_from_db(0.0), 0),
+        _ => (1.0, 0),
+    };
+
+    Some(FfParameterDescriptor {
+        parameter_id,
+        track_index,
+        parameter_slot,
+        name: ff_parameter_slot_name(parameter_slot)?,
+        default_normalized,
+        step_count,
+    })
+}
+
+pub fn ff_parameter_registry() -> Vec<FfParameterDescriptor> {
+    let mut registry = Vec::new();
+    for track_index in 0..8u8 {
+        for parameter_slot in FF_PARAM_SLOT_GAIN..=FF_PARAM_SLOT_CHOKE_TARGETS {
+            if let Some(descriptor) =
+                ff_track_parameter_id(track_index, parameter_slot).and_then(ff_parameter_descriptor)
+            {
+                registry.push(descriptor);
+            }
+        }
+    }
+    registry
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FfGroupParameterDescriptor {
+    pub parameter_id: u32,
+    pub group_index: u8,
+    pub parameter_slot: u32,
+    pub name: &'static str,
+    pub default_normalized: f32,
+    pub step_count: u32,
+}
+
+pub fn ff_group_parameter_descriptor(parameter_id: u32) -> Option<FfGroupParameterDescriptor> {
+    if parameter_id < FF_PARAM_GROUP_BASE {
+        return None;
+    }
+
+    let offset = parameter_id - FF_PARAM_GROUP_BASE;
+    let group_index = u8::try_from(offset / FF_PARAM_GROUP_STRIDE).ok()?;
+    let parameter_slot = offset % FF_PARAM_GROUP_STRIDE;
+    ff_group_parameter_id(group_index, parameter_slot)?;
+
+    let (default_normalized, step_count) = match parameter_slot {
+        FF_PARAM_GROUP_SLOT_GAIN => (ff_gain_normalized_from_db(0.0), 0),
+        _ => (0.0, 1),
+    };
+
+    Some(FfGroupParameterDescriptor {
+        parameter_id,
+        group_index,
+        parameter_slot,
+        name: ff_group_parameter_slot_name(parameter_slot)?,
+        default_normalized,
+        step_count,
+    })
+}
+
+pub fn ff_group_parameter_registry() -> Vec<FfGroupParameterDescriptor> {
+    (0..FF_TRACK_GROUP_COUNT)
+        .flat_map(|group_index| {
+            (FF_PARAM_GROUP_SLOT_GAIN..=FF_PARAM_GROUP_SLOT_CHOKE)
+                .filter_map(move |slot| ff_group_parameter_id(group_index, slot))
+        })
+        .filter_map(ff_group_parameter_descriptor)
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FfGlobalParameterDescriptor {
+    pub parameter_id: u32,
+    pub name: &'static str,
+    pub default_normalized: f32,
+    pub step_count: u32,
+}
+
+pub fn ff_global_parameter_descriptor(parameter_id: u32) -> Option<FfGlobalParameterDescriptor> {
+    let (name, default_normalized) = match parameter_id {
+        FF_PARAM_GLOBAL_SWING => ("swing", 0.0),
+        FF_PARAM_GLOBAL_MASTER_COMPRESSOR => ("master_compressor", 0.0),
+        FF_PARAM_GLOBAL_MASTER_FILTER => ("master_filter", 1.0),
+        FF_PARAM_GLOBAL_LIMITER_CEILING => ("limiter_ceiling", 1.0),
+        _ => return None,
+    };
+
+    Some(FfGlobalParameterDescriptor {
+        parameter_id,
+        name,
+        default_normalized,
+        step_count: 0,
+    })
+}
+
+pub fn ff_global_parameter_registry() -> Vec<FfGlobalParameterDescriptor> {
+    (FF_PARAM_GLOBAL_SWING..=FF_PARAM_GLOBAL_LIMITER_CEILING)
+        .filter_map(ff_global_parameter_descriptor)
+        .collect()
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct FfNoteEvent {
@@ -80,6 +390,51 @@ pub struct FfEvent {
     pub payload: FfEventPayload,
 }
 
+impl FfEvent {
+    pub fn track_index(&self) -> Option<u8> {
+        // Every payload variant is plain data, so reading the active one is sound.
+        match self.event_type {
+            FF_EVENT_TYPE_NOTE_ON | FF_EVENT_TYPE_NOTE_OFF => {
+                Some(unsafe { self.payload.note.track_index })
+            }
+            FF_EVENT_TYPE_TRIGGER => Some(unsafe { self.payload.trigger.track_index }),
+            _ => None,
+        }
+    }
+
+    // Ids outside the known set come from newer producers; filters should
+    // treat them like Unknown rather than drop the event.
+    pub fn source(&self) -> Option<FfEventSource> {
+        FfEventSource::from_id(self.source_id)
+    }
+
+    pub fn priority(&self) -> u32 {
+        ff_event_priority(self.event_type)
+    }
+
+    pub fn order_key(&self) -> (u32, u32, u8) {
+        (
+            self.block_offset,
+            self.priority(),
+            self.track_index().unwrap_or(0),
+        )
+    }
+}
+
+// Events sharing a block offset apply parameter updates first, then transport
+// changes, then chokes/note-offs, then note-ons and triggers, each in track order.
+pub fn ff_event_priority(event_type: u32) -> u32 {
+    match event_type {
+        FF_EVENT_TYPE_TRANSPORT_START | FF_EVENT_TYPE_TRANSPORT_STOP => FF_EVENT_PRIORITY_TRANSPORT,
+        FF_EVENT_TYPE_NOTE_OFF => FF_EVENT_PRIORITY_NOTE_OFF,
+        _ => FF_EVENT_PRIORITY_NOTE_ON,
+    }
+}
+
+pub fn ff_sort_events(events: &mut [FfEvent]) {
+    events.sort_by_key(FfEvent::order_key);
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct FfParameterUpdate {
@@ -89,11 +444,43 @@ pub struct FfParameterUpdate {
     pub reserved: u32,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FfLatencyReport {
+    pub total_samples: u32,
+    pub track_lookahead_samples: u32,
+    pub master_lookahead_samples: u32,
+    pub reserved: u32,
+}
+
+impl FfLatencyReport {
+    pub fn new(track_lookahead_samples: u32, master_lookahead_samples: u32) -> Self {
+        Self {
+            total_samples: track_lookahead_samples.saturating_add(master_lookahead_samples),
+            track_lookahead_samples,
+            master_lookahead_samples,
+            reserved: 0,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
-        ff_track_parameter_id, FfEvent, FfEventPayload, FfNoteEvent, FfParameterUpdate,
-        FfTriggerEvent, FF_PARAM_SLOT_CHOKE_GROUP, FF_PARAM_SLOT_GAIN,
+        ff_gain_db_from_normalized, ff_gain_db_to_linear, ff_gain_linear_to_db,
+        ff_gain_normalized_from_db, ff_global_parameter_descriptor, ff_global_parameter_registry,
+        ff_group_parameter_descriptor, ff_group_parameter_id, ff_group_parameter_registry,
+        ff_limiter_ceiling_db_from_normalized, ff_limiter_ceiling_normalized_from_db,
+        ff_parameter_descriptor, ff_parameter_registry, ff_sort_events, ff_swing_from_normalized,
+        ff_swing_normalized_from_swing, ff_track_parameter_id, FfEvent, FfEventPayload,
+        FfEventSource, FfLatencyReport, FfNoteEvent, FfParameterUpdate, FfTriggerEvent,
+        FF_EVENT_SOURCE_NOTE_REPEAT, FF_EVENT_TYPE_NOTE_OFF, FF_EVENT_TYPE_NOTE_ON,
+        FF_EVENT_TYPE_TRANSPORT_START, FF_EVENT_TYPE_TRIGGER, FF_GAIN_DB_MAX, FF_GAIN_DB_MIN,
+        FF_LIMITER_CEILING_DB_MIN, FF_PARAM_GLOBAL_LIMITER_CEILING,
+        FF_PARAM_GLOBAL_MASTER_COMPRESSOR, FF_PARAM_GLOBAL_SWING, FF_PARAM_GROUP_SLOT_CHOKE,
+        FF_PARAM_GROUP_SLOT_GAIN, FF_PARAM_GROUP_SLOT_SOLO, FF_PARAM_SLOT_CHOKE_GROUP,
+        FF_PARAM_SLOT_CHOKE_TARGETS, FF_PARAM_SLOT_DUCK_SOURCE, FF_PARAM_SLOT_GAIN,
+        FF_PARAM_SLOT_PAN, FF_PARAM_SLOT_REVERSE, FF_PARAM_SLOT_TRACK_GROUP, FF_SWING_MAX,
     };
     use std::mem::{align_of, offset_of, size_of};
 
@@ -135,6 +522,76 @@ mod tests {
         assert_eq!(offset_of!(FfEvent, payload), 20);
     }
 
+    #[test]
+    fn event_sources_roundtrip_through_their_ids() {
+        for source in FfEventSource::ALL {
+            assert_eq!(FfEventSource::from_id(source.id()), Some(source));
+        }
+        assert_eq!(FfEventSource::from_id(99), None);
+        let event = FfEvent {
+            source_id: FF_EVENT_SOURCE_NOTE_REPEAT,
+            ..FfEvent::default()
+        };
+        assert_eq!(event.source(), Some(FfEventSource::NoteRepeat));
+        assert_eq!(FfEvent::default().source(), Some(FfEventSource::Unknown));
+    }
+
+    #[test]
+    fn events_sharing_an_offset_sort_transport_then_note_off_then_note_on_by_track() {
+        let note = |block_offset: u32, event_type: u32, track_index: u8| FfEvent {
+            block_offset,
+            event_type,
+            payload: FfEventPayload {
+                note: FfNoteEvent {
+                    track_index,
+                    velocity: 1.0,
+                    ..FfNoteEvent::default()
+                },
+            },
+            ..FfEvent::default()
+        };
+        let trigger = |block_offset: u32, track_index: u8, step_index: u8| FfEvent {
+            block_offset,
+            event_type: FF_EVENT_TYPE_TRIGGER,
+            payload: FfEventPayload {
+                trigger: FfTriggerEvent {
+                    track_index,
+                    step_index,
+                    ..FfTriggerEvent::default()
+                },
+            },
+            ..FfEvent::default()
+        };
+        let mut events = vec![
+            trigger(64, 5, 1),
+            note(64, FF_EVENT_TYPE_NOTE_ON, 2),
+            trigger(64, 2, 2),
+            note(64, FF_EVENT_TYPE_NOTE_OFF, 7),
+            FfEvent {
+                block_offset: 64,
+                event_type: FF_EVENT_TYPE_TRANSPORT_START,
+                ..FfEvent::default()
+            },
+            note(0, FF_EVENT_TYPE_NOTE_ON, 3),
+        ];
+        ff_sort_events(&mut events);
+        let order: Vec<(u32, u32, Option<u8>)> = events
+            .iter()
+            .map(|event| (event.block_offset, event.event_type, event.track_index()))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                (0, FF_EVENT_TYPE_NOTE_ON, Some(3)),
+                (64, FF_EVENT_TYPE_TRANSPORT_START, None),
+                (64, FF_EVENT_TYPE_NOTE_OFF, Some(7)),
+                (64, FF_EVENT_TYPE_NOTE_ON, Some(2)),
+                (64, FF_EVENT_TYPE_TRIGGER, Some(2)),
+                (64, FF_EVENT_TYPE_TRIGGER, Some(5)),
+            ]
+        );
+    }
+
     #[test]
     fn parameter_update_layout_is_stable() {
         assert_eq!(size_of::<FfParameterUpdate>(), 16);
@@ -145,6 +602,17 @@ mod tests {
         assert_eq!(offset_of!(FfParameterUpdate, reserved), 12);
     }
 
+    #[test]
+    fn latency_report_layout_is_stable() {
+        assert_eq!(size_of::<FfLatencyReport>(), 16);
+        assert_eq!(align_of::<FfLatencyReport>(), 4);
+        assert_eq!(offset_of!(FfLatencyReport, total_samples), 0);
+        assert_eq!(offset_of!(FfLatencyReport, track_lookahead_samples), 4);
+        assert_eq!(offset_of!(FfLatencyReport, master_lookahead_samples), 8);
+        assert_eq!(offset_of!(FfLatencyReport, reserved), 12);
+        assert_eq!(FfLatencyReport::new(64, 32).total_samples, 96);
+    }
+
     #[test]
     fn track_parameter_id_is_stable() {
         assert_eq!(ff_track_parameter_id(0, FF_PARAM_SLOT_GAIN), Some(0x1001));
@@ -154,4 +622,121 @@ mod tests {
         );
         assert_eq!(ff_track_parameter_id(8, FF_PARAM_SLOT_GAIN), None);
     }
+
+    #[test]
+    fn parameter_registry_covers_every_track_slot() {
+        let registry = ff_parameter_registry();
+        assert_eq!(registry.len(), 120);
+        assert!(registry.iter().all(
+            |descriptor| ff_parameter_descriptor(descriptor.parameter_id) == Some(*descriptor)
+        ));
+
+        let pan = ff_parameter_descriptor(0x1032).expect("track 3 pan should exist");
+        assert_eq!(pan.track_index, 3);
+        assert_eq!(pan.parameter_slot, FF_PARAM_SLOT_PAN);
+        assert_eq!(pan.name, "pan");
+        let duck = ff_parameter_descriptor(0x1017).expect("track 1 duck source should exist");
+        assert_eq!(duck.parameter_slot, FF_PARAM_SLOT_DUCK_SOURCE);
+        assert_eq!(duck.step_count, 8);
+        assert_eq!(ff_parameter_descriptor(0x1000), None);
+        let group = ff_parameter_descriptor(0x102B).expect("track 2 group should exist");
+        assert_eq!(group.parameter_slot, FF_PARAM_SLOT_TRACK_GROUP);
+        assert_eq!(group.step_count, 4);
+        let reverse = ff_parameter_descriptor(0x102E).expect("track 2 reverse should exist");
+        assert_eq!(reverse.parameter_slot, FF_PARAM_SLOT_REVERSE);
+        assert_eq!(reverse.step_count, 1);
+        assert_eq!(
+            ff_parameter_descriptor(0x102D).map(|end| end.default_normalized),
+            Some(1.0)
+        );
+        let targets = ff_parameter_descriptor(0x101F).expect("track 1 choke targets should exist");
+        assert_eq!(targets.parameter_slot, FF_PARAM_SLOT_CHOKE_TARGETS);
+        assert_eq!(targets.step_count, 255);
+        assert_eq!(ff_parameter_descriptor(0x1080), None);
+    }
+
+    #[test]
+    fn group_parameters_live_in_their_own_id_range() {
+        assert_eq!(
+            ff_group_parameter_id(0, FF_PARAM_GROUP_SLOT_GAIN),
+            Some(0x2001)
+        );
+        assert_eq!(
+            ff_group_parameter_id(3, FF_PARAM_GROUP_SLOT_CHOKE),
+            Some(0x2034)
+        );
+        assert_eq!(ff_group_parameter_id(4, FF_PARAM_GROUP_SLOT_GAIN), None);
+        assert_eq!(ff_group_parameter_id(0, 5), None);
+
+        let registry = ff_group_parameter_registry();
+        assert_eq!(registry.len(), 16);
+        assert!(registry.iter().all(|descriptor| {
+            ff_group_parameter_descriptor(descriptor.parameter_id) == Some(*descriptor)
+                && ff_parameter_descriptor(descriptor.parameter_id).is_none()
+        }));
+        let solo = ff_group_parameter_descriptor(0x2013).expect("group 1 solo should exist");
+        assert_eq!(solo.group_index, 1);
+        assert_eq!(solo.parameter_slot, FF_PARAM_GROUP_SLOT_SOLO);
+        assert_eq!(solo.step_count, 1);
+        assert_eq!(ff_group_parameter_descriptor(0x1001), None);
+    }
+
+    #[test]
+    fn master_bus_parameters_follow_swing_in_the_global_range() {
+        let compressor =
+            ff_global_parameter_descriptor(FF_PARAM_GLOBAL_MASTER_COMPRESSOR).expect("compressor");
+        assert_eq!(compressor.parameter_id, 0x0102);
+        assert_eq!(compressor.default_normalized, 0.0);
+        let ceiling =
+            ff_global_parameter_descriptor(FF_PARAM_GLOBAL_LIMITER_CEILING).expect("ceiling");
+        assert_eq!(ceiling.name, "limiter_ceiling");
+        assert_eq!(ceiling.default_normalized, 1.0);
+        assert_eq!(ff_global_parameter_descriptor(0x0105), None);
+
+        assert_eq!(ff_limiter_ceiling_db_from_normalized(1.0), 0.0);
+        assert_eq!(
+            ff_limiter_ceiling_db_from_normalized(0.0),
+            FF_LIMITER_CEILING_DB_MIN
+        );
+        assert_eq!(ff_limiter_ceiling_db_from_normalized(f32::NAN), 0.0);
+        assert_eq!(ff_limiter_ceiling_normalized_from_db(-3.0), 0.75);
+        assert_eq!(ff_limiter_ceiling_normalized_from_db(-20.0), 0.0);
+    }
+
+    #[test]
+    fn swing_is_a_global_parameter_outside_the_track_and_group_ranges() {
+        let registry = ff_global_parameter_registry();
+        assert_eq!(registry.len(), 4);
+        let swing = ff_global_parameter_descriptor(FF_PARAM_GLOBAL_SWING).expect("swing");
+        assert_eq!(swing.parameter_id, 0x0101);
+        assert_eq!(swing.name, "swing");
+        assert_eq!(ff_parameter_descriptor(FF_PARAM_GLOBAL_SWING), None);
+        assert_eq!(ff_group_parameter_descriptor(FF_PARAM_GLOBAL_SWING), None);
+        assert_eq!(ff_global_parameter_descriptor(0x1001), None);
+
+        assert_eq!(ff_swing_from_normalized(1.0), FF_SWING_MAX);
+        assert_eq!(ff_swing_from_normalized(-1.0), 0.0);
+        assert_eq!(ff_swing_from_normalized(f32::NAN), 0.0);
+        assert_eq!(ff_swing_normalized_from_swing(FF_SWING_MAX / 2.0), 0.5);
+    }
+
+    #[test]
+    fn gain_maps_half_db_steps_with_unity_on_a_midi_value() {
+        assert_eq!(ff_gain_db_from_normalized(0.0), f32::NEG_INFINITY);
+        assert_eq!(ff_gain_db_from_normalized(1.0), FF_GAIN_DB_MAX);
+        assert!(ff_gain_db_from_normalized(115.0 / 127.0).abs() < 1e-4);
+        assert!((ff_gain_db_from_normalized(116.0 / 127.0) - 0.5).abs() < 1e-4);
+        assert!((ff_gain_normalized_from_db(0.0) * 127.0 - 115.0).abs() < 1e-3);
+        assert_eq!(ff_gain_normalized_from_db(FF_GAIN_DB_MIN), 0.0);
+        assert_eq!(ff_gain_normalized_from_db(24.0), 1.0);
+
+        assert_eq!(ff_gain_db_to_linear(0.0), 1.0);
+        assert_eq!(ff_gain_db_to_linear(f32::NEG_INFINITY), 0.0);
+        assert!((ff_gain_db_to_linear(-6.0) - 0.501).abs() < 1e-3);
+        assert!(ff_gain_linear_to_db(1.0).abs() < 1e-6);
+        assert_eq!(ff_gain_linear_to_db(0.0), f32::NEG_INFINITY);
+
+        let gain = ff_parameter_descriptor(0x1001).expect("track 0 gain should exist");
+        assert_eq!(gain.default_normalized, ff_gain_normalized_from_db(0.0));
+    }
 }
@@ -16,12 +16,16 @@ extern "C" {
 
 enum {
   FF_ABI_VERSION_MAJOR = 1,
-  FF_ABI_VERSION_MINOR = 0,
+  FF_ABI_VERSION_MINOR = 7,
 };
 
 enum {
+  FF_PARAM_GLOBAL_BASE = 0x0100,
   FF_PARAM_TRACK_BASE = 0x1000,
   FF_PARAM_TRACK_STRIDE = 0x10,
+  FF_PARAM_GROUP_BASE = 0x2000,
+  FF_PARAM_GROUP_STRIDE = 0x10,
+  FF_TRACK_GROUP_COUNT = 4,
 };
 
 enum {
@@ -31,8 +35,36 @@ enum {
   FF_PARAM_SLOT_ENVELOPE_DECAY = 4,
   FF_PARAM_SLOT_PITCH = 5,
   FF_PARAM_SLOT_CHOKE_GROUP = 6,
+  FF_PARAM_SLOT_DUCK_SOURCE = 7,
+  FF_PARAM_SLOT_DUCK_DEPTH = 8,
+  FF_PARAM_SLOT_DUCK_ATTACK = 9,
+  FF_PARAM_SLOT_DUCK_RELEASE = 10,
+  FF_PARAM_SLOT_TRACK_GROUP = 11,
+  FF_PARAM_SLOT_SAMPLE_START = 12,
+  FF_PARAM_SLOT_SAMPLE_END = 13,
+  FF_PARAM_SLOT_REVERSE = 14,
+  FF_PARAM_SLOT_CHOKE_TARGETS = 15,
 };
 
+enum {
+  FF_PARAM_GROUP_SLOT_GAIN = 1,
+  FF_PARAM_GROUP_SLOT_MUTE = 2,
+  FF_PARAM_GROUP_SLOT_SOLO = 3,
+  FF_PARAM_GROUP_SLOT_CHOKE = 4,
+};
+
+enum {
+  FF_PARAM_GLOBAL_SWING = FF_PARAM_GLOBAL_BASE + 1,
+  FF_PARAM_GLOBAL_MASTER_COMPRESSOR = FF_PARAM_GLOBAL_BASE + 2,
+  FF_PARAM_GLOBAL_MASTER_FILTER = FF_PARAM_GLOBAL_BASE + 3,
+  FF_PARAM_GLOBAL_LIMITER_CEILING = FF_PARAM_GLOBAL_BASE + 4,
+};
+
+#define FF_GAIN_DB_MIN (-57.5F)
+#define FF_GAIN_DB_MAX (6.0F)
+#define FF_SWING_MAX (0.45F)
+#define FF_LIMITER_CEILING_DB_MIN (-12.0F)
+
 enum {
   FF_EVENT_TYPE_NOTE_ON = 1,
   FF_EVENT_TYPE_NOTE_OFF = 2,
@@ -41,6 +73,27 @@ enum {
   FF_EVENT_TYPE_TRANSPORT_STOP = 5,
 };
 
+/* Events sharing a block offset apply in ascending priority, then ascending track index,
+ * keeping insertion order otherwise: parameter updates, transport, chokes/note-offs, then
+ * note-ons and triggers. */
+enum {
+  FF_EVENT_PRIORITY_PARAMETER = 0,
+  FF_EVENT_PRIORITY_TRANSPORT = 1,
+  FF_EVENT_PRIORITY_NOTE_OFF = 2,
+  FF_EVENT_PRIORITY_NOTE_ON = 3,
+};
+
+/* ff_event_t.source_id names the producer so hosts can filter or monitor per source.
+ * Unknown ids come from newer producers and should be treated like UNKNOWN. */
+enum {
+  FF_EVENT_SOURCE_UNKNOWN = 0,
+  FF_EVENT_SOURCE_SEQUENCER = 1,
+  FF_EVENT_SOURCE_LIVE_PAD = 2,
+  FF_EVENT_SOURCE_NOTE_REPEAT = 3,
+  FF_EVENT_SOURCE_SCRIPT = 4,
+  FF_EVENT_SOURCE_AUTOMATION = 5,
+};
+
 typedef struct ff_note_event_t {
   uint8_t track_index;
   uint8_t note;
@@ -81,6 +134,13 @@ typedef struct ff_parameter_update_t {
   uint32_t reserved;
 } ff_parameter_update_t;
 
+typedef struct ff_latency_report_t {
+  uint32_t total_samples;
+  uint32_t track_lookahead_samples;
+  uint32_t master_lookahead_samples;
+  uint32_t reserved;
+} ff_latency_report_t;
+
 FF_STATIC_ASSERT(sizeof(ff_note_event_t) == 8, "ff_note_event_t size must be 8 bytes");
 FF_STATIC_ASSERT(offsetof(ff_note_event_t, velocity) == 4,
                  "ff_note_event_t velocity offset must be 4 bytes");
@@ -116,6 +176,13 @@ FF_STATIC_ASSERT(offsetof(ff_parameter_update_t, ramp_samples) == 8,
 FF_STATIC_ASSERT(offsetof(ff_parameter_update_t, reserved) == 12,
                  "ff_parameter_update_t reserved offset must be 12 bytes");
 
+FF_STATIC_ASSERT(sizeof(ff_latency_report_t) == 16,
+                 "ff_latency_report_t size must be 16 bytes");
+FF_STATIC_ASSERT(offsetof(ff_latency_report_t, track_lookahead_samples) == 4,
+                 "ff_latency_report_t track_lookahead_samples offset must be 4 bytes");
+FF_STATIC_ASSERT(offsetof(ff_latency_report_t, master_lookahead_samples) == 8,
+                 "ff_latency_report_t master_lookahead_samples offset must be 8 bytes");
+
 #ifdef __cplusplus
 }  // extern "C"
 #endif
@@ -0,0 +1,560 @@
+use abi_rs::{FfLatencyReport, FfParameterDescriptor, FfParameterUpdate};
+use control_rs::{RecallState, StepTriggerEvent, STEPS_PER_BEAT, STEPS_PER_PATTERN, TRACK_COUNT};
+use presets_rs::Project;
+
+pub mod plugin;
+
+pub const CLAP_PARAM_IS_STEPPED: u32 = 1 << 0;
+pub const CLAP_PARAM_IS_AUTOMATABLE: u32 = 1 << 5;
+pub const CLAP_PARAM_IS_MODULATABLE: u32 = 1 << 10;
+
+pub const CLAP_TRANSPORT_HAS_TEMPO: u32 = 1 << 0;
+pub const CLAP_TRANSPORT_HAS_BEATS_TIMELINE: u32 = 1 << 1;
+pub const CLAP_TRANSPORT_IS_PLAYING: u32 = 1 << 4;
+
+pub const CLAP_BEATTIME_FACTOR: i64 = 1 << 31;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClapParamInfo {
+    pub id: u32,
+    pub flags: u32,
+    pub name: String,
+    pub module: String,
+    pub min_value: f64,
+    pub max_value: f64,
+    pub default_value: f64,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ClapAudioPortInfo {
+    pub id: u32,
+    pub name: String,
+    pub channel_count: u32,
+    pub is_main: bool,
+    pub tracks: Vec<usize>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ClapTransport {
+    pub flags: u32,
+    pub song_pos_beats: i64,
+    pub tempo: f64,
+}
+
+impl ClapTransport {
+    pub fn is_playing(&self) -> bool {
+        self.flags & CLAP_TRANSPORT_IS_PLAYING != 0
+    }
+
+    pub fn beats(&self) -> Option<f64> {
+        (self.flags & CLAP_TRANSPORT_HAS_BEATS_TIMELINE != 0)
+            .then(|| self.song_pos_beats as f64 / CLAP_BEATTIME_FACTOR as f64)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClapProcessOutput {
+    pub trigger_events: Vec<StepTriggerEvent>,
+    pub parameter_updates: Vec<FfParameterUpdate>,
+}
+
+#[derive(Debug)]
+pub struct ClapPluginBridge {
+    project: Project,
+    recall: RecallState,
+    sample_rate_hz: u32,
+    registry: Vec<FfParameterDescriptor>,
+    parameter_values: Vec<f32>,
+    pending_updates: Vec<FfParameterUpdate>,
+    latency: FfLatencyReport,
+}
+
+impl ClapPluginBridge {
+    pub fn new(project: Project, sample_rate_hz: u32) -> Result<Self, String> {
+        let recall = control_rs::recall_state_from_project(&project, sample_rate_hz)?;
+        let registry = abi_rs::ff_parameter_registry();
+        let parameter_values = registry
+            .iter()
+            .map(|descriptor| descriptor.default_normalized)
+            .collect();
+
+        let mut bridge = Self {
+            project,
+            recall,
+            sample_rate_hz,
+            registry,
+            parameter_values,
+            pending_updates: Vec::new(),
+            latency: FfLatencyReport::default(),
+        };
+        bridge.queue_recall_updates();
+        Ok(bridge)
+    }
+
+    pub fn project(&self) -> &Project {
+        &self.project
+    }
+
+    pub fn recall(&self) -> &RecallState {
+        &self.recall
+    }
+
+    pub fn sample_rate_hz(&self) -> u32 {
+        self.sample_rate_hz
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate_hz: u32) -> bool {
+        if !self.recall.sequencer_mut().set_sample_rate(sample_rate_hz) {
+            return false;
+        }
+        self.sample_rate_hz = sample_rate_hz;
+        true
+    }
+
+    pub fn audio_output_port_count(&self) -> usize {
+        self.project.output_routing.output_pair_count()
+    }
+
+    pub fn audio_output_port_info(&self, index: usize) -> Option<ClapAudioPortInfo> {
+        if index >= self.audio_output_port_count() {
+            return None;
+        }
+
+        Some(ClapAudioPortInfo {
+            id: index as u32,
+            name: if index == 0 {
+                "Main".to_string()
+            } else {
+                format!("Output {}", index + 1)
+            },
+            channel_count: 2,
+            is_main: index == 0,
+            tracks: self.project.output_routing.tracks_on_output(index),
+        })
+    }
+
+    pub fn latency_samples(&self) -> u32 {
+        self.latency.total_samples
+    }
+
+    pub fn set_engine_latency(&mut self, report: FfLatencyReport) -> bool {
+        let changed = report.total_samples != self.latency.total_samples;
+        self.latency = report;
+        changed
+    }
+
+    pub fn param_count(&self) -> usize {
+        self.registry.len()
+    }
+
+    pub fn param_info(&self, index: usize) -> Option<ClapParamInfo> {
+        let descriptor = self.registry.get(index)?;
+        let mut flags = CLAP_PARAM_IS_AUTOMATABLE;
+        if descriptor.step_count > 0 {
+            flags |= CLAP_PARAM_IS_STEPPED;
+        } else {
+            flags |= CLAP_PARAM_IS_MODULATABLE;
+        }
+
+        Some(ClapParamInfo {
+            id: descriptor.parameter_id,
+            flags,
+            name: format!("Track {} {}", descriptor.track_index + 1, descriptor.name),
+            module: format!("tracks/{}", descriptor.track_index + 1),
+            min_value: 0.0,
+            max_value: 1.0,
+            default_value: f64::from(descriptor.default_normalized),
+        })
+    }
+
+    pub fn param_value(&self, parameter_id: u32) -> Option<f64> {
+        self.registry_index(parameter_id)
+            .map(|index| f64::from(self.parameter_values[index]))
+    }
+
+    pub fn set_param_value(&mut self, parameter_id: u32, value: f64) -> bool {
+        let Some(index) = self.registry_index(parameter_id) else {
+            return false;
+        };
+        if !value.is_finite() {
+            return false;
+        }
+
+        let normalized = value.clamp(0.0, 1.0) as f32;
+        self.parameter_values[index] = normalized;
+        let descriptor = self.registry[index];
+        if descriptor.parameter_slot == abi_rs::FF_PARAM_SLOT_CHOKE_GROUP {
+            self.recall.sequencer_mut().set_track_choke_group(
+                usize::from(descriptor.track_index),
+                choke_group_from_normalized(normalized),
+            );
+        }
+        self.store_track_control(descriptor, normalized);
+
+        self.pending_updates.push(FfParameterUpdate {
+            parameter_id,
+            normalized_value: normalized,
+            ramp_samples: 0,
+            reserved: 0,
+        });
+        true
+    }
+
+    pub fn value_to_text(&self, parameter_id: u32, value: f64) -> Option<String> {
+        let index = self.registry_index(parameter_id)?;
+        let value = value.clamp(0.0, 1.0);
+        let text = match self.registry[index].parameter_slot {
+            abi_rs::FF_PARAM_SLOT_GAIN => {
+                let gain_db = abi_rs::ff_gain_db_from_normalized(value as f32);
+                if gain_db.is_finite() {
+                    format!("{gain_db:.1} dB")
+                } else {
+                    "-inf dB".to_string()
+                }
+            }
+            abi_rs::FF_PARAM_SLOT_PAN => format!("{:.2}", value * 2.0 - 1.0),
+            abi_rs::FF_PARAM_SLOT_PITCH => format!("{:.1} st", value * 48.0 - 24.0),
+            abi_rs::FF_PARAM_SLOT_CHOKE_GROUP => match choke_group_from_normalized(value as f32) {
+                Some(group) => format!("group {group}"),
+                None => "off".to_string(),
+            },
+            abi_rs::FF_PARAM_SLOT_DUCK_SOURCE => {
+                if value <= 0.0001 {
+                    "off".to_string()
+                } else {
+                    format!("track {}", ((value * 8.0).round() as u8).clamp(1, 8))
+                }
+            }
+            abi_rs::FF_PARAM_SLOT_TRACK_GROUP => {
+                let group_count = f64::from(abi_rs::FF_TRACK_GROUP_COUNT);
+                if value <= 0.0001 {
+                    "none".to_string()
+                } else {
+                    format!(
+                        "group {}",
+                        (value * group_count).round().clamp(1.0, group_count)
+                    )
+                }
+            }
+            abi_rs::FF_PARAM_SLOT_DUCK_ATTACK => format!("{:.1} ms", value * 100.0),
+            abi_rs::FF_PARAM_SLOT_DUCK_RELEASE => format!("{:.0} ms", value * 1_000.0),
+            abi_rs::FF_PARAM_SLOT_REVERSE => {
+                if value >= 0.5 {
+                    "reverse".to_string()
+                } else {
+                    "forward".to_string()
+                }
+            }
+            abi_rs::FF_PARAM_SLOT_CHOKE_TARGETS => {
+                let targets = (value * 255.0).round() as u8;
+                let tracks: Vec<String> = (0..TRACK_COUNT)
+                    .filter(|track| targets & (1 << track) != 0)
+                    .map(|track| (track + 1).to_string())
+                    .collect();
+                if tracks.is_empty() {
+                    "none".to_string()
+                } else {
+                    format!("tracks {}", tracks.join(","))
+                }
+            }
+            _ => format!("{:.1}%", value * 100.0),
+        };
+        Some(text)
+    }
+
+    pub fn sync_transport(&mut self, transport: &ClapTransport) {
+        let sequencer = self.recall.sequencer_mut();
+        if transport.flags & CLAP_TRANSPORT_HAS_TEMPO != 0 && transport.tempo.is_finite() {
+            sequencer.set_tempo_bpm(transport.tempo as f32);
+        }
+
+        if let Some(beats) = transport.beats().filter(|value| *value >= 0.0) {
+            let position = sequencer.position();
+            let current_beats = position.bar as f64 * beats_per_bar()
+                + ((position.step_index % STEPS_PER_PATTERN) as f64 + position.step_phase)
+                    / STEPS_PER_BEAT as f64;
+            let step_beats = 1.0 / STEPS_PER_BEAT as f64;
+            if !sequencer.transport().is_playing() || (beats - current_beats).abs() > step_beats {
+                let bar = (beats / beats_per_bar()).floor();
+                let steps = (beats - bar * beats_per_bar()) * STEPS_PER_BEAT as f64;
+                let step_index = (steps.floor() as usize).min(STEPS_PER_PATTERN - 1);
+                let step_phase = (steps - step_index as f64).clamp(0.0, 1.0 - f64::EPSILON);
+                let timeline_sample = position.timeline_sample;
+                let pattern_bar = (bar as u64 % sequencer.pattern().bar_count() as u64) as usize;
+                sequencer.locate(
+                    bar as u64,
+                    pattern_bar * STEPS_PER_PATTERN + step_index,
+                    step_phase,
+                    timeline_sample,
+                );
+            }
+        }
+
+        if transport.is_playing() {
+            sequencer.start();
+        } else {
+            sequencer.stop();
+        }
+    }
+
+    pub fn process(&mut self, frames: u32, transport: &ClapTransport) -> ClapProcessOutput {
+        self.sync_transport(transport);
+        ClapProcessOutput {
+            trigger_events: self.recall.sequencer_mut().process_block(frames),
+            parameter_updates: std::mem::take(&mut self.pending_updates),
+        }
+    }
+
+    pub fn save_state(&self) -> Vec<u8> {
+        presets_rs::save_project_to_text(&self.project).into_bytes()
+    }
+
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let text = std::str::from_utf8(bytes).map_err(|_| "state is not utf8".to_string())?;
+        let project = presets_rs::load_project_from_text(text)?;
+        self.recall = control_rs::recall_state_from_project(&project, self.sample_rate_hz)?;
+        self.project = project;
+        self.queue_recall_updates();
+        Ok(())
+    }
+
+    // Mirrors a track control into the active kit so `save_state` writes
+    // what the host last set; slots without a kit control stay engine-only.
+    fn store_track_control(&mut self, descriptor: FfParameterDescriptor, normalized: f32) {
+        let kit_index = self.project.active_kit.unwrap_or(0);
+        let Some(kit) = self.project.kits.get_mut(kit_index) else {
+            return;
+        };
+        let mut controls = kit
+            .track_controls(descriptor.track_index)
+            .unwrap_or_default();
+        match descriptor.parameter_slot {
+            abi_rs::FF_PARAM_SLOT_GAIN => {
+                controls.gain_db =
+                    abi_rs::ff_gain_db_from_normalized(normalized).max(presets_rs::GAIN_DB_MIN);
+            }
+            abi_rs::FF_PARAM_SLOT_PAN => controls.pan = normalized * 2.0 - 1.0,
+            abi_rs::FF_PARAM_SLOT_FILTER_CUTOFF => controls.filter_cutoff = normalized,
+            abi_rs::FF_PARAM_SLOT_ENVELOPE_DECAY => controls.envelope_decay = normalized,
+            abi_rs::FF_PARAM_SLOT_PITCH => controls.pitch_semitones = normalized * 48.0 - 24.0,
+            abi_rs::FF_PARAM_SLOT_CHOKE_GROUP => {
+                controls.choke_group = choke_group_from_normalized(normalized);
+            }
+            _ => return,
+        }
+        kit.set_track_controls(descriptor.track_index, controls);
+    }
+
+    fn registry_index(&self, parameter_id: u32) -> Option<usize> {
+        self.registry
+            .iter()
+            .position(|descriptor| descriptor.parameter_id == parameter_id)
+    }
+
+    fn queue_recall_updates(&mut self) {
+        let engine_recall = self.recall.to_engine_recall();
+        for update in &engine_recall.parameter_updates {
+            if let Some(index) = self.registry_index(update.parameter_id) {
+                self.parameter_values[index] = update.normalized_value;
+            }
+        }
+        self.pending_updates = engine_recall.parameter_updates;
+    }
+}
+
+fn beats_per_bar() -> f64 {
+    (STEPS_PER_PATTERN / STEPS_PER_BEAT) as f64
+}
+
+fn choke_group_from_normalized(value: f32) -> Option<u8> {
+    let group = (value.clamp(0.0, 1.0) * 16.0).round() as u8;
+    group.checked_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use abi_rs::{
+        ff_gain_normalized_from_db, ff_track_parameter_id, FfLatencyReport,
+        FF_PARAM_SLOT_CHOKE_GROUP, FF_PARAM_SLOT_CHOKE_TARGETS, FF_PARAM_SLOT_DUCK_SOURCE,
+        FF_PARAM_SLOT_GAIN, FF_PARAM_SLOT_PAN, FF_PARAM_SLOT_PITCH,
+    };
+    use presets_rs::{Kit, Pattern, PatternStep, Project, TrackControls};
+
+    use super::{
+        ClapPluginBridge, ClapTransport, CLAP_BEATTIME_FACTOR, CLAP_PARAM_IS_STEPPED,
+        CLAP_TRANSPORT_HAS_BEATS_TIMELINE, CLAP_TRANSPORT_HAS_TEMPO, CLAP_TRANSPORT_IS_PLAYING,
+    };
+
+    fn bridge_project() -> Project {
+        let mut project = Project {
+            name: "clap".to_string(),
+            kits: vec![Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![Pattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        project.kits[0].set_track_controls(
+            2,
+            TrackControls {
+                gain_db: -6.0,
+                ..TrackControls::default()
+            },
+        );
+        project.patterns[0].set_step(
+            0,
+            8,
+            PatternStep {
+                active: true,
+                velocity: 110,
+                ..PatternStep::default()
+            },
+        );
+        project
+    }
+
+    #[test]
+    fn params_are_registered_from_abi_registry() {
+        let mut bridge = ClapPluginBridge::new(bridge_project(), 48_000).expect("bridge");
+        assert_eq!(bridge.param_count(), 120);
+
+        let choke_id = ff_track_parameter_id(1, FF_PARAM_SLOT_CHOKE_GROUP).expect("id");
+        let choke_info = (0..bridge.param_count())
+            .filter_map(|index| bridge.param_info(index))
+            .find(|info| info.id == choke_id)
+            .expect("choke param should be registered");
+        assert_ne!(choke_info.flags & CLAP_PARAM_IS_STEPPED, 0);
+
+        let gain_id = ff_track_parameter_id(2, FF_PARAM_SLOT_GAIN).expect("id");
+        let recalled = bridge.param_value(gain_id).expect("gain value");
+        assert!((recalled - 103.0 / 127.0).abs() < 1e-6);
+        assert_eq!(
+            bridge.value_to_text(gain_id, 0.0).as_deref(),
+            Some("-inf dB")
+        );
+        assert_eq!(
+            bridge
+                .value_to_text(gain_id, f64::from(ff_gain_normalized_from_db(-6.0)))
+                .as_deref(),
+            Some("-6.0 dB")
+        );
+
+        assert!(bridge.set_param_value(choke_id, 0.25));
+        assert_eq!(
+            bridge.value_to_text(choke_id, 0.25).as_deref(),
+            Some("group 3")
+        );
+        let duck_id = ff_track_parameter_id(1, FF_PARAM_SLOT_DUCK_SOURCE).expect("id");
+        assert_eq!(
+            bridge.value_to_text(duck_id, 0.125).as_deref(),
+            Some("track 1")
+        );
+        let targets_id = ff_track_parameter_id(1, FF_PARAM_SLOT_CHOKE_TARGETS).expect("id");
+        assert_eq!(
+            bridge.value_to_text(targets_id, 1.0).as_deref(),
+            Some("tracks 1,2,3,4,5,6,7,8")
+        );
+        let output = bridge.process(64, &ClapTransport::default());
+        assert!(output
+            .parameter_updates
+            .iter()
+            .any(|update| update.parameter_id == choke_id));
+    }
+
+    #[test]
+    fn set_params_survive_save_and_load_state() {
+        let mut bridge = ClapPluginBridge::new(bridge_project(), 48_000).expect("bridge");
+        let gain_id = ff_track_parameter_id(2, FF_PARAM_SLOT_GAIN).expect("id");
+        let pan_id = ff_track_parameter_id(4, FF_PARAM_SLOT_PAN).expect("id");
+        let pitch_id = ff_track_parameter_id(4, FF_PARAM_SLOT_PITCH).expect("id");
+        let choke_id = ff_track_parameter_id(1, FF_PARAM_SLOT_CHOKE_GROUP).expect("id");
+        assert!(bridge.set_param_value(gain_id, 0.5));
+        assert!(bridge.set_param_value(pan_id, 0.25));
+        assert!(bridge.set_param_value(pitch_id, 0.75));
+        assert!(bridge.set_param_value(choke_id, 0.25));
+        let state = bridge.save_state();
+
+        let mut restored = ClapPluginBridge::new(bridge_project(), 48_000).expect("bridge");
+        restored.load_state(&state).expect("load");
+        for (parameter_id, value) in [(gain_id, 0.5), (pan_id, 0.25), (pitch_id, 0.75)] {
+            let recalled = restored.param_value(parameter_id).expect("value");
+            assert!(
+                (recalled - value).abs() <= 0.5 / 127.0,
+                "{parameter_id}: {recalled}"
+            );
+        }
+        assert_eq!(restored.param_value(choke_id), Some(0.25));
+        assert_eq!(
+            restored.project().kits[0]
+                .track_controls(1)
+                .and_then(|controls| controls.choke_group),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn routed_tracks_are_exposed_as_audio_ports() {
+        let mut project = bridge_project();
+        assert_eq!(
+            ClapPluginBridge::new(project.clone(), 48_000)
+                .expect("bridge")
+                .audio_output_port_count(),
+            1
+        );
+
+        project.output_routing.set_track_output(5, 1);
+        let bridge = ClapPluginBridge::new(project, 48_000).expect("bridge");
+        assert_eq!(bridge.audio_output_port_count(), 2);
+        let main = bridge.audio_output_port_info(0).expect("main port");
+        assert!(main.is_main);
+        assert!(!main.tracks.contains(&5));
+        let bus = bridge.audio_output_port_info(1).expect("bus port");
+        assert_eq!(bus.tracks, vec![5]);
+        assert_eq!(bus.channel_count, 2);
+        assert!(bridge.audio_output_port_info(2).is_none());
+    }
+
+    #[test]
+    fn host_transport_drives_sequencer_position() {
+        let mut bridge = ClapPluginBridge::new(bridge_project(), 48_000).expect("bridge");
+        let transport = ClapTransport {
+            flags: CLAP_TRANSPORT_HAS_TEMPO
+                | CLAP_TRANSPORT_HAS_BEATS_TIMELINE
+                | CLAP_TRANSPORT_IS_PLAYING,
+            song_pos_beats: 2 * CLAP_BEATTIME_FACTOR,
+            tempo: 128.0,
+        };
+
+        let output = bridge.process(128, &transport);
+        assert_eq!(bridge.recall().sequencer().transport().bpm(), 128.0);
+        assert_eq!(output.trigger_events.len(), 1);
+        assert_eq!(output.trigger_events[0].step_index, 8);
+    }
+
+    #[test]
+    fn state_roundtrips_through_presets_serialization() {
+        let bridge = ClapPluginBridge::new(bridge_project(), 48_000).expect("bridge");
+        let state = bridge.save_state();
+
+        let mut restored = ClapPluginBridge::new(
+            Project {
+                kits: vec![Kit::default()],
+                patterns: vec![Pattern::default()],
+                ..Project::default()
+            },
+            48_000,
+        )
+        .expect("bridge");
+        restored.load_state(&state).expect("state should load");
+        assert_eq!(restored.project(), bridge.project());
+    }
+
+    #[test]
+    fn engine_latency_is_reported_and_flags_host_restarts() {
+        let mut bridge = ClapPluginBridge::new(bridge_project(), 48_000).expect("bridge");
+        assert_eq!(bridge.latency_samples(), 0);
+        assert!(bridge.set_engine_latency(FfLatencyReport::new(64, 32)));
+        assert_eq!(bridge.latency_samples(), 96);
+        assert!(!bridge.set_engine_latency(FfLatencyReport::new(32, 64)));
+    }
+}
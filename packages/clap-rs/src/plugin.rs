@@ -0,0 +1,847 @@
+use std::ffi::{c_char, c_void, CStr};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::Mutex;
+
+use clap_sys::audio_buffer::clap_audio_buffer;
+use clap_sys::entry::clap_plugin_entry;
+use clap_sys::events::{
+    clap_event_header, clap_event_param_value, clap_input_events, clap_output_events,
+    CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_VALUE,
+};
+use clap_sys::ext::audio_ports::{
+    clap_audio_port_info, clap_plugin_audio_ports, CLAP_AUDIO_PORT_IS_MAIN, CLAP_EXT_AUDIO_PORTS,
+    CLAP_PORT_STEREO,
+};
+use clap_sys::ext::params::{clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS};
+use clap_sys::ext::state::{clap_plugin_state, CLAP_EXT_STATE};
+use clap_sys::factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID};
+use clap_sys::host::clap_host;
+use clap_sys::id::CLAP_INVALID_ID;
+use clap_sys::plugin::{clap_plugin, clap_plugin_descriptor};
+use clap_sys::plugin_features::{
+    CLAP_PLUGIN_FEATURE_DRUM_MACHINE, CLAP_PLUGIN_FEATURE_INSTRUMENT, CLAP_PLUGIN_FEATURE_STEREO,
+};
+use clap_sys::process::{
+    clap_process, clap_process_status, CLAP_PROCESS_CONTINUE, CLAP_PROCESS_ERROR,
+};
+use clap_sys::stream::{clap_istream, clap_ostream};
+use clap_sys::version::CLAP_VERSION;
+use presets_rs::{Kit, Pattern, Project};
+use render_rs::OfflineEngine;
+
+use crate::{ClapPluginBridge, ClapProcessOutput, ClapTransport};
+
+pub const PLUGIN_ID: &CStr = c"org.forestfloor.drum-machine";
+
+const DEFAULT_SAMPLE_RATE_HZ: u32 = 48_000;
+const VERSION: &CStr =
+    match CStr::from_bytes_with_nul(concat!(env!("CARGO_PKG_VERSION"), "\0").as_bytes()) {
+        Ok(version) => version,
+        Err(_) => panic!("package version is not a C string"),
+    };
+
+struct Features([*const c_char; 4]);
+
+// The feature list only points at static C strings.
+unsafe impl Sync for Features {}
+
+static FEATURES: Features = Features([
+    CLAP_PLUGIN_FEATURE_INSTRUMENT.as_ptr(),
+    CLAP_PLUGIN_FEATURE_DRUM_MACHINE.as_ptr(),
+    CLAP_PLUGIN_FEATURE_STEREO.as_ptr(),
+    ptr::null(),
+]);
+
+static DESCRIPTOR: clap_plugin_descriptor = clap_plugin_descriptor {
+    clap_version: CLAP_VERSION,
+    id: PLUGIN_ID.as_ptr(),
+    name: c"Forest Floor".as_ptr(),
+    vendor: c"Forest Floor".as_ptr(),
+    url: c"".as_ptr(),
+    manual_url: c"".as_ptr(),
+    support_url: c"".as_ptr(),
+    version: VERSION.as_ptr(),
+    description: c"Step-sequenced sample drum machine".as_ptr(),
+    features: FEATURES.0.as_ptr(),
+};
+
+static FACTORY: clap_plugin_factory = clap_plugin_factory {
+    get_plugin_count: Some(factory_plugin_count),
+    get_plugin_descriptor: Some(factory_plugin_descriptor),
+    create_plugin: Some(factory_create_plugin),
+};
+
+static PARAMS: clap_plugin_params = clap_plugin_params {
+    count: Some(params_count),
+    get_info: Some(params_get_info),
+    get_value: Some(params_get_value),
+    value_to_text: Some(params_value_to_text),
+    text_to_value: Some(params_text_to_value),
+    flush: Some(params_flush),
+};
+
+static STATE: clap_plugin_state = clap_plugin_state {
+    save: Some(state_save),
+    load: Some(state_load),
+};
+
+static AUDIO_PORTS: clap_plugin_audio_ports = clap_plugin_audio_ports {
+    count: Some(audio_ports_count),
+    get: Some(audio_ports_get),
+};
+
+#[no_mangle]
+#[allow(non_upper_case_globals)]
+pub static clap_entry: clap_plugin_entry = clap_plugin_entry {
+    clap_version: CLAP_VERSION,
+    init: Some(entry_init),
+    deinit: Some(entry_deinit),
+    get_factory: Some(entry_get_factory),
+};
+
+// The bridge and engine sit behind one lock: host main-thread calls take it,
+// and the audio thread only tries it, rendering silence for a block that
+// races a state load instead of waiting.
+struct Instance {
+    plugin: clap_plugin,
+    state: Mutex<InstanceState>,
+}
+
+struct InstanceState {
+    bridge: ClapPluginBridge,
+    engine: Option<OfflineEngine>,
+    buses: Vec<Vec<f32>>,
+}
+
+impl InstanceState {
+    fn activate(&mut self, sample_rate_hz: u32, max_frames: u32) -> Result<(), String> {
+        if !self.bridge.set_sample_rate(sample_rate_hz) {
+            return Err(format!("unsupported sample rate: {sample_rate_hz}"));
+        }
+        self.buses = vec![vec![0.0; max_frames as usize]; self.bridge.audio_output_port_count()];
+        self.engine = Some(self.prepare_engine()?);
+        Ok(())
+    }
+
+    // Kit sample IDs are read as file paths; a sample that does not load
+    // leaves its track silent.
+    fn prepare_engine(&self) -> Result<OfflineEngine, String> {
+        let project = self.bridge.project();
+        let mut engine = OfflineEngine::new(self.bridge.sample_rate_hz());
+        render_rs::switch_kit(
+            &mut engine,
+            project,
+            project.active_kit.unwrap_or(0),
+            0,
+            load_mono_sample,
+        )?;
+        engine.set_output_routing(&project.output_routing);
+        Ok(engine)
+    }
+
+    fn render(&mut self, output: &ClapProcessOutput, frames: u32) {
+        let Some(engine) = self.engine.as_mut() else {
+            return;
+        };
+        engine.apply_parameter_updates(&output.parameter_updates);
+        let frames = frames as usize;
+        let mut rendered = 0;
+        let mut process_range = |engine: &mut OfflineEngine, start: usize, end: usize| {
+            let mut segments: Vec<&mut [f32]> = self
+                .buses
+                .iter_mut()
+                .map(|bus| &mut bus[start..end])
+                .collect();
+            engine.process_outputs(&mut segments);
+        };
+        for event in &output.trigger_events {
+            let offset = (event.block_offset as usize).min(frames);
+            if offset > rendered {
+                process_range(engine, rendered, offset);
+                rendered = offset;
+            }
+            engine.trigger_track(
+                usize::from(event.track_index),
+                f32::from(event.velocity) / 127.0,
+            );
+        }
+        process_range(engine, rendered, frames);
+    }
+}
+
+fn default_project() -> Project {
+    Project {
+        name: "Forest Floor".to_string(),
+        kits: vec![Kit::default()],
+        active_kit: Some(0),
+        patterns: vec![Pattern::default()],
+        active_pattern: Some(0),
+        ..Project::default()
+    }
+}
+
+fn load_mono_sample(sample_id: &str) -> Option<Vec<f32>> {
+    let bytes = std::fs::read(sample_id).ok()?;
+    render_rs::wav::read_wav(&bytes)
+        .ok()
+        .map(|wav| wav.to_mono())
+}
+
+// Nothing may unwind into the host, so every callback body runs through here.
+fn guard<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(on_panic)
+}
+
+unsafe fn instance<'a>(plugin: *const clap_plugin) -> Option<&'a Instance> {
+    plugin
+        .as_ref()
+        .and_then(|plugin| (plugin.plugin_data as *const Instance).as_ref())
+}
+
+unsafe fn with_state<T: Copy>(
+    plugin: *const clap_plugin,
+    on_failure: T,
+    body: impl FnOnce(&mut InstanceState) -> T,
+) -> T {
+    guard(on_failure, || match instance(plugin) {
+        Some(instance) => match instance.state.lock() {
+            Ok(mut state) => body(&mut state),
+            Err(_) => on_failure,
+        },
+        None => on_failure,
+    })
+}
+
+unsafe fn write_c_text(text: &str, output: *mut c_char, capacity: usize) -> bool {
+    if output.is_null() || capacity == 0 {
+        return false;
+    }
+    let length = text.len().min(capacity - 1);
+    ptr::copy_nonoverlapping(text.as_ptr().cast::<c_char>(), output, length);
+    *output.add(length) = 0;
+    true
+}
+
+unsafe fn input_param_values(events: *const clap_input_events, state: &mut InstanceState) {
+    let Some(events) = events.as_ref() else {
+        return;
+    };
+    let (Some(size), Some(get)) = (events.size, events.get) else {
+        return;
+    };
+    for index in 0..size(events) {
+        let Some(header) = get(events, index).as_ref() else {
+            continue;
+        };
+        if header.space_id == CLAP_CORE_EVENT_SPACE_ID && header.type_ == CLAP_EVENT_PARAM_VALUE {
+            let event = &*(header as *const clap_event_header).cast::<clap_event_param_value>();
+            state.bridge.set_param_value(event.param_id, event.value);
+        }
+    }
+}
+
+unsafe extern "C" fn entry_init(_plugin_path: *const c_char) -> bool {
+    true
+}
+
+unsafe extern "C" fn entry_deinit() {}
+
+unsafe extern "C" fn entry_get_factory(factory_id: *const c_char) -> *const c_void {
+    if !factory_id.is_null() && CStr::from_ptr(factory_id) == CLAP_PLUGIN_FACTORY_ID {
+        ptr::from_ref(&FACTORY).cast()
+    } else {
+        ptr::null()
+    }
+}
+
+unsafe extern "C" fn factory_plugin_count(_factory: *const clap_plugin_factory) -> u32 {
+    1
+}
+
+unsafe extern "C" fn factory_plugin_descriptor(
+    _factory: *const clap_plugin_factory,
+    index: u32,
+) -> *const clap_plugin_descriptor {
+    if index == 0 {
+        &DESCRIPTOR
+    } else {
+        ptr::null()
+    }
+}
+
+unsafe extern "C" fn factory_create_plugin(
+    _factory: *const clap_plugin_factory,
+    host: *const clap_host,
+    plugin_id: *const c_char,
+) -> *const clap_plugin {
+    if host.is_null() || plugin_id.is_null() || CStr::from_ptr(plugin_id) != PLUGIN_ID {
+        return ptr::null();
+    }
+    let created = guard(None, || {
+        ClapPluginBridge::new(default_project(), DEFAULT_SAMPLE_RATE_HZ).ok()
+    });
+    let Some(bridge) = created else {
+        return ptr::null();
+    };
+
+    let instance = Box::into_raw(Box::new(Instance {
+        plugin: clap_plugin {
+            desc: &DESCRIPTOR,
+            plugin_data: ptr::null_mut(),
+            init: Some(plugin_init),
+            destroy: Some(plugin_destroy),
+            activate: Some(plugin_activate),
+            deactivate: Some(plugin_deactivate),
+            start_processing: Some(plugin_start_processing),
+            stop_processing: Some(plugin_stop_processing),
+            reset: Some(plugin_reset),
+            process: Some(plugin_process),
+            get_extension: Some(plugin_get_extension),
+            on_main_thread: Some(plugin_on_main_thread),
+        },
+        state: Mutex::new(InstanceState {
+            bridge,
+            engine: None,
+            buses: Vec::new(),
+        }),
+    }));
+    (*instance).plugin.plugin_data = instance.cast();
+    &(*instance).plugin
+}
+
+unsafe extern "C" fn plugin_init(_plugin: *const clap_plugin) -> bool {
+    true
+}
+
+unsafe extern "C" fn plugin_destroy(plugin: *const clap_plugin) {
+    if let Some(instance) = instance(plugin) {
+        drop(Box::from_raw(ptr::from_ref(instance).cast_mut()));
+    }
+}
+
+unsafe extern "C" fn plugin_activate(
+    plugin: *const clap_plugin,
+    sample_rate: f64,
+    _min_frames_count: u32,
+    max_frames_count: u32,
+) -> bool {
+    with_state(plugin, false, |state| {
+        state
+            .activate(sample_rate.round() as u32, max_frames_count)
+            .is_ok()
+    })
+}
+
+unsafe extern "C" fn plugin_deactivate(plugin: *const clap_plugin) {
+    with_state(plugin, (), |state| {
+        state.engine = None;
+        state.buses.clear();
+    });
+}
+
+unsafe extern "C" fn plugin_start_processing(_plugin: *const clap_plugin) -> bool {
+    true
+}
+
+unsafe extern "C" fn plugin_stop_processing(_plugin: *const clap_plugin) {}
+
+unsafe extern "C" fn plugin_reset(plugin: *const clap_plugin) {
+    with_state(plugin, (), |state| {
+        if state.engine.is_some() {
+            state.engine = state.prepare_engine().ok();
+        }
+    });
+}
+
+unsafe extern "C" fn plugin_process(
+    plugin: *const clap_plugin,
+    process: *const clap_process,
+) -> clap_process_status {
+    guard(CLAP_PROCESS_ERROR, || {
+        let (Some(instance), Some(process)) = (instance(plugin), process.as_ref()) else {
+            return CLAP_PROCESS_ERROR;
+        };
+        let frames = process.frames_count;
+        let outputs: &[clap_audio_buffer] = if process.audio_outputs.is_null() {
+            &[]
+        } else {
+            std::slice::from_raw_parts(process.audio_outputs, process.audio_outputs_count as usize)
+        };
+
+        let Ok(mut state) = instance.state.try_lock() else {
+            write_outputs(outputs, frames, |_| None);
+            return CLAP_PROCESS_CONTINUE;
+        };
+        if state.engine.is_none() || state.buses.iter().any(|bus| bus.len() < frames as usize) {
+            return CLAP_PROCESS_ERROR;
+        }
+
+        input_param_values(process.in_events, &mut state);
+        let transport = process
+            .transport
+            .as_ref()
+            .map(|transport| ClapTransport {
+                flags: transport.flags,
+                song_pos_beats: transport.song_pos_beats,
+                tempo: transport.tempo,
+            })
+            .unwrap_or_default();
+        let output = state.bridge.process(frames, &transport);
+        state.render(&output, frames);
+        write_outputs(outputs, frames, |port| {
+            state.buses.get(port).map(|bus| &bus[..frames as usize])
+        });
+        CLAP_PROCESS_CONTINUE
+    })
+}
+
+// The engine mixes each output pair to one bus, which goes to both channels
+// of the matching port.
+unsafe fn write_outputs<'a>(
+    outputs: &[clap_audio_buffer],
+    frames: u32,
+    bus: impl Fn(usize) -> Option<&'a [f32]>,
+) {
+    for (port, buffer) in outputs.iter().enumerate() {
+        if buffer.data32.is_null() {
+            continue;
+        }
+        let source = bus(port);
+        for channel in 0..buffer.channel_count as usize {
+            let data = *buffer.data32.add(channel);
+            if data.is_null() {
+                continue;
+            }
+            let channel = std::slice::from_raw_parts_mut(data, frames as usize);
+            match source {
+                Some(source) => channel.copy_from_slice(source),
+                None => channel.fill(0.0),
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn plugin_get_extension(
+    _plugin: *const clap_plugin,
+    id: *const c_char,
+) -> *const c_void {
+    if id.is_null() {
+        return ptr::null();
+    }
+    let id = CStr::from_ptr(id);
+    if id == CLAP_EXT_PARAMS {
+        ptr::from_ref(&PARAMS).cast()
+    } else if id == CLAP_EXT_STATE {
+        ptr::from_ref(&STATE).cast()
+    } else if id == CLAP_EXT_AUDIO_PORTS {
+        ptr::from_ref(&AUDIO_PORTS).cast()
+    } else {
+        ptr::null()
+    }
+}
+
+unsafe extern "C" fn plugin_on_main_thread(_plugin: *const clap_plugin) {}
+
+unsafe extern "C" fn params_count(plugin: *const clap_plugin) -> u32 {
+    with_state(plugin, 0, |state| state.bridge.param_count() as u32)
+}
+
+unsafe extern "C" fn params_get_info(
+    plugin: *const clap_plugin,
+    param_index: u32,
+    param_info: *mut clap_param_info,
+) -> bool {
+    if param_info.is_null() {
+        return false;
+    }
+    with_state(plugin, false, |state| {
+        let Some(info) = state.bridge.param_info(param_index as usize) else {
+            return false;
+        };
+        let target = &mut *param_info;
+        target.id = info.id;
+        target.flags = info.flags;
+        target.cookie = ptr::null_mut();
+        target.min_value = info.min_value;
+        target.max_value = info.max_value;
+        target.default_value = info.default_value;
+        write_c_text(&info.name, target.name.as_mut_ptr(), target.name.len())
+            && write_c_text(
+                &info.module,
+                target.module.as_mut_ptr(),
+                target.module.len(),
+            )
+    })
+}
+
+unsafe extern "C" fn params_get_value(
+    plugin: *const clap_plugin,
+    param_id: u32,
+    out_value: *mut f64,
+) -> bool {
+    if out_value.is_null() {
+        return false;
+    }
+    with_state(plugin, false, |state| {
+        match state.bridge.param_value(param_id) {
+            Some(value) => {
+                *out_value = value;
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+unsafe extern "C" fn params_value_to_text(
+    plugin: *const clap_plugin,
+    param_id: u32,
+    value: f64,
+    out_buffer: *mut c_char,
+    out_buffer_capacity: u32,
+) -> bool {
+    with_state(plugin, false, |state| {
+        state
+            .bridge
+            .value_to_text(param_id, value)
+            .is_some_and(|text| write_c_text(&text, out_buffer, out_buffer_capacity as usize))
+    })
+}
+
+unsafe extern "C" fn params_text_to_value(
+    _plugin: *const clap_plugin,
+    _param_id: u32,
+    _param_value_text: *const c_char,
+    _out_value: *mut f64,
+) -> bool {
+    false
+}
+
+unsafe extern "C" fn params_flush(
+    plugin: *const clap_plugin,
+    in_: *const clap_input_events,
+    _out: *const clap_output_events,
+) {
+    with_state(plugin, (), |state| input_param_values(in_, state));
+}
+
+unsafe extern "C" fn state_save(plugin: *const clap_plugin, stream: *const clap_ostream) -> bool {
+    let Some(write) = stream.as_ref().and_then(|stream| stream.write) else {
+        return false;
+    };
+    with_state(plugin, false, |state| {
+        let bytes = state.bridge.save_state();
+        let mut written = 0;
+        while written < bytes.len() {
+            let remaining = &bytes[written..];
+            let count = write(stream, remaining.as_ptr().cast(), remaining.len() as u64);
+            if count <= 0 {
+                return false;
+            }
+            written += count as usize;
+        }
+        true
+    })
+}
+
+unsafe extern "C" fn state_load(plugin: *const clap_plugin, stream: *const clap_istream) -> bool {
+    let Some(read) = stream.as_ref().and_then(|stream| stream.read) else {
+        return false;
+    };
+    let mut bytes = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let count = read(stream, chunk.as_mut_ptr().cast(), chunk.len() as u64);
+        match count {
+            0 => break,
+            count if count < 0 => return false,
+            count => bytes.extend_from_slice(&chunk[..count as usize]),
+        }
+    }
+    with_state(plugin, false, |state| {
+        if state.bridge.load_state(&bytes).is_err() {
+            return false;
+        }
+        if state.engine.is_some() {
+            let max_frames = state.buses.first().map_or(0, Vec::len) as u32;
+            let sample_rate_hz = state.bridge.sample_rate_hz();
+            return state.activate(sample_rate_hz, max_frames).is_ok();
+        }
+        true
+    })
+}
+
+unsafe extern "C" fn audio_ports_count(plugin: *const clap_plugin, is_input: bool) -> u32 {
+    if is_input {
+        return 0;
+    }
+    with_state(plugin, 0, |state| {
+        state.bridge.audio_output_port_count() as u32
+    })
+}
+
+unsafe extern "C" fn audio_ports_get(
+    plugin: *const clap_plugin,
+    index: u32,
+    is_input: bool,
+    info: *mut clap_audio_port_info,
+) -> bool {
+    if is_input || info.is_null() {
+        return false;
+    }
+    with_state(plugin, false, |state| {
+        let Some(port) = state.bridge.audio_output_port_info(index as usize) else {
+            return false;
+        };
+        let target = &mut *info;
+        target.id = port.id;
+        target.flags = if port.is_main {
+            CLAP_AUDIO_PORT_IS_MAIN
+        } else {
+            0
+        };
+        target.channel_count = port.channel_count;
+        target.port_type = CLAP_PORT_STEREO.as_ptr();
+        target.in_place_pair = CLAP_INVALID_ID;
+        write_c_text(&port.name, target.name.as_mut_ptr(), target.name.len())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{c_void, CStr};
+    use std::ptr;
+
+    use abi_rs::{ff_track_parameter_id, FF_PARAM_SLOT_PAN};
+    use clap_sys::audio_buffer::clap_audio_buffer;
+    use clap_sys::events::{
+        clap_event_header, clap_event_param_value, clap_event_transport, clap_input_events,
+        CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_VALUE, CLAP_EVENT_TRANSPORT,
+        CLAP_TRANSPORT_HAS_BEATS_TIMELINE, CLAP_TRANSPORT_HAS_TEMPO, CLAP_TRANSPORT_IS_PLAYING,
+    };
+    use clap_sys::ext::params::{clap_plugin_params, CLAP_EXT_PARAMS};
+    use clap_sys::ext::state::{clap_plugin_state, CLAP_EXT_STATE};
+    use clap_sys::factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID};
+    use clap_sys::host::clap_host;
+    use clap_sys::process::{clap_process, CLAP_PROCESS_CONTINUE};
+    use clap_sys::stream::{clap_istream, clap_ostream};
+    use clap_sys::version::CLAP_VERSION;
+    use presets_rs::{PatternStep, TrackAssignment};
+
+    use super::{clap_entry, default_project, PLUGIN_ID};
+
+    struct Stream {
+        bytes: Vec<u8>,
+        position: usize,
+    }
+
+    unsafe extern "C" fn write_stream(
+        stream: *const clap_ostream,
+        buffer: *const c_void,
+        size: u64,
+    ) -> i64 {
+        let target = &mut *(*stream).ctx.cast::<Stream>();
+        let bytes = std::slice::from_raw_parts(buffer.cast::<u8>(), size as usize);
+        target.bytes.extend_from_slice(bytes);
+        size as i64
+    }
+
+    unsafe extern "C" fn read_stream(
+        stream: *const clap_istream,
+        buffer: *mut c_void,
+        size: u64,
+    ) -> i64 {
+        let source = &mut *(*stream).ctx.cast::<Stream>();
+        let count = (size as usize).min(source.bytes.len() - source.position);
+        let bytes = &source.bytes[source.position..source.position + count];
+        ptr::copy_nonoverlapping(bytes.as_ptr(), buffer.cast::<u8>(), count);
+        source.position += count;
+        count as i64
+    }
+
+    unsafe extern "C" fn event_count(list: *const clap_input_events) -> u32 {
+        u32::from(!(*list).ctx.is_null())
+    }
+
+    unsafe extern "C" fn event_at(
+        list: *const clap_input_events,
+        _index: u32,
+    ) -> *const clap_event_header {
+        (*list).ctx.cast::<clap_event_header>()
+    }
+
+    #[test]
+    fn host_creates_the_plugin_loads_state_and_renders_the_pattern() {
+        let sample_path =
+            std::env::temp_dir().join(format!("ff-clap-plugin-{}.wav", std::process::id()));
+        std::fs::write(
+            &sample_path,
+            render_rs::wav::write_wav_16(&[0.5; 4_800], 1, 48_000),
+        )
+        .expect("write sample");
+        let mut project = default_project();
+        assert!(project.kits[0].add_assignment(TrackAssignment {
+            track_index: 0,
+            sample_id: sample_path.to_string_lossy().into_owned(),
+        }));
+        project.patterns[0].set_step(
+            0,
+            0,
+            PatternStep {
+                active: true,
+                velocity: 127,
+                ..PatternStep::default()
+            },
+        );
+        let saved = presets_rs::save_project_to_text(&project);
+
+        unsafe {
+            assert!(clap_entry.init.expect("init")(c"".as_ptr()));
+            assert!(clap_entry.get_factory.expect("get_factory")(c"other".as_ptr()).is_null());
+            let factory =
+                &*clap_entry.get_factory.expect("get_factory")(CLAP_PLUGIN_FACTORY_ID.as_ptr())
+                    .cast::<clap_plugin_factory>();
+            assert_eq!(factory.get_plugin_count.expect("count")(factory), 1);
+            let descriptor = &*factory.get_plugin_descriptor.expect("descriptor")(factory, 0);
+            assert_eq!(CStr::from_ptr(descriptor.id), PLUGIN_ID);
+
+            let host = clap_host {
+                clap_version: CLAP_VERSION,
+                host_data: ptr::null_mut(),
+                name: c"test host".as_ptr(),
+                vendor: c"".as_ptr(),
+                url: c"".as_ptr(),
+                version: c"1".as_ptr(),
+                get_extension: None,
+                request_restart: None,
+                request_process: None,
+                request_callback: None,
+            };
+            let create = factory.create_plugin.expect("create");
+            assert!(create(factory, &host, c"other".as_ptr()).is_null());
+            let plugin = &*create(factory, &host, PLUGIN_ID.as_ptr());
+            assert!(plugin.init.expect("init")(plugin));
+
+            let get_extension = plugin.get_extension.expect("get_extension");
+            let params =
+                &*get_extension(plugin, CLAP_EXT_PARAMS.as_ptr()).cast::<clap_plugin_params>();
+            assert_eq!(params.count.expect("count")(plugin), 120);
+            let state =
+                &*get_extension(plugin, CLAP_EXT_STATE.as_ptr()).cast::<clap_plugin_state>();
+            let mut source = Stream {
+                bytes: saved.clone().into_bytes(),
+                position: 0,
+            };
+            let input = clap_istream {
+                ctx: ptr::from_mut(&mut source).cast(),
+                read: Some(read_stream),
+            };
+            assert!(state.load.expect("load")(plugin, &input));
+
+            assert!(plugin.activate.expect("activate")(plugin, 48_000.0, 1, 256));
+            assert!(plugin.start_processing.expect("start")(plugin));
+            let pan_id = ff_track_parameter_id(0, FF_PARAM_SLOT_PAN).expect("id");
+            let pan = clap_event_param_value {
+                header: clap_event_header {
+                    size: size_of::<clap_event_param_value>() as u32,
+                    time: 0,
+                    space_id: CLAP_CORE_EVENT_SPACE_ID,
+                    type_: CLAP_EVENT_PARAM_VALUE,
+                    flags: 0,
+                },
+                param_id: pan_id,
+                cookie: ptr::null_mut(),
+                note_id: -1,
+                port_index: -1,
+                channel: -1,
+                key: -1,
+                value: 0.25,
+            };
+            let in_events = clap_input_events {
+                ctx: ptr::from_ref(&pan).cast_mut().cast(),
+                size: Some(event_count),
+                get: Some(event_at),
+            };
+            let transport = clap_event_transport {
+                header: clap_event_header {
+                    size: size_of::<clap_event_transport>() as u32,
+                    time: 0,
+                    space_id: CLAP_CORE_EVENT_SPACE_ID,
+                    type_: CLAP_EVENT_TRANSPORT,
+                    flags: 0,
+                },
+                flags: CLAP_TRANSPORT_HAS_TEMPO
+                    | CLAP_TRANSPORT_HAS_BEATS_TIMELINE
+                    | CLAP_TRANSPORT_IS_PLAYING,
+                song_pos_beats: 0,
+                song_pos_seconds: 0,
+                tempo: 120.0,
+                tempo_inc: 0.0,
+                loop_start_beats: 0,
+                loop_end_beats: 0,
+                loop_start_seconds: 0,
+                loop_end_seconds: 0,
+                bar_start: 0,
+                bar_number: 0,
+                tsig_num: 4,
+                tsig_denom: 4,
+            };
+            let mut left = [1.0f32; 256];
+            let mut right = [1.0f32; 256];
+            let mut channels = [left.as_mut_ptr(), right.as_mut_ptr()];
+            let mut output = clap_audio_buffer {
+                data32: channels.as_mut_ptr(),
+                data64: ptr::null_mut(),
+                channel_count: 2,
+                latency: 0,
+                constant_mask: 0,
+            };
+            let process = clap_process {
+                steady_time: 0,
+                frames_count: 256,
+                transport: &transport,
+                audio_inputs: ptr::null(),
+                audio_outputs: &mut output,
+                audio_inputs_count: 0,
+                audio_outputs_count: 1,
+                in_events: &in_events,
+                out_events: ptr::null(),
+            };
+            assert_eq!(
+                plugin.process.expect("process")(plugin, &process),
+                CLAP_PROCESS_CONTINUE
+            );
+            assert!(left.iter().any(|sample| sample.abs() > 0.01));
+            assert_eq!(left, right);
+            let mut pan_value = 0.0;
+            assert!(params.get_value.expect("get_value")(
+                plugin,
+                pan_id,
+                &mut pan_value
+            ));
+            assert_eq!(pan_value, 0.25);
+
+            let mut target = Stream {
+                bytes: Vec::new(),
+                position: 0,
+            };
+            let output_stream = clap_ostream {
+                ctx: ptr::from_mut(&mut target).cast(),
+                write: Some(write_stream),
+            };
+            assert!(state.save.expect("save")(plugin, &output_stream));
+            let restored = presets_rs::load_project_from_text(
+                std::str::from_utf8(&target.bytes).expect("utf8"),
+            )
+            .expect("saved state");
+            assert_eq!(restored.kits[0].tracks, project.kits[0].tracks);
+
+            plugin.stop_processing.expect("stop")(plugin);
+            plugin.deactivate.expect("deactivate")(plugin);
+            plugin.destroy.expect("destroy")(plugin);
+            clap_entry.deinit.expect("deinit")();
+        }
+        let _ = std::fs::remove_file(sample_path);
+    }
+}
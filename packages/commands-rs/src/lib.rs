@@ -0,0 +1,548 @@
+use std::collections::VecDeque;
+
+use control_rs::{Sequencer, Step};
+use presets_rs::{PatternStep, Project, TrackAssignment, TrackControls, MAX_STEP_PROBABILITY};
+
+pub const DEFAULT_HISTORY_CAPACITY: usize = 128;
+
+#[derive(Debug)]
+pub struct EditState {
+    pub project: Project,
+    pub sequencer: Sequencer,
+}
+
+impl EditState {
+    pub fn from_project(project: Project, sample_rate_hz: u32) -> Result<Self, String> {
+        let sequencer =
+            control_rs::recall_state_from_project(&project, sample_rate_hz)?.into_sequencer();
+        Ok(Self { project, sequencer })
+    }
+
+    fn active_pattern_index(&self) -> usize {
+        self.project.active_pattern.unwrap_or(0)
+    }
+
+    fn active_kit_index(&self) -> usize {
+        self.project.active_kit.unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    SetStep {
+        pattern_index: usize,
+        track_index: usize,
+        step_index: usize,
+        step: PatternStep,
+    },
+    SetControls {
+        kit_index: usize,
+        track_index: u8,
+        controls: TrackControls,
+    },
+    AssignSample {
+        kit_index: usize,
+        track_index: u8,
+        sample_id: Option<String>,
+    },
+    SetTempo {
+        bpm: f32,
+    },
+}
+
+impl Command {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::SetStep { .. } => "set step",
+            Self::SetControls { .. } => "set controls",
+            Self::AssignSample { .. } => "assign sample",
+            Self::SetTempo { .. } => "set tempo",
+        }
+    }
+
+    pub fn apply(&self, state: &mut EditState) -> Result<Command, String> {
+        match self {
+            Self::SetStep {
+                pattern_index,
+                track_index,
+                step_index,
+                step,
+            } => {
+                let mirror = *pattern_index == state.active_pattern_index();
+                let pattern = state
+                    .project
+                    .patterns
+                    .get_mut(*pattern_index)
+                    .ok_or_else(|| format!("pattern out of range: {pattern_index}"))?;
+                let previous = pattern.step(*track_index, *step_index).ok_or_else(|| {
+                    format!("step out of range: track={track_index}, step={step_index}")
+                })?;
+                let step = PatternStep {
+                    velocity: step.velocity.min(127),
+                    probability: step.probability.min(MAX_STEP_PROBABILITY),
+                    ..*step
+                };
+                pattern.set_step(*track_index, *step_index, step);
+                if mirror {
+                    state
+                        .sequencer
+                        .edit_step(*track_index, *step_index, Step::from(step));
+                }
+
+                Ok(Self::SetStep {
+                    pattern_index: *pattern_index,
+                    track_index: *track_index,
+                    step_index: *step_index,
+                    step: previous,
+                })
+            }
+            Self::SetControls {
+                kit_index,
+                track_index,
+                controls,
+            } => {
+                if usize::from(*track_index) >= presets_rs::TRACK_COUNT {
+                    return Err(format!("track out of range: {track_index}"));
+                }
+                let mirror = *kit_index == state.active_kit_index();
+                let kit = state
+                    .project
+                    .kits
+                    .get_mut(*kit_index)
+                    .ok_or_else(|| format!("kit out of range: {kit_index}"))?;
+                let previous = kit.track_controls(*track_index).unwrap_or_default();
+                kit.set_track_controls(*track_index, *controls);
+                if mirror {
+                    state
+                        .sequencer
+                        .set_track_choke_group(usize::from(*track_index), controls.choke_group);
+                }
+
+                Ok(Self::SetControls {
+                    kit_index: *kit_index,
+                    track_index: *track_index,
+                    controls: previous,
+                })
+            }
+            Self::AssignSample {
+                kit_index,
+                track_index,
+                sample_id,
+            } => {
+                if usize::from(*track_index) >= presets_rs::TRACK_COUNT {
+                    return Err(format!("track out of range: {track_index}"));
+                }
+                let kit = state
+                    .project
+                    .kits
+                    .get_mut(*kit_index)
+                    .ok_or_else(|| format!("kit out of range: {kit_index}"))?;
+                let existing = kit
+                    .tracks
+                    .iter()
+                    .position(|assignment| assignment.track_index == *track_index);
+                let previous = existing.map(|index| kit.tracks.remove(index).sample_id);
+                if let Some(sample_id) = sample_id {
+                    kit.tracks.push(TrackAssignment {
+                        track_index: *track_index,
+                        sample_id: sample_id.clone(),
+                    });
+                    kit.tracks.sort_by_key(|assignment| assignment.track_index);
+                }
+
+                Ok(Self::AssignSample {
+                    kit_index: *kit_index,
+                    track_index: *track_index,
+                    sample_id: previous,
+                })
+            }
+            Self::SetTempo { bpm } => {
+                if !bpm.is_finite() {
+                    return Err(format!("invalid tempo: {bpm}"));
+                }
+                let previous = state.sequencer.transport().bpm();
+                state.sequencer.set_tempo_bpm(*bpm);
+                Ok(Self::SetTempo { bpm: previous })
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transaction {
+    pub label: String,
+    entries: Vec<(Command, Command)>,
+}
+
+impl Transaction {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[derive(Debug)]
+pub struct CommandHistory {
+    capacity: usize,
+    undo: VecDeque<Transaction>,
+    redo: Vec<Transaction>,
+    open_group: Option<Transaction>,
+}
+
+impl Default for CommandHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_CAPACITY)
+    }
+}
+
+impl CommandHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            open_group: None,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn undo_label(&self) -> Option<&str> {
+        self.undo
+            .back()
+            .map(|transaction| transaction.label.as_str())
+    }
+
+    pub fn redo_label(&self) -> Option<&str> {
+        self.redo
+            .last()
+            .map(|transaction| transaction.label.as_str())
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.open_group = None;
+    }
+
+    pub fn begin_group(&mut self, label: &str) -> bool {
+        if self.open_group.is_some() {
+            return false;
+        }
+
+        self.open_group = Some(Transaction {
+            label: label.to_string(),
+            entries: Vec::new(),
+        });
+        true
+    }
+
+    pub fn end_group(&mut self) -> bool {
+        match self.open_group.take() {
+            Some(transaction) => {
+                if !transaction.is_empty() {
+                    self.push(transaction);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn cancel_group(&mut self, state: &mut EditState) -> Result<bool, String> {
+        let Some(transaction) = self.open_group.take() else {
+            return Ok(false);
+        };
+        if let Err(error) = revert(&transaction, state) {
+            self.open_group = Some(transaction);
+            return Err(error);
+        }
+        Ok(true)
+    }
+
+    pub fn execute(&mut self, state: &mut EditState, command: Command) -> Result<(), String> {
+        let inverse = command.apply(state)?;
+        match &mut self.open_group {
+            Some(transaction) => {
+                self.redo.clear();
+                transaction.entries.push((command, inverse));
+            }
+            None => self.push(Transaction {
+                label: command.label().to_string(),
+                entries: vec![(command, inverse)],
+            }),
+        }
+        Ok(())
+    }
+
+    pub fn undo(&mut self, state: &mut EditState) -> Result<bool, String> {
+        if self.open_group.is_some() {
+            return Err("cannot undo while a group is open".to_string());
+        }
+        let Some(transaction) = self.undo.pop_back() else {
+            return Ok(false);
+        };
+
+        if let Err(error) = revert(&transaction, state) {
+            self.undo.push_back(transaction);
+            return Err(error);
+        }
+        self.redo.push(transaction);
+        Ok(true)
+    }
+
+    pub fn redo(&mut self, state: &mut EditState) -> Result<bool, String> {
+        if self.open_group.is_some() {
+            return Err("cannot redo while a group is open".to_string());
+        }
+        let Some(transaction) = self.redo.pop() else {
+            return Ok(false);
+        };
+
+        let commands = transaction.entries.iter().map(|(command, _)| command);
+        if let Err(error) = apply_all(commands, state) {
+            self.redo.push(transaction);
+            return Err(error);
+        }
+        self.undo.push_back(transaction);
+        Ok(true)
+    }
+
+    fn push(&mut self, transaction: Transaction) {
+        self.redo.clear();
+        self.undo.push_back(transaction);
+        while self.undo.len() > self.capacity {
+            self.undo.pop_front();
+        }
+    }
+}
+
+fn revert(transaction: &Transaction, state: &mut EditState) -> Result<(), String> {
+    let inverses = transaction.entries.iter().rev().map(|(_, inverse)| inverse);
+    apply_all(inverses, state)
+}
+
+// A transaction applies whole or not at all: when one command fails, the
+// ones before it are rolled back and the state is left as it was found.
+fn apply_all<'a>(
+    commands: impl Iterator<Item = &'a Command>,
+    state: &mut EditState,
+) -> Result<(), String> {
+    let mut applied = Vec::new();
+    for command in commands {
+        match command.apply(state) {
+            Ok(inverse) => applied.push(inverse),
+            Err(error) => {
+                for inverse in applied.iter().rev() {
+                    inverse.apply(state)?;
+                }
+                return Err(error);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use presets_rs::{Kit, Pattern, PatternStep, Project, TrackControls};
+
+    use super::{Command, CommandHistory, EditState};
+
+    fn state() -> EditState {
+        let project = Project {
+            name: "edit".to_string(),
+            kits: vec![Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![Pattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        EditState::from_project(project, 48_000).expect("edit state")
+    }
+
+    fn set_step(track_index: usize, step_index: usize) -> Command {
+        Command::SetStep {
+            pattern_index: 0,
+            track_index,
+            step_index,
+            step: PatternStep {
+                active: true,
+                velocity: 110,
+                ..PatternStep::default()
+            },
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_restore_project_and_sequencer() {
+        let mut state = state();
+        let mut history = CommandHistory::default();
+        history
+            .execute(&mut state, set_step(1, 4))
+            .expect("set step");
+        history
+            .execute(&mut state, Command::SetTempo { bpm: 96.0 })
+            .expect("set tempo");
+        history
+            .execute(
+                &mut state,
+                Command::AssignSample {
+                    kit_index: 0,
+                    track_index: 2,
+                    sample_id: Some("snare".to_string()),
+                },
+            )
+            .expect("assign");
+        assert!(state.sequencer.pattern().step(1, 4).expect("step").active);
+
+        assert!(history.undo(&mut state).expect("undo"));
+        assert!(state.project.kits[0].tracks.is_empty());
+        assert!(history.undo(&mut state).expect("undo"));
+        assert_eq!(state.sequencer.transport().bpm(), 120.0);
+        assert!(history.undo(&mut state).expect("undo"));
+        assert!(!state.project.patterns[0].steps[1][4].active);
+        assert!(!state.sequencer.pattern().step(1, 4).expect("step").active);
+        assert!(!history.undo(&mut state).expect("empty undo"));
+
+        assert!(history.redo(&mut state).expect("redo"));
+        assert!(state.project.patterns[0].steps[1][4].active);
+        assert_eq!(history.redo_label(), Some("set tempo"));
+
+        history
+            .execute(&mut state, set_step(0, 0))
+            .expect("new edit");
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn grouped_transaction_undoes_as_one_entry() {
+        let mut state = state();
+        let mut history = CommandHistory::default();
+        assert!(history.begin_group("fill"));
+        for step_index in 0..4 {
+            history
+                .execute(&mut state, set_step(3, step_index))
+                .expect("step");
+        }
+        history
+            .execute(
+                &mut state,
+                Command::SetControls {
+                    kit_index: 0,
+                    track_index: 3,
+                    controls: TrackControls {
+                        choke_group: Some(2),
+                        ..TrackControls::default()
+                    },
+                },
+            )
+            .expect("controls");
+        assert!(history.undo(&mut state).is_err());
+        assert!(history.end_group());
+        assert_eq!(history.undo_len(), 1);
+        assert_eq!(history.undo_label(), Some("fill"));
+
+        assert!(history.undo(&mut state).expect("undo"));
+        assert!(state.project.patterns[0].steps[3]
+            .iter()
+            .all(|step| !step.active));
+        assert_eq!(
+            state.project.kits[0].track_controls(3),
+            Some(TrackControls::default())
+        );
+        assert!(history.execute(&mut state, set_step(8, 0)).is_err());
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut state = state();
+        let mut history = CommandHistory::new(3);
+        for step_index in 0..5 {
+            history
+                .execute(&mut state, set_step(0, step_index))
+                .expect("step");
+        }
+        assert_eq!(history.undo_len(), 3);
+        while history.undo(&mut state).expect("undo") {}
+        assert!(state.project.patterns[0].steps[0][0].active);
+        assert!(state.project.patterns[0].steps[0][1].active);
+        assert!(!state.project.patterns[0].steps[0][2].active);
+    }
+
+    #[test]
+    fn executing_inside_a_group_clears_redo_right_away() {
+        let mut state = state();
+        let mut history = CommandHistory::default();
+        history.execute(&mut state, set_step(0, 0)).expect("step");
+        assert!(history.undo(&mut state).expect("undo"));
+        assert!(history.can_redo());
+
+        assert!(history.begin_group("fill"));
+        history.execute(&mut state, set_step(1, 0)).expect("step");
+        assert!(!history.can_redo());
+        assert!(history.redo(&mut state).is_err());
+        assert!(history.end_group());
+        assert!(!history.redo(&mut state).expect("redo"));
+        assert!(!state.project.patterns[0].steps[0][0].active);
+    }
+
+    #[test]
+    fn failed_group_redo_rolls_back_and_stays_redoable() {
+        let mut state = state();
+        let mut history = CommandHistory::default();
+        assert!(history.begin_group("fill"));
+        history.execute(&mut state, set_step(3, 0)).expect("step");
+        history
+            .execute(
+                &mut state,
+                Command::SetControls {
+                    kit_index: 0,
+                    track_index: 3,
+                    controls: TrackControls {
+                        choke_group: Some(2),
+                        ..TrackControls::default()
+                    },
+                },
+            )
+            .expect("controls");
+        assert!(history.end_group());
+        assert!(history.undo(&mut state).expect("undo"));
+
+        let kits = std::mem::take(&mut state.project.kits);
+        assert!(history.redo(&mut state).is_err());
+        assert!(!state.project.patterns[0].steps[3][0].active);
+        assert_eq!((history.undo_len(), history.redo_len()), (0, 1));
+
+        state.project.kits = kits;
+        assert!(history.redo(&mut state).expect("redo"));
+        assert!(state.project.patterns[0].steps[3][0].active);
+        assert_eq!(
+            state.project.kits[0]
+                .track_controls(3)
+                .and_then(|controls| controls.choke_group),
+            Some(2)
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+
+use abi_rs::FfParameterUpdate;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimedParameterUpdate {
+    pub block_offset: u32,
+    pub update: FfParameterUpdate,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ParameterCoalescer {
+    pending: BTreeMap<u32, TimedParameterUpdate>,
+    received: usize,
+}
+
+impl ParameterCoalescer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn merged_count(&self) -> usize {
+        self.received - self.pending.len()
+    }
+
+    pub fn push(&mut self, block_offset: u32, update: FfParameterUpdate) {
+        self.received += 1;
+        let merged = match self.pending.get(&update.parameter_id) {
+            Some(earlier) if earlier.block_offset <= block_offset => {
+                let ramp_end = u64::from(block_offset) + u64::from(update.ramp_samples);
+                let earliest_end =
+                    u64::from(earlier.block_offset) + u64::from(earlier.update.ramp_samples);
+                TimedParameterUpdate {
+                    block_offset: earlier.block_offset,
+                    update: FfParameterUpdate {
+                        ramp_samples: (ramp_end.max(earliest_end) - u64::from(earlier.block_offset))
+                            .min(u64::from(u32::MAX)) as u32,
+                        ..update
+                    },
+                }
+            }
+            Some(later) => TimedParameterUpdate {
+                block_offset,
+                update: FfParameterUpdate {
+                    ramp_samples: update
+                        .ramp_samples
+                        .max(later.block_offset - block_offset + later.update.ramp_samples),
+                    ..later.update
+                },
+            },
+            None => TimedParameterUpdate {
+                block_offset,
+                update,
+            },
+        };
+        self.pending.insert(update.parameter_id, merged);
+    }
+
+    pub fn drain(&mut self) -> Vec<TimedParameterUpdate> {
+        let mut updates: Vec<TimedParameterUpdate> =
+            std::mem::take(&mut self.pending).into_values().collect();
+        updates.sort_by_key(|timed| (timed.block_offset, timed.update.parameter_id));
+        self.received = 0;
+        updates
+    }
+}
+
+pub fn coalesce_parameter_updates(updates: &[TimedParameterUpdate]) -> Vec<TimedParameterUpdate> {
+    let mut coalescer = ParameterCoalescer::new();
+    for timed in updates {
+        coalescer.push(timed.block_offset, timed.update);
+    }
+    coalescer.drain()
+}
+
+#[cfg(test)]
+mod tests {
+    use abi_rs::{ff_track_parameter_id, FfParameterUpdate, FF_PARAM_SLOT_GAIN, FF_PARAM_SLOT_PAN};
+
+    use super::{coalesce_parameter_updates, ParameterCoalescer, TimedParameterUpdate};
+
+    fn timed(block_offset: u32, slot: u32, value: f32, ramp_samples: u32) -> TimedParameterUpdate {
+        TimedParameterUpdate {
+            block_offset,
+            update: FfParameterUpdate {
+                parameter_id: ff_track_parameter_id(0, slot).expect("id"),
+                normalized_value: value,
+                ramp_samples,
+                reserved: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn sweeps_merge_to_the_last_value_from_the_earliest_ramp_start() {
+        let mut coalescer = ParameterCoalescer::new();
+        for (block_offset, value) in [(16, 0.2), (48, 0.4), (96, 0.9)] {
+            let sweep = timed(block_offset, FF_PARAM_SLOT_GAIN, value, 32);
+            coalescer.push(sweep.block_offset, sweep.update);
+        }
+        let pan = timed(8, FF_PARAM_SLOT_PAN, 0.5, 0);
+        coalescer.push(pan.block_offset, pan.update);
+        assert_eq!(coalescer.pending_len(), 2);
+        assert_eq!(coalescer.merged_count(), 2);
+
+        let drained = coalescer.drain();
+        assert_eq!(drained[0], pan);
+        assert_eq!(drained[1], timed(16, FF_PARAM_SLOT_GAIN, 0.9, 112));
+        assert!(coalescer.drain().is_empty());
+        assert_eq!(coalescer.merged_count(), 0);
+    }
+
+    #[test]
+    fn out_of_order_updates_keep_the_later_value() {
+        let coalesced = coalesce_parameter_updates(&[
+            timed(64, FF_PARAM_SLOT_GAIN, 0.8, 0),
+            timed(0, FF_PARAM_SLOT_GAIN, 0.1, 16),
+        ]);
+        assert_eq!(coalesced, vec![timed(0, FF_PARAM_SLOT_GAIN, 0.8, 64)]);
+    }
+}
@@ -0,0 +1,432 @@
+use crate::{Pattern, Step, TRACK_COUNT};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StepSelection {
+    pub first_track: usize,
+    pub last_track: usize,
+    pub first_step: usize,
+    pub last_step: usize,
+}
+
+impl StepSelection {
+    pub fn cell(track_index: usize, step_index: usize) -> Self {
+        Self {
+            first_track: track_index,
+            last_track: track_index,
+            first_step: step_index,
+            last_step: step_index,
+        }
+    }
+
+    pub fn spanning(anchor: (usize, usize), head: (usize, usize)) -> Self {
+        Self {
+            first_track: anchor.0.min(head.0),
+            last_track: anchor.0.max(head.0),
+            first_step: anchor.1.min(head.1),
+            last_step: anchor.1.max(head.1),
+        }
+    }
+
+    pub fn track_count(&self) -> usize {
+        self.last_track - self.first_track + 1
+    }
+
+    pub fn step_count(&self) -> usize {
+        self.last_step - self.first_step + 1
+    }
+
+    pub fn contains(&self, track_index: usize, step_index: usize) -> bool {
+        (self.first_track..=self.last_track).contains(&track_index)
+            && (self.first_step..=self.last_step).contains(&step_index)
+    }
+
+    pub fn fits(&self, pattern: &Pattern) -> bool {
+        self.first_track <= self.last_track
+            && self.first_step <= self.last_step
+            && self.last_track < TRACK_COUNT
+            && self.last_step < pattern.step_count()
+    }
+
+    fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (self.first_track..=self.last_track).flat_map(move |track_index| {
+            (self.first_step..=self.last_step).map(move |step_index| (track_index, step_index))
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StepClipboard {
+    track_count: usize,
+    step_count: usize,
+    steps: Vec<Step>,
+}
+
+impl StepClipboard {
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn track_count(&self) -> usize {
+        self.track_count
+    }
+
+    pub fn step_count(&self) -> usize {
+        self.step_count
+    }
+
+    pub fn step(&self, track_offset: usize, step_offset: usize) -> Option<Step> {
+        if track_offset >= self.track_count || step_offset >= self.step_count {
+            return None;
+        }
+        self.steps
+            .get(track_offset * self.step_count + step_offset)
+            .copied()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StepCursor {
+    track_index: usize,
+    step_index: usize,
+    anchor: Option<(usize, usize)>,
+    clipboard: StepClipboard,
+}
+
+impl StepCursor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn position(&self) -> (usize, usize) {
+        (self.track_index, self.step_index)
+    }
+
+    pub fn move_to(&mut self, pattern: &Pattern, track_index: usize, step_index: usize) -> bool {
+        if track_index >= TRACK_COUNT || step_index >= pattern.step_count() {
+            return false;
+        }
+        self.track_index = track_index;
+        self.step_index = step_index;
+        self.anchor = None;
+        true
+    }
+
+    pub fn move_by(&mut self, pattern: &Pattern, track_delta: isize, step_delta: isize) {
+        let (track_index, step_index) = self.offset_position(pattern, track_delta, step_delta);
+        self.track_index = track_index;
+        self.step_index = step_index;
+        self.anchor = None;
+    }
+
+    pub fn extend_by(&mut self, pattern: &Pattern, track_delta: isize, step_delta: isize) {
+        let anchor = self.anchor.unwrap_or(self.position());
+        let (track_index, step_index) = self.offset_position(pattern, track_delta, step_delta);
+        self.track_index = track_index;
+        self.step_index = step_index;
+        self.anchor = Some(anchor);
+    }
+
+    pub fn select(&mut self, pattern: &Pattern, selection: StepSelection) -> bool {
+        if !selection.fits(pattern) {
+            return false;
+        }
+        self.anchor = Some((selection.first_track, selection.first_step));
+        self.track_index = selection.last_track;
+        self.step_index = selection.last_step;
+        true
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.anchor = None;
+    }
+
+    pub fn has_selection(&self) -> bool {
+        self.anchor.is_some()
+    }
+
+    pub fn selection(&self) -> StepSelection {
+        StepSelection::spanning(self.anchor.unwrap_or(self.position()), self.position())
+    }
+
+    pub fn clipboard(&self) -> &StepClipboard {
+        &self.clipboard
+    }
+
+    pub fn toggle(&self, pattern: &mut Pattern) -> bool {
+        let selection = self.selection();
+        if !selection.fits(pattern) {
+            return false;
+        }
+        let activate = !selection.cells().all(|(track_index, step_index)| {
+            pattern
+                .step(track_index, step_index)
+                .is_some_and(|step| step.active)
+        });
+        for (track_index, step_index) in selection.cells() {
+            let Some(step) = pattern.step(track_index, step_index) else {
+                continue;
+            };
+            let toggled = if activate && !step.active {
+                pattern.default_hit(track_index)
+            } else {
+                Step {
+                    active: activate,
+                    ..step
+                }
+            };
+            pattern.set_step(track_index, step_index, toggled);
+        }
+        true
+    }
+
+    pub fn adjust_velocity(&self, pattern: &mut Pattern, delta: i16) -> usize {
+        let selection = self.selection();
+        let mut adjusted = 0;
+        for (track_index, step_index) in selection.cells() {
+            let Some(step) = pattern.step(track_index, step_index) else {
+                continue;
+            };
+            if !step.active {
+                continue;
+            }
+            let velocity = (i16::from(step.velocity) + delta).clamp(1, 127) as u8;
+            if velocity != step.velocity {
+                pattern.set_step(track_index, step_index, Step { velocity, ..step });
+                adjusted += 1;
+            }
+        }
+        adjusted
+    }
+
+    pub fn copy(&mut self, pattern: &Pattern) -> bool {
+        let selection = self.selection();
+        if !selection.fits(pattern) {
+            return false;
+        }
+        self.clipboard = StepClipboard {
+            track_count: selection.track_count(),
+            step_count: selection.step_count(),
+            steps: selection
+                .cells()
+                .filter_map(|(track_index, step_index)| pattern.step(track_index, step_index))
+                .collect(),
+        };
+        true
+    }
+
+    pub fn paste(&self, pattern: &mut Pattern) -> usize {
+        let origin = self.selection();
+        let mut pasted = 0;
+        for track_offset in 0..self.clipboard.track_count {
+            for step_offset in 0..self.clipboard.step_count {
+                let Some(step) = self.clipboard.step(track_offset, step_offset) else {
+                    continue;
+                };
+                if pattern.set_step(
+                    origin.first_track + track_offset,
+                    origin.first_step + step_offset,
+                    step,
+                ) {
+                    pasted += 1;
+                }
+            }
+        }
+        pasted
+    }
+
+    pub fn nudge(&mut self, pattern: &mut Pattern, step_delta: isize) -> bool {
+        let selection = self.selection();
+        if !selection.fits(pattern) {
+            return false;
+        }
+        let Some(first_step) = selection.first_step.checked_add_signed(step_delta) else {
+            return false;
+        };
+        let last_step = first_step + selection.step_count() - 1;
+        if last_step >= pattern.step_count() {
+            return false;
+        }
+
+        let moved: Vec<(usize, usize, Step)> = selection
+            .cells()
+            .filter_map(|(track_index, step_index)| {
+                Some((
+                    track_index,
+                    step_index,
+                    pattern.step(track_index, step_index)?,
+                ))
+            })
+            .collect();
+        for (track_index, step_index, _) in &moved {
+            pattern.set_step(*track_index, *step_index, Step::default());
+        }
+        for (track_index, step_index, step) in moved {
+            pattern.set_step(
+                track_index,
+                step_index - selection.first_step + first_step,
+                step,
+            );
+        }
+
+        self.anchor = self
+            .anchor
+            .map(|(track_index, step_index)| (track_index, shift(step_index, step_delta)));
+        self.step_index = shift(self.step_index, step_delta);
+        true
+    }
+
+    fn offset_position(
+        &self,
+        pattern: &Pattern,
+        track_delta: isize,
+        step_delta: isize,
+    ) -> (usize, usize) {
+        let track_index = self
+            .track_index
+            .saturating_add_signed(track_delta)
+            .min(TRACK_COUNT - 1);
+        let step_count = pattern.step_count() as isize;
+        let step_index = (self.step_index as isize + step_delta).rem_euclid(step_count) as usize;
+        (track_index, step_index)
+    }
+}
+
+fn shift(step_index: usize, step_delta: isize) -> usize {
+    (step_index as isize + step_delta) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StepCursor, StepSelection};
+    use crate::{Pattern, Step, MAX_STEP_PROBABILITY, STEPS_PER_PATTERN, TRACK_COUNT};
+
+    fn hit(velocity: u8) -> Step {
+        Step {
+            active: true,
+            velocity,
+            ..Step::default()
+        }
+    }
+
+    #[test]
+    fn cursor_moves_wrap_steps_and_extend_a_selection() {
+        let pattern = Pattern::default();
+        let mut cursor = StepCursor::new();
+        assert!(cursor.move_to(&pattern, 2, 3));
+        assert!(!cursor.move_to(&pattern, 2, STEPS_PER_PATTERN));
+        cursor.move_by(&pattern, 0, -4);
+        assert_eq!(cursor.position(), (2, STEPS_PER_PATTERN - 1));
+        cursor.move_by(&pattern, 10, 1);
+        assert_eq!(cursor.position(), (7, 0));
+        assert_eq!(cursor.selection(), StepSelection::cell(7, 0));
+
+        cursor.extend_by(&pattern, -2, 3);
+        cursor.extend_by(&pattern, 0, 1);
+        assert!(cursor.has_selection());
+        assert_eq!(
+            cursor.selection(),
+            StepSelection {
+                first_track: 5,
+                last_track: 7,
+                first_step: 0,
+                last_step: 4,
+            }
+        );
+        cursor.clear_selection();
+        assert_eq!(cursor.selection(), StepSelection::cell(5, 4));
+    }
+
+    #[test]
+    fn selection_edits_toggle_adjust_copy_paste_and_nudge() {
+        let mut pattern = Pattern::default();
+        pattern.set_step(0, 0, hit(100));
+        pattern.set_step(0, 2, hit(120));
+        pattern.set_step(1, 1, hit(60));
+
+        let mut cursor = StepCursor::new();
+        assert!(cursor.select(
+            &pattern,
+            StepSelection {
+                first_track: 0,
+                last_track: 1,
+                first_step: 0,
+                last_step: 2,
+            }
+        ));
+        assert_eq!(cursor.adjust_velocity(&mut pattern, 10), 3);
+        assert_eq!(pattern.step(0, 2), Some(hit(127)));
+        assert_eq!(pattern.step(1, 1), Some(hit(70)));
+        assert_eq!(pattern.step(0, 1).map(|step| step.velocity), Some(100));
+
+        assert!(cursor.copy(&pattern));
+        assert_eq!(cursor.clipboard().track_count(), 2);
+        assert_eq!(cursor.clipboard().step_count(), 3);
+        assert!(cursor.move_to(&pattern, 1, 14));
+        assert_eq!(cursor.paste(&mut pattern), 4);
+        assert_eq!(pattern.step(1, 14), Some(hit(110)));
+        assert_eq!(pattern.step(2, 15), Some(hit(70)));
+
+        assert!(cursor.select(
+            &pattern,
+            StepSelection {
+                first_track: 0,
+                last_track: 0,
+                first_step: 0,
+                last_step: 2,
+            }
+        ));
+        assert!(cursor.nudge(&mut pattern, 1));
+        assert_eq!(pattern.step(0, 0), Some(Step::default()));
+        assert_eq!(pattern.step(0, 1), Some(hit(110)));
+        assert_eq!(pattern.step(0, 3), Some(hit(127)));
+        assert_eq!(cursor.selection().first_step, 1);
+        assert!(!cursor.nudge(&mut pattern, -2));
+        assert!(!cursor.nudge(&mut pattern, 13));
+
+        assert!(cursor.toggle(&mut pattern));
+        assert!((1..=3).all(|step| pattern.step(0, step).is_some_and(|step| step.active)));
+        assert!(cursor.toggle(&mut pattern));
+        assert!((1..=3).all(|step| pattern.step(0, step).is_some_and(|step| !step.active)));
+    }
+
+    #[test]
+    fn toggling_steps_on_uses_the_track_default_step() {
+        let mut pattern = Pattern::default();
+        let template = Step {
+            velocity: 72,
+            probability: 50,
+            accent: true,
+            ..Step::default()
+        };
+        assert!(pattern.set_track_default_step(1, template));
+        assert!(!pattern.set_track_default_step(
+            1,
+            Step {
+                probability: MAX_STEP_PROBABILITY + 1,
+                ..template
+            }
+        ));
+        assert!(!pattern.set_track_default_step(TRACK_COUNT, template));
+        assert_eq!(pattern.track_default_step(1), Some(template));
+
+        let mut cursor = StepCursor::new();
+        assert!(cursor.select(
+            &pattern,
+            StepSelection {
+                first_track: 0,
+                last_track: 1,
+                first_step: 0,
+                last_step: 0,
+            }
+        ));
+        assert!(cursor.toggle(&mut pattern));
+        assert_eq!(pattern.step(0, 0), Some(hit(100)));
+        assert_eq!(
+            pattern.step(1, 0),
+            Some(Step {
+                active: true,
+                ..template
+            })
+        );
+    }
+}
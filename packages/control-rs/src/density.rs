@@ -0,0 +1,173 @@
+use crate::{Pattern, Step, TRACK_COUNT};
+
+pub const DEFAULT_ACCENT_VELOCITY: u8 = 100;
+pub const DEFAULT_THIN_VELOCITY: u8 = 64;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DensityReport {
+    pub hits_per_step: Vec<usize>,
+}
+
+impl DensityReport {
+    pub fn total_hits(&self) -> usize {
+        self.hits_per_step.iter().sum()
+    }
+
+    pub fn collisions(&self, step_index: usize) -> usize {
+        self.hits_per_step
+            .get(step_index)
+            .map_or(0, |hits| hits.saturating_sub(1))
+    }
+
+    pub fn colliding_steps(&self) -> Vec<usize> {
+        (0..self.hits_per_step.len())
+            .filter(|step_index| self.collisions(*step_index) > 0)
+            .collect()
+    }
+
+    pub fn busiest_step(&self) -> Option<usize> {
+        self.hits_per_step
+            .iter()
+            .enumerate()
+            .filter(|(_, hits)| **hits > 0)
+            .max_by(|(left_index, left), (right_index, right)| {
+                left.cmp(right).then(right_index.cmp(left_index))
+            })
+            .map(|(step_index, _)| step_index)
+    }
+}
+
+pub fn analyze_density(pattern: &Pattern) -> DensityReport {
+    DensityReport {
+        hits_per_step: (0..pattern.step_count())
+            .map(|step_index| {
+                (0..TRACK_COUNT)
+                    .filter(|track_index| {
+                        pattern
+                            .step(*track_index, step_index)
+                            .is_some_and(|step| step.active)
+                    })
+                    .count()
+            })
+            .collect(),
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ThinSettings {
+    pub accent_velocity: u8,
+    pub max_thinned_velocity: u8,
+}
+
+impl Default for ThinSettings {
+    fn default() -> Self {
+        Self {
+            accent_velocity: DEFAULT_ACCENT_VELOCITY,
+            max_thinned_velocity: DEFAULT_THIN_VELOCITY,
+        }
+    }
+}
+
+pub fn auto_thin(pattern: &mut Pattern, settings: ThinSettings) -> Vec<(usize, usize)> {
+    let mut removed = Vec::new();
+    for step_index in 0..pattern.step_count() {
+        let hits: Vec<(usize, Step)> = (0..TRACK_COUNT)
+            .filter_map(|track_index| {
+                pattern
+                    .step(track_index, step_index)
+                    .filter(|step| step.active)
+                    .map(|step| (track_index, step))
+            })
+            .collect();
+        let accents = hits
+            .iter()
+            .filter(|(_, step)| step.velocity >= settings.accent_velocity)
+            .count();
+        for (track_index, step) in &hits {
+            let is_accent = step.velocity >= settings.accent_velocity;
+            let collides_with_accent = accents > usize::from(is_accent);
+            if !is_accent && collides_with_accent && step.velocity <= settings.max_thinned_velocity
+            {
+                pattern.set_step(
+                    *track_index,
+                    step_index,
+                    Step {
+                        active: false,
+                        ..*step
+                    },
+                );
+                removed.push((*track_index, step_index));
+            }
+        }
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{analyze_density, auto_thin, ThinSettings};
+    use crate::{Pattern, Step};
+
+    fn hit(velocity: u8) -> Step {
+        Step {
+            active: true,
+            velocity,
+            ..Step::default()
+        }
+    }
+
+    #[test]
+    fn density_reports_hits_and_collisions_per_step() {
+        let mut pattern = Pattern::default();
+        pattern.set_step(0, 0, hit(120));
+        pattern.set_step(1, 0, hit(40));
+        pattern.set_step(2, 0, hit(90));
+        pattern.set_step(0, 4, hit(100));
+        pattern.set_step(3, 8, hit(30));
+        pattern.set_step(4, 8, hit(30));
+
+        let report = analyze_density(&pattern);
+        assert_eq!(report.hits_per_step.len(), pattern.step_count());
+        assert_eq!(report.total_hits(), 6);
+        assert_eq!(report.collisions(0), 2);
+        assert_eq!(report.collisions(4), 0);
+        assert_eq!(report.colliding_steps(), vec![0, 8]);
+        assert_eq!(report.busiest_step(), Some(0));
+        assert_eq!(analyze_density(&Pattern::default()).busiest_step(), None);
+    }
+
+    #[test]
+    fn auto_thin_removes_quiet_hits_colliding_with_accents_only() {
+        let mut pattern = Pattern::default();
+        pattern.set_step(0, 0, hit(120));
+        pattern.set_step(1, 0, hit(40));
+        pattern.set_step(2, 0, hit(90));
+        pattern.set_step(3, 8, hit(30));
+        pattern.set_step(4, 8, hit(30));
+        pattern.set_step(5, 12, hit(110));
+        pattern.set_step(6, 12, hit(127));
+
+        let removed = auto_thin(&mut pattern, ThinSettings::default());
+        assert_eq!(removed, vec![(1, 0)]);
+        assert_eq!(
+            pattern.step(1, 0),
+            Some(Step {
+                active: false,
+                velocity: 40,
+                ..Step::default()
+            })
+        );
+        assert_eq!(pattern.step(2, 0), Some(hit(90)));
+        assert_eq!(pattern.step(3, 8), Some(hit(30)));
+        assert_eq!(pattern.step(5, 12), Some(hit(110)));
+
+        let removed = auto_thin(
+            &mut pattern,
+            ThinSettings {
+                accent_velocity: 100,
+                max_thinned_velocity: 99,
+            },
+        );
+        assert_eq!(removed, vec![(2, 0)]);
+    }
+}
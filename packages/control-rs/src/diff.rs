@@ -0,0 +1,1424 @@
+use presets_rs::{
+    AccentCurve, AudioHints, BarMarker, GrooveTemplate, HumanizeDepth, Kit, MasterBus, Metronome,
+    MusicalKey, MuteSpan, ParameterLock, Pattern, PatternStep, ProgramChange, Project, SongEntry,
+    TrackAssignment, TrackChokeTargets, TrackControls, TrackDucking, TrackFreeze, TrackGroup,
+    TrackHumanize, TrackInputs, TrackLabel, TrackMidiOutput, TrackSampleTrim, VariationStep,
+    TRACK_GROUP_COUNT,
+};
+
+use crate::song::Song;
+use crate::{
+    GroupRecall, LockTarget, PatternScale, PatternTempo, PlaybackDirection, RecallState, Step,
+    StepResolution, SwingGrid, TrackRecall, PATTERN_VARIATION_COUNT, STEPS_PER_PATTERN,
+    TRACK_COUNT,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProjectChange {
+    Name(String),
+    ActiveKit(Option<usize>),
+    ActivePattern(Option<usize>),
+    TruncateKits(usize),
+    PushKit(Box<Kit>),
+    KitName {
+        kit_index: usize,
+        name: String,
+    },
+    TrackSample {
+        kit_index: usize,
+        track_index: u8,
+        sample_id: Option<String>,
+    },
+    TrackControls {
+        kit_index: usize,
+        track_index: u8,
+        controls: Option<TrackControls>,
+    },
+    TrackRegion {
+        kit_index: usize,
+        track_index: u8,
+        region: Option<(u64, u64)>,
+    },
+    TrackTrim {
+        kit_index: usize,
+        track_index: u8,
+        trim: Option<TrackSampleTrim>,
+    },
+    TrackDucking {
+        kit_index: usize,
+        track_index: u8,
+        ducking: Option<TrackDucking>,
+    },
+    TrackMidiOutput {
+        kit_index: usize,
+        track_index: u8,
+        output: Option<TrackMidiOutput>,
+    },
+    TrackHumanize {
+        kit_index: usize,
+        track_index: u8,
+        humanize: Option<TrackHumanize>,
+    },
+    TrackLabel {
+        kit_index: usize,
+        track_index: u8,
+        label: Option<TrackLabel>,
+    },
+    TrackChokeTargets {
+        kit_index: usize,
+        track_index: u8,
+        choke: Option<TrackChokeTargets>,
+    },
+    TrackGroup {
+        kit_index: usize,
+        group_index: u8,
+        group: Option<TrackGroup>,
+    },
+    KitMaster {
+        kit_index: usize,
+        master: MasterBus,
+    },
+    TrackOutput {
+        track_index: usize,
+        output_pair: usize,
+    },
+    TrackInputs(TrackInputs),
+    RandomSeed(u64),
+    Humanize(HumanizeDepth),
+    AudioHints(AudioHints),
+    Key(MusicalKey),
+    Metronome(Metronome),
+    FrozenTracks(Vec<TrackFreeze>),
+    ProgramChanges(Vec<ProgramChange>),
+    Grooves(Vec<GrooveTemplate>),
+    Song {
+        entries: Vec<SongEntry>,
+        looping: bool,
+    },
+    TruncatePatterns(usize),
+    PushPattern(Box<Pattern>),
+    PatternName {
+        pattern_index: usize,
+        name: String,
+    },
+    PatternSwing {
+        pattern_index: usize,
+        swing: f32,
+    },
+    PatternGroove {
+        pattern_index: usize,
+        groove: Option<String>,
+    },
+    PatternAccent {
+        pattern_index: usize,
+        accent: AccentCurve,
+    },
+    PatternBars {
+        pattern_index: usize,
+        bar_count: usize,
+    },
+    PatternLength {
+        pattern_index: usize,
+        step_count: usize,
+    },
+    PatternMarkers {
+        pattern_index: usize,
+        markers: Vec<BarMarker>,
+    },
+    PatternScale {
+        pattern_index: usize,
+        scale: PatternScale,
+    },
+    PatternTempo {
+        pattern_index: usize,
+        tempo: Option<PatternTempo>,
+    },
+    PatternVariations {
+        pattern_index: usize,
+        variations: Vec<VariationStep>,
+    },
+    PatternMutes {
+        pattern_index: usize,
+        mutes: Vec<MuteSpan>,
+    },
+    PatternLocks {
+        pattern_index: usize,
+        locks: Vec<ParameterLock>,
+    },
+    PatternDirections {
+        pattern_index: usize,
+        directions: [PlaybackDirection; TRACK_COUNT],
+    },
+    PatternResolutions {
+        pattern_index: usize,
+        resolutions: [StepResolution; TRACK_COUNT],
+    },
+    PatternSwingGrid {
+        pattern_index: usize,
+        swing_grid: SwingGrid,
+    },
+    PatternDefaultSteps {
+        pattern_index: usize,
+        default_steps: [PatternStep; TRACK_COUNT],
+    },
+    PatternStep {
+        pattern_index: usize,
+        track_index: usize,
+        step_index: usize,
+        step: PatternStep,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecallChange {
+    Song(Option<Song>),
+    Tempo(f32),
+    Swing(f32),
+    Groove(Option<GrooveTemplate>),
+    Accent(AccentCurve),
+    Humanize(HumanizeDepth),
+    BarCount(usize),
+    StepCount(usize),
+    Scale(PatternScale),
+    PatternTempo(Option<PatternTempo>),
+    Variation {
+        variation: u8,
+        overrides: Vec<(usize, usize, Step)>,
+    },
+    MuteSpans(Vec<MuteSpan>),
+    ParameterLocks(Vec<ParameterLock>),
+    Directions([PlaybackDirection; TRACK_COUNT]),
+    Resolutions([StepResolution; TRACK_COUNT]),
+    SwingGrid(SwingGrid),
+    DefaultSteps([Step; TRACK_COUNT]),
+    Step {
+        track_index: usize,
+        step_index: usize,
+        step: Step,
+    },
+    Track {
+        track_index: usize,
+        recall: TrackRecall,
+    },
+    Group {
+        group_index: usize,
+        recall: GroupRecall,
+    },
+    MasterBus(MasterBus),
+}
+
+fn track_sample(kit: &Kit, track_index: u8) -> Option<&str> {
+    kit.tracks
+        .iter()
+        .find(|assignment| assignment.track_index == track_index)
+        .map(|assignment| assignment.sample_id.as_str())
+}
+
+fn diff_kit(kit_index: usize, before: &Kit, after: &Kit, changes: &mut Vec<ProjectChange>) {
+    if before.name != after.name {
+        changes.push(ProjectChange::KitName {
+            kit_index,
+            name: after.name.clone(),
+        });
+    }
+
+    for track_index in 0..TRACK_COUNT as u8 {
+        let sample_id = track_sample(after, track_index);
+        if track_sample(before, track_index) != sample_id {
+            changes.push(ProjectChange::TrackSample {
+                kit_index,
+                track_index,
+                sample_id: sample_id.map(str::to_string),
+            });
+        }
+
+        let controls = after.track_controls(track_index);
+        if before.track_controls(track_index) != controls {
+            changes.push(ProjectChange::TrackControls {
+                kit_index,
+                track_index,
+                controls,
+            });
+        }
+
+        let region = after
+            .track_region(track_index)
+            .map(|region| (region.start_frame, region.end_frame));
+        if before
+            .track_region(track_index)
+            .map(|region| (region.start_frame, region.end_frame))
+            != region
+        {
+            changes.push(ProjectChange::TrackRegion {
+                kit_index,
+                track_index,
+                region,
+            });
+        }
+
+        let trim = after.track_trim(track_index);
+        if before.track_trim(track_index) != trim {
+            changes.push(ProjectChange::TrackTrim {
+                kit_index,
+                track_index,
+                trim,
+            });
+        }
+
+        let ducking = after.track_ducking(track_index);
+        if before.track_ducking(track_index) != ducking {
+            changes.push(ProjectChange::TrackDucking {
+                kit_index,
+                track_index,
+                ducking,
+            });
+        }
+
+        let output = after.track_midi_output(track_index);
+        if before.track_midi_output(track_index) != output {
+            changes.push(ProjectChange::TrackMidiOutput {
+                kit_index,
+                track_index,
+                output,
+            });
+        }
+
+        let humanize = after.track_humanize(track_index);
+        if before.track_humanize(track_index) != humanize {
+            changes.push(ProjectChange::TrackHumanize {
+                kit_index,
+                track_index,
+                humanize,
+            });
+        }
+
+        let label = after.track_label(track_index);
+        if before.track_label(track_index) != label {
+            changes.push(ProjectChange::TrackLabel {
+                kit_index,
+                track_index,
+                label: label.cloned(),
+            });
+        }
+
+        let choke = after.track_choke_targets(track_index);
+        if before.track_choke_targets(track_index) != choke {
+            changes.push(ProjectChange::TrackChokeTargets {
+                kit_index,
+                track_index,
+                choke,
+            });
+        }
+    }
+
+    for group_index in 0..TRACK_GROUP_COUNT as u8 {
+        let group = after.track_group(group_index);
+        if before.track_group(group_index) != group {
+            changes.push(ProjectChange::TrackGroup {
+                kit_index,
+                group_index,
+                group: group.cloned(),
+            });
+        }
+    }
+
+    if before.master != after.master {
+        changes.push(ProjectChange::KitMaster {
+            kit_index,
+            master: after.master,
+        });
+    }
+}
+
+fn diff_pattern(
+    pattern_index: usize,
+    before: &Pattern,
+    after: &Pattern,
+    changes: &mut Vec<ProjectChange>,
+) {
+    if before.name != after.name {
+        changes.push(ProjectChange::PatternName {
+            pattern_index,
+            name: after.name.clone(),
+        });
+    }
+    if before.swing != after.swing {
+        changes.push(ProjectChange::PatternSwing {
+            pattern_index,
+            swing: after.swing,
+        });
+    }
+    if before.groove != after.groove {
+        changes.push(ProjectChange::PatternGroove {
+            pattern_index,
+            groove: after.groove.clone(),
+        });
+    }
+    if before.accent != after.accent {
+        changes.push(ProjectChange::PatternAccent {
+            pattern_index,
+            accent: after.accent,
+        });
+    }
+    if before.bar_count != after.bar_count {
+        changes.push(ProjectChange::PatternBars {
+            pattern_index,
+            bar_count: after.bar_count,
+        });
+    }
+    // A bar count change already resets the length to whole bars.
+    if before.step_count() != after.step_count()
+        && (before.bar_count == after.bar_count || after.length_steps.is_some())
+    {
+        changes.push(ProjectChange::PatternLength {
+            pattern_index,
+            step_count: after.step_count(),
+        });
+    }
+    if before.markers != after.markers {
+        changes.push(ProjectChange::PatternMarkers {
+            pattern_index,
+            markers: after.markers.clone(),
+        });
+    }
+    if before.scale != after.scale {
+        changes.push(ProjectChange::PatternScale {
+            pattern_index,
+            scale: after.scale,
+        });
+    }
+    if before.tempo != after.tempo {
+        changes.push(ProjectChange::PatternTempo {
+            pattern_index,
+            tempo: after.tempo,
+        });
+    }
+
+    for track_index in 0..TRACK_COUNT {
+        for step_index in 0..after.step_count() {
+            let step = after.steps[track_index][step_index];
+            if before.steps[track_index][step_index] != step {
+                changes.push(ProjectChange::PatternStep {
+                    pattern_index,
+                    track_index,
+                    step_index,
+                    step,
+                });
+            }
+        }
+    }
+    if before.variations != after.variations {
+        changes.push(ProjectChange::PatternVariations {
+            pattern_index,
+            variations: after.variations.clone(),
+        });
+    }
+    if before.mutes != after.mutes {
+        changes.push(ProjectChange::PatternMutes {
+            pattern_index,
+            mutes: after.mutes.clone(),
+        });
+    }
+    if before.locks != after.locks {
+        changes.push(ProjectChange::PatternLocks {
+            pattern_index,
+            locks: after.locks.clone(),
+        });
+    }
+    if before.directions != after.directions {
+        changes.push(ProjectChange::PatternDirections {
+            pattern_index,
+            directions: after.directions,
+        });
+    }
+    if before.resolutions != after.resolutions {
+        changes.push(ProjectChange::PatternResolutions {
+            pattern_index,
+            resolutions: after.resolutions,
+        });
+    }
+    if before.swing_grid != after.swing_grid {
+        changes.push(ProjectChange::PatternSwingGrid {
+            pattern_index,
+            swing_grid: after.swing_grid,
+        });
+    }
+    if before.default_steps != after.default_steps {
+        changes.push(ProjectChange::PatternDefaultSteps {
+            pattern_index,
+            default_steps: after.default_steps,
+        });
+    }
+}
+
+pub fn diff_projects(before: &Project, after: &Project) -> Vec<ProjectChange> {
+    let mut changes = Vec::new();
+    if before.name != after.name {
+        changes.push(ProjectChange::Name(after.name.clone()));
+    }
+
+    if after.kits.len() < before.kits.len() {
+        changes.push(ProjectChange::TruncateKits(after.kits.len()));
+    }
+    for (kit_index, kit) in after.kits.iter().enumerate() {
+        match before.kits.get(kit_index) {
+            Some(previous) => diff_kit(kit_index, previous, kit, &mut changes),
+            None => changes.push(ProjectChange::PushKit(Box::new(kit.clone()))),
+        }
+    }
+
+    if after.patterns.len() < before.patterns.len() {
+        changes.push(ProjectChange::TruncatePatterns(after.patterns.len()));
+    }
+    for (pattern_index, pattern) in after.patterns.iter().enumerate() {
+        match before.patterns.get(pattern_index) {
+            Some(previous) => diff_pattern(pattern_index, previous, pattern, &mut changes),
+            None => changes.push(ProjectChange::PushPattern(Box::new(pattern.clone()))),
+        }
+    }
+
+    for track_index in 0..TRACK_COUNT {
+        let output_pair = after.output_routing.track_output(track_index);
+        if before.output_routing.track_output(track_index) != output_pair {
+            changes.push(ProjectChange::TrackOutput {
+                track_index,
+                output_pair: output_pair.unwrap_or(0),
+            });
+        }
+    }
+
+    if before.track_inputs != after.track_inputs {
+        changes.push(ProjectChange::TrackInputs(after.track_inputs));
+    }
+    if before.random_seed != after.random_seed {
+        changes.push(ProjectChange::RandomSeed(after.random_seed));
+    }
+    if before.humanize != after.humanize {
+        changes.push(ProjectChange::Humanize(after.humanize));
+    }
+    if before.audio_hints != after.audio_hints {
+        changes.push(ProjectChange::AudioHints(after.audio_hints));
+    }
+    if before.key != after.key {
+        changes.push(ProjectChange::Key(after.key));
+    }
+    if before.metronome != after.metronome {
+        changes.push(ProjectChange::Metronome(after.metronome.clone()));
+    }
+    if before.frozen_tracks != after.frozen_tracks {
+        changes.push(ProjectChange::FrozenTracks(after.frozen_tracks.clone()));
+    }
+    if before.program_changes != after.program_changes {
+        changes.push(ProjectChange::ProgramChanges(after.program_changes.clone()));
+    }
+    if before.grooves != after.grooves {
+        changes.push(ProjectChange::Grooves(after.grooves.clone()));
+    }
+    if before.song != after.song || before.song_loop != after.song_loop {
+        changes.push(ProjectChange::Song {
+            entries: after.song.clone(),
+            looping: after.song_loop,
+        });
+    }
+    if before.active_kit != after.active_kit {
+        changes.push(ProjectChange::ActiveKit(after.active_kit));
+    }
+    if before.active_pattern != after.active_pattern {
+        changes.push(ProjectChange::ActivePattern(after.active_pattern));
+    }
+    changes
+}
+
+fn kit_mut(project: &mut Project, kit_index: usize) -> Result<&mut Kit, String> {
+    project
+        .kits
+        .get_mut(kit_index)
+        .ok_or_else(|| format!("patch kit out of range: {kit_index}"))
+}
+
+fn pattern_mut(project: &mut Project, pattern_index: usize) -> Result<&mut Pattern, String> {
+    project
+        .patterns
+        .get_mut(pattern_index)
+        .ok_or_else(|| format!("patch pattern out of range: {pattern_index}"))
+}
+
+pub fn apply_project_change(project: &mut Project, change: &ProjectChange) -> Result<(), String> {
+    match change {
+        ProjectChange::Name(name) => project.name = name.clone(),
+        ProjectChange::ActiveKit(active_kit) => {
+            if active_kit.is_some_and(|index| index >= project.kits.len()) {
+                return Err(format!("patch active kit out of range: {active_kit:?}"));
+            }
+            project.active_kit = *active_kit;
+        }
+        ProjectChange::ActivePattern(active_pattern) => {
+            if active_pattern.is_some_and(|index| index >= project.patterns.len()) {
+                return Err(format!(
+                    "patch active pattern out of range: {active_pattern:?}"
+                ));
+            }
+            project.active_pattern = *active_pattern;
+        }
+        ProjectChange::TruncateKits(length) => project.kits.truncate(*length),
+        ProjectChange::PushKit(kit) => project.kits.push(kit.as_ref().clone()),
+        ProjectChange::KitName { kit_index, name } => {
+            kit_mut(project, *kit_index)?.name = name.clone()
+        }
+        ProjectChange::TrackSample {
+            kit_index,
+            track_index,
+            sample_id,
+        } => {
+            let kit = kit_mut(project, *kit_index)?;
+            kit.tracks
+                .retain(|assignment| assignment.track_index != *track_index);
+            if let Some(sample_id) = sample_id {
+                kit.tracks.push(TrackAssignment {
+                    track_index: *track_index,
+                    sample_id: sample_id.clone(),
+                });
+                kit.tracks.sort_by_key(|assignment| assignment.track_index);
+            }
+        }
+        ProjectChange::TrackControls {
+            kit_index,
+            track_index,
+            controls,
+        } => {
+            let kit = kit_mut(project, *kit_index)?;
+            match controls {
+                Some(controls) => kit.set_track_controls(*track_index, *controls),
+                None => kit
+                    .controls
+                    .retain(|control| control.track_index != *track_index),
+            }
+        }
+        ProjectChange::TrackRegion {
+            kit_index,
+            track_index,
+            region,
+        } => {
+            let kit = kit_mut(project, *kit_index)?;
+            match region {
+                Some((start_frame, end_frame)) => {
+                    if !kit.set_track_region(*track_index, *start_frame, *end_frame) {
+                        return Err(format!("patch region invalid for track {track_index}"));
+                    }
+                }
+                None => kit.clear_track_region(*track_index),
+            }
+        }
+        ProjectChange::TrackTrim {
+            kit_index,
+            track_index,
+            trim,
+        } => {
+            let kit = kit_mut(project, *kit_index)?;
+            match trim {
+                Some(trim) if trim.track_index == *track_index => {
+                    if !kit.set_track_trim(*trim) {
+                        return Err(format!("patch trim invalid for track {track_index}"));
+                    }
+                }
+                Some(_) => return Err(format!("patch trim track mismatch: {track_index}")),
+                None => kit.clear_track_trim(*track_index),
+            }
+        }
+        ProjectChange::TrackDucking {
+            kit_index,
+            track_index,
+            ducking,
+        } => {
+            let kit = kit_mut(project, *kit_index)?;
+            match ducking {
+                Some(ducking) if ducking.track_index == *track_index => {
+                    if !kit.set_track_ducking(*ducking) {
+                        return Err(format!("patch ducking invalid for track {track_index}"));
+                    }
+                }
+                Some(_) => return Err(format!("patch ducking track mismatch: {track_index}")),
+                None => kit.clear_track_ducking(*track_index),
+            }
+        }
+        ProjectChange::TrackMidiOutput {
+            kit_index,
+            track_index,
+            output,
+        } => {
+            let kit = kit_mut(project, *kit_index)?;
+            match output {
+                Some(output) if output.track_index == *track_index => {
+                    if !kit.set_track_midi_output(*output) {
+                        return Err(format!("patch midi output invalid for track {track_index}"));
+                    }
+                }
+                Some(_) => return Err(format!("patch midi output track mismatch: {track_index}")),
+                None => kit.clear_track_midi_output(*track_index),
+            }
+        }
+        ProjectChange::TrackHumanize {
+            kit_index,
+            track_index,
+            humanize,
+        } => {
+            let kit = kit_mut(project, *kit_index)?;
+            match humanize {
+                Some(humanize) if humanize.track_index == *track_index => {
+                    if !kit.set_track_humanize(*humanize) {
+                        return Err(format!("patch humanize invalid for track {track_index}"));
+                    }
+                }
+                Some(_) => return Err(format!("patch humanize track mismatch: {track_index}")),
+                None => kit.clear_track_humanize(*track_index),
+            }
+        }
+        ProjectChange::TrackLabel {
+            kit_index,
+            track_index,
+            label,
+        } => {
+            let kit = kit_mut(project, *kit_index)?;
+            match label {
+                Some(label) if label.track_index == *track_index => {
+                    if !kit.set_track_label(label.clone()) {
+                        return Err(format!("patch label invalid for track {track_index}"));
+                    }
+                }
+                Some(_) => return Err(format!("patch label track mismatch: {track_index}")),
+                None => kit.clear_track_label(*track_index),
+            }
+        }
+        ProjectChange::TrackChokeTargets {
+            kit_index,
+            track_index,
+            choke,
+        } => {
+            let kit = kit_mut(project, *kit_index)?;
+            match choke {
+                Some(choke) if choke.track_index == *track_index => {
+                    if !kit.set_track_choke_targets(*choke) {
+                        return Err(format!(
+                            "patch choke targets invalid for track {track_index}"
+                        ));
+                    }
+                }
+                Some(_) => {
+                    return Err(format!("patch choke targets track mismatch: {track_index}"))
+                }
+                None => kit.clear_track_choke_targets(*track_index),
+            }
+        }
+        ProjectChange::TrackGroup {
+            kit_index,
+            group_index,
+            group,
+        } => {
+            let kit = kit_mut(project, *kit_index)?;
+            match group {
+                Some(group) if group.group_index == *group_index => {
+                    if !kit.set_track_group(group.clone()) {
+                        return Err(format!("patch group invalid: {group_index}"));
+                    }
+                }
+                Some(_) => return Err(format!("patch group index mismatch: {group_index}")),
+                None => kit.clear_track_group(*group_index),
+            }
+        }
+        ProjectChange::KitMaster { kit_index, master } => {
+            if !kit_mut(project, *kit_index)?.set_master_bus(*master) {
+                return Err(format!("patch master bus invalid for kit {kit_index}"));
+            }
+        }
+        ProjectChange::TrackInputs(inputs) => project.track_inputs = *inputs,
+        ProjectChange::RandomSeed(seed) => project.random_seed = *seed,
+        ProjectChange::Humanize(humanize) => {
+            if !humanize.is_valid() {
+                return Err("patch humanize out of range".to_string());
+            }
+            project.humanize = *humanize;
+        }
+        ProjectChange::AudioHints(hints) => {
+            if !hints.is_valid() {
+                return Err("patch audio hints out of range".to_string());
+            }
+            project.audio_hints = *hints;
+        }
+        ProjectChange::Key(key) => project.key = *key,
+        ProjectChange::Metronome(metronome) => project.metronome = metronome.clone(),
+        ProjectChange::FrozenTracks(frozen_tracks) => project.frozen_tracks = frozen_tracks.clone(),
+        ProjectChange::Song { entries, looping } => {
+            if !project.set_song(entries) {
+                return Err("patch song entry out of range".to_string());
+            }
+            project.song_loop = *looping;
+        }
+        ProjectChange::ProgramChanges(program_changes) => {
+            project.program_changes.clear();
+            for change in program_changes {
+                if !project.set_program_change(*change) {
+                    return Err(format!("patch program change invalid: {}", change.program));
+                }
+            }
+        }
+        ProjectChange::Grooves(grooves) => {
+            project.grooves.clear();
+            for groove in grooves {
+                if !project.set_groove(groove.clone()) {
+                    return Err(format!("patch groove invalid: {}", groove.name));
+                }
+            }
+        }
+        ProjectChange::TrackOutput {
+            track_index,
+            output_pair,
+        } => {
+            if !project
+                .output_routing
+                .set_track_output(*track_index, *output_pair)
+            {
+                return Err(format!(
+                    "patch output route out of range: track={track_index}, pair={output_pair}"
+                ));
+            }
+        }
+        ProjectChange::TruncatePatterns(length) => project.patterns.truncate(*length),
+        ProjectChange::PushPattern(pattern) => project.patterns.push(pattern.as_ref().clone()),
+        ProjectChange::PatternName {
+            pattern_index,
+            name,
+        } => pattern_mut(project, *pattern_index)?.name = name.clone(),
+        ProjectChange::PatternSwing {
+            pattern_index,
+            swing,
+        } => pattern_mut(project, *pattern_index)?.set_swing(*swing),
+        ProjectChange::PatternGroove {
+            pattern_index,
+            groove,
+        } => pattern_mut(project, *pattern_index)?.groove = groove.clone(),
+        ProjectChange::PatternAccent {
+            pattern_index,
+            accent,
+        } => {
+            if !accent.is_valid() {
+                return Err(format!("patch accent out of range: {pattern_index}"));
+            }
+            pattern_mut(project, *pattern_index)?.accent = *accent;
+        }
+        ProjectChange::PatternBars {
+            pattern_index,
+            bar_count,
+        } => {
+            if !pattern_mut(project, *pattern_index)?.set_bar_count(*bar_count) {
+                return Err(format!("patch bar count out of range: {bar_count}"));
+            }
+        }
+        ProjectChange::PatternLength {
+            pattern_index,
+            step_count,
+        } => {
+            if !pattern_mut(project, *pattern_index)?.set_step_count(*step_count) {
+                return Err(format!("patch pattern length out of range: {step_count}"));
+            }
+        }
+        ProjectChange::PatternScale {
+            pattern_index,
+            scale,
+        } => pattern_mut(project, *pattern_index)?.scale = *scale,
+        ProjectChange::PatternTempo {
+            pattern_index,
+            tempo,
+        } => {
+            if tempo.is_some_and(|tempo| !tempo.is_valid()) {
+                return Err("patch pattern tempo out of range".to_string());
+            }
+            pattern_mut(project, *pattern_index)?.tempo = *tempo;
+        }
+        ProjectChange::PatternVariations {
+            pattern_index,
+            variations,
+        } => {
+            let pattern = pattern_mut(project, *pattern_index)?;
+            pattern.variations.clear();
+            for variation in variations {
+                if !pattern.set_variation_step(
+                    variation.variation,
+                    usize::from(variation.track_index),
+                    usize::from(variation.step_index),
+                    Some(variation.step),
+                ) {
+                    return Err("patch pattern variation out of range".to_string());
+                }
+            }
+        }
+        ProjectChange::PatternMutes {
+            pattern_index,
+            mutes,
+        } => {
+            let pattern = pattern_mut(project, *pattern_index)?;
+            pattern.mutes.clear();
+            for span in mutes {
+                if !pattern.set_mute_span(
+                    usize::from(span.track_index),
+                    span.first_bar,
+                    span.last_bar,
+                ) {
+                    return Err("patch pattern mute span out of range".to_string());
+                }
+            }
+        }
+        ProjectChange::PatternLocks {
+            pattern_index,
+            locks,
+        } => {
+            let pattern = pattern_mut(project, *pattern_index)?;
+            pattern.locks.clear();
+            for lock in locks {
+                if !pattern.set_parameter_lock(*lock) {
+                    return Err("patch pattern parameter lock out of range".to_string());
+                }
+            }
+        }
+        ProjectChange::PatternDirections {
+            pattern_index,
+            directions,
+        } => pattern_mut(project, *pattern_index)?.directions = *directions,
+        ProjectChange::PatternResolutions {
+            pattern_index,
+            resolutions,
+        } => pattern_mut(project, *pattern_index)?.resolutions = *resolutions,
+        ProjectChange::PatternSwingGrid {
+            pattern_index,
+            swing_grid,
+        } => pattern_mut(project, *pattern_index)?.swing_grid = *swing_grid,
+        ProjectChange::PatternDefaultSteps {
+            pattern_index,
+            default_steps,
+        } => pattern_mut(project, *pattern_index)?.default_steps = *default_steps,
+        ProjectChange::PatternMarkers {
+            pattern_index,
+            markers,
+        } => {
+            let pattern = pattern_mut(project, *pattern_index)?;
+            pattern.markers.clear();
+            for marker in markers {
+                if !pattern.set_bar_marker(marker.bar, &marker.label) {
+                    return Err(format!("patch marker bar out of range: {}", marker.bar));
+                }
+            }
+        }
+        ProjectChange::PatternStep {
+            pattern_index,
+            track_index,
+            step_index,
+            step,
+        } => {
+            if !pattern_mut(project, *pattern_index)?.set_step(*track_index, *step_index, *step) {
+                return Err(format!(
+                    "patch step out of range: track={track_index}, step={step_index}"
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn apply_project_patch(project: &mut Project, changes: &[ProjectChange]) -> Result<(), String> {
+    for change in changes {
+        apply_project_change(project, change)?;
+    }
+    Ok(())
+}
+
+pub fn diff_recall_states(before: &RecallState, after: &RecallState) -> Vec<RecallChange> {
+    let mut changes = Vec::new();
+    // The song reloads its first pattern, so it goes before the pattern changes.
+    if before.sequencer.song() != after.sequencer.song() {
+        changes.push(RecallChange::Song(after.sequencer.song().cloned()));
+    }
+    let bpm = after.sequencer.transport().bpm();
+    if before.sequencer.transport().bpm() != bpm {
+        changes.push(RecallChange::Tempo(bpm));
+    }
+    if before.sequencer.swing() != after.sequencer.swing() {
+        changes.push(RecallChange::Swing(after.sequencer.swing()));
+    }
+    if before.sequencer.groove() != after.sequencer.groove() {
+        changes.push(RecallChange::Groove(after.sequencer.groove().cloned()));
+    }
+    let accent = after.sequencer.pattern().accent_curve();
+    if before.sequencer.pattern().accent_curve() != accent {
+        changes.push(RecallChange::Accent(accent));
+    }
+    if before.sequencer.humanize() != after.sequencer.humanize() {
+        changes.push(RecallChange::Humanize(after.sequencer.humanize()));
+    }
+    let bar_count = after.sequencer.pattern().bar_count();
+    if before.sequencer.pattern().bar_count() != bar_count {
+        changes.push(RecallChange::BarCount(bar_count));
+    }
+    let step_count = after.sequencer.pattern().step_count();
+    if before.sequencer.pattern().step_count() != step_count
+        && (before.sequencer.pattern().bar_count() == bar_count
+            || step_count != bar_count * STEPS_PER_PATTERN)
+    {
+        changes.push(RecallChange::StepCount(step_count));
+    }
+    let scale = after.sequencer.pattern().scale();
+    if before.sequencer.pattern().scale() != scale {
+        changes.push(RecallChange::Scale(scale));
+    }
+    let tempo = after.sequencer.pattern().tempo();
+    if before.sequencer.pattern().tempo() != tempo {
+        changes.push(RecallChange::PatternTempo(tempo));
+    }
+
+    for track_index in 0..TRACK_COUNT {
+        for step_index in 0..after.sequencer.pattern().step_count() {
+            let step = after
+                .sequencer
+                .pattern()
+                .step(track_index, step_index)
+                .unwrap_or_default();
+            if before
+                .sequencer
+                .pattern()
+                .step(track_index, step_index)
+                .unwrap_or_default()
+                != step
+            {
+                changes.push(RecallChange::Step {
+                    track_index,
+                    step_index,
+                    step,
+                });
+            }
+        }
+    }
+
+    for variation in 1..PATTERN_VARIATION_COUNT as u8 {
+        let overrides = after.sequencer.pattern().variation_overrides(variation);
+        if before.sequencer.pattern().variation_overrides(variation) != overrides {
+            changes.push(RecallChange::Variation {
+                variation,
+                overrides,
+            });
+        }
+    }
+
+    let mutes = after.sequencer.pattern().mute_spans();
+    if before.sequencer.pattern().mute_spans() != mutes {
+        changes.push(RecallChange::MuteSpans(mutes.to_vec()));
+    }
+
+    let locks = after.sequencer.pattern().parameter_locks();
+    if before.sequencer.pattern().parameter_locks() != locks {
+        changes.push(RecallChange::ParameterLocks(locks.to_vec()));
+    }
+
+    let directions: [PlaybackDirection; TRACK_COUNT] = std::array::from_fn(|track_index| {
+        after
+            .sequencer
+            .pattern()
+            .track_direction(track_index)
+            .unwrap_or_default()
+    });
+    if (0..TRACK_COUNT).any(|track_index| {
+        before.sequencer.pattern().track_direction(track_index) != Some(directions[track_index])
+    }) {
+        changes.push(RecallChange::Directions(directions));
+    }
+
+    let resolutions: [StepResolution; TRACK_COUNT] = std::array::from_fn(|track_index| {
+        after
+            .sequencer
+            .pattern()
+            .track_resolution(track_index)
+            .unwrap_or_default()
+    });
+    if (0..TRACK_COUNT).any(|track_index| {
+        before.sequencer.pattern().track_resolution(track_index) != Some(resolutions[track_index])
+    }) {
+        changes.push(RecallChange::Resolutions(resolutions));
+    }
+
+    let swing_grid = after.sequencer.pattern().swing_grid();
+    if before.sequencer.pattern().swing_grid() != swing_grid {
+        changes.push(RecallChange::SwingGrid(swing_grid));
+    }
+
+    let default_steps: [Step; TRACK_COUNT] = std::array::from_fn(|track_index| {
+        after
+            .sequencer
+            .pattern()
+            .track_default_step(track_index)
+            .unwrap_or_default()
+    });
+    if (0..TRACK_COUNT).any(|track_index| {
+        before.sequencer.pattern().track_default_step(track_index)
+            != Some(default_steps[track_index])
+    }) {
+        changes.push(RecallChange::DefaultSteps(default_steps));
+    }
+
+    for (track_index, recall) in after.track_recall.iter().enumerate() {
+        if before.track_recall[track_index] != *recall {
+            changes.push(RecallChange::Track {
+                track_index,
+                recall: recall.clone(),
+            });
+        }
+    }
+    for (group_index, recall) in after.group_recall.iter().enumerate() {
+        if before.group_recall[group_index] != *recall {
+            changes.push(RecallChange::Group {
+                group_index,
+                recall: *recall,
+            });
+        }
+    }
+    if before.master_bus != after.master_bus {
+        changes.push(RecallChange::MasterBus(after.master_bus));
+    }
+    changes
+}
+
+pub fn apply_recall_change(state: &mut RecallState, change: &RecallChange) -> Result<(), String> {
+    match change {
+        RecallChange::Song(song) => {
+            if !state.sequencer.set_song(song.clone()) {
+                return Err("recall song has no entries".to_string());
+            }
+        }
+        RecallChange::Tempo(bpm) => state.sequencer.set_tempo_bpm(*bpm),
+        RecallChange::Swing(swing) => state.sequencer.set_swing(*swing),
+        RecallChange::Humanize(humanize) => {
+            if !state.sequencer.set_humanize(*humanize) {
+                return Err("recall humanize out of range".to_string());
+            }
+        }
+        RecallChange::Groove(groove) => {
+            if !state.sequencer.set_groove(groove.clone()) {
+                return Err("recall groove out of range".to_string());
+            }
+        }
+        RecallChange::Accent(accent) => {
+            if !state.sequencer.pattern_mut().set_accent_curve(*accent) {
+                return Err("recall accent out of range".to_string());
+            }
+        }
+        RecallChange::BarCount(bar_count) => {
+            if !state.sequencer.pattern_mut().set_bar_count(*bar_count) {
+                return Err(format!("recall bar count out of range: {bar_count}"));
+            }
+        }
+        RecallChange::StepCount(step_count) => {
+            if !state.sequencer.pattern_mut().set_step_count(*step_count) {
+                return Err(format!("recall step count out of range: {step_count}"));
+            }
+        }
+        RecallChange::Scale(scale) => state.sequencer.pattern_mut().set_scale(*scale),
+        RecallChange::PatternTempo(tempo) => {
+            if !state.sequencer.pattern_mut().set_tempo(*tempo) {
+                return Err("recall pattern tempo out of range".to_string());
+            }
+        }
+        RecallChange::Variation {
+            variation,
+            overrides,
+        } => {
+            let pattern = state.sequencer.pattern_mut();
+            pattern.clear_variation(*variation);
+            for (track_index, step_index, step) in overrides {
+                if !pattern.set_variation_step(*variation, *track_index, *step_index, Some(*step)) {
+                    return Err(format!(
+                        "recall variation out of range: track={track_index}, step={step_index}"
+                    ));
+                }
+            }
+        }
+        RecallChange::MuteSpans(mutes) => {
+            if !state.sequencer.pattern_mut().set_mute_spans(mutes) {
+                return Err("recall mute span out of range".to_string());
+            }
+        }
+        RecallChange::ParameterLocks(locks) => {
+            if !state.sequencer.pattern_mut().set_parameter_locks(locks) {
+                return Err("recall parameter lock out of range".to_string());
+            }
+        }
+        RecallChange::Directions(directions) => {
+            for (track_index, direction) in directions.iter().enumerate() {
+                state
+                    .sequencer
+                    .pattern_mut()
+                    .set_track_direction(track_index, *direction);
+            }
+        }
+        RecallChange::Resolutions(resolutions) => {
+            for (track_index, resolution) in resolutions.iter().enumerate() {
+                state
+                    .sequencer
+                    .pattern_mut()
+                    .set_track_resolution(track_index, *resolution);
+            }
+        }
+        RecallChange::SwingGrid(swing_grid) => {
+            state.sequencer.pattern_mut().set_swing_grid(*swing_grid)
+        }
+        RecallChange::DefaultSteps(default_steps) => {
+            for (track_index, step) in default_steps.iter().enumerate() {
+                state
+                    .sequencer
+                    .pattern_mut()
+                    .set_track_default_step(track_index, *step);
+            }
+        }
+        RecallChange::Step {
+            track_index,
+            step_index,
+            step,
+        } => {
+            if !state
+                .sequencer
+                .pattern_mut()
+                .set_step(*track_index, *step_index, *step)
+            {
+                return Err(format!(
+                    "patch step out of range: track={track_index}, step={step_index}"
+                ));
+            }
+        }
+        RecallChange::Track {
+            track_index,
+            recall,
+        } => {
+            let slot = state
+                .track_recall
+                .get_mut(*track_index)
+                .ok_or_else(|| format!("patch track out of range: {track_index}"))?;
+            *slot = recall.clone();
+            state
+                .sequencer
+                .set_track_choke_group(*track_index, recall.choke_group);
+            if !state
+                .sequencer
+                .set_track_humanize(*track_index, recall.humanize)
+            {
+                return Err(format!("patch humanize out of range: {track_index}"));
+            }
+            for target in LockTarget::ALL {
+                state
+                    .sequencer
+                    .set_track_lock_base(*track_index, target, recall.lock_base(target));
+            }
+        }
+        RecallChange::Group {
+            group_index,
+            recall,
+        } => {
+            *state
+                .group_recall
+                .get_mut(*group_index)
+                .ok_or_else(|| format!("patch group out of range: {group_index}"))? = *recall;
+        }
+        RecallChange::MasterBus(master) => {
+            if !master.is_valid() {
+                return Err("recall master bus out of range".to_string());
+            }
+            state.master_bus = *master;
+        }
+    }
+    Ok(())
+}
+
+pub fn apply_recall_patch(state: &mut RecallState, changes: &[RecallChange]) -> Result<(), String> {
+    for change in changes {
+        apply_recall_change(state, change)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use abi_rs::{FfParameterUpdate, FF_PARAM_GLOBAL_LIMITER_CEILING};
+    use presets_rs::{
+        AccentCurve, Kit, MasterBus, Pattern, PatternStep, Project, StepResolution,
+        TrackAssignment, TrackControls, TrackDucking, TrackSampleTrim,
+    };
+
+    use super::{
+        apply_project_patch, apply_recall_patch, diff_projects, diff_recall_states, ProjectChange,
+        RecallChange,
+    };
+    use crate::recall_state_from_project;
+
+    fn project() -> Project {
+        let mut kit = Kit::default();
+        kit.add_assignment(TrackAssignment {
+            track_index: 0,
+            sample_id: "kick".to_string(),
+        });
+        Project {
+            name: "sync".to_string(),
+            kits: vec![kit],
+            active_kit: Some(0),
+            patterns: vec![Pattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        }
+    }
+
+    #[test]
+    fn project_patch_is_minimal_and_round_trips() {
+        let before = project();
+        assert!(diff_projects(&before, &before).is_empty());
+
+        let mut after = before.clone();
+        after.patterns[0].set_step(
+            2,
+            5,
+            PatternStep {
+                active: true,
+                velocity: 80,
+                ..PatternStep::default()
+            },
+        );
+        after.kits[0].tracks.clear();
+        after.kits[0].set_track_controls(
+            1,
+            TrackControls {
+                pan: -0.5,
+                ..TrackControls::default()
+            },
+        );
+        after.patterns.push(Pattern::default());
+        after.active_pattern = Some(1);
+        after.output_routing.set_track_output(4, 1);
+        after.random_seed = 99;
+        after.audio_hints.sample_rate_hz = Some(96_000);
+        after.kits[0].set_track_ducking(TrackDucking {
+            track_index: 3,
+            source_track: 0,
+            depth: 0.5,
+            attack_ms: 2.0,
+            release_ms: 120.0,
+        });
+        after.kits[0].set_track_trim(TrackSampleTrim {
+            track_index: 2,
+            start: 0.1,
+            end: 0.9,
+            reverse: true,
+        });
+        after.patterns[0].set_mute_span(1, 0, 0);
+        after.patterns[0].set_track_default_step(
+            2,
+            PatternStep {
+                velocity: 64,
+                ..PatternStep::default()
+            },
+        );
+        after.kits[0].set_master_bus(MasterBus {
+            compressor_amount: 0.3,
+            ..MasterBus::default()
+        });
+
+        let changes = diff_projects(&before, &after);
+        assert_eq!(changes.len(), 13);
+        assert!(matches!(
+            changes[0],
+            ProjectChange::TrackSample {
+                sample_id: None,
+                ..
+            }
+        ));
+
+        let mut patched = before.clone();
+        apply_project_patch(&mut patched, &changes).expect("patch");
+        assert_eq!(patched, after);
+
+        let mut shrunk = after.clone();
+        shrunk.patterns.truncate(1);
+        shrunk.active_pattern = Some(0);
+        let mut patched = after.clone();
+        apply_project_patch(&mut patched, &diff_projects(&after, &shrunk)).expect("shrink");
+        assert_eq!(patched, shrunk);
+    }
+
+    #[test]
+    fn recall_patch_updates_sequencer_and_tracks() {
+        let before = project();
+        let mut after = before.clone();
+        after.patterns[0].set_swing(0.2);
+        after.patterns[0].accent = AccentCurve::flat(12);
+        after.patterns[0].set_mute_span(5, 0, 0);
+        after.patterns[0].set_track_resolution(2, StepResolution::SixteenthTriplet);
+        after.patterns[0].set_track_default_step(
+            4,
+            PatternStep {
+                velocity: 70,
+                gate: 50,
+                ..PatternStep::default()
+            },
+        );
+        after.kits[0].set_track_controls(
+            3,
+            TrackControls {
+                choke_group: Some(1),
+                ..TrackControls::default()
+            },
+        );
+        after.kits[0].set_master_bus(MasterBus {
+            limiter_ceiling_db: -3.0,
+            ..MasterBus::default()
+        });
+
+        let mut state = recall_state_from_project(&before, 48_000).expect("before");
+        let mut target = recall_state_from_project(&after, 48_000).expect("after");
+        target.sequencer_mut().set_tempo_bpm(140.0);
+
+        let changes = diff_recall_states(&state, &target);
+        assert_eq!(changes.len(), 8);
+        assert_eq!(changes[0], RecallChange::Tempo(140.0));
+        apply_recall_patch(&mut state, &changes).expect("patch");
+
+        assert!(diff_recall_states(&state, &target).is_empty());
+        assert_eq!(state.to_engine_recall(), target.to_engine_recall());
+        assert_eq!(state.sequencer().swing(), 0.2);
+        assert_eq!(state.sequencer().pattern().accent_amount(), 12);
+        assert_eq!(
+            state.sequencer().pattern().track_resolution(2),
+            Some(StepResolution::SixteenthTriplet)
+        );
+        assert_eq!(state.sequencer().pattern().default_hit(4).gate, 50);
+        assert!(state
+            .to_engine_recall()
+            .parameter_updates
+            .contains(&FfParameterUpdate {
+                parameter_id: FF_PARAM_GLOBAL_LIMITER_CEILING,
+                normalized_value: 0.75,
+                ramp_samples: 0,
+                reserved: 0,
+            }));
+    }
+
+    #[test]
+    fn pattern_length_changes_patch_projects_and_recall_state() {
+        let before = project();
+        let mut state = recall_state_from_project(&before, 48_000).expect("before");
+        let mut patched = before.clone();
+        for step_count in [24, 32, 12, 16] {
+            let mut after = patched.clone();
+            assert!(after.patterns[0].set_step_count(step_count));
+            let changes = diff_projects(&patched, &after);
+            apply_project_patch(&mut patched, &changes).expect("patch");
+            assert_eq!(patched, after);
+
+            let target = recall_state_from_project(&after, 48_000).expect("after");
+            let changes = diff_recall_states(&state, &target);
+            apply_recall_patch(&mut state, &changes).expect("recall patch");
+            assert_eq!(state.sequencer().pattern().step_count(), step_count);
+            assert!(diff_recall_states(&state, &target).is_empty());
+        }
+    }
+}
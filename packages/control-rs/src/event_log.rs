@@ -0,0 +1,141 @@
+use abi_rs::FfParameterUpdate;
+
+use crate::StepTriggerEvent;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoggedEvent {
+    Trigger(StepTriggerEvent),
+    Parameter {
+        timeline_sample: u64,
+        update: FfParameterUpdate,
+    },
+}
+
+impl LoggedEvent {
+    pub fn timeline_sample(&self) -> u64 {
+        match self {
+            LoggedEvent::Trigger(event) => event.timeline_sample,
+            LoggedEvent::Parameter {
+                timeline_sample, ..
+            } => *timeline_sample,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        match self {
+            LoggedEvent::Trigger(event) => format!(
+                "{{\"kind\":\"trigger\",\"timeline_sample\":{},\"block_offset\":{},\"source\":{},\"track\":{},\"step\":{},\"velocity\":{},\"choke_group\":{}}}",
+                event.timeline_sample,
+                event.block_offset,
+                event.source.id(),
+                event.track_index,
+                event.step_index,
+                event.velocity,
+                event
+                    .choke_group
+                    .map(|group| group.to_string())
+                    .unwrap_or_else(|| "null".to_string())
+            ),
+            LoggedEvent::Parameter {
+                timeline_sample,
+                update,
+            } => format!(
+                "{{\"kind\":\"parameter\",\"timeline_sample\":{},\"parameter_id\":{},\"normalized_value\":{:.6},\"ramp_samples\":{}}}",
+                timeline_sample, update.parameter_id, update.normalized_value, update.ramp_samples
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EventLog {
+    events: Vec<LoggedEvent>,
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn events(&self) -> &[LoggedEvent] {
+        &self.events
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    pub fn record_triggers(&mut self, events: &[StepTriggerEvent]) {
+        self.events
+            .extend(events.iter().copied().map(LoggedEvent::Trigger));
+    }
+
+    pub fn record_parameter_updates(
+        &mut self,
+        timeline_sample: u64,
+        updates: &[FfParameterUpdate],
+    ) {
+        self.events
+            .extend(updates.iter().map(|update| LoggedEvent::Parameter {
+                timeline_sample,
+                update: *update,
+            }));
+    }
+
+    pub fn to_jsonl(&self) -> String {
+        let mut ordered: Vec<&LoggedEvent> = self.events.iter().collect();
+        ordered.sort_by_key(|event| event.timeline_sample());
+        ordered
+            .into_iter()
+            .map(|event| event.to_json() + "\n")
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use abi_rs::FfParameterUpdate;
+
+    use super::EventLog;
+    use crate::StepTriggerEvent;
+
+    #[test]
+    fn session_events_export_as_timeline_ordered_jsonl() {
+        let mut log = EventLog::new();
+        log.record_triggers(&[StepTriggerEvent {
+            track_index: 2,
+            step_index: 4,
+            velocity: 100,
+            gate: crate::MAX_STEP_GATE,
+            choke_group: Some(1),
+            timeline_sample: 24_000,
+            block_offset: 64,
+            source: crate::FfEventSource::NoteRepeat,
+        }]);
+        log.record_parameter_updates(
+            0,
+            &[FfParameterUpdate {
+                parameter_id: 0x1000,
+                normalized_value: 0.5,
+                ramp_samples: 32,
+                reserved: 0,
+            }],
+        );
+        assert_eq!(log.len(), 2);
+        assert_eq!(
+            log.to_jsonl(),
+            "{\"kind\":\"parameter\",\"timeline_sample\":0,\"parameter_id\":4096,\"normalized_value\":0.500000,\"ramp_samples\":32}\n\
+             {\"kind\":\"trigger\",\"timeline_sample\":24000,\"block_offset\":64,\"source\":3,\"track\":2,\"step\":4,\"velocity\":100,\"choke_group\":1}\n"
+        );
+        log.clear();
+        assert!(log.to_jsonl().is_empty());
+    }
+}
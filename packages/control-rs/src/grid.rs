@@ -0,0 +1,198 @@
+use crate::{LockTarget, Sequencer, STEPS_PER_PATTERN, TRACK_COUNT};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct GridCell {
+    pub step_index: usize,
+    pub active: bool,
+    pub velocity: u8,
+    pub locked: bool,
+    pub playhead: bool,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct GridState {
+    pub active_track: usize,
+    pub page: usize,
+    pub page_count: usize,
+    pub playhead_step: Option<usize>,
+    pub cells: [GridCell; STEPS_PER_PATTERN],
+    pub track_muted: [bool; TRACK_COUNT],
+}
+
+impl GridState {
+    pub fn playhead_page(&self) -> Option<usize> {
+        self.playhead_step.map(|step| step / STEPS_PER_PATTERN)
+    }
+
+    pub fn changed_cells(&self, previous: &GridState) -> Vec<usize> {
+        (0..STEPS_PER_PATTERN)
+            .filter(|cell| self.cells[*cell] != previous.cells[*cell])
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GridView {
+    active_track: usize,
+    page: usize,
+    follow_playhead: bool,
+    state: GridState,
+}
+
+impl Default for GridView {
+    fn default() -> Self {
+        Self {
+            active_track: 0,
+            page: 0,
+            follow_playhead: true,
+            state: GridState::default(),
+        }
+    }
+}
+
+impl GridView {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self) -> &GridState {
+        &self.state
+    }
+
+    pub fn active_track(&self) -> usize {
+        self.active_track
+    }
+
+    pub fn set_active_track(&mut self, track_index: usize) -> bool {
+        if track_index >= TRACK_COUNT {
+            return false;
+        }
+        self.active_track = track_index;
+        true
+    }
+
+    pub fn follow_playhead(&self) -> bool {
+        self.follow_playhead
+    }
+
+    pub fn set_follow_playhead(&mut self, follow: bool) {
+        self.follow_playhead = follow;
+    }
+
+    // Picking a page by hand stops the view from following the playhead.
+    pub fn set_page(&mut self, sequencer: &Sequencer, page: usize) -> bool {
+        if page >= sequencer.pattern().bar_count() {
+            return false;
+        }
+        self.page = page;
+        self.follow_playhead = false;
+        true
+    }
+
+    // Call once per processed block; returns whether any LED-visible state
+    // changed so controller profiles only resend when needed.
+    pub fn update(&mut self, sequencer: &Sequencer) -> bool {
+        let pattern = sequencer.pattern();
+        let playhead_step = sequencer
+            .transport()
+            .is_playing()
+            .then(|| sequencer.track_play_step(self.active_track))
+            .flatten();
+        if let Some(step) = playhead_step.filter(|_| self.follow_playhead) {
+            self.page = step / STEPS_PER_PATTERN;
+        }
+        self.page = self.page.min(pattern.bar_count() - 1);
+
+        let first_step = self.page * STEPS_PER_PATTERN;
+        let state = GridState {
+            active_track: self.active_track,
+            page: self.page,
+            page_count: pattern.bar_count(),
+            playhead_step,
+            cells: std::array::from_fn(|cell| {
+                let step_index = first_step + cell;
+                let step = pattern
+                    .playing_step(self.active_track, step_index)
+                    .unwrap_or_default();
+                GridCell {
+                    step_index,
+                    active: step.active,
+                    velocity: step.velocity,
+                    locked: LockTarget::ALL.iter().any(|target| {
+                        pattern
+                            .parameter_lock(self.active_track, step_index, *target)
+                            .is_some()
+                    }),
+                    playhead: playhead_step == Some(step_index),
+                }
+            }),
+            track_muted: std::array::from_fn(|track_index| sequencer.track_muted(track_index)),
+        };
+        let changed = state != self.state;
+        self.state = state;
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GridView;
+    use crate::{LockTarget, ParameterLock, Sequencer, Step, STEPS_PER_PATTERN, TRACK_COUNT};
+
+    #[test]
+    fn grid_follows_the_active_track_playhead_across_pages() {
+        let mut sequencer = Sequencer::new(48_000);
+        let pattern = sequencer.pattern_mut();
+        assert!(pattern.set_bar_count(2));
+        pattern.set_step(
+            2,
+            17,
+            Step {
+                active: true,
+                velocity: 90,
+                ..Step::default()
+            },
+        );
+        assert!(pattern.set_parameter_lock(ParameterLock {
+            track_index: 2,
+            step_index: 17,
+            target: LockTarget::Pitch,
+            value: 0.75,
+        }));
+
+        let mut view = GridView::new();
+        assert!(!view.set_active_track(TRACK_COUNT));
+        assert!(view.set_active_track(2));
+        assert!(view.update(&sequencer));
+        assert_eq!(view.state().playhead_step, None);
+        assert_eq!(view.state().page_count, 2);
+        assert!(!view.update(&sequencer));
+
+        sequencer.start();
+        sequencer.process_block(6_000 * STEPS_PER_PATTERN as u32 + 100);
+        assert!(view.update(&sequencer));
+        let state = view.state();
+        assert_eq!((state.page, state.playhead_step), (1, Some(16)));
+        assert_eq!(state.playhead_page(), Some(1));
+        let cell = state.cells[1];
+        assert!(cell.active && cell.locked && !cell.playhead);
+        assert_eq!((cell.step_index, cell.velocity), (17, 90));
+        assert!(state.cells[0].playhead);
+
+        let previous = view.state().clone();
+        sequencer.process_block(6_000);
+        assert!(view.update(&sequencer));
+        assert!(view.state().cells[1].playhead);
+        assert_eq!(view.state().changed_cells(&previous), vec![0, 1]);
+
+        assert!(view.set_page(&sequencer, 0));
+        assert!(!view.set_page(&sequencer, 2));
+        sequencer.process_block(6_000);
+        view.update(&sequencer);
+        assert_eq!(
+            (view.state().page, view.state().playhead_step),
+            (0, Some(18))
+        );
+        assert!(view.state().cells.iter().all(|cell| !cell.playhead));
+    }
+}
@@ -0,0 +1,609 @@
+use crate::{Sequencer, SequencerPosition, STEPS_PER_BEAT, STEPS_PER_PATTERN, TICKS_PER_BEAT};
+
+#[cfg(feature = "jack")]
+pub use backend::JackBackend;
+
+pub const JACK_BEATS_PER_BAR: f32 = (STEPS_PER_PATTERN / STEPS_PER_BEAT) as f32;
+pub const JACK_BEAT_TYPE: f32 = 4.0;
+pub const JACK_RELOCATE_TOLERANCE_FRAMES: u64 = 1;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum JackTransportState {
+    Stopped,
+    Rolling,
+    Starting,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum JackTransportRole {
+    TimebaseMaster,
+    Follower,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JackPosition {
+    pub frame: u64,
+    pub frame_rate: u32,
+    pub bar: i32,
+    pub beat: i32,
+    pub tick: i32,
+    pub bar_start_tick: f64,
+    pub beats_per_bar: f32,
+    pub beat_type: f32,
+    pub ticks_per_beat: f64,
+    pub beats_per_minute: f64,
+}
+
+pub fn jack_position_from_sequencer(sequencer: &Sequencer) -> JackPosition {
+    let position = sequencer.position();
+    let ticks_per_bar = f64::from(JACK_BEATS_PER_BAR) * f64::from(TICKS_PER_BEAT);
+
+    JackPosition {
+        frame: position.timeline_sample,
+        frame_rate: sequencer.sample_rate_hz(),
+        bar: i32::try_from(position.bar + 1).unwrap_or(i32::MAX),
+        beat: position.beat as i32 + 1,
+        tick: position.tick as i32,
+        bar_start_tick: position.bar as f64 * ticks_per_bar,
+        beats_per_bar: JACK_BEATS_PER_BAR,
+        beat_type: JACK_BEAT_TYPE,
+        ticks_per_beat: f64::from(TICKS_PER_BEAT),
+        beats_per_minute: f64::from(sequencer.transport().bpm()),
+    }
+}
+
+pub fn sequencer_position_from_jack(position: &JackPosition) -> Option<SequencerPosition> {
+    if position.bar < 1
+        || position.beat < 1
+        || position.tick < 0
+        || position.ticks_per_beat <= 0.0
+        || !position.ticks_per_beat.is_finite()
+    {
+        return None;
+    }
+
+    let beat_index = usize::try_from(position.beat - 1).ok()?;
+    let beat_fraction = f64::from(position.tick) / position.ticks_per_beat;
+    if beat_index >= STEPS_PER_PATTERN / STEPS_PER_BEAT || beat_fraction >= 1.0 {
+        return None;
+    }
+
+    let steps_into_beat = beat_fraction * STEPS_PER_BEAT as f64;
+    let step_in_beat = steps_into_beat.floor();
+    let step_index = beat_index * STEPS_PER_BEAT + step_in_beat as usize;
+    let step_phase = steps_into_beat - step_in_beat;
+    let tick = (beat_fraction * f64::from(TICKS_PER_BEAT)) as u32;
+
+    Some(SequencerPosition {
+        bar: u64::try_from(position.bar - 1).ok()?,
+        pattern_bar: 0,
+        beat: beat_index as u32,
+        tick,
+        step_index,
+        step_phase,
+        timeline_sample: position.frame,
+    })
+}
+
+// Without a timebase master JACK only reports a frame, so bar/beat/tick are
+// derived from it at the sequencer's own tempo.
+pub fn jack_position_from_frame(frame: u64, frame_rate: u32, bpm: f64) -> JackPosition {
+    let beats = frame as f64 / f64::from(frame_rate.max(1)) * bpm / 60.0;
+    let beats_per_bar = f64::from(JACK_BEATS_PER_BAR);
+    let bar = (beats / beats_per_bar).floor();
+    let beat = (beats - bar * beats_per_bar).floor();
+    let tick = ((beats - beats.floor()) * f64::from(TICKS_PER_BEAT)) as i32;
+
+    JackPosition {
+        frame,
+        frame_rate,
+        bar: bar as i32 + 1,
+        beat: beat as i32 + 1,
+        tick: tick.min(TICKS_PER_BEAT as i32 - 1),
+        bar_start_tick: bar * beats_per_bar * f64::from(TICKS_PER_BEAT),
+        beats_per_bar: JACK_BEATS_PER_BAR,
+        beat_type: JACK_BEAT_TYPE,
+        ticks_per_beat: f64::from(TICKS_PER_BEAT),
+        beats_per_minute: bpm,
+    }
+}
+
+#[derive(Debug)]
+pub struct JackTransportSync {
+    role: JackTransportRole,
+    expected_frame: Option<u64>,
+}
+
+impl JackTransportSync {
+    pub fn new(role: JackTransportRole) -> Self {
+        Self {
+            role,
+            expected_frame: None,
+        }
+    }
+
+    pub fn role(&self) -> JackTransportRole {
+        self.role
+    }
+
+    pub fn publish(&self, sequencer: &Sequencer) -> Option<JackPosition> {
+        match self.role {
+            JackTransportRole::TimebaseMaster => Some(jack_position_from_sequencer(sequencer)),
+            JackTransportRole::Follower => None,
+        }
+    }
+
+    pub fn follow(
+        &mut self,
+        sequencer: &mut Sequencer,
+        state: JackTransportState,
+        position: &JackPosition,
+        frames: u32,
+    ) -> Result<(), String> {
+        if self.role == JackTransportRole::Follower && position.beats_per_minute.is_finite() {
+            let bpm = position.beats_per_minute as f32;
+            if (bpm - sequencer.transport().bpm()).abs() > f32::EPSILON {
+                sequencer.set_tempo_bpm(bpm);
+            }
+        }
+
+        let relocated = match self.expected_frame {
+            Some(expected) => expected.abs_diff(position.frame) > JACK_RELOCATE_TOLERANCE_FRAMES,
+            None => true,
+        };
+        if relocated {
+            let located = sequencer_position_from_jack(position)
+                .ok_or_else(|| format!("invalid jack position at frame {}", position.frame))?;
+            let bar_count = sequencer.pattern().bar_count() as u64;
+            let pattern_bar = (located.bar % bar_count) as usize;
+            sequencer.locate(
+                located.bar,
+                pattern_bar * STEPS_PER_PATTERN + located.step_index,
+                located.step_phase,
+                located.timeline_sample,
+            );
+        }
+
+        match state {
+            JackTransportState::Rolling => {
+                sequencer.start();
+                self.expected_frame = Some(position.frame + u64::from(frames));
+            }
+            JackTransportState::Starting => {
+                self.expected_frame = Some(position.frame);
+            }
+            JackTransportState::Stopped => {
+                sequencer.stop();
+                self.expected_frame = Some(position.frame);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// The JACK client library is loaded at runtime rather than linked, so builds
+// with the `jack` feature do not need libjack and fail to open cleanly without it.
+#[cfg(feature = "jack")]
+mod backend {
+    use std::ffi::{c_char, c_int, c_ulong, c_void, CStr, CString};
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::sync::{Mutex, MutexGuard};
+
+    use super::{
+        jack_position_from_frame, JackPosition, JackTransportRole, JackTransportState,
+        JackTransportSync,
+    };
+    use crate::midi_out::MidiOutputRouter;
+    use crate::{Sequencer, StepTriggerEvent};
+
+    const LIBRARY_NAMES: [&CStr; 2] = [c"libjack.so.0", c"libjack.so"];
+    const RTLD_NOW: c_int = 2;
+    const JACK_NO_START_SERVER: c_int = 0x01;
+    const JACK_PORT_IS_OUTPUT: c_ulong = 0x02;
+    const JACK_DEFAULT_MIDI_TYPE: &CStr = c"8 bit raw midi";
+    const JACK_POSITION_BBT: u32 = 0x10;
+    const JACK_TRANSPORT_ROLLING: c_int = 1;
+    const JACK_TRANSPORT_LOOPING: c_int = 2;
+    const JACK_TRANSPORT_STARTING: c_int = 3;
+    const JACK_TRANSPORT_NET_STARTING: c_int = 4;
+
+    // Mirrors the packed `jack_position_t` from <jack/types.h>.
+    #[repr(C, packed)]
+    #[derive(Clone, Copy)]
+    pub(super) struct RawPosition {
+        unique_1: u64,
+        usecs: u64,
+        frame_rate: u32,
+        frame: u32,
+        valid: u32,
+        bar: i32,
+        beat: i32,
+        tick: i32,
+        bar_start_tick: f64,
+        beats_per_bar: f32,
+        beat_type: f32,
+        ticks_per_beat: f64,
+        beats_per_minute: f64,
+        frame_time: f64,
+        next_time: f64,
+        bbt_offset: u32,
+        audio_frames_per_video_frame: f32,
+        video_offset: u32,
+        padding: [i32; 7],
+        unique_2: u64,
+    }
+
+    type TriggerSink = Box<dyn FnMut(&[StepTriggerEvent]) + Send>;
+    type ProcessCallback = unsafe extern "C" fn(u32, *mut c_void) -> c_int;
+
+    extern "C" {
+        fn dlopen(filename: *const c_char, flags: c_int) -> *mut c_void;
+        fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+        fn dlclose(handle: *mut c_void) -> c_int;
+    }
+
+    struct JackApi {
+        library: *mut c_void,
+        client_open: unsafe extern "C" fn(*const c_char, c_int, *mut c_int, ...) -> *mut c_void,
+        client_close: unsafe extern "C" fn(*mut c_void) -> c_int,
+        get_sample_rate: unsafe extern "C" fn(*mut c_void) -> u32,
+        port_register: unsafe extern "C" fn(
+            *mut c_void,
+            *const c_char,
+            *const c_char,
+            c_ulong,
+            c_ulong,
+        ) -> *mut c_void,
+        set_process_callback:
+            unsafe extern "C" fn(*mut c_void, ProcessCallback, *mut c_void) -> c_int,
+        activate: unsafe extern "C" fn(*mut c_void) -> c_int,
+        deactivate: unsafe extern "C" fn(*mut c_void) -> c_int,
+        port_get_buffer: unsafe extern "C" fn(*mut c_void, u32) -> *mut c_void,
+        midi_clear_buffer: unsafe extern "C" fn(*mut c_void),
+        midi_event_write: unsafe extern "C" fn(*mut c_void, u32, *const u8, usize) -> c_int,
+        transport_query: unsafe extern "C" fn(*mut c_void, *mut RawPosition) -> c_int,
+    }
+
+    impl JackApi {
+        fn load() -> Result<Self, String> {
+            let library = LIBRARY_NAMES
+                .iter()
+                .map(|name| unsafe { dlopen(name.as_ptr(), RTLD_NOW) })
+                .find(|library| !library.is_null())
+                .ok_or_else(|| "the JACK client library (libjack) is not available".to_string())?;
+            let api = unsafe {
+                Self {
+                    library,
+                    client_open: symbol(library, c"jack_client_open")?,
+                    client_close: symbol(library, c"jack_client_close")?,
+                    get_sample_rate: symbol(library, c"jack_get_sample_rate")?,
+                    port_register: symbol(library, c"jack_port_register")?,
+                    set_process_callback: symbol(library, c"jack_set_process_callback")?,
+                    activate: symbol(library, c"jack_activate")?,
+                    deactivate: symbol(library, c"jack_deactivate")?,
+                    port_get_buffer: symbol(library, c"jack_port_get_buffer")?,
+                    midi_clear_buffer: symbol(library, c"jack_midi_clear_buffer")?,
+                    midi_event_write: symbol(library, c"jack_midi_event_write")?,
+                    transport_query: symbol(library, c"jack_transport_query")?,
+                }
+            };
+            Ok(api)
+        }
+    }
+
+    impl Drop for JackApi {
+        fn drop(&mut self) {
+            unsafe {
+                dlclose(self.library);
+            }
+        }
+    }
+
+    // Resolves `name` as a function pointer of type `F`; the caller picks the
+    // signature matching the JACK header.
+    unsafe fn symbol<F: Copy>(library: *mut c_void, name: &CStr) -> Result<F, String> {
+        let address = dlsym(library, name.as_ptr());
+        if address.is_null() {
+            return Err(format!("libjack is missing {}", name.to_string_lossy()));
+        }
+        Ok(std::mem::transmute_copy(&address))
+    }
+
+    struct JackState {
+        sequencer: Sequencer,
+        router: MidiOutputRouter,
+        sync: JackTransportSync,
+        rolling: bool,
+        on_triggers: TriggerSink,
+    }
+
+    struct JackShared {
+        api: JackApi,
+        client: *mut c_void,
+        midi_port: *mut c_void,
+        sample_rate_hz: u32,
+        state: Mutex<JackState>,
+    }
+
+    impl JackShared {
+        fn process(&self, frames: u32) {
+            let midi = unsafe { (self.api.port_get_buffer)(self.midi_port, frames) };
+            if midi.is_null() {
+                return;
+            }
+            unsafe { (self.api.midi_clear_buffer)(midi) };
+            // Editors hold the lock briefly; a contended cycle is skipped
+            // rather than blocking the JACK thread.
+            let Ok(mut state) = self.state.try_lock() else {
+                return;
+            };
+            let state = &mut *state;
+
+            let (transport, position) = self.query_transport(state.sequencer.transport().bpm());
+            // A foreign position the grid cannot express (e.g. 7/8) keeps the
+            // sequencer on its own timeline; start/stop still follow.
+            if state
+                .sync
+                .follow(&mut state.sequencer, transport, &position, frames)
+                .is_err()
+            {
+                match transport {
+                    JackTransportState::Rolling => state.sequencer.start(),
+                    JackTransportState::Stopped => state.sequencer.stop(),
+                    JackTransportState::Starting => {}
+                }
+            }
+
+            let (internal, mut messages) = state
+                .router
+                .process_sequencer_block(&mut state.sequencer, frames);
+            let rolling = transport == JackTransportState::Rolling;
+            if state.rolling && !rolling {
+                messages.extend(state.router.all_notes_off());
+            }
+            state.rolling = rolling;
+
+            for message in &messages {
+                let offset = message.block_offset.min(frames.saturating_sub(1));
+                unsafe {
+                    (self.api.midi_event_write)(midi, offset, message.bytes.as_ptr(), 3);
+                }
+            }
+            if !internal.is_empty() {
+                (state.on_triggers)(&internal);
+            }
+        }
+
+        fn query_transport(&self, bpm: f32) -> (JackTransportState, JackPosition) {
+            let mut raw = RawPosition::zeroed();
+            let transport = unsafe { (self.api.transport_query)(self.client, &mut raw) };
+            let transport = match transport {
+                JACK_TRANSPORT_ROLLING | JACK_TRANSPORT_LOOPING => JackTransportState::Rolling,
+                JACK_TRANSPORT_STARTING | JACK_TRANSPORT_NET_STARTING => {
+                    JackTransportState::Starting
+                }
+                _ => JackTransportState::Stopped,
+            };
+            let frame = u64::from(raw.frame);
+            let position = if raw.valid & JACK_POSITION_BBT != 0 {
+                JackPosition {
+                    frame,
+                    frame_rate: self.sample_rate_hz,
+                    bar: raw.bar,
+                    beat: raw.beat,
+                    tick: raw.tick,
+                    bar_start_tick: raw.bar_start_tick,
+                    beats_per_bar: raw.beats_per_bar,
+                    beat_type: raw.beat_type,
+                    ticks_per_beat: raw.ticks_per_beat,
+                    beats_per_minute: raw.beats_per_minute,
+                }
+            } else {
+                jack_position_from_frame(frame, self.sample_rate_hz, f64::from(bpm))
+            };
+            (transport, position)
+        }
+    }
+
+    impl RawPosition {
+        pub(super) fn zeroed() -> Self {
+            // SAFETY: every field is a plain integer or float.
+            unsafe { std::mem::zeroed() }
+        }
+    }
+
+    unsafe extern "C" fn process_callback(frames: u32, shared: *mut c_void) -> c_int {
+        let shared = &*shared.cast::<JackShared>();
+        // Unwinding into libjack is undefined; a faulted cycle just goes silent.
+        let _ = catch_unwind(AssertUnwindSafe(|| shared.process(frames)));
+        0
+    }
+
+    /// A JACK client that runs the sequencer in the process cycle, follows the
+    /// JACK transport and sends MIDI-routed tracks out of a `midi_out` port.
+    pub struct JackBackend {
+        shared: Box<JackShared>,
+        active: bool,
+    }
+
+    impl JackBackend {
+        /// Connects to a running JACK server (never starting one) and activates
+        /// the client. Triggers for tracks without a MIDI output are handed to
+        /// `on_triggers` from the JACK thread.
+        pub fn open<F>(
+            client_name: &str,
+            mut sequencer: Sequencer,
+            on_triggers: F,
+        ) -> Result<Self, String>
+        where
+            F: FnMut(&[StepTriggerEvent]) + Send + 'static,
+        {
+            let name = CString::new(client_name)
+                .map_err(|_| "jack client name contains a NUL byte".to_string())?;
+            let api = JackApi::load()?;
+            let mut status = 0;
+            let client =
+                unsafe { (api.client_open)(name.as_ptr(), JACK_NO_START_SERVER, &mut status) };
+            if client.is_null() {
+                return Err(format!(
+                    "could not connect to the JACK server (status {status:#x})"
+                ));
+            }
+            let midi_port = unsafe {
+                (api.port_register)(
+                    client,
+                    c"midi_out".as_ptr(),
+                    JACK_DEFAULT_MIDI_TYPE.as_ptr(),
+                    JACK_PORT_IS_OUTPUT,
+                    0,
+                )
+            };
+            if midi_port.is_null() {
+                unsafe { (api.client_close)(client) };
+                return Err("could not register the JACK midi_out port".to_string());
+            }
+
+            let sample_rate_hz = unsafe { (api.get_sample_rate)(client) };
+            sequencer.set_sample_rate(sample_rate_hz);
+            let mut backend = Self {
+                shared: Box::new(JackShared {
+                    api,
+                    client,
+                    midi_port,
+                    sample_rate_hz,
+                    state: Mutex::new(JackState {
+                        sequencer,
+                        router: MidiOutputRouter::new(sample_rate_hz),
+                        sync: JackTransportSync::new(JackTransportRole::Follower),
+                        rolling: false,
+                        on_triggers: Box::new(on_triggers),
+                    }),
+                }),
+                active: false,
+            };
+
+            let shared = std::ptr::from_ref(&*backend.shared).cast_mut().cast();
+            let api = &backend.shared.api;
+            if unsafe { (api.set_process_callback)(client, process_callback, shared) } != 0
+                || unsafe { (api.activate)(client) } != 0
+            {
+                return Err("could not activate the JACK client".to_string());
+            }
+            backend.active = true;
+            Ok(backend)
+        }
+
+        pub fn sample_rate_hz(&self) -> u32 {
+            self.shared.sample_rate_hz
+        }
+
+        /// Edits the live sequencer and MIDI routing between process cycles.
+        pub fn with_state<T>(
+            &self,
+            edit: impl FnOnce(&mut Sequencer, &mut MidiOutputRouter) -> T,
+        ) -> T {
+            let mut state = self.lock();
+            let state = &mut *state;
+            edit(&mut state.sequencer, &mut state.router)
+        }
+
+        fn lock(&self) -> MutexGuard<'_, JackState> {
+            self.shared
+                .state
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+        }
+    }
+
+    impl Drop for JackBackend {
+        fn drop(&mut self) {
+            let api = &self.shared.api;
+            unsafe {
+                if self.active {
+                    (api.deactivate)(self.shared.client);
+                }
+                (api.client_close)(self.shared.client);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        jack_position_from_frame, jack_position_from_sequencer, sequencer_position_from_jack,
+        JackTransportRole, JackTransportState, JackTransportSync,
+    };
+    use crate::{Sequencer, Step};
+
+    #[test]
+    fn jack_bbt_roundtrips_through_sequencer_position() {
+        let mut sequencer = Sequencer::new(48_000);
+        assert!(sequencer.locate(2, 6, 0.5, 96_000));
+
+        let published = jack_position_from_sequencer(&sequencer);
+        assert_eq!(published.bar, 3);
+        assert_eq!(published.beat, 2);
+        assert_eq!(published.tick, 1200);
+
+        let located = sequencer_position_from_jack(&published).expect("position should map");
+        assert_eq!(located.bar, 2);
+        assert_eq!(located.step_index, 6);
+        assert!((located.step_phase - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn follower_locates_and_starts_on_rolling_transport() {
+        let mut sequencer = Sequencer::new(48_000);
+        assert!(sequencer.pattern_mut().set_step(
+            1,
+            4,
+            Step {
+                active: true,
+                velocity: 100,
+                ..Step::default()
+            },
+        ));
+
+        let mut master = Sequencer::new(48_000);
+        master.set_tempo_bpm(100.0);
+        assert!(master.locate(0, 4, 0.0, 28_800));
+
+        let mut sync = JackTransportSync::new(JackTransportRole::Follower);
+        sync.follow(
+            &mut sequencer,
+            JackTransportState::Rolling,
+            &jack_position_from_sequencer(&master),
+            256,
+        )
+        .expect("follow should succeed");
+
+        assert_eq!(sequencer.transport().bpm(), 100.0);
+        let events = sequencer.process_block(256);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].step_index, 4);
+        assert_eq!(events[0].timeline_sample, 28_800);
+    }
+
+    #[test]
+    fn frame_only_transport_maps_to_bars_at_the_sequencer_tempo() {
+        // 120 BPM at 48 kHz: one beat is 24_000 frames, one bar 96_000.
+        let position = jack_position_from_frame(96_000 + 36_000, 48_000, 120.0);
+        assert_eq!((position.bar, position.beat, position.tick), (2, 2, 960));
+
+        let located = sequencer_position_from_jack(&position).expect("position should map");
+        assert_eq!(located.bar, 1);
+        assert_eq!(located.step_index, 6);
+        assert_eq!(located.timeline_sample, 132_000);
+
+        let start = jack_position_from_frame(0, 48_000, 120.0);
+        assert_eq!((start.bar, start.beat, start.tick), (1, 1, 0));
+    }
+
+    #[cfg(feature = "jack")]
+    #[test]
+    fn raw_position_matches_the_packed_jack_layout() {
+        assert_eq!(size_of::<super::backend::RawPosition>(), 136);
+    }
+}
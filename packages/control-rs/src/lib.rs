@@ -1,14 +1,64 @@
 pub const TRACK_COUNT: usize = 8;
 pub const STEPS_PER_PATTERN: usize = 16;
+pub const MAX_PATTERN_BARS: usize = 4;
+pub const MAX_PATTERN_STEPS: usize = STEPS_PER_PATTERN * MAX_PATTERN_BARS;
 pub const DEFAULT_BPM: f32 = 120.0;
 pub const MIN_BPM: f32 = 20.0;
 pub const MAX_BPM: f32 = 300.0;
-pub const MAX_SWING: f32 = 0.45;
+pub const MAX_SWING: f32 = abi_rs::FF_SWING_MAX;
+pub const STEPS_PER_BEAT: usize = 4;
+pub const TICKS_PER_BEAT: u32 = 1920;
+pub const CLOCK_PPQN: u32 = 24;
+pub const TEMPO_GLIDE_BEATS: usize = STEPS_PER_PATTERN / STEPS_PER_BEAT;
+pub const FLAM_GRACE_VELOCITY_PERCENT: u16 = 50;
+pub const MAX_COUNT_IN_BARS: u8 = 2;
+const GRID_STEP_TICKS: f64 = (RESOLUTION_TICKS_PER_BEAT / STEPS_PER_BEAT as u32) as f64;
+
+pub mod coalesce;
+pub mod cursor;
+pub mod density;
+pub mod diff;
+pub mod event_log;
+pub mod grid;
+pub mod jack;
+pub mod midi_out;
+pub mod mutation;
+pub mod pitch;
+pub mod random;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod smoothing;
+pub mod snapshot;
+pub mod song;
+pub mod tab;
+pub mod telemetry;
+
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
+pub use abi_rs::FfEventSource;
+pub use presets_rs::{
+    AccentClip, AccentCurve, AudioHints, GrooveTemplate, HumanizeDepth, LockTarget, MuteSpan,
+    ParameterLock, PatternScale, PatternTempo, PlaybackDirection, SongEntry, StepResolution,
+    SwingGrid, TempoChange, TrackInputs, TrigCondition, DEFAULT_ACCENT_AMOUNT,
+    DEFAULT_FLAM_SPACING_MS, MAX_ACCENT_AMOUNT, MAX_FLAM_SPACING_MS, MAX_STEP_GATE,
+    MAX_STEP_PROBABILITY, MAX_TRIG_CYCLE, PATTERN_VARIATION_COUNT, RESOLUTION_TICKS_PER_BEAT,
+};
+use random::RandomSource;
+use snapshot::PatternSnapshots;
+use song::{Song, SongPosition};
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct Step {
     pub active: bool,
     pub velocity: u8,
+    pub probability: u8,
+    pub condition: TrigCondition,
+    pub accent: bool,
+    pub flam: bool,
+    pub flam_spacing_ms: u8,
+    pub gate: u8,
 }
 
 impl Default for Step {
@@ -16,782 +66,5117 @@ impl Default for Step {
         Self {
             active: false,
             velocity: 100,
+            probability: MAX_STEP_PROBABILITY,
+            condition: TrigCondition::Always,
+            accent: false,
+            flam: false,
+            flam_spacing_ms: DEFAULT_FLAM_SPACING_MS,
+            gate: MAX_STEP_GATE,
         }
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+impl Step {
+    pub fn is_valid(&self) -> bool {
+        self.probability <= MAX_STEP_PROBABILITY
+            && self.condition.is_valid()
+            && self.flam_spacing_ms <= MAX_FLAM_SPACING_MS
+            && (!self.flam || self.flam_spacing_ms > 0)
+            && (1..=MAX_STEP_GATE).contains(&self.gate)
+    }
+}
+
+impl From<presets_rs::PatternStep> for Step {
+    fn from(step: presets_rs::PatternStep) -> Self {
+        Self {
+            active: step.active,
+            velocity: step.velocity,
+            probability: step.probability,
+            condition: step.condition,
+            accent: step.accent,
+            flam: step.flam,
+            flam_spacing_ms: step.flam_spacing_ms,
+            gate: step.gate,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct StepOverride {
+    variation: u8,
+    track_index: usize,
+    step_index: usize,
+    step: Step,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct StoredStep {
+    track_index: usize,
+    step_index: usize,
+    step: Step,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Pattern {
-    tracks: [[Step; STEPS_PER_PATTERN]; TRACK_COUNT],
+    steps: Vec<StoredStep>,
+    bar_count: usize,
+    length_steps: Option<usize>,
+    scale: PatternScale,
+    tempo: Option<PatternTempo>,
+    swing: Option<f32>,
+    groove: Option<GrooveTemplate>,
+    overrides: Vec<StepOverride>,
+    active_variation: u8,
+    mutes: Vec<MuteSpan>,
+    locks: Vec<ParameterLock>,
+    directions: [PlaybackDirection; TRACK_COUNT],
+    resolutions: [StepResolution; TRACK_COUNT],
+    swing_grid: SwingGrid,
+    accent: AccentCurve,
+    default_steps: [Step; TRACK_COUNT],
 }
 
 impl Default for Pattern {
     fn default() -> Self {
         Self {
-            tracks: [[Step::default(); STEPS_PER_PATTERN]; TRACK_COUNT],
+            steps: Vec::new(),
+            bar_count: 1,
+            length_steps: None,
+            scale: PatternScale::Normal,
+            tempo: None,
+            swing: None,
+            groove: None,
+            overrides: Vec::new(),
+            active_variation: 0,
+            mutes: Vec::new(),
+            locks: Vec::new(),
+            directions: [PlaybackDirection::Forward; TRACK_COUNT],
+            resolutions: [StepResolution::Sixteenth; TRACK_COUNT],
+            swing_grid: SwingGrid::Track,
+            accent: AccentCurve::default(),
+            default_steps: [Step::default(); TRACK_COUNT],
         }
     }
 }
 
 impl Pattern {
-    pub fn set_step(&mut self, track_index: usize, step_index: usize, step: Step) -> bool {
-        if track_index >= TRACK_COUNT || step_index >= STEPS_PER_PATTERN {
-            return false;
-        }
+    pub fn accent_amount(&self) -> u8 {
+        self.accent.boosts.into_iter().max().unwrap_or(0)
+    }
 
-        self.tracks[track_index][step_index] = step;
-        true
+    pub fn set_accent_amount(&mut self, amount: u8) -> bool {
+        self.set_accent_curve(AccentCurve::flat(amount))
     }
 
-    pub fn step(&self, track_index: usize, step_index: usize) -> Option<Step> {
-        if track_index >= TRACK_COUNT || step_index >= STEPS_PER_PATTERN {
-            return None;
-        }
+    pub fn accent_curve(&self) -> AccentCurve {
+        self.accent
+    }
 
-        Some(self.tracks[track_index][step_index])
+    pub fn set_accent_curve(&mut self, curve: AccentCurve) -> bool {
+        if !curve.is_valid() {
+            return false;
+        }
+        self.accent = curve;
+        true
     }
-}
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct Transport {
-    bpm: f32,
-    is_playing: bool,
-}
+    pub fn track_default_step(&self, track_index: usize) -> Option<Step> {
+        self.default_steps.get(track_index).copied()
+    }
 
-impl Default for Transport {
-    fn default() -> Self {
-        Self {
-            bpm: DEFAULT_BPM,
-            is_playing: false,
+    pub fn set_track_default_step(&mut self, track_index: usize, step: Step) -> bool {
+        if track_index >= TRACK_COUNT || !step.is_valid() {
+            return false;
         }
+        self.default_steps[track_index] = Step {
+            active: false,
+            ..step
+        };
+        true
     }
-}
 
-impl Transport {
-    pub fn bpm(&self) -> f32 {
-        self.bpm
+    pub fn default_hit(&self, track_index: usize) -> Step {
+        Step {
+            active: true,
+            ..self.track_default_step(track_index).unwrap_or_default()
+        }
     }
 
-    pub fn is_playing(&self) -> bool {
-        self.is_playing
+    pub fn step_velocity(&self, step: Step) -> u8 {
+        if step.accent {
+            self.accent.apply(step.velocity)
+        } else {
+            step.velocity
+        }
     }
 
-    pub fn set_bpm(&mut self, bpm: f32) {
-        self.bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+    pub fn scale(&self) -> PatternScale {
+        self.scale
     }
 
-    pub fn start(&mut self) {
-        self.is_playing = true;
+    pub fn set_scale(&mut self, scale: PatternScale) {
+        self.scale = scale;
     }
 
-    pub fn stop(&mut self) {
-        self.is_playing = false;
+    pub fn tempo(&self) -> Option<PatternTempo> {
+        self.tempo
     }
-}
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub struct StepTriggerEvent {
-    pub track_index: u8,
-    pub step_index: u8,
-    pub velocity: u8,
-    pub choke_group: Option<u8>,
-    pub timeline_sample: u64,
-    pub block_offset: u32,
-}
+    pub fn set_tempo(&mut self, tempo: Option<PatternTempo>) -> bool {
+        if tempo.is_some_and(|tempo| !tempo.is_valid()) {
+            return false;
+        }
+        self.tempo = tempo;
+        true
+    }
 
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
-pub struct TrackPerformance {
-    pub choke_group: Option<u8>,
-}
+    pub fn swing(&self) -> Option<f32> {
+        self.swing
+    }
 
-#[derive(Debug)]
-pub struct Sequencer {
-    sample_rate_hz: u32,
-    transport: Transport,
-    pattern: Pattern,
-    swing: f32,
-    track_performance: [TrackPerformance; TRACK_COUNT],
-    current_step: usize,
-    samples_to_next_step: f64,
-    timeline_sample: u64,
-    emit_step_on_next_process: bool,
-}
+    // A pattern with its own swing replaces the sequencer's swing and groove
+    // when it is switched in; without one the sequencer keeps its feel.
+    pub fn set_swing(&mut self, swing: Option<f32>) -> bool {
+        if swing.is_some_and(|swing| !(0.0..=MAX_SWING).contains(&swing)) {
+            return false;
+        }
+        self.swing = swing;
+        true
+    }
 
-impl Sequencer {
-    pub fn new(sample_rate_hz: u32) -> Self {
-        let sample_rate_hz = sample_rate_hz.max(1);
-        let transport = Transport::default();
-        let samples_to_next_step = samples_per_step(sample_rate_hz, transport.bpm());
+    pub fn groove(&self) -> Option<&GrooveTemplate> {
+        self.groove.as_ref()
+    }
 
-        Self {
-            sample_rate_hz,
-            transport,
-            pattern: Pattern::default(),
-            swing: 0.0,
-            track_performance: [TrackPerformance::default(); TRACK_COUNT],
-            current_step: 0,
-            samples_to_next_step,
-            timeline_sample: 0,
-            emit_step_on_next_process: false,
+    pub fn set_groove(&mut self, groove: Option<GrooveTemplate>) -> bool {
+        if groove.as_ref().is_some_and(|groove| !groove.is_valid()) {
+            return false;
         }
+        self.groove = groove;
+        true
     }
 
-    pub fn transport(&self) -> Transport {
-        self.transport
+    pub fn bar_count(&self) -> usize {
+        self.bar_count
     }
 
-    pub fn set_tempo_bpm(&mut self, bpm: f32) {
-        self.transport.set_bpm(bpm);
-        self.samples_to_next_step = self
-            .samples_to_next_step
-            .min(self.step_interval_samples(self.current_step));
+    pub fn step_count(&self) -> usize {
+        self.length_steps
+            .unwrap_or(self.bar_count * STEPS_PER_PATTERN)
     }
 
-    pub fn set_swing(&mut self, swing: f32) {
-        self.swing = swing.clamp(0.0, MAX_SWING);
-        self.samples_to_next_step = self
-            .samples_to_next_step
-            .min(self.step_interval_samples(self.current_step));
-    }
+    pub fn set_bar_count(&mut self, bar_count: usize) -> bool {
+        if bar_count == 0 || bar_count > MAX_PATTERN_BARS {
+            return false;
+        }
 
-    pub fn swing(&self) -> f32 {
-        self.swing
+        self.bar_count = bar_count;
+        self.length_steps = None;
+        self.truncate_to_step_count();
+        true
     }
 
-    pub fn set_track_choke_group(&mut self, track_index: usize, choke_group: Option<u8>) -> bool {
-        if track_index >= TRACK_COUNT {
+    pub fn set_step_count(&mut self, step_count: usize) -> bool {
+        if step_count == 0 || step_count > MAX_PATTERN_STEPS {
             return false;
         }
 
-        self.track_performance[track_index].choke_group = choke_group;
+        self.set_bar_count(step_count.div_ceil(STEPS_PER_PATTERN));
+        self.length_steps = (!step_count.is_multiple_of(STEPS_PER_PATTERN)).then_some(step_count);
+        self.truncate_to_step_count();
         true
     }
 
-    pub fn start(&mut self) {
-        if !self.transport.is_playing() {
-            self.transport.start();
-            self.emit_step_on_next_process = true;
+    fn truncate_to_step_count(&mut self) {
+        let bar_count = self.bar_count;
+        let step_count = self.step_count();
+        self.steps
+            .retain(|existing| existing.step_index < step_count);
+        self.overrides
+            .retain(|existing| existing.step_index < step_count);
+        self.mutes.retain(|span| span.first_bar < bar_count);
+        for span in &mut self.mutes {
+            span.last_bar = span.last_bar.min(bar_count - 1);
         }
+        self.locks
+            .retain(|lock| usize::from(lock.step_index) < step_count);
     }
 
-    pub fn stop(&mut self) {
-        self.transport.stop();
-        self.emit_step_on_next_process = false;
+    pub fn track_direction(&self, track_index: usize) -> Option<PlaybackDirection> {
+        self.directions.get(track_index).copied()
     }
 
-    pub fn reset(&mut self) {
-        self.current_step = 0;
-        self.timeline_sample = 0;
-        self.samples_to_next_step = self.step_interval_samples(self.current_step);
-        self.emit_step_on_next_process = false;
+    pub fn set_track_direction(
+        &mut self,
+        track_index: usize,
+        direction: PlaybackDirection,
+    ) -> bool {
+        let Some(slot) = self.directions.get_mut(track_index) else {
+            return false;
+        };
+        *slot = direction;
+        true
     }
 
-    pub fn pattern(&self) -> &Pattern {
-        &self.pattern
+    pub fn track_resolution(&self, track_index: usize) -> Option<StepResolution> {
+        self.resolutions.get(track_index).copied()
     }
 
-    pub fn pattern_mut(&mut self) -> &mut Pattern {
-        &mut self.pattern
+    pub fn set_track_resolution(&mut self, track_index: usize, resolution: StepResolution) -> bool {
+        let Some(slot) = self.resolutions.get_mut(track_index) else {
+            return false;
+        };
+        *slot = resolution;
+        true
     }
 
-    pub fn process_block(&mut self, frames: u32) -> Vec<StepTriggerEvent> {
-        if frames == 0 || !self.transport.is_playing() {
-            return Vec::new();
-        }
+    pub fn swing_grid(&self) -> SwingGrid {
+        self.swing_grid
+    }
 
-        let mut events = Vec::new();
-        if self.emit_step_on_next_process {
-            self.collect_step_events(self.current_step, 0, self.timeline_sample, &mut events);
-            self.emit_step_on_next_process = false;
-            self.samples_to_next_step = self.step_interval_samples(self.current_step);
-        }
-
-        let mut remaining = f64::from(frames);
-        let mut consumed = 0.0;
-        while remaining > 0.0 {
-            if self.samples_to_next_step <= remaining + f64::EPSILON {
-                let step_advance = self.samples_to_next_step.max(0.0);
-                consumed += step_advance;
-                remaining -= step_advance;
-
-                let offset = consumed.round() as u32;
-                self.current_step = (self.current_step + 1) % STEPS_PER_PATTERN;
-                self.collect_step_events(
-                    self.current_step,
-                    offset,
-                    self.timeline_sample + u64::from(offset),
-                    &mut events,
-                );
-                self.samples_to_next_step = self.step_interval_samples(self.current_step);
-            } else {
-                self.samples_to_next_step -= remaining;
-                remaining = 0.0;
-            }
-        }
+    pub fn set_swing_grid(&mut self, swing_grid: SwingGrid) {
+        self.swing_grid = swing_grid;
+    }
 
-        self.timeline_sample += u64::from(frames);
-        events
+    pub fn parameter_locks(&self) -> &[ParameterLock] {
+        &self.locks
     }
 
-    fn collect_step_events(
-        &self,
-        step_index: usize,
-        block_offset: u32,
-        timeline_sample: u64,
-        output: &mut Vec<StepTriggerEvent>,
-    ) {
-        for track_index in 0..TRACK_COUNT {
-            let step = self.pattern.tracks[track_index][step_index];
-            if step.active {
-                output.push(StepTriggerEvent {
-                    track_index: track_index as u8,
-                    step_index: step_index as u8,
-                    velocity: step.velocity,
-                    choke_group: self.track_performance[track_index].choke_group,
-                    timeline_sample,
-                    block_offset,
-                });
-            }
+    pub fn set_parameter_locks(&mut self, locks: &[ParameterLock]) -> bool {
+        if locks
+            .iter()
+            .any(|lock| !lock.is_valid() || usize::from(lock.step_index) >= self.step_count())
+        {
+            return false;
         }
+        self.locks = locks.to_vec();
+        self.locks.sort_by_key(ParameterLock::key);
+        self.locks.dedup_by_key(|lock| lock.key());
+        true
     }
 
-    fn step_interval_samples(&self, step_index: usize) -> f64 {
-        let base = samples_per_step(self.sample_rate_hz, self.transport.bpm());
-        if self.swing <= f32::EPSILON {
-            return base;
+    pub fn set_parameter_lock(&mut self, lock: ParameterLock) -> bool {
+        if !lock.is_valid() || usize::from(lock.step_index) >= self.step_count() {
+            return false;
         }
-
-        let swing = f64::from(self.swing);
-        if step_index % 2 == 0 {
-            base * (1.0 + swing)
-        } else {
-            base * (1.0 - swing)
+        match self
+            .locks
+            .binary_search_by_key(&lock.key(), ParameterLock::key)
+        {
+            Ok(index) => self.locks[index] = lock,
+            Err(index) => self.locks.insert(index, lock),
         }
+        true
     }
-}
 
-fn samples_per_step(sample_rate_hz: u32, bpm: f32) -> f64 {
-    let safe_bpm = bpm.clamp(MIN_BPM, MAX_BPM);
-    f64::from(sample_rate_hz) * 60.0 / f64::from(safe_bpm) / 4.0
-}
-
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct TrackRecall {
-    pub sample_id: Option<String>,
-    pub choke_group: Option<u8>,
-    pub gain_normalized: u8,
-    pub pan_normalized: u8,
-    pub filter_cutoff_normalized: u8,
-    pub envelope_decay_normalized: u8,
-    pub pitch_normalized: u8,
-}
-
-impl Default for TrackRecall {
-    fn default() -> Self {
-        Self {
-            sample_id: None,
-            choke_group: None,
-            gain_normalized: 127,
-            pan_normalized: 64,
-            filter_cutoff_normalized: 127,
-            envelope_decay_normalized: 127,
-            pitch_normalized: 64,
+    pub fn clear_parameter_lock(
+        &mut self,
+        track_index: usize,
+        step_index: usize,
+        target: LockTarget,
+    ) -> bool {
+        match self.lock_index(track_index, step_index, target) {
+            Some(index) => {
+                self.locks.remove(index);
+                true
+            }
+            None => false,
         }
     }
-}
-
-#[derive(Debug)]
-pub struct RecallState {
-    sequencer: Sequencer,
-    track_recall: [TrackRecall; TRACK_COUNT],
-}
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct TrackSampleAssignment {
-    pub track_index: u8,
-    pub sample_id: String,
-}
+    pub fn parameter_lock(
+        &self,
+        track_index: usize,
+        step_index: usize,
+        target: LockTarget,
+    ) -> Option<f32> {
+        self.lock_index(track_index, step_index, target)
+            .map(|index| self.locks[index].value)
+    }
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct EngineRecall {
-    pub sample_assignments: Vec<TrackSampleAssignment>,
-    pub parameter_updates: Vec<abi_rs::FfParameterUpdate>,
-}
+    fn lock_index(
+        &self,
+        track_index: usize,
+        step_index: usize,
+        target: LockTarget,
+    ) -> Option<usize> {
+        let key = (
+            u8::try_from(track_index).ok()?,
+            u8::try_from(step_index).ok()?,
+            target,
+        );
+        self.locks
+            .binary_search_by_key(&key, ParameterLock::key)
+            .ok()
+    }
 
-impl RecallState {
-    pub fn sequencer(&self) -> &Sequencer {
-        &self.sequencer
+    pub fn mute_spans(&self) -> &[MuteSpan] {
+        &self.mutes
     }
 
-    pub fn sequencer_mut(&mut self) -> &mut Sequencer {
-        &mut self.sequencer
+    pub fn set_mute_spans(&mut self, spans: &[MuteSpan]) -> bool {
+        if spans.iter().any(|span| {
+            usize::from(span.track_index) >= TRACK_COUNT
+                || span.first_bar > span.last_bar
+                || span.last_bar >= self.bar_count
+        }) {
+            return false;
+        }
+        self.mutes = spans.to_vec();
+        true
     }
 
-    pub fn track_recall(&self, track_index: usize) -> Option<&TrackRecall> {
-        self.track_recall.get(track_index)
+    pub fn track_muted_at(&self, track_index: usize, bar: usize) -> bool {
+        self.mutes
+            .iter()
+            .any(|span| span.contains(track_index, bar))
     }
 
-    pub fn to_engine_recall(&self) -> EngineRecall {
-        let mut sample_assignments = Vec::new();
-        let mut parameter_updates = Vec::with_capacity(TRACK_COUNT * 6);
+    pub fn set_step(&mut self, track_index: usize, step_index: usize, step: Step) -> bool {
+        if track_index >= TRACK_COUNT || step_index >= self.step_count() || !step.is_valid() {
+            return false;
+        }
 
-        for (track_index, track_recall) in self.track_recall.iter().enumerate() {
-            let track_index = track_index as u8;
-            if let Some(sample_id) = &track_recall.sample_id {
-                sample_assignments.push(TrackSampleAssignment {
-                    track_index,
-                    sample_id: sample_id.clone(),
-                });
-            }
+        self.store_step(track_index, step_index, step);
+        true
+    }
 
-            push_parameter_update(
-                &mut parameter_updates,
-                track_index,
-                abi_rs::FF_PARAM_SLOT_GAIN,
-                normalized_from_u7(track_recall.gain_normalized),
-            );
-            push_parameter_update(
-                &mut parameter_updates,
-                track_index,
-                abi_rs::FF_PARAM_SLOT_PAN,
-                normalized_from_u7(track_recall.pan_normalized),
-            );
-            push_parameter_update(
-                &mut parameter_updates,
-                track_index,
-                abi_rs::FF_PARAM_SLOT_FILTER_CUTOFF,
-                normalized_from_u7(track_recall.filter_cutoff_normalized),
-            );
-            push_parameter_update(
-                &mut parameter_updates,
-                track_index,
-                abi_rs::FF_PARAM_SLOT_ENVELOPE_DECAY,
-                normalized_from_u7(track_recall.envelope_decay_normalized),
-            );
-            push_parameter_update(
-                &mut parameter_updates,
-                track_index,
-                abi_rs::FF_PARAM_SLOT_PITCH,
-                normalized_from_u7(track_recall.pitch_normalized),
-            );
-            push_parameter_update(
-                &mut parameter_updates,
-                track_index,
-                abi_rs::FF_PARAM_SLOT_CHOKE_GROUP,
-                normalize_choke_group_for_engine(track_recall.choke_group),
-            );
+    pub fn set_steps_bulk<I>(&mut self, track_index: usize, steps: I) -> bool
+    where
+        I: IntoIterator<Item = (usize, Step)>,
+    {
+        if track_index >= TRACK_COUNT {
+            return false;
         }
 
-        EngineRecall {
-            sample_assignments,
-            parameter_updates,
+        let steps: Vec<(usize, Step)> = steps.into_iter().collect();
+        if steps
+            .iter()
+            .any(|(step_index, step)| *step_index >= self.step_count() || !step.is_valid())
+        {
+            return false;
         }
+        for (step_index, step) in steps {
+            self.store_step(track_index, step_index, step);
+        }
+        true
     }
-}
 
-fn normalize_unit(value: f32) -> u8 {
-    let clamped = value.clamp(0.0, 1.0);
-    (clamped * 127.0).round() as u8
-}
+    pub fn update_step_range<F>(
+        &mut self,
+        track_index: usize,
+        range: Range<usize>,
+        stride: usize,
+        mut update: F,
+    ) -> bool
+    where
+        F: FnMut(Step) -> Step,
+    {
+        if track_index >= TRACK_COUNT
+            || stride == 0
+            || range.start > range.end
+            || range.end > self.step_count()
+        {
+            return false;
+        }
 
-fn normalize_pan(value: f32) -> u8 {
-    let clamped = value.clamp(-1.0, 1.0);
-    let normalized = (clamped + 1.0) * 0.5;
-    (normalized * 127.0).round() as u8
-}
+        let edits: Vec<(usize, Step)> = range
+            .step_by(stride)
+            .map(|step_index| {
+                (
+                    step_index,
+                    update(self.stored_step(track_index, step_index)),
+                )
+            })
+            .collect();
+        self.set_steps_bulk(track_index, edits)
+    }
 
-fn normalize_pitch(value: f32) -> u8 {
-    let clamped = value.clamp(-24.0, 24.0);
-    let normalized = (clamped + 24.0) / 48.0;
-    (normalized * 127.0).round() as u8
-}
+    pub fn set_velocity_range(
+        &mut self,
+        track_index: usize,
+        range: Range<usize>,
+        velocity: u8,
+    ) -> bool {
+        velocity <= 127
+            && self.update_step_range(track_index, range, 1, |step| Step { velocity, ..step })
+    }
 
-fn normalized_from_u7(value: u8) -> f32 {
-    f32::from(value) / 127.0
-}
+    pub fn clear_step_range(
+        &mut self,
+        track_index: usize,
+        range: Range<usize>,
+        stride: usize,
+    ) -> bool {
+        self.update_step_range(track_index, range, stride, |_| Step::default())
+    }
 
-fn normalize_choke_group_for_engine(choke_group: Option<u8>) -> f32 {
-    match choke_group {
-        Some(value) => (f32::from(value.min(15)) + 1.0) / 16.0,
-        None => 0.0,
+    pub fn step(&self, track_index: usize, step_index: usize) -> Option<Step> {
+        if track_index >= TRACK_COUNT || step_index >= self.step_count() {
+            return None;
+        }
+
+        Some(self.stored_step(track_index, step_index))
     }
-}
 
-fn push_parameter_update(
-    output: &mut Vec<abi_rs::FfParameterUpdate>,
-    track_index: u8,
-    parameter_slot: u32,
-    normalized_value: f32,
-) {
-    if let Some(parameter_id) = abi_rs::ff_track_parameter_id(track_index, parameter_slot) {
-        output.push(abi_rs::FfParameterUpdate {
-            parameter_id,
-            normalized_value: normalized_value.clamp(0.0, 1.0),
-            ramp_samples: 0,
-            reserved: 0,
-        });
+    fn stored_step_position(&self, track_index: usize, step_index: usize) -> Result<usize, usize> {
+        self.steps.binary_search_by(|existing| {
+            (existing.track_index, existing.step_index).cmp(&(track_index, step_index))
+        })
     }
-}
 
-pub fn recall_state_from_project(
-    project: &presets_rs::Project,
-    sample_rate_hz: u32,
-) -> Result<RecallState, String> {
-    let kit_index = project
-        .active_kit
-        .or_else(|| (!project.kits.is_empty()).then_some(0))
-        .ok_or_else(|| "project has no kits".to_string())?;
-    if kit_index >= project.kits.len() {
-        return Err(format!("active kit out of range: {kit_index}"));
+    fn stored_step(&self, track_index: usize, step_index: usize) -> Step {
+        self.stored_step_position(track_index, step_index)
+            .map_or_else(|_| Step::default(), |position| self.steps[position].step)
     }
 
-    let pattern_index = project
-        .active_pattern
-        .or_else(|| (!project.patterns.is_empty()).then_some(0))
-        .ok_or_else(|| "project has no patterns".to_string())?;
-    if pattern_index >= project.patterns.len() {
-        return Err(format!("active pattern out of range: {pattern_index}"));
+    fn store_step(&mut self, track_index: usize, step_index: usize, step: Step) {
+        match (
+            self.stored_step_position(track_index, step_index),
+            step == Step::default(),
+        ) {
+            (Ok(position), true) => {
+                self.steps.remove(position);
+            }
+            (Ok(position), false) => self.steps[position].step = step,
+            (Err(_), true) => {}
+            (Err(position), false) => self.steps.insert(
+                position,
+                StoredStep {
+                    track_index,
+                    step_index,
+                    step,
+                },
+            ),
+        }
     }
 
-    let kit = &project.kits[kit_index];
-    let pattern = &project.patterns[pattern_index];
+    pub fn active_variation(&self) -> u8 {
+        self.active_variation
+    }
 
-    let mut sequencer = Sequencer::new(sample_rate_hz);
-    sequencer.set_swing(pattern.swing);
+    pub fn set_active_variation(&mut self, variation: u8) -> bool {
+        if usize::from(variation) >= PATTERN_VARIATION_COUNT {
+            return false;
+        }
+        self.active_variation = variation;
+        true
+    }
 
-    for track_index in 0..TRACK_COUNT {
-        for step_index in 0..STEPS_PER_PATTERN {
-            let step = pattern.steps[track_index][step_index];
-            if !sequencer.pattern_mut().set_step(
+    pub fn set_variation_step(
+        &mut self,
+        variation: u8,
+        track_index: usize,
+        step_index: usize,
+        step: Option<Step>,
+    ) -> bool {
+        if variation == 0
+            || usize::from(variation) >= PATTERN_VARIATION_COUNT
+            || track_index >= TRACK_COUNT
+            || step_index >= self.step_count()
+            || step.is_some_and(|step| !step.is_valid())
+        {
+            return false;
+        }
+
+        self.overrides.retain(|existing| {
+            (
+                existing.variation,
+                existing.track_index,
+                existing.step_index,
+            ) != (variation, track_index, step_index)
+        });
+        if let Some(step) = step {
+            self.overrides.push(StepOverride {
+                variation,
                 track_index,
                 step_index,
-                Step {
-                    active: step.active,
-                    velocity: step.velocity,
-                },
-            ) {
-                return Err(format!(
-                    "failed to apply pattern step track={track_index}, step={step_index}"
-                ));
-            }
+                step,
+            });
         }
+        true
     }
 
-    let mut track_recall = std::array::from_fn(|_| TrackRecall::default());
-    for assignment in &kit.tracks {
-        let track_index = usize::from(assignment.track_index);
-        if track_index >= TRACK_COUNT {
-            return Err(format!(
-                "kit track assignment out of range: {}",
-                assignment.track_index
-            ));
-        }
-        track_recall[track_index].sample_id = Some(assignment.sample_id.clone());
+    pub fn variation_step(
+        &self,
+        variation: u8,
+        track_index: usize,
+        step_index: usize,
+    ) -> Option<Step> {
+        let base = self.step(track_index, step_index)?;
+        Some(self.resolve_step(variation, track_index, step_index, base))
     }
 
-    for control in &kit.controls {
-        let track_index = usize::from(control.track_index);
-        if track_index >= TRACK_COUNT {
-            return Err(format!(
-                "kit control track out of range: {}",
-                control.track_index
-            ));
-        }
+    pub fn variation_overrides(&self, variation: u8) -> Vec<(usize, usize, Step)> {
+        let mut overrides: Vec<(usize, usize, Step)> = self
+            .overrides
+            .iter()
+            .filter(|existing| existing.variation == variation)
+            .map(|existing| (existing.track_index, existing.step_index, existing.step))
+            .collect();
+        overrides.sort_by_key(|(track_index, step_index, _)| (*track_index, *step_index));
+        overrides
+    }
 
-        track_recall[track_index].choke_group = control.controls.choke_group;
-        track_recall[track_index].gain_normalized = normalize_unit(control.controls.gain);
-        track_recall[track_index].pan_normalized = normalize_pan(control.controls.pan);
-        track_recall[track_index].filter_cutoff_normalized =
-            normalize_unit(control.controls.filter_cutoff);
-        track_recall[track_index].envelope_decay_normalized =
-            normalize_unit(control.controls.envelope_decay);
-        track_recall[track_index].pitch_normalized =
-            normalize_pitch(control.controls.pitch_semitones);
+    pub fn clear_variation(&mut self, variation: u8) {
+        self.overrides
+            .retain(|existing| existing.variation != variation);
+    }
 
-        if !sequencer.set_track_choke_group(track_index, control.controls.choke_group) {
-            return Err(format!(
-                "failed to apply choke group to track {track_index}"
-            ));
+    pub fn playing_step(&self, track_index: usize, step_index: usize) -> Option<Step> {
+        self.variation_step(self.active_variation, track_index, step_index)
+    }
+
+    fn resolve_step(
+        &self,
+        variation: u8,
+        track_index: usize,
+        step_index: usize,
+        base: Step,
+    ) -> Step {
+        if variation == 0 {
+            return base;
         }
+        self.overrides
+            .iter()
+            .find(|existing| {
+                existing.variation == variation
+                    && existing.track_index == track_index
+                    && existing.step_index == step_index
+            })
+            .map_or(base, |existing| existing.step)
     }
 
-    Ok(RecallState {
-        sequencer,
-        track_recall,
-    })
+    fn playing(&self, track_index: usize, step_index: usize) -> Step {
+        self.resolve_step(
+            self.active_variation,
+            track_index,
+            step_index,
+            self.stored_step(track_index, step_index),
+        )
+    }
 }
 
-pub fn render_recall_events(
-    project: &presets_rs::Project,
-    sample_rate_hz: u32,
-    blocks: &[u32],
-) -> Result<Vec<StepTriggerEvent>, String> {
-    let mut recall = recall_state_from_project(project, sample_rate_hz)?;
-    let mut events = Vec::new();
-    recall.sequencer_mut().start();
-    for frames in blocks {
-        events.extend(recall.sequencer_mut().process_block(*frames));
-    }
-    Ok(events)
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct TempoRamp {
+    start_bpm: f32,
+    target_bpm: f32,
+    duration_beats: f64,
+    elapsed_beats: f64,
 }
 
-pub fn engine_recall_from_project(
-    project: &presets_rs::Project,
-    sample_rate_hz: u32,
-) -> Result<EngineRecall, String> {
-    let recall = recall_state_from_project(project, sample_rate_hz)?;
-    Ok(recall.to_engine_recall())
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transport {
+    bpm: f32,
+    is_playing: bool,
+    ramp: Option<TempoRamp>,
 }
 
-#[cfg(test)]
-mod tests {
-    use abi_rs::{
-        ff_track_parameter_id, FF_PARAM_SLOT_CHOKE_GROUP, FF_PARAM_SLOT_GAIN, FF_PARAM_SLOT_PAN,
-        FF_PARAM_TRACK_BASE, FF_PARAM_TRACK_STRIDE,
-    };
-    use presets_rs::{
-        load_project_from_text, save_project_to_text, Kit, Pattern as PresetPattern, PatternStep,
-        Project, TrackAssignment, TrackControls,
-    };
+impl Default for Transport {
+    fn default() -> Self {
+        Self {
+            bpm: DEFAULT_BPM,
+            is_playing: false,
+            ramp: None,
+        }
+    }
+}
 
-    use super::{
-        engine_recall_from_project, recall_state_from_project, render_recall_events, Pattern,
-        Sequencer, Step, Transport, DEFAULT_BPM, MAX_BPM, MAX_SWING, MIN_BPM, STEPS_PER_PATTERN,
-        TRACK_COUNT,
-    };
+impl Transport {
+    pub fn bpm(&self) -> f32 {
+        self.bpm
+    }
 
-    const PHASE2_ENGINE_RECALL_FIXTURE: &str =
-        include_str!("../../../fixtures/interop/phase2_engine_recall_updates.csv");
+    pub fn is_playing(&self) -> bool {
+        self.is_playing
+    }
 
-    fn canonical_fixture_project() -> Project {
-        let mut project = Project {
-            name: "phase2-fixture".to_string(),
-            kits: vec![Kit::default()],
-            active_kit: Some(0),
-            patterns: vec![PresetPattern::default()],
-            active_pattern: Some(0),
-        };
-        project.kits[0].set_track_controls(
-            0,
-            TrackControls {
-                gain: 1.0,
-                pan: 1.0,
+    pub fn set_bpm(&mut self, bpm: f32) {
+        self.ramp = None;
+        self.bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+    }
+
+    pub fn ramp_bpm(&mut self, target: f32, duration_beats: f32) -> bool {
+        if !target.is_finite() || !duration_beats.is_finite() || duration_beats < 0.0 {
+            return false;
+        }
+        if duration_beats == 0.0 {
+            self.set_bpm(target);
+            return true;
+        }
+
+        self.ramp = Some(TempoRamp {
+            start_bpm: self.bpm,
+            target_bpm: target.clamp(MIN_BPM, MAX_BPM),
+            duration_beats: f64::from(duration_beats),
+            elapsed_beats: 0.0,
+        });
+        true
+    }
+
+    pub fn ramp_target(&self) -> Option<f32> {
+        self.ramp.map(|ramp| ramp.target_bpm)
+    }
+
+    fn advance_ramp(&mut self, beats: f64) {
+        let Some(ramp) = self.ramp.as_mut() else {
+            return;
+        };
+        ramp.elapsed_beats += beats;
+        let progress = (ramp.elapsed_beats / ramp.duration_beats).min(1.0) as f32;
+        self.bpm = ramp.start_bpm + (ramp.target_bpm - ramp.start_bpm) * progress;
+        if progress >= 1.0 {
+            self.bpm = ramp.target_bpm;
+            self.ramp = None;
+        }
+    }
+
+    fn finish_ramp(&mut self) {
+        if let Some(ramp) = self.ramp.take() {
+            self.bpm = ramp.target_bpm;
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.is_playing = true;
+    }
+
+    pub fn stop(&mut self) {
+        self.is_playing = false;
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StepTriggerEvent {
+    pub track_index: u8,
+    pub step_index: u8,
+    pub velocity: u8,
+    pub gate: u8,
+    pub choke_group: Option<u8>,
+    pub timeline_sample: u64,
+    pub block_offset: u32,
+    pub source: FfEventSource,
+}
+
+impl StepTriggerEvent {
+    pub fn order_key(&self) -> (u32, u32, u8) {
+        (
+            self.block_offset,
+            abi_rs::FF_EVENT_PRIORITY_NOTE_ON,
+            self.track_index,
+        )
+    }
+}
+
+pub fn sort_trigger_events(events: &mut [StepTriggerEvent]) {
+    events.sort_by_key(StepTriggerEvent::order_key);
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ClockBoundaries {
+    pub next_tick_sample: u64,
+    pub next_beat_sample: u64,
+    pub next_bar_sample: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SequencerPosition {
+    pub bar: u64,
+    pub pattern_bar: usize,
+    pub beat: u32,
+    pub tick: u32,
+    pub step_index: usize,
+    pub step_phase: f64,
+    pub timeline_sample: u64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StepChange {
+    pub bar: u64,
+    pub pattern_bar: usize,
+    pub step_index: usize,
+    pub block_offset: u32,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ClickKind {
+    Downbeat,
+    Beat,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ClickEvent {
+    pub kind: ClickKind,
+    pub count_in: bool,
+    pub timeline_sample: u64,
+    pub block_offset: u32,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RepeatRate {
+    Quarter,
+    Eighth,
+    Sixteenth,
+    ThirtySecond,
+}
+
+impl RepeatRate {
+    pub fn steps_per_hit(self) -> usize {
+        match self {
+            RepeatRate::Quarter => STEPS_PER_BEAT,
+            RepeatRate::Eighth => 2,
+            RepeatRate::Sixteenth | RepeatRate::ThirtySecond => 1,
+        }
+    }
+
+    pub fn hits_per_step(self) -> usize {
+        match self {
+            RepeatRate::ThirtySecond => 2,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NoteRepeat {
+    pub rate: RepeatRate,
+    pub velocity: u8,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TrackPerformance {
+    pub choke_group: Option<u8>,
+    pub muted: bool,
+    pub repeat: Option<NoteRepeat>,
+    pub humanize: Option<HumanizeDepth>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct PendingTrigger {
+    samples_until: f64,
+    track_index: u8,
+    step_index: u8,
+    velocity: u8,
+    gate: u8,
+    source: FfEventSource,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LaunchQuantize {
+    None,
+    Beat,
+    #[default]
+    Bar,
+}
+
+impl LaunchQuantize {
+    pub fn boundary_steps(self) -> Option<usize> {
+        match self {
+            LaunchQuantize::None => None,
+            LaunchQuantize::Beat => Some(STEPS_PER_BEAT),
+            LaunchQuantize::Bar => Some(STEPS_PER_PATTERN),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LiveAction {
+    SwitchPattern(Box<Pattern>),
+    SetTrackMute { track_index: usize, muted: bool },
+    SetFill(bool),
+    SwitchVariation(u8),
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum RecallTiming {
+    #[default]
+    Immediate,
+    NextBar,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScheduledRecall {
+    pub timeline_sample: u64,
+    pub block_offset: u32,
+    pub recall: EngineRecall,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PadInputRoute {
+    pub monitor: bool,
+    pub record: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct CountIn {
+    beat_samples: f64,
+    beats: u32,
+    elapsed_samples: f64,
+}
+
+#[derive(Debug)]
+pub struct Sequencer {
+    sample_rate_hz: u32,
+    transport: Transport,
+    pattern: Arc<Pattern>,
+    pattern_snapshots: Option<PatternSnapshots>,
+    snapshot_version: u64,
+    swing: f32,
+    pending_swing: Option<f32>,
+    groove: Option<GrooveTemplate>,
+    track_swing: [Option<f32>; TRACK_COUNT],
+    humanize: HumanizeDepth,
+    track_performance: [TrackPerformance; TRACK_COUNT],
+    current_step: usize,
+    current_bar: u64,
+    samples_to_next_step: f64,
+    timeline_sample: u64,
+    emit_step_on_next_process: bool,
+    resume_mid_step: bool,
+    random: RandomSource,
+    launch_quantize: LaunchQuantize,
+    pending_actions: Vec<LiveAction>,
+    fill_active: bool,
+    step_changes: Vec<StepChange>,
+    metronome_enabled: bool,
+    count_in_bars: u8,
+    count_in: Option<CountIn>,
+    click_events: Vec<ClickEvent>,
+    pending_triggers: Vec<PendingTrigger>,
+    track_inputs: TrackInputs,
+    edit_preview: bool,
+    pending_previews: Vec<StepTriggerEvent>,
+    pending_recall: Option<EngineRecall>,
+    due_recall: Option<ScheduledRecall>,
+    lock_bases: [[f32; LockTarget::ALL.len()]; TRACK_COUNT],
+    locked_targets: [u8; TRACK_COUNT],
+    lock_updates: Vec<coalesce::TimedParameterUpdate>,
+    last_fired: [bool; TRACK_COUNT],
+    pattern_pass: u64,
+    played_steps: [usize; TRACK_COUNT],
+    song: Option<Song>,
+    song_position: SongPosition,
+    process_faults: u64,
+    dropped_triggers: u64,
+}
+
+impl Sequencer {
+    pub fn new(sample_rate_hz: u32) -> Self {
+        let sample_rate_hz = sample_rate_hz.max(1);
+        let transport = Transport::default();
+        let samples_to_next_step = samples_per_step(sample_rate_hz, transport.bpm());
+
+        Self {
+            sample_rate_hz,
+            transport,
+            pattern: Arc::new(Pattern::default()),
+            pattern_snapshots: None,
+            snapshot_version: 0,
+            swing: 0.0,
+            pending_swing: None,
+            groove: None,
+            track_swing: [None; TRACK_COUNT],
+            humanize: HumanizeDepth::default(),
+            track_performance: [TrackPerformance::default(); TRACK_COUNT],
+            current_step: 0,
+            current_bar: 0,
+            samples_to_next_step,
+            timeline_sample: 0,
+            emit_step_on_next_process: false,
+            resume_mid_step: false,
+            random: RandomSource::default(),
+            launch_quantize: LaunchQuantize::default(),
+            pending_actions: Vec::new(),
+            fill_active: false,
+            step_changes: Vec::new(),
+            metronome_enabled: false,
+            count_in_bars: 0,
+            count_in: None,
+            click_events: Vec::new(),
+            pending_triggers: Vec::new(),
+            track_inputs: TrackInputs::default(),
+            edit_preview: true,
+            pending_previews: Vec::new(),
+            pending_recall: None,
+            due_recall: None,
+            lock_bases: [LockTarget::ALL.map(|target| TrackRecall::default().lock_base(target));
+                TRACK_COUNT],
+            locked_targets: [0; TRACK_COUNT],
+            lock_updates: Vec::new(),
+            last_fired: [false; TRACK_COUNT],
+            pattern_pass: 0,
+            played_steps: [0; TRACK_COUNT],
+            song: None,
+            song_position: SongPosition::default(),
+            process_faults: 0,
+            dropped_triggers: 0,
+        }
+    }
+
+    pub fn transport(&self) -> Transport {
+        self.transport
+    }
+
+    pub fn sample_rate_hz(&self) -> u32 {
+        self.sample_rate_hz
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate_hz: u32) -> bool {
+        if sample_rate_hz == 0 {
+            return false;
+        }
+        let ratio = f64::from(sample_rate_hz) / f64::from(self.sample_rate_hz);
+        let rescale = |sample: u64| (sample as f64 * ratio).round() as u64;
+        self.sample_rate_hz = sample_rate_hz;
+        self.samples_to_next_step *= ratio;
+        self.timeline_sample = rescale(self.timeline_sample);
+        for pending in &mut self.pending_triggers {
+            pending.samples_until *= ratio;
+        }
+        for preview in &mut self.pending_previews {
+            preview.timeline_sample = rescale(preview.timeline_sample);
+        }
+        if let Some(due) = &mut self.due_recall {
+            due.timeline_sample = rescale(due.timeline_sample);
+            due.block_offset = (f64::from(due.block_offset) * ratio).round() as u32;
+        }
+        if let Some(count_in) = &mut self.count_in {
+            count_in.beat_samples *= ratio;
+            count_in.elapsed_samples *= ratio;
+        }
+        true
+    }
+
+    pub fn set_tempo_bpm(&mut self, bpm: f32) {
+        self.transport.set_bpm(bpm);
+        self.samples_to_next_step = self
+            .samples_to_next_step
+            .min(self.step_interval_samples(self.current_step));
+    }
+
+    pub fn ramp_tempo_bpm(&mut self, target: f32, duration_beats: f32) -> bool {
+        if !self.transport.ramp_bpm(target, duration_beats) {
+            return false;
+        }
+        self.samples_to_next_step = self
+            .samples_to_next_step
+            .min(self.step_interval_samples(self.current_step));
+        true
+    }
+
+    pub fn tempo_glide_target(&self) -> Option<f32> {
+        self.transport.ramp_target()
+    }
+
+    fn apply_pattern_feel(&mut self) {
+        if let Some(swing) = self.pattern.swing {
+            self.set_swing(swing);
+            self.groove = self.pattern.groove.clone();
+        }
+    }
+
+    fn apply_pattern_tempo(&mut self, tempo: PatternTempo) {
+        if tempo.change == TempoChange::Immediate || !self.transport.is_playing() {
+            self.set_tempo_bpm(tempo.bpm);
+        } else {
+            self.ramp_tempo_bpm(tempo.bpm, TEMPO_GLIDE_BEATS as f32);
+        }
+    }
+
+    pub fn set_swing(&mut self, swing: f32) {
+        self.pending_swing = None;
+        self.swing = swing.clamp(0.0, MAX_SWING);
+        self.samples_to_next_step = self
+            .samples_to_next_step
+            .min(self.step_interval_samples(self.current_step));
+    }
+
+    pub fn swing(&self) -> f32 {
+        self.swing
+    }
+
+    pub fn automate_swing(&mut self, swing: f32) {
+        if self.transport.is_playing() {
+            self.pending_swing = Some(swing.clamp(0.0, MAX_SWING));
+        } else {
+            self.set_swing(swing);
+        }
+    }
+
+    pub fn pending_swing(&self) -> Option<f32> {
+        self.pending_swing
+    }
+
+    pub fn track_swing(&self, track_index: usize) -> Option<f32> {
+        self.track_swing.get(track_index).copied().flatten()
+    }
+
+    pub fn set_track_swing(&mut self, track_index: usize, swing: Option<f32>) -> bool {
+        if track_index >= TRACK_COUNT
+            || swing.is_some_and(|swing| !(0.0..=MAX_SWING).contains(&swing))
+        {
+            return false;
+        }
+        self.track_swing[track_index] = swing;
+        self.samples_to_next_step = self
+            .samples_to_next_step
+            .min(self.step_interval_samples(self.current_step));
+        true
+    }
+
+    pub fn effective_track_swing(&self, track_index: usize) -> f32 {
+        self.track_swing(track_index).unwrap_or(self.swing)
+    }
+
+    // The step grid swings by the least-swung track so no track ever has to
+    // fire ahead of its step boundary; tracks swung harder than the grid are
+    // delayed by the difference on odd steps.
+    fn grid_swing(&self) -> f32 {
+        (0..TRACK_COUNT).fold(self.swing, |grid, track_index| {
+            grid.min(self.effective_track_swing(track_index))
+        })
+    }
+
+    pub fn groove(&self) -> Option<&GrooveTemplate> {
+        self.groove.as_ref()
+    }
+
+    pub fn set_groove(&mut self, groove: Option<GrooveTemplate>) -> bool {
+        if groove.as_ref().is_some_and(|groove| !groove.is_valid()) {
+            return false;
+        }
+        self.groove = groove;
+        true
+    }
+
+    pub fn apply_parameter_update(&mut self, update: &abi_rs::FfParameterUpdate) -> bool {
+        match update.parameter_id {
+            abi_rs::FF_PARAM_GLOBAL_SWING => {
+                self.automate_swing(abi_rs::ff_swing_from_normalized(update.normalized_value));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn apply_pending_swing(&mut self) {
+        if let Some(swing) = self.pending_swing.take() {
+            self.swing = swing;
+        }
+    }
+
+    pub fn random_seed(&self) -> u64 {
+        self.random.seed()
+    }
+
+    pub fn set_random_seed(&mut self, seed: u64) {
+        self.random.reseed(seed);
+    }
+
+    pub fn random_mut(&mut self) -> &mut RandomSource {
+        &mut self.random
+    }
+
+    pub fn launch_quantize(&self) -> LaunchQuantize {
+        self.launch_quantize
+    }
+
+    pub fn set_launch_quantize(&mut self, quantize: LaunchQuantize) {
+        self.launch_quantize = quantize;
+    }
+
+    pub fn queue_action(&mut self, action: LiveAction) -> bool {
+        match action {
+            LiveAction::SetTrackMute { track_index, .. } if track_index >= TRACK_COUNT => {
+                return false;
+            }
+            LiveAction::SwitchVariation(variation)
+                if usize::from(variation) >= PATTERN_VARIATION_COUNT =>
+            {
+                return false;
+            }
+            _ => {}
+        }
+        if self.launch_quantize == LaunchQuantize::None || !self.transport.is_playing() {
+            self.apply_action(action);
+        } else {
+            self.pending_actions.push(action);
+        }
+        true
+    }
+
+    pub fn pending_action_count(&self) -> usize {
+        self.pending_actions.len()
+    }
+
+    pub fn song(&self) -> Option<&Song> {
+        self.song.as_ref()
+    }
+
+    pub fn set_song(&mut self, song: Option<Song>) -> bool {
+        if song.as_ref().is_some_and(|song| song.chain().is_empty()) {
+            return false;
+        }
+
+        self.song = song;
+        self.song_position = SongPosition::default();
+        self.load_song_entry();
+        true
+    }
+
+    pub fn song_position(&self) -> Option<SongPosition> {
+        self.song.as_ref().map(|_| self.song_position)
+    }
+
+    fn load_song_entry(&mut self) {
+        let Some(pattern) = self
+            .song
+            .as_ref()
+            .and_then(|song| song.entry_pattern(self.song_position.entry_index))
+        else {
+            return;
+        };
+        self.apply_action(LiveAction::SwitchPattern(Box::new(pattern.clone())));
+    }
+
+    fn advance_song(&mut self) -> bool {
+        let Some(song) = &self.song else {
+            return true;
+        };
+        let next = self.song_position.next(song);
+        let entry_changed =
+            next.is_none_or(|next| next.entry_index != self.song_position.entry_index);
+        self.song_position = next.unwrap_or_default();
+        if entry_changed {
+            self.load_song_entry();
+        }
+        next.is_some()
+    }
+
+    pub fn schedule_recall(&mut self, recall: EngineRecall, timing: RecallTiming) {
+        if timing == RecallTiming::NextBar && self.transport.is_playing() {
+            self.pending_recall = Some(recall);
+        } else {
+            self.pending_recall = None;
+            self.release_recall(recall, 0);
+        }
+    }
+
+    pub fn has_pending_recall(&self) -> bool {
+        self.pending_recall.is_some()
+    }
+
+    pub fn take_due_recall(&mut self) -> Option<ScheduledRecall> {
+        self.due_recall.take()
+    }
+
+    fn release_recall(&mut self, recall: EngineRecall, block_offset: u32) {
+        self.due_recall = Some(ScheduledRecall {
+            timeline_sample: self.timeline_sample + u64::from(block_offset),
+            block_offset,
+            recall,
+        });
+    }
+
+    fn release_pending_recall(&mut self, block_offset: u32) {
+        if let Some(recall) = self.pending_recall.take() {
+            self.release_recall(recall, block_offset);
+        }
+    }
+
+    pub fn track_muted(&self, track_index: usize) -> bool {
+        self.track_performance
+            .get(track_index)
+            .is_some_and(|performance| performance.muted)
+    }
+
+    pub fn fill_active(&self) -> bool {
+        self.fill_active
+    }
+
+    pub fn set_note_repeat(&mut self, track_index: usize, repeat: Option<NoteRepeat>) -> bool {
+        if track_index >= TRACK_COUNT || repeat.is_some_and(|repeat| repeat.velocity > 127) {
+            return false;
+        }
+
+        self.track_performance[track_index].repeat = repeat;
+        if repeat.is_none() {
+            self.pending_triggers.retain(|pending| {
+                pending.source != FfEventSource::NoteRepeat
+                    || usize::from(pending.track_index) != track_index
+            });
+        }
+        true
+    }
+
+    pub fn note_repeat(&self, track_index: usize) -> Option<NoteRepeat> {
+        self.track_performance
+            .get(track_index)
+            .and_then(|performance| performance.repeat)
+    }
+
+    fn apply_action(&mut self, action: LiveAction) {
+        match action {
+            LiveAction::SwitchPattern(pattern) => {
+                self.pattern = Arc::new(*pattern);
+                self.pattern_pass = 0;
+                self.current_step %= self.pattern.step_count();
+                if let Some(tempo) = self.pattern.tempo {
+                    self.apply_pattern_tempo(tempo);
+                }
+                self.apply_pattern_feel();
+            }
+            LiveAction::SetTrackMute { track_index, muted } => {
+                self.track_performance[track_index].muted = muted;
+            }
+            LiveAction::SetFill(active) => self.fill_active = active,
+            LiveAction::SwitchVariation(variation) => {
+                Arc::make_mut(&mut self.pattern).set_active_variation(variation);
+            }
+        }
+    }
+
+    fn apply_pending_actions(&mut self, step_index: usize) {
+        let on_boundary = self
+            .launch_quantize
+            .boundary_steps()
+            .is_none_or(|steps| step_index.is_multiple_of(steps));
+        if on_boundary && !self.pending_actions.is_empty() {
+            for action in std::mem::take(&mut self.pending_actions) {
+                self.apply_action(action);
+            }
+        }
+    }
+
+    pub fn track_inputs(&self) -> TrackInputs {
+        self.track_inputs
+    }
+
+    pub fn set_track_inputs(&mut self, inputs: TrackInputs) {
+        self.track_inputs = inputs;
+    }
+
+    pub fn set_track_armed(&mut self, track_index: usize, armed: bool) -> bool {
+        self.track_inputs.set_armed(track_index, armed)
+    }
+
+    pub fn set_track_monitoring(&mut self, track_index: usize, monitoring: bool) -> bool {
+        self.track_inputs.set_monitoring(track_index, monitoring)
+    }
+
+    pub fn route_pad_input(&self, track_index: usize) -> PadInputRoute {
+        PadInputRoute {
+            monitor: self.track_inputs.is_monitoring(track_index),
+            record: self.track_inputs.is_armed(track_index),
+        }
+    }
+
+    pub fn set_track_humanize(
+        &mut self,
+        track_index: usize,
+        humanize: Option<HumanizeDepth>,
+    ) -> bool {
+        if track_index >= TRACK_COUNT || humanize.is_some_and(|humanize| !humanize.is_valid()) {
+            return false;
+        }
+
+        self.track_performance[track_index].humanize = humanize;
+        true
+    }
+
+    pub fn set_humanize(&mut self, humanize: HumanizeDepth) -> bool {
+        if !humanize.is_valid() {
+            return false;
+        }
+        self.humanize = humanize;
+        true
+    }
+
+    pub fn humanize(&self) -> HumanizeDepth {
+        self.humanize
+    }
+
+    pub fn effective_track_humanize(&self, track_index: usize) -> HumanizeDepth {
+        self.track_humanize(track_index).unwrap_or(self.humanize)
+    }
+
+    pub fn track_humanize(&self, track_index: usize) -> Option<HumanizeDepth> {
+        self.track_performance
+            .get(track_index)
+            .and_then(|performance| performance.humanize)
+    }
+
+    pub fn set_track_choke_group(&mut self, track_index: usize, choke_group: Option<u8>) -> bool {
+        if track_index >= TRACK_COUNT {
+            return false;
+        }
+
+        self.track_performance[track_index].choke_group = choke_group;
+        true
+    }
+
+    pub fn set_track_lock_base(
+        &mut self,
+        track_index: usize,
+        target: LockTarget,
+        value: f32,
+    ) -> bool {
+        if track_index >= TRACK_COUNT || !(0.0..=1.0).contains(&value) {
+            return false;
+        }
+
+        self.lock_bases[track_index][target.index()] = value;
+        true
+    }
+
+    pub fn track_lock_base(&self, track_index: usize, target: LockTarget) -> Option<f32> {
+        self.lock_bases
+            .get(track_index)
+            .map(|bases| bases[target.index()])
+    }
+
+    pub fn parameter_lock_updates(&self) -> &[coalesce::TimedParameterUpdate] {
+        &self.lock_updates
+    }
+
+    pub fn edit_preview(&self) -> bool {
+        self.edit_preview
+    }
+
+    pub fn set_edit_preview(&mut self, enabled: bool) {
+        self.edit_preview = enabled;
+        if !enabled {
+            self.pending_previews.clear();
+        }
+    }
+
+    pub fn edit_step(&mut self, track_index: usize, step_index: usize, step: Step) -> bool {
+        if !Arc::make_mut(&mut self.pattern).set_step(track_index, step_index, step) {
+            return false;
+        }
+        if self.edit_preview
+            && step.active
+            && !self.transport.is_playing()
+            && !self.track_muted(track_index)
+        {
+            self.pending_previews.push(StepTriggerEvent {
+                track_index: track_index as u8,
+                step_index: step_index as u8,
+                velocity: self.pattern.step_velocity(step),
+                gate: step.gate,
+                choke_group: self.track_performance[track_index].choke_group,
+                timeline_sample: self.timeline_sample,
+                block_offset: 0,
+                source: FfEventSource::LivePad,
+            });
+        }
+        true
+    }
+
+    pub fn start(&mut self) {
+        self.pending_previews.clear();
+        if !self.transport.is_playing() {
+            self.transport.start();
+            self.emit_step_on_next_process = !self.resume_mid_step;
+            self.resume_mid_step = false;
+            if self.emit_step_on_next_process && self.count_in_bars > 0 {
+                self.count_in = Some(CountIn {
+                    beat_samples: samples_per_step(self.sample_rate_hz, self.transport.bpm())
+                        * STEPS_PER_BEAT as f64,
+                    beats: u32::from(self.count_in_bars)
+                        * (STEPS_PER_PATTERN / STEPS_PER_BEAT) as u32,
+                    elapsed_samples: 0.0,
+                });
+            }
+        }
+    }
+
+    pub fn metronome_enabled(&self) -> bool {
+        self.metronome_enabled
+    }
+
+    pub fn set_metronome_enabled(&mut self, enabled: bool) {
+        self.metronome_enabled = enabled;
+    }
+
+    pub fn count_in_bars(&self) -> u8 {
+        self.count_in_bars
+    }
+
+    pub fn set_count_in_bars(&mut self, bars: u8) -> bool {
+        if bars > MAX_COUNT_IN_BARS {
+            return false;
+        }
+        self.count_in_bars = bars;
+        true
+    }
+
+    pub fn is_counting_in(&self) -> bool {
+        self.count_in.is_some()
+    }
+
+    pub fn click_events(&self) -> &[ClickEvent] {
+        &self.click_events
+    }
+
+    pub fn stop(&mut self) {
+        self.transport.finish_ramp();
+        self.transport.stop();
+        self.emit_step_on_next_process = false;
+        self.count_in = None;
+        self.pending_triggers.clear();
+    }
+
+    pub fn reset(&mut self) {
+        self.current_step = 0;
+        self.current_bar = 0;
+        self.timeline_sample = 0;
+        self.samples_to_next_step = self.step_interval_samples(self.current_step);
+        self.emit_step_on_next_process = false;
+        self.resume_mid_step = false;
+        self.random.rewind();
+        self.pending_triggers.clear();
+        self.last_fired = [false; TRACK_COUNT];
+        self.pattern_pass = 0;
+        if self.song.is_some() {
+            self.song_position = SongPosition::default();
+            self.load_song_entry();
+        }
+    }
+
+    pub fn loop_count(&self) -> u64 {
+        self.pattern_pass
+    }
+
+    pub fn track_last_fired(&self, track_index: usize) -> Option<bool> {
+        self.last_fired.get(track_index).copied()
+    }
+
+    pub fn track_play_step(&self, track_index: usize) -> Option<usize> {
+        self.played_steps.get(track_index).copied()
+    }
+
+    pub fn position(&self) -> SequencerPosition {
+        let step_phase = if self.emit_step_on_next_process {
+            0.0
+        } else {
+            let interval = self.step_interval_samples(self.current_step);
+            (1.0 - self.samples_to_next_step / interval).clamp(0.0, 1.0)
+        };
+        let step_in_beat = (self.current_step % STEPS_PER_BEAT) as f64 + step_phase;
+        let tick = (step_in_beat * f64::from(TICKS_PER_BEAT) / STEPS_PER_BEAT as f64) as u32;
+
+        SequencerPosition {
+            bar: self.current_bar,
+            pattern_bar: self.current_step / STEPS_PER_PATTERN,
+            beat: (self.current_step % STEPS_PER_PATTERN / STEPS_PER_BEAT) as u32,
+            tick: tick.min(TICKS_PER_BEAT - 1),
+            step_index: self.current_step,
+            step_phase,
+            timeline_sample: self.timeline_sample,
+        }
+    }
+
+    pub fn next_clock_boundaries(&self) -> Option<ClockBoundaries> {
+        if !self.transport.is_playing() {
+            return None;
+        }
+
+        let ticks_per_step = f64::from(CLOCK_PPQN) / STEPS_PER_BEAT as f64;
+        let (step_index, step_offset, tick_offset) = if self.emit_step_on_next_process {
+            (self.current_step, 0.0, 0.0)
+        } else {
+            let remaining = self.samples_to_next_step.max(0.0);
+            let tick_length = self.step_interval_samples(self.current_step) / ticks_per_step;
+            let later_ticks = ((remaining / tick_length).ceil() - 1.0).max(0.0);
+            (
+                (self.current_step + 1) % self.pattern.step_count(),
+                remaining,
+                remaining - later_ticks * tick_length,
+            )
+        };
+
+        let boundary_offset = |steps: usize| {
+            let mut step_index = step_index;
+            let mut offset = step_offset;
+            while !step_index.is_multiple_of(steps) {
+                offset += self.step_interval_samples(step_index);
+                step_index = (step_index + 1) % self.pattern.step_count();
+            }
+            offset
+        };
+        let at = |offset: f64| self.timeline_sample + offset.round() as u64;
+        Some(ClockBoundaries {
+            next_tick_sample: at(tick_offset),
+            next_beat_sample: at(boundary_offset(STEPS_PER_BEAT)),
+            next_bar_sample: at(boundary_offset(STEPS_PER_PATTERN)),
+        })
+    }
+
+    pub fn locate(
+        &mut self,
+        bar: u64,
+        step_index: usize,
+        step_phase: f64,
+        timeline_sample: u64,
+    ) -> bool {
+        if step_index >= self.pattern.step_count() || !(0.0..1.0).contains(&step_phase) {
+            return false;
+        }
+
+        self.current_bar = bar;
+        self.timeline_sample = timeline_sample;
+        self.place_playhead(step_index, step_phase);
+        true
+    }
+
+    pub fn seek_to_step(&mut self, step_index: usize, step_phase: f64) -> bool {
+        if step_index >= self.pattern.step_count() || !(0.0..1.0).contains(&step_phase) {
+            return false;
+        }
+
+        let scheduled = self.pending_triggers.len();
+        self.pending_triggers
+            .retain(|pending| pending.source != FfEventSource::Sequencer);
+        self.dropped_triggers += (scheduled - self.pending_triggers.len()) as u64;
+        self.place_playhead(step_index, step_phase);
+        true
+    }
+
+    fn place_playhead(&mut self, step_index: usize, step_phase: f64) {
+        self.current_step = step_index;
+        self.samples_to_next_step = self.step_interval_samples(step_index) * (1.0 - step_phase);
+
+        let on_step_boundary = step_phase <= f64::EPSILON;
+        if self.transport.is_playing() {
+            self.emit_step_on_next_process = on_step_boundary;
+            self.resume_mid_step = false;
+        } else {
+            self.emit_step_on_next_process = false;
+            self.resume_mid_step = !on_step_boundary;
+        }
+    }
+
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    pub fn pattern_mut(&mut self) -> &mut Pattern {
+        Arc::make_mut(&mut self.pattern)
+    }
+
+    pub fn pattern_snapshots(&self) -> Option<&PatternSnapshots> {
+        self.pattern_snapshots.as_ref()
+    }
+
+    pub fn snapshot_version(&self) -> u64 {
+        self.snapshot_version
+    }
+
+    pub fn attach_pattern_snapshots(&mut self, snapshots: PatternSnapshots) {
+        self.pattern_snapshots = Some(snapshots);
+        self.snapshot_version = 0;
+    }
+
+    pub fn detach_pattern_snapshots(&mut self) -> Option<PatternSnapshots> {
+        self.snapshot_version = 0;
+        self.pattern_snapshots.take()
+    }
+
+    fn adopt_pattern_snapshot(&mut self) {
+        let Some((version, pattern)) = self
+            .pattern_snapshots
+            .as_ref()
+            .and_then(|snapshots| snapshots.newer_than(self.snapshot_version))
+        else {
+            return;
+        };
+        let tempo_changed = pattern.tempo != self.pattern.tempo;
+        let feel_changed =
+            pattern.swing != self.pattern.swing || pattern.groove != self.pattern.groove;
+        self.snapshot_version = version;
+        self.pattern = pattern;
+        self.current_step %= self.pattern.step_count();
+        if let Some(tempo) = self.pattern.tempo.filter(|_| tempo_changed) {
+            self.apply_pattern_tempo(tempo);
+        }
+        if feel_changed {
+            self.apply_pattern_feel();
+        }
+    }
+
+    pub fn sub_sample_phase(&self) -> f64 {
+        self.samples_to_next_step.rem_euclid(1.0)
+    }
+
+    pub fn step_changes(&self) -> &[StepChange] {
+        &self.step_changes
+    }
+
+    pub fn process_block(&mut self, frames: u32) -> Vec<StepTriggerEvent> {
+        self.process_block_guarded(frames, None::<fn(u64, &mut Pattern)>)
+    }
+
+    pub fn process_faults(&self) -> u64 {
+        self.process_faults
+    }
+
+    pub fn take_dropped_triggers(&mut self) -> u64 {
+        std::mem::take(&mut self.dropped_triggers)
+    }
+
+    pub fn scheduled_trigger_count(&self) -> usize {
+        self.pending_triggers.len() + self.pending_previews.len()
+    }
+
+    // A panic inside step processing (a bar hook, a script, a logic error in
+    // a feature) is caught here so it never unwinds through the audio thread.
+    // The faulted block emits nothing, in-flight scheduled triggers are
+    // dropped, and the timeline still advances by the block length.
+    pub fn process_block_with_bar_hook<F>(
+        &mut self,
+        frames: u32,
+        on_bar: F,
+    ) -> Vec<StepTriggerEvent>
+    where
+        F: FnMut(u64, &mut Pattern),
+    {
+        self.process_block_guarded(frames, Some(on_bar))
+    }
+
+    fn process_block_guarded<F>(&mut self, frames: u32, on_bar: Option<F>) -> Vec<StepTriggerEvent>
+    where
+        F: FnMut(u64, &mut Pattern),
+    {
+        let timeline_sample = self.timeline_sample;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            self.process_block_unguarded(frames, on_bar)
+        }));
+        match result {
+            Ok(events) => events,
+            Err(_) => {
+                self.recover_from_fault(timeline_sample, frames);
+                Vec::new()
+            }
+        }
+    }
+
+    fn recover_from_fault(&mut self, timeline_sample: u64, frames: u32) {
+        self.process_faults = self.process_faults.saturating_add(1);
+        self.dropped_triggers += self.scheduled_trigger_count() as u64;
+        self.step_changes.clear();
+        self.click_events.clear();
+        self.lock_updates.clear();
+        self.pending_triggers.clear();
+        self.pending_previews.clear();
+        self.emit_step_on_next_process = false;
+        if self.current_step >= self.pattern.step_count() {
+            self.current_step = 0;
+        }
+        if !self.samples_to_next_step.is_finite() || self.samples_to_next_step < 0.0 {
+            self.samples_to_next_step = samples_per_step(self.sample_rate_hz, self.transport.bpm());
+        }
+        self.timeline_sample = timeline_sample + u64::from(frames);
+    }
+
+    fn process_block_unguarded<F>(
+        &mut self,
+        frames: u32,
+        mut on_bar: Option<F>,
+    ) -> Vec<StepTriggerEvent>
+    where
+        F: FnMut(u64, &mut Pattern),
+    {
+        self.step_changes.clear();
+        self.click_events.clear();
+        self.lock_updates.clear();
+        if frames == 0 {
+            return Vec::new();
+        }
+        if let Some(due) = &mut self.due_recall {
+            due.timeline_sample = self.timeline_sample;
+            due.block_offset = 0;
+        }
+        if !self.transport.is_playing() {
+            self.adopt_pattern_snapshot();
+            self.release_pending_recall(0);
+            let mut previews = std::mem::take(&mut self.pending_previews);
+            sort_trigger_events(&mut previews);
+            return previews;
+        }
+
+        let mut events = Vec::new();
+        let block_frames = f64::from(frames);
+        let Some(lead_in) = self.advance_count_in(frames) else {
+            self.collect_pending_triggers(frames, &mut events);
+            sort_trigger_events(&mut events);
+            self.timeline_sample += u64::from(frames);
+            return events;
+        };
+        let lead_offset = lead_in.round() as u32;
+        if self.emit_step_on_next_process {
+            self.adopt_pattern_snapshot();
+            self.apply_pending_actions(self.current_step);
+            if self.current_step.is_multiple_of(STEPS_PER_PATTERN) {
+                self.release_pending_recall(lead_offset);
+                if let Some(on_bar) = &mut on_bar {
+                    on_bar(self.current_bar, Arc::make_mut(&mut self.pattern));
+                }
+            }
+            self.record_step_change(lead_offset);
+            self.collect_step_events(self.current_step, lead_in, &mut events);
+            self.emit_step_on_next_process = false;
+            self.apply_pending_swing();
+            self.samples_to_next_step = self.ramped_step_interval_samples(self.current_step);
+            self.schedule_repeats(lead_in);
+        }
+
+        let mut consumed = lead_in;
+        loop {
+            let step_position = consumed + self.samples_to_next_step;
+            let offset = step_position.round().max(0.0);
+            if offset >= block_frames {
+                self.samples_to_next_step = step_position - block_frames;
+                break;
+            }
+
+            consumed = step_position;
+            let offset = offset as u32;
+            self.current_step += 1;
+            if self.current_step >= self.pattern.step_count() {
+                self.current_step = 0;
+                self.pattern_pass += 1;
+                if !self.advance_song() {
+                    self.stop();
+                    self.samples_to_next_step = self.step_interval_samples(0);
+                    break;
+                }
+            }
+            self.adopt_pattern_snapshot();
+            self.apply_pending_actions(self.current_step);
+            if self.current_step.is_multiple_of(STEPS_PER_PATTERN) {
+                self.current_bar += 1;
+                self.release_pending_recall(offset);
+                if let Some(on_bar) = &mut on_bar {
+                    on_bar(self.current_bar, Arc::make_mut(&mut self.pattern));
+                }
+            }
+            self.record_step_change(offset);
+            self.collect_step_events(self.current_step, consumed, &mut events);
+            self.apply_pending_swing();
+            self.samples_to_next_step = self.ramped_step_interval_samples(self.current_step);
+            self.schedule_repeats(consumed);
+        }
+
+        self.collect_pending_triggers(frames, &mut events);
+        sort_trigger_events(&mut events);
+        self.timeline_sample += u64::from(frames);
+        events
+    }
+
+    fn schedule_repeats(&mut self, step_start: f64) {
+        let step_index = self.current_step;
+        let interval = self.samples_to_next_step;
+        for track_index in 0..TRACK_COUNT {
+            let performance = self.track_performance[track_index];
+            let Some(repeat) = performance.repeat else {
+                continue;
+            };
+            if performance.muted
+                || self
+                    .pattern
+                    .track_muted_at(track_index, step_index / STEPS_PER_PATTERN)
+                || !step_index.is_multiple_of(repeat.rate.steps_per_hit())
+            {
+                continue;
+            }
+
+            let hits = repeat.rate.hits_per_step();
+            let programmed = self
+                .pattern
+                .playing(track_index, self.played_steps[track_index])
+                .active;
+            for hit in usize::from(programmed)..hits {
+                self.pending_triggers.push(PendingTrigger {
+                    samples_until: step_start + interval * hit as f64 / hits as f64,
+                    track_index: track_index as u8,
+                    step_index: step_index as u8,
+                    velocity: repeat.velocity,
+                    gate: MAX_STEP_GATE,
+                    source: FfEventSource::NoteRepeat,
+                });
+            }
+        }
+    }
+
+    fn collect_pending_triggers(&mut self, frames: u32, output: &mut Vec<StepTriggerEvent>) {
+        if self.pending_triggers.is_empty() {
+            return;
+        }
+
+        // Triggers are released on the sample they round to, so a hit due a
+        // hair before a block boundary still lands on the boundary.
+        let block_frames = f64::from(frames);
+        for pending in &mut self.pending_triggers {
+            if pending.samples_until < block_frames - 0.5 {
+                let block_offset = (pending.samples_until.round() as u32).min(frames - 1);
+                output.push(StepTriggerEvent {
+                    track_index: pending.track_index,
+                    step_index: pending.step_index,
+                    velocity: pending.velocity,
+                    gate: pending.gate,
+                    choke_group: self.track_performance[usize::from(pending.track_index)]
+                        .choke_group,
+                    timeline_sample: self.timeline_sample + u64::from(block_offset),
+                    block_offset,
+                    source: pending.source,
+                });
+            }
+            pending.samples_until -= block_frames;
+        }
+        self.pending_triggers
+            .retain(|pending| pending.samples_until >= -0.5);
+    }
+
+    fn record_step_change(&mut self, block_offset: u32) {
+        self.step_changes.push(StepChange {
+            bar: self.current_bar,
+            pattern_bar: self.current_step / STEPS_PER_PATTERN,
+            step_index: self.current_step,
+            block_offset,
+        });
+        if self.metronome_enabled && self.current_step.is_multiple_of(STEPS_PER_BEAT) {
+            let kind = if self.current_step.is_multiple_of(STEPS_PER_PATTERN) {
+                ClickKind::Downbeat
+            } else {
+                ClickKind::Beat
+            };
+            self.push_click(kind, false, block_offset);
+        }
+    }
+
+    fn push_click(&mut self, kind: ClickKind, count_in: bool, block_offset: u32) {
+        self.click_events.push(ClickEvent {
+            kind,
+            count_in,
+            timeline_sample: self.timeline_sample + u64::from(block_offset),
+            block_offset,
+        });
+    }
+
+    fn advance_count_in(&mut self, frames: u32) -> Option<f64> {
+        let Some(mut count_in) = self.count_in else {
+            return Some(0.0);
+        };
+        let block_frames = f64::from(frames);
+        let beats_per_bar = (STEPS_PER_PATTERN / STEPS_PER_BEAT) as u32;
+        // A beat within half a sample before the block rounded past the end of
+        // the previous one, so it is clicked at the start of this block.
+        let first_beat = ((count_in.elapsed_samples - 0.5) / count_in.beat_samples)
+            .ceil()
+            .max(0.0) as u32;
+        for beat in first_beat..count_in.beats {
+            let offset = (f64::from(beat) * count_in.beat_samples - count_in.elapsed_samples)
+                .round()
+                .max(0.0);
+            if offset >= block_frames {
+                break;
+            }
+            let kind = if beat.is_multiple_of(beats_per_bar) {
+                ClickKind::Downbeat
+            } else {
+                ClickKind::Beat
+            };
+            self.push_click(kind, true, offset as u32);
+        }
+
+        let remaining =
+            f64::from(count_in.beats) * count_in.beat_samples - count_in.elapsed_samples;
+        if remaining.round() < block_frames {
+            self.count_in = None;
+            return Some(remaining.max(0.0));
+        }
+        count_in.elapsed_samples += block_frames;
+        self.count_in = Some(count_in);
+        None
+    }
+
+    fn collect_step_events(
+        &mut self,
+        position: usize,
+        step_start: f64,
+        output: &mut Vec<StepTriggerEvent>,
+    ) {
+        for track_index in 0..TRACK_COUNT {
+            let resolution = self.pattern.resolutions[track_index];
+            if resolution == StepResolution::Sixteenth {
+                let row = (position, self.pattern_pass);
+                self.collect_track_step(track_index, position, row, 0.0, step_start, output);
+                continue;
+            }
+
+            let step_count = self.pattern.step_count();
+            let window_start = position as f64 + self.step_offset(position);
+            let first = self.first_track_step_at(position, resolution);
+            let last = self.first_track_step_at(position + 1, resolution);
+            let pass_steps = self.first_track_step_at(step_count, resolution) as u64;
+            let track_step_length = f64::from(resolution.ticks_per_step()) / GRID_STEP_TICKS;
+            for track_step in first..last {
+                let at = track_step as f64 * track_step_length;
+                let swung = match self.pattern.swing_grid {
+                    SwingGrid::Track if track_step % 2 == 1 => track_step_length,
+                    SwingGrid::Sixteenth if (at.floor() as usize) % 2 == 1 => 1.0,
+                    _ => 0.0,
+                };
+                let swing = swung * f64::from(self.effective_track_swing(track_index));
+                let lead = ((at - window_start).max(0.0) + swing) * self.base_step_samples();
+                let row_step = self.pattern_pass * pass_steps + track_step as u64;
+                let row = (
+                    (row_step % step_count as u64) as usize,
+                    row_step / step_count as u64,
+                );
+                self.collect_track_step(track_index, position, row, lead, step_start, output);
+            }
+        }
+    }
+
+    // Tracks on a finer or triplet grid are laid out from the top of the
+    // pattern and swing on their own grid. Each grid step owns the track steps
+    // whose straight time falls between its own (swung) start and the next
+    // one's, so a pass plays every track step exactly once whatever the swing
+    // or groove. The track's row loops on its own: a 1/16T row of 16 steps
+    // plays one and a half times per pattern pass and picks up where it left
+    // off on the next pass.
+    fn first_track_step_at(&self, position: usize, resolution: StepResolution) -> usize {
+        let step_count = self.pattern.step_count();
+        let ticks_per_step = f64::from(resolution.ticks_per_step());
+        if position == 0 {
+            return 0;
+        }
+        if position >= step_count {
+            return (step_count as f64 * GRID_STEP_TICKS / ticks_per_step).ceil() as usize;
+        }
+        let start = position as f64 + self.step_offset(position);
+        (start * GRID_STEP_TICKS / ticks_per_step).ceil().max(0.0) as usize
+    }
+
+    fn collect_track_step(
+        &mut self,
+        track_index: usize,
+        position: usize,
+        (row_position, row_pass): (usize, u64),
+        lead: f64,
+        step_start: f64,
+        output: &mut Vec<StepTriggerEvent>,
+    ) {
+        let block_offset = step_start.round().max(0.0) as u32;
+        let step_index = self.directed_step(track_index, row_position, row_pass);
+        self.played_steps[track_index] = step_index;
+        let step = self.pattern.playing(track_index, step_index);
+        if !step.active {
+            return;
+        }
+        let fires = !self.track_performance[track_index].muted
+            && !self
+                .pattern
+                .track_muted_at(track_index, position / STEPS_PER_PATTERN)
+            && self.condition_met(track_index, step.condition)
+            && (step.probability >= MAX_STEP_PROBABILITY
+                || self
+                    .random
+                    .chance(f64::from(step.probability) / f64::from(MAX_STEP_PROBABILITY)));
+        self.last_fired[track_index] = fires;
+        if !fires {
+            return;
+        }
+
+        self.collect_lock_updates(track_index, step_index, block_offset);
+        let humanize = self.effective_track_humanize(track_index);
+        let mut velocity = self.pattern.step_velocity(step);
+        if let Some(groove) = &self.groove {
+            velocity = (i16::from(velocity) + i16::from(groove.velocity_offset(step_index)))
+                .clamp(1, 127) as u8;
+        }
+        if humanize.velocity > 0 {
+            let depth = i64::from(humanize.velocity);
+            velocity = (i64::from(velocity) + self.random.range_inclusive(-depth, depth))
+                .clamp(1, 127) as u8;
+        }
+        let mut delay = lead;
+        if self.pattern.resolutions[track_index] == StepResolution::Sixteenth
+            && !position.is_multiple_of(2)
+        {
+            let extra_swing =
+                f64::from(self.effective_track_swing(track_index) - self.grid_swing());
+            delay += self.base_step_samples() * extra_swing;
+        }
+        if humanize.timing_samples > 0 {
+            delay += self
+                .random
+                .range_inclusive(0, i64::from(humanize.timing_samples)) as f64;
+        }
+        let hits = if step.flam {
+            let grace = (u16::from(velocity) * FLAM_GRACE_VELOCITY_PERCENT / 100).max(1);
+            let spacing =
+                f64::from(step.flam_spacing_ms) * f64::from(self.sample_rate_hz) / 1_000.0;
+            [
+                Some((grace as u8, delay)),
+                Some((velocity, delay + spacing)),
+            ]
+        } else {
+            [Some((velocity, delay)), None]
+        };
+        for (velocity, delay) in hits.into_iter().flatten() {
+            if delay > 0.0 {
+                self.pending_triggers.push(PendingTrigger {
+                    samples_until: step_start + delay,
+                    track_index: track_index as u8,
+                    step_index: step_index as u8,
+                    velocity,
+                    gate: step.gate,
+                    source: FfEventSource::Sequencer,
+                });
+                continue;
+            }
+            output.push(StepTriggerEvent {
+                track_index: track_index as u8,
+                step_index: step_index as u8,
+                velocity,
+                gate: step.gate,
+                choke_group: self.track_performance[track_index].choke_group,
+                timeline_sample: self.timeline_sample + u64::from(block_offset),
+                block_offset,
+                source: FfEventSource::Sequencer,
+            });
+        }
+    }
+
+    fn directed_step(&mut self, track_index: usize, position: usize, pass: u64) -> usize {
+        let last_step = self.pattern.step_count() - 1;
+        match self.pattern.directions[track_index] {
+            PlaybackDirection::Forward => position,
+            PlaybackDirection::Reverse => last_step - position,
+            PlaybackDirection::PingPong if pass % 2 == 1 => last_step - position,
+            PlaybackDirection::PingPong => position,
+            PlaybackDirection::Random => self.random.range_inclusive(0, last_step as i64) as usize,
+        }
+    }
+
+    fn condition_met(&self, track_index: usize, condition: TrigCondition) -> bool {
+        let neighbor = track_index
+            .checked_sub(1)
+            .is_some_and(|neighbor| self.last_fired[neighbor]);
+        match condition {
+            TrigCondition::Always => true,
+            TrigCondition::Pre => self.last_fired[track_index],
+            TrigCondition::NotPre => !self.last_fired[track_index],
+            TrigCondition::Neighbor => neighbor,
+            TrigCondition::NotNeighbor => !neighbor,
+            TrigCondition::Cycle { hit, of } => {
+                self.pattern_pass % u64::from(of) == u64::from(hit - 1)
+            }
+            TrigCondition::Fill => self.fill_active,
+            TrigCondition::NotFill => !self.fill_active,
+            TrigCondition::First => self.pattern_pass == 0,
+            TrigCondition::NotFirst => self.pattern_pass != 0,
+        }
+    }
+
+    fn collect_lock_updates(&mut self, track_index: usize, step_index: usize, block_offset: u32) {
+        for target in LockTarget::ALL {
+            let bit = 1 << target.index();
+            let value = match self.pattern.parameter_lock(track_index, step_index, target) {
+                Some(value) => {
+                    self.locked_targets[track_index] |= bit;
+                    value
+                }
+                None if self.locked_targets[track_index] & bit != 0 => {
+                    self.locked_targets[track_index] &= !bit;
+                    self.lock_bases[track_index][target.index()]
+                }
+                None => continue,
+            };
+            if let Some(parameter_id) =
+                abi_rs::ff_track_parameter_id(track_index as u8, lock_target_slot(target))
+            {
+                self.lock_updates.push(coalesce::TimedParameterUpdate {
+                    block_offset,
+                    update: abi_rs::FfParameterUpdate {
+                        parameter_id,
+                        normalized_value: value,
+                        ramp_samples: 0,
+                        reserved: 0,
+                    },
+                });
+            }
+        }
+    }
+
+    fn step_interval_samples(&self, step_index: usize) -> f64 {
+        self.base_step_samples()
+            * (1.0 + self.step_offset(step_index + 1) - self.step_offset(step_index))
+    }
+
+    // Moves a transport ramp across the step about to play and times that
+    // step at the log-mean of its start and end tempo, which is exactly how
+    // long it lasts under a linear tempo curve.
+    fn ramped_step_interval_samples(&mut self, step_index: usize) -> f64 {
+        if self.transport.ramp.is_none() {
+            return self.step_interval_samples(step_index);
+        }
+        let start_bpm = f64::from(self.transport.bpm());
+        let beats = self.step_interval_samples(step_index)
+            / samples_per_step(self.sample_rate_hz, self.transport.bpm())
+            / STEPS_PER_BEAT as f64;
+        self.transport.advance_ramp(beats);
+        let end_bpm = f64::from(self.transport.bpm());
+        let mean_bpm = if (end_bpm - start_bpm).abs() < 1e-6 {
+            end_bpm
+        } else {
+            (end_bpm - start_bpm) / (end_bpm / start_bpm).ln()
+        };
+        self.step_interval_samples(step_index) * end_bpm / mean_bpm
+    }
+
+    fn base_step_samples(&self) -> f64 {
+        samples_per_step(self.sample_rate_hz, self.transport.bpm()) / self.pattern.scale.speed()
+    }
+
+    fn step_offset(&self, step_index: usize) -> f64 {
+        let swing = if step_index.is_multiple_of(2) {
+            0.0
+        } else {
+            f64::from(self.grid_swing())
+        };
+        let groove = self
+            .groove
+            .as_ref()
+            .map_or(0.0, |groove| f64::from(groove.timing_offset(step_index)));
+        swing + groove
+    }
+}
+
+fn samples_per_step(sample_rate_hz: u32, bpm: f32) -> f64 {
+    let safe_bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+    f64::from(sample_rate_hz) * 60.0 / f64::from(safe_bpm) / 4.0
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TrackRecall {
+    pub sample_id: Option<String>,
+    pub choke_group: Option<u8>,
+    pub gain_normalized: u8,
+    pub pan_normalized: u8,
+    pub filter_cutoff_normalized: u8,
+    pub envelope_decay_normalized: u8,
+    pub pitch_normalized: u8,
+    pub duck_source: Option<u8>,
+    pub duck_depth_normalized: u8,
+    pub duck_attack_normalized: u8,
+    pub duck_release_normalized: u8,
+    pub group: Option<u8>,
+    pub sample_start_normalized: u16,
+    pub sample_end_normalized: u16,
+    pub reverse: bool,
+    pub humanize: Option<HumanizeDepth>,
+    pub choke_targets: u8,
+    pub name: Option<String>,
+    pub color: Option<u32>,
+}
+
+impl TrackRecall {
+    pub fn lock_base(&self, target: LockTarget) -> f32 {
+        normalized_from_u7(match target {
+            LockTarget::Gain => self.gain_normalized,
+            LockTarget::FilterCutoff => self.filter_cutoff_normalized,
+            LockTarget::EnvelopeDecay => self.envelope_decay_normalized,
+            LockTarget::Pitch => self.pitch_normalized,
+        })
+    }
+}
+
+impl Default for TrackRecall {
+    fn default() -> Self {
+        Self {
+            sample_id: None,
+            choke_group: None,
+            gain_normalized: normalize_gain_db(0.0),
+            pan_normalized: 64,
+            filter_cutoff_normalized: 127,
+            envelope_decay_normalized: 127,
+            pitch_normalized: 64,
+            duck_source: None,
+            duck_depth_normalized: 0,
+            duck_attack_normalized: 6,
+            duck_release_normalized: 25,
+            group: None,
+            sample_start_normalized: 0,
+            sample_end_normalized: u16::MAX,
+            reverse: false,
+            humanize: None,
+            choke_targets: 0,
+            name: None,
+            color: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct GroupRecall {
+    pub gain_normalized: u8,
+    pub muted: bool,
+    pub soloed: bool,
+    pub choke: bool,
+}
+
+impl Default for GroupRecall {
+    fn default() -> Self {
+        Self {
+            gain_normalized: normalize_gain_db(0.0),
+            muted: false,
+            soloed: false,
+            choke: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RecallState {
+    sequencer: Sequencer,
+    track_recall: [TrackRecall; TRACK_COUNT],
+    group_recall: [GroupRecall; presets_rs::TRACK_GROUP_COUNT],
+    midi_outputs: [Option<presets_rs::TrackMidiOutput>; TRACK_COUNT],
+    master_bus: presets_rs::MasterBus,
+    pitch_quantizer: pitch::PitchQuantizer,
+    audio_hints: AudioHints,
+    unresolved_samples: Vec<String>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TrackSampleAssignment {
+    pub track_index: u8,
+    pub sample_id: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EngineRecall {
+    pub sample_assignments: Vec<TrackSampleAssignment>,
+    // Tracks whose sample must be unloaded. Only diffs fill this; a full
+    // recall already implies that tracks without an assignment are empty.
+    pub cleared_tracks: Vec<u8>,
+    pub parameter_updates: Vec<abi_rs::FfParameterUpdate>,
+}
+
+impl RecallState {
+    pub fn sequencer(&self) -> &Sequencer {
+        &self.sequencer
+    }
+
+    pub fn sequencer_mut(&mut self) -> &mut Sequencer {
+        &mut self.sequencer
+    }
+
+    pub fn into_sequencer(self) -> Sequencer {
+        self.sequencer
+    }
+
+    pub fn track_recall(&self, track_index: usize) -> Option<&TrackRecall> {
+        self.track_recall.get(track_index)
+    }
+
+    pub fn track_display_name(&self, track_index: usize) -> Option<String> {
+        let recall = self.track_recall.get(track_index)?;
+        Some(
+            recall
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("Track {}", track_index + 1)),
+        )
+    }
+
+    pub fn group_recall(&self, group_index: usize) -> Option<&GroupRecall> {
+        self.group_recall.get(group_index)
+    }
+
+    pub fn master_bus(&self) -> presets_rs::MasterBus {
+        self.master_bus
+    }
+
+    pub fn audio_hints(&self) -> AudioHints {
+        self.audio_hints
+    }
+
+    pub fn track_midi_output(&self, track_index: usize) -> Option<presets_rs::TrackMidiOutput> {
+        self.midi_outputs.get(track_index).copied().flatten()
+    }
+
+    pub fn pitch_quantizer(&self) -> pitch::PitchQuantizer {
+        self.pitch_quantizer
+    }
+
+    pub fn resolve_samples<F>(&mut self, mut is_available: F) -> &[String]
+    where
+        F: FnMut(&str) -> bool,
+    {
+        let mut unresolved: Vec<String> = self
+            .track_recall
+            .iter()
+            .filter_map(|track| track.sample_id.as_deref())
+            .filter(|sample_id| !is_available(sample_id))
+            .map(str::to_string)
+            .collect();
+        unresolved.sort();
+        unresolved.dedup();
+        self.unresolved_samples = unresolved;
+        &self.unresolved_samples
+    }
+
+    pub fn unresolved_samples(&self) -> &[String] {
+        &self.unresolved_samples
+    }
+
+    pub fn has_placeholder(&self, track_index: usize) -> bool {
+        self.track_recall
+            .get(track_index)
+            .and_then(|track| track.sample_id.as_ref())
+            .is_some_and(|sample_id| self.unresolved_samples.contains(sample_id))
+    }
+
+    pub fn mark_sample_resolved(&mut self, sample_id: &str) -> bool {
+        let before = self.unresolved_samples.len();
+        self.unresolved_samples
+            .retain(|unresolved| unresolved != sample_id);
+        self.unresolved_samples.len() != before
+    }
+
+    pub fn midi_output_router(&self) -> midi_out::MidiOutputRouter {
+        let mut router = midi_out::MidiOutputRouter::new(self.sequencer.sample_rate_hz());
+        router.set_tempo_bpm(self.sequencer.transport().bpm());
+        for (track_index, output) in self.midi_outputs.iter().enumerate() {
+            router.set_track_output(track_index, *output);
+        }
+        router
+    }
+
+    pub fn to_engine_recall(&self) -> EngineRecall {
+        self.to_engine_recall_ramped(0)
+    }
+
+    pub fn to_engine_recall_ramped(&self, ramp_samples: u32) -> EngineRecall {
+        let mut sample_assignments = Vec::new();
+        let mut parameter_updates = Vec::with_capacity(TRACK_COUNT * 15);
+
+        for (track_index, track_recall) in self.track_recall.iter().enumerate() {
+            let track_index = track_index as u8;
+            if let Some(sample_id) = &track_recall.sample_id {
+                sample_assignments.push(TrackSampleAssignment {
+                    track_index,
+                    sample_id: sample_id.clone(),
+                });
+            }
+
+            push_parameter_update(
+                &mut parameter_updates,
+                track_index,
+                abi_rs::FF_PARAM_SLOT_GAIN,
+                normalized_from_u7(track_recall.gain_normalized),
+                ramp_samples,
+            );
+            push_parameter_update(
+                &mut parameter_updates,
+                track_index,
+                abi_rs::FF_PARAM_SLOT_PAN,
+                normalized_from_u7(track_recall.pan_normalized),
+                ramp_samples,
+            );
+            push_parameter_update(
+                &mut parameter_updates,
+                track_index,
+                abi_rs::FF_PARAM_SLOT_FILTER_CUTOFF,
+                normalized_from_u7(track_recall.filter_cutoff_normalized),
+                ramp_samples,
+            );
+            push_parameter_update(
+                &mut parameter_updates,
+                track_index,
+                abi_rs::FF_PARAM_SLOT_ENVELOPE_DECAY,
+                normalized_from_u7(track_recall.envelope_decay_normalized),
+                ramp_samples,
+            );
+            push_parameter_update(
+                &mut parameter_updates,
+                track_index,
+                abi_rs::FF_PARAM_SLOT_PITCH,
+                normalized_from_u7(track_recall.pitch_normalized),
+                ramp_samples,
+            );
+            push_parameter_update(
+                &mut parameter_updates,
+                track_index,
+                abi_rs::FF_PARAM_SLOT_CHOKE_GROUP,
+                normalize_choke_group_for_engine(track_recall.choke_group),
+                0,
+            );
+            push_parameter_update(
+                &mut parameter_updates,
+                track_index,
+                abi_rs::FF_PARAM_SLOT_DUCK_SOURCE,
+                normalize_duck_source_for_engine(track_recall.duck_source),
+                0,
+            );
+            push_parameter_update(
+                &mut parameter_updates,
+                track_index,
+                abi_rs::FF_PARAM_SLOT_DUCK_DEPTH,
+                normalized_from_u7(track_recall.duck_depth_normalized),
+                ramp_samples,
+            );
+            push_parameter_update(
+                &mut parameter_updates,
+                track_index,
+                abi_rs::FF_PARAM_SLOT_DUCK_ATTACK,
+                normalized_from_u7(track_recall.duck_attack_normalized),
+                0,
+            );
+            push_parameter_update(
+                &mut parameter_updates,
+                track_index,
+                abi_rs::FF_PARAM_SLOT_DUCK_RELEASE,
+                normalized_from_u7(track_recall.duck_release_normalized),
+                0,
+            );
+            push_parameter_update(
+                &mut parameter_updates,
+                track_index,
+                abi_rs::FF_PARAM_SLOT_TRACK_GROUP,
+                normalize_track_group_for_engine(track_recall.group),
+                0,
+            );
+            push_parameter_update(
+                &mut parameter_updates,
+                track_index,
+                abi_rs::FF_PARAM_SLOT_SAMPLE_START,
+                normalized_from_u16(track_recall.sample_start_normalized),
+                0,
+            );
+            push_parameter_update(
+                &mut parameter_updates,
+                track_index,
+                abi_rs::FF_PARAM_SLOT_SAMPLE_END,
+                normalized_from_u16(track_recall.sample_end_normalized),
+                0,
+            );
+            push_parameter_update(
+                &mut parameter_updates,
+                track_index,
+                abi_rs::FF_PARAM_SLOT_REVERSE,
+                f32::from(u8::from(track_recall.reverse)),
+                0,
+            );
+            push_parameter_update(
+                &mut parameter_updates,
+                track_index,
+                abi_rs::FF_PARAM_SLOT_CHOKE_TARGETS,
+                f32::from(track_recall.choke_targets) / 255.0,
+                0,
+            );
+        }
+
+        for (group_index, group) in self.group_recall.iter().enumerate() {
+            let group_index = group_index as u8;
+            for (parameter_slot, normalized_value) in [
+                (
+                    abi_rs::FF_PARAM_GROUP_SLOT_GAIN,
+                    normalized_from_u7(group.gain_normalized),
+                ),
+                (
+                    abi_rs::FF_PARAM_GROUP_SLOT_MUTE,
+                    f32::from(u8::from(group.muted)),
+                ),
+                (
+                    abi_rs::FF_PARAM_GROUP_SLOT_SOLO,
+                    f32::from(u8::from(group.soloed)),
+                ),
+                (
+                    abi_rs::FF_PARAM_GROUP_SLOT_CHOKE,
+                    f32::from(u8::from(group.choke)),
+                ),
+            ] {
+                if let Some(parameter_id) =
+                    abi_rs::ff_group_parameter_id(group_index, parameter_slot)
+                {
+                    parameter_updates.push(abi_rs::FfParameterUpdate {
+                        parameter_id,
+                        normalized_value,
+                        ramp_samples: if parameter_slot == abi_rs::FF_PARAM_GROUP_SLOT_GAIN {
+                            ramp_samples
+                        } else {
+                            0
+                        },
+                        reserved: 0,
+                    });
+                }
+            }
+        }
+
+        for (parameter_id, normalized_value) in [
+            (
+                abi_rs::FF_PARAM_GLOBAL_MASTER_COMPRESSOR,
+                self.master_bus.compressor_amount,
+            ),
+            (
+                abi_rs::FF_PARAM_GLOBAL_MASTER_FILTER,
+                self.master_bus.filter_cutoff,
+            ),
+            (
+                abi_rs::FF_PARAM_GLOBAL_LIMITER_CEILING,
+                abi_rs::ff_limiter_ceiling_normalized_from_db(self.master_bus.limiter_ceiling_db),
+            ),
+        ] {
+            parameter_updates.push(abi_rs::FfParameterUpdate {
+                parameter_id,
+                normalized_value,
+                ramp_samples,
+                reserved: 0,
+            });
+        }
+
+        EngineRecall {
+            sample_assignments,
+            cleared_tracks: Vec::new(),
+            parameter_updates,
+        }
+    }
+
+    pub fn diff_against(&self, previous: &RecallState) -> EngineRecall {
+        self.diff_against_ramped(previous, 0)
+    }
+
+    pub fn diff_against_ramped(&self, previous: &RecallState, ramp_samples: u32) -> EngineRecall {
+        let current = self.to_engine_recall_ramped(ramp_samples);
+        let before = previous.to_engine_recall_ramped(ramp_samples);
+        let cleared_tracks = before
+            .sample_assignments
+            .iter()
+            .map(|assignment| assignment.track_index)
+            .filter(|track_index| {
+                !current
+                    .sample_assignments
+                    .iter()
+                    .any(|assignment| assignment.track_index == *track_index)
+            })
+            .collect();
+        EngineRecall {
+            sample_assignments: current
+                .sample_assignments
+                .into_iter()
+                .filter(|assignment| !before.sample_assignments.contains(assignment))
+                .collect(),
+            cleared_tracks,
+            parameter_updates: current
+                .parameter_updates
+                .into_iter()
+                .filter(|update| {
+                    !before.parameter_updates.iter().any(|previous| {
+                        previous.parameter_id == update.parameter_id
+                            && previous.normalized_value == update.normalized_value
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+fn normalize_unit(value: f32) -> u8 {
+    let clamped = value.clamp(0.0, 1.0);
+    (clamped * 127.0).round() as u8
+}
+
+fn normalize_gain_db(gain_db: f32) -> u8 {
+    (abi_rs::ff_gain_normalized_from_db(gain_db) * 127.0).round() as u8
+}
+
+fn normalize_pan(value: f32) -> u8 {
+    let clamped = value.clamp(-1.0, 1.0);
+    let normalized = (clamped + 1.0) * 0.5;
+    (normalized * 127.0).round() as u8
+}
+
+fn normalize_pitch(value: f32) -> u8 {
+    let clamped = value.clamp(-24.0, 24.0);
+    let normalized = (clamped + 24.0) / 48.0;
+    (normalized * 127.0).round() as u8
+}
+
+fn lock_target_slot(target: LockTarget) -> u32 {
+    match target {
+        LockTarget::Gain => abi_rs::FF_PARAM_SLOT_GAIN,
+        LockTarget::FilterCutoff => abi_rs::FF_PARAM_SLOT_FILTER_CUTOFF,
+        LockTarget::EnvelopeDecay => abi_rs::FF_PARAM_SLOT_ENVELOPE_DECAY,
+        LockTarget::Pitch => abi_rs::FF_PARAM_SLOT_PITCH,
+    }
+}
+
+fn normalized_from_u7(value: u8) -> f32 {
+    f32::from(value) / 127.0
+}
+
+fn normalize_fine(value: f32) -> u16 {
+    (value.clamp(0.0, 1.0) * f32::from(u16::MAX)).round() as u16
+}
+
+fn normalized_from_u16(value: u16) -> f32 {
+    f32::from(value) / f32::from(u16::MAX)
+}
+
+fn normalize_choke_group_for_engine(choke_group: Option<u8>) -> f32 {
+    match choke_group {
+        Some(value) => (f32::from(value.min(15)) + 1.0) / 16.0,
+        None => 0.0,
+    }
+}
+
+fn normalize_duck_source_for_engine(duck_source: Option<u8>) -> f32 {
+    match duck_source {
+        Some(track_index) => (f32::from(track_index.min(7)) + 1.0) / TRACK_COUNT as f32,
+        None => 0.0,
+    }
+}
+
+fn normalize_track_group_for_engine(group: Option<u8>) -> f32 {
+    let group_count = presets_rs::TRACK_GROUP_COUNT as u8;
+    match group {
+        Some(group_index) => {
+            (f32::from(group_index.min(group_count - 1)) + 1.0) / f32::from(group_count)
+        }
+        None => 0.0,
+    }
+}
+
+fn push_parameter_update(
+    output: &mut Vec<abi_rs::FfParameterUpdate>,
+    track_index: u8,
+    parameter_slot: u32,
+    normalized_value: f32,
+    ramp_samples: u32,
+) {
+    if let Some(parameter_id) = abi_rs::ff_track_parameter_id(track_index, parameter_slot) {
+        output.push(abi_rs::FfParameterUpdate {
+            parameter_id,
+            normalized_value: normalized_value.clamp(0.0, 1.0),
+            ramp_samples,
+            reserved: 0,
+        });
+    }
+}
+
+fn pattern_from_preset(
+    project: &presets_rs::Project,
+    pattern: &presets_rs::Pattern,
+) -> Result<Pattern, String> {
+    let mut converted = Pattern::default();
+    converted.set_scale(pattern.scale);
+    if !converted.set_swing(Some(pattern.swing)) {
+        return Err(format!("pattern swing out of range: {}", pattern.swing));
+    }
+    if let Some(name) = &pattern.groove {
+        let groove = project
+            .groove(name)
+            .ok_or_else(|| format!("unknown groove: {name}"))?;
+        if !converted.set_groove(Some(groove)) {
+            return Err(format!("invalid groove: {name}"));
+        }
+    }
+    if !converted.set_accent_curve(pattern.accent) {
+        return Err("pattern accent out of range".to_string());
+    }
+    if !converted.set_tempo(pattern.tempo) {
+        return Err("pattern tempo out of range".to_string());
+    }
+    if !converted.set_bar_count(pattern.bar_count) {
+        return Err(format!(
+            "pattern bar count out of range: {}",
+            pattern.bar_count
+        ));
+    }
+    if pattern.length_steps.is_some() && !converted.set_step_count(pattern.step_count()) {
+        return Err(format!(
+            "pattern step count out of range: {}",
+            pattern.step_count()
+        ));
+    }
+
+    for track_index in 0..TRACK_COUNT {
+        for step_index in 0..pattern.step_count() {
+            let step = pattern.steps[track_index][step_index];
+            if !converted.set_step(track_index, step_index, Step::from(step)) {
+                return Err(format!(
+                    "failed to apply pattern step track={track_index}, step={step_index}"
+                ));
+            }
+        }
+    }
+    for variation in &pattern.variations {
+        if !converted.set_variation_step(
+            variation.variation,
+            usize::from(variation.track_index),
+            usize::from(variation.step_index),
+            Some(Step::from(variation.step)),
+        ) {
+            return Err(format!(
+                "failed to apply pattern variation {} track={}, step={}",
+                variation.variation, variation.track_index, variation.step_index
+            ));
+        }
+    }
+    if !converted.set_mute_spans(&pattern.mutes) {
+        return Err("pattern mute span out of range".to_string());
+    }
+    if !converted.set_parameter_locks(&pattern.locks) {
+        return Err("pattern parameter lock out of range".to_string());
+    }
+    for (track_index, direction) in pattern.directions.iter().enumerate() {
+        converted.set_track_direction(track_index, *direction);
+    }
+    for (track_index, resolution) in pattern.resolutions.iter().enumerate() {
+        converted.set_track_resolution(track_index, *resolution);
+    }
+    converted.set_swing_grid(pattern.swing_grid);
+    for (track_index, step) in pattern.default_steps.iter().enumerate() {
+        if !converted.set_track_default_step(track_index, Step::from(*step)) {
+            return Err(format!("invalid default step for track {track_index}"));
+        }
+    }
+    Ok(converted)
+}
+
+pub fn recall_state_from_texts(
+    kit_text: &str,
+    pattern_text: &str,
+    sample_rate_hz: u32,
+) -> Result<RecallState, String> {
+    let kit =
+        presets_rs::load_kit_from_text(kit_text).map_err(|error| format!("kit text: {error}"))?;
+    let pattern = presets_rs::load_pattern_from_text(pattern_text)
+        .map_err(|error| format!("pattern text: {error}"))?;
+    let project = presets_rs::Project {
+        kits: vec![kit],
+        active_kit: Some(0),
+        patterns: vec![pattern],
+        active_pattern: Some(0),
+        ..presets_rs::Project::default()
+    };
+    recall_state_from_project(&project, sample_rate_hz)
+}
+
+pub fn recall_state_from_project(
+    project: &presets_rs::Project,
+    sample_rate_hz: u32,
+) -> Result<RecallState, String> {
+    let kit_index = project
+        .active_kit
+        .or_else(|| (!project.kits.is_empty()).then_some(0))
+        .ok_or_else(|| "project has no kits".to_string())?;
+    if kit_index >= project.kits.len() {
+        return Err(format!("active kit out of range: {kit_index}"));
+    }
+
+    let pattern_index = project
+        .song
+        .first()
+        .map(|entry| entry.pattern_index)
+        .or(project.active_pattern)
+        .or_else(|| (!project.patterns.is_empty()).then_some(0))
+        .ok_or_else(|| "project has no patterns".to_string())?;
+    if pattern_index >= project.patterns.len() {
+        return Err(format!("active pattern out of range: {pattern_index}"));
+    }
+
+    let kit = &project.kits[kit_index];
+    let pattern = &project.patterns[pattern_index];
+    if !project.key.is_valid() {
+        return Err(format!(
+            "project key root out of range: {}",
+            project.key.root
+        ));
+    }
+    let pitch_quantizer = pitch::PitchQuantizer::new(project.key);
+
+    let mut sequencer = Sequencer::new(sample_rate_hz);
+    let active_pattern = pattern_from_preset(project, pattern)?;
+    sequencer.set_swing(pattern.swing);
+    sequencer.set_groove(active_pattern.groove().cloned());
+    sequencer.set_random_seed(project.random_seed);
+    if !sequencer.set_humanize(project.humanize) {
+        return Err("project humanize out of range".to_string());
+    }
+    sequencer.set_track_inputs(project.track_inputs);
+    *sequencer.pattern_mut() = active_pattern;
+    if let Some(tempo) = pattern.tempo {
+        sequencer.set_tempo_bpm(tempo.bpm);
+    }
+    if !project.song.is_empty() {
+        let mut song = Song::new(
+            project
+                .patterns
+                .iter()
+                .map(|pattern| pattern_from_preset(project, pattern))
+                .collect::<Result<Vec<Pattern>, String>>()?,
+        );
+        song.set_looping(project.song_loop);
+        if !song.set_chain(&project.song) || !sequencer.set_song(Some(song)) {
+            return Err("song entry out of range".to_string());
+        }
+    }
+
+    let mut track_recall = std::array::from_fn(|_| TrackRecall::default());
+    for assignment in &kit.tracks {
+        let track_index = usize::from(assignment.track_index);
+        if track_index >= TRACK_COUNT {
+            return Err(format!(
+                "kit track assignment out of range: {}",
+                assignment.track_index
+            ));
+        }
+        track_recall[track_index].sample_id = Some(assignment.sample_id.clone());
+    }
+
+    for control in &kit.controls {
+        let track_index = usize::from(control.track_index);
+        if track_index >= TRACK_COUNT {
+            return Err(format!(
+                "kit control track out of range: {}",
+                control.track_index
+            ));
+        }
+
+        track_recall[track_index].choke_group = control.controls.choke_group;
+        track_recall[track_index].gain_normalized = normalize_gain_db(control.controls.gain_db);
+        track_recall[track_index].pan_normalized = normalize_pan(control.controls.pan);
+        track_recall[track_index].filter_cutoff_normalized =
+            normalize_unit(control.controls.filter_cutoff);
+        track_recall[track_index].envelope_decay_normalized =
+            normalize_unit(control.controls.envelope_decay);
+        track_recall[track_index].pitch_normalized =
+            normalize_pitch(pitch_quantizer.quantize_semitones(control.controls.pitch_semitones));
+
+        if !sequencer.set_track_choke_group(track_index, control.controls.choke_group) {
+            return Err(format!(
+                "failed to apply choke group to track {track_index}"
+            ));
+        }
+    }
+
+    for ducking in &kit.ducking {
+        if !ducking.is_valid() {
+            return Err(format!(
+                "kit ducking out of range: track {}",
+                ducking.track_index
+            ));
+        }
+
+        let recall = &mut track_recall[usize::from(ducking.track_index)];
+        recall.duck_source = Some(ducking.source_track);
+        recall.duck_depth_normalized = normalize_unit(ducking.depth);
+        recall.duck_attack_normalized =
+            normalize_unit(ducking.attack_ms / presets_rs::MAX_DUCK_ATTACK_MS);
+        recall.duck_release_normalized =
+            normalize_unit(ducking.release_ms / presets_rs::MAX_DUCK_RELEASE_MS);
+    }
+
+    for trim in &kit.trims {
+        if !trim.is_valid() {
+            return Err(format!("kit trim out of range: track {}", trim.track_index));
+        }
+
+        let recall = &mut track_recall[usize::from(trim.track_index)];
+        recall.sample_start_normalized = normalize_fine(trim.start);
+        recall.sample_end_normalized = normalize_fine(trim.end);
+        recall.reverse = trim.reverse;
+    }
+
+    for humanize in &kit.humanize {
+        let depth = HumanizeDepth {
+            timing_samples: humanize.timing_samples,
+            velocity: humanize.velocity,
+        };
+        let track_index = usize::from(humanize.track_index);
+        if !humanize.is_valid() || !sequencer.set_track_humanize(track_index, Some(depth)) {
+            return Err(format!(
+                "kit humanize out of range: track {}",
+                humanize.track_index
+            ));
+        }
+        track_recall[track_index].humanize = Some(depth);
+    }
+
+    for choke in &kit.choke_targets {
+        if !choke.is_valid() {
+            return Err(format!(
+                "kit choke targets out of range: track {}",
+                choke.track_index
+            ));
+        }
+
+        track_recall[usize::from(choke.track_index)].choke_targets = choke.targets;
+    }
+
+    for label in &kit.labels {
+        if !label.is_valid() {
+            return Err(format!(
+                "kit label out of range: track {}",
+                label.track_index
+            ));
+        }
+
+        let recall = &mut track_recall[usize::from(label.track_index)];
+        recall.name = (!label.name.is_empty()).then(|| label.name.clone());
+        recall.color = label.color;
+    }
+
+    let mut group_recall = [GroupRecall::default(); presets_rs::TRACK_GROUP_COUNT];
+    for group in &kit.groups {
+        if !group.is_valid() {
+            return Err(format!("kit group out of range: {}", group.group_index));
+        }
+
+        group_recall[usize::from(group.group_index)] = GroupRecall {
+            gain_normalized: normalize_gain_db(group.gain_db),
+            muted: group.muted,
+            soloed: group.soloed,
+            choke: group.choke,
+        };
+        for track_index in &group.members {
+            track_recall[usize::from(*track_index)].group = Some(group.group_index);
+        }
+    }
+
+    let mut midi_outputs = [None; TRACK_COUNT];
+    for output in &kit.midi_outputs {
+        if !output.is_valid() {
+            return Err(format!(
+                "kit midi output out of range: track {}",
+                output.track_index
+            ));
+        }
+
+        let track_index = usize::from(output.track_index);
+        midi_outputs[track_index] = Some(*output);
+        track_recall[track_index].sample_id = None;
+    }
+
+    for (track_index, recall) in track_recall.iter().enumerate() {
+        for target in LockTarget::ALL {
+            sequencer.set_track_lock_base(track_index, target, recall.lock_base(target));
+        }
+    }
+
+    if !kit.master.is_valid() {
+        return Err("kit master bus out of range".to_string());
+    }
+    if !project.audio_hints.is_valid() {
+        return Err("project audio hints out of range".to_string());
+    }
+
+    Ok(RecallState {
+        sequencer,
+        track_recall,
+        group_recall,
+        midi_outputs,
+        master_bus: kit.master,
+        pitch_quantizer,
+        audio_hints: project.audio_hints,
+        unresolved_samples: Vec::new(),
+    })
+}
+
+pub fn render_recall_events(
+    project: &presets_rs::Project,
+    sample_rate_hz: u32,
+    blocks: &[u32],
+) -> Result<Vec<StepTriggerEvent>, String> {
+    let mut recall = recall_state_from_project(project, sample_rate_hz)?;
+    let mut events = Vec::new();
+    recall.sequencer_mut().start();
+    for frames in blocks {
+        events.extend(recall.sequencer_mut().process_block(*frames));
+    }
+    Ok(events)
+}
+
+// Trigger streams depend only on the total frames rendered, never on how the host
+// splits them into blocks: every event keeps its timeline sample, track, step and
+// velocity, and only block offsets differ between block-size sequences.
+pub fn verify_block_size_independence(
+    project: &presets_rs::Project,
+    sample_rate_hz: u32,
+    block_sequences: &[&[u32]],
+) -> Result<Vec<StepTriggerEvent>, String> {
+    let timeline_events = |blocks: &[u32]| -> Result<Vec<StepTriggerEvent>, String> {
+        Ok(render_recall_events(project, sample_rate_hz, blocks)?
+            .into_iter()
+            .map(|event| StepTriggerEvent {
+                block_offset: 0,
+                ..event
+            })
+            .collect())
+    };
+    let Some((reference_blocks, others)) = block_sequences.split_first() else {
+        return Err("no block sequences to compare".to_string());
+    };
+    let total_frames = |blocks: &[u32]| blocks.iter().map(|frames| u64::from(*frames)).sum();
+    let reference_frames: u64 = total_frames(reference_blocks);
+    let reference = timeline_events(reference_blocks)?;
+    for (sequence_index, blocks) in others.iter().enumerate() {
+        let sequence_index = sequence_index + 1;
+        let frames: u64 = total_frames(blocks);
+        if frames != reference_frames {
+            return Err(format!(
+                "block sequence {sequence_index} renders {frames} frames, expected {reference_frames}"
+            ));
+        }
+        let events = timeline_events(blocks)?;
+        if let Some(event_index) = (0..reference.len().max(events.len()))
+            .find(|index| reference.get(*index) != events.get(*index))
+        {
+            return Err(format!(
+                "block sequence {sequence_index} diverges at event {event_index}: {:?} vs {:?}",
+                reference.get(event_index),
+                events.get(event_index)
+            ));
+        }
+    }
+    Ok(reference)
+}
+
+pub fn engine_recall_from_project(
+    project: &presets_rs::Project,
+    sample_rate_hz: u32,
+) -> Result<EngineRecall, String> {
+    let recall = recall_state_from_project(project, sample_rate_hz)?;
+    Ok(recall.to_engine_recall())
+}
+
+#[cfg(test)]
+mod tests {
+    use abi_rs::{
+        ff_track_parameter_id, FfParameterUpdate, FF_PARAM_SLOT_CHOKE_GROUP,
+        FF_PARAM_SLOT_FILTER_CUTOFF, FF_PARAM_SLOT_GAIN, FF_PARAM_SLOT_PAN, FF_PARAM_TRACK_BASE,
+        FF_PARAM_TRACK_STRIDE,
+    };
+    use presets_rs::{
+        load_project_from_text, save_project_to_text, Kit, Pattern as PresetPattern, PatternStep,
+        Project, TrackAssignment, TrackControls, TrackHumanize, GAIN_DB_MIN,
+    };
+
+    use super::snapshot::PatternSnapshots;
+    use super::{
+        engine_recall_from_project, recall_state_from_project, recall_state_from_texts,
+        render_recall_events, sort_trigger_events, verify_block_size_independence, AccentClip,
+        AccentCurve, AudioHints, ClickKind, ClockBoundaries, EngineRecall, FfEventSource,
+        GrooveTemplate, HumanizeDepth, LaunchQuantize, LiveAction, LockTarget, MuteSpan,
+        NoteRepeat, PadInputRoute, ParameterLock, Pattern, PatternScale, PatternTempo,
+        PlaybackDirection, RecallTiming, RepeatRate, Sequencer, SongEntry, Step, StepResolution,
+        StepTriggerEvent, SwingGrid, TempoChange, TrackSampleAssignment, Transport, TrigCondition,
+        DEFAULT_ACCENT_AMOUNT, DEFAULT_BPM, DEFAULT_FLAM_SPACING_MS, MAX_ACCENT_AMOUNT, MAX_BPM,
+        MAX_COUNT_IN_BARS, MAX_FLAM_SPACING_MS, MAX_PATTERN_BARS, MAX_PATTERN_STEPS, MAX_STEP_GATE,
+        MAX_STEP_PROBABILITY, MAX_SWING, MIN_BPM, STEPS_PER_PATTERN, TEMPO_GLIDE_BEATS,
+        TRACK_COUNT,
+    };
+
+    use super::song::{Song, SongPosition};
+
+    const PHASE2_ENGINE_RECALL_FIXTURE: &str =
+        include_str!("../../../fixtures/interop/phase2_engine_recall_updates.csv");
+
+    fn canonical_fixture_project() -> Project {
+        let mut project = Project {
+            name: "phase2-fixture".to_string(),
+            kits: vec![Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![PresetPattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        project.kits[0].set_track_controls(
+            0,
+            TrackControls {
+                gain_db: 0.0,
+                pan: 1.0,
                 filter_cutoff: 1.0,
                 envelope_decay: 1.0,
                 pitch_semitones: 24.0,
                 choke_group: Some(3),
             },
         );
-        project.kits[0].set_track_controls(
-            3,
-            TrackControls {
-                gain: 0.0,
-                pan: -1.0,
-                filter_cutoff: 0.0,
-                envelope_decay: 0.0,
-                pitch_semitones: -24.0,
-                choke_group: None,
+        project.kits[0].set_track_controls(
+            3,
+            TrackControls {
+                gain_db: GAIN_DB_MIN,
+                pan: -1.0,
+                filter_cutoff: 0.0,
+                envelope_decay: 0.0,
+                pitch_semitones: -24.0,
+                choke_group: None,
+            },
+        );
+        project
+    }
+
+    fn track_index_from_parameter_id(parameter_id: u32) -> Option<u8> {
+        if parameter_id < FF_PARAM_TRACK_BASE {
+            return None;
+        }
+
+        let track_offset = parameter_id - FF_PARAM_TRACK_BASE;
+        let track_index = track_offset / FF_PARAM_TRACK_STRIDE;
+        (track_index < TRACK_COUNT as u32).then_some(track_index as u8)
+    }
+
+    #[allow(clippy::format_in_format_args)]
+    fn format_updates_csv_for_tracks(
+        updates: &[abi_rs::FfParameterUpdate],
+        tracks: &[u8],
+    ) -> String {
+        let mut lines = vec!["# parameter_id,normalized_value".to_string()];
+        for update in updates {
+            if let Some(track_index) = track_index_from_parameter_id(update.parameter_id) {
+                if tracks.contains(&track_index) {
+                    lines.push(format!(
+                        "{},{}",
+                        update.parameter_id,
+                        format!("{:.6}", update.normalized_value)
+                    ));
+                }
+            }
+        }
+        lines.join("\n")
+    }
+
+    fn normalize_newlines(value: &str) -> String {
+        value.lines().collect::<Vec<_>>().join("\n")
+    }
+
+    #[test]
+    fn pattern_supports_eight_tracks_and_sixteen_steps() {
+        let mut pattern = Pattern::default();
+        assert!(pattern.set_step(
+            TRACK_COUNT - 1,
+            STEPS_PER_PATTERN - 1,
+            Step {
+                active: true,
+                velocity: 127,
+                ..Step::default()
+            },
+        ));
+        assert!(
+            pattern
+                .step(TRACK_COUNT - 1, STEPS_PER_PATTERN - 1)
+                .expect("step should exist")
+                .active
+        );
+        assert!(!pattern.set_step(
+            TRACK_COUNT,
+            0,
+            Step {
+                active: true,
+                velocity: 100,
+                ..Step::default()
+            },
+        ));
+    }
+
+    #[test]
+    fn transport_clamps_tempo() {
+        let mut transport = Transport::default();
+        transport.set_bpm(9999.0);
+        assert_eq!(transport.bpm(), MAX_BPM);
+        transport.set_bpm(1.0);
+        assert_eq!(transport.bpm(), MIN_BPM);
+        transport.set_bpm(DEFAULT_BPM);
+        assert_eq!(transport.bpm(), DEFAULT_BPM);
+    }
+
+    #[test]
+    fn tempo_ramps_stretch_steps_continuously_towards_the_target() {
+        let mut transport = Transport::default();
+        assert!(!transport.ramp_bpm(140.0, -1.0));
+        assert!(!transport.ramp_bpm(f32::NAN, 4.0));
+        assert!(transport.ramp_bpm(140.0, 0.0));
+        assert_eq!(transport.bpm(), 140.0);
+        assert_eq!(transport.ramp_target(), None);
+
+        let mut sequencer = Sequencer::new(48_000);
+        for step_index in 0..STEPS_PER_PATTERN {
+            assert!(sequencer.pattern_mut().set_step(
+                0,
+                step_index,
+                Step {
+                    active: true,
+                    ..Step::default()
+                }
+            ));
+        }
+        assert!(sequencer.ramp_tempo_bpm(60.0, 1.0));
+        assert_eq!(sequencer.transport().ramp_target(), Some(60.0));
+        sequencer.start();
+        let timestamps: Vec<u64> = sequencer
+            .process_block(48_000)
+            .iter()
+            .map(|event| event.timeline_sample)
+            .collect();
+        let gaps: Vec<u64> = timestamps
+            .windows(2)
+            .map(|pair| pair[1] - pair[0])
+            .collect();
+        assert!(gaps[..4].windows(2).all(|pair| pair[0] < pair[1]));
+        // A linear 120 -> 60 bpm glide over one beat lasts ln(2) seconds.
+        let ramp_samples = (48_000.0 * std::f64::consts::LN_2).round() as u64;
+        assert!(timestamps[4].abs_diff(timestamps[0] + ramp_samples) <= 1);
+        assert_eq!(gaps[4], 12_000);
+        assert_eq!(sequencer.transport().bpm(), 60.0);
+        assert_eq!(sequencer.transport().ramp_target(), None);
+
+        sequencer.set_tempo_bpm(100.0);
+        assert!(sequencer.ramp_tempo_bpm(180.0, 8.0));
+        sequencer.set_tempo_bpm(90.0);
+        assert_eq!(sequencer.transport().ramp_target(), None);
+        assert!(sequencer.ramp_tempo_bpm(180.0, 8.0));
+        sequencer.stop();
+        assert_eq!(sequencer.transport().bpm(), 180.0);
+    }
+
+    #[test]
+    fn sequencer_emits_step_zero_immediately_on_start() {
+        let mut sequencer = Sequencer::new(48_000);
+        assert!(sequencer.pattern_mut().set_step(
+            0,
+            0,
+            Step {
+                active: true,
+                velocity: 120,
+                ..Step::default()
+            },
+        ));
+        sequencer.start();
+
+        let events = sequencer.process_block(128);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].track_index, 0);
+        assert_eq!(events[0].step_index, 0);
+        assert_eq!(events[0].choke_group, None);
+        assert_eq!(events[0].block_offset, 0);
+    }
+
+    #[test]
+    fn sequencer_emits_multi_track_step_events() {
+        let mut sequencer = Sequencer::new(48_000);
+        assert!(sequencer.pattern_mut().set_step(
+            1,
+            5,
+            Step {
+                active: true,
+                velocity: 90,
+                ..Step::default()
+            },
+        ));
+        assert!(sequencer.pattern_mut().set_step(
+            3,
+            5,
+            Step {
+                active: true,
+                velocity: 110,
+                ..Step::default()
+            },
+        ));
+
+        sequencer.start();
+        let events = sequencer.process_block(30_001);
+        let step_five_events: Vec<_> = events
+            .iter()
+            .filter(|event| event.step_index == 5)
+            .collect();
+        assert_eq!(step_five_events.len(), 2);
+        assert!(step_five_events.iter().any(|event| event.track_index == 1));
+        assert!(step_five_events.iter().any(|event| event.track_index == 3));
+    }
+
+    #[test]
+    fn odd_block_sizes_carry_sub_sample_phase_without_drift() {
+        let mut sequencer = Sequencer::new(44_100);
+        sequencer.set_tempo_bpm(123.0);
+        for step_index in 0..STEPS_PER_PATTERN {
+            sequencer.pattern_mut().set_step(
+                0,
+                step_index,
+                Step {
+                    active: true,
+                    velocity: 100,
+                    ..Step::default()
+                },
+            );
+        }
+        sequencer.start();
+
+        let interval = super::samples_per_step(44_100, 123.0);
+        let mut timeline = Vec::new();
+        for _ in 0..20_000 {
+            let events = sequencer.process_block(113);
+            assert!(events.iter().all(|event| event.block_offset < 113));
+            timeline.extend(events.iter().map(|event| event.timeline_sample));
+        }
+        assert!(timeline.len() > 400);
+        for (index, timeline_sample) in timeline.iter().enumerate() {
+            assert_eq!(*timeline_sample, (index as f64 * interval).round() as u64);
+        }
+        let next_step = timeline.len() as f64 * interval - 20_000.0 * 113.0;
+        assert!((sequencer.sub_sample_phase() - next_step.rem_euclid(1.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn sequencer_wraps_after_sixteen_steps() {
+        let mut sequencer = Sequencer::new(48_000);
+        assert!(sequencer.pattern_mut().set_step(
+            2,
+            0,
+            Step {
+                active: true,
+                velocity: 127,
+                ..Step::default()
+            },
+        ));
+        sequencer.start();
+
+        let first_bar = sequencer.process_block(96_000);
+        let second_bar = sequencer.process_block(96_000);
+
+        assert!(first_bar
+            .iter()
+            .any(|event| event.step_index == 0 && event.track_index == 2));
+        assert!(second_bar
+            .iter()
+            .any(|event| event.step_index == 0 && event.track_index == 2));
+    }
+
+    #[test]
+    fn odd_length_patterns_wrap_after_their_last_step() {
+        let mut sequencer = Sequencer::new(48_000);
+        let hit = Step {
+            active: true,
+            ..Step::default()
+        };
+        assert!(sequencer.pattern_mut().set_step_count(24));
+        assert_eq!(sequencer.pattern().bar_count(), 2);
+        assert!(sequencer.pattern_mut().set_step(0, 0, hit));
+        assert!(sequencer.pattern_mut().set_step(1, 23, hit));
+        assert!(!sequencer.pattern_mut().set_step(1, 24, hit));
+        sequencer.start();
+
+        let events: Vec<(u8, u8, u64)> = sequencer
+            .process_block(6_000 * 25)
+            .iter()
+            .map(|event| (event.track_index, event.step_index, event.timeline_sample))
+            .collect();
+        assert_eq!(
+            events,
+            vec![(0, 0, 0), (1, 23, 6_000 * 23), (0, 0, 6_000 * 24)]
+        );
+    }
+
+    #[test]
+    fn multi_bar_patterns_report_bar_within_pattern() {
+        let mut sequencer = Sequencer::new(48_000);
+        let hit = Step {
+            active: true,
+            velocity: 100,
+            ..Step::default()
+        };
+        assert!(!sequencer.pattern_mut().set_step(0, STEPS_PER_PATTERN, hit));
+        assert!(sequencer.pattern_mut().set_bar_count(2));
+        assert!(!sequencer.pattern_mut().set_bar_count(MAX_PATTERN_BARS + 1));
+        assert!(sequencer
+            .pattern_mut()
+            .set_step(0, STEPS_PER_PATTERN + 2, hit));
+        sequencer.start();
+
+        sequencer.process_block(95_000);
+        let position = sequencer.position();
+        assert_eq!((position.bar, position.pattern_bar), (0, 0));
+        assert_eq!(sequencer.step_changes().len(), 16);
+
+        let events = sequencer.process_block(96_000);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].step_index as usize, STEPS_PER_PATTERN + 2);
+        let first_change = sequencer.step_changes()[0];
+        assert_eq!(first_change.step_index, STEPS_PER_PATTERN);
+        assert_eq!((first_change.bar, first_change.pattern_bar), (1, 1));
+        let position = sequencer.position();
+        assert_eq!((position.pattern_bar, position.beat), (1, 3));
+
+        sequencer.process_block(2_000);
+        let position = sequencer.position();
+        assert_eq!(
+            (position.bar, position.pattern_bar, position.step_index),
+            (2, 0, 0)
+        );
+    }
+
+    #[test]
+    fn swing_delays_offbeat_steps() {
+        let mut sequencer = Sequencer::new(48_000);
+        sequencer.set_swing(0.4);
+        assert!(sequencer.pattern_mut().set_step(
+            0,
+            1,
+            Step {
+                active: true,
+                velocity: 110,
+                ..Step::default()
+            },
+        ));
+        sequencer.start();
+
+        let events = sequencer.process_block(9_000);
+        let offbeat = events
+            .iter()
+            .find(|event| event.step_index == 1)
+            .expect("step 1 event should exist");
+        assert_eq!(offbeat.block_offset, 8_400);
+    }
+
+    #[test]
+    fn groove_templates_offset_step_timing_and_velocity_on_top_of_swing() {
+        let mut sequencer = Sequencer::new(48_000);
+        sequencer.set_swing(0.1);
+        let mut lazy = GrooveTemplate::new("lazy");
+        lazy.timing[1] = 0.25;
+        lazy.velocity[1] = -30;
+        lazy.timing[2] = 0.1;
+        let mut invalid = lazy.clone();
+        invalid.timing[0] = 0.5;
+        assert!(!sequencer.set_groove(Some(invalid)));
+        assert!(sequencer.set_groove(Some(lazy)));
+        for step_index in 0..4 {
+            assert!(sequencer.pattern_mut().set_step(
+                0,
+                step_index,
+                Step {
+                    active: true,
+                    velocity: 100,
+                    ..Step::default()
+                },
+            ));
+        }
+        sequencer.start();
+
+        let hits: Vec<(u32, u8)> = sequencer
+            .process_block(24_000)
+            .into_iter()
+            .map(|event| (event.block_offset, event.velocity))
+            .collect();
+        assert_eq!(
+            hits,
+            vec![(0, 100), (8_100, 70), (12_600, 100), (18_600, 100)]
+        );
+
+        let mut project = canonical_fixture_project();
+        project.patterns[0].groove = Some("mpc54".to_string());
+        let state = recall_state_from_project(&project, 48_000).expect("recall");
+        assert_eq!(
+            state
+                .sequencer()
+                .groove()
+                .map(|groove| groove.name.as_str()),
+            Some("mpc54")
+        );
+        project.patterns[0].groove = Some("missing".to_string());
+        assert!(recall_state_from_project(&project, 48_000).is_err());
+    }
+
+    #[test]
+    fn track_swing_overrides_fall_back_to_the_pattern_swing() {
+        let offbeat_offsets = |sequencer: &mut Sequencer| {
+            for track_index in [0, 2] {
+                assert!(sequencer.pattern_mut().set_step(
+                    track_index,
+                    1,
+                    Step {
+                        active: true,
+                        ..Step::default()
+                    },
+                ));
+            }
+            sequencer.start();
+            let mut offsets: Vec<(u8, u32)> = sequencer
+                .process_block(12_000)
+                .into_iter()
+                .map(|event| (event.track_index, event.block_offset))
+                .collect();
+            offsets.sort();
+            offsets
+        };
+
+        let mut hats_swung = Sequencer::new(48_000);
+        assert!(hats_swung.set_track_swing(2, Some(0.4)));
+        assert!(!hats_swung.set_track_swing(2, Some(MAX_SWING + 0.1)));
+        assert!(!hats_swung.set_track_swing(TRACK_COUNT, Some(0.1)));
+        assert_eq!(hats_swung.track_swing(2), Some(0.4));
+        assert_eq!(hats_swung.effective_track_swing(0), 0.0);
+        assert_eq!(
+            offbeat_offsets(&mut hats_swung),
+            vec![(0, 6_000), (2, 8_400)]
+        );
+
+        let mut kick_straight = Sequencer::new(48_000);
+        kick_straight.set_swing(0.4);
+        assert!(kick_straight.set_track_swing(0, Some(0.0)));
+        assert_eq!(kick_straight.effective_track_swing(2), 0.4);
+        assert_eq!(
+            offbeat_offsets(&mut kick_straight),
+            vec![(0, 6_000), (2, 8_400)]
+        );
+    }
+
+    #[test]
+    fn automated_swing_takes_effect_on_the_next_step_boundary() {
+        let mut sequencer = Sequencer::new(48_000);
+        for step_index in [1, 2] {
+            assert!(sequencer.pattern_mut().set_step(
+                0,
+                step_index,
+                Step {
+                    active: true,
+                    velocity: 100,
+                    ..Step::default()
+                },
+            ));
+        }
+        sequencer.start();
+        sequencer.process_block(1_000);
+
+        let swing = FfParameterUpdate {
+            parameter_id: abi_rs::FF_PARAM_GLOBAL_SWING,
+            normalized_value: 1.0,
+            ramp_samples: 0,
+            reserved: 0,
+        };
+        assert!(sequencer.apply_parameter_update(&swing));
+        assert_eq!(sequencer.swing(), 0.0);
+        assert_eq!(sequencer.pending_swing(), Some(MAX_SWING));
+
+        let offsets: Vec<(u8, u32)> = sequencer
+            .process_block(9_000)
+            .iter()
+            .map(|event| (event.step_index, event.block_offset))
+            .collect();
+        assert_eq!(offsets, vec![(1, 5_000), (2, 8_300)]);
+        assert_eq!(sequencer.swing(), MAX_SWING);
+        assert_eq!(sequencer.pending_swing(), None);
+        assert!(!sequencer.apply_parameter_update(&FfParameterUpdate {
+            parameter_id: FF_PARAM_TRACK_BASE + 1,
+            ..swing
+        }));
+    }
+
+    #[test]
+    fn note_repeat_follows_the_swung_step_grid() {
+        let mut sequencer = Sequencer::new(48_000);
+        sequencer.set_swing(0.2);
+        assert!(sequencer.pattern_mut().set_step(
+            1,
+            0,
+            Step {
+                active: true,
+                velocity: 127,
+                ..Step::default()
+            },
+        ));
+        let rolls = NoteRepeat {
+            rate: RepeatRate::ThirtySecond,
+            velocity: 90,
+        };
+        assert!(sequencer.set_note_repeat(1, Some(rolls)));
+        assert!(sequencer.set_note_repeat(
+            2,
+            Some(NoteRepeat {
+                rate: RepeatRate::Quarter,
+                ..rolls
+            }),
+        ));
+        assert!(!sequencer.set_note_repeat(TRACK_COUNT, Some(rolls)));
+        sequencer.start();
+
+        let mut hits = Vec::new();
+        for _ in 0..3 {
+            let block_start = sequencer.position().timeline_sample;
+            hits.extend(sequencer.process_block(10_000).into_iter().map(|event| {
+                (
+                    event.track_index,
+                    block_start + u64::from(event.block_offset),
+                    event.source,
+                )
+            }));
+        }
+        let roll: Vec<u64> = hits
+            .iter()
+            .filter(|(track, _, _)| *track == 1)
+            .map(|(_, at, _)| *at)
+            .collect();
+        assert_eq!(roll[..6], [0, 3_600, 7_200, 9_600, 12_000, 15_600]);
+        let roll_sources: Vec<FfEventSource> = hits
+            .iter()
+            .filter(|(track, _, _)| *track == 1)
+            .map(|(_, _, source)| *source)
+            .take(2)
+            .collect();
+        assert_eq!(
+            roll_sources,
+            vec![FfEventSource::Sequencer, FfEventSource::NoteRepeat]
+        );
+        let quarters: Vec<u64> = hits
+            .iter()
+            .filter(|(track, _, _)| *track == 2)
+            .map(|(_, at, _)| *at)
+            .collect();
+        assert_eq!(quarters, vec![0, 24_000]);
+
+        assert!(sequencer.set_note_repeat(1, None));
+        assert_eq!(sequencer.note_repeat(1), None);
+        assert!(sequencer
+            .process_block(10_000)
+            .iter()
+            .all(|event| event.track_index != 1 || event.step_index == 0));
+    }
+
+    #[test]
+    fn humanize_depth_loosens_only_the_tracks_it_is_set_on() {
+        let mut sequencer = Sequencer::new(48_000);
+        sequencer.set_random_seed(7);
+        for track_index in [0, 4] {
+            for step_index in (0..STEPS_PER_PATTERN).step_by(2) {
+                assert!(sequencer.pattern_mut().set_step(
+                    track_index,
+                    step_index,
+                    Step {
+                        active: true,
+                        velocity: 100,
+                        ..Step::default()
+                    },
+                ));
+            }
+        }
+        let loose = HumanizeDepth {
+            timing_samples: 480,
+            velocity: 20,
+        };
+        assert!(sequencer.set_track_humanize(4, Some(loose)));
+        assert!(!sequencer.set_track_humanize(
+            4,
+            Some(HumanizeDepth {
+                velocity: 65,
+                ..loose
+            }),
+        ));
+        assert!(!sequencer.set_track_humanize(TRACK_COUNT, Some(loose)));
+        assert_eq!(sequencer.track_humanize(4), Some(loose));
+        assert_eq!(sequencer.track_humanize(0), None);
+
+        let play = |sequencer: &mut Sequencer| {
+            sequencer.start();
+            let mut events = Vec::new();
+            for _ in 0..16 {
+                events.extend(sequencer.process_block(6_000));
+            }
+            sequencer.stop();
+            events
+        };
+        let events = play(&mut sequencer);
+        let kick: Vec<&StepTriggerEvent> = events.iter().filter(|e| e.track_index == 0).collect();
+        let perc: Vec<&StepTriggerEvent> = events.iter().filter(|e| e.track_index == 4).collect();
+        assert_eq!(kick.len(), 8);
+        assert_eq!(perc.len(), 8);
+        assert!(kick
+            .iter()
+            .all(|event| event.velocity == 100 && event.timeline_sample % 12_000 == 0));
+        assert!(perc.iter().all(|event| {
+            let step_sample = u64::from(event.step_index) * 6_000;
+            (80..=120).contains(&event.velocity)
+                && (step_sample..=step_sample + 480).contains(&event.timeline_sample)
+        }));
+        assert!(perc.iter().any(|event| event.velocity != 100));
+        assert!(perc.iter().any(|event| event.timeline_sample % 12_000 != 0));
+
+        sequencer.reset();
+        assert_eq!(play(&mut sequencer), events);
+
+        let tight = HumanizeDepth {
+            timing_samples: 0,
+            velocity: 4,
+        };
+        assert!(sequencer.set_humanize(tight));
+        assert!(!sequencer.set_humanize(HumanizeDepth {
+            timing_samples: presets_rs::MAX_HUMANIZE_TIMING_SAMPLES + 1,
+            velocity: 0,
+        }));
+        assert_eq!(sequencer.humanize(), tight);
+        assert_eq!(sequencer.effective_track_humanize(0), tight);
+        assert_eq!(sequencer.effective_track_humanize(4), loose);
+        sequencer.reset();
+        let events = play(&mut sequencer);
+        assert!(events
+            .iter()
+            .filter(|event| event.track_index == 0)
+            .all(
+                |event| (96..=104).contains(&event.velocity) && event.timeline_sample % 12_000 == 0
+            ));
+        assert!(events
+            .iter()
+            .any(|event| event.track_index == 0 && event.velocity != 100));
+
+        assert!(sequencer.set_track_humanize(4, Some(HumanizeDepth::default())));
+        assert_eq!(
+            sequencer.effective_track_humanize(4),
+            HumanizeDepth::default()
+        );
+        sequencer.reset();
+        assert!(play(&mut sequencer)
+            .iter()
+            .filter(|event| event.track_index == 4)
+            .all(|event| event.velocity == 100 && event.timeline_sample % 12_000 == 0));
+        assert!(sequencer.set_track_humanize(4, None));
+        assert_eq!(sequencer.effective_track_humanize(4), tight);
+    }
+
+    #[test]
+    fn step_probability_rolls_a_seeded_chance_per_step() {
+        let hat = |probability: u8| Step {
+            active: true,
+            probability,
+            ..Step::default()
+        };
+        let mut pattern = Pattern::default();
+        for step_index in 0..STEPS_PER_PATTERN {
+            assert!(pattern.set_step(0, step_index, hat(50)));
+            assert!(pattern.set_step(1, step_index, hat(0)));
+            assert!(pattern.set_step(2, step_index, hat(MAX_STEP_PROBABILITY)));
+        }
+        assert!(!pattern.set_step(0, 0, hat(101)));
+
+        let render = |seed: u64| {
+            let mut sequencer = Sequencer::new(48_000);
+            *sequencer.pattern_mut() = pattern.clone();
+            sequencer.set_random_seed(seed);
+            sequencer.start();
+            let mut events = Vec::new();
+            for _ in 0..4 {
+                events.extend(sequencer.process_block(24_000));
+            }
+            events
+        };
+        let events = render(11);
+        let count = |track_index: u8| {
+            events
+                .iter()
+                .filter(|event| event.track_index == track_index)
+                .count()
+        };
+        assert!((1..STEPS_PER_PATTERN).contains(&count(0)));
+        assert_eq!(count(1), 0);
+        assert_eq!(count(2), STEPS_PER_PATTERN);
+        assert_eq!(render(11), events);
+        assert_ne!(render(12), events);
+    }
+
+    #[test]
+    fn trig_conditions_follow_the_last_fired_state_of_the_track_and_its_neighbor() {
+        let trig = |probability: u8, condition: TrigCondition| Step {
+            active: true,
+            probability,
+            condition,
+            ..Step::default()
+        };
+        let mut sequencer = Sequencer::new(48_000);
+        let pattern = sequencer.pattern_mut();
+        pattern.set_step(0, 0, trig(0, TrigCondition::Always));
+        pattern.set_step(0, 4, trig(100, TrigCondition::Pre));
+        pattern.set_step(0, 8, trig(100, TrigCondition::NotPre));
+        pattern.set_step(0, 12, trig(100, TrigCondition::Pre));
+        pattern.set_step(1, 4, trig(100, TrigCondition::Always));
+        pattern.set_step(1, 12, trig(0, TrigCondition::Always));
+        pattern.set_step(2, 4, trig(100, TrigCondition::NotNeighbor));
+        pattern.set_step(2, 12, trig(100, TrigCondition::NotNeighbor));
+        pattern.set_step(3, 4, trig(100, TrigCondition::Neighbor));
+
+        sequencer.start();
+        let events = sequencer.process_block(96_000);
+        let fired: Vec<(u8, u8)> = events
+            .iter()
+            .map(|event| (event.track_index, event.step_index))
+            .collect();
+        assert_eq!(fired, vec![(1, 4), (0, 8), (0, 12), (2, 12)]);
+        assert_eq!(sequencer.track_last_fired(1), Some(false));
+        assert_eq!(sequencer.track_last_fired(2), Some(true));
+        sequencer.reset();
+        assert_eq!(sequencer.track_last_fired(2), Some(false));
+    }
+
+    #[test]
+    fn seeking_jumps_the_playhead_without_resetting_the_timeline() {
+        let mut sequencer = Sequencer::new(48_000);
+        let hit = Step {
+            active: true,
+            velocity: 100,
+            ..Step::default()
+        };
+        assert!(sequencer.pattern_mut().set_step(0, 9, hit));
+        assert!(sequencer
+            .pattern_mut()
+            .set_step(0, 12, Step { flam: true, ..hit }));
+        let hits = |events: Vec<StepTriggerEvent>| -> Vec<(u8, u64)> {
+            events
+                .into_iter()
+                .map(|event| (event.step_index, event.timeline_sample))
+                .collect()
+        };
+
+        sequencer.start();
+        assert!(sequencer.process_block(6_000).is_empty());
+        assert!(sequencer.seek_to_step(8, 0.5));
+        assert_eq!(hits(sequencer.process_block(6_000)), vec![(9, 9_000)]);
+        assert_eq!(sequencer.position().step_index, 9);
+        assert_eq!(sequencer.position().timeline_sample, 12_000);
+
+        assert!(sequencer.seek_to_step(12, 0.0));
+        assert_eq!(hits(sequencer.process_block(256)), vec![(12, 12_000)]);
+        assert!(sequencer.seek_to_step(2, 0.5));
+        assert!(sequencer.process_block(2_048).is_empty());
+        assert_eq!(sequencer.position().step_index, 2);
+        assert_eq!(sequencer.position().timeline_sample, 14_304);
+
+        assert!(!sequencer.seek_to_step(STEPS_PER_PATTERN, 0.0));
+        assert!(!sequencer.seek_to_step(0, 1.0));
+        assert_eq!(sequencer.position().step_index, 2);
+    }
+
+    #[test]
+    fn count_in_clicks_a_bar_before_playback_and_the_metronome_follows_beats() {
+        let mut sequencer = Sequencer::new(48_000);
+        let hit = Step {
+            active: true,
+            velocity: 100,
+            ..Step::default()
+        };
+        assert!(sequencer.pattern_mut().set_step(0, 0, hit));
+        assert!(sequencer.pattern_mut().set_step(0, 5, hit));
+        assert!(!sequencer.set_count_in_bars(MAX_COUNT_IN_BARS + 1));
+        assert!(sequencer.set_count_in_bars(1));
+        sequencer.set_metronome_enabled(true);
+
+        sequencer.start();
+        assert!(sequencer.is_counting_in());
+        let mut hits = Vec::new();
+        let mut clicks = Vec::new();
+        for _ in 0..14 {
+            hits.extend(
+                sequencer
+                    .process_block(10_000)
+                    .into_iter()
+                    .map(|event| (event.step_index, event.timeline_sample, event.block_offset)),
+            );
+            clicks.extend(
+                sequencer
+                    .click_events()
+                    .iter()
+                    .map(|click| (click.kind, click.count_in, click.timeline_sample)),
+            );
+        }
+        assert!(!sequencer.is_counting_in());
+        assert_eq!(hits, vec![(0, 96_000, 6_000), (5, 126_000, 6_000)]);
+        assert_eq!(
+            clicks,
+            vec![
+                (ClickKind::Downbeat, true, 0),
+                (ClickKind::Beat, true, 24_000),
+                (ClickKind::Beat, true, 48_000),
+                (ClickKind::Beat, true, 72_000),
+                (ClickKind::Downbeat, false, 96_000),
+                (ClickKind::Beat, false, 120_000),
+            ]
+        );
+
+        sequencer.stop();
+        sequencer.reset();
+        sequencer.set_metronome_enabled(false);
+        sequencer.start();
+        sequencer.process_block(512);
+        assert_eq!(sequencer.click_events().len(), 1);
+        sequencer.stop();
+        assert!(!sequencer.is_counting_in());
+        assert!(sequencer.locate(0, 2, 0.5, 0));
+        sequencer.start();
+        assert!(!sequencer.is_counting_in());
+    }
+
+    #[test]
+    fn sample_rate_changes_mid_count_in_keep_the_remaining_beats_in_time() {
+        let mut sequencer = Sequencer::new(48_000);
+        assert!(sequencer.pattern_mut().set_step(
+            0,
+            0,
+            Step {
+                active: true,
+                ..Step::default()
+            },
+        ));
+        assert!(sequencer.set_count_in_bars(1));
+        sequencer.set_metronome_enabled(true);
+        sequencer.start();
+        for _ in 0..3 {
+            sequencer.process_block(10_000);
+        }
+
+        assert!(sequencer.set_sample_rate(96_000));
+        assert_eq!(sequencer.position().timeline_sample, 60_000);
+        let mut hits = Vec::new();
+        let mut clicks = Vec::new();
+        for _ in 0..8 {
+            hits.extend(
+                sequencer
+                    .process_block(20_000)
+                    .into_iter()
+                    .map(|event| event.timeline_sample),
+            );
+            clicks.extend(
+                sequencer
+                    .click_events()
+                    .iter()
+                    .map(|click| (click.kind, click.count_in, click.timeline_sample)),
+            );
+        }
+        assert_eq!(hits, vec![192_000]);
+        assert_eq!(
+            clicks,
+            vec![
+                (ClickKind::Beat, true, 96_000),
+                (ClickKind::Beat, true, 144_000),
+                (ClickKind::Downbeat, false, 192_000),
+            ]
+        );
+    }
+
+    #[test]
+    fn playback_directions_reverse_ping_pong_and_seed_random_order_across_blocks() {
+        let hit = Step {
+            active: true,
+            ..Step::default()
+        };
+        let mut sequencer = Sequencer::new(48_000);
+        let pattern = sequencer.pattern_mut();
+        pattern.set_step(0, 0, hit);
+        pattern.set_step(0, 1, hit);
+        pattern.set_step(1, 3, hit);
+        assert!(pattern.set_track_direction(0, PlaybackDirection::Reverse));
+        assert!(pattern.set_track_direction(1, PlaybackDirection::PingPong));
+        assert!(!pattern.set_track_direction(TRACK_COUNT, PlaybackDirection::Reverse));
+        assert_eq!(
+            pattern.track_direction(1),
+            Some(PlaybackDirection::PingPong)
+        );
+
+        sequencer.start();
+        let mut fired = Vec::new();
+        for _ in 0..(192_000 / 500) {
+            fired.extend(
+                sequencer
+                    .process_block(500)
+                    .iter()
+                    .map(|event| (event.track_index, event.step_index, event.timeline_sample)),
+            );
+        }
+        assert_eq!(
+            fired,
+            vec![
+                (1, 3, 18_000),
+                (0, 1, 84_000),
+                (0, 0, 90_000),
+                (1, 3, 168_000),
+                (0, 1, 180_000),
+                (0, 0, 186_000),
+            ]
+        );
+
+        let random_order = |seed: u64| {
+            let mut sequencer = Sequencer::new(48_000);
+            for step_index in 0..STEPS_PER_PATTERN {
+                sequencer.pattern_mut().set_step(0, step_index, hit);
+            }
+            sequencer
+                .pattern_mut()
+                .set_track_direction(0, PlaybackDirection::Random);
+            sequencer.set_random_seed(seed);
+            sequencer.start();
+            sequencer
+                .process_block(192_000)
+                .iter()
+                .map(|event| event.step_index)
+                .collect::<Vec<u8>>()
+        };
+        assert_eq!(random_order(7).len(), 2 * STEPS_PER_PATTERN);
+        assert_eq!(random_order(7), random_order(7));
+        assert_ne!(random_order(7), random_order(8));
+    }
+
+    #[test]
+    fn song_chain_switches_patterns_on_bar_boundaries_and_stops_at_the_end() {
+        let mut patterns = vec![Pattern::default(); 2];
+        for (track_index, pattern) in patterns.iter_mut().enumerate() {
+            pattern.set_step(
+                track_index,
+                0,
+                Step {
+                    active: true,
+                    ..Step::default()
+                },
+            );
+        }
+        let mut song = Song::new(patterns);
+        assert!(song.set_chain(&[
+            SongEntry {
+                pattern_index: 0,
+                repeats: 2,
             },
+            SongEntry {
+                pattern_index: 1,
+                repeats: 1,
+            },
+        ]));
+        let mut sequencer = Sequencer::new(48_000);
+        assert!(!sequencer.set_song(Some(Song::new(Vec::new()))));
+        assert!(sequencer.set_song(Some(song)));
+
+        sequencer.start();
+        let mut fired = Vec::new();
+        for _ in 0..(384_000 / 500) {
+            fired.extend(
+                sequencer
+                    .process_block(500)
+                    .iter()
+                    .map(|event| (event.track_index, event.timeline_sample)),
+            );
+            if sequencer.timeline_sample == 250_000 {
+                assert_eq!(
+                    sequencer.song_position(),
+                    Some(SongPosition {
+                        entry_index: 1,
+                        repeat: 0,
+                    })
+                );
+            }
+        }
+        assert_eq!(fired, vec![(0, 0), (0, 96_000), (1, 192_000)]);
+        assert!(!sequencer.transport().is_playing());
+        assert_eq!(sequencer.song_position(), Some(SongPosition::default()));
+        assert_eq!(
+            sequencer.pattern().step(0, 0).map(|step| step.active),
+            Some(true)
         );
-        project
+
+        let mut project = canonical_fixture_project();
+        let mut bridge = PresetPattern::default();
+        bridge.set_bar_count(2);
+        project.patterns.push(bridge);
+        assert!(project.set_song(&[presets_rs::SongEntry {
+            pattern_index: 1,
+            repeats: 3,
+        }]));
+        let recall = recall_state_from_project(&project, 48_000).expect("recall");
+        assert_eq!(
+            recall.sequencer().song().map(|song| song.chain().len()),
+            Some(1)
+        );
+        assert_eq!(recall.sequencer().pattern().bar_count(), 2);
     }
 
-    fn track_index_from_parameter_id(parameter_id: u32) -> Option<u8> {
-        if parameter_id < FF_PARAM_TRACK_BASE {
-            return None;
+    #[test]
+    fn song_mode_applies_each_pattern_swing_and_groove() {
+        let mut project = canonical_fixture_project();
+        let mut swung = PresetPattern::default();
+        swung.set_swing(0.25);
+        swung.groove = Some("mpc58".to_string());
+        project.patterns.push(swung);
+        for pattern in &mut project.patterns {
+            assert!(pattern.set_step(
+                0,
+                1,
+                PatternStep {
+                    active: true,
+                    ..PatternStep::default()
+                },
+            ));
+        }
+        assert!(project.set_song(&[
+            presets_rs::SongEntry {
+                pattern_index: 0,
+                repeats: 1,
+            },
+            presets_rs::SongEntry {
+                pattern_index: 1,
+                repeats: 1,
+            },
+        ]));
+        let mut recall = recall_state_from_project(&project, 48_000).expect("recall");
+        let sequencer = recall.sequencer_mut();
+        assert_eq!(sequencer.swing(), 0.0);
+        assert_eq!(sequencer.pattern().swing(), Some(0.0));
+        assert!(sequencer.groove().is_none());
+
+        sequencer.start();
+        let mut hits = Vec::new();
+        for _ in 0..(200_000 / 500) {
+            hits.extend(
+                sequencer
+                    .process_block(500)
+                    .iter()
+                    .filter(|event| event.track_index == 0)
+                    .map(|event| event.timeline_sample),
+            );
+            if sequencer.timeline_sample == 100_000 {
+                assert_eq!(sequencer.swing(), 0.25);
+                assert_eq!(sequencer.groove(), project.groove("mpc58").as_ref());
+            }
         }
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0], 6_000);
+        assert!(hits[1] > 96_000 + 6_000);
 
-        let track_offset = parameter_id - FF_PARAM_TRACK_BASE;
-        let track_index = (track_offset / FF_PARAM_TRACK_STRIDE) as u8;
-        (track_index < TRACK_COUNT as u8).then_some(track_index)
+        assert!(!sequencer.transport().is_playing());
+        assert_eq!(sequencer.swing(), 0.0);
+        assert!(sequencer.groove().is_none());
     }
 
-    fn format_updates_csv_for_tracks(
-        updates: &[abi_rs::FfParameterUpdate],
-        tracks: &[u8],
-    ) -> String {
-        let mut lines = vec!["# parameter_id,normalized_value".to_string()];
-        for update in updates {
-            if let Some(track_index) = track_index_from_parameter_id(update.parameter_id) {
-                if tracks.contains(&track_index) {
-                    lines.push(format!(
-                        "{},{}",
-                        update.parameter_id,
-                        format!("{:.6}", update.normalized_value)
-                    ));
-                }
+    #[test]
+    fn cycle_fill_and_first_loop_conditions_follow_the_loop_counter() {
+        let trig = |condition: TrigCondition| Step {
+            active: true,
+            condition,
+            ..Step::default()
+        };
+        let mut sequencer = Sequencer::new(48_000);
+        let pattern = sequencer.pattern_mut();
+        assert!(!pattern.set_step(0, 0, trig(TrigCondition::Cycle { hit: 3, of: 2 })));
+        assert!(!pattern.set_step(0, 0, trig(TrigCondition::Cycle { hit: 0, of: 2 })));
+        assert!(!pattern.set_step(0, 0, trig(TrigCondition::Cycle { hit: 1, of: 9 })));
+        pattern.set_step(0, 0, trig(TrigCondition::Cycle { hit: 1, of: 2 }));
+        pattern.set_step(1, 0, trig(TrigCondition::Cycle { hit: 3, of: 4 }));
+        pattern.set_step(2, 0, trig(TrigCondition::First));
+        pattern.set_step(3, 0, trig(TrigCondition::NotFirst));
+        pattern.set_step(4, 0, trig(TrigCondition::Fill));
+        pattern.set_step(5, 0, trig(TrigCondition::NotFill));
+
+        sequencer.start();
+        let mut fired = Vec::new();
+        for bar in 0..4 {
+            if bar == 3 {
+                sequencer.queue_action(LiveAction::SetFill(true));
             }
+            let tracks: Vec<u8> = sequencer
+                .process_block(96_000)
+                .iter()
+                .filter(|event| event.step_index == 0)
+                .map(|event| event.track_index)
+                .collect();
+            fired.push(tracks);
         }
-        lines.join("\n")
+        assert_eq!(
+            fired,
+            vec![vec![0, 2, 5], vec![3, 5], vec![0, 1, 3, 5], vec![3, 4]]
+        );
+        assert_eq!(sequencer.loop_count(), 3);
+        sequencer.stop();
+        sequencer.queue_action(LiveAction::SwitchPattern(Box::default()));
+        assert_eq!(sequencer.loop_count(), 0);
     }
 
-    fn normalize_newlines(value: &str) -> String {
-        value.lines().collect::<Vec<_>>().join("\n")
+    #[test]
+    fn clock_boundaries_report_the_next_tick_beat_and_bar_samples() {
+        let mut sequencer = Sequencer::new(48_000);
+        assert_eq!(sequencer.next_clock_boundaries(), None);
+        sequencer.start();
+        assert_eq!(
+            sequencer.next_clock_boundaries(),
+            Some(ClockBoundaries {
+                next_tick_sample: 0,
+                next_beat_sample: 0,
+                next_bar_sample: 0,
+            })
+        );
+
+        sequencer.process_block(500);
+        assert_eq!(
+            sequencer.next_clock_boundaries(),
+            Some(ClockBoundaries {
+                next_tick_sample: 1_000,
+                next_beat_sample: 24_000,
+                next_bar_sample: 96_000,
+            })
+        );
+        sequencer.process_block(29_750);
+        assert_eq!(
+            sequencer.next_clock_boundaries(),
+            Some(ClockBoundaries {
+                next_tick_sample: 31_000,
+                next_beat_sample: 48_000,
+                next_bar_sample: 96_000,
+            })
+        );
+
+        let mut swung = Sequencer::new(48_000);
+        swung.set_swing(0.25);
+        swung.start();
+        swung.process_block(500);
+        let boundaries = swung.next_clock_boundaries().expect("playing");
+        assert_eq!(boundaries.next_tick_sample, 1_250);
+        assert_eq!(boundaries.next_beat_sample, 24_000);
     }
 
     #[test]
-    fn pattern_supports_eight_tracks_and_sixteen_steps() {
-        let mut pattern = Pattern::default();
-        assert!(pattern.set_step(
-            TRACK_COUNT - 1,
-            STEPS_PER_PATTERN - 1,
+    fn flam_steps_emit_a_quieter_grace_hit_before_the_main_hit_across_blocks() {
+        let flam = Step {
+            active: true,
+            velocity: 100,
+            flam: true,
+            ..Step::default()
+        };
+        let mut sequencer = Sequencer::new(48_000);
+        let pattern = sequencer.pattern_mut();
+        assert!(pattern.set_step(1, 0, flam));
+        assert!(!pattern.set_step(
+            1,
+            1,
             Step {
-                active: true,
-                velocity: 127,
-            },
+                flam_spacing_ms: 0,
+                ..flam
+            }
         ));
-        assert!(
-            pattern
-                .step(TRACK_COUNT - 1, STEPS_PER_PATTERN - 1)
-                .expect("step should exist")
-                .active
-        );
         assert!(!pattern.set_step(
-            TRACK_COUNT,
+            1,
+            1,
+            Step {
+                flam_spacing_ms: MAX_FLAM_SPACING_MS + 1,
+                ..flam
+            }
+        ));
+        sequencer.start();
+
+        let mut hits = Vec::new();
+        for _ in 0..2 {
+            hits.extend(
+                sequencer
+                    .process_block(512)
+                    .into_iter()
+                    .map(|event| (event.velocity, event.timeline_sample, event.block_offset)),
+            );
+        }
+        let spacing = 48 * u64::from(DEFAULT_FLAM_SPACING_MS);
+        assert_eq!(
+            hits,
+            vec![(50, 0, 0), (100, spacing, (spacing - 512) as u32)]
+        );
+    }
+
+    #[test]
+    fn panicking_bar_hooks_are_contained_and_counted_as_process_faults() {
+        let mut sequencer = Sequencer::new(48_000);
+        assert!(sequencer.pattern_mut().set_step(
+            0,
             0,
             Step {
                 active: true,
+                ..Step::default()
+            }
+        ));
+        sequencer.start();
+
+        let events = sequencer.process_block_with_bar_hook(512, |_, _| panic!("bar hook failed"));
+        assert!(events.is_empty());
+        assert_eq!(sequencer.process_faults(), 1);
+        assert_eq!(sequencer.position().timeline_sample, 512);
+
+        let events = sequencer.process_block(6_000);
+        assert_eq!(events.len(), 0);
+        assert_eq!(sequencer.process_faults(), 1);
+        assert_eq!(sequencer.position().timeline_sample, 6_512);
+    }
+
+    #[test]
+    fn bulk_step_edits_validate_once_and_apply_atomically() {
+        let hit = Step {
+            active: true,
+            ..Step::default()
+        };
+        let mut pattern = Pattern::default();
+        assert!(pattern.set_steps_bulk(
+            2,
+            (0..STEPS_PER_PATTERN).map(|step_index| (step_index, hit))
+        ));
+        assert!(pattern.set_velocity_range(2, 0..8, 64));
+        assert!(pattern.clear_step_range(2, 1..STEPS_PER_PATTERN, 2));
+        let velocities: Vec<Option<u8>> = (0..STEPS_PER_PATTERN)
+            .map(|step_index| {
+                pattern
+                    .step(2, step_index)
+                    .filter(|step| step.active)
+                    .map(|step| step.velocity)
+            })
+            .collect();
+        assert_eq!(
+            velocities,
+            [64, 64, 64, 64, 100, 100, 100, 100]
+                .iter()
+                .flat_map(|velocity| [Some(*velocity), None])
+                .collect::<Vec<_>>()
+        );
+
+        let before = pattern.clone();
+        assert!(!pattern.set_steps_bulk(2, [(0, Step::default()), (STEPS_PER_PATTERN, hit)]));
+        assert!(!pattern.set_steps_bulk(
+            2,
+            [
+                (1, hit),
+                (
+                    3,
+                    Step {
+                        probability: MAX_STEP_PROBABILITY + 1,
+                        ..hit
+                    }
+                )
+            ]
+        ));
+        assert!(!pattern.set_velocity_range(2, 0..4, 128));
+        assert!(!pattern.clear_step_range(2, 0..STEPS_PER_PATTERN + 1, 1));
+        assert!(!pattern.clear_step_range(2, 0..4, 0));
+        assert!(!pattern.clear_step_range(TRACK_COUNT, 0..4, 1));
+        assert_eq!(pattern, before);
+    }
+
+    #[test]
+    fn published_pattern_snapshots_are_adopted_at_the_next_step_boundary() {
+        let hit = Step {
+            active: true,
+            ..Step::default()
+        };
+        let snapshots = PatternSnapshots::new(Pattern::default());
+        let mut sequencer = Sequencer::new(48_000);
+        sequencer.attach_pattern_snapshots(snapshots.clone());
+        assert!(sequencer.process_block(64).is_empty());
+        assert_eq!(sequencer.snapshot_version(), 1);
+
+        sequencer.start();
+        assert!(sequencer.process_block(64).is_empty());
+        assert!(
+            snapshots.edit(|pattern| pattern.set_step(0, 0, hit) && pattern.set_step(2, 1, hit))
+        );
+        assert_eq!(sequencer.pattern().step(2, 1), Some(Step::default()));
+
+        let events = sequencer.process_block(6_000);
+        assert_eq!(
+            events
+                .iter()
+                .map(|event| (event.track_index, event.step_index))
+                .collect::<Vec<_>>(),
+            vec![(2, 1)]
+        );
+        assert_eq!(sequencer.snapshot_version(), 2);
+        assert!(std::ptr::eq(sequencer.pattern(), &*snapshots.current()));
+
+        assert!(sequencer.pattern_mut().set_step(3, 0, hit));
+        assert_eq!(snapshots.current().step(3, 0), Some(Step::default()));
+        assert!(sequencer.detach_pattern_snapshots().is_some());
+        assert!(snapshots.edit(|pattern| pattern.set_bar_count(2)));
+        sequencer.process_block(6_000);
+        assert_eq!(sequencer.pattern().step_count(), STEPS_PER_PATTERN);
+    }
+
+    #[test]
+    fn sparse_step_storage_keeps_edits_canonical_across_long_patterns() {
+        let mut pattern = Pattern::default();
+        assert!(pattern.set_bar_count(MAX_PATTERN_BARS));
+        let empty = pattern.clone();
+        let last = MAX_PATTERN_STEPS - 1;
+        let hit = Step {
+            active: true,
+            velocity: 90,
+            ..Step::default()
+        };
+
+        assert!(pattern.set_step(TRACK_COUNT - 1, last, hit));
+        assert!(pattern.set_step(0, 3, hit));
+        assert!(pattern.set_step(0, 3, Step::default()));
+        assert!(pattern.clear_step_range(TRACK_COUNT - 1, last..MAX_PATTERN_STEPS, 1));
+        assert_eq!(pattern, empty);
+
+        let muted = Step {
+            active: false,
+            ..hit
+        };
+        assert!(pattern.set_step(2, STEPS_PER_PATTERN * 2, muted));
+        assert_eq!(pattern.step(2, STEPS_PER_PATTERN * 2), Some(muted));
+        assert_ne!(pattern, empty);
+
+        assert!(pattern.set_bar_count(1));
+        assert!(pattern.set_bar_count(MAX_PATTERN_BARS));
+        assert_eq!(pattern, empty);
+    }
+
+    #[test]
+    fn accented_steps_boost_velocity_by_the_pattern_accent_amount() {
+        let step = |velocity: u8, accent: bool| Step {
+            active: true,
+            velocity,
+            accent,
+            ..Step::default()
+        };
+        let mut sequencer = Sequencer::new(48_000);
+        sequencer.edit_step(3, 8, step(50, true));
+        assert_eq!(
+            sequencer.process_block(64)[0].velocity,
+            50 + DEFAULT_ACCENT_AMOUNT
+        );
+
+        let pattern = sequencer.pattern_mut();
+        pattern.set_step(0, 0, step(60, true));
+        pattern.set_step(1, 0, step(110, true));
+        pattern.set_step(2, 0, step(60, false));
+        assert!(!pattern.set_accent_amount(MAX_ACCENT_AMOUNT + 1));
+        assert!(pattern.set_accent_amount(40));
+        sequencer.start();
+        let velocities: Vec<u8> = sequencer
+            .process_block(64)
+            .iter()
+            .map(|event| event.velocity)
+            .collect();
+        assert_eq!(velocities, vec![100, 127, 60]);
+
+        assert!(!sequencer.pattern_mut().set_accent_curve(AccentCurve {
+            boosts: [MAX_ACCENT_AMOUNT + 1; 5],
+            clip: AccentClip::Clamp,
+        }));
+        assert!(sequencer.pattern_mut().set_accent_curve(AccentCurve {
+            boosts: [60, 60, 40, 20, 20],
+            clip: AccentClip::Headroom,
+        }));
+        assert_eq!(sequencer.pattern().accent_amount(), 60);
+        sequencer.stop();
+        sequencer.reset();
+        sequencer.start();
+        let velocities: Vec<u8> = sequencer
+            .process_block(64)
+            .iter()
+            .map(|event| event.velocity)
+            .collect();
+        assert_eq!(velocities, vec![82, 113, 60]);
+    }
+
+    #[test]
+    fn parameter_locks_emit_updates_and_restore_the_recalled_base() {
+        let mut project = canonical_fixture_project();
+        for step_index in [0, 4] {
+            project.patterns[0].set_step(
+                0,
+                step_index,
+                PatternStep {
+                    active: true,
+                    velocity: 100,
+                    ..PatternStep::default()
+                },
+            );
+        }
+        assert!(project.patterns[0].set_parameter_lock(ParameterLock {
+            track_index: 0,
+            step_index: 0,
+            target: LockTarget::FilterCutoff,
+            value: 0.25,
+        }));
+        let mut recall = recall_state_from_project(&project, 48_000).expect("recall");
+        let sequencer = recall.sequencer_mut();
+        assert_eq!(sequencer.pattern().parameter_locks().len(), 1);
+        assert!(!sequencer.pattern_mut().set_parameter_lock(ParameterLock {
+            track_index: 0,
+            step_index: 16,
+            target: LockTarget::Pitch,
+            value: 0.5,
+        }));
+        let base = sequencer
+            .track_lock_base(0, LockTarget::FilterCutoff)
+            .expect("base");
+        let cutoff_id = ff_track_parameter_id(0, FF_PARAM_SLOT_FILTER_CUTOFF).expect("id");
+
+        sequencer.start();
+        let events = sequencer.process_block(6_000);
+        assert_eq!(events[0].step_index, 0);
+        let locks = sequencer.parameter_lock_updates();
+        assert_eq!(locks.len(), 1);
+        assert_eq!(locks[0].block_offset, events[0].block_offset);
+        assert_eq!(locks[0].update.parameter_id, cutoff_id);
+        assert_eq!(locks[0].update.normalized_value, 0.25);
+
+        let mut restored = Vec::new();
+        for _ in 0..4 {
+            sequencer.process_block(6_000);
+            restored.extend_from_slice(sequencer.parameter_lock_updates());
+        }
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].update.normalized_value, base);
+    }
+
+    #[test]
+    fn events_sharing_an_offset_come_out_in_track_order() {
+        let mut sequencer = Sequencer::new(48_000);
+        for track_index in [6, 3] {
+            assert!(sequencer.pattern_mut().set_step(
+                track_index,
+                0,
+                Step {
+                    active: true,
+                    velocity: 100,
+                    ..Step::default()
+                },
+            ));
+        }
+        assert!(sequencer.set_note_repeat(
+            1,
+            Some(NoteRepeat {
+                rate: RepeatRate::Quarter,
+                velocity: 90,
+            }),
+        ));
+        sequencer.start();
+        let order: Vec<(u32, u8)> = sequencer
+            .process_block(256)
+            .iter()
+            .map(|event| (event.block_offset, event.track_index))
+            .collect();
+        assert_eq!(order, vec![(0, 1), (0, 3), (0, 6)]);
+
+        let mut events = vec![
+            StepTriggerEvent {
+                track_index: 4,
+                step_index: 0,
+                velocity: 100,
+                gate: MAX_STEP_GATE,
+                choke_group: None,
+                timeline_sample: 64,
+                block_offset: 64,
+                source: FfEventSource::Sequencer,
+            },
+            StepTriggerEvent {
+                track_index: 2,
+                step_index: 0,
                 velocity: 100,
+                gate: MAX_STEP_GATE,
+                choke_group: None,
+                timeline_sample: 64,
+                block_offset: 64,
+                source: FfEventSource::Sequencer,
             },
-        ));
+        ];
+        sort_trigger_events(&mut events);
+        assert_eq!(events[0].track_index, 2);
     }
 
     #[test]
-    fn transport_clamps_tempo() {
-        let mut transport = Transport::default();
-        transport.set_bpm(9999.0);
-        assert_eq!(transport.bpm(), MAX_BPM);
-        transport.set_bpm(1.0);
-        assert_eq!(transport.bpm(), MIN_BPM);
-        transport.set_bpm(DEFAULT_BPM);
-        assert_eq!(transport.bpm(), DEFAULT_BPM);
+    fn pattern_scale_changes_step_length_for_every_track() {
+        let mut project = presets_rs::Project {
+            kits: vec![presets_rs::Kit::default()],
+            patterns: vec![presets_rs::Pattern::default()],
+            ..presets_rs::Project::default()
+        };
+        project.patterns[0].scale = PatternScale::Double;
+        for track_index in [0, 5] {
+            project.patterns[0].set_step(
+                track_index,
+                1,
+                presets_rs::PatternStep {
+                    active: true,
+                    velocity: 100,
+                    ..PatternStep::default()
+                },
+            );
+        }
+
+        let mut recall = recall_state_from_project(&project, 48_000).expect("recall");
+        assert_eq!(recall.sequencer().pattern().scale(), PatternScale::Double);
+        let sequencer = recall.sequencer_mut();
+        sequencer.start();
+        let events = sequencer.process_block(4_000);
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|event| event.block_offset == 3_000));
+
+        sequencer.stop();
+        sequencer.reset();
+        sequencer.pattern_mut().set_scale(PatternScale::Half);
+        sequencer.start();
+        assert!(sequencer.process_block(12_000).is_empty());
+        assert_eq!(sequencer.process_block(1).len(), 2);
     }
 
     #[test]
-    fn sequencer_emits_step_zero_immediately_on_start() {
+    fn switched_patterns_apply_their_tempo_override() {
         let mut sequencer = Sequencer::new(48_000);
-        assert!(sequencer.pattern_mut().set_step(
-            0,
-            0,
-            Step {
-                active: true,
-                velocity: 120,
-            },
-        ));
+        let mut fast = Pattern::default();
+        assert!(!fast.set_tempo(Some(PatternTempo {
+            bpm: 500.0,
+            change: TempoChange::Immediate,
+        })));
+        assert!(fast.set_tempo(Some(PatternTempo {
+            bpm: 150.0,
+            change: TempoChange::Immediate,
+        })));
         sequencer.start();
+        sequencer.process_block(1);
+        assert!(sequencer.queue_action(LiveAction::SwitchPattern(Box::new(fast.clone()))));
+        sequencer.process_block(6_000 * STEPS_PER_PATTERN as u32);
+        assert_eq!(sequencer.transport().bpm(), 150.0);
 
-        let events = sequencer.process_block(128);
-        assert_eq!(events.len(), 1);
-        assert_eq!(events[0].track_index, 0);
-        assert_eq!(events[0].step_index, 0);
-        assert_eq!(events[0].choke_group, None);
-        assert_eq!(events[0].block_offset, 0);
+        let mut slow = fast;
+        slow.set_tempo(Some(PatternTempo {
+            bpm: 110.0,
+            change: TempoChange::Smooth,
+        }));
+        sequencer.queue_action(LiveAction::SwitchPattern(Box::new(slow)));
+        sequencer.process_block(4_800 * STEPS_PER_PATTERN as u32);
+        assert_eq!(sequencer.tempo_glide_target(), Some(110.0));
+        let gliding = sequencer.transport().bpm();
+        assert!(gliding < 150.0 && gliding > 110.0);
+        sequencer.process_block(6_000 * STEPS_PER_PATTERN as u32);
+        assert_eq!(sequencer.tempo_glide_target(), None);
+        assert!((sequencer.transport().bpm() - 110.0).abs() < 1e-3);
     }
 
     #[test]
-    fn sequencer_emits_multi_track_step_events() {
+    fn smooth_pattern_tempo_glides_continuously_over_one_bar() {
         let mut sequencer = Sequencer::new(48_000);
-        assert!(sequencer.pattern_mut().set_step(
-            1,
-            5,
-            Step {
-                active: true,
-                velocity: 90,
-            },
-        ));
-        assert!(sequencer.pattern_mut().set_step(
-            3,
-            5,
-            Step {
-                active: true,
-                velocity: 110,
-            },
-        ));
-
+        sequencer.set_tempo_bpm(150.0);
+        let mut slow = Pattern::default();
+        for step_index in 0..STEPS_PER_PATTERN {
+            assert!(slow.set_step(
+                0,
+                step_index,
+                Step {
+                    active: true,
+                    ..Step::default()
+                }
+            ));
+        }
+        slow.set_tempo(Some(PatternTempo {
+            bpm: 110.0,
+            change: TempoChange::Smooth,
+        }));
         sequencer.start();
-        let events = sequencer.process_block(30_000);
-        let step_five_events: Vec<_> = events
+        sequencer.process_block(1);
+        assert!(sequencer.queue_action(LiveAction::SwitchPattern(Box::new(slow))));
+        let hits: Vec<u64> = sequencer
+            .process_block(48_000 * 5)
             .iter()
-            .filter(|event| event.step_index == 5)
+            .map(|event| event.timeline_sample)
             .collect();
-        assert_eq!(step_five_events.len(), 2);
-        assert!(step_five_events.iter().any(|event| event.track_index == 1));
-        assert!(step_five_events.iter().any(|event| event.track_index == 3));
+        assert!(hits.len() > STEPS_PER_PATTERN + 1);
+        let spacing: Vec<i64> = hits
+            .windows(2)
+            .map(|pair| pair[1] as i64 - pair[0] as i64)
+            .collect();
+
+        // Under a linear tempo curve the bar lasts the integral of 60/bpm
+        // over its four beats, and every step is a little longer than the last.
+        let glide_bar =
+            48_000.0 * 60.0 * TEMPO_GLIDE_BEATS as f64 * (110.0_f64 / 150.0).ln() / (110.0 - 150.0);
+        let bar = (hits[STEPS_PER_PATTERN] - hits[0]) as f64;
+        assert!((bar - glide_bar).abs() <= 2.0, "{bar} vs {glide_bar}");
+        assert!(spacing[0] > 4_800 && spacing[STEPS_PER_PATTERN - 1] < 6_546);
+        assert!(spacing[..STEPS_PER_PATTERN]
+            .windows(2)
+            .all(|pair| pair[1] > pair[0]));
+        let growth: Vec<i64> = spacing[..STEPS_PER_PATTERN]
+            .windows(2)
+            .map(|pair| pair[1] - pair[0])
+            .collect();
+        assert!(growth.iter().max().unwrap() - growth.iter().min().unwrap() <= 60);
+        assert!(spacing[STEPS_PER_PATTERN..]
+            .iter()
+            .all(|interval| (6_545..=6_546).contains(interval)));
     }
 
     #[test]
-    fn sequencer_wraps_after_sixteen_steps() {
+    fn recalled_track_inputs_route_pads_to_monitoring_and_recording() {
+        let mut project = Project {
+            kits: vec![Kit::default()],
+            patterns: vec![PresetPattern::default()],
+            ..Project::default()
+        };
+        project.track_inputs.set_armed(1, true);
+        project.track_inputs.set_monitoring(2, false);
+
+        let mut recall = recall_state_from_project(&project, 48_000).expect("recall");
+        let sequencer = recall.sequencer_mut();
+        assert_eq!(
+            sequencer.route_pad_input(1),
+            PadInputRoute {
+                monitor: true,
+                record: true,
+            }
+        );
+        assert_eq!(sequencer.route_pad_input(2), PadInputRoute::default());
+        assert!(sequencer.set_track_armed(2, true));
+        assert!(!sequencer.set_track_armed(TRACK_COUNT, true));
+        assert!(sequencer.route_pad_input(2).record);
+        assert_eq!(sequencer.track_inputs().armed_tracks(), vec![1, 2]);
+        assert!(!sequencer.route_pad_input(TRACK_COUNT).monitor);
+    }
+
+    #[test]
+    fn swing_is_clamped() {
+        let mut sequencer = Sequencer::new(48_000);
+        sequencer.set_swing(1.0);
+        assert_eq!(sequencer.swing(), MAX_SWING);
+    }
+
+    #[test]
+    fn choke_group_is_carried_in_step_events() {
         let mut sequencer = Sequencer::new(48_000);
+        assert!(sequencer.set_track_choke_group(3, Some(1)));
         assert!(sequencer.pattern_mut().set_step(
-            2,
+            3,
             0,
             Step {
                 active: true,
                 velocity: 127,
+                ..Step::default()
             },
         ));
         sequencer.start();
 
-        let first_bar = sequencer.process_block(96_000);
-        let second_bar = sequencer.process_block(96_000);
-
-        assert!(first_bar
-            .iter()
-            .any(|event| event.step_index == 0 && event.track_index == 2));
-        assert!(second_bar
+        let events = sequencer.process_block(64);
+        let event = events
             .iter()
-            .any(|event| event.step_index == 0 && event.track_index == 2));
+            .find(|value| value.track_index == 3)
+            .expect("track 3 event should exist");
+        assert_eq!(event.choke_group, Some(1));
     }
 
     #[test]
-    fn swing_delays_offbeat_steps() {
+    fn sample_rate_changes_keep_the_musical_position() {
         let mut sequencer = Sequencer::new(48_000);
-        sequencer.set_swing(0.4);
         assert!(sequencer.pattern_mut().set_step(
             0,
-            1,
+            2,
             Step {
                 active: true,
-                velocity: 110,
+                ..Step::default()
             },
         ));
+        assert!(sequencer.set_note_repeat(
+            1,
+            Some(NoteRepeat {
+                rate: RepeatRate::Sixteenth,
+                velocity: 90,
+            }),
+        ));
         sequencer.start();
+        sequencer.process_block(9_000);
+        assert!(!sequencer.set_sample_rate(0));
+        assert!(sequencer.set_sample_rate(96_000));
+        assert_eq!(sequencer.sample_rate_hz(), 96_000);
 
-        let events = sequencer.process_block(9_000);
-        let offbeat = events
+        let events = sequencer.process_block(7_000);
+        let kick: Vec<u64> = events
             .iter()
-            .find(|event| event.step_index == 1)
-            .expect("step 1 event should exist");
-        assert_eq!(offbeat.block_offset, 8_400);
+            .filter(|event| event.track_index == 0)
+            .map(|event| event.timeline_sample)
+            .collect();
+        assert_eq!(kick, vec![24_000]);
+        assert!(events
+            .iter()
+            .filter(|event| event.track_index == 1)
+            .all(|event| event.timeline_sample % 12_000 == 0));
     }
 
     #[test]
-    fn swing_is_clamped() {
+    fn bar_scheduled_recall_is_released_at_the_next_bar_offset() {
+        let recall = |sample_id: &str| EngineRecall {
+            sample_assignments: vec![TrackSampleAssignment {
+                track_index: 0,
+                sample_id: sample_id.to_string(),
+            }],
+            cleared_tracks: Vec::new(),
+            parameter_updates: Vec::new(),
+        };
         let mut sequencer = Sequencer::new(48_000);
-        sequencer.set_swing(1.0);
-        assert_eq!(sequencer.swing(), MAX_SWING);
+        sequencer.schedule_recall(recall("stopped.wav"), RecallTiming::NextBar);
+        assert!(!sequencer.has_pending_recall());
+        assert_eq!(
+            sequencer.take_due_recall().map(|due| due.block_offset),
+            Some(0)
+        );
+
+        sequencer.start();
+        sequencer.process_block(10_000);
+        sequencer.schedule_recall(recall("next.wav"), RecallTiming::NextBar);
+        assert!(sequencer.has_pending_recall());
+        for _ in 1..9 {
+            sequencer.process_block(10_000);
+            assert_eq!(sequencer.take_due_recall(), None);
+        }
+        sequencer.process_block(10_000);
+        let due = sequencer.take_due_recall().expect("recall due at the bar");
+        assert_eq!(due.timeline_sample, 96_000);
+        assert_eq!(due.block_offset, 6_000);
+        assert_eq!(due.recall, recall("next.wav"));
+        assert!(!sequencer.has_pending_recall());
+
+        sequencer.schedule_recall(recall("now.wav"), RecallTiming::Immediate);
+        sequencer.process_block(10_000);
+        let due = sequencer.take_due_recall().expect("immediate recall");
+        assert_eq!((due.timeline_sample, due.block_offset), (100_000, 0));
     }
 
     #[test]
-    fn choke_group_is_carried_in_step_events() {
+    fn queued_live_actions_land_on_launch_quantize_boundaries() {
         let mut sequencer = Sequencer::new(48_000);
-        assert!(sequencer.set_track_choke_group(3, Some(1)));
-        assert!(sequencer.pattern_mut().set_step(
-            3,
-            0,
-            Step {
-                active: true,
-                velocity: 127,
-            },
-        ));
+        let hit = Step {
+            active: true,
+            velocity: 100,
+            ..Step::default()
+        };
+        assert!(sequencer.pattern_mut().set_step(0, 0, hit));
+        assert!(sequencer.pattern_mut().set_step(0, 4, hit));
+        sequencer.set_launch_quantize(LaunchQuantize::Beat);
         sequencer.start();
+        assert_eq!(sequencer.process_block(100).len(), 1);
 
-        let events = sequencer.process_block(64);
-        let event = events
+        assert!(sequencer.queue_action(LiveAction::SetTrackMute {
+            track_index: 0,
+            muted: true,
+        }));
+        assert!(!sequencer.queue_action(LiveAction::SetTrackMute {
+            track_index: TRACK_COUNT,
+            muted: true,
+        }));
+        assert_eq!(sequencer.pending_action_count(), 1);
+        assert!(sequencer.process_block(30_000).is_empty());
+        assert!(sequencer.track_muted(0));
+
+        let mut next = Pattern::default();
+        assert!(next.set_step(1, 0, hit));
+        sequencer.set_launch_quantize(LaunchQuantize::Bar);
+        sequencer.queue_action(LiveAction::SwitchPattern(Box::new(next)));
+        sequencer.queue_action(LiveAction::SetFill(true));
+        assert!(sequencer.process_block(48_000).is_empty());
+        assert_eq!(sequencer.pending_action_count(), 2);
+        let events = sequencer.process_block(48_000);
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].track_index, events[0].step_index), (1, 0));
+        assert!(sequencer.fill_active());
+
+        sequencer.set_launch_quantize(LaunchQuantize::None);
+        sequencer.queue_action(LiveAction::SetTrackMute {
+            track_index: 0,
+            muted: false,
+        });
+        assert!(!sequencer.track_muted(0));
+    }
+
+    #[test]
+    fn recalled_variations_switch_on_the_next_bar() {
+        let hit = PatternStep {
+            active: true,
+            velocity: 100,
+            ..PatternStep::default()
+        };
+        let mut project = Project {
+            kits: vec![Kit::default()],
+            patterns: vec![PresetPattern::default()],
+            ..Project::default()
+        };
+        project.patterns[0].set_step(0, 0, hit);
+        project.patterns[0].set_variation_step(2, 0, 0, Some(PatternStep::default()));
+        project.patterns[0].set_variation_step(2, 3, 0, Some(hit));
+
+        let mut recall = recall_state_from_project(&project, 48_000).expect("recall");
+        let sequencer = recall.sequencer_mut();
+        assert_eq!(
+            sequencer
+                .pattern()
+                .variation_step(2, 3, 0)
+                .map(|step| step.active),
+            Some(true)
+        );
+        sequencer.set_launch_quantize(LaunchQuantize::Bar);
+        sequencer.start();
+        let first = sequencer.process_block(100);
+        assert_eq!(
+            first
+                .iter()
+                .map(|event| event.track_index)
+                .collect::<Vec<_>>(),
+            vec![0]
+        );
+
+        assert!(sequencer.queue_action(LiveAction::SwitchVariation(2)));
+        assert!(!sequencer.queue_action(LiveAction::SwitchVariation(4)));
+        assert_eq!(sequencer.pattern().active_variation(), 0);
+        let next_bar = sequencer.process_block(96_000);
+        assert_eq!(sequencer.pattern().active_variation(), 2);
+        assert_eq!(
+            next_bar
+                .iter()
+                .map(|event| event.track_index)
+                .collect::<Vec<_>>(),
+            vec![3]
+        );
+        assert_eq!(
+            sequencer.pattern().step(0, 0).map(|step| step.active),
+            Some(true)
+        );
+        assert_eq!(
+            sequencer
+                .pattern()
+                .playing_step(0, 0)
+                .map(|step| step.active),
+            Some(false)
+        );
+    }
+
+    #[test]
+    fn step_edits_while_stopped_emit_one_shot_previews() {
+        let hit = Step {
+            active: true,
+            velocity: 90,
+            ..Step::default()
+        };
+        let mut sequencer = Sequencer::new(48_000);
+        assert!(sequencer.edit_preview());
+        assert!(sequencer.edit_step(2, 5, hit));
+        assert!(sequencer.edit_step(3, 5, Step::default()));
+        assert!(!sequencer.edit_step(TRACK_COUNT, 0, hit));
+        let previews = sequencer.process_block(128);
+        assert_eq!(
+            previews
+                .iter()
+                .map(|event| (event.track_index, event.step_index, event.velocity))
+                .collect::<Vec<_>>(),
+            vec![(2, 5, 90)]
+        );
+        assert_eq!(previews[0].source, FfEventSource::LivePad);
+        assert!(sequencer.process_block(128).is_empty());
+
+        sequencer.set_edit_preview(false);
+        assert!(sequencer.edit_step(4, 0, hit));
+        assert!(sequencer.process_block(128).is_empty());
+
+        sequencer.set_edit_preview(true);
+        sequencer.start();
+        assert!(sequencer.edit_step(5, 8, hit));
+        assert!(sequencer
+            .process_block(128)
             .iter()
-            .find(|value| value.track_index == 3)
-            .expect("track 3 event should exist");
-        assert_eq!(event.choke_group, Some(1));
+            .all(|event| event.track_index != 5));
+    }
+
+    #[test]
+    fn recalled_mute_spans_silence_tracks_for_their_bars() {
+        let hit = PatternStep {
+            active: true,
+            velocity: 100,
+            ..PatternStep::default()
+        };
+        let mut project = Project {
+            kits: vec![Kit::default()],
+            patterns: vec![PresetPattern::default()],
+            ..Project::default()
+        };
+        let pattern = &mut project.patterns[0];
+        assert!(pattern.set_bar_count(2));
+        for track_index in 0..2 {
+            pattern.set_step(track_index, 0, hit);
+            pattern.set_step(track_index, STEPS_PER_PATTERN, hit);
+        }
+        assert!(pattern.set_mute_span(0, 1, 1));
+
+        let mut recall = recall_state_from_project(&project, 48_000).expect("recall");
+        let sequencer = recall.sequencer_mut();
+        assert!(sequencer.pattern().track_muted_at(0, 1));
+        sequencer.start();
+        let events = sequencer.process_block(190_000);
+        assert_eq!(
+            events
+                .iter()
+                .map(|event| (event.track_index, event.step_index))
+                .collect::<Vec<_>>(),
+            vec![(0, 0), (1, 0), (1, 16)]
+        );
+        assert!(!sequencer.pattern_mut().set_mute_spans(&[MuteSpan {
+            track_index: 0,
+            first_bar: 0,
+            last_bar: 2,
+        }]));
+    }
+
+    #[test]
+    fn recalled_kit_humanize_reaches_the_sequencer_and_track_recall() {
+        let mut project = Project {
+            kits: vec![Kit::default()],
+            patterns: vec![PresetPattern::default()],
+            ..Project::default()
+        };
+        assert!(
+            project.kits[0].set_track_humanize(presets_rs::TrackHumanize {
+                track_index: 3,
+                timing_samples: 96,
+                velocity: 10,
+            })
+        );
+        let recall = recall_state_from_project(&project, 48_000).expect("recall");
+        let depth = HumanizeDepth {
+            timing_samples: 96,
+            velocity: 10,
+        };
+        assert_eq!(recall.sequencer().track_humanize(3), Some(depth));
+        assert_eq!(
+            recall.track_recall(3).and_then(|track| track.humanize),
+            Some(depth)
+        );
+        assert_eq!(recall.sequencer().track_humanize(2), None);
+    }
+
+    #[test]
+    fn recalled_track_labels_name_and_color_tracks() {
+        let mut project = Project {
+            kits: vec![Kit::default()],
+            patterns: vec![PresetPattern::default()],
+            ..Project::default()
+        };
+        assert!(project.kits[0].set_track_label(presets_rs::TrackLabel {
+            track_index: 1,
+            name: "Snare".to_string(),
+            color: Some(0xFF_4400),
+        }));
+        assert!(project.kits[0].set_track_label(presets_rs::TrackLabel {
+            track_index: 2,
+            name: String::new(),
+            color: Some(0x00_88FF),
+        }));
+        let recall = recall_state_from_project(&project, 48_000).expect("recall");
+        assert_eq!(recall.track_display_name(1).as_deref(), Some("Snare"));
+        assert_eq!(
+            recall.track_recall(1).and_then(|track| track.color),
+            Some(0xFF_4400)
+        );
+        assert_eq!(recall.track_display_name(2).as_deref(), Some("Track 3"));
+        assert_eq!(
+            recall.track_recall(2).and_then(|track| track.color),
+            Some(0x00_88FF)
+        );
+        assert_eq!(recall.track_display_name(TRACK_COUNT), None);
     }
 
     #[test]
@@ -802,39 +5187,138 @@ mod tests {
             active_kit: Some(0),
             patterns: vec![PresetPattern::default()],
             active_pattern: Some(0),
+            ..Project::default()
         };
         project.kits[0].add_assignment(TrackAssignment {
             track_index: 4,
             sample_id: "hihat-open".to_string(),
         });
-        project.kits[0].set_track_controls(
-            4,
+        project.kits[0].set_track_controls(
+            4,
+            TrackControls {
+                gain_db: -2.5,
+                pan: -0.5,
+                filter_cutoff: 0.35,
+                envelope_decay: 0.65,
+                pitch_semitones: 12.0,
+                choke_group: Some(2),
+            },
+        );
+        project.patterns[0].set_swing(0.25);
+        project.random_seed = 1_234;
+        project.patterns[0].set_step(
+            4,
+            0,
+            PatternStep {
+                active: true,
+                velocity: 118,
+                ..PatternStep::default()
+            },
+        );
+
+        let mut recall = recall_state_from_project(&project, 48_000).expect("recall should map");
+        let sequencer = recall.sequencer_mut();
+        assert_eq!(sequencer.random_seed(), 1_234);
+        let first_draw = sequencer.random_mut().next_u64();
+        sequencer.reset();
+        assert_eq!(sequencer.random_mut().next_u64(), first_draw);
+
+        let track = recall.track_recall(4).expect("track 4 should exist");
+        assert_eq!(track.sample_id.as_deref(), Some("hihat-open"));
+        assert_eq!(track.choke_group, Some(2));
+        assert!(track.gain_normalized > 90);
+        assert!(track.pan_normalized < 64);
+        assert!(track.pitch_normalized > 90);
+    }
+
+    #[test]
+    fn missing_samples_recall_as_placeholders_and_are_reported() {
+        let mut kit = Kit::default();
+        for (track_index, sample_id) in [(0, "kick.wav"), (1, "lost.wav"), (2, "lost.wav")] {
+            assert!(kit.add_assignment(TrackAssignment {
+                track_index,
+                sample_id: sample_id.to_string(),
+            }));
+        }
+        let project = Project {
+            kits: vec![kit],
+            patterns: vec![PresetPattern::default()],
+            ..Project::default()
+        };
+
+        let mut recall = recall_state_from_project(&project, 48_000).expect("recall should map");
+        assert!(recall.unresolved_samples().is_empty());
+        assert_eq!(
+            recall.resolve_samples(|sample_id| sample_id == "kick.wav"),
+            ["lost.wav".to_string()]
+        );
+        assert!(!recall.has_placeholder(0));
+        assert!(recall.has_placeholder(2));
+        assert_eq!(recall.to_engine_recall().sample_assignments.len(), 3);
+        assert!(recall.mark_sample_resolved("lost.wav"));
+        assert!(!recall.mark_sample_resolved("lost.wav"));
+        assert!(!recall.has_placeholder(1));
+    }
+
+    #[test]
+    fn recall_diff_sends_only_changed_samples_and_parameters() {
+        let mut project = Project {
+            kits: vec![Kit::default(); 2],
+            active_kit: Some(0),
+            patterns: vec![PresetPattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        for kit in &mut project.kits {
+            kit.add_assignment(TrackAssignment {
+                track_index: 0,
+                sample_id: "kick.wav".to_string(),
+            });
+        }
+        project.kits[1].add_assignment(TrackAssignment {
+            track_index: 1,
+            sample_id: "snare.wav".to_string(),
+        });
+        project.kits[1].set_track_controls(
+            0,
             TrackControls {
-                gain: 0.75,
-                pan: -0.5,
-                filter_cutoff: 0.35,
-                envelope_decay: 0.65,
-                pitch_semitones: 12.0,
-                choke_group: Some(2),
+                gain_db: -6.0,
+                ..TrackControls::default()
             },
         );
-        project.patterns[0].set_swing(0.25);
-        project.patterns[0].set_step(
-            4,
-            0,
-            PatternStep {
-                active: true,
-                velocity: 118,
-            },
+
+        let previous = recall_state_from_project(&project, 48_000).expect("recall");
+        assert_eq!(
+            previous.diff_against(&previous),
+            EngineRecall {
+                sample_assignments: Vec::new(),
+                cleared_tracks: Vec::new(),
+                parameter_updates: Vec::new(),
+            }
         );
 
-        let recall = recall_state_from_project(&project, 48_000).expect("recall should map");
-        let track = recall.track_recall(4).expect("track 4 should exist");
-        assert_eq!(track.sample_id.as_deref(), Some("hihat-open"));
-        assert_eq!(track.choke_group, Some(2));
-        assert!(track.gain_normalized > 90);
-        assert!(track.pan_normalized < 64);
-        assert!(track.pitch_normalized > 90);
+        project.active_kit = Some(1);
+        let next = recall_state_from_project(&project, 48_000).expect("recall");
+        let diff = next.diff_against_ramped(&previous, 64);
+        assert_eq!(
+            diff.sample_assignments,
+            vec![TrackSampleAssignment {
+                track_index: 1,
+                sample_id: "snare.wav".to_string(),
+            }]
+        );
+        assert_eq!(diff.parameter_updates.len(), 1);
+        assert_eq!(
+            diff.parameter_updates[0].parameter_id,
+            ff_track_parameter_id(0, FF_PARAM_SLOT_GAIN).expect("id")
+        );
+        assert_eq!(diff.parameter_updates[0].ramp_samples, 64);
+        assert!(diff.cleared_tracks.is_empty());
+        assert!(next.to_engine_recall().parameter_updates.len() > diff.parameter_updates.len());
+
+        let back = previous.diff_against(&next);
+        assert!(back.sample_assignments.is_empty());
+        assert_eq!(back.cleared_tracks, vec![1]);
     }
 
     #[test]
@@ -845,6 +5329,7 @@ mod tests {
             active_kit: Some(0),
             patterns: vec![PresetPattern::default()],
             active_pattern: Some(0),
+            ..Project::default()
         };
         project.kits[0].add_assignment(TrackAssignment {
             track_index: 2,
@@ -853,7 +5338,7 @@ mod tests {
         project.kits[0].set_track_controls(
             2,
             TrackControls {
-                gain: 0.5,
+                gain_db: -6.0,
                 pan: -0.25,
                 filter_cutoff: 0.7,
                 envelope_decay: 0.9,
@@ -879,7 +5364,7 @@ mod tests {
             .iter()
             .find(|update| update.parameter_id == gain_id)
             .expect("gain parameter update should exist");
-        assert!(gain_update.normalized_value > 0.45 && gain_update.normalized_value < 0.55);
+        assert_eq!(gain_update.normalized_value, 103.0 / 127.0);
 
         let pan_update = recall
             .parameter_updates
@@ -896,6 +5381,60 @@ mod tests {
         assert!((choke_update.normalized_value - 0.25).abs() < 0.0001);
     }
 
+    #[test]
+    fn kit_groups_map_to_track_membership_and_group_parameters() {
+        let mut project = Project {
+            kits: vec![Kit::default()],
+            patterns: vec![PresetPattern::default()],
+            ..Project::default()
+        };
+        assert!(project.kits[0].set_track_group(presets_rs::TrackGroup {
+            members: vec![4, 5],
+            gain_db: -6.0,
+            soloed: true,
+            ..presets_rs::TrackGroup::new(2, "perc")
+        }));
+
+        let state = recall_state_from_project(&project, 48_000).expect("recall");
+        assert_eq!(state.track_recall(5).expect("track").group, Some(2));
+        assert_eq!(state.track_recall(0).expect("track").group, None);
+        assert!(state.group_recall(2).expect("group").soloed);
+
+        let updates = state.to_engine_recall().parameter_updates;
+        let value_of = |parameter_id: Option<u32>| {
+            let parameter_id = parameter_id.expect("id");
+            updates
+                .iter()
+                .find(|update| update.parameter_id == parameter_id)
+                .map(|update| update.normalized_value)
+        };
+        assert_eq!(
+            value_of(ff_track_parameter_id(4, abi_rs::FF_PARAM_SLOT_TRACK_GROUP)),
+            Some(0.75)
+        );
+        assert_eq!(
+            value_of(abi_rs::ff_group_parameter_id(
+                2,
+                abi_rs::FF_PARAM_GROUP_SLOT_GAIN
+            )),
+            Some(103.0 / 127.0)
+        );
+        assert_eq!(
+            value_of(abi_rs::ff_group_parameter_id(
+                2,
+                abi_rs::FF_PARAM_GROUP_SLOT_SOLO
+            )),
+            Some(1.0)
+        );
+        assert_eq!(
+            value_of(abi_rs::ff_group_parameter_id(
+                0,
+                abi_rs::FF_PARAM_GROUP_SLOT_MUTE
+            )),
+            Some(0.0)
+        );
+    }
+
     #[test]
     fn phase2_recall_fixture_matches_generated_parameter_updates() {
         let project = canonical_fixture_project();
@@ -907,6 +5446,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn recall_from_kit_and_pattern_texts_matches_project_recall() {
+        let mut project = canonical_fixture_project();
+        project.kits[0].add_assignment(TrackAssignment {
+            track_index: 3,
+            sample_id: "snare-02".to_string(),
+        });
+        project.patterns[0].steps[3][4] = PatternStep {
+            active: true,
+            velocity: 96,
+            ..PatternStep::default()
+        };
+        let kit_text = presets_rs::save_kit_to_text(&project.kits[0]);
+        let pattern_text = presets_rs::save_pattern_to_text(&project.patterns[0]);
+
+        let mut from_texts =
+            recall_state_from_texts(&kit_text, &pattern_text, 48_000).expect("text recall");
+        let mut from_project = recall_state_from_project(&project, 48_000).expect("recall");
+        for track_index in 0..TRACK_COUNT {
+            assert_eq!(
+                from_texts.track_recall(track_index),
+                from_project.track_recall(track_index)
+            );
+        }
+        from_texts.sequencer_mut().start();
+        from_project.sequencer_mut().start();
+        assert_eq!(
+            from_texts.sequencer_mut().process_block(48_000),
+            from_project.sequencer_mut().process_block(48_000)
+        );
+
+        let error = recall_state_from_texts("FF_KIT_V1\ntrack|99|00", &pattern_text, 48_000)
+            .expect_err("bad kit");
+        assert!(error.starts_with("kit text: "));
+        assert!(recall_state_from_texts(&kit_text, "nonsense", 48_000)
+            .expect_err("bad pattern")
+            .starts_with("pattern text: "));
+        assert_eq!(from_texts.audio_hints(), AudioHints::default());
+        project.audio_hints = AudioHints {
+            sample_rate_hz: Some(44_100),
+            block_size: Some(128),
+        };
+        let recall = recall_state_from_project(&project, 48_000).expect("hinted recall");
+        assert_eq!(recall.audio_hints(), project.audio_hints);
+        assert_eq!(recall.sequencer().sample_rate_hz(), 48_000);
+        project.audio_hints.block_size = Some(0);
+        assert!(recall_state_from_project(&project, 48_000).is_err());
+
+        project.patterns[0].groove = Some("lazy".to_string());
+        let pattern_text = presets_rs::save_pattern_to_text(&project.patterns[0]);
+        assert!(recall_state_from_texts(&kit_text, &pattern_text, 48_000).is_err());
+    }
+
     #[test]
     fn saved_and_loaded_project_produce_identical_event_streams() {
         let mut project = Project {
@@ -915,6 +5507,7 @@ mod tests {
             active_kit: Some(0),
             patterns: vec![PresetPattern::default()],
             active_pattern: Some(0),
+            ..Project::default()
         };
 
         project.kits[0].add_assignment(TrackAssignment {
@@ -924,7 +5517,7 @@ mod tests {
         project.kits[0].set_track_controls(
             0,
             TrackControls {
-                gain: 1.0,
+                gain_db: 0.0,
                 pan: 0.0,
                 filter_cutoff: 0.5,
                 envelope_decay: 0.7,
@@ -939,6 +5532,7 @@ mod tests {
             PatternStep {
                 active: true,
                 velocity: 120,
+                ..PatternStep::default()
             },
         );
         project.patterns[0].set_step(
@@ -947,8 +5541,14 @@ mod tests {
             PatternStep {
                 active: true,
                 velocity: 100,
+                ..PatternStep::default()
             },
         );
+        project.random_seed = 0x5EED;
+        project.humanize = HumanizeDepth {
+            timing_samples: 240,
+            velocity: 12,
+        };
 
         let blocks = [480u32, 960u32, 2048u32, 4096u32, 16384u32];
         let original_events =
@@ -961,6 +5561,447 @@ mod tests {
         assert_eq!(original_events, loaded_events);
     }
 
+    #[test]
+    fn step_probability_survives_save_load_and_playback() {
+        let mut project = Project {
+            kits: vec![Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![PresetPattern::default()],
+            active_pattern: Some(0),
+            random_seed: 0x5EED,
+            ..Project::default()
+        };
+        let hit = PatternStep {
+            active: true,
+            velocity: 100,
+            ..PatternStep::default()
+        };
+        let pattern = &mut project.patterns[0];
+        pattern.set_step(0, 0, hit);
+        pattern.set_step(
+            1,
+            4,
+            PatternStep {
+                probability: 0,
+                ..hit
+            },
+        );
+        for step_index in 0..STEPS_PER_PATTERN {
+            pattern.set_step(
+                2,
+                step_index,
+                PatternStep {
+                    probability: 50,
+                    ..hit
+                },
+            );
+        }
+        assert!(pattern.set_variation_step(
+            1,
+            0,
+            8,
+            Some(PatternStep {
+                probability: 0,
+                ..hit
+            }),
+        ));
+
+        let loaded = load_project_from_text(&save_project_to_text(&project)).expect("load");
+        assert_eq!(loaded.patterns, project.patterns);
+        let recall = recall_state_from_project(&loaded, 48_000).expect("recall");
+        let pattern = recall.sequencer().pattern();
+        assert_eq!(pattern.step(1, 4).map(|step| step.probability), Some(0));
+        assert_eq!(pattern.step(2, 9).map(|step| step.probability), Some(50));
+        assert_eq!(
+            pattern.variation_step(1, 0, 8).map(|step| step.probability),
+            Some(0)
+        );
+
+        let blocks = [96_000; 4];
+        let events = render_recall_events(&loaded, 48_000, &blocks).expect("render loaded");
+        assert_eq!(
+            events,
+            render_recall_events(&project, 48_000, &blocks).expect("render original")
+        );
+        let hits = |track_index: u8| {
+            events
+                .iter()
+                .filter(|event| event.track_index == track_index)
+                .count()
+        };
+        assert_eq!(hits(0), 4);
+        assert_eq!(hits(1), 0);
+        assert!((1..4 * STEPS_PER_PATTERN).contains(&hits(2)));
+    }
+
+    #[test]
+    fn trig_conditions_survive_save_load_and_playback() {
+        let mut project = Project {
+            kits: vec![Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![PresetPattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        let hit = PatternStep {
+            active: true,
+            velocity: 100,
+            ..PatternStep::default()
+        };
+        let pattern = &mut project.patterns[0];
+        pattern.set_step(0, 0, hit);
+        for (track_index, condition) in [
+            (1, TrigCondition::Cycle { hit: 1, of: 2 }),
+            (2, TrigCondition::NotFirst),
+            (3, TrigCondition::Neighbor),
+            (4, TrigCondition::Fill),
+        ] {
+            pattern.set_step(track_index, 4, PatternStep { condition, ..hit });
+        }
+
+        let loaded = load_project_from_text(&save_project_to_text(&project)).expect("load");
+        assert_eq!(loaded.patterns, project.patterns);
+        let recall = recall_state_from_project(&loaded, 48_000).expect("recall");
+        assert_eq!(
+            recall
+                .sequencer()
+                .pattern()
+                .step(1, 4)
+                .map(|step| step.condition),
+            Some(TrigCondition::Cycle { hit: 1, of: 2 })
+        );
+
+        let events = render_recall_events(&loaded, 48_000, &[96_000; 4]).expect("render");
+        let hits = |track_index: u8| {
+            events
+                .iter()
+                .filter(|event| event.track_index == track_index)
+                .count()
+        };
+        assert_eq!(
+            (hits(0), hits(1), hits(2), hits(3), hits(4)),
+            (4, 2, 3, 3, 0)
+        );
+    }
+
+    #[test]
+    fn accented_steps_survive_save_load_and_playback() {
+        let mut project = Project {
+            kits: vec![Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![PresetPattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        let hit = PatternStep {
+            active: true,
+            velocity: 90,
+            ..PatternStep::default()
+        };
+        let pattern = &mut project.patterns[0];
+        pattern.accent = AccentCurve::flat(20);
+        pattern.set_step(
+            0,
+            0,
+            PatternStep {
+                accent: true,
+                ..hit
+            },
+        );
+        pattern.set_step(0, 4, hit);
+        assert!(pattern.set_variation_step(
+            1,
+            0,
+            4,
+            Some(PatternStep {
+                accent: true,
+                ..hit
+            })
+        ));
+
+        let loaded = load_project_from_text(&save_project_to_text(&project)).expect("load");
+        assert_eq!(loaded.patterns, project.patterns);
+        let mut recall = recall_state_from_project(&loaded, 48_000).expect("recall");
+        assert_eq!(
+            recall
+                .sequencer()
+                .pattern()
+                .variation_step(1, 0, 4)
+                .map(|step| step.accent),
+            Some(true)
+        );
+        let sequencer = recall.sequencer_mut();
+        sequencer.start();
+        let velocities: Vec<u8> = sequencer
+            .process_block(96_000)
+            .iter()
+            .map(|event| event.velocity)
+            .collect();
+        assert_eq!(velocities, vec![110, 90]);
+    }
+
+    #[test]
+    fn flams_survive_save_load_and_playback() {
+        let mut project = Project {
+            kits: vec![Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![PresetPattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        project.patterns[0].set_step(
+            0,
+            4,
+            PatternStep {
+                active: true,
+                velocity: 90,
+                flam: true,
+                flam_spacing_ms: 35,
+                ..PatternStep::default()
+            },
+        );
+
+        let loaded = load_project_from_text(&save_project_to_text(&project)).expect("load");
+        assert_eq!(loaded.patterns, project.patterns);
+        let hits: Vec<(u8, u64)> = render_recall_events(&loaded, 48_000, &[96_000])
+            .expect("render")
+            .iter()
+            .map(|event| (event.velocity, event.timeline_sample))
+            .collect();
+        assert_eq!(hits, vec![(45, 24_000), (90, 24_000 + 48 * 35)]);
+    }
+
+    #[test]
+    fn track_default_steps_survive_save_and_load() {
+        let mut project = Project {
+            kits: vec![Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![PresetPattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        assert!(project.patterns[0].set_track_default_step(
+            2,
+            PatternStep {
+                velocity: 72,
+                probability: 80,
+                gate: 50,
+                ..PatternStep::default()
+            },
+        ));
+
+        let loaded = load_project_from_text(&save_project_to_text(&project)).expect("load");
+        let recall = recall_state_from_project(&loaded, 48_000).expect("recall");
+        let hit = recall.sequencer().pattern().default_hit(2);
+        assert!(hit.active);
+        assert_eq!((hit.velocity, hit.probability, hit.gate), (72, 80, 50));
+        assert_eq!(
+            recall.sequencer().pattern().default_hit(3),
+            Step {
+                active: true,
+                ..Step::default()
+            }
+        );
+    }
+
+    #[test]
+    fn triplet_and_thirty_second_tracks_play_against_straight_kicks() {
+        let mut project = Project {
+            kits: vec![Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![PresetPattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        let hit = PatternStep {
+            active: true,
+            velocity: 100,
+            ..PatternStep::default()
+        };
+        let pattern = &mut project.patterns[0];
+        for step_index in 0..STEPS_PER_PATTERN {
+            pattern.set_step(
+                0,
+                step_index,
+                PatternStep {
+                    active: step_index % 4 == 0,
+                    ..hit
+                },
+            );
+            pattern.set_step(1, step_index, hit);
+            pattern.set_step(2, step_index, hit);
+            pattern.set_step(3, step_index, hit);
+        }
+        assert!(pattern.set_track_resolution(1, StepResolution::SixteenthTriplet));
+        assert!(pattern.set_track_resolution(2, StepResolution::EighthTriplet));
+        assert!(pattern.set_track_resolution(3, StepResolution::ThirtySecond));
+
+        let blocks = [512u32; 375];
+        let events = render_recall_events(&project, 48_000, &blocks).expect("render");
+        let hits = |track_index: u8| -> Vec<(u64, u8)> {
+            events
+                .iter()
+                .filter(|event| event.track_index == track_index)
+                .map(|event| (event.timeline_sample, event.step_index))
+                .collect()
+        };
+        assert_eq!(
+            hits(0),
+            (0..8u64)
+                .map(|beat| (beat * 24_000, (beat % 4 * 4) as u8))
+                .collect::<Vec<_>>()
+        );
+        // Each row loops its own 16 steps: a bar of 1/8T plays 12 of them and
+        // the next bar carries on from step 12.
+        let steps_per_bar = |track_index: u8| -> Vec<Vec<u8>> {
+            let mut bars = vec![Vec::new(); 2];
+            for (timeline_sample, step_index) in hits(track_index) {
+                bars[(timeline_sample / 96_000) as usize].push(step_index);
+            }
+            bars
+        };
+        let steps = |range: std::ops::Range<u8>| range.collect::<Vec<_>>();
+        assert_eq!(
+            steps_per_bar(1),
+            vec![
+                [steps(0..16), steps(0..8)].concat(),
+                [steps(8..16), steps(0..16)].concat(),
+            ]
+        );
+        assert_eq!(
+            steps_per_bar(2),
+            vec![steps(0..12), [steps(12..16), steps(0..8)].concat()]
+        );
+        assert_eq!(
+            steps_per_bar(3),
+            vec![
+                [steps(0..16), steps(0..16)].concat(),
+                [steps(0..16), steps(0..16)].concat(),
+            ]
+        );
+        assert!(hits(2)
+            .iter()
+            .enumerate()
+            .all(|(hit, (timeline_sample, _))| *timeline_sample == hit as u64 * 8_000));
+        assert!(hits(3)
+            .iter()
+            .enumerate()
+            .all(|(hit, (timeline_sample, _))| *timeline_sample == hit as u64 * 3_000));
+        assert_eq!(
+            verify_block_size_independence(&project, 48_000, &[&[512; 375], &[4_000; 48]])
+                .map(|events| events.len()),
+            Ok(events.len())
+        );
+    }
+
+    #[test]
+    fn swing_follows_each_tracks_own_grid_unless_sixteenth_swing_is_chosen() {
+        let mut project = Project {
+            kits: vec![Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![PresetPattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        let hit = PatternStep {
+            active: true,
+            velocity: 100,
+            ..PatternStep::default()
+        };
+        let pattern = &mut project.patterns[0];
+        pattern.set_swing(0.3);
+        for step_index in 0..STEPS_PER_PATTERN {
+            pattern.set_step(1, step_index, hit);
+            pattern.set_step(2, step_index, hit);
+            pattern.set_step(3, step_index, hit);
+        }
+        pattern.set_track_resolution(2, StepResolution::EighthTriplet);
+        pattern.set_track_resolution(3, StepResolution::ThirtySecond);
+
+        // One bar at 120 BPM. Each track's odd steps land 30% of its own step
+        // length late: 1800 samples for sixteenths, 2400 for triplet eighths
+        // and 900 for thirty-seconds.
+        let hits = |project: &Project, track_index: u8| -> Vec<u64> {
+            verify_block_size_independence(project, 48_000, &[&[480; 200], &[4_000; 24]])
+                .expect("render")
+                .iter()
+                .filter(|event| event.track_index == track_index)
+                .map(|event| event.timeline_sample)
+                .collect()
+        };
+        let swung = |count: u64, length: u64, late: &dyn Fn(u64) -> u64| -> Vec<u64> {
+            (0..count).map(|step| step * length + late(step)).collect()
+        };
+        assert_eq!(
+            hits(&project, 1),
+            swung(16, 6_000, &|step| step % 2 * 1_800)
+        );
+        assert_eq!(
+            hits(&project, 2),
+            swung(12, 8_000, &|step| step % 2 * 2_400)
+        );
+        assert_eq!(hits(&project, 3), swung(32, 3_000, &|step| step % 2 * 900));
+
+        project.patterns[0].swing_grid = SwingGrid::Sixteenth;
+        let project = load_project_from_text(&save_project_to_text(&project)).expect("load");
+        assert_eq!(
+            hits(&project, 1),
+            swung(16, 6_000, &|step| step % 2 * 1_800)
+        );
+        assert_eq!(
+            hits(&project, 2),
+            swung(12, 8_000, &|step| (step * 4 / 3) % 2 * 1_800)
+        );
+        assert_eq!(
+            hits(&project, 3),
+            swung(32, 3_000, &|step| (step / 2) % 2 * 1_800)
+        );
+    }
+
+    #[test]
+    fn event_streams_do_not_depend_on_block_sizes() {
+        let mut project = Project {
+            kits: vec![Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![PresetPattern::default()],
+            active_pattern: Some(0),
+            random_seed: 99,
+            ..Project::default()
+        };
+        project.kits[0].set_track_humanize(TrackHumanize {
+            track_index: 1,
+            timing_samples: 700,
+            velocity: 12,
+        });
+        project.patterns[0].set_swing(0.3);
+        for (track_index, step_index) in [(0, 0), (0, 6), (1, 2), (1, 3), (2, 15)] {
+            project.patterns[0].set_step(
+                track_index,
+                step_index,
+                PatternStep {
+                    active: true,
+                    velocity: 110,
+                    ..PatternStep::default()
+                },
+            );
+        }
+
+        let even = [512u32; 375];
+        let mut uneven = vec![1u32, 127, 4_096, 33, 10_000];
+        uneven.push(192_000 - uneven.iter().sum::<u32>());
+        let events = verify_block_size_independence(
+            &project,
+            44_100,
+            &[&even, &[192_000], &uneven, &[64; 3_000]],
+        )
+        .expect("identical streams");
+        assert_eq!(events.len(), 12);
+        assert!(events.iter().all(|event| event.block_offset == 0));
+
+        assert!(verify_block_size_independence(&project, 44_100, &[&even, &[512]]).is_err());
+        assert!(verify_block_size_independence(&project, 44_100, &[]).is_err());
+    }
+
     #[test]
     fn saved_and_loaded_project_produce_identical_engine_recall() {
         let mut project = Project {
@@ -969,6 +6010,7 @@ mod tests {
             active_kit: Some(0),
             patterns: vec![PresetPattern::default()],
             active_pattern: Some(0),
+            ..Project::default()
         };
 
         project.kits[0].add_assignment(TrackAssignment {
@@ -978,7 +6020,7 @@ mod tests {
         project.kits[0].set_track_controls(
             1,
             TrackControls {
-                gain: 0.8,
+                gain_db: -2.0,
                 pan: 0.0,
                 filter_cutoff: 0.45,
                 envelope_decay: 0.5,
@@ -0,0 +1,314 @@
+use presets_rs::TrackMidiOutput;
+
+use crate::{
+    samples_per_step, Sequencer, StepTriggerEvent, DEFAULT_BPM, MAX_STEP_GATE, TRACK_COUNT,
+};
+
+pub const MIDI_NOTE_OFF: u8 = 0x80;
+pub const MIDI_NOTE_ON: u8 = 0x90;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MidiOutputEvent {
+    pub track_index: u8,
+    pub block_offset: u32,
+    pub bytes: [u8; 3],
+}
+
+impl MidiOutputEvent {
+    pub fn is_note_on(&self) -> bool {
+        self.bytes[0] & 0xF0 == MIDI_NOTE_ON
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct HeldNote {
+    track_index: u8,
+    channel: u8,
+    note: u8,
+    release_frame: u64,
+}
+
+impl HeldNote {
+    fn note_off(&self, block_offset: u32) -> MidiOutputEvent {
+        MidiOutputEvent {
+            track_index: self.track_index,
+            block_offset,
+            bytes: [MIDI_NOTE_OFF | self.channel, self.note, 0],
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MidiOutputRouter {
+    sample_rate_hz: u32,
+    bpm: f32,
+    outputs: [Option<TrackMidiOutput>; TRACK_COUNT],
+    held: Vec<HeldNote>,
+    block_start: u64,
+}
+
+impl MidiOutputRouter {
+    pub fn new(sample_rate_hz: u32) -> Self {
+        Self {
+            sample_rate_hz: sample_rate_hz.max(1),
+            bpm: DEFAULT_BPM,
+            outputs: [None; TRACK_COUNT],
+            held: Vec::new(),
+            block_start: 0,
+        }
+    }
+
+    pub fn set_tempo_bpm(&mut self, bpm: f32) {
+        self.bpm = bpm;
+    }
+
+    pub fn set_track_output(
+        &mut self,
+        track_index: usize,
+        output: Option<TrackMidiOutput>,
+    ) -> bool {
+        if track_index >= TRACK_COUNT
+            || output.is_some_and(|output| {
+                !output.is_valid() || usize::from(output.track_index) != track_index
+            })
+        {
+            return false;
+        }
+
+        self.outputs[track_index] = output;
+        true
+    }
+
+    pub fn track_output(&self, track_index: usize) -> Option<TrackMidiOutput> {
+        self.outputs.get(track_index).copied().flatten()
+    }
+
+    pub fn held_note_count(&self) -> usize {
+        self.held.len()
+    }
+
+    pub fn process_sequencer_block(
+        &mut self,
+        sequencer: &mut Sequencer,
+        frames: u32,
+    ) -> (Vec<StepTriggerEvent>, Vec<MidiOutputEvent>) {
+        self.set_tempo_bpm(sequencer.transport().bpm());
+        let events = sequencer.process_block(frames);
+        self.process_block(&events, frames)
+    }
+
+    pub fn process_block(
+        &mut self,
+        events: &[StepTriggerEvent],
+        frames: u32,
+    ) -> (Vec<StepTriggerEvent>, Vec<MidiOutputEvent>) {
+        let step_frames = samples_per_step(self.sample_rate_hz, self.bpm);
+        let mut internal = Vec::with_capacity(events.len());
+        let mut messages = Vec::new();
+
+        for event in events {
+            let Some(output) = self.track_output(usize::from(event.track_index)) else {
+                internal.push(*event);
+                continue;
+            };
+
+            let offset = event.block_offset.min(frames);
+            let frame = self.block_start + u64::from(offset);
+            self.release_until(frame, &mut messages);
+            if let Some(position) = self
+                .held
+                .iter()
+                .position(|held| held.channel == output.channel && held.note == output.note)
+            {
+                messages.push(self.held.remove(position).note_off(offset));
+            }
+
+            messages.push(MidiOutputEvent {
+                track_index: event.track_index,
+                block_offset: offset,
+                bytes: [
+                    MIDI_NOTE_ON | output.channel,
+                    output.note,
+                    event.velocity.clamp(1, 127),
+                ],
+            });
+            // A step's gate shortens the track's note length, never extends it.
+            let gate = f64::from(event.gate) / f64::from(MAX_STEP_GATE);
+            let length_frames =
+                (f64::from(output.length_steps) * step_frames * gate).round() as u64;
+            self.held.push(HeldNote {
+                track_index: event.track_index,
+                channel: output.channel,
+                note: output.note,
+                release_frame: frame + length_frames.max(1),
+            });
+        }
+
+        if frames > 0 {
+            self.release_until(self.block_start + u64::from(frames) - 1, &mut messages);
+        }
+        self.block_start += u64::from(frames);
+        messages.sort_by_key(|message| (message.block_offset, message.is_note_on()));
+        (internal, messages)
+    }
+
+    pub fn all_notes_off(&mut self) -> Vec<MidiOutputEvent> {
+        self.held.drain(..).map(|held| held.note_off(0)).collect()
+    }
+
+    fn release_until(&mut self, frame: u64, messages: &mut Vec<MidiOutputEvent>) {
+        let block_start = self.block_start;
+        self.held.retain(|held| {
+            if held.release_frame > frame {
+                return true;
+            }
+            let offset = held.release_frame.saturating_sub(block_start);
+            messages.push(held.note_off(offset as u32));
+            false
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use presets_rs::{Kit, Pattern, Project, TrackAssignment, TrackMidiOutput};
+
+    use super::{MidiOutputRouter, MIDI_NOTE_OFF, MIDI_NOTE_ON};
+    use crate::{recall_state_from_project, Sequencer, Step};
+
+    #[test]
+    fn recall_routes_kit_midi_outputs_away_from_samples() {
+        let output = TrackMidiOutput {
+            track_index: 6,
+            channel: 9,
+            note: 38,
+            length_steps: 1.0,
+        };
+        let mut kit = Kit::default();
+        kit.add_assignment(TrackAssignment {
+            track_index: 6,
+            sample_id: "snare.wav".to_string(),
+        });
+        assert!(kit.set_track_midi_output(output));
+        let project = Project {
+            kits: vec![kit],
+            patterns: vec![Pattern::default()],
+            ..Project::default()
+        };
+
+        let recall = recall_state_from_project(&project, 48_000).expect("recall");
+        assert_eq!(recall.track_midi_output(6), Some(output));
+        assert!(recall.to_engine_recall().sample_assignments.is_empty());
+        assert_eq!(recall.midi_output_router().track_output(6), Some(output));
+    }
+
+    #[test]
+    fn midi_tracks_emit_notes_instead_of_internal_triggers() {
+        let mut sequencer = Sequencer::new(48_000);
+        for track_index in [0, 3] {
+            sequencer.pattern_mut().set_step(
+                track_index,
+                0,
+                Step {
+                    active: true,
+                    velocity: 90,
+                    ..Step::default()
+                },
+            );
+        }
+        sequencer.start();
+
+        let mut router = MidiOutputRouter::new(48_000);
+        assert!(router.set_track_output(
+            3,
+            Some(TrackMidiOutput {
+                track_index: 3,
+                channel: 2,
+                note: 60,
+                length_steps: 0.5,
+            }),
+        ));
+        assert!(!router.set_track_output(1, router.track_output(3)));
+
+        let (internal, messages) = router.process_sequencer_block(&mut sequencer, 2_000);
+        assert_eq!(internal.len(), 1);
+        assert_eq!(internal[0].track_index, 0);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].bytes, [MIDI_NOTE_ON | 2, 60, 90]);
+        assert_eq!(router.held_note_count(), 1);
+
+        let (_, messages) = router.process_sequencer_block(&mut sequencer, 2_000);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].bytes, [MIDI_NOTE_OFF | 2, 60, 0]);
+        assert_eq!(messages[0].block_offset, 1_000);
+        assert_eq!(router.held_note_count(), 0);
+    }
+
+    #[test]
+    fn step_gates_shorten_the_track_note_length() {
+        let mut sequencer = Sequencer::new(48_000);
+        sequencer.pattern_mut().set_step(
+            0,
+            0,
+            Step {
+                active: true,
+                gate: 25,
+                ..Step::default()
+            },
+        );
+        sequencer.start();
+
+        let mut router = MidiOutputRouter::new(48_000);
+        router.set_track_output(
+            0,
+            Some(TrackMidiOutput {
+                track_index: 0,
+                channel: 0,
+                note: 36,
+                length_steps: 2.0,
+            }),
+        );
+        let (_, messages) = router.process_sequencer_block(&mut sequencer, 4_000);
+        let kinds: Vec<(u32, bool)> = messages
+            .iter()
+            .map(|message| (message.block_offset, message.is_note_on()))
+            .collect();
+        assert_eq!(kinds, vec![(0, true), (3_000, false)]);
+    }
+
+    #[test]
+    fn retriggered_notes_release_before_sounding_again() {
+        let mut sequencer = Sequencer::new(48_000);
+        for step_index in [0, 1] {
+            sequencer.pattern_mut().set_step(
+                0,
+                step_index,
+                Step {
+                    active: true,
+                    velocity: 100,
+                    ..Step::default()
+                },
+            );
+        }
+        sequencer.start();
+
+        let mut router = MidiOutputRouter::new(48_000);
+        router.set_track_output(
+            0,
+            Some(TrackMidiOutput {
+                track_index: 0,
+                channel: 0,
+                note: 36,
+                length_steps: 4.0,
+            }),
+        );
+        let (_, messages) = router.process_sequencer_block(&mut sequencer, 8_000);
+        let kinds: Vec<(u32, bool)> = messages
+            .iter()
+            .map(|message| (message.block_offset, message.is_note_on()))
+            .collect();
+        assert_eq!(kinds, vec![(0, true), (6_000, false), (6_000, true)]);
+        assert_eq!(router.all_notes_off().len(), 1);
+        assert_eq!(router.held_note_count(), 0);
+    }
+}
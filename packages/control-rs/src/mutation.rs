@@ -0,0 +1,288 @@
+use std::ops::RangeInclusive;
+
+use crate::random::RandomSource;
+use crate::{ParameterLock, Pattern, Step, TRACK_COUNT};
+
+pub const DEFAULT_MUTATION_VELOCITY: u8 = 100;
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DensityEdit {
+    pub added: Vec<usize>,
+    pub removed: Vec<usize>,
+}
+
+// Every operation draws from its own stream per track, so the result for a
+// track depends only on the seed and that track's steps.
+fn track_random(seed: u64, track_index: usize) -> RandomSource {
+    RandomSource::new(seed).fork(track_index as u64)
+}
+
+fn check_track(track_index: usize) -> Result<(), String> {
+    if track_index >= TRACK_COUNT {
+        return Err(format!("mutation track out of range: {track_index}"));
+    }
+    Ok(())
+}
+
+// Partial Fisher-Yates: the first `count` entries become a seeded sample.
+fn pick(random: &mut RandomSource, candidates: &mut [usize], count: usize) {
+    let count = count.min(candidates.len());
+    for index in 0..count {
+        let swap = random.range_inclusive(index as i64, candidates.len() as i64 - 1) as usize;
+        candidates.swap(index, swap);
+    }
+}
+
+fn active_steps(pattern: &Pattern, track_index: usize, active: bool) -> Vec<usize> {
+    (0..pattern.step_count())
+        .filter(|step_index| {
+            pattern
+                .step(track_index, *step_index)
+                .is_some_and(|step| step.active == active)
+        })
+        .collect()
+}
+
+pub fn randomize_velocities(
+    pattern: &mut Pattern,
+    track_index: usize,
+    range: RangeInclusive<u8>,
+    seed: u64,
+) -> Result<usize, String> {
+    check_track(track_index)?;
+    if range.is_empty() || *range.start() == 0 || *range.end() > 127 {
+        return Err(format!(
+            "invalid velocity range: {}..={}",
+            range.start(),
+            range.end()
+        ));
+    }
+
+    let mut random = track_random(seed, track_index);
+    let hits = active_steps(pattern, track_index, true);
+    for step_index in &hits {
+        let step = pattern.step(track_index, *step_index).unwrap_or_default();
+        let velocity =
+            random.range_inclusive(i64::from(*range.start()), i64::from(*range.end())) as u8;
+        pattern.set_step(track_index, *step_index, Step { velocity, ..step });
+    }
+    Ok(hits.len())
+}
+
+// Adds hits on empty steps or removes existing ones until the track holds
+// `density_percent` of its steps. Removed hits keep their velocity.
+pub fn fit_density(
+    pattern: &mut Pattern,
+    track_index: usize,
+    density_percent: u8,
+    velocity: u8,
+    seed: u64,
+) -> Result<DensityEdit, String> {
+    check_track(track_index)?;
+    if density_percent > 100 {
+        return Err(format!("density out of range: {density_percent}%"));
+    }
+    if !(1..=127).contains(&velocity) {
+        return Err(format!("velocity out of range: {velocity}"));
+    }
+
+    let mut random = track_random(seed, track_index);
+    let target = (pattern.step_count() * usize::from(density_percent) + 50) / 100;
+    let mut hits = active_steps(pattern, track_index, true);
+    let mut edit = DensityEdit::default();
+    if hits.len() > target {
+        let count = hits.len() - target;
+        pick(&mut random, &mut hits, count);
+        edit.removed = hits[..count].to_vec();
+        edit.removed.sort_unstable();
+        for step_index in &edit.removed {
+            let step = pattern.step(track_index, *step_index).unwrap_or_default();
+            pattern.set_step(
+                track_index,
+                *step_index,
+                Step {
+                    active: false,
+                    ..step
+                },
+            );
+        }
+    } else {
+        let count = target - hits.len();
+        let mut rests = active_steps(pattern, track_index, false);
+        pick(&mut random, &mut rests, count);
+        edit.added = rests[..count].to_vec();
+        edit.added.sort_unstable();
+        for step_index in &edit.added {
+            pattern.set_step(
+                track_index,
+                *step_index,
+                Step {
+                    active: true,
+                    velocity,
+                    ..Step::default()
+                },
+            );
+        }
+    }
+    Ok(edit)
+}
+
+// Parameter locks travel with their steps.
+pub fn shuffle_track(pattern: &mut Pattern, track_index: usize, seed: u64) -> Result<(), String> {
+    check_track(track_index)?;
+
+    let mut random = track_random(seed, track_index);
+    let step_count = pattern.step_count();
+    let mut order: Vec<usize> = (0..step_count).collect();
+    pick(&mut random, &mut order, step_count);
+
+    let steps: Vec<Step> = (0..step_count)
+        .map(|step_index| pattern.step(track_index, step_index).unwrap_or_default())
+        .collect();
+    let mut destination = vec![0; step_count];
+    for (step_index, source) in order.iter().enumerate() {
+        pattern.set_step(track_index, step_index, steps[*source]);
+        destination[*source] = step_index;
+    }
+    let locks: Vec<ParameterLock> = pattern
+        .parameter_locks()
+        .iter()
+        .map(|lock| {
+            if usize::from(lock.track_index) != track_index {
+                return *lock;
+            }
+            ParameterLock {
+                step_index: destination[usize::from(lock.step_index)] as u8,
+                ..*lock
+            }
+        })
+        .collect();
+    pattern.set_parameter_locks(&locks);
+    Ok(())
+}
+
+// Toggles roughly `percent` of all step cells: rests become hits at
+// DEFAULT_MUTATION_VELOCITY and hits become rests. Returns the toggled
+// (track, step) cells in order.
+pub fn mutate(
+    pattern: &mut Pattern,
+    percent: u8,
+    seed: u64,
+) -> Result<Vec<(usize, usize)>, String> {
+    if percent > 100 {
+        return Err(format!("mutation amount out of range: {percent}%"));
+    }
+
+    let mut toggled = Vec::new();
+    for track_index in 0..TRACK_COUNT {
+        let mut random = track_random(seed, track_index);
+        for step_index in 0..pattern.step_count() {
+            if !random.chance(f64::from(percent) / 100.0) {
+                continue;
+            }
+            let step = pattern.step(track_index, step_index).unwrap_or_default();
+            let mutated = if step.active {
+                Step {
+                    active: false,
+                    ..step
+                }
+            } else {
+                Step {
+                    active: true,
+                    velocity: DEFAULT_MUTATION_VELOCITY,
+                    ..Step::default()
+                }
+            };
+            pattern.set_step(track_index, step_index, mutated);
+            toggled.push((track_index, step_index));
+        }
+    }
+    Ok(toggled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fit_density, mutate, randomize_velocities, shuffle_track};
+    use crate::{LockTarget, ParameterLock, Pattern, Step, STEPS_PER_PATTERN, TRACK_COUNT};
+
+    fn hit(velocity: u8) -> Step {
+        Step {
+            active: true,
+            velocity,
+            ..Step::default()
+        }
+    }
+
+    fn four_on_the_floor() -> Pattern {
+        let mut pattern = Pattern::default();
+        for step_index in (0..STEPS_PER_PATTERN).step_by(4) {
+            pattern.set_step(0, step_index, hit(100));
+        }
+        pattern
+    }
+
+    #[test]
+    fn velocity_and_density_edits_repeat_per_seed() {
+        let mut first = four_on_the_floor();
+        let mut second = four_on_the_floor();
+        assert_eq!(randomize_velocities(&mut first, 0, 40..=60, 9), Ok(4));
+        assert_eq!(randomize_velocities(&mut second, 0, 40..=60, 9), Ok(4));
+        assert_eq!(first, second);
+        assert!((0..STEPS_PER_PATTERN)
+            .filter_map(|step_index| first.step(0, step_index))
+            .filter(|step| step.active)
+            .all(|step| (40..=60).contains(&step.velocity)));
+        assert!(randomize_velocities(&mut first, 0, 0..=60, 9).is_err());
+        assert!(randomize_velocities(&mut first, TRACK_COUNT, 1..=60, 9).is_err());
+
+        let added = fit_density(&mut first, 0, 50, 90, 3).expect("fill");
+        assert_eq!((added.added.len(), added.removed.len()), (4, 0));
+        assert_eq!(fit_density(&mut second, 0, 50, 90, 3).expect("fill"), added);
+        assert_eq!(first.step(0, added.added[0]), Some(hit(90)));
+
+        let removed = fit_density(&mut first, 0, 25, 90, 3).expect("thin");
+        assert_eq!((removed.added.len(), removed.removed.len()), (0, 4));
+        assert!(!first.step(0, removed.removed[0]).expect("step").active);
+        assert!(fit_density(&mut first, 0, 101, 90, 3).is_err());
+    }
+
+    #[test]
+    fn shuffles_keep_hits_and_locks_together_and_mutation_is_seeded() {
+        let mut pattern = four_on_the_floor();
+        assert!(pattern.set_parameter_lock(ParameterLock {
+            track_index: 0,
+            step_index: 4,
+            target: LockTarget::Pitch,
+            value: 0.25,
+        }));
+        let mut other = pattern.clone();
+        assert_eq!(shuffle_track(&mut pattern, 0, 11), Ok(()));
+        assert_eq!(shuffle_track(&mut other, 0, 11), Ok(()));
+        assert_eq!(pattern, other);
+        let locked = usize::from(pattern.parameter_locks()[0].step_index);
+        assert_eq!(pattern.step(0, locked), Some(hit(100)));
+        assert_eq!(
+            (0..STEPS_PER_PATTERN)
+                .filter(|step_index| pattern.step(0, *step_index).expect("step").active)
+                .count(),
+            4
+        );
+
+        let before = pattern.clone();
+        let toggled = mutate(&mut pattern, 25, 5).expect("mutate");
+        assert_eq!(mutate(&mut other, 25, 5), Ok(toggled.clone()));
+        assert!(!toggled.is_empty());
+        for (track_index, step_index) in &toggled {
+            assert_ne!(
+                pattern
+                    .step(*track_index, *step_index)
+                    .map(|step| step.active),
+                before
+                    .step(*track_index, *step_index)
+                    .map(|step| step.active)
+            );
+        }
+        assert_eq!(mutate(&mut pattern, 0, 5), Ok(Vec::new()));
+        assert!(mutate(&mut pattern, 101, 5).is_err());
+    }
+}
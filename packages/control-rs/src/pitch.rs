@@ -0,0 +1,177 @@
+use abi_rs::FfParameterUpdate;
+use presets_rs::{MusicalKey, MusicalScale};
+
+pub const PITCH_RANGE_SEMITONES: f32 = 24.0;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PitchQuantizer {
+    key: MusicalKey,
+}
+
+impl PitchQuantizer {
+    pub fn new(key: MusicalKey) -> Self {
+        Self { key }
+    }
+
+    pub fn key(&self) -> MusicalKey {
+        self.key
+    }
+
+    pub fn set_key(&mut self, key: MusicalKey) -> bool {
+        if !key.is_valid() {
+            return false;
+        }
+        self.key = key;
+        true
+    }
+
+    pub fn is_chromatic(&self) -> bool {
+        self.key.scale == MusicalScale::Chromatic
+    }
+
+    pub fn quantize_semitones(&self, semitones: f32) -> f32 {
+        if self.is_chromatic() || !semitones.is_finite() {
+            return semitones;
+        }
+        self.nearest_in_scale(semitones, i32::MIN, i32::MAX) as f32
+    }
+
+    pub fn quantize_note(&self, note: u8) -> u8 {
+        if self.is_chromatic() {
+            return note;
+        }
+        self.nearest_in_scale(f32::from(note), 0, 127) as u8
+    }
+
+    pub fn keyboard_pitch(&self, note: u8, base_note: u8) -> f32 {
+        let offset = i32::from(self.quantize_note(note)) - i32::from(base_note);
+        (offset as f32).clamp(-PITCH_RANGE_SEMITONES, PITCH_RANGE_SEMITONES)
+    }
+
+    pub fn keyboard_pitch_update(
+        &self,
+        track_index: u8,
+        note: u8,
+        base_note: u8,
+    ) -> Option<FfParameterUpdate> {
+        Some(FfParameterUpdate {
+            parameter_id: abi_rs::ff_track_parameter_id(track_index, abi_rs::FF_PARAM_SLOT_PITCH)?,
+            normalized_value: normalized_from_semitones(self.keyboard_pitch(note, base_note)),
+            ramp_samples: 0,
+            reserved: 0,
+        })
+    }
+
+    pub fn quantize_parameter_update(&self, update: &FfParameterUpdate) -> FfParameterUpdate {
+        let is_pitch = abi_rs::ff_parameter_descriptor(update.parameter_id)
+            .is_some_and(|descriptor| descriptor.parameter_slot == abi_rs::FF_PARAM_SLOT_PITCH);
+        if !is_pitch || self.is_chromatic() {
+            return *update;
+        }
+
+        let semitones = update.normalized_value.clamp(0.0, 1.0) * PITCH_RANGE_SEMITONES * 2.0
+            - PITCH_RANGE_SEMITONES;
+        let quantized = self.nearest_in_scale(
+            semitones,
+            -PITCH_RANGE_SEMITONES as i32,
+            PITCH_RANGE_SEMITONES as i32,
+        );
+        FfParameterUpdate {
+            normalized_value: normalized_from_semitones(quantized as f32),
+            ..*update
+        }
+    }
+
+    fn nearest_in_scale(&self, value: f32, low: i32, high: i32) -> i32 {
+        let center = value.round() as i32;
+        (center.saturating_sub(6)..=center.saturating_add(6))
+            .filter(|semitone| (low..=high).contains(semitone))
+            .filter(|semitone| self.key.contains_semitone(*semitone))
+            .fold(None, |best: Option<i32>, semitone| match best {
+                Some(best) if (best as f32 - value).abs() <= (semitone as f32 - value).abs() => {
+                    Some(best)
+                }
+                _ => Some(semitone),
+            })
+            .unwrap_or(center.clamp(low, high))
+    }
+}
+
+fn normalized_from_semitones(semitones: f32) -> f32 {
+    ((semitones + PITCH_RANGE_SEMITONES) / (PITCH_RANGE_SEMITONES * 2.0)).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use abi_rs::{
+        ff_track_parameter_id, FfParameterUpdate, FF_PARAM_SLOT_GAIN, FF_PARAM_SLOT_PITCH,
+    };
+    use presets_rs::{Kit, MusicalKey, MusicalScale, Pattern, Project, TrackControls};
+
+    use super::PitchQuantizer;
+    use crate::recall_state_from_project;
+
+    fn a_minor() -> MusicalKey {
+        MusicalKey {
+            root: 9,
+            scale: MusicalScale::Minor,
+        }
+    }
+
+    #[test]
+    fn pitches_and_notes_snap_to_the_nearest_scale_degree() {
+        let quantizer = PitchQuantizer::new(a_minor());
+        assert_eq!(quantizer.quantize_semitones(1.0), 0.0);
+        assert_eq!(quantizer.quantize_semitones(1.6), 2.0);
+        assert_eq!(quantizer.quantize_semitones(-4.0), -5.0);
+        assert_eq!(quantizer.quantize_note(61), 60);
+        assert_eq!(quantizer.quantize_note(68), 67);
+        assert_eq!(quantizer.quantize_note(127), 127);
+        assert_eq!(quantizer.keyboard_pitch(66, 60), 5.0);
+        assert_eq!(PitchQuantizer::default().quantize_semitones(1.3), 1.3);
+
+        let pitch_id = ff_track_parameter_id(2, FF_PARAM_SLOT_PITCH).expect("id");
+        let snapped = quantizer.quantize_parameter_update(&FfParameterUpdate {
+            parameter_id: pitch_id,
+            normalized_value: 24.6 / 48.0,
+            ramp_samples: 0,
+            reserved: 0,
+        });
+        assert_eq!(snapped.normalized_value, 0.5);
+        let gain = FfParameterUpdate {
+            parameter_id: ff_track_parameter_id(2, FF_PARAM_SLOT_GAIN).expect("id"),
+            normalized_value: 25.0 / 48.0,
+            ramp_samples: 0,
+            reserved: 0,
+        };
+        assert_eq!(quantizer.quantize_parameter_update(&gain), gain);
+        assert_eq!(
+            quantizer
+                .keyboard_pitch_update(2, 61, 60)
+                .map(|update| (update.parameter_id, update.normalized_value)),
+            Some((pitch_id, 0.5))
+        );
+    }
+
+    #[test]
+    fn recall_snaps_kit_pitch_to_the_project_key() {
+        let mut project = Project {
+            kits: vec![Kit::default()],
+            patterns: vec![Pattern::default()],
+            key: a_minor(),
+            ..Project::default()
+        };
+        project.kits[0].set_track_controls(
+            1,
+            TrackControls {
+                pitch_semitones: 6.0,
+                ..TrackControls::default()
+            },
+        );
+
+        let recall = recall_state_from_project(&project, 48_000).expect("recall");
+        assert_eq!(recall.pitch_quantizer().key(), a_minor());
+        let pitch = recall.track_recall(1).expect("track").pitch_normalized;
+        assert_eq!(pitch, (29.0f32 / 48.0 * 127.0).round() as u8);
+    }
+}
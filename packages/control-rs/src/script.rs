@@ -0,0 +1,345 @@
+use std::cell::RefCell;
+use std::ops::Range;
+use std::rc::Rc;
+
+use rhai::{Dynamic, Engine, EvalAltResult, Scope, AST};
+
+use crate::random::RandomSource;
+use crate::{Pattern, Sequencer, Step, StepTriggerEvent, TRACK_COUNT};
+
+pub const SCRIPT_BAR_FUNCTION: &str = "on_bar";
+pub const SCRIPT_MAX_OPERATIONS: u64 = 200_000;
+
+#[derive(Debug)]
+struct ScriptState {
+    pattern: Pattern,
+    random: RandomSource,
+}
+
+fn checked_index(
+    track: i64,
+    step: i64,
+    step_count: usize,
+) -> Result<(usize, usize), Box<EvalAltResult>> {
+    match (usize::try_from(track), usize::try_from(step)) {
+        (Ok(track), Ok(step)) if track < TRACK_COUNT && step < step_count => Ok((track, step)),
+        _ => Err(format!("step out of range: track={track}, step={step}").into()),
+    }
+}
+
+fn checked_range(
+    track: i64,
+    first: i64,
+    end: i64,
+) -> Result<(usize, Range<usize>), Box<EvalAltResult>> {
+    match (
+        usize::try_from(track),
+        usize::try_from(first),
+        usize::try_from(end),
+    ) {
+        (Ok(track), Ok(first), Ok(end)) => Ok((track, first..end)),
+        _ => Err(format!("step range out of range: track={track}, steps={first}..{end}").into()),
+    }
+}
+
+pub struct PatternScript {
+    engine: Engine,
+    ast: AST,
+    seed: u64,
+    state: Rc<RefCell<ScriptState>>,
+    last_error: Option<String>,
+}
+
+impl std::fmt::Debug for PatternScript {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter
+            .debug_struct("PatternScript")
+            .field("seed", &self.seed)
+            .field("last_error", &self.last_error)
+            .finish()
+    }
+}
+
+impl PatternScript {
+    pub fn compile(source: &str, seed: u64) -> Result<Self, String> {
+        let state = Rc::new(RefCell::new(ScriptState {
+            pattern: Pattern::default(),
+            random: RandomSource::new(seed),
+        }));
+
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(SCRIPT_MAX_OPERATIONS)
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(4_096)
+            .set_max_array_size(1_024)
+            .set_max_map_size(256)
+            .on_print(|_| {})
+            .on_debug(|_, _, _| {});
+        engine.disable_symbol("eval");
+
+        let shared = Rc::clone(&state);
+        engine.register_fn(
+            "step_active",
+            move |track: i64, step: i64| -> Result<bool, Box<EvalAltResult>> {
+                let (track, step) =
+                    checked_index(track, step, shared.borrow().pattern.step_count())?;
+                Ok(shared.borrow().pattern.stored_step(track, step).active)
+            },
+        );
+        let shared = Rc::clone(&state);
+        engine.register_fn(
+            "step_velocity",
+            move |track: i64, step: i64| -> Result<i64, Box<EvalAltResult>> {
+                let (track, step) =
+                    checked_index(track, step, shared.borrow().pattern.step_count())?;
+                Ok(i64::from(
+                    shared.borrow().pattern.stored_step(track, step).velocity,
+                ))
+            },
+        );
+        let shared = Rc::clone(&state);
+        engine.register_fn(
+            "set_step",
+            move |track: i64,
+                  step: i64,
+                  active: bool,
+                  velocity: i64|
+                  -> Result<(), Box<EvalAltResult>> {
+                let (track, step) =
+                    checked_index(track, step, shared.borrow().pattern.step_count())?;
+                shared.borrow_mut().pattern.store_step(
+                    track,
+                    step,
+                    Step {
+                        active,
+                        velocity: velocity.clamp(0, 127) as u8,
+                        ..Step::default()
+                    },
+                );
+                Ok(())
+            },
+        );
+        let shared = Rc::clone(&state);
+        engine.register_fn(
+            "set_velocity_range",
+            move |track: i64,
+                  first: i64,
+                  end: i64,
+                  velocity: i64|
+                  -> Result<(), Box<EvalAltResult>> {
+                let (track_index, steps) = checked_range(track, first, end)?;
+                if !shared.borrow_mut().pattern.set_velocity_range(
+                    track_index,
+                    steps,
+                    velocity.clamp(0, 127) as u8,
+                ) {
+                    return Err(format!("step range out of range: track={track}").into());
+                }
+                Ok(())
+            },
+        );
+        let shared = Rc::clone(&state);
+        engine.register_fn(
+            "clear_steps",
+            move |track: i64,
+                  first: i64,
+                  end: i64,
+                  stride: i64|
+                  -> Result<(), Box<EvalAltResult>> {
+                let (track_index, steps) = checked_range(track, first, end)?;
+                let stride = usize::try_from(stride).unwrap_or(0);
+                if !shared
+                    .borrow_mut()
+                    .pattern
+                    .clear_step_range(track_index, steps, stride)
+                {
+                    return Err(format!("step range out of range: track={track}").into());
+                }
+                Ok(())
+            },
+        );
+        let shared = Rc::clone(&state);
+        engine.register_fn(
+            "toggle_step",
+            move |track: i64, step: i64| -> Result<(), Box<EvalAltResult>> {
+                let (track, step) =
+                    checked_index(track, step, shared.borrow().pattern.step_count())?;
+                let mut state = shared.borrow_mut();
+                let current = state.pattern.stored_step(track, step);
+                let toggled = if current.active {
+                    Step {
+                        active: false,
+                        ..current
+                    }
+                } else {
+                    state.pattern.default_hit(track)
+                };
+                state.pattern.store_step(track, step, toggled);
+                Ok(())
+            },
+        );
+        let shared = Rc::clone(&state);
+        engine.register_fn("random", move || -> f64 {
+            shared.borrow_mut().random.next_unit()
+        });
+        let shared = Rc::clone(&state);
+        engine.register_fn("random_int", move |low: i64, high: i64| -> i64 {
+            if high <= low {
+                return low;
+            }
+            let span = high.abs_diff(low);
+            low.wrapping_add((shared.borrow_mut().random.next_u64() % span) as i64)
+        });
+        let shared = Rc::clone(&state);
+        engine.register_fn("chance", move |probability: f64| -> bool {
+            shared.borrow_mut().random.chance(probability)
+        });
+
+        let ast = engine
+            .compile(source)
+            .map_err(|error| format!("script compile error: {error}"))?;
+        if !ast
+            .iter_functions()
+            .any(|function| function.name == SCRIPT_BAR_FUNCTION && function.params.len() == 1)
+        {
+            return Err(format!("script must define fn {SCRIPT_BAR_FUNCTION}(bar)"));
+        }
+
+        Ok(Self {
+            engine,
+            ast,
+            seed,
+            state,
+            last_error: None,
+        })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    pub fn run_bar(&mut self, bar: u64, pattern: &mut Pattern) -> Result<(), String> {
+        {
+            let mut state = self.state.borrow_mut();
+            state.pattern = pattern.clone();
+            state.random = RandomSource::new(self.seed).fork(bar);
+        }
+
+        let mut scope = Scope::new();
+        scope.push_constant("TRACKS", TRACK_COUNT as i64);
+        scope.push_constant("STEPS", pattern.step_count() as i64);
+        let result = self.engine.call_fn::<Dynamic>(
+            &mut scope,
+            &self.ast,
+            SCRIPT_BAR_FUNCTION,
+            (i64::try_from(bar).unwrap_or(i64::MAX),),
+        );
+
+        match result {
+            Ok(_) => {
+                *pattern = self.state.borrow().pattern.clone();
+                self.last_error = None;
+                Ok(())
+            }
+            Err(error) => {
+                let message = format!("script error at bar {bar}: {error}");
+                self.last_error = Some(message.clone());
+                Err(message)
+            }
+        }
+    }
+}
+
+pub fn process_block_scripted(
+    sequencer: &mut Sequencer,
+    script: &mut PatternScript,
+    frames: u32,
+) -> Vec<StepTriggerEvent> {
+    sequencer.process_block_with_bar_hook(frames, |bar, pattern| {
+        let _ = script.run_bar(bar, pattern);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{process_block_scripted, PatternScript};
+    use crate::{Pattern, Sequencer};
+
+    const FILL_SCRIPT: &str = r#"
+        fn on_bar(bar) {
+            for step in 0..STEPS {
+                set_step(1, step, chance(0.5), random_int(60, 127));
+            }
+            if bar % 2 == 1 {
+                toggle_step(0, 0);
+            }
+        }
+    "#;
+
+    #[test]
+    fn scripts_are_deterministic_per_seed_and_bar() {
+        let mut first = PatternScript::compile(FILL_SCRIPT, 7).expect("compile");
+        let mut second = PatternScript::compile(FILL_SCRIPT, 7).expect("compile");
+        let mut other_seed = PatternScript::compile(FILL_SCRIPT, 8).expect("compile");
+
+        let mut a = Pattern::default();
+        let mut b = Pattern::default();
+        let mut c = Pattern::default();
+        first.run_bar(3, &mut a).expect("run");
+        second.run_bar(3, &mut b).expect("run");
+        other_seed.run_bar(3, &mut c).expect("run");
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert!(a.step(0, 0).expect("step").active);
+
+        assert!(PatternScript::compile("let x = 1;", 0).is_err());
+    }
+
+    #[test]
+    fn failing_or_runaway_scripts_leave_pattern_untouched() {
+        let mut out_of_range = PatternScript::compile(
+            "fn on_bar(bar) { set_step(0, 0, true, 100); set_step(9, 0, true, 100); }",
+            1,
+        )
+        .expect("compile");
+        let mut pattern = Pattern::default();
+        assert!(out_of_range.run_bar(0, &mut pattern).is_err());
+        assert_eq!(pattern, Pattern::default());
+        assert!(out_of_range.last_error().is_some());
+
+        let mut bad_range = PatternScript::compile(
+            "fn on_bar(bar) { set_velocity_range(0, 0, 8, 90); clear_steps(0, 0, 99, 2); }",
+            1,
+        )
+        .expect("compile");
+        assert!(bad_range.run_bar(0, &mut pattern).is_err());
+        assert_eq!(pattern, Pattern::default());
+
+        let mut runaway =
+            PatternScript::compile("fn on_bar(bar) { loop { } }", 1).expect("compile");
+        assert!(runaway.run_bar(0, &mut pattern).is_err());
+    }
+
+    #[test]
+    fn sequencer_runs_script_at_each_bar_start() {
+        let mut script =
+            PatternScript::compile("fn on_bar(bar) { set_step(2, 0, true, 10 + bar); }", 0)
+                .expect("compile");
+        let mut sequencer = Sequencer::new(48_000);
+        sequencer.start();
+
+        let mut velocities = Vec::new();
+        for _ in 0..200 {
+            for event in process_block_scripted(&mut sequencer, &mut script, 1_024) {
+                velocities.push(event.velocity);
+            }
+        }
+        assert_eq!(&velocities[..3], &[10, 11, 12]);
+    }
+}
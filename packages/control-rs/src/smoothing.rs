@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+
+use abi_rs::FfParameterUpdate;
+
+pub const SMOOTHED_SLOT_COUNT: usize = 6;
+pub const DEFAULT_SLOT_SMOOTHING_MS: [f32; SMOOTHED_SLOT_COUNT] = [10.0, 10.0, 20.0, 5.0, 5.0, 0.0];
+pub const MAX_SMOOTHING_MS: f32 = 2_000.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SmoothingConfig {
+    slot_time_ms: [f32; SMOOTHED_SLOT_COUNT],
+}
+
+impl Default for SmoothingConfig {
+    fn default() -> Self {
+        Self {
+            slot_time_ms: DEFAULT_SLOT_SMOOTHING_MS,
+        }
+    }
+}
+
+impl SmoothingConfig {
+    pub fn immediate() -> Self {
+        Self {
+            slot_time_ms: [0.0; SMOOTHED_SLOT_COUNT],
+        }
+    }
+
+    pub fn slot_time_ms(&self, parameter_slot: u32) -> Option<f32> {
+        let index = (parameter_slot as usize).checked_sub(1)?;
+        self.slot_time_ms.get(index).copied()
+    }
+
+    pub fn set_slot_time_ms(&mut self, parameter_slot: u32, time_ms: f32) -> bool {
+        if parameter_slot == abi_rs::FF_PARAM_SLOT_CHOKE_GROUP
+            || !time_ms.is_finite()
+            || !(0.0..=MAX_SMOOTHING_MS).contains(&time_ms)
+        {
+            return false;
+        }
+        let Some(index) = (parameter_slot as usize).checked_sub(1) else {
+            return false;
+        };
+        match self.slot_time_ms.get_mut(index) {
+            Some(slot_time_ms) => {
+                *slot_time_ms = time_ms;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ParameterSmoother {
+    sample_rate_hz: u32,
+    block_frames: u32,
+    config: SmoothingConfig,
+    pending: BTreeMap<u32, FfParameterUpdate>,
+}
+
+impl ParameterSmoother {
+    pub fn new(sample_rate_hz: u32, block_frames: u32, config: SmoothingConfig) -> Self {
+        Self {
+            sample_rate_hz: sample_rate_hz.max(1),
+            block_frames: block_frames.max(1),
+            config,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &SmoothingConfig {
+        &self.config
+    }
+
+    pub fn config_mut(&mut self) -> &mut SmoothingConfig {
+        &mut self.config
+    }
+
+    pub fn set_block_frames(&mut self, block_frames: u32) {
+        self.block_frames = block_frames.max(1);
+    }
+
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn ramp_samples(&self, parameter_slot: u32) -> u32 {
+        let time_ms = self.config.slot_time_ms(parameter_slot).unwrap_or(0.0);
+        if time_ms <= 0.0 {
+            return 0;
+        }
+        let samples = (f64::from(time_ms) * f64::from(self.sample_rate_hz) / 1_000.0).ceil() as u32;
+        samples.div_ceil(self.block_frames).max(1) * self.block_frames
+    }
+
+    pub fn push(&mut self, update: FfParameterUpdate) -> bool {
+        let Some(descriptor) = abi_rs::ff_parameter_descriptor(update.parameter_id) else {
+            return false;
+        };
+        let ramp_samples = update
+            .ramp_samples
+            .max(self.ramp_samples(descriptor.parameter_slot));
+        self.pending.insert(
+            update.parameter_id,
+            FfParameterUpdate {
+                normalized_value: update.normalized_value.clamp(0.0, 1.0),
+                ramp_samples,
+                ..update
+            },
+        );
+        true
+    }
+
+    pub fn push_all(&mut self, updates: &[FfParameterUpdate]) -> bool {
+        let mut all_accepted = true;
+        for update in updates {
+            all_accepted &= self.push(*update);
+        }
+        all_accepted
+    }
+
+    pub fn drain(&mut self) -> Vec<FfParameterUpdate> {
+        std::mem::take(&mut self.pending).into_values().collect()
+    }
+
+    pub fn drain_into(&mut self, output: &mut [FfParameterUpdate]) -> usize {
+        let mut written = 0;
+        while written < output.len() {
+            let Some((_, update)) = self.pending.pop_first() else {
+                break;
+            };
+            output[written] = update;
+            written += 1;
+        }
+        written
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use abi_rs::{
+        ff_track_parameter_id, FfParameterUpdate, FF_PARAM_SLOT_CHOKE_GROUP, FF_PARAM_SLOT_GAIN,
+        FF_PARAM_SLOT_PITCH,
+    };
+
+    use super::{ParameterSmoother, SmoothingConfig};
+
+    fn update(track_index: u8, slot: u32, value: f32) -> FfParameterUpdate {
+        FfParameterUpdate {
+            parameter_id: ff_track_parameter_id(track_index, slot).expect("id"),
+            normalized_value: value,
+            ramp_samples: 0,
+            reserved: 0,
+        }
+    }
+
+    #[test]
+    fn smoother_coalesces_updates_into_block_aligned_ramps() {
+        let mut smoother = ParameterSmoother::new(48_000, 256, SmoothingConfig::default());
+        assert_eq!(smoother.ramp_samples(FF_PARAM_SLOT_GAIN), 512);
+        assert_eq!(smoother.ramp_samples(FF_PARAM_SLOT_CHOKE_GROUP), 0);
+
+        assert!(smoother.push(update(1, FF_PARAM_SLOT_GAIN, 0.2)));
+        assert!(smoother.push(update(1, FF_PARAM_SLOT_GAIN, 0.7)));
+        assert!(smoother.push(update(0, FF_PARAM_SLOT_CHOKE_GROUP, 0.5)));
+        assert!(!smoother.push(FfParameterUpdate::default()));
+        assert_eq!(smoother.pending_len(), 2);
+
+        let drained = smoother.drain();
+        assert_eq!(drained.len(), 2);
+        assert_eq!(drained[0].ramp_samples, 0);
+        assert_eq!(drained[1].normalized_value, 0.7);
+        assert_eq!(drained[1].ramp_samples, 512);
+        assert!(smoother.drain().is_empty());
+    }
+
+    #[test]
+    fn slot_times_are_configurable_and_validated() {
+        let mut config = SmoothingConfig::immediate();
+        assert!(config.set_slot_time_ms(FF_PARAM_SLOT_PITCH, 50.0));
+        assert!(!config.set_slot_time_ms(FF_PARAM_SLOT_CHOKE_GROUP, 5.0));
+        assert!(!config.set_slot_time_ms(FF_PARAM_SLOT_GAIN, f32::NAN));
+        assert!(!config.set_slot_time_ms(0, 5.0));
+
+        let mut smoother = ParameterSmoother::new(44_100, 128, config);
+        assert_eq!(smoother.ramp_samples(FF_PARAM_SLOT_GAIN), 0);
+        assert_eq!(smoother.ramp_samples(FF_PARAM_SLOT_PITCH), 2_304);
+
+        smoother.push_all(&[
+            update(0, FF_PARAM_SLOT_PITCH, 1.0),
+            update(2, FF_PARAM_SLOT_GAIN, 0.5),
+        ]);
+        let mut output = [FfParameterUpdate::default(); 1];
+        assert_eq!(smoother.drain_into(&mut output), 1);
+        assert_eq!(output[0].ramp_samples, 2_304);
+        assert_eq!(smoother.pending_len(), 1);
+    }
+}
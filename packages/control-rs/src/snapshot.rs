@@ -0,0 +1,121 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, TryLockError};
+
+use crate::Pattern;
+
+#[derive(Debug)]
+struct SnapshotSlot {
+    latest: Mutex<Arc<Pattern>>,
+    version: AtomicU64,
+}
+
+// Shared between an editor and the audio thread. Edits build a fresh pattern
+// version off to the side and publish it with a pointer swap; the sequencer
+// picks the newest version up at its next step boundary and never blocks on
+// an editor that is mid-publish.
+#[derive(Clone, Debug)]
+pub struct PatternSnapshots {
+    slot: Arc<SnapshotSlot>,
+}
+
+impl PatternSnapshots {
+    pub fn new(pattern: Pattern) -> Self {
+        Self {
+            slot: Arc::new(SnapshotSlot {
+                latest: Mutex::new(Arc::new(pattern)),
+                version: AtomicU64::new(1),
+            }),
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        self.slot.version.load(Ordering::Acquire)
+    }
+
+    pub fn current(&self) -> Arc<Pattern> {
+        Arc::clone(&self.lock())
+    }
+
+    pub fn publish(&self, pattern: Pattern) {
+        let mut latest = self.lock();
+        *latest = Arc::new(pattern);
+        self.slot.version.fetch_add(1, Ordering::AcqRel);
+    }
+
+    // Applies `edit` to a copy of the latest version. Nothing is published
+    // when the edit reports failure, so a rejected edit never reaches the
+    // audio thread half-applied.
+    pub fn edit<F>(&self, edit: F) -> bool
+    where
+        F: FnOnce(&mut Pattern) -> bool,
+    {
+        let mut latest = self.lock();
+        let mut next = Pattern::clone(&latest);
+        if !edit(&mut next) {
+            return false;
+        }
+        *latest = Arc::new(next);
+        self.slot.version.fetch_add(1, Ordering::AcqRel);
+        true
+    }
+
+    pub(crate) fn newer_than(&self, seen_version: u64) -> Option<(u64, Arc<Pattern>)> {
+        if self.version() == seen_version {
+            return None;
+        }
+        let latest = match self.slot.latest.try_lock() {
+            Ok(latest) => latest,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => return None,
+        };
+        Some((self.version(), Arc::clone(&latest)))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Arc<Pattern>> {
+        self.slot
+            .latest
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::PatternSnapshots;
+    use crate::{Pattern, Step};
+
+    #[test]
+    fn edits_publish_new_versions_without_touching_earlier_snapshots() {
+        let snapshots = PatternSnapshots::new(Pattern::default());
+        let before = snapshots.current();
+        let hit = Step {
+            active: true,
+            ..Step::default()
+        };
+
+        assert!(snapshots.edit(|pattern| pattern.set_step(0, 4, hit)));
+        assert!(!snapshots
+            .edit(|pattern| pattern.set_step(0, 4, Step::default()) && pattern.set_bar_count(0)));
+        assert_eq!(snapshots.version(), 2);
+        assert_eq!(before.step(0, 4), Some(Step::default()));
+        assert_eq!(snapshots.current().step(0, 4), Some(hit));
+
+        let (version, latest) = snapshots.newer_than(1).expect("newer version");
+        assert_eq!(version, 2);
+        assert_eq!(latest.step(0, 4), Some(hit));
+        assert!(snapshots.newer_than(2).is_none());
+
+        let editor = snapshots.clone();
+        thread::spawn(move || {
+            for step_index in 0..8 {
+                assert!(editor.edit(|pattern| pattern.set_step(1, step_index, hit)));
+            }
+        })
+        .join()
+        .expect("editor thread");
+        assert_eq!(snapshots.version(), 10);
+        assert_eq!(snapshots.current().step(1, 7), Some(hit));
+    }
+}
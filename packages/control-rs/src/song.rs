@@ -0,0 +1,119 @@
+use crate::{Pattern, SongEntry};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Song {
+    patterns: Vec<Pattern>,
+    chain: Vec<SongEntry>,
+    looping: bool,
+}
+
+impl Song {
+    pub fn new(patterns: Vec<Pattern>) -> Self {
+        Self {
+            patterns,
+            ..Self::default()
+        }
+    }
+
+    pub fn patterns(&self) -> &[Pattern] {
+        &self.patterns
+    }
+
+    pub fn chain(&self) -> &[SongEntry] {
+        &self.chain
+    }
+
+    pub fn push_entry(&mut self, entry: SongEntry) -> bool {
+        if entry.repeats == 0 || entry.pattern_index >= self.patterns.len() {
+            return false;
+        }
+
+        self.chain.push(entry);
+        true
+    }
+
+    pub fn set_chain(&mut self, chain: &[SongEntry]) -> bool {
+        if chain
+            .iter()
+            .any(|entry| entry.repeats == 0 || entry.pattern_index >= self.patterns.len())
+        {
+            return false;
+        }
+
+        self.chain = chain.to_vec();
+        true
+    }
+
+    pub fn looping(&self) -> bool {
+        self.looping
+    }
+
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    pub fn entry_pattern(&self, entry_index: usize) -> Option<&Pattern> {
+        self.chain
+            .get(entry_index)
+            .and_then(|entry| self.patterns.get(entry.pattern_index))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SongPosition {
+    pub entry_index: usize,
+    pub repeat: u8,
+}
+
+impl SongPosition {
+    // Returns None once the last repeat of the last entry has played and the
+    // song does not loop.
+    pub fn next(self, song: &Song) -> Option<SongPosition> {
+        let entry = song.chain.get(self.entry_index)?;
+        if self.repeat + 1 < entry.repeats {
+            return Some(SongPosition {
+                repeat: self.repeat + 1,
+                ..self
+            });
+        }
+        if self.entry_index + 1 < song.chain.len() {
+            return Some(SongPosition {
+                entry_index: self.entry_index + 1,
+                repeat: 0,
+            });
+        }
+        song.looping.then_some(SongPosition::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Song, SongPosition};
+    use crate::{Pattern, SongEntry};
+
+    #[test]
+    fn song_positions_walk_repeats_then_entries_and_optionally_loop() {
+        let mut song = Song::new(vec![Pattern::default(); 2]);
+        let entry = |pattern_index: usize, repeats: u8| SongEntry {
+            pattern_index,
+            repeats,
+        };
+        assert!(song.push_entry(entry(1, 2)));
+        assert!(song.push_entry(entry(0, 1)));
+        assert!(!song.push_entry(entry(2, 1)));
+        assert!(!song.push_entry(entry(0, 0)));
+        assert!(!song.set_chain(&[entry(0, 1), entry(5, 1)]));
+        assert_eq!(song.chain().len(), 2);
+
+        let position = |entry_index: usize, repeat: u8| SongPosition {
+            entry_index,
+            repeat,
+        };
+        assert_eq!(position(0, 0).next(&song), Some(position(0, 1)));
+        assert_eq!(position(0, 1).next(&song), Some(position(1, 0)));
+        assert_eq!(position(1, 0).next(&song), None);
+        song.set_looping(true);
+        assert_eq!(position(1, 0).next(&song), Some(position(0, 0)));
+        assert_eq!(song.entry_pattern(2), None);
+    }
+}
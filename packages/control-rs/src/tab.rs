@@ -0,0 +1,166 @@
+use crate::{Pattern, Step, MAX_PATTERN_STEPS, STEPS_PER_PATTERN, TRACK_COUNT};
+
+pub const TAB_ACCENT_VELOCITY: u8 = 127;
+pub const TAB_HIT_VELOCITY: u8 = 100;
+pub const TAB_SOFT_VELOCITY: u8 = 64;
+pub const TAB_GHOST_VELOCITY: u8 = 32;
+
+pub fn tab_symbol_step(symbol: char) -> Option<Step> {
+    let velocity = match symbol {
+        '-' | '.' => return Some(Step::default()),
+        'X' => TAB_ACCENT_VELOCITY,
+        'x' => TAB_HIT_VELOCITY,
+        'o' => TAB_SOFT_VELOCITY,
+        'g' => TAB_GHOST_VELOCITY,
+        _ => return None,
+    };
+    Some(Step {
+        active: true,
+        velocity,
+        ..Step::default()
+    })
+}
+
+pub fn step_tab_symbol(step: Step) -> char {
+    match step.velocity {
+        _ if !step.active => '-',
+        TAB_ACCENT_VELOCITY.. => 'X',
+        TAB_HIT_VELOCITY.. => 'x',
+        TAB_SOFT_VELOCITY.. => 'o',
+        _ => 'g',
+    }
+}
+
+pub fn parse_tab(tab: &str) -> Result<Vec<Step>, String> {
+    let body = tab.split_once(':').map_or(tab, |(_, body)| body);
+    let steps = body
+        .chars()
+        .filter(|symbol| *symbol != '|' && !symbol.is_whitespace())
+        .map(|symbol| {
+            tab_symbol_step(symbol).ok_or_else(|| format!("invalid tab symbol: {symbol}"))
+        })
+        .collect::<Result<Vec<Step>, String>>()?;
+    if steps.is_empty() {
+        return Err(format!("empty tab: {tab}"));
+    }
+    if steps.len() > MAX_PATTERN_STEPS {
+        return Err(format!("tab longer than a pattern: {} steps", steps.len()));
+    }
+    Ok(steps)
+}
+
+fn fit_bars(pattern: &mut Pattern, step_count: usize) {
+    let bar_count = step_count.div_ceil(STEPS_PER_PATTERN);
+    if bar_count > pattern.bar_count() {
+        pattern.set_bar_count(bar_count);
+    }
+}
+
+fn apply_tab(pattern: &mut Pattern, track_index: usize, steps: &[Step]) -> usize {
+    for step_index in 0..pattern.step_count() {
+        pattern.set_step(track_index, step_index, steps[step_index % steps.len()]);
+    }
+    (0..pattern.step_count())
+        .filter(|step_index| steps[step_index % steps.len()].active)
+        .count()
+}
+
+pub fn import_tab_track(
+    pattern: &mut Pattern,
+    track_index: usize,
+    tab: &str,
+) -> Result<usize, String> {
+    if track_index >= TRACK_COUNT {
+        return Err(format!("tab track out of range: {track_index}"));
+    }
+    let steps = parse_tab(tab)?;
+    fit_bars(pattern, steps.len());
+    Ok(apply_tab(pattern, track_index, &steps))
+}
+
+pub fn import_tab(pattern: &mut Pattern, text: &str) -> Result<usize, String> {
+    let tracks = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(parse_tab)
+        .collect::<Result<Vec<Vec<Step>>, String>>()?;
+    if tracks.len() > TRACK_COUNT {
+        return Err(format!("tab has more than {TRACK_COUNT} tracks"));
+    }
+    fit_bars(pattern, tracks.iter().map(Vec::len).max().unwrap_or(0));
+    Ok(tracks
+        .iter()
+        .enumerate()
+        .map(|(track_index, steps)| apply_tab(pattern, track_index, steps))
+        .sum())
+}
+
+pub fn export_tab(pattern: &Pattern, track_index: usize) -> Option<String> {
+    if track_index >= TRACK_COUNT {
+        return None;
+    }
+    let bars: Vec<String> = (0..pattern.bar_count())
+        .map(|bar| {
+            (0..STEPS_PER_PATTERN)
+                .filter_map(|step| pattern.step(track_index, bar * STEPS_PER_PATTERN + step))
+                .map(step_tab_symbol)
+                .collect()
+        })
+        .collect();
+    Some(bars.join("|"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export_tab, import_tab, import_tab_track, parse_tab, TAB_GHOST_VELOCITY};
+    use crate::{Pattern, Step, MAX_PATTERN_STEPS};
+
+    fn hit(velocity: u8) -> Step {
+        Step {
+            active: true,
+            velocity,
+            ..Step::default()
+        }
+    }
+
+    #[test]
+    fn tab_lines_parse_velocity_symbols_and_ignore_labels_and_bars() {
+        let steps = parse_tab("HH: X-x-|o-g.").expect("tab");
+        assert_eq!(
+            steps,
+            vec![
+                hit(127),
+                Step::default(),
+                hit(100),
+                Step::default(),
+                hit(64),
+                Step::default(),
+                hit(TAB_GHOST_VELOCITY),
+                Step::default(),
+            ]
+        );
+        assert!(parse_tab("x--?").is_err());
+        assert!(parse_tab("BD: | |").is_err());
+        assert!(parse_tab(&"x".repeat(MAX_PATTERN_STEPS + 1)).is_err());
+    }
+
+    #[test]
+    fn tabs_tile_short_lines_grow_bars_and_export_back() {
+        let mut pattern = Pattern::default();
+        assert_eq!(import_tab_track(&mut pattern, 0, "x---"), Ok(4));
+        assert_eq!(pattern.step(0, 12), Some(hit(100)));
+        assert!(import_tab_track(&mut pattern, 8, "x---").is_err());
+
+        let text = "BD: X---x---x---x---\n\nSD: ----X-------X---|----X-------X-g-\n";
+        assert_eq!(import_tab(&mut pattern, text), Ok(13));
+        assert_eq!(pattern.bar_count(), 2);
+        assert_eq!(pattern.step(0, 16), Some(hit(127)));
+        assert_eq!(pattern.step(1, 30), Some(hit(TAB_GHOST_VELOCITY)));
+        assert_eq!(
+            export_tab(&pattern, 1).as_deref(),
+            Some("----X-------X---|----X-------X-g-")
+        );
+        assert_eq!(export_tab(&pattern, 8), None);
+        assert!(import_tab(&mut pattern, &"x\n".repeat(9)).is_err());
+    }
+}
@@ -0,0 +1,311 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::{Sequencer, StepTriggerEvent, TRACK_COUNT};
+
+pub const BLOCK_DURATION_BUCKETS_MICROS: [u64; 8] =
+    [50, 100, 250, 500, 1_000, 2_000, 5_000, 10_000];
+pub const BLOCK_DURATION_BUCKET_COUNT: usize = BLOCK_DURATION_BUCKETS_MICROS.len() + 1;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MetricsSnapshot {
+    pub blocks_processed: u64,
+    pub frames_processed: u64,
+    pub block_duration_histogram: [u64; BLOCK_DURATION_BUCKET_COUNT],
+    pub max_block_duration_micros: u64,
+    pub total_block_duration_micros: u64,
+    pub events_emitted: u64,
+    pub max_events_per_block: u64,
+    pub queue_high_water_mark: u64,
+    pub active_voices: u64,
+    pub peak_voices: u64,
+    pub dropped_events: u64,
+    pub process_faults: u64,
+}
+
+impl MetricsSnapshot {
+    pub fn mean_block_duration_micros(&self) -> f64 {
+        if self.blocks_processed == 0 {
+            return 0.0;
+        }
+        self.total_block_duration_micros as f64 / self.blocks_processed as f64
+    }
+
+    pub fn mean_events_per_block(&self) -> f64 {
+        if self.blocks_processed == 0 {
+            return 0.0;
+        }
+        self.events_emitted as f64 / self.blocks_processed as f64
+    }
+
+    pub fn block_duration_percentile_micros(&self, percentile: f64) -> Option<u64> {
+        let total: u64 = self.block_duration_histogram.iter().sum();
+        if total == 0 {
+            return None;
+        }
+
+        let target = ((percentile.clamp(0.0, 1.0) * total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, count) in self.block_duration_histogram.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                return Some(
+                    BLOCK_DURATION_BUCKETS_MICROS
+                        .get(bucket)
+                        .copied()
+                        .unwrap_or(self.max_block_duration_micros),
+                );
+            }
+        }
+        Some(self.max_block_duration_micros)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TrackMeterReading {
+    pub peak: f32,
+    pub rms: f32,
+}
+
+#[derive(Debug, Default)]
+pub struct EngineMetrics {
+    blocks_processed: AtomicU64,
+    frames_processed: AtomicU64,
+    block_duration_histogram: [AtomicU64; BLOCK_DURATION_BUCKET_COUNT],
+    max_block_duration_micros: AtomicU64,
+    total_block_duration_micros: AtomicU64,
+    events_emitted: AtomicU64,
+    max_events_per_block: AtomicU64,
+    queue_high_water_mark: AtomicU64,
+    active_voices: AtomicU64,
+    peak_voices: AtomicU64,
+    dropped_events: AtomicU64,
+    process_faults: AtomicU64,
+    track_peaks: [AtomicU32; TRACK_COUNT],
+    track_rms: [AtomicU32; TRACK_COUNT],
+}
+
+impl EngineMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_block(&self, duration: Duration, frames: u32, events: usize) {
+        let micros = u64::try_from(duration.as_micros()).unwrap_or(u64::MAX);
+        let bucket = BLOCK_DURATION_BUCKETS_MICROS
+            .iter()
+            .position(|upper| micros <= *upper)
+            .unwrap_or(BLOCK_DURATION_BUCKET_COUNT - 1);
+
+        self.blocks_processed.fetch_add(1, Ordering::Relaxed);
+        self.frames_processed
+            .fetch_add(u64::from(frames), Ordering::Relaxed);
+        self.block_duration_histogram[bucket].fetch_add(1, Ordering::Relaxed);
+        self.max_block_duration_micros
+            .fetch_max(micros, Ordering::Relaxed);
+        self.total_block_duration_micros
+            .fetch_add(micros, Ordering::Relaxed);
+        self.events_emitted
+            .fetch_add(events as u64, Ordering::Relaxed);
+        self.max_events_per_block
+            .fetch_max(events as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_queue_depth(&self, depth: usize) {
+        self.queue_high_water_mark
+            .fetch_max(depth as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_voice_count(&self, voices: usize) {
+        self.active_voices.store(voices as u64, Ordering::Relaxed);
+        self.peak_voices.fetch_max(voices as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_dropped_events(&self, count: usize) {
+        self.dropped_events
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_process_faults(&self, count: u64) {
+        self.process_faults.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn record_track_meters(&self, meters: &[TrackMeterReading; TRACK_COUNT]) {
+        for (track_index, meter) in meters.iter().enumerate() {
+            self.track_peaks[track_index].store(meter.peak.to_bits(), Ordering::Relaxed);
+            self.track_rms[track_index].store(meter.rms.to_bits(), Ordering::Relaxed);
+        }
+    }
+
+    pub fn track_meters(&self) -> [TrackMeterReading; TRACK_COUNT] {
+        std::array::from_fn(|track_index| TrackMeterReading {
+            peak: f32::from_bits(self.track_peaks[track_index].load(Ordering::Relaxed)),
+            rms: f32::from_bits(self.track_rms[track_index].load(Ordering::Relaxed)),
+        })
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            blocks_processed: self.blocks_processed.load(Ordering::Relaxed),
+            frames_processed: self.frames_processed.load(Ordering::Relaxed),
+            block_duration_histogram: std::array::from_fn(|bucket| {
+                self.block_duration_histogram[bucket].load(Ordering::Relaxed)
+            }),
+            max_block_duration_micros: self.max_block_duration_micros.load(Ordering::Relaxed),
+            total_block_duration_micros: self.total_block_duration_micros.load(Ordering::Relaxed),
+            events_emitted: self.events_emitted.load(Ordering::Relaxed),
+            max_events_per_block: self.max_events_per_block.load(Ordering::Relaxed),
+            queue_high_water_mark: self.queue_high_water_mark.load(Ordering::Relaxed),
+            active_voices: self.active_voices.load(Ordering::Relaxed),
+            peak_voices: self.peak_voices.load(Ordering::Relaxed),
+            dropped_events: self.dropped_events.load(Ordering::Relaxed),
+            process_faults: self.process_faults.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn reset(&self) {
+        self.blocks_processed.store(0, Ordering::Relaxed);
+        self.frames_processed.store(0, Ordering::Relaxed);
+        for bucket in &self.block_duration_histogram {
+            bucket.store(0, Ordering::Relaxed);
+        }
+        self.max_block_duration_micros.store(0, Ordering::Relaxed);
+        self.total_block_duration_micros.store(0, Ordering::Relaxed);
+        self.events_emitted.store(0, Ordering::Relaxed);
+        self.max_events_per_block.store(0, Ordering::Relaxed);
+        self.queue_high_water_mark.store(0, Ordering::Relaxed);
+        self.peak_voices.store(
+            self.active_voices.load(Ordering::Relaxed),
+            Ordering::Relaxed,
+        );
+        self.dropped_events.store(0, Ordering::Relaxed);
+        self.process_faults.store(0, Ordering::Relaxed);
+        self.record_track_meters(&[TrackMeterReading::default(); TRACK_COUNT]);
+    }
+}
+
+pub fn process_block_metered(
+    sequencer: &mut Sequencer,
+    frames: u32,
+    metrics: &EngineMetrics,
+) -> Vec<StepTriggerEvent> {
+    let started = Instant::now();
+    let faults = sequencer.process_faults();
+    metrics.record_queue_depth(sequencer.scheduled_trigger_count());
+    let events = sequencer.process_block(frames);
+    metrics.record_block(started.elapsed(), frames, events.len());
+    metrics.record_queue_depth(sequencer.scheduled_trigger_count());
+    metrics.record_process_faults(sequencer.process_faults() - faults);
+    metrics.record_dropped_events(sequencer.take_dropped_triggers() as usize);
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{
+        process_block_metered, EngineMetrics, TrackMeterReading, BLOCK_DURATION_BUCKET_COUNT,
+    };
+    use crate::{Sequencer, Step, TRACK_COUNT};
+
+    #[test]
+    fn block_recording_fills_histogram_and_counters() {
+        let metrics = EngineMetrics::new();
+        metrics.record_block(Duration::from_micros(40), 128, 2);
+        metrics.record_block(Duration::from_micros(700), 128, 0);
+        metrics.record_block(Duration::from_millis(50), 128, 5);
+        metrics.record_queue_depth(12);
+        metrics.record_queue_depth(4);
+        metrics.record_voice_count(6);
+        metrics.record_voice_count(2);
+        metrics.record_dropped_events(3);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.blocks_processed, 3);
+        assert_eq!(snapshot.frames_processed, 384);
+        assert_eq!(snapshot.block_duration_histogram[0], 1);
+        assert_eq!(snapshot.block_duration_histogram[4], 1);
+        assert_eq!(
+            snapshot.block_duration_histogram[BLOCK_DURATION_BUCKET_COUNT - 1],
+            1
+        );
+        assert_eq!(snapshot.max_block_duration_micros, 50_000);
+        assert_eq!(snapshot.max_events_per_block, 5);
+        assert_eq!(snapshot.queue_high_water_mark, 12);
+        assert_eq!((snapshot.active_voices, snapshot.peak_voices), (2, 6));
+        assert_eq!(snapshot.dropped_events, 3);
+        assert_eq!(snapshot.block_duration_percentile_micros(0.5), Some(1_000));
+
+        metrics.reset();
+        let cleared = metrics.snapshot();
+        assert_eq!(cleared.blocks_processed, 0);
+        assert_eq!(cleared.peak_voices, 2);
+    }
+
+    #[test]
+    fn track_meters_publish_peak_and_rms() {
+        let reading = TrackMeterReading {
+            peak: 0.8,
+            rms: 0.25,
+        };
+        let metrics = EngineMetrics::new();
+        let mut meters = [TrackMeterReading::default(); TRACK_COUNT];
+        meters[3] = reading;
+        metrics.record_track_meters(&meters);
+        assert_eq!(metrics.track_meters()[3], reading);
+        metrics.reset();
+        assert_eq!(metrics.track_meters()[3], TrackMeterReading::default());
+    }
+
+    #[test]
+    fn metered_processing_counts_sequencer_events() {
+        let mut sequencer = Sequencer::new(48_000);
+        sequencer.pattern_mut().set_step(
+            0,
+            0,
+            Step {
+                active: true,
+                velocity: 100,
+                ..Step::default()
+            },
+        );
+        sequencer.start();
+
+        let metrics = EngineMetrics::new();
+        let events = process_block_metered(&mut sequencer, 256, &metrics);
+        let snapshot = metrics.snapshot();
+        assert_eq!(events.len(), 1);
+        assert_eq!(snapshot.events_emitted, 1);
+        assert_eq!(snapshot.frames_processed, 256);
+    }
+
+    #[test]
+    fn metered_processing_tracks_queued_and_dropped_hits() {
+        let mut sequencer = Sequencer::new(48_000);
+        sequencer.pattern_mut().set_step(
+            0,
+            0,
+            Step {
+                active: true,
+                flam: true,
+                flam_spacing_ms: 30,
+                ..Step::default()
+            },
+        );
+        sequencer.start();
+
+        let metrics = EngineMetrics::new();
+        assert_eq!(
+            process_block_metered(&mut sequencer, 256, &metrics).len(),
+            1
+        );
+        assert_eq!(metrics.snapshot().queue_high_water_mark, 1);
+        assert!(sequencer.seek_to_step(4, 0.0));
+        process_block_metered(&mut sequencer, 256, &metrics);
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.dropped_events, 1);
+        assert_eq!(snapshot.events_emitted, 1);
+        assert_eq!(sequencer.scheduled_trigger_count(), 0);
+    }
+}
@@ -28,6 +28,33 @@ struct TrackParameters final {
   float envelope_decay = 1.0F;
   float pitch_semitones = 0.0F;
   int choke_group = -1;
+  int duck_source = -1;
+  float duck_depth = 0.0F;
+  float duck_attack_ms = 5.0F;
+  float duck_release_ms = 200.0F;
+  int group = -1;
+  float sample_start = 0.0F;
+  float sample_end = 1.0F;
+  bool reverse = false;
+  std::uint8_t choke_targets = 0;
+};
+
+struct GroupParameters final {
+  float gain = 1.0F;
+  bool muted = false;
+  bool soloed = false;
+  bool choke = false;
+};
+
+struct MasterBusParameters final {
+  float compressor_amount = 0.0F;
+  float filter_cutoff = 1.0F;
+  float limiter_ceiling_db = 0.0F;
+};
+
+struct TrackMeter final {
+  float peak = 0.0F;
+  float rms = 0.0F;
 };
 
 struct PerformanceStats final {
@@ -43,7 +70,9 @@ struct PerformanceStats final {
 class Engine final {
  public:
   static constexpr std::size_t kTrackCount = 8;
+  static constexpr std::size_t kGroupCount = FF_TRACK_GROUP_COUNT;
   static constexpr std::uint8_t kDefaultPadBaseNote = 36;
+  static constexpr std::uint32_t kMaxLookaheadSamples = 1U << 16;
 
   Engine() = default;
 
@@ -51,13 +80,33 @@ class Engine final {
   void process(float* mono_buffer, std::size_t frames) noexcept;
 
   bool setTrackSample(std::size_t track_index, std::vector<float> sample);
+  bool setTrackSampleCrossfade(std::size_t track_index, std::vector<float> sample,
+                               std::uint32_t fade_frames);
+  bool releaseTrackSample(std::size_t track_index, std::uint32_t fade_frames) noexcept;
   void clearTrackSample(std::size_t track_index) noexcept;
+  [[nodiscard]] std::size_t activeVoiceCount() const noexcept;
+  [[nodiscard]] TrackMeter trackMeter(std::size_t track_index) const noexcept;
   bool triggerTrack(std::size_t track_index, float velocity) noexcept;
   bool setTrackParameters(std::size_t track_index, TrackParameters parameters) noexcept;
   [[nodiscard]] TrackParameters trackParameters(std::size_t track_index) const noexcept;
+  bool setGroupParameters(std::size_t group_index, GroupParameters parameters) noexcept;
+  [[nodiscard]] GroupParameters groupParameters(std::size_t group_index) const noexcept;
+  [[nodiscard]] MasterBusParameters masterBusParameters() const noexcept;
   bool applyParameterUpdate(std::uint32_t parameter_id, float normalized_value) noexcept;
+  bool applyParameterUpdate(std::uint32_t parameter_id, float normalized_value,
+                            std::uint32_t ramp_samples) noexcept;
   bool applyParameterUpdates(const ff_parameter_update_t* updates, std::size_t count) noexcept;
 
+  bool setTrackLookahead(std::size_t track_index, std::uint32_t samples);
+  bool setMasterLookahead(std::uint32_t samples);
+  [[nodiscard]] ff_latency_report_t latencyReport() const noexcept;
+
+  bool auditionSample(std::vector<float> sample, float velocity);
+  void stopAudition() noexcept;
+  [[nodiscard]] bool isAuditioning() const noexcept;
+  void setAuditionGain(float gain) noexcept;
+  [[nodiscard]] float auditionGain() const noexcept;
+
   bool handleMidiNoteOn(std::uint8_t note, std::uint8_t velocity) noexcept;
   void setPadBaseNote(std::uint8_t base_note) noexcept;
   [[nodiscard]] std::uint8_t padBaseNote() const noexcept;
@@ -69,6 +118,7 @@ class Engine final {
   [[nodiscard]] float tempoBpm() const noexcept;
 
   bool setAudioDeviceConfig(AudioDeviceConfig config);
+  bool setSampleRate(std::uint32_t sample_rate_hz) noexcept;
   [[nodiscard]] AudioDeviceConfig audioDeviceConfig() const;
 
   void setProfilingEnabled(bool enabled) noexcept;
@@ -77,6 +127,13 @@ class Engine final {
   [[nodiscard]] PerformanceStats performanceStats() const noexcept;
 
  private:
+  static constexpr std::size_t kRampedSlotCount = 5;
+
+  struct ParameterRamp final {
+    float target = 0.0F;
+    std::uint32_t remaining = 0;
+  };
+
   struct TrackVoice final {
     std::vector<float> sample;
     double playhead = 0.0;
@@ -85,6 +142,11 @@ class Engine final {
     float filter_state = 0.0F;
     bool active = false;
     TrackParameters parameters;
+    ParameterRamp ramps[kRampedSlotCount]{};
+    std::uint32_t fade_remaining = 0;
+    std::uint32_t fade_total = 0;
+    float duck_level = 0.0F;
+    bool duck_attacking = false;
   };
 
   static float clampGain(float gain) noexcept;
@@ -93,11 +155,20 @@ class Engine final {
   static float clampEnvelopeDecay(float decay) noexcept;
   static float clampPitchSemitones(float semitones) noexcept;
   static int clampChokeGroup(int choke_group) noexcept;
-  [[nodiscard]] float sampleAt(const TrackVoice& track) const noexcept;
+  static int clampDuckSource(int duck_source) noexcept;
+  static int clampTrackGroup(int group) noexcept;
+  bool applyGroupParameterUpdate(std::uint32_t parameter_id, float normalized_value) noexcept;
+  bool applyMasterParameterUpdate(std::uint32_t parameter_id, float normalized_value) noexcept;
+  [[nodiscard]] float groupGain(std::size_t track_index, bool any_soloed) const noexcept;
+  [[nodiscard]] float sampleAt(const TrackVoice& track, double position) const noexcept;
+  float renderVoice(TrackVoice& voice) noexcept;
   [[nodiscard]] float pitchRatio(float semitones) const noexcept;
   [[nodiscard]] float filterAlpha(float cutoff) const noexcept;
   [[nodiscard]] float envelopeCoefficient(float decay) const noexcept;
   [[nodiscard]] float panGain(float pan) const noexcept;
+  float advanceDuck(TrackVoice& voice) const noexcept;
+  void resizeDelayLines();
+  float delayTrack(std::size_t track_index, float level) noexcept;
   void recordProcessTiming(std::size_t frames, double elapsed_us) noexcept;
   [[nodiscard]] double blockBudgetMicros(std::size_t frames) const noexcept;
 
@@ -105,6 +176,16 @@ class Engine final {
 
   ff::dsp::GainProcessor master_gain_;
   TrackVoice tracks_[kTrackCount];
+  TrackVoice tails_[kTrackCount];
+  TrackVoice audition_;
+  GroupParameters groups_[kGroupCount]{};
+  MasterBusParameters master_bus_{};
+  TrackMeter track_meters_[kTrackCount]{};
+  std::uint32_t track_lookahead_[kTrackCount]{};
+  std::uint32_t master_lookahead_ = 0;
+  std::vector<float> delay_lines_[kTrackCount];
+  std::size_t delay_positions_[kTrackCount]{};
+  float audition_gain_ = 1.0F;
   std::uint8_t pad_base_note_ = kDefaultPadBaseNote;
   TransportState transport_;
   AudioDeviceConfig audio_device_config_;
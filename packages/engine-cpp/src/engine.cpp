@@ -4,6 +4,7 @@
 #include <chrono>
 #include <cmath>
 #include <cstddef>
+#include <iterator>
 #include <utility>
 
 namespace ff::engine {
@@ -21,6 +22,52 @@ int normalizedToChokeGroup(float normalized) noexcept {
   return std::clamp(static_cast<int>(std::lround(clamped * 16.0F)) - 1, 0, 15);
 }
 
+int normalizedToDuckSource(float normalized) noexcept {
+  const float clamped = clampNormalized(normalized);
+  if (clamped <= 0.0001F) {
+    return -1;
+  }
+
+  return std::clamp(static_cast<int>(std::lround(clamped * 8.0F)) - 1, 0, 7);
+}
+
+int normalizedToTrackGroup(float normalized) noexcept {
+  const float clamped = clampNormalized(normalized);
+  if (clamped <= 0.0001F) {
+    return -1;
+  }
+
+  constexpr float kGroups = static_cast<float>(FF_TRACK_GROUP_COUNT);
+  return std::clamp(static_cast<int>(std::lround(clamped * kGroups)) - 1, 0,
+                    FF_TRACK_GROUP_COUNT - 1);
+}
+
+float normalizedToGain(float normalized) noexcept {
+  if (normalized <= 0.0F) {
+    return 0.0F;
+  }
+
+  const float gain_db = FF_GAIN_DB_MIN + (normalized * (FF_GAIN_DB_MAX - FF_GAIN_DB_MIN));
+  return std::pow(10.0F, gain_db / 20.0F);
+}
+
+float* rampedParameter(TrackParameters& parameters, std::uint32_t slot) noexcept {
+  switch (slot) {
+    case FF_PARAM_SLOT_GAIN:
+      return &parameters.gain;
+    case FF_PARAM_SLOT_PAN:
+      return &parameters.pan;
+    case FF_PARAM_SLOT_FILTER_CUTOFF:
+      return &parameters.filter_cutoff;
+    case FF_PARAM_SLOT_ENVELOPE_DECAY:
+      return &parameters.envelope_decay;
+    case FF_PARAM_SLOT_PITCH:
+      return &parameters.pitch_semitones;
+    default:
+      return nullptr;
+  }
+}
+
 }  // namespace
 
 void Engine::setMasterGain(float gain) noexcept { master_gain_.setGain(gain); }
@@ -34,38 +81,39 @@ void Engine::process(float* mono_buffer, std::size_t frames) noexcept {
                                              : std::chrono::steady_clock::time_point{};
 
   std::fill_n(mono_buffer, frames, 0.0F);
+  const bool any_soloed = std::any_of(std::begin(groups_), std::end(groups_),
+                                      [](const GroupParameters& group) { return group.soloed; });
+  float group_gains[kTrackCount]{};
+  for (std::size_t track_index = 0; track_index < kTrackCount; ++track_index) {
+    group_gains[track_index] = groupGain(track_index, any_soloed);
+  }
+  float peaks[kTrackCount]{};
+  double energy[kTrackCount]{};
   for (std::size_t frame = 0; frame < frames; ++frame) {
     float mixed_sample = 0.0F;
-    for (auto& track : tracks_) {
-      if (!track.active) {
-        continue;
-      }
-      if (track.sample.empty()) {
-        track.active = false;
-        continue;
-      }
-
-      const float input = sampleAt(track);
-      track.playhead += pitchRatio(track.parameters.pitch_semitones);
-      if (track.playhead >= static_cast<double>(track.sample.size())) {
-        track.active = false;
-      }
-
-      track.filter_state += filterAlpha(track.parameters.filter_cutoff) * (input - track.filter_state);
-      const float amplitude = track.parameters.gain * track.trigger_velocity * track.envelope_value *
-                              panGain(track.parameters.pan);
-      mixed_sample += track.filter_state * amplitude;
-
-      track.envelope_value *= envelopeCoefficient(track.parameters.envelope_decay);
-      if (track.envelope_value < 0.0001F) {
-        track.active = false;
-      }
+    for (std::size_t track_index = 0; track_index < kTrackCount; ++track_index) {
+      const float level = delayTrack(
+          track_index, renderVoice(tracks_[track_index]) + renderVoice(tails_[track_index]));
+      peaks[track_index] = std::max(peaks[track_index], std::fabs(level));
+      energy[track_index] += static_cast<double>(level) * static_cast<double>(level);
+      mixed_sample += level * group_gains[track_index];
     }
     mono_buffer[frame] = mixed_sample;
   }
+  for (std::size_t track_index = 0; track_index < kTrackCount; ++track_index) {
+    track_meters_[track_index].peak = peaks[track_index];
+    track_meters_[track_index].rms =
+        static_cast<float>(std::sqrt(energy[track_index] / static_cast<double>(frames)));
+  }
 
   master_gain_.process(mono_buffer, frames);
 
+  if (audition_.active) {
+    for (std::size_t frame = 0; frame < frames; ++frame) {
+      mono_buffer[frame] += renderVoice(audition_) * audition_gain_;
+    }
+  }
+
   if (profiling_enabled_) {
     const auto elapsed =
         std::chrono::duration_cast<std::chrono::microseconds>(std::chrono::steady_clock::now() - started_at)
@@ -74,6 +122,66 @@ void Engine::process(float* mono_buffer, std::size_t frames) noexcept {
   }
 }
 
+bool Engine::setTrackLookahead(std::size_t track_index, std::uint32_t samples) {
+  if (track_index >= kTrackCount || samples > kMaxLookaheadSamples) {
+    return false;
+  }
+
+  track_lookahead_[track_index] = samples;
+  resizeDelayLines();
+  return true;
+}
+
+bool Engine::setMasterLookahead(std::uint32_t samples) {
+  if (samples > kMaxLookaheadSamples) {
+    return false;
+  }
+
+  master_lookahead_ = samples;
+  resizeDelayLines();
+  return true;
+}
+
+ff_latency_report_t Engine::latencyReport() const noexcept {
+  ff_latency_report_t report{};
+  report.track_lookahead_samples =
+      *std::max_element(std::begin(track_lookahead_), std::end(track_lookahead_));
+  report.master_lookahead_samples = master_lookahead_;
+  report.total_samples = report.track_lookahead_samples + report.master_lookahead_samples;
+  return report;
+}
+
+void Engine::resizeDelayLines() {
+  const std::uint32_t total = latencyReport().total_samples;
+  for (std::size_t track_index = 0; track_index < kTrackCount; ++track_index) {
+    const std::size_t delay = total - track_lookahead_[track_index];
+    if (delay_lines_[track_index].size() != delay) {
+      delay_lines_[track_index].assign(delay, 0.0F);
+      delay_positions_[track_index] = 0;
+    }
+  }
+}
+
+float Engine::delayTrack(std::size_t track_index, float level) noexcept {
+  auto& line = delay_lines_[track_index];
+  if (line.empty()) {
+    return level;
+  }
+
+  auto& position = delay_positions_[track_index];
+  const float delayed = line[position];
+  line[position] = level;
+  position = (position + 1) % line.size();
+  return delayed;
+}
+
+TrackMeter Engine::trackMeter(std::size_t track_index) const noexcept {
+  if (track_index >= kTrackCount) {
+    return TrackMeter{};
+  }
+  return track_meters_[track_index];
+}
+
 bool Engine::setTrackSample(std::size_t track_index, std::vector<float> sample) {
   if (track_index >= kTrackCount || sample.empty()) {
     return false;
@@ -89,6 +197,38 @@ bool Engine::setTrackSample(std::size_t track_index, std::vector<float> sample)
   return true;
 }
 
+bool Engine::setTrackSampleCrossfade(std::size_t track_index, std::vector<float> sample,
+                                     std::uint32_t fade_frames) {
+  if (sample.empty() || !releaseTrackSample(track_index, fade_frames)) {
+    return false;
+  }
+
+  return setTrackSample(track_index, std::move(sample));
+}
+
+bool Engine::releaseTrackSample(std::size_t track_index, std::uint32_t fade_frames) noexcept {
+  if (track_index >= kTrackCount) {
+    return false;
+  }
+
+  auto& track = tracks_[track_index];
+  if (fade_frames > 0 && track.active) {
+    auto& tail = tails_[track_index];
+    tail = std::move(track);
+    tail.fade_remaining = fade_frames;
+    tail.fade_total = fade_frames;
+  }
+
+  clearTrackSample(track_index);
+  return true;
+}
+
+std::size_t Engine::activeVoiceCount() const noexcept {
+  const auto is_active = [](const TrackVoice& voice) { return voice.active; };
+  return static_cast<std::size_t>(std::count_if(std::begin(tracks_), std::end(tracks_), is_active) +
+                                  std::count_if(std::begin(tails_), std::end(tails_), is_active));
+}
+
 void Engine::clearTrackSample(std::size_t track_index) noexcept {
   if (track_index >= kTrackCount) {
     return;
@@ -113,18 +253,33 @@ bool Engine::triggerTrack(std::size_t track_index, float velocity) noexcept {
     return false;
   }
 
-  if (track.parameters.choke_group >= 0) {
+  const int group = track.parameters.group;
+  const bool group_chokes = group >= 0 && groups_[group].choke;
+  const unsigned choke_targets = track.parameters.choke_targets;
+  if (track.parameters.choke_group >= 0 || group_chokes || choke_targets != 0U) {
     for (std::size_t other_track_index = 0; other_track_index < kTrackCount; ++other_track_index) {
       if (other_track_index == track_index) {
         continue;
       }
       auto& other = tracks_[other_track_index];
-      if (other.active && other.parameters.choke_group == track.parameters.choke_group) {
+      const bool same_choke = track.parameters.choke_group >= 0 &&
+                              other.parameters.choke_group == track.parameters.choke_group;
+      const bool same_group = group_chokes && other.parameters.group == group;
+      const bool targeted = (choke_targets & (1U << other_track_index)) != 0U;
+      if (other.active && (same_choke || same_group || targeted)) {
         other.active = false;
       }
     }
   }
 
+  for (std::size_t other_track_index = 0; other_track_index < kTrackCount; ++other_track_index) {
+    auto& other = tracks_[other_track_index];
+    if (other_track_index != track_index &&
+        other.parameters.duck_source == static_cast<int>(track_index)) {
+      other.duck_attacking = true;
+    }
+  }
+
   track.playhead = 0.0;
   track.trigger_velocity = clampVelocity(velocity);
   track.envelope_value = 1.0F;
@@ -144,10 +299,98 @@ bool Engine::setTrackParameters(std::size_t track_index, TrackParameters paramet
   parameters.envelope_decay = clampEnvelopeDecay(parameters.envelope_decay);
   parameters.pitch_semitones = clampPitchSemitones(parameters.pitch_semitones);
   parameters.choke_group = clampChokeGroup(parameters.choke_group);
+  parameters.duck_source = clampDuckSource(parameters.duck_source);
+  parameters.duck_depth = std::clamp(parameters.duck_depth, 0.0F, 1.0F);
+  parameters.duck_attack_ms = std::clamp(parameters.duck_attack_ms, 0.0F, 100.0F);
+  parameters.duck_release_ms = std::clamp(parameters.duck_release_ms, 0.0F, 1000.0F);
+  parameters.group = clampTrackGroup(parameters.group);
+  parameters.sample_start = std::clamp(parameters.sample_start, 0.0F, 1.0F);
+  parameters.sample_end = std::clamp(parameters.sample_end, 0.0F, 1.0F);
   tracks_[track_index].parameters = parameters;
   return true;
 }
 
+bool Engine::setGroupParameters(std::size_t group_index, GroupParameters parameters) noexcept {
+  if (group_index >= kGroupCount) {
+    return false;
+  }
+
+  parameters.gain = clampGain(parameters.gain);
+  groups_[group_index] = parameters;
+  return true;
+}
+
+GroupParameters Engine::groupParameters(std::size_t group_index) const noexcept {
+  if (group_index >= kGroupCount) {
+    return GroupParameters{};
+  }
+
+  return groups_[group_index];
+}
+
+bool Engine::applyGroupParameterUpdate(std::uint32_t parameter_id,
+                                       float normalized_value) noexcept {
+  const std::uint32_t group_offset = parameter_id - FF_PARAM_GROUP_BASE;
+  const std::size_t group_index = static_cast<std::size_t>(group_offset / FF_PARAM_GROUP_STRIDE);
+  if (group_index >= kGroupCount) {
+    return false;
+  }
+
+  GroupParameters parameters = groups_[group_index];
+  const float clamped = clampNormalized(normalized_value);
+  switch (group_offset % FF_PARAM_GROUP_STRIDE) {
+    case FF_PARAM_GROUP_SLOT_GAIN:
+      parameters.gain = normalizedToGain(clamped);
+      break;
+    case FF_PARAM_GROUP_SLOT_MUTE:
+      parameters.muted = clamped >= 0.5F;
+      break;
+    case FF_PARAM_GROUP_SLOT_SOLO:
+      parameters.soloed = clamped >= 0.5F;
+      break;
+    case FF_PARAM_GROUP_SLOT_CHOKE:
+      parameters.choke = clamped >= 0.5F;
+      break;
+    default:
+      return false;
+  }
+
+  return setGroupParameters(group_index, parameters);
+}
+
+MasterBusParameters Engine::masterBusParameters() const noexcept { return master_bus_; }
+
+bool Engine::applyMasterParameterUpdate(std::uint32_t parameter_id,
+                                        float normalized_value) noexcept {
+  const float clamped = clampNormalized(normalized_value);
+  switch (parameter_id) {
+    case FF_PARAM_GLOBAL_MASTER_COMPRESSOR:
+      master_bus_.compressor_amount = clamped;
+      return true;
+    case FF_PARAM_GLOBAL_MASTER_FILTER:
+      master_bus_.filter_cutoff = clamped;
+      return true;
+    case FF_PARAM_GLOBAL_LIMITER_CEILING:
+      master_bus_.limiter_ceiling_db = FF_LIMITER_CEILING_DB_MIN * (1.0F - clamped);
+      return true;
+    default:
+      return false;
+  }
+}
+
+float Engine::groupGain(std::size_t track_index, bool any_soloed) const noexcept {
+  const int group = tracks_[track_index].parameters.group;
+  if (group < 0) {
+    return any_soloed ? 0.0F : 1.0F;
+  }
+
+  const auto& parameters = groups_[group];
+  if (parameters.muted || (any_soloed && !parameters.soloed)) {
+    return 0.0F;
+  }
+  return parameters.gain;
+}
+
 TrackParameters Engine::trackParameters(std::size_t track_index) const noexcept {
   if (track_index >= kTrackCount) {
     return TrackParameters{};
@@ -157,8 +400,16 @@ TrackParameters Engine::trackParameters(std::size_t track_index) const noexcept
 }
 
 bool Engine::applyParameterUpdate(std::uint32_t parameter_id, float normalized_value) noexcept {
+  return applyParameterUpdate(parameter_id, normalized_value, 0);
+}
+
+bool Engine::applyParameterUpdate(std::uint32_t parameter_id, float normalized_value,
+                                  std::uint32_t ramp_samples) noexcept {
+  if (parameter_id >= FF_PARAM_GROUP_BASE) {
+    return applyGroupParameterUpdate(parameter_id, normalized_value);
+  }
   if (parameter_id < FF_PARAM_TRACK_BASE) {
-    return false;
+    return applyMasterParameterUpdate(parameter_id, normalized_value);
   }
 
   const std::uint32_t track_offset = parameter_id - FF_PARAM_TRACK_BASE;
@@ -172,7 +423,7 @@ bool Engine::applyParameterUpdate(std::uint32_t parameter_id, float normalized_v
   const float clamped = clampNormalized(normalized_value);
   switch (slot) {
     case FF_PARAM_SLOT_GAIN:
-      parameters.gain = clamped * 2.0F;
+      parameters.gain = normalizedToGain(clamped);
       break;
     case FF_PARAM_SLOT_PAN:
       parameters.pan = (clamped * 2.0F) - 1.0F;
@@ -189,10 +440,49 @@ bool Engine::applyParameterUpdate(std::uint32_t parameter_id, float normalized_v
     case FF_PARAM_SLOT_CHOKE_GROUP:
       parameters.choke_group = normalizedToChokeGroup(clamped);
       break;
+    case FF_PARAM_SLOT_DUCK_SOURCE:
+      parameters.duck_source = normalizedToDuckSource(clamped);
+      break;
+    case FF_PARAM_SLOT_DUCK_DEPTH:
+      parameters.duck_depth = clamped;
+      break;
+    case FF_PARAM_SLOT_DUCK_ATTACK:
+      parameters.duck_attack_ms = clamped * 100.0F;
+      break;
+    case FF_PARAM_SLOT_DUCK_RELEASE:
+      parameters.duck_release_ms = clamped * 1000.0F;
+      break;
+    case FF_PARAM_SLOT_TRACK_GROUP:
+      parameters.group = normalizedToTrackGroup(clamped);
+      break;
+    case FF_PARAM_SLOT_SAMPLE_START:
+      parameters.sample_start = clamped;
+      break;
+    case FF_PARAM_SLOT_SAMPLE_END:
+      parameters.sample_end = clamped;
+      break;
+    case FF_PARAM_SLOT_REVERSE:
+      parameters.reverse = clamped >= 0.5F;
+      break;
+    case FF_PARAM_SLOT_CHOKE_TARGETS:
+      parameters.choke_targets = static_cast<std::uint8_t>(std::lround(clamped * 255.0F));
+      break;
     default:
       return false;
   }
 
+  auto& track = tracks_[track_index];
+  const float* target = rampedParameter(parameters, slot);
+  if (target != nullptr) {
+    auto& ramp = track.ramps[slot - 1];
+    if (ramp_samples > 0) {
+      ramp.target = *target;
+      ramp.remaining = ramp_samples;
+      return true;
+    }
+    ramp.remaining = 0;
+  }
+
   return setTrackParameters(track_index, parameters);
 }
 
@@ -204,7 +494,7 @@ bool Engine::applyParameterUpdates(const ff_parameter_update_t* updates, std::si
   bool all_applied = true;
   for (std::size_t index = 0; index < count; ++index) {
     const auto& update = updates[index];
-    if (!applyParameterUpdate(update.parameter_id, update.normalized_value)) {
+    if (!applyParameterUpdate(update.parameter_id, update.normalized_value, update.ramp_samples)) {
       all_applied = false;
     }
   }
@@ -212,6 +502,28 @@ bool Engine::applyParameterUpdates(const ff_parameter_update_t* updates, std::si
   return all_applied;
 }
 
+bool Engine::auditionSample(std::vector<float> sample, float velocity) {
+  if (sample.empty()) {
+    return false;
+  }
+
+  audition_.sample = std::move(sample);
+  audition_.playhead = 0.0;
+  audition_.trigger_velocity = clampVelocity(velocity);
+  audition_.envelope_value = 1.0F;
+  audition_.filter_state = 0.0F;
+  audition_.active = audition_.trigger_velocity > 0.0F;
+  return audition_.active;
+}
+
+void Engine::stopAudition() noexcept { audition_.active = false; }
+
+bool Engine::isAuditioning() const noexcept { return audition_.active; }
+
+void Engine::setAuditionGain(float gain) noexcept { audition_gain_ = clampGain(gain); }
+
+float Engine::auditionGain() const noexcept { return audition_gain_; }
+
 bool Engine::handleMidiNoteOn(std::uint8_t note, std::uint8_t velocity) noexcept {
   if (velocity == 0) {
     return false;
@@ -254,6 +566,34 @@ bool Engine::setAudioDeviceConfig(AudioDeviceConfig config) {
   return true;
 }
 
+bool Engine::setSampleRate(std::uint32_t sample_rate_hz) noexcept {
+  if (sample_rate_hz == 0) {
+    return false;
+  }
+
+  const double ratio = static_cast<double>(sample_rate_hz) /
+                       static_cast<double>(std::max(audio_device_config_.sample_rate_hz, 1U));
+  const auto rescale = [ratio](std::uint32_t frames) {
+    return static_cast<std::uint32_t>(std::llround(static_cast<double>(frames) * ratio));
+  };
+  const auto rescale_voice = [&rescale](TrackVoice& voice) {
+    for (auto& ramp : voice.ramps) {
+      ramp.remaining = rescale(ramp.remaining);
+    }
+    if (voice.fade_remaining > 0) {
+      voice.fade_remaining = std::max(rescale(voice.fade_remaining), 1U);
+      voice.fade_total = std::max(rescale(voice.fade_total), voice.fade_remaining);
+    }
+  };
+  for (std::size_t track_index = 0; track_index < kTrackCount; ++track_index) {
+    rescale_voice(tracks_[track_index]);
+    rescale_voice(tails_[track_index]);
+  }
+  rescale_voice(audition_);
+  audio_device_config_.sample_rate_hz = sample_rate_hz;
+  return true;
+}
+
 AudioDeviceConfig Engine::audioDeviceConfig() const { return audio_device_config_; }
 
 void Engine::setProfilingEnabled(bool enabled) noexcept { profiling_enabled_ = enabled; }
@@ -291,13 +631,27 @@ int Engine::clampChokeGroup(int choke_group) noexcept {
   return std::min(choke_group, 15);
 }
 
-float Engine::sampleAt(const TrackVoice& track) const noexcept {
+int Engine::clampDuckSource(int duck_source) noexcept {
+  if (duck_source < 0 || duck_source >= static_cast<int>(kTrackCount)) {
+    return -1;
+  }
+  return duck_source;
+}
+
+int Engine::clampTrackGroup(int group) noexcept {
+  if (group < 0 || group >= static_cast<int>(kGroupCount)) {
+    return -1;
+  }
+  return group;
+}
+
+float Engine::sampleAt(const TrackVoice& track, double position) const noexcept {
   if (track.sample.empty()) {
     return 0.0F;
   }
 
   const double clamped_playhead =
-      std::clamp(track.playhead, 0.0, static_cast<double>(track.sample.size() - 1));
+      std::clamp(position, 0.0, static_cast<double>(track.sample.size() - 1));
   const std::size_t lower_index = static_cast<std::size_t>(clamped_playhead);
   const std::size_t upper_index =
       std::min(lower_index + 1, static_cast<std::size_t>(track.sample.size() - 1));
@@ -307,6 +661,58 @@ float Engine::sampleAt(const TrackVoice& track) const noexcept {
   return lower_sample + ((upper_sample - lower_sample) * fraction);
 }
 
+float Engine::renderVoice(TrackVoice& voice) noexcept {
+  for (std::uint32_t slot = 1; slot <= kRampedSlotCount; ++slot) {
+    auto& ramp = voice.ramps[slot - 1];
+    if (ramp.remaining == 0) {
+      continue;
+    }
+    float* value = rampedParameter(voice.parameters, slot);
+    *value += (ramp.target - *value) / static_cast<float>(ramp.remaining);
+    ramp.remaining -= 1;
+  }
+  const float duck_gain = advanceDuck(voice);
+
+  if (!voice.active) {
+    return 0.0F;
+  }
+  if (voice.sample.empty()) {
+    voice.active = false;
+    return 0.0F;
+  }
+
+  const double length = static_cast<double>(voice.sample.size());
+  const double start_frame = std::min(
+      std::floor(static_cast<double>(voice.parameters.sample_start) * length), length - 1.0);
+  const double end_frame =
+      std::max(std::ceil(static_cast<double>(voice.parameters.sample_end) * length),
+               start_frame + 1.0);
+  const double position = voice.parameters.reverse ? end_frame - 1.0 - voice.playhead
+                                                   : start_frame + voice.playhead;
+  const float input = sampleAt(voice, position);
+  voice.playhead += pitchRatio(voice.parameters.pitch_semitones);
+  if (voice.playhead >= end_frame - start_frame) {
+    voice.active = false;
+  }
+
+  voice.filter_state += filterAlpha(voice.parameters.filter_cutoff) * (input - voice.filter_state);
+  float amplitude = voice.parameters.gain * voice.trigger_velocity * voice.envelope_value *
+                    panGain(voice.parameters.pan) * duck_gain;
+  if (voice.fade_total > 0) {
+    amplitude *= static_cast<float>(voice.fade_remaining) / static_cast<float>(voice.fade_total);
+    voice.fade_remaining = voice.fade_remaining > 0 ? voice.fade_remaining - 1 : 0;
+    if (voice.fade_remaining == 0) {
+      voice.active = false;
+    }
+  }
+
+  voice.envelope_value *= envelopeCoefficient(voice.parameters.envelope_decay);
+  if (voice.envelope_value < 0.0001F) {
+    voice.active = false;
+  }
+  return voice.filter_state * amplitude;
+}
+
 float Engine::pitchRatio(float semitones) const noexcept {
   return std::pow(2.0F, semitones / 12.0F);
 }
@@ -321,6 +727,21 @@ float Engine::envelopeCoefficient(float decay) const noexcept {
 
 float Engine::panGain(float pan) const noexcept { return 1.0F - (std::fabs(pan) * 0.5F); }
 
+float Engine::advanceDuck(TrackVoice& voice) const noexcept {
+  const float samples_per_ms =
+      static_cast<float>(std::max(audio_device_config_.sample_rate_hz, 1U)) / 1000.0F;
+  if (voice.duck_attacking) {
+    const float attack_samples = std::max(voice.parameters.duck_attack_ms * samples_per_ms, 1.0F);
+    voice.duck_level = std::min(voice.duck_level + (1.0F / attack_samples), 1.0F);
+    voice.duck_attacking = voice.duck_level < 1.0F;
+  } else if (voice.duck_level > 0.0F) {
+    const float release_samples =
+        std::max(voice.parameters.duck_release_ms * samples_per_ms, 1.0F);
+    voice.duck_level = std::max(voice.duck_level - (1.0F / release_samples), 0.0F);
+  }
+  return 1.0F - (voice.parameters.duck_depth * voice.duck_level);
+}
+
 void Engine::recordProcessTiming(std::size_t frames, double elapsed_us) noexcept {
   performance_stats_.processed_blocks += 1;
   performance_stats_.processed_frames += static_cast<std::uint64_t>(frames);
@@ -17,6 +17,8 @@ bool almostEqual(float left, float right, float epsilon = 0.0001F) {
   return std::fabs(left - right) <= epsilon;
 }
 
+constexpr float kUnityGainNormalized = 115.0F / 127.0F;
+
 std::vector<ff_parameter_update_t> loadParameterUpdatesFromFixture(const std::string& path) {
   std::ifstream fixture(path);
   assert(fixture.is_open());
@@ -151,6 +153,238 @@ void chokeGroupsSilencePreviousTrack() {
   assert(almostEqual(buffer[0], 1.0F));
 }
 
+void chokeTargetsSilenceOnlyTheTargetedDirection() {
+  ff::engine::Engine engine;
+  assert(engine.setTrackSample(0, std::vector<float>{1.0F, 1.0F}));
+  assert(engine.setTrackSample(1, std::vector<float>{1.0F, 1.0F}));
+
+  const auto choke_targets_id = static_cast<std::uint32_t>(FF_PARAM_TRACK_BASE) +
+                                static_cast<std::uint32_t>(FF_PARAM_SLOT_CHOKE_TARGETS);
+  assert(engine.applyParameterUpdate(choke_targets_id, 2.0F / 255.0F));
+  assert(engine.trackParameters(0).choke_targets == 0b10U);
+
+  assert(engine.triggerTrack(1, 1.0F));
+  assert(engine.triggerTrack(0, 1.0F));
+  assert(engine.activeVoiceCount() == 1U);
+
+  assert(engine.triggerTrack(1, 1.0F));
+  assert(engine.activeVoiceCount() == 2U);
+}
+
+void trackGroupsScaleMuteSoloAndChokeMembers() {
+  ff::engine::Engine engine;
+  for (std::size_t track_index = 0; track_index < 3; ++track_index) {
+    assert(engine.setTrackSample(track_index, std::vector<float>(8, 1.0F)));
+  }
+
+  const auto track_group_id = [](std::uint32_t track_index) {
+    return static_cast<std::uint32_t>(FF_PARAM_TRACK_BASE) +
+           (track_index * static_cast<std::uint32_t>(FF_PARAM_TRACK_STRIDE)) +
+           static_cast<std::uint32_t>(FF_PARAM_SLOT_TRACK_GROUP);
+  };
+  const auto group_id = [](std::uint32_t group_index, std::uint32_t slot) {
+    return static_cast<std::uint32_t>(FF_PARAM_GROUP_BASE) +
+           (group_index * static_cast<std::uint32_t>(FF_PARAM_GROUP_STRIDE)) + slot;
+  };
+  assert(engine.applyParameterUpdate(track_group_id(0), 1.0F / 4.0F));
+  assert(engine.applyParameterUpdate(track_group_id(1), 1.0F / 4.0F));
+  assert(engine.applyParameterUpdate(track_group_id(2), 2.0F / 4.0F));
+  assert(engine.trackParameters(2).group == 1);
+  assert(engine.applyParameterUpdate(group_id(0, FF_PARAM_GROUP_SLOT_GAIN), 103.0F / 127.0F));
+  assert(!engine.applyParameterUpdate(group_id(4, FF_PARAM_GROUP_SLOT_GAIN), 0.5F));
+
+  std::array<float, 1> buffer{};
+  assert(engine.triggerTrack(0, 1.0F));
+  assert(engine.triggerTrack(2, 1.0F));
+  engine.process(buffer.data(), buffer.size());
+  const float half = std::pow(10.0F, -6.0F / 20.0F);
+  assert(almostEqual(buffer[0], half + 1.0F));
+
+  assert(engine.applyParameterUpdate(group_id(0, FF_PARAM_GROUP_SLOT_CHOKE), 1.0F));
+  assert(engine.triggerTrack(1, 1.0F));
+  assert(engine.activeVoiceCount() == 2);
+
+  assert(engine.applyParameterUpdate(group_id(1, FF_PARAM_GROUP_SLOT_SOLO), 1.0F));
+  engine.process(buffer.data(), buffer.size());
+  assert(almostEqual(buffer[0], 1.0F));
+
+  assert(engine.applyParameterUpdate(group_id(1, FF_PARAM_GROUP_SLOT_MUTE), 1.0F));
+  engine.process(buffer.data(), buffer.size());
+  assert(almostEqual(buffer[0], 0.0F));
+  assert(engine.groupParameters(1).muted && engine.groupParameters(1).soloed);
+}
+
+void masterBusParametersAreStoredFromGlobalUpdates() {
+  ff::engine::Engine engine;
+  assert(almostEqual(engine.masterBusParameters().filter_cutoff, 1.0F));
+  assert(engine.applyParameterUpdate(FF_PARAM_GLOBAL_MASTER_COMPRESSOR, 0.4F));
+  assert(engine.applyParameterUpdate(FF_PARAM_GLOBAL_MASTER_FILTER, 1.5F));
+  assert(engine.applyParameterUpdate(FF_PARAM_GLOBAL_LIMITER_CEILING, 0.75F));
+  assert(!engine.applyParameterUpdate(FF_PARAM_GLOBAL_SWING, 0.5F));
+
+  const auto master = engine.masterBusParameters();
+  assert(almostEqual(master.compressor_amount, 0.4F));
+  assert(almostEqual(master.filter_cutoff, 1.0F));
+  assert(almostEqual(master.limiter_ceiling_db, -3.0F));
+}
+
+void duckingFollowsSourceTrackTriggers() {
+  ff::engine::Engine engine;
+  assert(engine.setTrackSample(0, std::vector<float>(256, 0.0F)));
+  assert(engine.setTrackSample(1, std::vector<float>(256, 1.0F)));
+
+  const auto track1_base = static_cast<std::uint32_t>(FF_PARAM_TRACK_BASE) +
+                           static_cast<std::uint32_t>(FF_PARAM_TRACK_STRIDE);
+  assert(engine.applyParameterUpdate(
+      track1_base + static_cast<std::uint32_t>(FF_PARAM_SLOT_DUCK_SOURCE), 1.0F / 8.0F));
+  assert(engine.applyParameterUpdate(
+      track1_base + static_cast<std::uint32_t>(FF_PARAM_SLOT_DUCK_DEPTH), 1.0F));
+  assert(engine.applyParameterUpdate(
+      track1_base + static_cast<std::uint32_t>(FF_PARAM_SLOT_DUCK_ATTACK), 0.0F));
+  assert(engine.applyParameterUpdate(
+      track1_base + static_cast<std::uint32_t>(FF_PARAM_SLOT_DUCK_RELEASE), 0.001F));
+  const auto parameters = engine.trackParameters(1);
+  assert(parameters.duck_source == 0);
+  assert(almostEqual(parameters.duck_release_ms, 1.0F));
+
+  assert(engine.triggerTrack(1, 1.0F));
+  std::array<float, 4> buffer{};
+  engine.process(buffer.data(), buffer.size());
+  assert(buffer[3] > 0.5F);
+
+  assert(engine.triggerTrack(0, 1.0F));
+  engine.process(buffer.data(), 1);
+  assert(almostEqual(buffer[0], 0.0F));
+
+  std::array<float, 64> recovery{};
+  engine.process(recovery.data(), recovery.size());
+  assert(recovery[63] > 0.5F);
+}
+
+void trackMetersReportPerBlockLevels() {
+  ff::engine::Engine engine;
+  assert(engine.setTrackSample(2, std::vector<float>(256, 0.5F)));
+  assert(engine.triggerTrack(2, 1.0F));
+  engine.setMasterGain(0.0F);
+
+  std::array<float, 32> buffer{};
+  engine.process(buffer.data(), buffer.size());
+  const auto meter = engine.trackMeter(2);
+  assert(meter.peak > 0.0F && meter.peak <= 0.5F);
+  assert(meter.rms > 0.0F && meter.rms <= meter.peak);
+  assert(almostEqual(engine.trackMeter(0).peak, 0.0F));
+  assert(almostEqual(engine.trackMeter(ff::engine::Engine::kTrackCount).rms, 0.0F));
+}
+
+void preTriggeredTracksLeadTheLatencyCompensatedMix() {
+  ff::engine::Engine engine;
+  assert(engine.setTrackSample(0, std::vector<float>{1.0F}));
+  assert(engine.setTrackSample(1, std::vector<float>{1.0F}));
+  assert(engine.setTrackLookahead(1, 3));
+  assert(engine.setMasterLookahead(2));
+  assert(!engine.setTrackLookahead(8, 1));
+  const ff_latency_report_t report = engine.latencyReport();
+  assert(report.total_samples == 5);
+  assert(report.track_lookahead_samples == 3);
+  assert(report.master_lookahead_samples == 2);
+
+  assert(engine.triggerTrack(0, 1.0F));
+  assert(engine.triggerTrack(1, 1.0F));
+  std::array<float, 8> buffer{};
+  engine.process(buffer.data(), buffer.size());
+  for (std::size_t frame = 0; frame < buffer.size(); ++frame) {
+    assert((buffer[frame] > 0.0F) == (frame == 2 || frame == 5));
+  }
+}
+
+void auditionVoiceBypassesChokeGroupsAndMasterGain() {
+  ff::engine::Engine engine;
+  assert(engine.setTrackSample(0, std::vector<float>{1.0F, 1.0F}));
+  ff::engine::TrackParameters choke;
+  choke.choke_group = 0;
+  assert(engine.setTrackParameters(0, choke));
+  engine.setMasterGain(0.0F);
+
+  assert(engine.triggerTrack(0, 1.0F));
+  assert(engine.auditionSample(std::vector<float>{0.5F, 0.5F}, 1.0F));
+  assert(engine.isAuditioning());
+
+  std::array<float, 1> buffer{};
+  engine.process(buffer.data(), buffer.size());
+  assert(almostEqual(buffer[0], 0.5F));
+
+  engine.setMasterGain(1.0F);
+  engine.process(buffer.data(), buffer.size());
+  assert(almostEqual(buffer[0], 1.5F));
+
+  engine.stopAudition();
+  assert(!engine.isAuditioning());
+  assert(!engine.auditionSample(std::vector<float>{}, 1.0F));
+}
+
+void trimAndReversePlayOnlyTheSelectedRegion() {
+  ff::engine::Engine engine;
+  std::vector<float> ramp(8);
+  for (std::size_t frame = 0; frame < ramp.size(); ++frame) {
+    ramp[frame] = static_cast<float>(frame) / 10.0F;
+  }
+  assert(engine.setTrackSample(0, ramp));
+
+  const auto track_slot_id = [](std::uint32_t slot) {
+    return static_cast<std::uint32_t>(FF_PARAM_TRACK_BASE) + slot;
+  };
+  assert(engine.applyParameterUpdate(track_slot_id(FF_PARAM_SLOT_SAMPLE_START), 0.5F));
+  assert(engine.applyParameterUpdate(track_slot_id(FF_PARAM_SLOT_REVERSE), 1.0F));
+  assert(engine.trackParameters(0).reverse);
+
+  std::array<float, 6> buffer{};
+  assert(engine.triggerTrack(0, 1.0F));
+  engine.process(buffer.data(), buffer.size());
+  const std::array<float, 6> expected{0.7F, 0.6F, 0.5F, 0.4F, 0.0F, 0.0F};
+  for (std::size_t frame = 0; frame < buffer.size(); ++frame) {
+    assert(almostEqual(buffer[frame], expected[frame], 0.001F));
+  }
+}
+
+void rampedUpdatesAndCrossfadedSamplesAvoidHardCuts() {
+  ff::engine::Engine engine;
+  assert(engine.setTrackSample(0, std::vector<float>(1000, 1.0F)));
+  const std::uint32_t gain_id =
+      static_cast<std::uint32_t>(FF_PARAM_TRACK_BASE) + static_cast<std::uint32_t>(FF_PARAM_SLOT_GAIN);
+  assert(engine.applyParameterUpdate(gain_id, kUnityGainNormalized));
+  assert(engine.applyParameterUpdate(gain_id, 1.0F, 4));
+  std::array<float, 2> ramp_buffer{};
+  engine.process(ramp_buffer.data(), ramp_buffer.size());
+  const float max_gain = std::pow(10.0F, FF_GAIN_DB_MAX / 20.0F);
+  assert(almostEqual(engine.trackParameters(0).gain, (1.0F + max_gain) * 0.5F));
+
+  assert(engine.triggerTrack(0, 1.0F));
+  assert(engine.setTrackSampleCrossfade(0, std::vector<float>(8, 0.5F), 100));
+  assert(engine.activeVoiceCount() == 1);
+
+  std::array<float, 100> tail{};
+  engine.process(tail.data(), tail.size());
+  assert(tail[0] > tail[50] && tail[50] > tail[99] && tail[99] > 0.0F);
+  assert(engine.activeVoiceCount() == 0);
+}
+
+void sampleRateChangesStretchPendingRamps() {
+  ff::engine::Engine engine;
+  assert(engine.setTrackSample(0, std::vector<float>(1000, 1.0F)));
+  const std::uint32_t gain_id =
+      static_cast<std::uint32_t>(FF_PARAM_TRACK_BASE) + static_cast<std::uint32_t>(FF_PARAM_SLOT_GAIN);
+  assert(engine.applyParameterUpdate(gain_id, kUnityGainNormalized));
+  assert(engine.applyParameterUpdate(gain_id, 1.0F, 4));
+  assert(!engine.setSampleRate(0));
+  assert(engine.setSampleRate(96000));
+  assert(engine.audioDeviceConfig().sample_rate_hz == 96000);
+
+  std::array<float, 4> ramp_buffer{};
+  engine.process(ramp_buffer.data(), ramp_buffer.size());
+  const float max_gain = std::pow(10.0F, FF_GAIN_DB_MAX / 20.0F);
+  assert(almostEqual(engine.trackParameters(0).gain, (1.0F + max_gain) * 0.5F));
+}
+
 void pitchControlChangesPlaybackRate() {
   ff::engine::Engine engine;
   assert(engine.setTrackSample(0, std::vector<float>{1.0F, 0.0F, 1.0F, 0.0F}));
@@ -184,13 +418,13 @@ void parameterUpdatesMapNormalizedValuesToTrackParameters() {
   const auto choke_id = static_cast<std::uint32_t>(FF_PARAM_TRACK_BASE) +
                         static_cast<std::uint32_t>(FF_PARAM_SLOT_CHOKE_GROUP);
 
-  assert(engine.applyParameterUpdate(gain_id, 0.5F));
+  assert(engine.applyParameterUpdate(gain_id, 103.0F / 127.0F));
   assert(engine.applyParameterUpdate(pan_id, 0.75F));
   assert(engine.applyParameterUpdate(pitch_id, 0.75F));
   assert(engine.applyParameterUpdate(choke_id, 0.25F));
 
   const auto restored = engine.trackParameters(0);
-  assert(almostEqual(restored.gain, 1.0F));
+  assert(almostEqual(restored.gain, std::pow(10.0F, -6.0F / 20.0F)));
   assert(almostEqual(restored.pan, 0.5F));
   assert(almostEqual(restored.pitch_semitones, 12.0F));
   assert(restored.choke_group == 3);
@@ -214,7 +448,7 @@ void recallFixtureUpdatesApplyThroughAbiBatchPath() {
   assert(engine.applyParameterUpdates(updates.data(), updates.size()));
 
   const auto track0 = engine.trackParameters(0);
-  assert(almostEqual(track0.gain, 2.0F));
+  assert(almostEqual(track0.gain, 1.0F, 0.001F));
   assert(almostEqual(track0.pan, 1.0F));
   assert(almostEqual(track0.filter_cutoff, 1.0F));
   assert(almostEqual(track0.envelope_decay, 1.0F));
@@ -270,6 +504,16 @@ int main() {
   transportAndAudioDeviceConfigRoundTrip();
   trackParametersAffectOutput();
   chokeGroupsSilencePreviousTrack();
+  chokeTargetsSilenceOnlyTheTargetedDirection();
+  sampleRateChangesStretchPendingRamps();
+  trackGroupsScaleMuteSoloAndChokeMembers();
+  masterBusParametersAreStoredFromGlobalUpdates();
+  duckingFollowsSourceTrackTriggers();
+  trackMetersReportPerBlockLevels();
+  preTriggeredTracksLeadTheLatencyCompensatedMix();
+  auditionVoiceBypassesChokeGroupsAndMasterGain();
+  trimAndReversePlayOnlyTheSelectedRegion();
+  rampedUpdatesAndCrossfadedSamplesAvoidHardCuts();
   pitchControlChangesPlaybackRate();
   parameterUpdatesMapNormalizedValuesToTrackParameters();
   invalidParameterUpdateIsRejected();
@@ -0,0 +1,19 @@
+const SONAME_MAJOR: &str = "1";
+
+fn main() {
+    let target_os = std::env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    match target_os.as_str() {
+        "linux" | "android" | "freebsd" => {
+            println!(
+                "cargo:rustc-cdylib-link-arg=-Wl,-soname,libforestfloor_ffi.so.{SONAME_MAJOR}"
+            );
+        }
+        "macos" => {
+            println!(
+                "cargo:rustc-cdylib-link-arg=-Wl,-install_name,@rpath/libforestfloor_ffi.{SONAME_MAJOR}.dylib"
+            );
+        }
+        _ => {}
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+}
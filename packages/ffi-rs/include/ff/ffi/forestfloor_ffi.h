@@ -0,0 +1,119 @@
+#ifndef FF_FFI_FORESTFLOOR_FFI_H_
+#define FF_FFI_FORESTFLOOR_FFI_H_
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#include "ff/abi/contracts.h"
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Linked as libforestfloor_ffi.so.1 / libforestfloor_ffi.1.dylib.
+ * Every exported symbol carries the ff_ffi_v1_ prefix; an incompatible
+ * change ships as ff_ffi_v2_* alongside a new soname major. */
+
+enum {
+  FF_FFI_VERSION_MAJOR = 1,
+  FF_FFI_VERSION_MINOR = 2,
+};
+
+/* A panic inside any call is caught at the boundary: status-returning calls
+ * report FF_FFI_ERROR_PANIC, create calls return NULL, and the message is
+ * available from ff_ffi_v1_last_error. */
+enum {
+  FF_FFI_OK = 0,
+  FF_FFI_ERROR_NULL_POINTER = -1,
+  FF_FFI_ERROR_INVALID_ARGUMENT = -2,
+  FF_FFI_ERROR_PARSE = -3,
+  FF_FFI_ERROR_PANIC = -4,
+};
+
+enum {
+  FF_FFI_MIDI_NOTE_ON = 1,
+  FF_FFI_MIDI_NOTE_OFF = 2,
+  FF_FFI_MIDI_CONTROL_CHANGE = 3,
+};
+
+enum {
+  FF_FFI_SOURCE_SEQUENCER = 1,
+  FF_FFI_SOURCE_LIVE_PAD = 2,
+  FF_FFI_SOURCE_NOTE_REPEAT = 3,
+};
+
+typedef struct ff_ffi_sequencer_t ff_ffi_sequencer_t;
+typedef struct ff_ffi_smoother_t ff_ffi_smoother_t;
+
+typedef struct ff_ffi_midi_message_t {
+  uint32_t kind;
+  uint8_t channel;
+  uint8_t data1;
+  uint8_t data2;
+  uint8_t reserved;
+} ff_ffi_midi_message_t;
+
+/* Versions are packed as (major << 16) | minor. */
+uint32_t ff_ffi_v1_library_version(void);
+uint32_t ff_ffi_v1_abi_version(void);
+
+/* Message for the most recent failure on the calling thread. */
+const char* ff_ffi_v1_last_error(void);
+
+ff_ffi_sequencer_t* ff_ffi_v1_sequencer_create(uint32_t sample_rate_hz);
+/* Returns NULL on failure; see ff_ffi_v1_last_error. */
+ff_ffi_sequencer_t* ff_ffi_v1_sequencer_create_from_project(const char* project_text,
+                                                            uint32_t sample_rate_hz);
+void ff_ffi_v1_sequencer_destroy(ff_ffi_sequencer_t* sequencer);
+
+int32_t ff_ffi_v1_sequencer_start(ff_ffi_sequencer_t* sequencer);
+int32_t ff_ffi_v1_sequencer_stop(ff_ffi_sequencer_t* sequencer);
+int32_t ff_ffi_v1_sequencer_reset(ff_ffi_sequencer_t* sequencer);
+int32_t ff_ffi_v1_sequencer_set_tempo_bpm(ff_ffi_sequencer_t* sequencer, float bpm);
+/* Rescales the pending step phase, timeline and scheduled triggers to the new rate. */
+int32_t ff_ffi_v1_sequencer_set_sample_rate(ff_ffi_sequencer_t* sequencer,
+                                            uint32_t sample_rate_hz);
+int32_t ff_ffi_v1_sequencer_set_swing(ff_ffi_sequencer_t* sequencer, float swing);
+/* choke_group < 0 clears the group. */
+int32_t ff_ffi_v1_sequencer_set_track_choke_group(ff_ffi_sequencer_t* sequencer,
+                                                  uint32_t track_index, int32_t choke_group);
+/* While stopped, activating a step queues a one-shot preview trigger for the
+ * next process_block call unless edit preview is disabled. */
+int32_t ff_ffi_v1_sequencer_set_step(ff_ffi_sequencer_t* sequencer, uint32_t track_index,
+                                     uint32_t step_index, bool active, uint8_t velocity);
+int32_t ff_ffi_v1_sequencer_set_edit_preview(ff_ffi_sequencer_t* sequencer, bool enabled);
+
+/* Writes up to event_capacity FF_EVENT_TYPE_TRIGGER events and returns the
+ * number produced by the block; events past the capacity are dropped. */
+ptrdiff_t ff_ffi_v1_sequencer_process_block(ff_ffi_sequencer_t* sequencer, uint32_t frames,
+                                            ff_event_t* out_events, size_t event_capacity);
+
+/* Size-query calls: pass a NULL buffer with zero capacity to get the count. */
+ptrdiff_t ff_ffi_v1_project_parameter_updates(const char* project_text,
+                                              uint32_t sample_rate_hz,
+                                              ff_parameter_update_t* out_updates,
+                                              size_t update_capacity);
+ptrdiff_t ff_ffi_v1_project_sample_id(const char* project_text, uint32_t track_index,
+                                      char* out_text, size_t text_capacity);
+
+/* Coalesces stepwise updates per parameter id and stamps each with the
+ * slot's smoothing time, rounded up to whole blocks. Drain once per block;
+ * a NULL buffer with zero capacity returns the pending count. */
+ff_ffi_smoother_t* ff_ffi_v1_smoother_create(uint32_t sample_rate_hz, uint32_t block_frames);
+void ff_ffi_v1_smoother_destroy(ff_ffi_smoother_t* smoother);
+int32_t ff_ffi_v1_smoother_set_slot_time_ms(ff_ffi_smoother_t* smoother, uint32_t parameter_slot,
+                                            float time_ms);
+int32_t ff_ffi_v1_smoother_push(ff_ffi_smoother_t* smoother,
+                                const ff_parameter_update_t* update);
+ptrdiff_t ff_ffi_v1_smoother_drain(ff_ffi_smoother_t* smoother, ff_parameter_update_t* out_updates,
+                                   size_t update_capacity);
+
+int32_t ff_ffi_v1_parse_midi(const uint8_t* bytes, size_t length,
+                             ff_ffi_midi_message_t* out_message);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif
+
+#endif  // FF_FFI_FORESTFLOOR_FFI_H_
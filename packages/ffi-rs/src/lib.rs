@@ -0,0 +1,716 @@
+#![allow(clippy::missing_safety_doc)]
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+
+use abi_rs::{FfEvent, FfEventPayload, FfParameterUpdate, FfTriggerEvent, FF_EVENT_TYPE_TRIGGER};
+use control_rs::smoothing::{ParameterSmoother, SmoothingConfig};
+use control_rs::{Sequencer, Step, TRACK_COUNT};
+use midi_rs::MidiMessage;
+
+pub const FF_FFI_VERSION_MAJOR: u32 = 1;
+pub const FF_FFI_VERSION_MINOR: u32 = 2;
+
+pub const FF_FFI_OK: i32 = 0;
+pub const FF_FFI_ERROR_NULL_POINTER: i32 = -1;
+pub const FF_FFI_ERROR_INVALID_ARGUMENT: i32 = -2;
+pub const FF_FFI_ERROR_PARSE: i32 = -3;
+pub const FF_FFI_ERROR_PANIC: i32 = -4;
+
+pub const FF_FFI_MIDI_NOTE_ON: u32 = 1;
+pub const FF_FFI_MIDI_NOTE_OFF: u32 = 2;
+pub const FF_FFI_MIDI_CONTROL_CHANGE: u32 = 3;
+
+pub const FF_FFI_SOURCE_SEQUENCER: u16 = abi_rs::FF_EVENT_SOURCE_SEQUENCER;
+pub const FF_FFI_SOURCE_LIVE_PAD: u16 = abi_rs::FF_EVENT_SOURCE_LIVE_PAD;
+pub const FF_FFI_SOURCE_NOTE_REPEAT: u16 = abi_rs::FF_EVENT_SOURCE_NOTE_REPEAT;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FfFfiMidiMessage {
+    pub kind: u32,
+    pub channel: u8,
+    pub data1: u8,
+    pub data2: u8,
+    pub reserved: u8,
+}
+
+#[derive(Debug)]
+pub struct FfFfiSequencer {
+    sequencer: Sequencer,
+}
+
+#[derive(Debug)]
+pub struct FfFfiSmoother {
+    smoother: ParameterSmoother,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+fn set_last_error(message: &str) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message);
+}
+
+// Every export runs its body through here so a panic never unwinds into
+// the host: it is reported through the last error and the export returns
+// `on_panic` instead.
+fn guard<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown panic");
+        set_last_error(&format!("panic: {message}"));
+        on_panic
+    })
+}
+
+unsafe fn text_argument<'a>(text: *const c_char) -> Result<&'a str, i32> {
+    if text.is_null() {
+        set_last_error("text pointer is null");
+        return Err(FF_FFI_ERROR_NULL_POINTER);
+    }
+    CStr::from_ptr(text).to_str().map_err(|_| {
+        set_last_error("text is not valid UTF-8");
+        FF_FFI_ERROR_INVALID_ARGUMENT
+    })
+}
+
+fn load_project(text: &str) -> Result<presets_rs::Project, i32> {
+    presets_rs::load_project_from_text(text).map_err(|error| {
+        set_last_error(&error);
+        FF_FFI_ERROR_PARSE
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn ff_ffi_v1_library_version() -> u32 {
+    guard(0, || (FF_FFI_VERSION_MAJOR << 16) | FF_FFI_VERSION_MINOR)
+}
+
+#[no_mangle]
+pub extern "C" fn ff_ffi_v1_abi_version() -> u32 {
+    guard(0, || {
+        (abi_rs::FF_ABI_VERSION_MAJOR << 16) | abi_rs::FF_ABI_VERSION_MINOR
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn ff_ffi_v1_last_error() -> *const c_char {
+    guard(std::ptr::null(), || {
+        LAST_ERROR.with(|last_error| last_error.borrow().as_ptr())
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn ff_ffi_v1_sequencer_create(sample_rate_hz: u32) -> *mut FfFfiSequencer {
+    guard(std::ptr::null_mut(), || {
+        Box::into_raw(Box::new(FfFfiSequencer {
+            sequencer: Sequencer::new(sample_rate_hz),
+        }))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ff_ffi_v1_sequencer_create_from_project(
+    project_text: *const c_char,
+    sample_rate_hz: u32,
+) -> *mut FfFfiSequencer {
+    guard(std::ptr::null_mut(), || {
+        let recall = text_argument(project_text)
+            .and_then(load_project)
+            .and_then(|project| {
+                control_rs::recall_state_from_project(&project, sample_rate_hz).map_err(|error| {
+                    set_last_error(&error);
+                    FF_FFI_ERROR_INVALID_ARGUMENT
+                })
+            });
+
+        match recall {
+            Ok(recall) => Box::into_raw(Box::new(FfFfiSequencer {
+                sequencer: recall.into_sequencer(),
+            })),
+            Err(_) => std::ptr::null_mut(),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ff_ffi_v1_sequencer_destroy(sequencer: *mut FfFfiSequencer) {
+    guard((), || {
+        if !sequencer.is_null() {
+            drop(Box::from_raw(sequencer));
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ff_ffi_v1_sequencer_start(sequencer: *mut FfFfiSequencer) -> i32 {
+    guard(FF_FFI_ERROR_PANIC, || match sequencer.as_mut() {
+        Some(handle) => {
+            handle.sequencer.start();
+            FF_FFI_OK
+        }
+        None => FF_FFI_ERROR_NULL_POINTER,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ff_ffi_v1_sequencer_stop(sequencer: *mut FfFfiSequencer) -> i32 {
+    guard(FF_FFI_ERROR_PANIC, || match sequencer.as_mut() {
+        Some(handle) => {
+            handle.sequencer.stop();
+            FF_FFI_OK
+        }
+        None => FF_FFI_ERROR_NULL_POINTER,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ff_ffi_v1_sequencer_reset(sequencer: *mut FfFfiSequencer) -> i32 {
+    guard(FF_FFI_ERROR_PANIC, || match sequencer.as_mut() {
+        Some(handle) => {
+            handle.sequencer.reset();
+            FF_FFI_OK
+        }
+        None => FF_FFI_ERROR_NULL_POINTER,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ff_ffi_v1_sequencer_set_tempo_bpm(
+    sequencer: *mut FfFfiSequencer,
+    bpm: f32,
+) -> i32 {
+    guard(FF_FFI_ERROR_PANIC, || match sequencer.as_mut() {
+        Some(handle) => {
+            handle.sequencer.set_tempo_bpm(bpm);
+            FF_FFI_OK
+        }
+        None => FF_FFI_ERROR_NULL_POINTER,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ff_ffi_v1_sequencer_set_sample_rate(
+    sequencer: *mut FfFfiSequencer,
+    sample_rate_hz: u32,
+) -> i32 {
+    guard(FF_FFI_ERROR_PANIC, || {
+        let Some(handle) = sequencer.as_mut() else {
+            return FF_FFI_ERROR_NULL_POINTER;
+        };
+        if handle.sequencer.set_sample_rate(sample_rate_hz) {
+            FF_FFI_OK
+        } else {
+            FF_FFI_ERROR_INVALID_ARGUMENT
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ff_ffi_v1_sequencer_set_swing(
+    sequencer: *mut FfFfiSequencer,
+    swing: f32,
+) -> i32 {
+    guard(FF_FFI_ERROR_PANIC, || match sequencer.as_mut() {
+        Some(handle) => {
+            handle.sequencer.set_swing(swing);
+            FF_FFI_OK
+        }
+        None => FF_FFI_ERROR_NULL_POINTER,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ff_ffi_v1_sequencer_set_track_choke_group(
+    sequencer: *mut FfFfiSequencer,
+    track_index: u32,
+    choke_group: i32,
+) -> i32 {
+    guard(FF_FFI_ERROR_PANIC, || {
+        let Some(handle) = sequencer.as_mut() else {
+            return FF_FFI_ERROR_NULL_POINTER;
+        };
+        let choke_group = u8::try_from(choke_group).ok();
+        if handle
+            .sequencer
+            .set_track_choke_group(track_index as usize, choke_group)
+        {
+            FF_FFI_OK
+        } else {
+            FF_FFI_ERROR_INVALID_ARGUMENT
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ff_ffi_v1_sequencer_set_step(
+    sequencer: *mut FfFfiSequencer,
+    track_index: u32,
+    step_index: u32,
+    active: bool,
+    velocity: u8,
+) -> i32 {
+    guard(FF_FFI_ERROR_PANIC, || {
+        let Some(handle) = sequencer.as_mut() else {
+            return FF_FFI_ERROR_NULL_POINTER;
+        };
+        let step = Step {
+            active,
+            velocity: velocity.min(127),
+            ..Step::default()
+        };
+        if handle
+            .sequencer
+            .edit_step(track_index as usize, step_index as usize, step)
+        {
+            FF_FFI_OK
+        } else {
+            FF_FFI_ERROR_INVALID_ARGUMENT
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ff_ffi_v1_sequencer_set_edit_preview(
+    sequencer: *mut FfFfiSequencer,
+    enabled: bool,
+) -> i32 {
+    guard(FF_FFI_ERROR_PANIC, || match sequencer.as_mut() {
+        Some(handle) => {
+            handle.sequencer.set_edit_preview(enabled);
+            FF_FFI_OK
+        }
+        None => FF_FFI_ERROR_NULL_POINTER,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ff_ffi_v1_sequencer_process_block(
+    sequencer: *mut FfFfiSequencer,
+    frames: u32,
+    out_events: *mut FfEvent,
+    event_capacity: usize,
+) -> isize {
+    guard(FF_FFI_ERROR_PANIC as isize, || {
+        let Some(handle) = sequencer.as_mut() else {
+            return FF_FFI_ERROR_NULL_POINTER as isize;
+        };
+        if out_events.is_null() && event_capacity > 0 {
+            return FF_FFI_ERROR_NULL_POINTER as isize;
+        }
+
+        let events = handle.sequencer.process_block(frames);
+        for (index, event) in events.iter().take(event_capacity).enumerate() {
+            out_events.add(index).write(FfEvent {
+                timeline_sample: event.timeline_sample,
+                block_offset: event.block_offset,
+                source_id: event.source.id(),
+                reserved: 0,
+                event_type: FF_EVENT_TYPE_TRIGGER,
+                payload: FfEventPayload {
+                    trigger: FfTriggerEvent {
+                        track_index: event.track_index,
+                        step_index: event.step_index,
+                        reserved: 0,
+                        velocity: f32::from(event.velocity) / 127.0,
+                    },
+                },
+            });
+        }
+        events.len() as isize
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ff_ffi_v1_project_parameter_updates(
+    project_text: *const c_char,
+    sample_rate_hz: u32,
+    out_updates: *mut FfParameterUpdate,
+    update_capacity: usize,
+) -> isize {
+    guard(FF_FFI_ERROR_PANIC as isize, || {
+        if out_updates.is_null() && update_capacity > 0 {
+            return FF_FFI_ERROR_NULL_POINTER as isize;
+        }
+
+        let recall = text_argument(project_text)
+            .and_then(load_project)
+            .and_then(|project| {
+                control_rs::engine_recall_from_project(&project, sample_rate_hz).map_err(|error| {
+                    set_last_error(&error);
+                    FF_FFI_ERROR_INVALID_ARGUMENT
+                })
+            });
+        let recall = match recall {
+            Ok(recall) => recall,
+            Err(code) => return code as isize,
+        };
+
+        for (index, update) in recall
+            .parameter_updates
+            .iter()
+            .take(update_capacity)
+            .enumerate()
+        {
+            out_updates.add(index).write(*update);
+        }
+        recall.parameter_updates.len() as isize
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ff_ffi_v1_project_sample_id(
+    project_text: *const c_char,
+    track_index: u32,
+    out_text: *mut c_char,
+    text_capacity: usize,
+) -> isize {
+    guard(FF_FFI_ERROR_PANIC as isize, || {
+        if out_text.is_null() && text_capacity > 0 {
+            return FF_FFI_ERROR_NULL_POINTER as isize;
+        }
+        if track_index as usize >= TRACK_COUNT {
+            return FF_FFI_ERROR_INVALID_ARGUMENT as isize;
+        }
+
+        let recall = text_argument(project_text)
+            .and_then(load_project)
+            .and_then(|project| {
+                control_rs::engine_recall_from_project(&project, 48_000).map_err(|error| {
+                    set_last_error(&error);
+                    FF_FFI_ERROR_INVALID_ARGUMENT
+                })
+            });
+        let recall = match recall {
+            Ok(recall) => recall,
+            Err(code) => return code as isize,
+        };
+
+        let sample_id = recall
+            .sample_assignments
+            .iter()
+            .find(|assignment| u32::from(assignment.track_index) == track_index)
+            .map(|assignment| assignment.sample_id.as_bytes())
+            .unwrap_or_default();
+        if text_capacity > 0 {
+            let copied = sample_id.len().min(text_capacity - 1);
+            std::ptr::copy_nonoverlapping(sample_id.as_ptr().cast::<c_char>(), out_text, copied);
+            out_text.add(copied).write(0);
+        }
+        sample_id.len() as isize
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ff_ffi_v1_parse_midi(
+    bytes: *const u8,
+    length: usize,
+    out_message: *mut FfFfiMidiMessage,
+) -> i32 {
+    guard(FF_FFI_ERROR_PANIC, || {
+        if bytes.is_null() || out_message.is_null() {
+            return FF_FFI_ERROR_NULL_POINTER;
+        }
+
+        let bytes = std::slice::from_raw_parts(bytes, length);
+        let (kind, channel, data1, data2) = match midi_rs::parse_midi_message(bytes) {
+            Some(MidiMessage::NoteOn {
+                channel,
+                note,
+                velocity,
+            }) => (FF_FFI_MIDI_NOTE_ON, channel, note, velocity),
+            Some(MidiMessage::NoteOff {
+                channel,
+                note,
+                velocity,
+            }) => (FF_FFI_MIDI_NOTE_OFF, channel, note, velocity),
+            Some(MidiMessage::ControlChange {
+                channel,
+                controller,
+                value,
+            }) => (FF_FFI_MIDI_CONTROL_CHANGE, channel, controller, value),
+            None => return FF_FFI_ERROR_PARSE,
+        };
+
+        out_message.write(FfFfiMidiMessage {
+            kind,
+            channel,
+            data1,
+            data2,
+            reserved: 0,
+        });
+        FF_FFI_OK
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn ff_ffi_v1_smoother_create(
+    sample_rate_hz: u32,
+    block_frames: u32,
+) -> *mut FfFfiSmoother {
+    guard(std::ptr::null_mut(), || {
+        Box::into_raw(Box::new(FfFfiSmoother {
+            smoother: ParameterSmoother::new(
+                sample_rate_hz,
+                block_frames,
+                SmoothingConfig::default(),
+            ),
+        }))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ff_ffi_v1_smoother_destroy(smoother: *mut FfFfiSmoother) {
+    guard((), || {
+        if !smoother.is_null() {
+            drop(Box::from_raw(smoother));
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ff_ffi_v1_smoother_set_slot_time_ms(
+    smoother: *mut FfFfiSmoother,
+    parameter_slot: u32,
+    time_ms: f32,
+) -> i32 {
+    guard(FF_FFI_ERROR_PANIC, || {
+        let Some(handle) = smoother.as_mut() else {
+            return FF_FFI_ERROR_NULL_POINTER;
+        };
+        if handle
+            .smoother
+            .config_mut()
+            .set_slot_time_ms(parameter_slot, time_ms)
+        {
+            FF_FFI_OK
+        } else {
+            set_last_error("invalid smoothing slot or time");
+            FF_FFI_ERROR_INVALID_ARGUMENT
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ff_ffi_v1_smoother_push(
+    smoother: *mut FfFfiSmoother,
+    update: *const FfParameterUpdate,
+) -> i32 {
+    guard(FF_FFI_ERROR_PANIC, || {
+        let (Some(handle), Some(update)) = (smoother.as_mut(), update.as_ref()) else {
+            return FF_FFI_ERROR_NULL_POINTER;
+        };
+        if handle.smoother.push(*update) {
+            FF_FFI_OK
+        } else {
+            set_last_error("unknown parameter id");
+            FF_FFI_ERROR_INVALID_ARGUMENT
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ff_ffi_v1_smoother_drain(
+    smoother: *mut FfFfiSmoother,
+    out_updates: *mut FfParameterUpdate,
+    update_capacity: usize,
+) -> isize {
+    guard(FF_FFI_ERROR_PANIC as isize, || {
+        let Some(handle) = smoother.as_mut() else {
+            return FF_FFI_ERROR_NULL_POINTER as isize;
+        };
+        if out_updates.is_null() && update_capacity > 0 {
+            return FF_FFI_ERROR_NULL_POINTER as isize;
+        }
+        if update_capacity == 0 {
+            return handle.smoother.pending_len() as isize;
+        }
+        let output = std::slice::from_raw_parts_mut(out_updates, update_capacity);
+        handle.smoother.drain_into(output) as isize
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString};
+
+    use abi_rs::{FfEvent, FfParameterUpdate, FF_EVENT_TYPE_TRIGGER};
+
+    use super::{
+        ff_ffi_v1_last_error, ff_ffi_v1_parse_midi, ff_ffi_v1_project_parameter_updates,
+        ff_ffi_v1_project_sample_id, ff_ffi_v1_sequencer_create,
+        ff_ffi_v1_sequencer_create_from_project, ff_ffi_v1_sequencer_destroy,
+        ff_ffi_v1_sequencer_process_block, ff_ffi_v1_sequencer_set_sample_rate,
+        ff_ffi_v1_sequencer_set_step, ff_ffi_v1_sequencer_start, ff_ffi_v1_smoother_create,
+        ff_ffi_v1_smoother_destroy, ff_ffi_v1_smoother_drain, ff_ffi_v1_smoother_push,
+        ff_ffi_v1_smoother_set_slot_time_ms, guard, FfFfiMidiMessage,
+        FF_FFI_ERROR_INVALID_ARGUMENT, FF_FFI_ERROR_PANIC, FF_FFI_ERROR_PARSE, FF_FFI_MIDI_NOTE_ON,
+        FF_FFI_OK, FF_FFI_SOURCE_LIVE_PAD, FF_FFI_SOURCE_SEQUENCER,
+    };
+
+    const STARTER_PROJECT: &str = include_str!("../../../assets/starter-kit/default.ffproject");
+
+    #[test]
+    fn sequencer_handle_writes_abi_trigger_events() {
+        unsafe {
+            let sequencer = ff_ffi_v1_sequencer_create(48_000);
+            assert_eq!(
+                ff_ffi_v1_sequencer_set_step(sequencer, 3, 0, true, 127),
+                FF_FFI_OK
+            );
+            assert_eq!(
+                ff_ffi_v1_sequencer_set_step(sequencer, 8, 0, true, 127),
+                FF_FFI_ERROR_INVALID_ARGUMENT
+            );
+            let mut events = [FfEvent::default(); 4];
+            let count = ff_ffi_v1_sequencer_process_block(sequencer, 64, events.as_mut_ptr(), 4);
+            assert_eq!(count, 1);
+            assert_eq!(events[0].source_id, FF_FFI_SOURCE_LIVE_PAD);
+            assert_eq!(
+                ff_ffi_v1_sequencer_set_sample_rate(sequencer, 0),
+                FF_FFI_ERROR_INVALID_ARGUMENT
+            );
+            assert_eq!(
+                ff_ffi_v1_sequencer_set_sample_rate(sequencer, 96_000),
+                FF_FFI_OK
+            );
+            assert_eq!(ff_ffi_v1_sequencer_start(sequencer), FF_FFI_OK);
+
+            let count = ff_ffi_v1_sequencer_process_block(sequencer, 256, events.as_mut_ptr(), 4);
+            assert_eq!(count, 1);
+            assert_eq!(events[0].event_type, FF_EVENT_TYPE_TRIGGER);
+            assert_eq!(events[0].source_id, FF_FFI_SOURCE_SEQUENCER);
+            assert_eq!(events[0].payload.trigger.track_index, 3);
+            assert_eq!(events[0].payload.trigger.velocity, 1.0);
+            ff_ffi_v1_sequencer_destroy(sequencer);
+        }
+    }
+
+    #[test]
+    fn project_entry_points_report_sizes_and_errors() {
+        let project = CString::new(STARTER_PROJECT).expect("c string");
+        unsafe {
+            let required = ff_ffi_v1_project_parameter_updates(
+                project.as_ptr(),
+                48_000,
+                std::ptr::null_mut(),
+                0,
+            );
+            assert_eq!(required, 139);
+            let mut updates = vec![FfParameterUpdate::default(); required as usize];
+            assert_eq!(
+                ff_ffi_v1_project_parameter_updates(
+                    project.as_ptr(),
+                    48_000,
+                    updates.as_mut_ptr(),
+                    updates.len()
+                ),
+                139
+            );
+
+            let mut sample_id = [0 as std::ffi::c_char; 64];
+            let length =
+                ff_ffi_v1_project_sample_id(project.as_ptr(), 0, sample_id.as_mut_ptr(), 64);
+            assert!(length > 0);
+            assert_eq!(
+                CStr::from_ptr(sample_id.as_ptr()).to_bytes().len(),
+                length as usize
+            );
+
+            let sequencer = ff_ffi_v1_sequencer_create_from_project(project.as_ptr(), 48_000);
+            assert!(!sequencer.is_null());
+            ff_ffi_v1_sequencer_destroy(sequencer);
+
+            let broken = CString::new("not a project").expect("c string");
+            assert!(ff_ffi_v1_sequencer_create_from_project(broken.as_ptr(), 48_000).is_null());
+            assert!(!CStr::from_ptr(ff_ffi_v1_last_error()).is_empty());
+            assert_eq!(
+                ff_ffi_v1_project_parameter_updates(
+                    broken.as_ptr(),
+                    48_000,
+                    std::ptr::null_mut(),
+                    0
+                ),
+                FF_FFI_ERROR_PARSE as isize
+            );
+        }
+    }
+
+    #[test]
+    fn midi_parse_fills_message_struct() {
+        let mut message = FfFfiMidiMessage::default();
+        unsafe {
+            assert_eq!(
+                ff_ffi_v1_parse_midi([0x91, 36, 100].as_ptr(), 3, &mut message),
+                FF_FFI_OK
+            );
+            assert_eq!(
+                ff_ffi_v1_parse_midi([0xF8].as_ptr(), 1, &mut message),
+                FF_FFI_ERROR_PARSE
+            );
+        }
+        assert_eq!(message.kind, FF_FFI_MIDI_NOTE_ON);
+        assert_eq!(message.channel, 1);
+        assert_eq!(message.data1, 36);
+    }
+
+    #[test]
+    fn smoother_handle_ramps_and_drains_updates() {
+        let update = FfParameterUpdate {
+            parameter_id: abi_rs::ff_track_parameter_id(2, abi_rs::FF_PARAM_SLOT_GAIN).expect("id"),
+            normalized_value: 0.25,
+            ramp_samples: 0,
+            reserved: 0,
+        };
+        unsafe {
+            let smoother = ff_ffi_v1_smoother_create(48_000, 128);
+            assert_eq!(
+                ff_ffi_v1_smoother_set_slot_time_ms(smoother, abi_rs::FF_PARAM_SLOT_GAIN, 4.0),
+                FF_FFI_OK
+            );
+            assert_eq!(
+                ff_ffi_v1_smoother_set_slot_time_ms(smoother, 0, 4.0),
+                FF_FFI_ERROR_INVALID_ARGUMENT
+            );
+            assert_eq!(ff_ffi_v1_smoother_push(smoother, &update), FF_FFI_OK);
+            assert_eq!(
+                ff_ffi_v1_smoother_push(smoother, &FfParameterUpdate::default()),
+                FF_FFI_ERROR_INVALID_ARGUMENT
+            );
+            assert_eq!(
+                ff_ffi_v1_smoother_drain(smoother, std::ptr::null_mut(), 0),
+                1
+            );
+
+            let mut updates = [FfParameterUpdate::default(); 2];
+            assert_eq!(
+                ff_ffi_v1_smoother_drain(smoother, updates.as_mut_ptr(), 2),
+                1
+            );
+            assert_eq!(updates[0].ramp_samples, 256);
+            assert_eq!(
+                ff_ffi_v1_smoother_drain(smoother, std::ptr::null_mut(), 0),
+                0
+            );
+            ff_ffi_v1_smoother_destroy(smoother);
+        }
+    }
+
+    #[test]
+    fn panics_are_caught_at_the_boundary_and_reported() {
+        let status = guard(FF_FFI_ERROR_PANIC, || -> i32 {
+            panic!("step table corrupt")
+        });
+        assert_eq!(status, FF_FFI_ERROR_PANIC);
+        let message = unsafe { CStr::from_ptr(ff_ffi_v1_last_error()) };
+        assert_eq!(message.to_str(), Ok("panic: step table corrupt"));
+        assert!(guard(std::ptr::null_mut::<u8>(), || panic!("no handle")).is_null());
+    }
+}
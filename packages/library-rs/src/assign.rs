@@ -0,0 +1,235 @@
+use std::path::Path;
+
+use presets_rs::{Kit, TrackAssignment, TRACK_COUNT};
+
+use crate::{LibraryEntry, LibraryIndex};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DrumRole {
+    Kick,
+    Snare,
+    Clap,
+    ClosedHat,
+    OpenHat,
+    Tom,
+    Percussion,
+    Cymbal,
+}
+
+impl DrumRole {
+    pub const TRACK_ORDER: [DrumRole; TRACK_COUNT] = [
+        DrumRole::Kick,
+        DrumRole::Snare,
+        DrumRole::Clap,
+        DrumRole::ClosedHat,
+        DrumRole::OpenHat,
+        DrumRole::Tom,
+        DrumRole::Percussion,
+        DrumRole::Cymbal,
+    ];
+
+    pub fn track_index(self) -> u8 {
+        Self::TRACK_ORDER
+            .iter()
+            .position(|role| *role == self)
+            .unwrap_or(0) as u8
+    }
+
+    fn tags(self) -> &'static [&'static str] {
+        match self {
+            DrumRole::Kick => &["kick", "kik", "bd", "bassdrum"],
+            DrumRole::Snare => &["snare", "snr", "sd", "rimshot"],
+            DrumRole::Clap => &["clap", "clp", "cp", "snap"],
+            DrumRole::ClosedHat => &["hat", "hihat", "hh", "chh", "ch"],
+            DrumRole::OpenHat => &["openhat", "ohh", "oh"],
+            DrumRole::Tom => &["tom", "lt", "mt", "ht", "floor"],
+            DrumRole::Percussion => &[
+                "perc", "shaker", "cowbell", "conga", "bongo", "tamb", "clave", "rim", "block",
+            ],
+            DrumRole::Cymbal => &["cymbal", "cym", "crash", "ride", "splash", "china"],
+        }
+    }
+}
+
+fn tokens(text: &str) -> Vec<String> {
+    text.to_ascii_lowercase()
+        .split(|character: char| !character.is_ascii_alphabetic())
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn token_matches(token: &str, tag: &str) -> bool {
+    token == tag || token.strip_suffix('s') == Some(tag) || (tag.len() >= 4 && token.contains(tag))
+}
+
+fn classify_tokens(tokens: &[String]) -> Option<DrumRole> {
+    let has = |role: DrumRole| {
+        tokens
+            .iter()
+            .any(|token| role.tags().iter().any(|tag| token_matches(token, tag)))
+    };
+    if has(DrumRole::OpenHat)
+        || (has(DrumRole::ClosedHat) && tokens.iter().any(|token| token == "open"))
+    {
+        return Some(DrumRole::OpenHat);
+    }
+    [
+        DrumRole::Kick,
+        DrumRole::Snare,
+        DrumRole::Clap,
+        DrumRole::ClosedHat,
+        DrumRole::Cymbal,
+        DrumRole::Tom,
+        DrumRole::Percussion,
+    ]
+    .into_iter()
+    .find(|role| has(*role))
+}
+
+pub fn classify_sample_path(relative_path: &str) -> Option<DrumRole> {
+    let mut components: Vec<&str> = relative_path.split('/').collect();
+    let file_name = components.pop().unwrap_or_default();
+    let stem = file_name
+        .rsplit_once('.')
+        .map_or(file_name, |(stem, _)| stem);
+    classify_tokens(&tokens(stem)).or_else(|| {
+        components
+            .iter()
+            .rev()
+            .find_map(|component| classify_tokens(&tokens(component)))
+    })
+}
+
+pub trait KitAutoAssign {
+    fn auto_assign_from_index(library: &LibraryIndex, folder: &Path) -> Kit;
+}
+
+impl KitAutoAssign for Kit {
+    fn auto_assign_from_index(library: &LibraryIndex, folder: &Path) -> Kit {
+        let mut candidates: Vec<(String, &LibraryEntry)> = library
+            .entries()
+            .filter_map(|entry| {
+                let path = library.entry_path(entry)?;
+                let relative = path.strip_prefix(folder).ok()?;
+                let relative = relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                Some((relative, entry))
+            })
+            .collect();
+        candidates.sort_by(|left, right| left.0.cmp(&right.0));
+
+        let mut slots: [Option<&LibraryEntry>; TRACK_COUNT] = [None; TRACK_COUNT];
+        let mut leftovers = Vec::new();
+        for (relative, entry) in &candidates {
+            match classify_sample_path(relative) {
+                Some(role) if slots[usize::from(role.track_index())].is_none() => {
+                    slots[usize::from(role.track_index())] = Some(entry);
+                }
+                _ => leftovers.push(*entry),
+            }
+        }
+        let mut leftovers = leftovers.into_iter();
+        for slot in slots.iter_mut().filter(|slot| slot.is_none()) {
+            *slot = leftovers.next();
+        }
+
+        let mut kit = Kit {
+            name: folder
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            ..Kit::default()
+        };
+        for (track_index, entry) in slots.iter().enumerate() {
+            if let Some(entry) = entry {
+                kit.add_assignment(TrackAssignment {
+                    track_index: track_index as u8,
+                    sample_id: entry.sample_id.clone(),
+                });
+            }
+        }
+        kit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use presets_rs::Kit;
+
+    use super::{classify_sample_path, DrumRole, KitAutoAssign};
+    use crate::LibraryIndex;
+
+    #[test]
+    fn file_names_and_folders_classify_into_roles() {
+        assert_eq!(classify_sample_path("BD_909.wav"), Some(DrumRole::Kick));
+        assert_eq!(classify_sample_path("Snare 02.wav"), Some(DrumRole::Snare));
+        assert_eq!(
+            classify_sample_path("hats/open-hat.wav"),
+            Some(DrumRole::OpenHat)
+        );
+        assert_eq!(
+            classify_sample_path("hats/closed_01.wav"),
+            Some(DrumRole::ClosedHat)
+        );
+        assert_eq!(
+            classify_sample_path("CrashLong.wav"),
+            Some(DrumRole::Cymbal)
+        );
+        assert_eq!(classify_sample_path("misc/texture.wav"), None);
+    }
+
+    #[test]
+    fn folder_samples_fill_tracks_by_role_then_leftovers() {
+        let root = std::env::temp_dir().join(format!("ff-library-assign-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let folder = root.join("808");
+        std::fs::create_dir_all(folder.join("hats")).expect("create dir");
+        std::fs::create_dir_all(root.join("other")).expect("create dir");
+        let files = [
+            ("808/kick.wav", 1),
+            ("808/snare.wav", 2),
+            ("808/hats/hh_closed.wav", 3),
+            ("808/texture.wav", 4),
+            ("808/kick_alt.wav", 5),
+            ("other/clap.wav", 6),
+        ];
+        for (path, seed) in files {
+            std::fs::write(root.join(path), crate::tests::wav_bytes(&[seed; 8], 48_000))
+                .expect("write");
+        }
+
+        let mut library = LibraryIndex::new();
+        library.add_root(&root);
+        library.scan();
+        let sample_id = |path: &str| {
+            library
+                .resolve(path)
+                .map(|entry| entry.sample_id.clone())
+                .expect("indexed")
+        };
+
+        let kit = Kit::auto_assign_from_index(&library, &folder);
+        assert_eq!(kit.name, "808");
+        let assigned: Vec<(u8, String)> = kit
+            .tracks
+            .iter()
+            .map(|track| (track.track_index, track.sample_id.clone()))
+            .collect();
+        assert_eq!(
+            assigned,
+            vec![
+                (0, sample_id("808/kick.wav")),
+                (1, sample_id("808/snare.wav")),
+                (2, sample_id("808/kick_alt.wav")),
+                (3, sample_id("808/hats/hh_closed.wav")),
+                (4, sample_id("808/texture.wav")),
+            ]
+        );
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
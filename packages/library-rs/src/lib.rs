@@ -0,0 +1,587 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use presets_rs::{decode_text, encode_text, Kit};
+use sha2::{Digest, Sha256};
+
+pub mod assign;
+pub mod pack;
+
+pub const SAMPLE_ID_PREFIX: &str = "sha256:";
+pub const LIBRARY_EXTENSIONS: [&str; 2] = ["wav", "wave"];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct WavMetadata {
+    pub format_tag: u16,
+    pub channels: u16,
+    pub sample_rate_hz: u32,
+    pub bits_per_sample: u16,
+    pub frame_count: u64,
+}
+
+impl WavMetadata {
+    pub fn duration_seconds(&self) -> f64 {
+        self.frame_count as f64 / f64::from(self.sample_rate_hz.max(1))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LibraryEntry {
+    pub sample_id: String,
+    pub root_index: usize,
+    pub relative_path: String,
+    pub byte_length: u64,
+    pub modified_secs: u64,
+    pub metadata: WavMetadata,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ScanReport {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: usize,
+    pub duplicates: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LibraryIndex {
+    roots: Vec<PathBuf>,
+    entries: BTreeMap<String, LibraryEntry>,
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, String> {
+    bytes
+        .get(offset..offset + 2)
+        .map(|value| u16::from_le_bytes([value[0], value[1]]))
+        .ok_or_else(|| "truncated wav header".to_string())
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, String> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|value| u32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+        .ok_or_else(|| "truncated wav header".to_string())
+}
+
+pub fn read_wav_metadata(bytes: &[u8]) -> Result<WavMetadata, String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("not a RIFF/WAVE file".to_string());
+    }
+
+    let mut format: Option<(u16, u16, u32, u16)> = None;
+    let mut data_length: Option<u64> = None;
+    let mut cursor = 12;
+    while cursor + 8 <= bytes.len() {
+        let chunk_id = &bytes[cursor..cursor + 4];
+        let chunk_size = read_u32(bytes, cursor + 4)? as usize;
+        let body_start = cursor + 8;
+        let body_end = body_start.saturating_add(chunk_size);
+
+        if chunk_id == b"fmt " {
+            let body = bytes
+                .get(body_start..body_end)
+                .ok_or_else(|| "truncated wav fmt chunk".to_string())?;
+            if body.len() < 16 {
+                return Err("wav fmt chunk too small".to_string());
+            }
+            format = Some((
+                read_u16(body, 0)?,
+                read_u16(body, 2)?,
+                read_u32(body, 4)?,
+                read_u16(body, 14)?,
+            ));
+        } else if chunk_id == b"data" {
+            let available = bytes.len().saturating_sub(body_start);
+            data_length = Some(chunk_size.min(available) as u64);
+        }
+
+        cursor = body_end.saturating_add(chunk_size % 2);
+    }
+
+    let (format_tag, channels, sample_rate_hz, bits_per_sample) =
+        format.ok_or_else(|| "wav file has no fmt chunk".to_string())?;
+    let data_length = data_length.ok_or_else(|| "wav file has no data chunk".to_string())?;
+    if channels == 0 || sample_rate_hz == 0 || bits_per_sample == 0 {
+        return Err("wav format has zero channels, rate or bit depth".to_string());
+    }
+
+    let frame_bytes = u64::from(channels) * u64::from(bits_per_sample).div_ceil(8);
+    Ok(WavMetadata {
+        format_tag,
+        channels,
+        sample_rate_hz,
+        bits_per_sample,
+        frame_count: data_length / frame_bytes,
+    })
+}
+
+pub fn content_sample_id(bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+    let mut sample_id = String::with_capacity(SAMPLE_ID_PREFIX.len() + digest.len() * 2);
+    sample_id.push_str(SAMPLE_ID_PREFIX);
+    for byte in digest.iter() {
+        sample_id.push_str(&format!("{byte:02x}"));
+    }
+    sample_id
+}
+
+fn is_library_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            LIBRARY_EXTENSIONS
+                .iter()
+                .any(|candidate| extension.eq_ignore_ascii_case(candidate))
+        })
+}
+
+fn collect_files(directory: &Path, files: &mut Vec<PathBuf>, errors: &mut Vec<String>) {
+    let entries = match std::fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(error) => {
+            errors.push(format!("failed to read {}: {error}", directory.display()));
+            return;
+        }
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            collect_files(&path, files, errors);
+        } else if is_library_file(&path) {
+            files.push(path);
+        }
+    }
+}
+
+fn relative_text(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+impl LibraryIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    pub fn add_root(&mut self, root: impl Into<PathBuf>) -> usize {
+        let root = root.into();
+        match self.roots.iter().position(|existing| *existing == root) {
+            Some(index) => index,
+            None => {
+                self.roots.push(root);
+                self.roots.len() - 1
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &LibraryEntry> {
+        self.entries.values()
+    }
+
+    pub fn entry_path(&self, entry: &LibraryEntry) -> Option<PathBuf> {
+        self.roots
+            .get(entry.root_index)
+            .map(|root| root.join(&entry.relative_path))
+    }
+
+    pub fn scan(&mut self) -> ScanReport {
+        let mut report = ScanReport::default();
+        let mut previous: BTreeMap<(usize, String), LibraryEntry> =
+            std::mem::take(&mut self.entries)
+                .into_values()
+                .map(|entry| ((entry.root_index, entry.relative_path.clone()), entry))
+                .collect();
+
+        for (root_index, root) in self.roots.iter().enumerate() {
+            let mut files = Vec::new();
+            collect_files(root, &mut files, &mut report.errors);
+            for path in files {
+                let relative_path = relative_text(root, &path);
+                let Ok(file_metadata) = std::fs::metadata(&path) else {
+                    report
+                        .errors
+                        .push(format!("failed to stat {}", path.display()));
+                    continue;
+                };
+                let modified_secs = file_metadata
+                    .modified()
+                    .ok()
+                    .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                    .map_or(0, |duration| duration.as_secs());
+                let key = (root_index, relative_path.clone());
+
+                let known = previous.remove(&key);
+                if let Some(existing) = known.as_ref().filter(|existing| {
+                    existing.byte_length == file_metadata.len()
+                        && existing.modified_secs == modified_secs
+                }) {
+                    report.unchanged += 1;
+                    self.entries
+                        .insert(existing.sample_id.clone(), existing.clone());
+                    continue;
+                }
+
+                let bytes = match std::fs::read(&path) {
+                    Ok(bytes) => bytes,
+                    Err(error) => {
+                        report
+                            .errors
+                            .push(format!("failed to read {}: {error}", path.display()));
+                        continue;
+                    }
+                };
+                match read_wav_metadata(&bytes) {
+                    Ok(metadata) => {
+                        let sample_id = content_sample_id(&bytes);
+                        if self.entries.contains_key(&sample_id) {
+                            report.duplicates.push(relative_path);
+                            continue;
+                        }
+                        if known.is_some() {
+                            report.updated.push(relative_path.clone());
+                        } else {
+                            report.added.push(relative_path.clone());
+                        }
+                        self.entries.insert(
+                            sample_id.clone(),
+                            LibraryEntry {
+                                sample_id,
+                                root_index,
+                                relative_path,
+                                byte_length: bytes.len() as u64,
+                                modified_secs,
+                                metadata,
+                            },
+                        );
+                    }
+                    Err(error) => report.errors.push(format!("{}: {error}", path.display())),
+                }
+            }
+        }
+
+        report.removed = previous.into_keys().map(|(_, path)| path).collect();
+        report
+    }
+
+    pub fn get(&self, sample_id: &str) -> Option<&LibraryEntry> {
+        self.entries.get(sample_id)
+    }
+
+    pub fn resolve(&self, sample_id: &str) -> Option<&LibraryEntry> {
+        if let Some(entry) = self.entries.get(sample_id) {
+            return Some(entry);
+        }
+        let normalized = sample_id.replace('\\', "/");
+        self.entries
+            .values()
+            .find(|entry| entry.relative_path == normalized)
+            .or_else(|| {
+                let mut by_name = self.entries.values().filter(|entry| {
+                    entry.relative_path.rsplit('/').next() == normalized.rsplit('/').next()
+                });
+                let first = by_name.next();
+                by_name.next().is_none().then_some(first).flatten()
+            })
+    }
+
+    pub fn resolve_path(&self, sample_id: &str) -> Option<PathBuf> {
+        self.resolve(sample_id)
+            .and_then(|entry| self.entry_path(entry))
+    }
+
+    pub fn unresolved_samples(&self, kit: &Kit) -> Vec<String> {
+        kit.tracks
+            .iter()
+            .filter(|assignment| self.resolve(&assignment.sample_id).is_none())
+            .map(|assignment| assignment.sample_id.clone())
+            .collect()
+    }
+
+    pub fn rewrite_kit_ids(&self, kit: &mut Kit) -> usize {
+        let mut rewritten = 0;
+        for assignment in &mut kit.tracks {
+            if let Some(entry) = self.resolve(&assignment.sample_id) {
+                if entry.sample_id != assignment.sample_id {
+                    assignment.sample_id = entry.sample_id.clone();
+                    rewritten += 1;
+                }
+            }
+        }
+        rewritten
+    }
+
+    pub fn save_to_text(&self) -> String {
+        let mut lines = vec!["FF_LIBRARY_V1".to_string()];
+        for root in &self.roots {
+            lines.push(format!("root|{}", encode_text(&root.to_string_lossy())));
+        }
+        for entry in self.entries.values() {
+            let metadata = &entry.metadata;
+            lines.push(format!(
+                "sample|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}",
+                entry.sample_id,
+                entry.root_index,
+                encode_text(&entry.relative_path),
+                entry.byte_length,
+                entry.modified_secs,
+                metadata.format_tag,
+                metadata.channels,
+                metadata.sample_rate_hz,
+                metadata.bits_per_sample,
+                metadata.frame_count,
+            ));
+        }
+        lines.join("\n")
+    }
+
+    pub fn load_from_text(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines();
+        let header = lines
+            .next()
+            .ok_or_else(|| "missing library header".to_string())?;
+        if header != "FF_LIBRARY_V1" {
+            return Err(format!("unexpected library header: {header}"));
+        }
+
+        let mut index = Self::default();
+        for line in lines {
+            if let Some(root) = line.strip_prefix("root|") {
+                index.roots.push(PathBuf::from(decode_text(root)?));
+                continue;
+            }
+
+            let Some(rest) = line.strip_prefix("sample|") else {
+                return Err(format!("unknown library line: {line}"));
+            };
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 10 || !fields[0].starts_with(SAMPLE_ID_PREFIX) {
+                return Err(format!("invalid sample line: {line}"));
+            }
+            let root_index = parse_field(fields[1], "root_index")?;
+            if root_index >= index.roots.len() {
+                return Err(format!("sample root out of range: {line}"));
+            }
+            let entry = LibraryEntry {
+                sample_id: fields[0].to_string(),
+                root_index,
+                relative_path: decode_text(fields[2])?,
+                byte_length: parse_field(fields[3], "byte_length")?,
+                modified_secs: parse_field(fields[4], "modified_secs")?,
+                metadata: WavMetadata {
+                    format_tag: parse_field(fields[5], "format_tag")?,
+                    channels: parse_field(fields[6], "channels")?,
+                    sample_rate_hz: parse_field(fields[7], "sample_rate_hz")?,
+                    bits_per_sample: parse_field(fields[8], "bits_per_sample")?,
+                    frame_count: parse_field(fields[9], "frame_count")?,
+                },
+            };
+            index.entries.insert(entry.sample_id.clone(), entry);
+        }
+        Ok(index)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.save_to_text())
+            .map_err(|error| format!("failed to write {}: {error}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+        Self::load_from_text(&text)
+    }
+}
+
+fn parse_field<T: std::str::FromStr>(value: &str, field: &str) -> Result<T, String> {
+    value
+        .parse::<T>()
+        .map_err(|_| format!("invalid value for {field}: {value}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use presets_rs::{Kit, Project, TrackAssignment};
+
+    use super::pack::{pack_project, unpack_project, ProjectArchive};
+    use super::{content_sample_id, read_wav_metadata, LibraryIndex};
+
+    pub(crate) fn wav_bytes(samples: &[i16], sample_rate_hz: u32) -> Vec<u8> {
+        let data_length = (samples.len() * 2) as u32;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_length).to_le_bytes());
+        bytes.extend_from_slice(b"WAVEfmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&sample_rate_hz.to_le_bytes());
+        bytes.extend_from_slice(&(sample_rate_hz * 2).to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_length.to_le_bytes());
+        for sample in samples {
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("ff-library-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(directory.join("drums/kicks")).expect("create dir");
+        directory
+    }
+
+    #[test]
+    fn metadata_and_content_ids_are_stable() {
+        let kick = wav_bytes(&[0, 1_000, -1_000, 0], 44_100);
+        let metadata = read_wav_metadata(&kick).expect("metadata");
+        assert_eq!(metadata.sample_rate_hz, 44_100);
+        assert_eq!(metadata.frame_count, 4);
+        assert_eq!(content_sample_id(&kick), content_sample_id(&kick.clone()));
+        assert_ne!(
+            content_sample_id(&kick),
+            content_sample_id(&wav_bytes(&[0, 1_000, -1_000, 1], 44_100))
+        );
+        assert!(content_sample_id(&kick).starts_with("sha256:"));
+        assert!(read_wav_metadata(b"RIFF....WAVE").is_err());
+    }
+
+    #[test]
+    fn scan_tracks_changes_persists_and_resolves_kits() {
+        let root = scratch_dir("scan");
+        let kick = wav_bytes(&[0, 8_000, -8_000, 0], 48_000);
+        std::fs::write(root.join("drums/kicks/kick.wav"), &kick).expect("write");
+        std::fs::write(root.join("drums/snare.WAV"), wav_bytes(&[5; 16], 48_000)).expect("write");
+        std::fs::write(root.join("drums/copy.wav"), &kick).expect("write");
+        std::fs::write(root.join("notes.txt"), "not audio").expect("write");
+
+        let mut index = LibraryIndex::new();
+        index.add_root(&root);
+        let report = index.scan();
+        assert_eq!(report.added.len(), 2);
+        assert_eq!(report.duplicates, vec!["drums/kicks/kick.wav".to_string()]);
+        assert_eq!(index.len(), 2);
+
+        let rescan = index.scan();
+        assert_eq!(rescan.unchanged, 2);
+        assert!(rescan.added.is_empty());
+        assert!(rescan.removed.is_empty());
+
+        let reloaded = LibraryIndex::load_from_text(&index.save_to_text()).expect("load");
+        assert_eq!(reloaded, index);
+
+        let mut kit = Kit::default();
+        kit.add_assignment(TrackAssignment {
+            track_index: 0,
+            sample_id: "snare.WAV".to_string(),
+        });
+        kit.add_assignment(TrackAssignment {
+            track_index: 1,
+            sample_id: "missing.wav".to_string(),
+        });
+        assert_eq!(
+            index.unresolved_samples(&kit),
+            vec!["missing.wav".to_string()]
+        );
+        assert_eq!(index.rewrite_kit_ids(&mut kit), 1);
+        assert_eq!(
+            index.resolve_path(&kit.tracks[0].sample_id),
+            Some(root.join("drums/snare.WAV"))
+        );
+
+        std::fs::remove_file(root.join("drums/snare.WAV")).expect("remove");
+        assert_eq!(index.scan().removed, vec!["drums/snare.WAV".to_string()]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn packed_project_dedupes_samples_and_relinks_on_unpack() {
+        let root = scratch_dir("pack");
+        let kick = wav_bytes(&[0, 9_000, -9_000, 0], 48_000);
+        std::fs::write(root.join("kick.wav"), &kick).expect("write");
+        std::fs::write(root.join("drums/kick_copy.wav"), &kick).expect("write");
+        std::fs::write(
+            root.join("drums/kicks/snare.wav"),
+            wav_bytes(&[3; 8], 48_000),
+        )
+        .expect("write");
+
+        let mut first = Kit::default();
+        first.add_assignment(TrackAssignment {
+            track_index: 0,
+            sample_id: "kick.wav".to_string(),
+        });
+        first.add_assignment(TrackAssignment {
+            track_index: 1,
+            sample_id: "drums/kicks/snare.wav".to_string(),
+        });
+        let mut second = Kit::default();
+        second.add_assignment(TrackAssignment {
+            track_index: 0,
+            sample_id: "drums/kick_copy.wav".to_string(),
+        });
+        let project = Project {
+            name: "beat".to_string(),
+            kits: vec![first, second],
+            active_kit: Some(0),
+            patterns: vec![Default::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        let project_path = root.join("beat.ffproject");
+        std::fs::write(&project_path, presets_rs::save_project_to_text(&project)).expect("write");
+
+        let archive = pack_project(&project_path, None).expect("pack");
+        assert_eq!(archive.samples.len(), 2);
+        assert_eq!(
+            archive.project.kits[0].tracks[0].sample_id,
+            archive.project.kits[1].tracks[0].sample_id
+        );
+        let bytes = archive.to_bytes();
+        assert_eq!(ProjectArchive::from_bytes(&bytes).expect("decode"), archive);
+
+        let archive_path = root.join("beat.ffpack");
+        std::fs::write(&archive_path, &bytes).expect("write");
+        let (unpacked_path, unpacked) =
+            unpack_project(&archive_path, &root.join("shared")).expect("unpack");
+        let relinked = &unpacked.kits[0].tracks[0].sample_id;
+        assert!(relinked.starts_with("samples/"));
+        let restored =
+            std::fs::read(unpacked_path.parent().expect("dir").join(relinked)).expect("sample");
+        assert_eq!(restored, kick);
+
+        std::fs::remove_file(root.join("kick.wav")).expect("remove");
+        assert!(pack_project(&project_path, None)
+            .expect_err("missing")
+            .contains("kick.wav"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
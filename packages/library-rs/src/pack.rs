@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use presets_rs::Project;
+
+use crate::{content_sample_id, LibraryIndex, SAMPLE_ID_PREFIX};
+
+pub const ARCHIVE_MAGIC: &[u8; 4] = b"FFPK";
+pub const ARCHIVE_VERSION: u16 = 1;
+pub const ARCHIVE_PROJECT_ENTRY: &str = "project.ffproject";
+pub const ARCHIVE_SAMPLE_DIR: &str = "samples";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProjectArchive {
+    pub project: Project,
+    pub samples: BTreeMap<String, Vec<u8>>,
+}
+
+fn sample_entry_name(sample_id: &str) -> String {
+    let hash = sample_id
+        .strip_prefix(SAMPLE_ID_PREFIX)
+        .unwrap_or(sample_id);
+    format!("{ARCHIVE_SAMPLE_DIR}/{hash}.wav")
+}
+
+fn read_bytes<'a>(bytes: &'a [u8], cursor: &mut usize, length: usize) -> Result<&'a [u8], String> {
+    let end = cursor
+        .checked_add(length)
+        .filter(|end| *end <= bytes.len())
+        .ok_or_else(|| "truncated project archive".to_string())?;
+    let slice = &bytes[*cursor..end];
+    *cursor = end;
+    Ok(slice)
+}
+
+impl ProjectArchive {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut entries = vec![(
+            ARCHIVE_PROJECT_ENTRY.to_string(),
+            presets_rs::save_project_to_text(&self.project).into_bytes(),
+        )];
+        for (sample_id, bytes) in &self.samples {
+            entries.push((sample_entry_name(sample_id), bytes.clone()));
+        }
+
+        let mut output = Vec::new();
+        output.extend_from_slice(ARCHIVE_MAGIC);
+        output.extend_from_slice(&ARCHIVE_VERSION.to_le_bytes());
+        output.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        for (name, data) in entries {
+            output.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            output.extend_from_slice(name.as_bytes());
+            output.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            output.extend_from_slice(&data);
+        }
+        output
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < 10 || &bytes[0..4] != ARCHIVE_MAGIC {
+            return Err("not a ffpack project archive".to_string());
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != ARCHIVE_VERSION {
+            return Err(format!("unsupported archive version: {version}"));
+        }
+        let entry_count = u32::from_le_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]);
+
+        let mut cursor = 10;
+        let mut project = None;
+        let mut samples = BTreeMap::new();
+        for _ in 0..entry_count {
+            let name_length = read_bytes(bytes, &mut cursor, 2)?;
+            let name_length = usize::from(u16::from_le_bytes([name_length[0], name_length[1]]));
+            let name = std::str::from_utf8(read_bytes(bytes, &mut cursor, name_length)?)
+                .map_err(|_| "archive entry name is not utf8".to_string())?
+                .to_string();
+            let mut data_length = [0u8; 8];
+            data_length.copy_from_slice(read_bytes(bytes, &mut cursor, 8)?);
+            let data_length = usize::try_from(u64::from_le_bytes(data_length))
+                .map_err(|_| "archive entry too large".to_string())?;
+            let data = read_bytes(bytes, &mut cursor, data_length)?;
+
+            if name == ARCHIVE_PROJECT_ENTRY {
+                let text = std::str::from_utf8(data)
+                    .map_err(|_| "archived project is not utf8".to_string())?;
+                project = Some(presets_rs::load_project_from_text(text)?);
+                continue;
+            }
+
+            let sample_id = content_sample_id(data);
+            if name != sample_entry_name(&sample_id) {
+                return Err(format!("archive sample hash mismatch: {name}"));
+            }
+            samples.insert(sample_id, data.to_vec());
+        }
+
+        let project = project.ok_or_else(|| "archive has no project entry".to_string())?;
+        for kit in &project.kits {
+            for assignment in &kit.tracks {
+                if !samples.contains_key(&assignment.sample_id) {
+                    return Err(format!(
+                        "archive is missing sample: {}",
+                        assignment.sample_id
+                    ));
+                }
+            }
+        }
+        Ok(Self { project, samples })
+    }
+
+    pub fn unpack(&self, directory: &Path) -> Result<(PathBuf, Project), String> {
+        let sample_dir = directory.join(ARCHIVE_SAMPLE_DIR);
+        std::fs::create_dir_all(&sample_dir)
+            .map_err(|error| format!("failed to create {}: {error}", sample_dir.display()))?;
+        for (sample_id, bytes) in &self.samples {
+            let path = directory.join(sample_entry_name(sample_id));
+            std::fs::write(&path, bytes)
+                .map_err(|error| format!("failed to write {}: {error}", path.display()))?;
+        }
+
+        let mut project = self.project.clone();
+        for kit in &mut project.kits {
+            for assignment in &mut kit.tracks {
+                assignment.sample_id = sample_entry_name(&assignment.sample_id);
+            }
+        }
+        let project_path = directory.join(ARCHIVE_PROJECT_ENTRY);
+        std::fs::write(&project_path, presets_rs::save_project_to_text(&project))
+            .map_err(|error| format!("failed to write {}: {error}", project_path.display()))?;
+        Ok((project_path, project))
+    }
+}
+
+pub fn pack_project(path: &Path, library: Option<&LibraryIndex>) -> Result<ProjectArchive, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+    let project = presets_rs::load_project_from_text(&text)?;
+    let project_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    pack_loaded_project(&project, &project_dir, library)
+}
+
+pub fn pack_loaded_project(
+    project: &Project,
+    project_dir: &Path,
+    library: Option<&LibraryIndex>,
+) -> Result<ProjectArchive, String> {
+    let mut archive = ProjectArchive {
+        project: project.clone(),
+        samples: BTreeMap::new(),
+    };
+    let mut resolved: BTreeMap<String, String> = BTreeMap::new();
+    let mut missing = Vec::new();
+    for kit in &mut archive.project.kits {
+        for assignment in &mut kit.tracks {
+            if let Some(sample_id) = resolved.get(&assignment.sample_id) {
+                assignment.sample_id = sample_id.clone();
+                continue;
+            }
+
+            let path = library
+                .and_then(|library| library.resolve_path(&assignment.sample_id))
+                .unwrap_or_else(|| project_dir.join(&assignment.sample_id));
+            let Ok(bytes) = std::fs::read(&path) else {
+                missing.push(assignment.sample_id.clone());
+                continue;
+            };
+            let sample_id = content_sample_id(&bytes);
+            resolved.insert(assignment.sample_id.clone(), sample_id.clone());
+            archive.samples.entry(sample_id.clone()).or_insert(bytes);
+            assignment.sample_id = sample_id;
+        }
+    }
+
+    if !missing.is_empty() {
+        missing.sort();
+        missing.dedup();
+        return Err(format!(
+            "cannot pack missing samples: {}",
+            missing.join(", ")
+        ));
+    }
+    Ok(archive)
+}
+
+pub fn unpack_project(archive_path: &Path, directory: &Path) -> Result<(PathBuf, Project), String> {
+    let bytes = std::fs::read(archive_path)
+        .map_err(|error| format!("failed to read {}: {error}", archive_path.display()))?;
+    ProjectArchive::from_bytes(&bytes)?.unpack(directory)
+}
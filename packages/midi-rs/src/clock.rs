@@ -0,0 +1,169 @@
+use std::collections::VecDeque;
+
+pub const MIDI_CLOCK_PPQN: u32 = 24;
+pub const MIDI_CLOCK_AVERAGE_WINDOW: usize = 24;
+pub const DEFAULT_CLOCK_STABILITY_THRESHOLD: f64 = 0.1;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MidiRealtime {
+    Clock,
+    Start,
+    Continue,
+    Stop,
+}
+
+pub fn parse_midi_realtime(bytes: &[u8]) -> Option<MidiRealtime> {
+    match bytes.first()? {
+        0xF8 => Some(MidiRealtime::Clock),
+        0xFA => Some(MidiRealtime::Start),
+        0xFB => Some(MidiRealtime::Continue),
+        0xFC => Some(MidiRealtime::Stop),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ClockTransportChange {
+    Start,
+    Continue,
+    Stop,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClockStats {
+    pub mean_interval_samples: f64,
+    pub stddev_samples: f64,
+    pub phase_error_samples: f64,
+    pub intervals: usize,
+}
+
+impl ClockStats {
+    pub fn jitter_ratio(&self) -> f64 {
+        if self.mean_interval_samples > 0.0 {
+            self.stddev_samples / self.mean_interval_samples
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MidiClockFollower {
+    sample_rate_hz: u32,
+    running: bool,
+    last_tick_sample: Option<u64>,
+    tick_intervals: VecDeque<u64>,
+    ticks_since_start: u64,
+    stability_threshold: f64,
+}
+
+impl MidiClockFollower {
+    pub fn new(sample_rate_hz: u32) -> Self {
+        Self {
+            sample_rate_hz: sample_rate_hz.max(1),
+            running: false,
+            last_tick_sample: None,
+            tick_intervals: VecDeque::with_capacity(MIDI_CLOCK_AVERAGE_WINDOW),
+            ticks_since_start: 0,
+            stability_threshold: DEFAULT_CLOCK_STABILITY_THRESHOLD,
+        }
+    }
+
+    pub fn stability_threshold(&self) -> f64 {
+        self.stability_threshold
+    }
+
+    pub fn set_stability_threshold(&mut self, threshold: f64) -> bool {
+        if !threshold.is_finite() || threshold < 0.0 {
+            return false;
+        }
+        self.stability_threshold = threshold;
+        true
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn ticks_since_start(&self) -> u64 {
+        self.ticks_since_start
+    }
+
+    pub fn handle(
+        &mut self,
+        message: MidiRealtime,
+        timestamp_sample: u64,
+    ) -> Option<ClockTransportChange> {
+        match message {
+            MidiRealtime::Clock => {
+                if let Some(last) = self.last_tick_sample {
+                    if timestamp_sample > last {
+                        if self.tick_intervals.len() == MIDI_CLOCK_AVERAGE_WINDOW {
+                            self.tick_intervals.pop_front();
+                        }
+                        self.tick_intervals.push_back(timestamp_sample - last);
+                    }
+                }
+                self.last_tick_sample = Some(timestamp_sample);
+                if self.running {
+                    self.ticks_since_start += 1;
+                }
+                None
+            }
+            MidiRealtime::Start => {
+                self.running = true;
+                self.ticks_since_start = 0;
+                Some(ClockTransportChange::Start)
+            }
+            MidiRealtime::Continue => {
+                self.running = true;
+                Some(ClockTransportChange::Continue)
+            }
+            MidiRealtime::Stop => {
+                self.running = false;
+                Some(ClockTransportChange::Stop)
+            }
+        }
+    }
+
+    pub fn bpm(&self) -> Option<f32> {
+        if self.tick_intervals.is_empty() {
+            return None;
+        }
+
+        let average =
+            self.tick_intervals.iter().sum::<u64>() as f64 / self.tick_intervals.len() as f64;
+        let seconds_per_beat =
+            average * f64::from(MIDI_CLOCK_PPQN) / f64::from(self.sample_rate_hz);
+        Some((60.0 / seconds_per_beat) as f32)
+    }
+
+    // Phase error is how far the latest tick landed from where the window
+    // average predicted it; positive means late.
+    pub fn stats(&self) -> Option<ClockStats> {
+        let last = *self.tick_intervals.back()?;
+        let count = self.tick_intervals.len() as f64;
+        let mean = self.tick_intervals.iter().sum::<u64>() as f64 / count;
+        let variance = self
+            .tick_intervals
+            .iter()
+            .map(|interval| (*interval as f64 - mean).powi(2))
+            .sum::<f64>()
+            / count;
+        Some(ClockStats {
+            mean_interval_samples: mean,
+            stddev_samples: variance.sqrt(),
+            phase_error_samples: last as f64 - mean,
+            intervals: self.tick_intervals.len(),
+        })
+    }
+
+    pub fn is_stable(&self) -> bool {
+        self.stats()
+            .is_some_and(|stats| stats.jitter_ratio() <= self.stability_threshold)
+    }
+
+    pub fn stable_bpm(&self) -> Option<f32> {
+        self.bpm().filter(|_| self.is_stable())
+    }
+}
@@ -1,3 +1,5 @@
+pub mod clock;
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct MidiBinding {
     pub cc: u8,
@@ -66,10 +68,27 @@ pub struct PadTrigger {
     pub velocity: u8,
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NoteLearnProgress {
+    pub learned: u8,
+    pub track_count: u8,
+}
+
+impl NoteLearnProgress {
+    pub fn next_track(&self) -> Option<u8> {
+        (self.learned < self.track_count).then_some(self.learned)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.learned >= self.track_count
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct NoteMap {
     note_to_track: [Option<u8>; 128],
     track_count: u8,
+    learn_all: Option<u8>,
 }
 
 impl NoteMap {
@@ -77,9 +96,48 @@ impl NoteMap {
         Self {
             note_to_track: [None; 128],
             track_count: track_count.max(1),
+            learn_all: None,
         }
     }
 
+    // Learn-all starts from an empty map so pads left over from a previous
+    // controller cannot alias the newly learned ones.
+    pub fn begin_learn_all(&mut self) {
+        self.note_to_track = [None; 128];
+        self.learn_all = Some(0);
+    }
+
+    pub fn cancel_learn_all(&mut self) {
+        self.learn_all = None;
+    }
+
+    pub fn learn_all_progress(&self) -> Option<NoteLearnProgress> {
+        self.learn_all.map(|learned| NoteLearnProgress {
+            learned,
+            track_count: self.track_count,
+        })
+    }
+
+    pub fn handle_message_for_learn_all(
+        &mut self,
+        message: MidiMessage,
+    ) -> Option<NoteLearnProgress> {
+        let learned = self.learn_all?;
+        let MidiMessage::NoteOn { note, velocity, .. } = message else {
+            return None;
+        };
+        if velocity == 0 || self.resolve_track(note).is_some() || !self.bind_note(note, learned) {
+            return None;
+        }
+
+        let progress = NoteLearnProgress {
+            learned: learned + 1,
+            track_count: self.track_count,
+        };
+        self.learn_all = (!progress.is_complete()).then_some(progress.learned);
+        Some(progress)
+    }
+
     pub fn bind_note(&mut self, note: u8, track_index: u8) -> bool {
         if !is_midi_data_byte(note) || track_index >= self.track_count {
             return false;
@@ -104,14 +162,25 @@ impl Default for NoteMap {
     }
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BindingActivity {
+    pub cc: u8,
+    pub value: u8,
+    pub at_sample: u64,
+}
+
 #[derive(Debug, Default)]
 pub struct MappingProfile {
     bindings: Vec<MidiBinding>,
     learn_target: Option<LearnTarget>,
+    activity: Vec<BindingActivity>,
 }
 
 impl MappingProfile {
+    // Rebinding a controller drops its history so the new parameter does not
+    // show activity it never received.
     pub fn bind_cc(&mut self, cc: u8, parameter_id: impl Into<String>) {
+        self.activity.retain(|activity| activity.cc != cc);
         if let Some(existing) = self.bindings.iter_mut().find(|binding| binding.cc == cc) {
             existing.parameter_id = parameter_id.into();
             return;
@@ -130,6 +199,46 @@ impl MappingProfile {
             .map(|binding| binding.parameter_id.as_str())
     }
 
+    // Resolves a bound controller and records its value as the binding's latest
+    // activity; unbound controllers are not tracked.
+    pub fn handle_control_change(&mut self, cc: u8, value: u8, at_sample: u64) -> Option<&str> {
+        let binding = self.bindings.iter().find(|binding| binding.cc == cc)?;
+        let activity = BindingActivity {
+            cc,
+            value,
+            at_sample,
+        };
+        match self.activity.iter_mut().find(|existing| existing.cc == cc) {
+            Some(existing) => *existing = activity,
+            None => self.activity.push(activity),
+        }
+        Some(binding.parameter_id.as_str())
+    }
+
+    pub fn last_activity(&self, cc: u8) -> Option<BindingActivity> {
+        self.activity
+            .iter()
+            .find(|activity| activity.cc == cc)
+            .copied()
+    }
+
+    pub fn last_value(&self, cc: u8) -> Option<u8> {
+        self.last_activity(cc).map(|activity| activity.value)
+    }
+
+    pub fn is_recently_moved(&self, cc: u8, now_sample: u64, window_samples: u64) -> bool {
+        self.last_activity(cc).is_some_and(|activity| {
+            activity.at_sample <= now_sample && now_sample - activity.at_sample <= window_samples
+        })
+    }
+
+    pub fn recently_moved(&self, now_sample: u64, window_samples: u64) -> Vec<&MidiBinding> {
+        self.bindings
+            .iter()
+            .filter(|binding| self.is_recently_moved(binding.cc, now_sample, window_samples))
+            .collect()
+    }
+
     pub fn begin_learn(&mut self, target: LearnTarget) {
         self.learn_target = Some(target);
     }
@@ -215,9 +324,10 @@ pub fn note_on_to_pad_trigger(note_map: &NoteMap, note: u8, velocity: u8) -> Opt
 
 #[cfg(test)]
 mod tests {
+    use super::clock::{parse_midi_realtime, ClockTransportChange, MidiClockFollower};
     use super::{
-        note_on_to_pad_trigger, parse_midi_message, LearnTarget, MappingProfile, MidiMessage,
-        NoteMap,
+        note_on_to_pad_trigger, parse_midi_message, BindingActivity, LearnTarget, MappingProfile,
+        MidiMessage, NoteMap,
     };
 
     #[test]
@@ -238,6 +348,44 @@ mod tests {
         assert_eq!(note_map.resolve_track(43), Some(7));
     }
 
+    #[test]
+    fn learn_all_binds_new_notes_to_tracks_in_order_and_reports_progress() {
+        let mut note_map = NoteMap::new(3);
+        assert!(note_map.bind_note(60, 2));
+        note_map.begin_learn_all();
+        assert_eq!(note_map.resolve_track(60), None);
+        let progress = note_map.learn_all_progress().expect("learning");
+        assert_eq!((progress.learned, progress.next_track()), (0, Some(0)));
+
+        let note_on = |note: u8| MidiMessage::NoteOn {
+            channel: 9,
+            note,
+            velocity: 90,
+        };
+        let progress = note_map.handle_message_for_learn_all(note_on(36));
+        assert_eq!(progress.and_then(|progress| progress.next_track()), Some(1));
+        assert_eq!(note_map.handle_message_for_learn_all(note_on(36)), None);
+        assert_eq!(
+            note_map.handle_message_for_learn_all(MidiMessage::ControlChange {
+                channel: 9,
+                controller: 1,
+                value: 1,
+            }),
+            None
+        );
+        assert!(note_map.handle_message_for_learn_all(note_on(38)).is_some());
+        let progress = note_map
+            .handle_message_for_learn_all(note_on(42))
+            .expect("last pad");
+        assert!(progress.is_complete());
+        assert_eq!(note_map.learn_all_progress(), None);
+        assert_eq!(note_map.handle_message_for_learn_all(note_on(44)), None);
+        assert_eq!(
+            [36, 38, 42].map(|note| note_map.resolve_track(note)),
+            [Some(0), Some(1), Some(2)]
+        );
+    }
+
     #[test]
     fn note_map_rejects_out_of_range_tracks() {
         let mut note_map = NoteMap::new(8);
@@ -307,6 +455,42 @@ mod tests {
         assert_eq!(profile.active_learn_target(), None);
     }
 
+    #[test]
+    fn bound_controllers_record_their_latest_value_for_activity_queries() {
+        let mut profile = MappingProfile::default();
+        profile.bind_cc(21, "engine.track.0.gain");
+        profile.bind_cc(22, "engine.track.1.gain");
+        assert_eq!(
+            profile.handle_control_change(21, 40, 1_000),
+            Some("engine.track.0.gain")
+        );
+        assert_eq!(profile.handle_control_change(23, 90, 1_000), None);
+        assert_eq!(profile.last_activity(23), None);
+        profile.handle_control_change(21, 64, 2_000);
+        profile.handle_control_change(22, 10, 500);
+
+        assert_eq!(
+            profile.last_activity(21),
+            Some(BindingActivity {
+                cc: 21,
+                value: 64,
+                at_sample: 2_000,
+            })
+        );
+        assert_eq!(profile.last_value(22), Some(10));
+        assert!(profile.is_recently_moved(21, 2_400, 480));
+        assert!(!profile.is_recently_moved(22, 2_400, 480));
+        let moved: Vec<u8> = profile
+            .recently_moved(2_400, 2_000)
+            .iter()
+            .map(|binding| binding.cc)
+            .collect();
+        assert_eq!(moved, vec![21, 22]);
+
+        profile.bind_cc(21, "engine.track.2.gain");
+        assert_eq!(profile.last_value(21), None);
+    }
+
     #[test]
     fn midi_learn_ignores_non_control_messages() {
         let mut profile = MappingProfile::default();
@@ -357,4 +541,51 @@ mod tests {
             Some(0x1074)
         );
     }
+
+    #[test]
+    fn clock_follower_estimates_tempo_from_ticks() {
+        let mut follower = MidiClockFollower::new(48_000);
+        assert_eq!(follower.bpm(), None);
+        let start = parse_midi_realtime(&[0xFA]).expect("start");
+        assert_eq!(follower.handle(start, 0), Some(ClockTransportChange::Start));
+
+        let clock = parse_midi_realtime(&[0xF8]).expect("clock");
+        for tick in 0..48u64 {
+            follower.handle(clock, tick * 1_000);
+        }
+        assert!((follower.bpm().expect("bpm") - 120.0).abs() < 0.01);
+        assert_eq!(follower.ticks_since_start(), 48);
+        assert_eq!(parse_midi_realtime(&[0x90]), None);
+    }
+
+    #[test]
+    fn clock_stats_report_jitter_and_gate_tempo_on_stability() {
+        let mut follower = MidiClockFollower::new(48_000);
+        assert_eq!(follower.stats(), None);
+        let clock = parse_midi_realtime(&[0xF8]).expect("clock");
+        let mut at = 0;
+        for interval in [0, 1_000, 1_000, 1_000, 1_000] {
+            at += interval;
+            follower.handle(clock, at);
+        }
+        let stats = follower.stats().expect("stats");
+        assert_eq!(stats.intervals, 4);
+        assert_eq!(stats.mean_interval_samples, 1_000.0);
+        assert_eq!(stats.stddev_samples, 0.0);
+        assert!(follower.stable_bpm().is_some());
+
+        for interval in [700, 1_300, 700, 1_300] {
+            at += interval;
+            follower.handle(clock, at);
+        }
+        let stats = follower.stats().expect("stats");
+        assert_eq!(stats.mean_interval_samples, 1_000.0);
+        assert!((stats.stddev_samples - 212.13).abs() < 0.01);
+        assert_eq!(stats.phase_error_samples, 300.0);
+        assert!(follower.bpm().is_some());
+        assert_eq!(follower.stable_bpm(), None);
+        assert!(!follower.set_stability_threshold(-1.0));
+        assert!(follower.set_stability_threshold(0.25));
+        assert!(follower.is_stable());
+    }
 }
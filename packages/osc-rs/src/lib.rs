@@ -0,0 +1,496 @@
+use std::net::{SocketAddr, UdpSocket};
+
+use abi_rs::FfParameterUpdate;
+use control_rs::{Sequencer, Step, MAX_PATTERN_STEPS, TRACK_COUNT};
+
+pub const OSC_ADDRESS_PREFIX: &str = "/ff";
+pub const OSC_MAX_PACKET_BYTES: usize = 4096;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OscArgument {
+    Int(i32),
+    Float(f32),
+    Str(String),
+}
+
+impl OscArgument {
+    fn as_f32(&self) -> Option<f32> {
+        match self {
+            OscArgument::Int(value) => Some(*value as f32),
+            OscArgument::Float(value) => Some(*value),
+            OscArgument::Str(_) => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OscMessage {
+    pub address: String,
+    pub arguments: Vec<OscArgument>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OscCommand {
+    Parameter(FfParameterUpdate),
+    PatternStep {
+        track_index: usize,
+        step_index: usize,
+        step: Step,
+    },
+    TransportStart,
+    TransportStop,
+    Tempo(f32),
+}
+
+pub fn osc_parameter_address(parameter_id: u32) -> Option<String> {
+    if let Some(descriptor) = abi_rs::ff_global_parameter_descriptor(parameter_id) {
+        return Some(format!("{OSC_ADDRESS_PREFIX}/global/{}", descriptor.name));
+    }
+    if let Some(descriptor) = abi_rs::ff_group_parameter_descriptor(parameter_id) {
+        return Some(format!(
+            "{OSC_ADDRESS_PREFIX}/group/{}/{}",
+            descriptor.group_index, descriptor.name
+        ));
+    }
+    let descriptor = abi_rs::ff_parameter_descriptor(parameter_id)?;
+    Some(format!(
+        "{OSC_ADDRESS_PREFIX}/track/{}/{}",
+        descriptor.track_index, descriptor.name
+    ))
+}
+
+pub fn osc_address_space() -> Vec<String> {
+    let mut addresses: Vec<String> = abi_rs::ff_parameter_registry()
+        .iter()
+        .map(|descriptor| descriptor.parameter_id)
+        .chain(
+            abi_rs::ff_group_parameter_registry()
+                .iter()
+                .map(|descriptor| descriptor.parameter_id),
+        )
+        .chain(
+            abi_rs::ff_global_parameter_registry()
+                .iter()
+                .map(|descriptor| descriptor.parameter_id),
+        )
+        .filter_map(osc_parameter_address)
+        .collect();
+    addresses.push(format!("{OSC_ADDRESS_PREFIX}/pattern/step/<track>/<step>"));
+    addresses.push(format!("{OSC_ADDRESS_PREFIX}/transport/start"));
+    addresses.push(format!("{OSC_ADDRESS_PREFIX}/transport/stop"));
+    addresses.push(format!("{OSC_ADDRESS_PREFIX}/transport/tempo"));
+    addresses
+}
+
+pub fn parse_osc_packet(bytes: &[u8]) -> Result<Vec<OscMessage>, String> {
+    let mut messages = Vec::new();
+    parse_packet_into(bytes, &mut messages, 0)?;
+    Ok(messages)
+}
+
+fn parse_packet_into(
+    bytes: &[u8],
+    output: &mut Vec<OscMessage>,
+    depth: usize,
+) -> Result<(), String> {
+    if depth > 8 {
+        return Err("osc bundle nesting too deep".to_string());
+    }
+
+    if bytes.starts_with(b"#bundle\0") {
+        let mut cursor = 16;
+        if bytes.len() < cursor {
+            return Err("truncated osc bundle header".to_string());
+        }
+        while cursor < bytes.len() {
+            let size = read_i32(bytes, &mut cursor)?;
+            let size = usize::try_from(size)
+                .map_err(|_| format!("invalid bundle element size: {size}"))?;
+            let end = cursor
+                .checked_add(size)
+                .filter(|end| *end <= bytes.len())
+                .ok_or_else(|| "truncated osc bundle element".to_string())?;
+            parse_packet_into(&bytes[cursor..end], output, depth + 1)?;
+            cursor = end;
+        }
+        return Ok(());
+    }
+
+    output.push(parse_osc_message(bytes)?);
+    Ok(())
+}
+
+pub fn parse_osc_message(bytes: &[u8]) -> Result<OscMessage, String> {
+    let mut cursor = 0;
+    let address = read_padded_string(bytes, &mut cursor)?;
+    if !address.starts_with('/') {
+        return Err(format!("invalid osc address: {address}"));
+    }
+
+    let mut arguments = Vec::new();
+    if cursor >= bytes.len() {
+        return Ok(OscMessage { address, arguments });
+    }
+
+    let type_tags = read_padded_string(bytes, &mut cursor)?;
+    let tags = type_tags
+        .strip_prefix(',')
+        .ok_or_else(|| format!("invalid osc type tags: {type_tags}"))?;
+    for tag in tags.chars() {
+        match tag {
+            'i' => arguments.push(OscArgument::Int(read_i32(bytes, &mut cursor)?)),
+            'f' => arguments.push(OscArgument::Float(f32::from_bits(
+                read_i32(bytes, &mut cursor)? as u32,
+            ))),
+            's' => arguments.push(OscArgument::Str(read_padded_string(bytes, &mut cursor)?)),
+            'T' => arguments.push(OscArgument::Int(1)),
+            'F' => arguments.push(OscArgument::Int(0)),
+            _ => return Err(format!("unsupported osc type tag: {tag}")),
+        }
+    }
+
+    Ok(OscMessage { address, arguments })
+}
+
+pub fn encode_osc_message(message: &OscMessage) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_padded_string(&mut bytes, &message.address);
+
+    let mut type_tags = String::from(",");
+    for argument in &message.arguments {
+        type_tags.push(match argument {
+            OscArgument::Int(_) => 'i',
+            OscArgument::Float(_) => 'f',
+            OscArgument::Str(_) => 's',
+        });
+    }
+    write_padded_string(&mut bytes, &type_tags);
+
+    for argument in &message.arguments {
+        match argument {
+            OscArgument::Int(value) => bytes.extend_from_slice(&value.to_be_bytes()),
+            OscArgument::Float(value) => bytes.extend_from_slice(&value.to_bits().to_be_bytes()),
+            OscArgument::Str(value) => write_padded_string(&mut bytes, value),
+        }
+    }
+    bytes
+}
+
+pub fn osc_message_to_command(message: &OscMessage) -> Result<OscCommand, String> {
+    let path = message
+        .address
+        .strip_prefix(OSC_ADDRESS_PREFIX)
+        .ok_or_else(|| format!("unknown osc address: {}", message.address))?;
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+
+    match segments.as_slice() {
+        ["track", track, slot_name] => {
+            let track_index = parse_index(track, TRACK_COUNT, "track")?;
+            let parameter_id = abi_rs::ff_parameter_registry()
+                .iter()
+                .find(|descriptor| {
+                    usize::from(descriptor.track_index) == track_index
+                        && descriptor.name == *slot_name
+                })
+                .map(|descriptor| descriptor.parameter_id)
+                .ok_or_else(|| format!("unknown track parameter: {slot_name}"))?;
+            let value = first_number(message)?;
+            Ok(OscCommand::Parameter(FfParameterUpdate {
+                parameter_id,
+                normalized_value: value.clamp(0.0, 1.0),
+                ramp_samples: 0,
+                reserved: 0,
+            }))
+        }
+        ["group", group, slot_name] => {
+            let group_index =
+                parse_index(group, usize::from(abi_rs::FF_TRACK_GROUP_COUNT), "group")?;
+            let parameter_id = abi_rs::ff_group_parameter_registry()
+                .iter()
+                .find(|descriptor| {
+                    usize::from(descriptor.group_index) == group_index
+                        && descriptor.name == *slot_name
+                })
+                .map(|descriptor| descriptor.parameter_id)
+                .ok_or_else(|| format!("unknown group parameter: {slot_name}"))?;
+            let value = first_number(message)?;
+            Ok(OscCommand::Parameter(FfParameterUpdate {
+                parameter_id,
+                normalized_value: value.clamp(0.0, 1.0),
+                ramp_samples: 0,
+                reserved: 0,
+            }))
+        }
+        ["global", name] => {
+            let parameter_id = abi_rs::ff_global_parameter_registry()
+                .iter()
+                .find(|descriptor| descriptor.name == *name)
+                .map(|descriptor| descriptor.parameter_id)
+                .ok_or_else(|| format!("unknown global parameter: {name}"))?;
+            let value = first_number(message)?;
+            Ok(OscCommand::Parameter(FfParameterUpdate {
+                parameter_id,
+                normalized_value: value.clamp(0.0, 1.0),
+                ramp_samples: 0,
+                reserved: 0,
+            }))
+        }
+        ["pattern", "step", track, step] => {
+            let track_index = parse_index(track, TRACK_COUNT, "track")?;
+            let step_index = parse_index(step, MAX_PATTERN_STEPS, "step")?;
+            let active = first_number(message)? > 0.5;
+            let velocity = match message.arguments.get(1) {
+                Some(argument) => argument
+                    .as_f32()
+                    .ok_or_else(|| "step velocity must be numeric".to_string())?
+                    .clamp(0.0, 127.0) as u8,
+                None => Step::default().velocity,
+            };
+            Ok(OscCommand::PatternStep {
+                track_index,
+                step_index,
+                step: Step {
+                    active,
+                    velocity,
+                    ..Step::default()
+                },
+            })
+        }
+        ["transport", "start"] => Ok(OscCommand::TransportStart),
+        ["transport", "stop"] => Ok(OscCommand::TransportStop),
+        ["transport", "tempo"] => Ok(OscCommand::Tempo(first_number(message)?)),
+        _ => Err(format!("unknown osc address: {}", message.address)),
+    }
+}
+
+pub fn apply_command_to_sequencer(sequencer: &mut Sequencer, command: &OscCommand) -> bool {
+    match command {
+        OscCommand::Parameter(update) => sequencer.apply_parameter_update(update),
+        OscCommand::PatternStep {
+            track_index,
+            step_index,
+            step,
+        } => sequencer.edit_step(*track_index, *step_index, *step),
+        OscCommand::TransportStart => {
+            sequencer.start();
+            true
+        }
+        OscCommand::TransportStop => {
+            sequencer.stop();
+            true
+        }
+        OscCommand::Tempo(bpm) => {
+            sequencer.set_tempo_bpm(*bpm);
+            true
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct OscControlServer {
+    socket: UdpSocket,
+    rejected_messages: u64,
+}
+
+impl OscControlServer {
+    pub fn bind(address: &str) -> Result<Self, String> {
+        let socket =
+            UdpSocket::bind(address).map_err(|error| format!("osc bind failed: {error}"))?;
+        socket
+            .set_nonblocking(true)
+            .map_err(|error| format!("osc socket setup failed: {error}"))?;
+        Ok(Self {
+            socket,
+            rejected_messages: 0,
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, String> {
+        self.socket
+            .local_addr()
+            .map_err(|error| format!("osc local address unavailable: {error}"))
+    }
+
+    pub fn rejected_messages(&self) -> u64 {
+        self.rejected_messages
+    }
+
+    pub fn poll(&mut self) -> Vec<OscCommand> {
+        let mut commands = Vec::new();
+        let mut buffer = [0u8; OSC_MAX_PACKET_BYTES];
+        while let Ok((size, _)) = self.socket.recv_from(&mut buffer) {
+            match parse_osc_packet(&buffer[..size]) {
+                Ok(messages) => {
+                    for message in &messages {
+                        match osc_message_to_command(message) {
+                            Ok(command) => commands.push(command),
+                            Err(_) => self.rejected_messages += 1,
+                        }
+                    }
+                }
+                Err(_) => self.rejected_messages += 1,
+            }
+        }
+        commands
+    }
+}
+
+fn parse_index(value: &str, limit: usize, field: &str) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .ok()
+        .filter(|index| *index < limit)
+        .ok_or_else(|| format!("invalid osc {field} index: {value}"))
+}
+
+fn first_number(message: &OscMessage) -> Result<f32, String> {
+    message
+        .arguments
+        .first()
+        .and_then(OscArgument::as_f32)
+        .filter(|value| value.is_finite())
+        .ok_or_else(|| format!("{} expects a numeric argument", message.address))
+}
+
+fn read_padded_string(bytes: &[u8], cursor: &mut usize) -> Result<String, String> {
+    let start = *cursor;
+    let terminator = bytes[start.min(bytes.len())..]
+        .iter()
+        .position(|byte| *byte == 0)
+        .ok_or_else(|| "unterminated osc string".to_string())?;
+    let value = std::str::from_utf8(&bytes[start..start + terminator])
+        .map_err(|_| "invalid utf8 in osc string".to_string())?
+        .to_string();
+    *cursor = start + (terminator + 4) / 4 * 4;
+    Ok(value)
+}
+
+fn write_padded_string(bytes: &mut Vec<u8>, value: &str) {
+    bytes.extend_from_slice(value.as_bytes());
+    let padding = 4 - (value.len() % 4);
+    bytes.extend(std::iter::repeat_n(0u8, padding));
+}
+
+fn read_i32(bytes: &[u8], cursor: &mut usize) -> Result<i32, String> {
+    let end = *cursor + 4;
+    let chunk = bytes
+        .get(*cursor..end)
+        .ok_or_else(|| "truncated osc argument".to_string())?;
+    *cursor = end;
+    Ok(i32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::UdpSocket;
+
+    use abi_rs::{ff_track_parameter_id, FfParameterUpdate, FF_PARAM_SLOT_GAIN};
+    use control_rs::Sequencer;
+
+    use super::{
+        apply_command_to_sequencer, encode_osc_message, osc_address_space, osc_message_to_command,
+        parse_osc_packet, OscArgument, OscCommand, OscControlServer, OscMessage,
+    };
+
+    fn message(address: &str, arguments: Vec<OscArgument>) -> OscMessage {
+        OscMessage {
+            address: address.to_string(),
+            arguments,
+        }
+    }
+
+    #[test]
+    fn address_space_is_generated_from_parameter_registry() {
+        let addresses = osc_address_space();
+        assert!(addresses.contains(&"/ff/track/3/gain".to_string()));
+        assert!(addresses.contains(&"/ff/track/7/choke_group".to_string()));
+        assert!(addresses.contains(&"/ff/track/2/duck_depth".to_string()));
+        assert!(addresses.contains(&"/ff/track/5/reverse".to_string()));
+        assert_eq!(
+            addresses
+                .iter()
+                .filter(|address| address.starts_with("/ff/track/"))
+                .count(),
+            120
+        );
+        assert!(addresses.contains(&"/ff/group/1/solo".to_string()));
+        assert!(addresses.contains(&"/ff/global/swing".to_string()));
+        let swing =
+            osc_message_to_command(&message("/ff/global/swing", vec![OscArgument::Float(0.5)]))
+                .expect("swing command");
+        let mut sequencer = Sequencer::new(48_000);
+        assert!(apply_command_to_sequencer(&mut sequencer, &swing));
+        assert_eq!(sequencer.swing(), abi_rs::FF_SWING_MAX / 2.0);
+        assert_eq!(
+            osc_message_to_command(&message("/ff/group/1/solo", vec![OscArgument::Int(1)])),
+            Ok(OscCommand::Parameter(FfParameterUpdate {
+                parameter_id: abi_rs::ff_group_parameter_id(1, abi_rs::FF_PARAM_GROUP_SLOT_SOLO)
+                    .expect("id"),
+                normalized_value: 1.0,
+                ramp_samples: 0,
+                reserved: 0,
+            }))
+        );
+    }
+
+    #[test]
+    fn messages_translate_to_parameter_updates_and_step_edits() {
+        let encoded =
+            encode_osc_message(&message("/ff/track/3/gain", vec![OscArgument::Float(0.25)]));
+        let decoded = parse_osc_packet(&encoded).expect("packet should decode");
+        match osc_message_to_command(&decoded[0]).expect("command") {
+            OscCommand::Parameter(update) => {
+                assert_eq!(
+                    Some(update.parameter_id),
+                    ff_track_parameter_id(3, FF_PARAM_SLOT_GAIN)
+                );
+                assert_eq!(update.normalized_value, 0.25);
+            }
+            other => panic!("unexpected command: {other:?}"),
+        }
+
+        let mut sequencer = Sequencer::new(48_000);
+        let step = osc_message_to_command(&message(
+            "/ff/pattern/step/2/9",
+            vec![OscArgument::Int(1), OscArgument::Int(90)],
+        ))
+        .expect("step command");
+        assert!(apply_command_to_sequencer(&mut sequencer, &step));
+        let applied = sequencer.pattern().step(2, 9).expect("step");
+        assert!(applied.active);
+        assert_eq!(applied.velocity, 90);
+
+        assert!(osc_message_to_command(&message("/ff/track/8/gain", vec![])).is_err());
+        assert!(osc_message_to_command(&message("/ff/track/0/volume", vec![])).is_err());
+    }
+
+    #[test]
+    fn server_polls_pending_datagrams() {
+        let mut server = OscControlServer::bind("127.0.0.1:0").expect("bind");
+        let target = server.local_addr().expect("local address");
+        let client = UdpSocket::bind("127.0.0.1:0").expect("client bind");
+        client
+            .send_to(
+                &encode_osc_message(&message(
+                    "/ff/transport/tempo",
+                    vec![OscArgument::Float(96.0)],
+                )),
+                target,
+            )
+            .expect("send");
+        client
+            .send_to(&encode_osc_message(&message("/ff/unknown", vec![])), target)
+            .expect("send");
+
+        let mut commands = Vec::new();
+        for _ in 0..100 {
+            commands.extend(server.poll());
+            if !commands.is_empty() && server.rejected_messages() > 0 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert_eq!(commands, vec![OscCommand::Tempo(96.0)]);
+        assert_eq!(server.rejected_messages(), 1);
+    }
+}
@@ -1,5 +1,43 @@
+use std::collections::BTreeMap;
+
 pub const TRACK_COUNT: usize = 8;
 pub const STEPS_PER_PATTERN: usize = 16;
+pub const MAX_PATTERN_BARS: usize = 4;
+pub const MAX_PATTERN_STEPS: usize = STEPS_PER_PATTERN * MAX_PATTERN_BARS;
+pub const MAX_OUTPUT_PAIRS: usize = 8;
+pub const MAX_DUCK_ATTACK_MS: f32 = 100.0;
+pub const MAX_DUCK_RELEASE_MS: f32 = 1_000.0;
+pub const MIDI_CHANNEL_COUNT: u8 = 16;
+pub const MAX_MIDI_NOTE_LENGTH_STEPS: f32 = 64.0;
+pub const MAX_HUMANIZE_TIMING_SAMPLES: u32 = 4_800;
+pub const MAX_HUMANIZE_VELOCITY: u8 = 64;
+pub const MAX_TRACK_NAME_CHARS: usize = 32;
+pub const MAX_TRACK_COLOR: u32 = 0xFF_FFFF;
+pub const GAIN_DB_MIN: f32 = -57.5;
+pub const GAIN_DB_MAX: f32 = 6.0;
+pub const TRACK_GROUP_COUNT: usize = 4;
+pub const LIMITER_CEILING_DB_MIN: f32 = -12.0;
+pub const LIMITER_CEILING_DB_MAX: f32 = 0.0;
+pub const MIN_PATTERN_BPM: f32 = 20.0;
+pub const MAX_PATTERN_BPM: f32 = 300.0;
+pub const PATTERN_VARIATION_COUNT: usize = 4;
+pub const MIDI_PROGRAM_COUNT: u8 = 128;
+pub const GROOVE_SLOT_COUNT: usize = STEPS_PER_PATTERN;
+pub const MAX_GROOVE_TIMING_OFFSET: f32 = 0.45;
+pub const MAX_GROOVE_VELOCITY_OFFSET: i8 = 64;
+pub const BUILTIN_GROOVE_NAMES: [&str; 3] = ["mpc54", "mpc58", "shuffle"];
+pub const MIN_HINT_SAMPLE_RATE_HZ: u32 = 8_000;
+pub const MAX_HINT_SAMPLE_RATE_HZ: u32 = 384_000;
+pub const MAX_HINT_BLOCK_SIZE: u32 = 8_192;
+pub const DEFAULT_ACCENT_AMOUNT: u8 = 32;
+pub const MAX_ACCENT_AMOUNT: u8 = 127;
+pub const ACCENT_CURVE_POINTS: usize = 5;
+pub const MAX_STEP_PROBABILITY: u8 = 100;
+pub const MAX_STEP_GATE: u8 = 100;
+pub const MAX_TRIG_CYCLE: u8 = 8;
+pub const DEFAULT_FLAM_SPACING_MS: u8 = 20;
+pub const MAX_FLAM_SPACING_MS: u8 = 60;
+const GAIN_UNITS_DB: &str = "db";
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct TrackAssignment {
@@ -9,7 +47,7 @@ pub struct TrackAssignment {
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct TrackControls {
-    pub gain: f32,
+    pub gain_db: f32,
     pub pan: f32,
     pub filter_cutoff: f32,
     pub envelope_decay: f32,
@@ -20,7 +58,7 @@ pub struct TrackControls {
 impl Default for TrackControls {
     fn default() -> Self {
         Self {
-            gain: 1.0,
+            gain_db: 0.0,
             pan: 0.0,
             filter_cutoff: 1.0,
             envelope_decay: 1.0,
@@ -36,11 +74,200 @@ pub struct TrackControlAssignment {
     pub controls: TrackControls,
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TrackSampleRegion {
+    pub track_index: u8,
+    pub start_frame: u64,
+    pub end_frame: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrackSampleTrim {
+    pub track_index: u8,
+    pub start: f32,
+    pub end: f32,
+    pub reverse: bool,
+}
+
+impl TrackSampleTrim {
+    pub fn is_valid(&self) -> bool {
+        usize::from(self.track_index) < TRACK_COUNT
+            && (0.0..1.0).contains(&self.start)
+            && self.end <= 1.0
+            && self.start < self.end
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrackDucking {
+    pub track_index: u8,
+    pub source_track: u8,
+    pub depth: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+}
+
+impl TrackDucking {
+    pub fn is_valid(&self) -> bool {
+        usize::from(self.track_index) < TRACK_COUNT
+            && usize::from(self.source_track) < TRACK_COUNT
+            && self.track_index != self.source_track
+            && (0.0..=1.0).contains(&self.depth)
+            && (0.0..=MAX_DUCK_ATTACK_MS).contains(&self.attack_ms)
+            && (0.0..=MAX_DUCK_RELEASE_MS).contains(&self.release_ms)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrackMidiOutput {
+    pub track_index: u8,
+    pub channel: u8,
+    pub note: u8,
+    pub length_steps: f32,
+}
+
+impl TrackMidiOutput {
+    pub fn is_valid(&self) -> bool {
+        usize::from(self.track_index) < TRACK_COUNT
+            && self.channel < MIDI_CHANNEL_COUNT
+            && self.note <= 127
+            && self.length_steps > 0.0
+            && self.length_steps <= MAX_MIDI_NOTE_LENGTH_STEPS
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct HumanizeDepth {
+    pub timing_samples: u32,
+    pub velocity: u8,
+}
+
+impl HumanizeDepth {
+    pub fn is_valid(&self) -> bool {
+        self.timing_samples <= MAX_HUMANIZE_TIMING_SAMPLES && self.velocity <= MAX_HUMANIZE_VELOCITY
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TrackHumanize {
+    pub track_index: u8,
+    pub timing_samples: u32,
+    pub velocity: u8,
+}
+
+impl TrackHumanize {
+    pub fn is_valid(&self) -> bool {
+        usize::from(self.track_index) < TRACK_COUNT
+            && self.timing_samples <= MAX_HUMANIZE_TIMING_SAMPLES
+            && self.velocity <= MAX_HUMANIZE_VELOCITY
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TrackChokeTargets {
+    pub track_index: u8,
+    pub targets: u8,
+}
+
+impl TrackChokeTargets {
+    pub fn is_valid(&self) -> bool {
+        usize::from(self.track_index) < TRACK_COUNT && self.targets & (1 << self.track_index) == 0
+    }
+
+    pub fn chokes(&self, target_track: u8) -> bool {
+        usize::from(target_track) < TRACK_COUNT && self.targets & (1 << target_track) != 0
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TrackLabel {
+    pub track_index: u8,
+    pub name: String,
+    pub color: Option<u32>,
+}
+
+impl TrackLabel {
+    pub fn is_valid(&self) -> bool {
+        usize::from(self.track_index) < TRACK_COUNT
+            && self.name.chars().count() <= MAX_TRACK_NAME_CHARS
+            && self.color.is_none_or(|color| color <= MAX_TRACK_COLOR)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrackGroup {
+    pub group_index: u8,
+    pub name: String,
+    pub members: Vec<u8>,
+    pub gain_db: f32,
+    pub muted: bool,
+    pub soloed: bool,
+    pub choke: bool,
+}
+
+impl TrackGroup {
+    pub fn new(group_index: u8, name: &str) -> Self {
+        Self {
+            group_index,
+            name: name.to_string(),
+            members: Vec::new(),
+            gain_db: 0.0,
+            muted: false,
+            soloed: false,
+            choke: false,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        usize::from(self.group_index) < TRACK_GROUP_COUNT
+            && self
+                .members
+                .iter()
+                .all(|track_index| usize::from(*track_index) < TRACK_COUNT)
+            && !self.gain_db.is_nan()
+            && self.gain_db <= GAIN_DB_MAX
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MasterBus {
+    pub compressor_amount: f32,
+    pub filter_cutoff: f32,
+    pub limiter_ceiling_db: f32,
+}
+
+impl Default for MasterBus {
+    fn default() -> Self {
+        Self {
+            compressor_amount: 0.0,
+            filter_cutoff: 1.0,
+            limiter_ceiling_db: LIMITER_CEILING_DB_MAX,
+        }
+    }
+}
+
+impl MasterBus {
+    pub fn is_valid(&self) -> bool {
+        (0.0..=1.0).contains(&self.compressor_amount)
+            && (0.0..=1.0).contains(&self.filter_cutoff)
+            && (LIMITER_CEILING_DB_MIN..=LIMITER_CEILING_DB_MAX).contains(&self.limiter_ceiling_db)
+    }
+}
+
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Kit {
     pub name: String,
     pub tracks: Vec<TrackAssignment>,
     pub controls: Vec<TrackControlAssignment>,
+    pub regions: Vec<TrackSampleRegion>,
+    pub trims: Vec<TrackSampleTrim>,
+    pub ducking: Vec<TrackDucking>,
+    pub midi_outputs: Vec<TrackMidiOutput>,
+    pub humanize: Vec<TrackHumanize>,
+    pub labels: Vec<TrackLabel>,
+    pub choke_targets: Vec<TrackChokeTargets>,
+    pub groups: Vec<TrackGroup>,
+    pub master: MasterBus,
 }
 
 impl Kit {
@@ -79,552 +306,4198 @@ impl Kit {
             .find(|value| value.track_index == track_index)
             .map(|value| value.controls)
     }
-}
-
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub struct PatternStep {
-    pub active: bool,
-    pub velocity: u8,
-}
 
-impl Default for PatternStep {
-    fn default() -> Self {
-        Self {
-            active: false,
-            velocity: 100,
+    pub fn set_track_region(&mut self, track_index: u8, start_frame: u64, end_frame: u64) -> bool {
+        if usize::from(track_index) >= TRACK_COUNT || start_frame >= end_frame {
+            return false;
         }
+
+        self.clear_track_region(track_index);
+        self.regions.push(TrackSampleRegion {
+            track_index,
+            start_frame,
+            end_frame,
+        });
+        true
     }
-}
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct Pattern {
-    pub name: String,
-    pub swing: f32,
-    pub steps: [[PatternStep; STEPS_PER_PATTERN]; TRACK_COUNT],
-}
+    pub fn clear_track_region(&mut self, track_index: u8) {
+        self.regions
+            .retain(|region| region.track_index != track_index);
+    }
 
-impl Default for Pattern {
-    fn default() -> Self {
-        Self {
-            name: "pattern".to_string(),
-            swing: 0.0,
-            steps: [[PatternStep::default(); STEPS_PER_PATTERN]; TRACK_COUNT],
+    pub fn track_region(&self, track_index: u8) -> Option<TrackSampleRegion> {
+        self.regions
+            .iter()
+            .find(|region| region.track_index == track_index)
+            .copied()
+    }
+
+    pub fn set_track_trim(&mut self, trim: TrackSampleTrim) -> bool {
+        if !trim.is_valid() {
+            return false;
         }
+
+        self.clear_track_trim(trim.track_index);
+        self.trims.push(trim);
+        true
     }
-}
 
-impl Pattern {
-    pub fn set_step(&mut self, track_index: usize, step_index: usize, step: PatternStep) -> bool {
-        if track_index >= TRACK_COUNT || step_index >= STEPS_PER_PATTERN {
+    pub fn clear_track_trim(&mut self, track_index: u8) {
+        self.trims.retain(|trim| trim.track_index != track_index);
+    }
+
+    pub fn track_trim(&self, track_index: u8) -> Option<TrackSampleTrim> {
+        self.trims
+            .iter()
+            .find(|trim| trim.track_index == track_index)
+            .copied()
+    }
+
+    pub fn set_track_ducking(&mut self, ducking: TrackDucking) -> bool {
+        if !ducking.is_valid() {
             return false;
         }
 
-        self.steps[track_index][step_index] = step;
+        self.clear_track_ducking(ducking.track_index);
+        self.ducking.push(ducking);
         true
     }
 
-    pub fn step(&self, track_index: usize, step_index: usize) -> Option<PatternStep> {
-        if track_index >= TRACK_COUNT || step_index >= STEPS_PER_PATTERN {
-            return None;
+    pub fn clear_track_ducking(&mut self, track_index: u8) {
+        self.ducking
+            .retain(|ducking| ducking.track_index != track_index);
+    }
+
+    pub fn track_ducking(&self, track_index: u8) -> Option<TrackDucking> {
+        self.ducking
+            .iter()
+            .find(|ducking| ducking.track_index == track_index)
+            .copied()
+    }
+
+    pub fn set_track_midi_output(&mut self, output: TrackMidiOutput) -> bool {
+        if !output.is_valid() {
+            return false;
         }
 
-        Some(self.steps[track_index][step_index])
+        self.clear_track_midi_output(output.track_index);
+        self.midi_outputs.push(output);
+        true
     }
 
-    pub fn set_swing(&mut self, swing: f32) {
-        self.swing = swing.clamp(0.0, 0.45);
+    pub fn clear_track_midi_output(&mut self, track_index: u8) {
+        self.midi_outputs
+            .retain(|output| output.track_index != track_index);
     }
-}
 
-#[derive(Clone, Debug, Default, PartialEq)]
-pub struct Project {
-    pub name: String,
-    pub kits: Vec<Kit>,
-    pub active_kit: Option<usize>,
-    pub patterns: Vec<Pattern>,
-    pub active_pattern: Option<usize>,
-}
+    pub fn track_midi_output(&self, track_index: u8) -> Option<TrackMidiOutput> {
+        self.midi_outputs
+            .iter()
+            .find(|output| output.track_index == track_index)
+            .copied()
+    }
 
-impl Project {
-    pub fn set_active_kit(&mut self, index: usize) -> bool {
-        if index >= self.kits.len() {
+    pub fn set_track_humanize(&mut self, humanize: TrackHumanize) -> bool {
+        if !humanize.is_valid() {
             return false;
         }
 
-        self.active_kit = Some(index);
+        self.clear_track_humanize(humanize.track_index);
+        self.humanize.push(humanize);
         true
     }
 
-    pub fn set_active_pattern(&mut self, index: usize) -> bool {
-        if index >= self.patterns.len() {
+    pub fn clear_track_humanize(&mut self, track_index: u8) {
+        self.humanize
+            .retain(|humanize| humanize.track_index != track_index);
+    }
+
+    pub fn track_humanize(&self, track_index: u8) -> Option<TrackHumanize> {
+        self.humanize
+            .iter()
+            .find(|humanize| humanize.track_index == track_index)
+            .copied()
+    }
+
+    pub fn set_track_choke_targets(&mut self, choke: TrackChokeTargets) -> bool {
+        if !choke.is_valid() {
             return false;
         }
 
-        self.active_pattern = Some(index);
+        self.clear_track_choke_targets(choke.track_index);
+        if choke.targets != 0 {
+            self.choke_targets.push(choke);
+            self.choke_targets.sort_by_key(|value| value.track_index);
+        }
         true
     }
-}
 
-fn format_f32(value: f32) -> String {
-    format!("{value:.6}")
-}
+    pub fn clear_track_choke_targets(&mut self, track_index: u8) {
+        self.choke_targets
+            .retain(|choke| choke.track_index != track_index);
+    }
 
-fn parse_f32(value: &str, field: &str) -> Result<f32, String> {
-    value
-        .parse::<f32>()
-        .map_err(|_| format!("invalid float for {field}: {value}"))
-}
+    pub fn track_choke_targets(&self, track_index: u8) -> Option<TrackChokeTargets> {
+        self.choke_targets
+            .iter()
+            .find(|choke| choke.track_index == track_index)
+            .copied()
+    }
 
-fn parse_usize(value: &str, field: &str) -> Result<usize, String> {
-    value
-        .parse::<usize>()
-        .map_err(|_| format!("invalid usize for {field}: {value}"))
-}
+    pub fn chokes(&self, source_track: u8, target_track: u8) -> bool {
+        self.track_choke_targets(source_track)
+            .is_some_and(|choke| choke.chokes(target_track))
+    }
 
-fn parse_u8(value: &str, field: &str) -> Result<u8, String> {
-    value
-        .parse::<u8>()
-        .map_err(|_| format!("invalid u8 for {field}: {value}"))
-}
+    pub fn set_track_label(&mut self, label: TrackLabel) -> bool {
+        if !label.is_valid() {
+            return false;
+        }
 
-fn encode_text(value: &str) -> String {
-    let mut encoded = String::with_capacity(value.len() * 2);
-    for byte in value.as_bytes() {
-        encoded.push_str(&format!("{byte:02X}"));
+        self.clear_track_label(label.track_index);
+        self.labels.push(label);
+        self.labels.sort_by_key(|value| value.track_index);
+        true
     }
-    encoded
-}
 
-fn decode_text(value: &str) -> Result<String, String> {
-    if !value.len().is_multiple_of(2) {
-        return Err("hex string length must be even".to_string());
+    pub fn clear_track_label(&mut self, track_index: u8) {
+        self.labels.retain(|label| label.track_index != track_index);
     }
 
-    let mut bytes = Vec::with_capacity(value.len() / 2);
-    let mut index = 0;
-    while index < value.len() {
-        let end = index + 2;
-        let byte = u8::from_str_radix(&value[index..end], 16)
-            .map_err(|_| format!("invalid hex byte: {}", &value[index..end]))?;
-        bytes.push(byte);
-        index = end;
+    pub fn track_label(&self, track_index: u8) -> Option<&TrackLabel> {
+        self.labels
+            .iter()
+            .find(|label| label.track_index == track_index)
     }
 
-    String::from_utf8(bytes).map_err(|_| "invalid utf8 in encoded text".to_string())
-}
+    pub fn set_track_group(&mut self, mut group: TrackGroup) -> bool {
+        if !group.is_valid() {
+            return false;
+        }
 
-fn serialize_kit_body(kit: &Kit) -> Vec<String> {
-    let mut lines = Vec::new();
-    lines.push(format!("name={}", encode_text(&kit.name)));
+        group.members.sort_unstable();
+        group.members.dedup();
+        self.clear_track_group(group.group_index);
+        for other in &mut self.groups {
+            other
+                .members
+                .retain(|track_index| !group.members.contains(track_index));
+        }
+        self.groups.push(group);
+        self.groups.sort_by_key(|value| value.group_index);
+        true
+    }
 
-    let mut tracks = kit.tracks.clone();
-    tracks.sort_by_key(|value| value.track_index);
-    for track in tracks {
-        lines.push(format!(
-            "track|{}|{}",
-            track.track_index,
-            encode_text(&track.sample_id)
-        ));
+    pub fn clear_track_group(&mut self, group_index: u8) {
+        self.groups.retain(|group| group.group_index != group_index);
     }
 
-    let mut controls = kit.controls.clone();
-    controls.sort_by_key(|value| value.track_index);
-    for control in controls {
-        lines.push(format!(
-            "control|{}|{}|{}|{}|{}|{}|{}",
-            control.track_index,
-            format_f32(control.controls.gain),
-            format_f32(control.controls.pan),
-            format_f32(control.controls.filter_cutoff),
-            format_f32(control.controls.envelope_decay),
-            format_f32(control.controls.pitch_semitones),
-            control.controls.choke_group.map(i32::from).unwrap_or(-1),
-        ));
+    pub fn track_group(&self, group_index: u8) -> Option<&TrackGroup> {
+        self.groups
+            .iter()
+            .find(|group| group.group_index == group_index)
     }
 
-    lines
+    pub fn set_master_bus(&mut self, master: MasterBus) -> bool {
+        if !master.is_valid() {
+            return false;
+        }
+
+        self.master = master;
+        true
+    }
+
+    pub fn group_of_track(&self, track_index: u8) -> Option<u8> {
+        self.groups
+            .iter()
+            .find(|group| group.members.contains(&track_index))
+            .map(|group| group.group_index)
+    }
 }
 
-fn deserialize_kit_body(lines: &[String]) -> Result<Kit, String> {
-    let mut kit = Kit::default();
+// Pre looks at the last active step on the same track, Neighbor at the last
+// active step on the track before it (track 0 has no neighbor). Cycle is the
+// Elektron "hit:of" condition, firing on loop `hit` of every `of` loops, and
+// First and Cycle count loops since the last reset or pattern switch.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TrigCondition {
+    #[default]
+    Always,
+    Pre,
+    NotPre,
+    Neighbor,
+    NotNeighbor,
+    Cycle {
+        hit: u8,
+        of: u8,
+    },
+    Fill,
+    NotFill,
+    First,
+    NotFirst,
+}
 
-    for line in lines {
-        if let Some(name_hex) = line.strip_prefix("name=") {
-            kit.name = decode_text(name_hex)?;
-            continue;
+impl TrigCondition {
+    pub fn is_valid(self) -> bool {
+        match self {
+            TrigCondition::Cycle { hit, of } => {
+                (1..=MAX_TRIG_CYCLE).contains(&of) && (1..=of).contains(&hit)
+            }
+            _ => true,
         }
+    }
 
-        if let Some(rest) = line.strip_prefix("track|") {
-            let fields: Vec<&str> = rest.split('|').collect();
-            if fields.len() != 2 {
-                return Err(format!("invalid track line: {line}"));
-            }
-
-            let track_index = parse_u8(fields[0], "track_index")?;
-            if usize::from(track_index) >= TRACK_COUNT {
-                return Err(format!(
-                    "track assignment out of range: {track_index} (max {})",
-                    TRACK_COUNT - 1
-                ));
-            }
-            let sample_id = decode_text(fields[1])?;
-            if !kit.add_assignment(TrackAssignment {
-                track_index,
-                sample_id,
-            }) {
-                return Err(format!("duplicate track assignment: {track_index}"));
-            }
-            continue;
+    // Cycle conditions are written in the Elektron "hit:of" form, e.g. `1:4`.
+    pub fn to_text(self) -> String {
+        match self {
+            TrigCondition::Always => "always".to_string(),
+            TrigCondition::Pre => "pre".to_string(),
+            TrigCondition::NotPre => "not_pre".to_string(),
+            TrigCondition::Neighbor => "neighbor".to_string(),
+            TrigCondition::NotNeighbor => "not_neighbor".to_string(),
+            TrigCondition::Cycle { hit, of } => format!("{hit}:{of}"),
+            TrigCondition::Fill => "fill".to_string(),
+            TrigCondition::NotFill => "not_fill".to_string(),
+            TrigCondition::First => "first".to_string(),
+            TrigCondition::NotFirst => "not_first".to_string(),
         }
+    }
 
-        if let Some(rest) = line.strip_prefix("control|") {
-            let fields: Vec<&str> = rest.split('|').collect();
-            if fields.len() != 7 {
-                return Err(format!("invalid control line: {line}"));
+    pub fn from_text(text: &str) -> Option<Self> {
+        let condition = match text {
+            "always" => TrigCondition::Always,
+            "pre" => TrigCondition::Pre,
+            "not_pre" => TrigCondition::NotPre,
+            "neighbor" => TrigCondition::Neighbor,
+            "not_neighbor" => TrigCondition::NotNeighbor,
+            "fill" => TrigCondition::Fill,
+            "not_fill" => TrigCondition::NotFill,
+            "first" => TrigCondition::First,
+            "not_first" => TrigCondition::NotFirst,
+            _ => {
+                let (hit, of) = text.split_once(':')?;
+                TrigCondition::Cycle {
+                    hit: hit.parse().ok()?,
+                    of: of.parse().ok()?,
+                }
             }
+        };
+        condition.is_valid().then_some(condition)
+    }
+}
 
-            let track_index = parse_u8(fields[0], "control.track_index")?;
-            if usize::from(track_index) >= TRACK_COUNT {
-                return Err(format!(
-                    "control track out of range: {track_index} (max {})",
-                    TRACK_COUNT - 1
-                ));
-            }
-            let choke_group_value = fields[6]
-                .parse::<i32>()
-                .map_err(|_| format!("invalid choke group: {}", fields[6]))?;
-            let choke_group = if choke_group_value < 0 {
-                None
-            } else {
-                if choke_group_value > 15 {
-                    return Err(format!(
-                        "choke group out of semantic range: {choke_group_value} (max 15)"
-                    ));
-                }
-                Some(
-                    u8::try_from(choke_group_value)
-                        .map_err(|_| format!("choke group out of range: {choke_group_value}"))?,
-                )
-            };
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PatternStep {
+    pub active: bool,
+    pub velocity: u8,
+    pub probability: u8,
+    pub condition: TrigCondition,
+    pub accent: bool,
+    pub flam: bool,
+    pub flam_spacing_ms: u8,
+    pub gate: u8,
+}
 
-            kit.set_track_controls(
-                track_index,
-                TrackControls {
-                    gain: parse_f32(fields[1], "control.gain")?,
-                    pan: parse_f32(fields[2], "control.pan")?,
-                    filter_cutoff: parse_f32(fields[3], "control.filter_cutoff")?,
-                    envelope_decay: parse_f32(fields[4], "control.envelope_decay")?,
-                    pitch_semitones: parse_f32(fields[5], "control.pitch_semitones")?,
-                    choke_group,
-                },
-            );
-            continue;
+impl Default for PatternStep {
+    fn default() -> Self {
+        Self {
+            active: false,
+            velocity: 100,
+            probability: MAX_STEP_PROBABILITY,
+            condition: TrigCondition::Always,
+            accent: false,
+            flam: false,
+            flam_spacing_ms: DEFAULT_FLAM_SPACING_MS,
+            gate: MAX_STEP_GATE,
         }
+    }
+}
 
-        return Err(format!("unknown kit line: {line}"));
+impl PatternStep {
+    pub fn is_valid(&self) -> bool {
+        self.velocity <= 127
+            && self.probability <= MAX_STEP_PROBABILITY
+            && self.condition.is_valid()
+            && self.flam_spacing_ms <= MAX_FLAM_SPACING_MS
+            && (!self.flam || self.flam_spacing_ms > 0)
+            && (1..=MAX_STEP_GATE).contains(&self.gate)
     }
+}
 
-    Ok(kit)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BarMarker {
+    pub bar: usize,
+    pub label: String,
 }
 
-fn serialize_pattern_body(pattern: &Pattern) -> Vec<String> {
-    let mut lines = Vec::new();
-    lines.push(format!("name={}", encode_text(&pattern.name)));
-    lines.push(format!("swing={}", format_f32(pattern.swing)));
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PatternScale {
+    Half,
+    #[default]
+    Normal,
+    Double,
+}
 
-    for track_index in 0..TRACK_COUNT {
-        for step_index in 0..STEPS_PER_PATTERN {
-            let step = pattern.steps[track_index][step_index];
-            lines.push(format!(
-                "step|{}|{}|{}|{}",
-                track_index,
-                step_index,
-                if step.active { 1 } else { 0 },
-                step.velocity
-            ));
+impl PatternScale {
+    pub const ALL: [PatternScale; 3] = [
+        PatternScale::Half,
+        PatternScale::Normal,
+        PatternScale::Double,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PatternScale::Half => "half",
+            PatternScale::Normal => "normal",
+            PatternScale::Double => "double",
         }
     }
 
-    lines
-}
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scale| scale.name() == name)
+    }
 
-fn deserialize_pattern_body(lines: &[String]) -> Result<Pattern, String> {
-    let mut pattern = Pattern::default();
-    for line in lines {
-        if let Some(name_hex) = line.strip_prefix("name=") {
-            pattern.name = decode_text(name_hex)?;
-            continue;
+    pub fn speed(self) -> f64 {
+        match self {
+            PatternScale::Half => 0.5,
+            PatternScale::Normal => 1.0,
+            PatternScale::Double => 2.0,
         }
+    }
+}
 
-        if let Some(value) = line.strip_prefix("swing=") {
-            pattern.set_swing(parse_f32(value, "pattern.swing")?);
-            continue;
-        }
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AccentClip {
+    // Boosted velocities saturate at 127.
+    #[default]
+    Clamp,
+    // The boost shrinks with the headroom left above the step velocity, so
+    // loud steps lift less and never flatten against the ceiling.
+    Headroom,
+}
 
-        if let Some(rest) = line.strip_prefix("step|") {
-            let fields: Vec<&str> = rest.split('|').collect();
-            if fields.len() != 4 {
-                return Err(format!("invalid step line: {line}"));
-            }
+impl AccentClip {
+    pub const ALL: [AccentClip; 2] = [AccentClip::Clamp, AccentClip::Headroom];
 
-            let track_index = parse_usize(fields[0], "step.track_index")?;
-            let step_index = parse_usize(fields[1], "step.step_index")?;
-            let active = match fields[2] {
-                "0" => false,
-                "1" => true,
-                _ => return Err(format!("invalid step active value: {}", fields[2])),
-            };
-            let velocity = parse_u8(fields[3], "step.velocity")?;
-            if velocity > 127 {
-                return Err(format!(
-                    "step velocity out of semantic range: {velocity} (max 127)"
-                ));
-            }
-            if !pattern.set_step(track_index, step_index, PatternStep { active, velocity }) {
-                return Err(format!("step index out of range: {line}"));
-            }
-            continue;
+    pub fn name(self) -> &'static str {
+        match self {
+            AccentClip::Clamp => "clamp",
+            AccentClip::Headroom => "headroom",
         }
+    }
 
-        return Err(format!("unknown pattern line: {line}"));
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|clip| clip.name() == name)
     }
+}
 
-    Ok(pattern)
+// How much an accent adds to a step's velocity. `boosts` are sampled at
+// evenly spaced input velocities from 0 to 127 and interpolated between.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AccentCurve {
+    pub boosts: [u8; ACCENT_CURVE_POINTS],
+    pub clip: AccentClip,
 }
 
-pub fn save_kit_to_text(kit: &Kit) -> String {
-    let mut lines = Vec::new();
-    lines.push("FF_KIT_V1".to_string());
-    lines.extend(serialize_kit_body(kit));
-    lines.join("\n")
+impl Default for AccentCurve {
+    fn default() -> Self {
+        Self::flat(DEFAULT_ACCENT_AMOUNT)
+    }
 }
 
-pub fn load_kit_from_text(text: &str) -> Result<Kit, String> {
-    let mut lines = text.lines();
-    let header = lines
-        .next()
-        .ok_or_else(|| "missing kit header".to_string())?;
-    if header != "FF_KIT_V1" {
-        return Err(format!("unexpected kit header: {header}"));
+impl AccentCurve {
+    pub fn flat(amount: u8) -> Self {
+        Self {
+            boosts: [amount; ACCENT_CURVE_POINTS],
+            clip: AccentClip::Clamp,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.boosts.iter().all(|boost| *boost <= MAX_ACCENT_AMOUNT)
+    }
+
+    pub fn boost_at(&self, velocity: u8) -> u32 {
+        let span = (ACCENT_CURVE_POINTS - 1) as u32;
+        let scaled = u32::from(velocity.min(127)) * span;
+        let index = (scaled / 127).min(span - 1) as usize;
+        let fraction = scaled - index as u32 * 127;
+        let low = u32::from(self.boosts[index]);
+        let high = u32::from(self.boosts[index + 1]);
+        (low * (127 - fraction) + high * fraction + 63) / 127
+    }
+
+    pub fn apply(&self, velocity: u8) -> u8 {
+        let velocity = u32::from(velocity.min(127));
+        let boost = match self.clip {
+            AccentClip::Clamp => self.boost_at(velocity as u8),
+            AccentClip::Headroom => (self.boost_at(velocity as u8) * (127 - velocity) + 63) / 127,
+        };
+        (velocity + boost).min(127) as u8
     }
-    deserialize_kit_body(&lines.map(|line| line.to_string()).collect::<Vec<_>>())
 }
 
-pub fn save_pattern_to_text(pattern: &Pattern) -> String {
-    let mut lines = Vec::new();
-    lines.push("FF_PATTERN_V1".to_string());
-    lines.extend(serialize_pattern_body(pattern));
-    lines.join("\n")
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PlaybackDirection {
+    #[default]
+    Forward,
+    Reverse,
+    PingPong,
+    Random,
 }
 
-pub fn load_pattern_from_text(text: &str) -> Result<Pattern, String> {
-    let mut lines = text.lines();
-    let header = lines
-        .next()
-        .ok_or_else(|| "missing pattern header".to_string())?;
-    if header != "FF_PATTERN_V1" {
-        return Err(format!("unexpected pattern header: {header}"));
+impl PlaybackDirection {
+    pub const ALL: [PlaybackDirection; 4] = [
+        PlaybackDirection::Forward,
+        PlaybackDirection::Reverse,
+        PlaybackDirection::PingPong,
+        PlaybackDirection::Random,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PlaybackDirection::Forward => "forward",
+            PlaybackDirection::Reverse => "reverse",
+            PlaybackDirection::PingPong => "pingpong",
+            PlaybackDirection::Random => "random",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|direction| direction.name() == name)
     }
-    deserialize_pattern_body(&lines.map(|line| line.to_string()).collect::<Vec<_>>())
 }
 
-pub fn save_project_to_text(project: &Project) -> String {
-    let mut lines = Vec::new();
-    lines.push("FF_PROJECT_V1".to_string());
-    lines.push(format!("name={}", encode_text(&project.name)));
-    lines.push(format!(
-        "active_kit={}",
-        project
-            .active_kit
-            .map(|value| value.to_string())
-            .unwrap_or_else(|| "-1".to_string())
-    ));
-    lines.push(format!(
-        "active_pattern={}",
-        project
-            .active_pattern
-            .map(|value| value.to_string())
-            .unwrap_or_else(|| "-1".to_string())
-    ));
+// Per-track step lengths, counted in 24ths of a beat so straight and triplet
+// grids share one integer timeline.
+pub const RESOLUTION_TICKS_PER_BEAT: u32 = 24;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum StepResolution {
+    #[default]
+    Sixteenth,
+    EighthTriplet,
+    SixteenthTriplet,
+    ThirtySecond,
+}
+
+impl StepResolution {
+    pub const ALL: [StepResolution; 4] = [
+        StepResolution::Sixteenth,
+        StepResolution::EighthTriplet,
+        StepResolution::SixteenthTriplet,
+        StepResolution::ThirtySecond,
+    ];
 
-    for kit in &project.kits {
-        lines.push("BEGIN_KIT".to_string());
-        lines.extend(serialize_kit_body(kit));
-        lines.push("END_KIT".to_string());
+    pub fn name(self) -> &'static str {
+        match self {
+            StepResolution::Sixteenth => "1/16",
+            StepResolution::EighthTriplet => "1/8t",
+            StepResolution::SixteenthTriplet => "1/16t",
+            StepResolution::ThirtySecond => "1/32",
+        }
     }
 
-    for pattern in &project.patterns {
-        lines.push("BEGIN_PATTERN".to_string());
-        lines.extend(serialize_pattern_body(pattern));
-        lines.push("END_PATTERN".to_string());
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|resolution| resolution.name() == name)
     }
 
-    lines.join("\n")
+    pub fn ticks_per_step(self) -> u32 {
+        match self {
+            StepResolution::Sixteenth => 6,
+            StepResolution::EighthTriplet => 8,
+            StepResolution::SixteenthTriplet => 4,
+            StepResolution::ThirtySecond => 3,
+        }
+    }
 }
 
-pub fn load_project_from_text(text: &str) -> Result<Project, String> {
-    let mut lines = text.lines().peekable();
-    let header = lines
-        .next()
-        .ok_or_else(|| "missing project header".to_string())?;
-    if header != "FF_PROJECT_V1" {
-        return Err(format!("unexpected project header: {header}"));
-    }
+// Which steps swing on tracks with a non-16th resolution: every other step
+// of the track's own grid, or (the older behavior) any step that lands in an
+// odd 16th of the pattern.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SwingGrid {
+    #[default]
+    Track,
+    Sixteenth,
+}
 
-    let mut project = Project::default();
-    let mut active_kit_raw: Option<isize> = None;
-    let mut active_pattern_raw: Option<isize> = None;
+impl SwingGrid {
+    pub const ALL: [SwingGrid; 2] = [SwingGrid::Track, SwingGrid::Sixteenth];
 
-    while let Some(line) = lines.next() {
-        if let Some(name_hex) = line.strip_prefix("name=") {
-            project.name = decode_text(name_hex)?;
-            continue;
+    pub fn name(self) -> &'static str {
+        match self {
+            SwingGrid::Track => "track",
+            SwingGrid::Sixteenth => "sixteenth",
         }
+    }
 
-        if let Some(value) = line.strip_prefix("active_kit=") {
-            active_kit_raw = Some(
-                value
-                    .parse::<isize>()
-                    .map_err(|_| format!("invalid active_kit value: {value}"))?,
-            );
-            continue;
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|grid| grid.name() == name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TempoChange {
+    #[default]
+    Immediate,
+    Smooth,
+}
+
+impl TempoChange {
+    pub const ALL: [TempoChange; 2] = [TempoChange::Immediate, TempoChange::Smooth];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TempoChange::Immediate => "immediate",
+            TempoChange::Smooth => "smooth",
         }
+    }
 
-        if let Some(value) = line.strip_prefix("active_pattern=") {
-            active_pattern_raw = Some(
-                value
-                    .parse::<isize>()
-                    .map_err(|_| format!("invalid active_pattern value: {value}"))?,
-            );
-            continue;
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|change| change.name() == name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PatternTempo {
+    pub bpm: f32,
+    pub change: TempoChange,
+}
+
+impl PatternTempo {
+    pub fn is_valid(&self) -> bool {
+        (MIN_PATTERN_BPM..=MAX_PATTERN_BPM).contains(&self.bpm)
+    }
+}
+
+// Timing offsets are late-only fractions of a step, so a groove layered on
+// top of swing still leaves every step interval positive. Velocity offsets
+// are added to each hit landing in the slot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GrooveTemplate {
+    pub name: String,
+    pub timing: [f32; GROOVE_SLOT_COUNT],
+    pub velocity: [i8; GROOVE_SLOT_COUNT],
+}
+
+impl GrooveTemplate {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            timing: [0.0; GROOVE_SLOT_COUNT],
+            velocity: [0; GROOVE_SLOT_COUNT],
         }
+    }
 
-        if line == "BEGIN_KIT" {
-            let mut block = Vec::new();
-            loop {
-                let next_line = lines
-                    .next()
-                    .ok_or_else(|| "unterminated kit block".to_string())?;
-                if next_line == "END_KIT" {
-                    break;
-                }
-                block.push(next_line.to_string());
-            }
-            project.kits.push(deserialize_kit_body(&block)?);
-            continue;
+    // MPC-style swing percentages place the second sixteenth of each eighth
+    // at `percent` of the eighth, i.e. `percent / 50 - 1` of a step late.
+    pub fn builtin(name: &str) -> Option<Self> {
+        let mut groove = Self::new(name);
+        let (late, accent) = match name {
+            "mpc54" => (0.08, 0),
+            "mpc58" => (0.16, 0),
+            "shuffle" => (1.0 / 3.0, -12),
+            _ => return None,
+        };
+        for slot in (1..GROOVE_SLOT_COUNT).step_by(2) {
+            groove.timing[slot] = late;
+            groove.velocity[slot] = accent;
         }
+        Some(groove)
+    }
 
-        if line == "BEGIN_PATTERN" {
-            let mut block = Vec::new();
-            loop {
-                let next_line = lines
-                    .next()
-                    .ok_or_else(|| "unterminated pattern block".to_string())?;
-                if next_line == "END_PATTERN" {
-                    break;
+    pub fn is_builtin(name: &str) -> bool {
+        BUILTIN_GROOVE_NAMES.contains(&name)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        !self.name.is_empty()
+            && self.name.chars().count() <= MAX_TRACK_NAME_CHARS
+            && self
+                .timing
+                .iter()
+                .all(|offset| (0.0..=MAX_GROOVE_TIMING_OFFSET).contains(offset))
+            && self
+                .velocity
+                .iter()
+                .all(|offset| offset.unsigned_abs() <= MAX_GROOVE_VELOCITY_OFFSET.unsigned_abs())
+    }
+
+    pub fn timing_offset(&self, step_index: usize) -> f32 {
+        self.timing[step_index % GROOVE_SLOT_COUNT]
+    }
+
+    pub fn velocity_offset(&self, step_index: usize) -> i8 {
+        self.velocity[step_index % GROOVE_SLOT_COUNT]
+    }
+}
+
+pub const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum MusicalScale {
+    #[default]
+    Chromatic,
+    Major,
+    Minor,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    Locrian,
+    HarmonicMinor,
+    MajorPentatonic,
+    MinorPentatonic,
+}
+
+impl MusicalScale {
+    pub const ALL: [MusicalScale; 11] = [
+        MusicalScale::Chromatic,
+        MusicalScale::Major,
+        MusicalScale::Minor,
+        MusicalScale::Dorian,
+        MusicalScale::Phrygian,
+        MusicalScale::Lydian,
+        MusicalScale::Mixolydian,
+        MusicalScale::Locrian,
+        MusicalScale::HarmonicMinor,
+        MusicalScale::MajorPentatonic,
+        MusicalScale::MinorPentatonic,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MusicalScale::Chromatic => "chromatic",
+            MusicalScale::Major => "major",
+            MusicalScale::Minor => "minor",
+            MusicalScale::Dorian => "dorian",
+            MusicalScale::Phrygian => "phrygian",
+            MusicalScale::Lydian => "lydian",
+            MusicalScale::Mixolydian => "mixolydian",
+            MusicalScale::Locrian => "locrian",
+            MusicalScale::HarmonicMinor => "harmonic_minor",
+            MusicalScale::MajorPentatonic => "major_pentatonic",
+            MusicalScale::MinorPentatonic => "minor_pentatonic",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scale| scale.name() == name)
+    }
+
+    pub fn intervals(self) -> &'static [u8] {
+        match self {
+            MusicalScale::Chromatic => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            MusicalScale::Major => &[0, 2, 4, 5, 7, 9, 11],
+            MusicalScale::Minor => &[0, 2, 3, 5, 7, 8, 10],
+            MusicalScale::Dorian => &[0, 2, 3, 5, 7, 9, 10],
+            MusicalScale::Phrygian => &[0, 1, 3, 5, 7, 8, 10],
+            MusicalScale::Lydian => &[0, 2, 4, 6, 7, 9, 11],
+            MusicalScale::Mixolydian => &[0, 2, 4, 5, 7, 9, 10],
+            MusicalScale::Locrian => &[0, 1, 3, 5, 6, 8, 10],
+            MusicalScale::HarmonicMinor => &[0, 2, 3, 5, 7, 8, 11],
+            MusicalScale::MajorPentatonic => &[0, 2, 4, 7, 9],
+            MusicalScale::MinorPentatonic => &[0, 3, 5, 7, 10],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MusicalKey {
+    pub root: u8,
+    pub scale: MusicalScale,
+}
+
+impl MusicalKey {
+    pub fn is_valid(&self) -> bool {
+        usize::from(self.root) < NOTE_NAMES.len()
+    }
+
+    pub fn root_name(&self) -> &'static str {
+        NOTE_NAMES[usize::from(self.root) % NOTE_NAMES.len()]
+    }
+
+    pub fn contains_semitone(&self, semitone: i32) -> bool {
+        let pitch_class = (semitone - i32::from(self.root)).rem_euclid(12) as u8;
+        self.scale.intervals().contains(&pitch_class)
+    }
+
+    pub fn to_text(&self) -> String {
+        format!("{}|{}", self.root_name(), self.scale.name())
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let (root, scale) = text
+            .split_once('|')
+            .ok_or_else(|| format!("invalid key value: {text}"))?;
+        Ok(Self {
+            root: NOTE_NAMES
+                .iter()
+                .position(|name| *name == root)
+                .ok_or_else(|| format!("unknown key root: {root}"))? as u8,
+            scale: MusicalScale::from_name(scale)
+                .ok_or_else(|| format!("unknown key scale: {scale}"))?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct VariationStep {
+    pub variation: u8,
+    pub track_index: u8,
+    pub step_index: u8,
+    pub step: PatternStep,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MuteSpan {
+    pub track_index: u8,
+    pub first_bar: usize,
+    pub last_bar: usize,
+}
+
+impl MuteSpan {
+    pub fn contains(&self, track_index: usize, bar: usize) -> bool {
+        usize::from(self.track_index) == track_index
+            && (self.first_bar..=self.last_bar).contains(&bar)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum LockTarget {
+    Gain,
+    FilterCutoff,
+    EnvelopeDecay,
+    Pitch,
+}
+
+impl LockTarget {
+    pub const ALL: [LockTarget; 4] = [
+        LockTarget::Gain,
+        LockTarget::FilterCutoff,
+        LockTarget::EnvelopeDecay,
+        LockTarget::Pitch,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LockTarget::Gain => "gain",
+            LockTarget::FilterCutoff => "cutoff",
+            LockTarget::EnvelopeDecay => "decay",
+            LockTarget::Pitch => "pitch",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|target| target.name() == name)
+    }
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParameterLock {
+    pub track_index: u8,
+    pub step_index: u8,
+    pub target: LockTarget,
+    pub value: f32,
+}
+
+impl ParameterLock {
+    pub fn is_valid(&self) -> bool {
+        usize::from(self.track_index) < TRACK_COUNT
+            && usize::from(self.step_index) < MAX_PATTERN_STEPS
+            && (0.0..=1.0).contains(&self.value)
+    }
+
+    pub fn key(&self) -> (u8, u8, LockTarget) {
+        (self.track_index, self.step_index, self.target)
+    }
+}
+
+pub fn variation_name(variation: u8) -> Option<char> {
+    (usize::from(variation) < PATTERN_VARIATION_COUNT).then(|| char::from(b'A' + variation))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern {
+    pub name: String,
+    pub swing: f32,
+    pub steps: [[PatternStep; MAX_PATTERN_STEPS]; TRACK_COUNT],
+    pub bar_count: usize,
+    // Set when the pattern ends part-way through its last bar.
+    pub length_steps: Option<usize>,
+    pub markers: Vec<BarMarker>,
+    pub scale: PatternScale,
+    pub tempo: Option<PatternTempo>,
+    pub variations: Vec<VariationStep>,
+    pub mutes: Vec<MuteSpan>,
+    pub locks: Vec<ParameterLock>,
+    pub directions: [PlaybackDirection; TRACK_COUNT],
+    pub resolutions: [StepResolution; TRACK_COUNT],
+    pub swing_grid: SwingGrid,
+    pub default_steps: [PatternStep; TRACK_COUNT],
+    pub groove: Option<String>,
+    pub accent: AccentCurve,
+}
+
+impl Default for Pattern {
+    fn default() -> Self {
+        Self {
+            name: "pattern".to_string(),
+            swing: 0.0,
+            steps: [[PatternStep::default(); MAX_PATTERN_STEPS]; TRACK_COUNT],
+            bar_count: 1,
+            length_steps: None,
+            markers: Vec::new(),
+            scale: PatternScale::Normal,
+            tempo: None,
+            variations: Vec::new(),
+            mutes: Vec::new(),
+            locks: Vec::new(),
+            directions: [PlaybackDirection::Forward; TRACK_COUNT],
+            resolutions: [StepResolution::Sixteenth; TRACK_COUNT],
+            swing_grid: SwingGrid::Track,
+            default_steps: [PatternStep::default(); TRACK_COUNT],
+            groove: None,
+            accent: AccentCurve::default(),
+        }
+    }
+}
+
+impl Pattern {
+    pub fn step_count(&self) -> usize {
+        self.length_steps
+            .unwrap_or(self.bar_count * STEPS_PER_PATTERN)
+    }
+
+    pub fn set_bar_count(&mut self, bar_count: usize) -> bool {
+        if bar_count == 0 || bar_count > MAX_PATTERN_BARS {
+            return false;
+        }
+
+        self.bar_count = bar_count;
+        self.length_steps = None;
+        self.truncate_to_step_count();
+        true
+    }
+
+    pub fn set_step_count(&mut self, step_count: usize) -> bool {
+        if step_count == 0 || step_count > MAX_PATTERN_STEPS {
+            return false;
+        }
+
+        self.set_bar_count(step_count.div_ceil(STEPS_PER_PATTERN));
+        self.length_steps = (!step_count.is_multiple_of(STEPS_PER_PATTERN)).then_some(step_count);
+        self.truncate_to_step_count();
+        true
+    }
+
+    fn truncate_to_step_count(&mut self) {
+        let bar_count = self.bar_count;
+        let step_count = self.step_count();
+        for track in &mut self.steps {
+            track[step_count..].fill(PatternStep::default());
+        }
+        self.markers.retain(|marker| marker.bar < bar_count);
+        self.variations
+            .retain(|variation| usize::from(variation.step_index) < step_count);
+        self.mutes.retain(|span| span.first_bar < bar_count);
+        for span in &mut self.mutes {
+            span.last_bar = span.last_bar.min(bar_count - 1);
+        }
+        self.locks
+            .retain(|lock| usize::from(lock.step_index) < step_count);
+    }
+
+    pub fn set_track_direction(
+        &mut self,
+        track_index: usize,
+        direction: PlaybackDirection,
+    ) -> bool {
+        let Some(slot) = self.directions.get_mut(track_index) else {
+            return false;
+        };
+        *slot = direction;
+        true
+    }
+
+    pub fn set_track_resolution(&mut self, track_index: usize, resolution: StepResolution) -> bool {
+        let Some(slot) = self.resolutions.get_mut(track_index) else {
+            return false;
+        };
+        *slot = resolution;
+        true
+    }
+
+    // The template a step starts from when it is toggled on; stored inactive.
+    pub fn set_track_default_step(&mut self, track_index: usize, step: PatternStep) -> bool {
+        if track_index >= TRACK_COUNT || !step.is_valid() {
+            return false;
+        }
+        self.default_steps[track_index] = PatternStep {
+            active: false,
+            ..step
+        };
+        true
+    }
+
+    pub fn set_parameter_lock(&mut self, lock: ParameterLock) -> bool {
+        if !lock.is_valid() || usize::from(lock.step_index) >= self.step_count() {
+            return false;
+        }
+
+        match self
+            .locks
+            .binary_search_by_key(&lock.key(), ParameterLock::key)
+        {
+            Ok(index) => self.locks[index] = lock,
+            Err(index) => self.locks.insert(index, lock),
+        }
+        true
+    }
+
+    pub fn clear_parameter_lock(
+        &mut self,
+        track_index: usize,
+        step_index: usize,
+        target: LockTarget,
+    ) -> bool {
+        let before = self.locks.len();
+        self.locks.retain(|lock| {
+            (
+                usize::from(lock.track_index),
+                usize::from(lock.step_index),
+                lock.target,
+            ) != (track_index, step_index, target)
+        });
+        self.locks.len() != before
+    }
+
+    pub fn parameter_lock(
+        &self,
+        track_index: usize,
+        step_index: usize,
+        target: LockTarget,
+    ) -> Option<f32> {
+        self.locks
+            .iter()
+            .find(|lock| {
+                (
+                    usize::from(lock.track_index),
+                    usize::from(lock.step_index),
+                    lock.target,
+                ) == (track_index, step_index, target)
+            })
+            .map(|lock| lock.value)
+    }
+
+    pub fn set_mute_span(&mut self, track_index: usize, first_bar: usize, last_bar: usize) -> bool {
+        if track_index >= TRACK_COUNT || first_bar > last_bar || last_bar >= self.bar_count {
+            return false;
+        }
+
+        let mut span = MuteSpan {
+            track_index: track_index as u8,
+            first_bar,
+            last_bar,
+        };
+        self.mutes.retain(|existing| {
+            let touches = usize::from(existing.track_index) == track_index
+                && existing.first_bar <= span.last_bar + 1
+                && span.first_bar <= existing.last_bar + 1;
+            if touches {
+                span.first_bar = span.first_bar.min(existing.first_bar);
+                span.last_bar = span.last_bar.max(existing.last_bar);
+            }
+            !touches
+        });
+        let index = self.mutes.partition_point(|existing| {
+            (existing.track_index, existing.first_bar) < (span.track_index, span.first_bar)
+        });
+        self.mutes.insert(index, span);
+        true
+    }
+
+    pub fn clear_mute_spans(&mut self, track_index: usize) {
+        self.mutes
+            .retain(|span| usize::from(span.track_index) != track_index);
+    }
+
+    pub fn track_muted_at(&self, track_index: usize, bar: usize) -> bool {
+        self.mutes
+            .iter()
+            .any(|span| span.contains(track_index, bar))
+    }
+
+    pub fn set_variation_step(
+        &mut self,
+        variation: u8,
+        track_index: usize,
+        step_index: usize,
+        step: Option<PatternStep>,
+    ) -> bool {
+        if variation == 0
+            || usize::from(variation) >= PATTERN_VARIATION_COUNT
+            || track_index >= TRACK_COUNT
+            || step_index >= self.step_count()
+            || step.is_some_and(|step| !step.is_valid())
+        {
+            return false;
+        }
+
+        let key = (variation, track_index as u8, step_index as u8);
+        let position = self.variations.partition_point(|existing| {
+            (
+                existing.variation,
+                existing.track_index,
+                existing.step_index,
+            ) < key
+        });
+        let existing = self.variations.get(position).is_some_and(|existing| {
+            (
+                existing.variation,
+                existing.track_index,
+                existing.step_index,
+            ) == key
+        });
+        match (step, existing) {
+            (Some(step), true) => self.variations[position].step = step,
+            (Some(step), false) => self.variations.insert(
+                position,
+                VariationStep {
+                    variation,
+                    track_index: key.1,
+                    step_index: key.2,
+                    step,
+                },
+            ),
+            (None, true) => {
+                self.variations.remove(position);
+            }
+            (None, false) => {}
+        }
+        true
+    }
+
+    pub fn clear_variation(&mut self, variation: u8) {
+        self.variations
+            .retain(|existing| existing.variation != variation);
+    }
+
+    pub fn variation_step(
+        &self,
+        variation: u8,
+        track_index: usize,
+        step_index: usize,
+    ) -> Option<PatternStep> {
+        let base = self.step(track_index, step_index)?;
+        Some(
+            self.variations
+                .iter()
+                .find(|existing| {
+                    existing.variation == variation
+                        && usize::from(existing.track_index) == track_index
+                        && usize::from(existing.step_index) == step_index
+                })
+                .map_or(base, |existing| existing.step),
+        )
+    }
+
+    pub fn capture_variation(&mut self, variation: u8, edited: &Pattern) -> bool {
+        if variation == 0 || usize::from(variation) >= PATTERN_VARIATION_COUNT {
+            return false;
+        }
+
+        self.clear_variation(variation);
+        for track_index in 0..TRACK_COUNT {
+            for step_index in 0..self.step_count() {
+                let step = edited.step(track_index, step_index).unwrap_or_default();
+                if step != self.steps[track_index][step_index] {
+                    self.set_variation_step(variation, track_index, step_index, Some(step));
                 }
-                block.push(next_line.to_string());
             }
-            project.patterns.push(deserialize_pattern_body(&block)?);
-            continue;
         }
+        true
+    }
+
+    pub fn set_bar_marker(&mut self, bar: usize, label: &str) -> bool {
+        if bar >= self.bar_count {
+            return false;
+        }
+
+        self.clear_bar_marker(bar);
+        let index = self.markers.partition_point(|marker| marker.bar < bar);
+        self.markers.insert(
+            index,
+            BarMarker {
+                bar,
+                label: label.to_string(),
+            },
+        );
+        true
+    }
+
+    pub fn clear_bar_marker(&mut self, bar: usize) {
+        self.markers.retain(|marker| marker.bar != bar);
+    }
+
+    pub fn bar_marker(&self, bar: usize) -> Option<&str> {
+        self.markers
+            .iter()
+            .find(|marker| marker.bar == bar)
+            .map(|marker| marker.label.as_str())
+    }
+
+    pub fn set_step(&mut self, track_index: usize, step_index: usize, step: PatternStep) -> bool {
+        if track_index >= TRACK_COUNT || step_index >= self.step_count() {
+            return false;
+        }
+
+        self.steps[track_index][step_index] = step;
+        true
+    }
+
+    pub fn step(&self, track_index: usize, step_index: usize) -> Option<PatternStep> {
+        if track_index >= TRACK_COUNT || step_index >= self.step_count() {
+            return None;
+        }
+
+        Some(self.steps[track_index][step_index])
+    }
+
+    pub fn set_swing(&mut self, swing: f32) {
+        self.swing = swing.clamp(0.0, 0.45);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct OutputRouting {
+    track_outputs: [u8; TRACK_COUNT],
+}
+
+impl OutputRouting {
+    pub fn set_track_output(&mut self, track_index: usize, output_pair: usize) -> bool {
+        if track_index >= TRACK_COUNT || output_pair >= MAX_OUTPUT_PAIRS {
+            return false;
+        }
+
+        self.track_outputs[track_index] = output_pair as u8;
+        true
+    }
+
+    pub fn track_output(&self, track_index: usize) -> Option<usize> {
+        self.track_outputs
+            .get(track_index)
+            .map(|pair| usize::from(*pair))
+    }
+
+    pub fn output_pair_count(&self) -> usize {
+        usize::from(self.track_outputs.iter().copied().max().unwrap_or(0)) + 1
+    }
+
+    pub fn tracks_on_output(&self, output_pair: usize) -> Vec<usize> {
+        (0..TRACK_COUNT)
+            .filter(|track_index| self.track_output(*track_index) == Some(output_pair))
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TrackInputs {
+    armed: [bool; TRACK_COUNT],
+    monitoring: [bool; TRACK_COUNT],
+}
+
+impl Default for TrackInputs {
+    fn default() -> Self {
+        Self {
+            armed: [false; TRACK_COUNT],
+            monitoring: [true; TRACK_COUNT],
+        }
+    }
+}
+
+impl TrackInputs {
+    pub fn all_armed() -> Self {
+        Self {
+            armed: [true; TRACK_COUNT],
+            ..Self::default()
+        }
+    }
+
+    pub fn set_armed(&mut self, track_index: usize, armed: bool) -> bool {
+        match self.armed.get_mut(track_index) {
+            Some(slot) => {
+                *slot = armed;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_armed(&self, track_index: usize) -> bool {
+        self.armed.get(track_index).copied().unwrap_or(false)
+    }
+
+    pub fn set_monitoring(&mut self, track_index: usize, monitoring: bool) -> bool {
+        match self.monitoring.get_mut(track_index) {
+            Some(slot) => {
+                *slot = monitoring;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_monitoring(&self, track_index: usize) -> bool {
+        self.monitoring.get(track_index).copied().unwrap_or(false)
+    }
+
+    pub fn armed_tracks(&self) -> Vec<usize> {
+        (0..TRACK_COUNT)
+            .filter(|track_index| self.is_armed(*track_index))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Metronome {
+    pub enabled: bool,
+    pub accent_sample_id: String,
+    pub click_sample_id: String,
+    pub gain_db: f32,
+    pub output_pair: u8,
+    pub include_in_render: bool,
+}
+
+impl Default for Metronome {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            accent_sample_id: String::new(),
+            click_sample_id: String::new(),
+            gain_db: 0.0,
+            output_pair: 0,
+            include_in_render: false,
+        }
+    }
+}
+
+// The device settings a project was made with. Hosts may use them to
+// configure audio I/O; nothing in the project depends on them.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct AudioHints {
+    pub sample_rate_hz: Option<u32>,
+    pub block_size: Option<u32>,
+}
+
+impl AudioHints {
+    pub fn is_valid(&self) -> bool {
+        self.sample_rate_hz
+            .is_none_or(|rate| (MIN_HINT_SAMPLE_RATE_HZ..=MAX_HINT_SAMPLE_RATE_HZ).contains(&rate))
+            && self
+                .block_size
+                .is_none_or(|size| (1..=MAX_HINT_BLOCK_SIZE).contains(&size))
+    }
+}
+
+impl Metronome {
+    pub fn is_valid(&self) -> bool {
+        usize::from(self.output_pair) < MAX_OUTPUT_PAIRS
+            && !self.gain_db.is_nan()
+            && self.gain_db <= GAIN_DB_MAX
+    }
+
+    pub fn renders(&self) -> bool {
+        self.enabled && self.include_in_render
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrackFreeze {
+    pub kit_index: usize,
+    pub pattern_index: usize,
+    pub track_index: u8,
+    pub bounce_sample_id: String,
+    pub original_kit: Kit,
+    pub original_steps: Vec<PatternStep>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ProgramChange {
+    pub program: u8,
+    pub pattern_index: Option<usize>,
+    pub kit_index: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SongEntry {
+    pub pattern_index: usize,
+    pub repeats: u8,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SampleRemapReport {
+    pub replaced: usize,
+    pub unresolved: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Project {
+    pub name: String,
+    pub kits: Vec<Kit>,
+    pub active_kit: Option<usize>,
+    pub patterns: Vec<Pattern>,
+    pub active_pattern: Option<usize>,
+    pub output_routing: OutputRouting,
+    pub track_inputs: TrackInputs,
+    pub random_seed: u64,
+    pub humanize: HumanizeDepth,
+    pub audio_hints: AudioHints,
+    pub frozen_tracks: Vec<TrackFreeze>,
+    pub key: MusicalKey,
+    pub metronome: Metronome,
+    pub program_changes: Vec<ProgramChange>,
+    pub grooves: Vec<GrooveTemplate>,
+    pub song: Vec<SongEntry>,
+    pub song_loop: bool,
+}
+
+impl Project {
+    pub fn set_active_kit(&mut self, index: usize) -> bool {
+        if index >= self.kits.len() {
+            return false;
+        }
+
+        self.active_kit = Some(index);
+        true
+    }
+
+    pub fn set_active_pattern(&mut self, index: usize) -> bool {
+        if index >= self.patterns.len() {
+            return false;
+        }
+
+        self.active_pattern = Some(index);
+        true
+    }
+
+    pub fn program_change(&self, program: u8) -> Option<&ProgramChange> {
+        self.program_changes
+            .iter()
+            .find(|change| change.program == program)
+    }
+
+    pub fn set_program_change(&mut self, change: ProgramChange) -> bool {
+        if change.program >= MIDI_PROGRAM_COUNT
+            || (change.pattern_index.is_none() && change.kit_index.is_none())
+            || change
+                .pattern_index
+                .is_some_and(|index| index >= self.patterns.len())
+            || change
+                .kit_index
+                .is_some_and(|index| index >= self.kits.len())
+        {
+            return false;
+        }
+
+        self.program_changes
+            .retain(|existing| existing.program != change.program);
+        self.program_changes.push(change);
+        self.program_changes
+            .sort_by_key(|existing| existing.program);
+        true
+    }
+
+    pub fn clear_program_change(&mut self, program: u8) -> bool {
+        let before = self.program_changes.len();
+        self.program_changes
+            .retain(|existing| existing.program != program);
+        self.program_changes.len() != before
+    }
+
+    pub fn apply_program_change(&mut self, program: u8) -> bool {
+        let Some(change) = self.program_change(program).copied() else {
+            return false;
+        };
+        if let Some(pattern_index) = change.pattern_index {
+            self.set_active_pattern(pattern_index);
+        }
+        if let Some(kit_index) = change.kit_index {
+            self.set_active_kit(kit_index);
+        }
+        true
+    }
+
+    pub fn set_song(&mut self, entries: &[SongEntry]) -> bool {
+        if entries
+            .iter()
+            .any(|entry| entry.repeats == 0 || entry.pattern_index >= self.patterns.len())
+        {
+            return false;
+        }
+
+        self.song = entries.to_vec();
+        true
+    }
+
+    // User grooves resolve first; built-in names are reserved so a project
+    // can never shadow them.
+    pub fn groove(&self, name: &str) -> Option<GrooveTemplate> {
+        self.grooves
+            .iter()
+            .find(|groove| groove.name == name)
+            .cloned()
+            .or_else(|| GrooveTemplate::builtin(name))
+    }
+
+    pub fn set_groove(&mut self, groove: GrooveTemplate) -> bool {
+        if !groove.is_valid() || GrooveTemplate::is_builtin(&groove.name) {
+            return false;
+        }
+
+        match self
+            .grooves
+            .iter_mut()
+            .find(|existing| existing.name == groove.name)
+        {
+            Some(existing) => *existing = groove,
+            None => self.grooves.push(groove),
+        }
+        true
+    }
+
+    pub fn clear_groove(&mut self, name: &str) -> bool {
+        let before = self.grooves.len();
+        self.grooves.retain(|groove| groove.name != name);
+        self.grooves.len() != before
+    }
+
+    pub fn track_freeze(&self, track_index: u8) -> Option<&TrackFreeze> {
+        let kit_index = self.active_kit.unwrap_or(0);
+        self.frozen_tracks
+            .iter()
+            .find(|freeze| freeze.kit_index == kit_index && freeze.track_index == track_index)
+    }
+
+    pub fn freeze_track(&mut self, track_index: u8, bounce_sample_id: &str) -> bool {
+        let kit_index = self.active_kit.unwrap_or(0);
+        let pattern_index = self.active_pattern.unwrap_or(0);
+        let track = usize::from(track_index);
+        if track >= TRACK_COUNT
+            || bounce_sample_id.is_empty()
+            || self.track_freeze(track_index).is_some()
+            || kit_index >= self.kits.len()
+            || pattern_index >= self.patterns.len()
+        {
+            return false;
+        }
+
+        let kit = &mut self.kits[kit_index];
+        let original_kit = Kit {
+            name: String::new(),
+            tracks: take_track_entries(&mut kit.tracks, |value| value.track_index == track_index),
+            controls: take_track_entries(&mut kit.controls, |value| {
+                value.track_index == track_index
+            }),
+            regions: take_track_entries(&mut kit.regions, |value| value.track_index == track_index),
+            trims: take_track_entries(&mut kit.trims, |value| value.track_index == track_index),
+            ducking: take_track_entries(&mut kit.ducking, |value| value.track_index == track_index),
+            midi_outputs: take_track_entries(&mut kit.midi_outputs, |value| {
+                value.track_index == track_index
+            }),
+            humanize: take_track_entries(&mut kit.humanize, |value| {
+                value.track_index == track_index
+            }),
+            labels: Vec::new(),
+            choke_targets: Vec::new(),
+            groups: Vec::new(),
+            master: MasterBus::default(),
+        };
+        kit.tracks.push(TrackAssignment {
+            track_index,
+            sample_id: bounce_sample_id.to_string(),
+        });
+        kit.tracks.sort_by_key(|value| value.track_index);
+
+        let pattern = &mut self.patterns[pattern_index];
+        let step_count = pattern.step_count();
+        let original_steps = pattern.steps[track][..step_count].to_vec();
+        pattern.steps[track].fill(PatternStep::default());
+        pattern.steps[track][0] = PatternStep {
+            active: true,
+            velocity: 127,
+            ..PatternStep::default()
+        };
+
+        self.frozen_tracks.push(TrackFreeze {
+            kit_index,
+            pattern_index,
+            track_index,
+            bounce_sample_id: bounce_sample_id.to_string(),
+            original_kit,
+            original_steps,
+        });
+        true
+    }
+
+    pub fn unfreeze_track(&mut self, track_index: u8) -> bool {
+        let kit_index = self.active_kit.unwrap_or(0);
+        let Some(position) = self
+            .frozen_tracks
+            .iter()
+            .position(|freeze| freeze.kit_index == kit_index && freeze.track_index == track_index)
+        else {
+            return false;
+        };
+        let freeze = self.frozen_tracks.remove(position);
+
+        if let Some(kit) = self.kits.get_mut(freeze.kit_index) {
+            kit.tracks.retain(|value| value.track_index != track_index);
+            kit.controls
+                .retain(|value| value.track_index != track_index);
+            kit.regions.retain(|value| value.track_index != track_index);
+            kit.trims.retain(|value| value.track_index != track_index);
+            kit.ducking.retain(|value| value.track_index != track_index);
+            kit.midi_outputs
+                .retain(|value| value.track_index != track_index);
+            kit.humanize
+                .retain(|value| value.track_index != track_index);
+            kit.tracks.extend(freeze.original_kit.tracks);
+            kit.controls.extend(freeze.original_kit.controls);
+            kit.regions.extend(freeze.original_kit.regions);
+            kit.trims.extend(freeze.original_kit.trims);
+            kit.ducking.extend(freeze.original_kit.ducking);
+            kit.midi_outputs.extend(freeze.original_kit.midi_outputs);
+            kit.humanize.extend(freeze.original_kit.humanize);
+            kit.tracks.sort_by_key(|value| value.track_index);
+            kit.controls.sort_by_key(|value| value.track_index);
+            kit.regions.sort_by_key(|value| value.track_index);
+            kit.trims.sort_by_key(|value| value.track_index);
+            kit.ducking.sort_by_key(|value| value.track_index);
+            kit.midi_outputs.sort_by_key(|value| value.track_index);
+            kit.humanize.sort_by_key(|value| value.track_index);
+        }
+
+        if let Some(pattern) = self.patterns.get_mut(freeze.pattern_index) {
+            let track = usize::from(track_index);
+            pattern.steps[track].fill(PatternStep::default());
+            for (step_index, step) in freeze.original_steps.into_iter().enumerate() {
+                pattern.set_step(track, step_index, step);
+            }
+        }
+        true
+    }
+
+    pub fn remap_sample_ids(&mut self, map: &BTreeMap<String, String>) -> SampleRemapReport {
+        let mut report = SampleRemapReport::default();
+        let mut remap = |sample_id: &mut String| {
+            if sample_id.is_empty() {
+                return;
+            }
+            match map.get(sample_id.as_str()) {
+                Some(replacement) if replacement != sample_id => {
+                    *sample_id = replacement.clone();
+                    report.replaced += 1;
+                }
+                Some(_) => {}
+                None => report.unresolved.push(sample_id.clone()),
+            }
+        };
+
+        for kit in &mut self.kits {
+            for assignment in &mut kit.tracks {
+                remap(&mut assignment.sample_id);
+            }
+        }
+        for freeze in &mut self.frozen_tracks {
+            remap(&mut freeze.bounce_sample_id);
+            for assignment in &mut freeze.original_kit.tracks {
+                remap(&mut assignment.sample_id);
+            }
+        }
+        remap(&mut self.metronome.accent_sample_id);
+        remap(&mut self.metronome.click_sample_id);
+
+        report.unresolved.sort();
+        report.unresolved.dedup();
+        report
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SetListEntry {
+    pub project_path: String,
+    pub tempo_bpm: Option<f32>,
+    pub start_pattern: Option<usize>,
+}
+
+impl SetListEntry {
+    pub fn new(project_path: &str) -> Self {
+        Self {
+            project_path: project_path.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        !self.project_path.is_empty()
+            && self
+                .tempo_bpm
+                .is_none_or(|bpm| (MIN_PATTERN_BPM..=MAX_PATTERN_BPM).contains(&bpm))
+    }
+
+    pub fn apply_defaults(&self, project: &mut Project) -> bool {
+        match self.start_pattern {
+            Some(index) => project.set_active_pattern(index),
+            None => true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SetList {
+    pub name: String,
+    pub entries: Vec<SetListEntry>,
+}
+
+impl SetList {
+    pub fn add_entry(&mut self, entry: SetListEntry) -> bool {
+        if !entry.is_valid() {
+            return false;
+        }
+        self.entries.push(entry);
+        true
+    }
+
+    pub fn move_entry(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.entries.len() || to >= self.entries.len() {
+            return false;
+        }
+        let entry = self.entries.remove(from);
+        self.entries.insert(to, entry);
+        true
+    }
+
+    pub fn remove_entry(&mut self, index: usize) -> Option<SetListEntry> {
+        (index < self.entries.len()).then(|| self.entries.remove(index))
+    }
+}
+
+fn take_track_entries<T>(entries: &mut Vec<T>, matches: impl Fn(&T) -> bool) -> Vec<T> {
+    let mut taken = Vec::new();
+    let mut index = 0;
+    while index < entries.len() {
+        if matches(&entries[index]) {
+            taken.push(entries.remove(index));
+        } else {
+            index += 1;
+        }
+    }
+    taken
+}
+
+// No stored float is anywhere near this; anything larger is corrupt input.
+const MAX_STORED_FLOAT: f32 = 1.0e6;
+
+// Six decimals keeps files readable; values that would not survive that
+// rounding are written in their shortest exact form instead. Rust's float
+// formatting and parsing never consult the system locale, so files always
+// use a decimal point wherever they are written or read.
+fn format_f32(value: f32) -> String {
+    let fixed = format!("{value:.6}");
+    if fixed.parse::<f32>() == Ok(value) {
+        fixed
+    } else {
+        value.to_string()
+    }
+}
+
+fn parse_f32(value: &str, field: &str) -> Result<f32, String> {
+    let parsed = value
+        .parse::<f32>()
+        .map_err(|_| format!("invalid float for {field}: {value}"))?;
+    if !parsed.is_finite() {
+        return Err(format!("non-finite float for {field}: {value}"));
+    }
+    if parsed.abs() > MAX_STORED_FLOAT {
+        return Err(format!("float out of range for {field}: {value}"));
+    }
+    Ok(parsed)
+}
+
+// Hand-edited and Windows-edited files pick up byte-order marks, CRLF
+// endings, trailing whitespace and blank lines; none of those carry meaning.
+fn text_lines(text: &str) -> impl Iterator<Item = &str> {
+    text.trim_start_matches('\u{feff}')
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty())
+}
+
+fn parse_usize(value: &str, field: &str) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .map_err(|_| format!("invalid usize for {field}: {value}"))
+}
+
+fn parse_u64(value: &str, field: &str) -> Result<u64, String> {
+    value
+        .parse::<u64>()
+        .map_err(|_| format!("invalid u64 for {field}: {value}"))
+}
+
+fn parse_u8(value: &str, field: &str) -> Result<u8, String> {
+    value
+        .parse::<u8>()
+        .map_err(|_| format!("invalid u8 for {field}: {value}"))
+}
+
+pub fn encode_text(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len() * 2);
+    for byte in value.as_bytes() {
+        encoded.push_str(&format!("{byte:02X}"));
+    }
+    encoded
+}
+
+pub fn decode_text(value: &str) -> Result<String, String> {
+    if !value.len().is_multiple_of(2) {
+        return Err("hex string length must be even".to_string());
+    }
+
+    let mut bytes = Vec::with_capacity(value.len() / 2);
+    let mut index = 0;
+    while index < value.len() {
+        let end = index + 2;
+        let byte = u8::from_str_radix(&value[index..end], 16)
+            .map_err(|_| format!("invalid hex byte: {}", &value[index..end]))?;
+        bytes.push(byte);
+        index = end;
+    }
+
+    String::from_utf8(bytes).map_err(|_| "invalid utf8 in encoded text".to_string())
+}
+
+pub fn gain_db_from_linear(linear: f32) -> f32 {
+    if linear.is_nan() || linear <= 0.0 {
+        return GAIN_DB_MIN;
+    }
+    (20.0 * linear.log10()).clamp(GAIN_DB_MIN, GAIN_DB_MAX)
+}
+
+fn serialize_kit_body(kit: &Kit) -> Vec<String> {
+    let mut lines = Vec::new();
+    lines.push(format!("name={}", encode_text(&kit.name)));
+    if !kit.controls.is_empty() {
+        lines.push(format!("gain_units={GAIN_UNITS_DB}"));
+    }
+
+    let mut tracks = kit.tracks.clone();
+    tracks.sort_by_key(|value| value.track_index);
+    for track in tracks {
+        lines.push(format!(
+            "track|{}|{}",
+            track.track_index,
+            encode_text(&track.sample_id)
+        ));
+    }
+
+    let mut controls = kit.controls.clone();
+    controls.sort_by_key(|value| value.track_index);
+    for control in controls {
+        lines.push(format!(
+            "control|{}|{}|{}|{}|{}|{}|{}",
+            control.track_index,
+            format_f32(control.controls.gain_db),
+            format_f32(control.controls.pan),
+            format_f32(control.controls.filter_cutoff),
+            format_f32(control.controls.envelope_decay),
+            format_f32(control.controls.pitch_semitones),
+            control.controls.choke_group.map(i32::from).unwrap_or(-1),
+        ));
+    }
+
+    let mut regions = kit.regions.clone();
+    regions.sort_by_key(|value| value.track_index);
+    for region in regions {
+        lines.push(format!(
+            "region|{}|{}|{}",
+            region.track_index, region.start_frame, region.end_frame
+        ));
+    }
+
+    let mut trims = kit.trims.clone();
+    trims.sort_by_key(|value| value.track_index);
+    for trim in trims {
+        lines.push(format!(
+            "trim|{}|{}|{}|{}",
+            trim.track_index,
+            format_f32(trim.start),
+            format_f32(trim.end),
+            u8::from(trim.reverse)
+        ));
+    }
+
+    let mut ducking = kit.ducking.clone();
+    ducking.sort_by_key(|value| value.track_index);
+    for duck in ducking {
+        lines.push(format!(
+            "duck|{}|{}|{}|{}|{}",
+            duck.track_index,
+            duck.source_track,
+            format_f32(duck.depth),
+            format_f32(duck.attack_ms),
+            format_f32(duck.release_ms)
+        ));
+    }
+
+    let mut midi_outputs = kit.midi_outputs.clone();
+    midi_outputs.sort_by_key(|value| value.track_index);
+    for output in midi_outputs {
+        lines.push(format!(
+            "midi_out|{}|{}|{}|{}",
+            output.track_index,
+            output.channel,
+            output.note,
+            format_f32(output.length_steps)
+        ));
+    }
+
+    for choke in &kit.choke_targets {
+        lines.push(format!("choke|{}|{}", choke.track_index, choke.targets));
+    }
+
+    for label in &kit.labels {
+        lines.push(format!(
+            "label|{}|{}|{}",
+            label.track_index,
+            encode_text(&label.name),
+            label
+                .color
+                .map_or_else(|| "-".to_string(), |color| format!("{color:06X}"))
+        ));
+    }
+
+    let mut humanize = kit.humanize.clone();
+    humanize.sort_by_key(|value| value.track_index);
+    for value in humanize {
+        lines.push(format!(
+            "humanize|{}|{}|{}",
+            value.track_index, value.timing_samples, value.velocity
+        ));
+    }
+
+    for group in &kit.groups {
+        let members: Vec<String> = group
+            .members
+            .iter()
+            .map(|track_index| track_index.to_string())
+            .collect();
+        lines.push(format!(
+            "group|{}|{}|{}|{}|{}|{}|{}",
+            group.group_index,
+            encode_text(&group.name),
+            if members.is_empty() {
+                "-".to_string()
+            } else {
+                members.join(",")
+            },
+            format_f32(group.gain_db),
+            u8::from(group.muted),
+            u8::from(group.soloed),
+            u8::from(group.choke)
+        ));
+    }
+
+    if kit.master != MasterBus::default() {
+        lines.push(format!(
+            "master|{}|{}|{}",
+            format_f32(kit.master.compressor_amount),
+            format_f32(kit.master.filter_cutoff),
+            format_f32(kit.master.limiter_ceiling_db)
+        ));
+    }
+
+    lines
+}
+
+fn deserialize_kit_body(lines: &[String]) -> Result<Kit, String> {
+    let mut kit = Kit::default();
+    let mut gain_in_db = false;
+
+    for line in lines {
+        if let Some(name_hex) = line.strip_prefix("name=") {
+            kit.name = decode_text(name_hex)?;
+            continue;
+        }
+
+        if let Some(units) = line.strip_prefix("gain_units=") {
+            if units != GAIN_UNITS_DB {
+                return Err(format!("unsupported gain units: {units}"));
+            }
+            gain_in_db = true;
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("track|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 2 {
+                return Err(format!("invalid track line: {line}"));
+            }
+
+            let track_index = parse_u8(fields[0], "track_index")?;
+            if usize::from(track_index) >= TRACK_COUNT {
+                return Err(format!(
+                    "track assignment out of range: {track_index} (max {})",
+                    TRACK_COUNT - 1
+                ));
+            }
+            let sample_id = decode_text(fields[1])?;
+            if !kit.add_assignment(TrackAssignment {
+                track_index,
+                sample_id,
+            }) {
+                return Err(format!("duplicate track assignment: {track_index}"));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("control|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 7 {
+                return Err(format!("invalid control line: {line}"));
+            }
+
+            let track_index = parse_u8(fields[0], "control.track_index")?;
+            if usize::from(track_index) >= TRACK_COUNT {
+                return Err(format!(
+                    "control track out of range: {track_index} (max {})",
+                    TRACK_COUNT - 1
+                ));
+            }
+            let choke_group_value = fields[6]
+                .parse::<i32>()
+                .map_err(|_| format!("invalid choke group: {}", fields[6]))?;
+            let choke_group = if choke_group_value < 0 {
+                None
+            } else {
+                if choke_group_value > 15 {
+                    return Err(format!(
+                        "choke group out of semantic range: {choke_group_value} (max 15)"
+                    ));
+                }
+                Some(
+                    u8::try_from(choke_group_value)
+                        .map_err(|_| format!("choke group out of range: {choke_group_value}"))?,
+                )
+            };
+
+            kit.set_track_controls(
+                track_index,
+                TrackControls {
+                    gain_db: parse_f32(fields[1], "control.gain")?,
+                    pan: parse_f32(fields[2], "control.pan")?,
+                    filter_cutoff: parse_f32(fields[3], "control.filter_cutoff")?,
+                    envelope_decay: parse_f32(fields[4], "control.envelope_decay")?,
+                    pitch_semitones: parse_f32(fields[5], "control.pitch_semitones")?,
+                    choke_group,
+                },
+            );
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("region|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 3 {
+                return Err(format!("invalid region line: {line}"));
+            }
+
+            let track_index = parse_u8(fields[0], "region.track_index")?;
+            let start_frame = parse_u64(fields[1], "region.start_frame")?;
+            let end_frame = parse_u64(fields[2], "region.end_frame")?;
+            if !kit.set_track_region(track_index, start_frame, end_frame) {
+                return Err(format!("invalid region: {line}"));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("trim|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 4 {
+                return Err(format!("invalid trim line: {line}"));
+            }
+
+            let reverse = match fields[3] {
+                "0" => false,
+                "1" => true,
+                value => return Err(format!("invalid trim.reverse: {value}")),
+            };
+            if !kit.set_track_trim(TrackSampleTrim {
+                track_index: parse_u8(fields[0], "trim.track_index")?,
+                start: parse_f32(fields[1], "trim.start")?,
+                end: parse_f32(fields[2], "trim.end")?,
+                reverse,
+            }) {
+                return Err(format!("invalid trim: {line}"));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("duck|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 5 {
+                return Err(format!("invalid duck line: {line}"));
+            }
+
+            if !kit.set_track_ducking(TrackDucking {
+                track_index: parse_u8(fields[0], "duck.track_index")?,
+                source_track: parse_u8(fields[1], "duck.source_track")?,
+                depth: parse_f32(fields[2], "duck.depth")?,
+                attack_ms: parse_f32(fields[3], "duck.attack_ms")?,
+                release_ms: parse_f32(fields[4], "duck.release_ms")?,
+            }) {
+                return Err(format!("invalid ducking: {line}"));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("midi_out|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 4 {
+                return Err(format!("invalid midi_out line: {line}"));
+            }
+
+            if !kit.set_track_midi_output(TrackMidiOutput {
+                track_index: parse_u8(fields[0], "midi_out.track_index")?,
+                channel: parse_u8(fields[1], "midi_out.channel")?,
+                note: parse_u8(fields[2], "midi_out.note")?,
+                length_steps: parse_f32(fields[3], "midi_out.length_steps")?,
+            }) {
+                return Err(format!("invalid midi output: {line}"));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("choke|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 2 {
+                return Err(format!("invalid choke line: {line}"));
+            }
+
+            if !kit.set_track_choke_targets(TrackChokeTargets {
+                track_index: parse_u8(fields[0], "choke.track_index")?,
+                targets: parse_u8(fields[1], "choke.targets")?,
+            }) {
+                return Err(format!("invalid choke targets: {line}"));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("label|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 3 {
+                return Err(format!("invalid label line: {line}"));
+            }
+
+            let color = match fields[2] {
+                "-" => None,
+                value => Some(
+                    u32::from_str_radix(value, 16)
+                        .map_err(|_| format!("invalid label.color: {value}"))?,
+                ),
+            };
+            if !kit.set_track_label(TrackLabel {
+                track_index: parse_u8(fields[0], "label.track_index")?,
+                name: decode_text(fields[1])?,
+                color,
+            }) {
+                return Err(format!("invalid label: {line}"));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("humanize|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 3 {
+                return Err(format!("invalid humanize line: {line}"));
+            }
+
+            let timing_samples = parse_u64(fields[1], "humanize.timing_samples")?;
+            if !kit.set_track_humanize(TrackHumanize {
+                track_index: parse_u8(fields[0], "humanize.track_index")?,
+                timing_samples: u32::try_from(timing_samples).unwrap_or(u32::MAX),
+                velocity: parse_u8(fields[2], "humanize.velocity")?,
+            }) {
+                return Err(format!("invalid humanize: {line}"));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("group|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 7 {
+                return Err(format!("invalid group line: {line}"));
+            }
+
+            let members = if fields[2] == "-" {
+                Vec::new()
+            } else {
+                fields[2]
+                    .split(',')
+                    .map(|value| parse_u8(value, "group.member"))
+                    .collect::<Result<Vec<u8>, String>>()?
+            };
+            let flag = |value: &str, field: &str| match value {
+                "0" => Ok(false),
+                "1" => Ok(true),
+                _ => Err(format!("invalid {field}: {value}")),
+            };
+            if !kit.set_track_group(TrackGroup {
+                group_index: parse_u8(fields[0], "group.index")?,
+                name: decode_text(fields[1])?,
+                members,
+                gain_db: parse_f32(fields[3], "group.gain_db")?,
+                muted: flag(fields[4], "group.mute")?,
+                soloed: flag(fields[5], "group.solo")?,
+                choke: flag(fields[6], "group.choke")?,
+            }) {
+                return Err(format!("invalid group: {line}"));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("master|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 3 {
+                return Err(format!("invalid master line: {line}"));
+            }
+
+            if !kit.set_master_bus(MasterBus {
+                compressor_amount: parse_f32(fields[0], "master.compressor")?,
+                filter_cutoff: parse_f32(fields[1], "master.filter_cutoff")?,
+                limiter_ceiling_db: parse_f32(fields[2], "master.limiter_ceiling")?,
+            }) {
+                return Err(format!("invalid master bus: {line}"));
+            }
+            continue;
+        }
+
+        return Err(format!("unknown kit line: {line}"));
+    }
+
+    if !gain_in_db {
+        for control in &mut kit.controls {
+            control.controls.gain_db = gain_db_from_linear(control.controls.gain_db);
+        }
+    }
+
+    Ok(kit)
+}
+
+// Step, variation and freeze lines share this `active|velocity` layout.
+// Anything beyond those two is written as an optional `name=value` field only
+// when it differs from the default, so older files keep loading unchanged.
+fn format_step_fields(step: &PatternStep) -> String {
+    let mut fields = format!("{}|{}", u8::from(step.active), step.velocity);
+    if step.probability != MAX_STEP_PROBABILITY {
+        fields.push_str(&format!("|probability={}", step.probability));
+    }
+    if step.condition != TrigCondition::Always {
+        fields.push_str(&format!("|condition={}", step.condition.to_text()));
+    }
+    if step.accent {
+        fields.push_str("|accent=1");
+    }
+    if step.flam {
+        fields.push_str("|flam=1");
+    }
+    if step.flam_spacing_ms != DEFAULT_FLAM_SPACING_MS {
+        fields.push_str(&format!("|flam_spacing={}", step.flam_spacing_ms));
+    }
+    if step.gate != MAX_STEP_GATE {
+        fields.push_str(&format!("|gate={}", step.gate));
+    }
+    fields
+}
+
+fn parse_step_fields(fields: &[&str], field: &str) -> Result<PatternStep, String> {
+    let mut step = PatternStep {
+        active: match fields[0] {
+            "0" => false,
+            "1" => true,
+            _ => return Err(format!("invalid {field} active value: {}", fields[0])),
+        },
+        velocity: parse_u8(fields[1], &format!("{field}.velocity"))?,
+        ..PatternStep::default()
+    };
+    if step.velocity > 127 {
+        return Err(format!(
+            "{field} velocity out of semantic range: {} (max 127)",
+            step.velocity
+        ));
+    }
+    for option in &fields[2..] {
+        let (name, value) = option
+            .split_once('=')
+            .ok_or_else(|| format!("invalid {field} field: {option}"))?;
+        match name {
+            "probability" => {
+                step.probability = parse_u8(value, &format!("{field}.probability"))?;
+            }
+            "condition" => {
+                step.condition = TrigCondition::from_text(value)
+                    .ok_or_else(|| format!("invalid {field} condition: {value}"))?;
+            }
+            "accent" => {
+                step.accent = match value {
+                    "0" => false,
+                    "1" => true,
+                    _ => return Err(format!("invalid {field} accent value: {value}")),
+                };
+            }
+            "flam" => {
+                step.flam = match value {
+                    "0" => false,
+                    "1" => true,
+                    _ => return Err(format!("invalid {field} flam value: {value}")),
+                };
+            }
+            "flam_spacing" => {
+                step.flam_spacing_ms = parse_u8(value, &format!("{field}.flam_spacing"))?;
+            }
+            "gate" => {
+                step.gate = parse_u8(value, &format!("{field}.gate"))?;
+            }
+            _ => return Err(format!("unknown {field} field: {name}")),
+        }
+    }
+    if !step.is_valid() {
+        return Err(format!(
+            "{field} out of semantic range: {}",
+            fields.join("|")
+        ));
+    }
+    Ok(step)
+}
+
+fn serialize_pattern_body(pattern: &Pattern) -> Vec<String> {
+    let mut lines = Vec::new();
+    lines.push(format!("name={}", encode_text(&pattern.name)));
+    lines.push(format!("swing={}", format_f32(pattern.swing)));
+    if pattern.bar_count > 1 {
+        lines.push(format!("bars={}", pattern.bar_count));
+    }
+    if let Some(length_steps) = pattern.length_steps {
+        lines.push(format!("length={length_steps}"));
+    }
+    if pattern.scale != PatternScale::Normal {
+        lines.push(format!("scale={}", pattern.scale.name()));
+    }
+    if pattern.swing_grid != SwingGrid::Track {
+        lines.push(format!("swing_grid={}", pattern.swing_grid.name()));
+    }
+    if let Some(groove) = &pattern.groove {
+        lines.push(format!("groove={}", encode_text(groove)));
+    }
+    if pattern.accent != AccentCurve::default() {
+        let boosts: Vec<String> = pattern
+            .accent
+            .boosts
+            .iter()
+            .map(|boost| boost.to_string())
+            .collect();
+        lines.push(format!(
+            "accent={}|{}",
+            boosts.join(","),
+            pattern.accent.clip.name()
+        ));
+    }
+    if let Some(tempo) = pattern.tempo {
+        lines.push(format!(
+            "tempo={}|{}",
+            format_f32(tempo.bpm),
+            tempo.change.name()
+        ));
+    }
+    for marker in &pattern.markers {
+        lines.push(format!(
+            "marker|{}|{}",
+            marker.bar,
+            encode_text(&marker.label)
+        ));
+    }
+    for span in &pattern.mutes {
+        lines.push(format!(
+            "mute|{}|{}|{}",
+            span.track_index, span.first_bar, span.last_bar
+        ));
+    }
+    for lock in &pattern.locks {
+        lines.push(format!(
+            "lock|{}|{}|{}|{}",
+            lock.track_index,
+            lock.step_index,
+            lock.target.name(),
+            format_f32(lock.value)
+        ));
+    }
+    for (track_index, direction) in pattern.directions.iter().enumerate() {
+        if *direction != PlaybackDirection::Forward {
+            lines.push(format!("direction|{track_index}|{}", direction.name()));
+        }
+    }
+    for (track_index, resolution) in pattern.resolutions.iter().enumerate() {
+        if *resolution != StepResolution::Sixteenth {
+            lines.push(format!("resolution|{track_index}|{}", resolution.name()));
+        }
+    }
+    for (track_index, step) in pattern.default_steps.iter().enumerate() {
+        if *step != PatternStep::default() {
+            lines.push(format!(
+                "default|{track_index}|{}",
+                format_step_fields(step)
+            ));
+        }
+    }
+
+    // Default steps are implied by the loader, so only edited ones are written.
+    for track_index in 0..TRACK_COUNT {
+        for step_index in 0..pattern.step_count() {
+            let step = pattern.steps[track_index][step_index];
+            if step == PatternStep::default() {
+                continue;
+            }
+            lines.push(format!(
+                "step|{}|{}|{}",
+                track_index,
+                step_index,
+                format_step_fields(&step)
+            ));
+        }
+    }
+    for variation in &pattern.variations {
+        lines.push(format!(
+            "variation|{}|{}|{}|{}",
+            variation.variation,
+            variation.track_index,
+            variation.step_index,
+            format_step_fields(&variation.step)
+        ));
+    }
+
+    lines
+}
+
+fn deserialize_pattern_body(lines: &[String]) -> Result<Pattern, String> {
+    let mut pattern = Pattern::default();
+    for line in lines {
+        if let Some(name_hex) = line.strip_prefix("name=") {
+            pattern.name = decode_text(name_hex)?;
+            continue;
+        }
+
+        if let Some(value) = line.strip_prefix("swing=") {
+            pattern.set_swing(parse_f32(value, "pattern.swing")?);
+            continue;
+        }
+
+        if let Some(value) = line.strip_prefix("bars=") {
+            if !pattern.set_bar_count(parse_usize(value, "pattern.bars")?) {
+                return Err(format!("pattern bar count out of range: {value}"));
+            }
+            continue;
+        }
+
+        if let Some(value) = line.strip_prefix("length=") {
+            let length_steps = parse_usize(value, "pattern.length")?;
+            if length_steps.div_ceil(STEPS_PER_PATTERN) != pattern.bar_count
+                || !pattern.set_step_count(length_steps)
+                || pattern.length_steps.is_none()
+            {
+                return Err(format!("pattern length out of range: {value}"));
+            }
+            continue;
+        }
+
+        if let Some(value) = line.strip_prefix("accent=") {
+            let (boosts, clip) = value
+                .split_once('|')
+                .ok_or_else(|| format!("invalid pattern accent: {value}"))?;
+            let boosts = boosts
+                .split(',')
+                .map(|boost| parse_u8(boost, "pattern.accent"))
+                .collect::<Result<Vec<u8>, String>>()?;
+            pattern.accent = AccentCurve {
+                boosts: boosts
+                    .try_into()
+                    .map_err(|_| format!("invalid pattern accent: {value}"))?,
+                clip: AccentClip::from_name(clip)
+                    .ok_or_else(|| format!("unknown accent clip: {clip}"))?,
+            };
+            if !pattern.accent.is_valid() {
+                return Err(format!("pattern accent out of range: {value}"));
+            }
+            continue;
+        }
+
+        if let Some(value) = line.strip_prefix("scale=") {
+            pattern.scale = PatternScale::from_name(value)
+                .ok_or_else(|| format!("unknown pattern scale: {value}"))?;
+            continue;
+        }
+
+        if let Some(value) = line.strip_prefix("swing_grid=") {
+            pattern.swing_grid = SwingGrid::from_name(value)
+                .ok_or_else(|| format!("unknown swing grid: {value}"))?;
+            continue;
+        }
+
+        if let Some(name_hex) = line.strip_prefix("groove=") {
+            let name = decode_text(name_hex)?;
+            if name.is_empty() {
+                return Err("pattern groove name is empty".to_string());
+            }
+            pattern.groove = Some(name);
+            continue;
+        }
+
+        if let Some(value) = line.strip_prefix("tempo=") {
+            let (bpm, change) = value
+                .split_once('|')
+                .ok_or_else(|| format!("invalid pattern tempo: {value}"))?;
+            let tempo = PatternTempo {
+                bpm: parse_f32(bpm, "pattern.tempo")?,
+                change: TempoChange::from_name(change)
+                    .ok_or_else(|| format!("unknown tempo change: {change}"))?,
+            };
+            if !tempo.is_valid() {
+                return Err(format!("pattern tempo out of range: {bpm}"));
+            }
+            pattern.tempo = Some(tempo);
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("marker|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 2 {
+                return Err(format!("invalid marker line: {line}"));
+            }
+
+            let bar = parse_usize(fields[0], "marker.bar")?;
+            if !pattern.set_bar_marker(bar, &decode_text(fields[1])?) {
+                return Err(format!("marker bar out of range: {line}"));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("mute|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 3 {
+                return Err(format!("invalid mute line: {line}"));
+            }
+
+            if !pattern.set_mute_span(
+                parse_usize(fields[0], "mute.track_index")?,
+                parse_usize(fields[1], "mute.first_bar")?,
+                parse_usize(fields[2], "mute.last_bar")?,
+            ) {
+                return Err(format!("mute span out of range: {line}"));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("direction|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 2 {
+                return Err(format!("invalid direction line: {line}"));
+            }
+
+            let direction = PlaybackDirection::from_name(fields[1])
+                .ok_or_else(|| format!("invalid playback direction: {}", fields[1]))?;
+            if !pattern
+                .set_track_direction(parse_usize(fields[0], "direction.track_index")?, direction)
+            {
+                return Err(format!("direction track out of range: {line}"));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("resolution|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 2 {
+                return Err(format!("invalid resolution line: {line}"));
+            }
+
+            let resolution = StepResolution::from_name(fields[1])
+                .ok_or_else(|| format!("invalid step resolution: {}", fields[1]))?;
+            if !pattern.set_track_resolution(
+                parse_usize(fields[0], "resolution.track_index")?,
+                resolution,
+            ) {
+                return Err(format!("resolution track out of range: {line}"));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("default|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() < 3 {
+                return Err(format!("invalid default step line: {line}"));
+            }
+
+            if !pattern.set_track_default_step(
+                parse_usize(fields[0], "default.track_index")?,
+                parse_step_fields(&fields[1..], "default")?,
+            ) {
+                return Err(format!("default step track out of range: {line}"));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("lock|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 4 {
+                return Err(format!("invalid lock line: {line}"));
+            }
+
+            let track_index = parse_usize(fields[0], "lock.track_index")?;
+            let step_index = parse_usize(fields[1], "lock.step_index")?;
+            let target = LockTarget::from_name(fields[2])
+                .ok_or_else(|| format!("invalid lock target: {}", fields[2]))?;
+            if track_index >= TRACK_COUNT
+                || step_index >= MAX_PATTERN_STEPS
+                || !pattern.set_parameter_lock(ParameterLock {
+                    track_index: track_index as u8,
+                    step_index: step_index as u8,
+                    target,
+                    value: parse_f32(fields[3], "lock.value")?,
+                })
+            {
+                return Err(format!("parameter lock out of range: {line}"));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("step|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() < 4 {
+                return Err(format!("invalid step line: {line}"));
+            }
+
+            let track_index = parse_usize(fields[0], "step.track_index")?;
+            let step_index = parse_usize(fields[1], "step.step_index")?;
+            let step = parse_step_fields(&fields[2..], "step")?;
+            if !pattern.set_step(track_index, step_index, step) {
+                return Err(format!("step index out of range: {line}"));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("variation|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() < 5 {
+                return Err(format!("invalid variation line: {line}"));
+            }
+
+            if !pattern.set_variation_step(
+                parse_u8(fields[0], "variation.slot")?,
+                parse_usize(fields[1], "variation.track_index")?,
+                parse_usize(fields[2], "variation.step_index")?,
+                Some(parse_step_fields(&fields[3..], "variation")?),
+            ) {
+                return Err(format!("variation out of range: {line}"));
+            }
+            continue;
+        }
+
+        return Err(format!("unknown pattern line: {line}"));
+    }
+
+    Ok(pattern)
+}
+
+fn serialize_freeze_body(freeze: &TrackFreeze) -> Vec<String> {
+    let mut lines = Vec::new();
+    lines.push(format!("kit={}", freeze.kit_index));
+    lines.push(format!("pattern={}", freeze.pattern_index));
+    lines.push(format!("track={}", freeze.track_index));
+    lines.push(format!("bounce={}", encode_text(&freeze.bounce_sample_id)));
+    lines.extend(
+        serialize_kit_body(&freeze.original_kit)
+            .into_iter()
+            .map(|line| format!("kit|{line}")),
+    );
+    for (step_index, step) in freeze.original_steps.iter().enumerate() {
+        lines.push(format!("step|{}|{}", step_index, format_step_fields(step)));
+    }
+    lines
+}
+
+fn deserialize_freeze_body(lines: &[String]) -> Result<TrackFreeze, String> {
+    let mut freeze = TrackFreeze {
+        kit_index: 0,
+        pattern_index: 0,
+        track_index: 0,
+        bounce_sample_id: String::new(),
+        original_kit: Kit::default(),
+        original_steps: Vec::new(),
+    };
+    let mut kit_lines = Vec::new();
+    for line in lines {
+        if let Some(value) = line.strip_prefix("kit=") {
+            freeze.kit_index = parse_usize(value, "freeze.kit")?;
+            continue;
+        }
+
+        if let Some(value) = line.strip_prefix("pattern=") {
+            freeze.pattern_index = parse_usize(value, "freeze.pattern")?;
+            continue;
+        }
+
+        if let Some(value) = line.strip_prefix("track=") {
+            freeze.track_index = parse_u8(value, "freeze.track")?;
+            if usize::from(freeze.track_index) >= TRACK_COUNT {
+                return Err(format!("freeze track out of range: {value}"));
+            }
+            continue;
+        }
+
+        if let Some(value) = line.strip_prefix("bounce=") {
+            freeze.bounce_sample_id = decode_text(value)?;
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("kit|") {
+            kit_lines.push(rest.to_string());
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("step|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() < 3 {
+                return Err(format!("invalid freeze step line: {line}"));
+            }
+
+            let step_index = parse_usize(fields[0], "freeze.step_index")?;
+            if step_index != freeze.original_steps.len() || step_index >= MAX_PATTERN_STEPS {
+                return Err(format!("freeze step out of order: {line}"));
+            }
+            freeze
+                .original_steps
+                .push(parse_step_fields(&fields[1..], "freeze.step")?);
+            continue;
+        }
+
+        return Err(format!("unknown freeze line: {line}"));
+    }
+
+    if freeze.bounce_sample_id.is_empty() {
+        return Err("freeze block missing bounce sample".to_string());
+    }
+    freeze.original_kit = deserialize_kit_body(&kit_lines)?;
+    Ok(freeze)
+}
+
+pub fn save_kit_to_text(kit: &Kit) -> String {
+    let mut lines = Vec::new();
+    lines.push("FF_KIT_V1".to_string());
+    lines.extend(serialize_kit_body(kit));
+    lines.join("\n")
+}
+
+pub fn load_kit_from_text(text: &str) -> Result<Kit, String> {
+    let mut lines = text_lines(text);
+    let header = lines
+        .next()
+        .ok_or_else(|| "missing kit header".to_string())?;
+    if header != "FF_KIT_V1" {
+        return Err(format!("unexpected kit header: {header}"));
+    }
+    deserialize_kit_body(&lines.map(|line| line.to_string()).collect::<Vec<_>>())
+}
+
+pub fn save_pattern_to_text(pattern: &Pattern) -> String {
+    let mut lines = Vec::new();
+    lines.push("FF_PATTERN_V1".to_string());
+    lines.extend(serialize_pattern_body(pattern));
+    lines.join("\n")
+}
+
+pub fn load_pattern_from_text(text: &str) -> Result<Pattern, String> {
+    let mut lines = text_lines(text);
+    let header = lines
+        .next()
+        .ok_or_else(|| "missing pattern header".to_string())?;
+    if header != "FF_PATTERN_V1" {
+        return Err(format!("unexpected pattern header: {header}"));
+    }
+    deserialize_pattern_body(&lines.map(|line| line.to_string()).collect::<Vec<_>>())
+}
+
+pub fn save_project_to_text(project: &Project) -> String {
+    let mut text = serialize_project_header(project).join("\n");
+    for block in project
+        .kits
+        .iter()
+        .map(kit_block)
+        .chain(project.patterns.iter().map(pattern_block))
+        .chain(project.frozen_tracks.iter().map(freeze_block))
+    {
+        text.push('\n');
+        text.push_str(&block);
+    }
+    text
+}
+
+#[derive(Clone, Debug)]
+struct SavedBlock<T> {
+    value: T,
+    text: String,
+}
+
+// Remembers every kit, pattern and freeze block as last written so frequent
+// autosaves only re-encode the blocks that changed since. The text is
+// always identical to a full save of the same project.
+#[derive(Clone, Debug, Default)]
+pub struct IncrementalProjectSave {
+    kits: Vec<SavedBlock<Kit>>,
+    patterns: Vec<SavedBlock<Pattern>>,
+    frozen_tracks: Vec<SavedBlock<TrackFreeze>>,
+}
+
+impl IncrementalProjectSave {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn dirty_kits(&self, project: &Project) -> Vec<usize> {
+        dirty_blocks(&self.kits, &project.kits)
+    }
+
+    pub fn dirty_patterns(&self, project: &Project) -> Vec<usize> {
+        dirty_blocks(&self.patterns, &project.patterns)
+    }
+
+    pub fn dirty_frozen_tracks(&self, project: &Project) -> Vec<usize> {
+        dirty_blocks(&self.frozen_tracks, &project.frozen_tracks)
+    }
+
+    pub fn save(&mut self, project: &Project) -> String {
+        refresh_blocks(&mut self.kits, &project.kits, kit_block);
+        refresh_blocks(&mut self.patterns, &project.patterns, pattern_block);
+        refresh_blocks(
+            &mut self.frozen_tracks,
+            &project.frozen_tracks,
+            freeze_block,
+        );
+
+        let mut text = serialize_project_header(project).join("\n");
+        for block in self
+            .kits
+            .iter()
+            .map(|block| &block.text)
+            .chain(self.patterns.iter().map(|block| &block.text))
+            .chain(self.frozen_tracks.iter().map(|block| &block.text))
+        {
+            text.push('\n');
+            text.push_str(block);
+        }
+        text
+    }
+}
+
+fn dirty_blocks<T: PartialEq>(saved: &[SavedBlock<T>], current: &[T]) -> Vec<usize> {
+    (0..current.len())
+        .filter(|&index| {
+            saved
+                .get(index)
+                .is_none_or(|block| block.value != current[index])
+        })
+        .collect()
+}
+
+fn refresh_blocks<T: Clone + PartialEq>(
+    saved: &mut Vec<SavedBlock<T>>,
+    current: &[T],
+    encode: fn(&T) -> String,
+) {
+    saved.truncate(current.len());
+    for (index, value) in current.iter().enumerate() {
+        match saved.get_mut(index) {
+            Some(block) if block.value == *value => {}
+            Some(block) => {
+                block.value = value.clone();
+                block.text = encode(value);
+            }
+            None => saved.push(SavedBlock {
+                value: value.clone(),
+                text: encode(value),
+            }),
+        }
+    }
+}
+
+fn wrap_block(begin: &str, body: Vec<String>, end: &str) -> String {
+    let mut lines = vec![begin.to_string()];
+    lines.extend(body);
+    lines.push(end.to_string());
+    lines.join("\n")
+}
+
+fn kit_block(kit: &Kit) -> String {
+    wrap_block("BEGIN_KIT", serialize_kit_body(kit), "END_KIT")
+}
+
+fn pattern_block(pattern: &Pattern) -> String {
+    wrap_block(
+        "BEGIN_PATTERN",
+        serialize_pattern_body(pattern),
+        "END_PATTERN",
+    )
+}
+
+fn freeze_block(freeze: &TrackFreeze) -> String {
+    wrap_block("BEGIN_FREEZE", serialize_freeze_body(freeze), "END_FREEZE")
+}
+
+fn serialize_project_header(project: &Project) -> Vec<String> {
+    let mut lines = Vec::new();
+    lines.push("FF_PROJECT_V1".to_string());
+    lines.push(format!("name={}", encode_text(&project.name)));
+    lines.push(format!(
+        "active_kit={}",
+        project
+            .active_kit
+            .map(|value| value.to_string())
+            .unwrap_or_else(|| "-1".to_string())
+    ));
+    lines.push(format!(
+        "active_pattern={}",
+        project
+            .active_pattern
+            .map(|value| value.to_string())
+            .unwrap_or_else(|| "-1".to_string())
+    ));
+    if project.random_seed != 0 {
+        lines.push(format!("seed={}", project.random_seed));
+    }
+    if project.humanize != HumanizeDepth::default() {
+        lines.push(format!(
+            "humanize={}|{}",
+            project.humanize.timing_samples, project.humanize.velocity
+        ));
+    }
+    if project.audio_hints != AudioHints::default() {
+        let hint =
+            |value: Option<u32>| value.map_or_else(|| "-".to_string(), |value| value.to_string());
+        lines.push(format!(
+            "audio_hints={}|{}",
+            hint(project.audio_hints.sample_rate_hz),
+            hint(project.audio_hints.block_size)
+        ));
+    }
+    if project.key != MusicalKey::default() {
+        lines.push(format!("key={}", project.key.to_text()));
+    }
+    if project.metronome != Metronome::default() {
+        let metronome = &project.metronome;
+        let sample = |sample_id: &str| {
+            if sample_id.is_empty() {
+                "-".to_string()
+            } else {
+                encode_text(sample_id)
+            }
+        };
+        lines.push(format!(
+            "metronome|{}|{}|{}|{}|{}|{}",
+            u8::from(metronome.enabled),
+            sample(&metronome.accent_sample_id),
+            sample(&metronome.click_sample_id),
+            format_f32(metronome.gain_db),
+            metronome.output_pair,
+            u8::from(metronome.include_in_render)
+        ));
+    }
+    for track_index in 0..TRACK_COUNT {
+        let output_pair = project
+            .output_routing
+            .track_output(track_index)
+            .unwrap_or(0);
+        if output_pair != 0 {
+            lines.push(format!("output|{track_index}|{output_pair}"));
+        }
+    }
+    let optional_index = |index: Option<usize>| {
+        index
+            .map(|value| value.to_string())
+            .unwrap_or_else(|| "-".to_string())
+    };
+    for change in &project.program_changes {
+        lines.push(format!(
+            "program|{}|{}|{}",
+            change.program,
+            optional_index(change.pattern_index),
+            optional_index(change.kit_index)
+        ));
+    }
+    for groove in &project.grooves {
+        let timing: Vec<String> = groove.timing.iter().copied().map(format_f32).collect();
+        let velocity: Vec<String> = groove.velocity.iter().map(i8::to_string).collect();
+        lines.push(format!(
+            "groove|{}|{}|{}",
+            encode_text(&groove.name),
+            timing.join(","),
+            velocity.join(",")
+        ));
+    }
+    for entry in &project.song {
+        lines.push(format!("song|{}|{}", entry.pattern_index, entry.repeats));
+    }
+    if project.song_loop {
+        lines.push("song_loop=1".to_string());
+    }
+    let default_inputs = TrackInputs::default();
+    for track_index in 0..TRACK_COUNT {
+        let armed = project.track_inputs.is_armed(track_index);
+        let monitoring = project.track_inputs.is_monitoring(track_index);
+        if armed != default_inputs.is_armed(track_index)
+            || monitoring != default_inputs.is_monitoring(track_index)
+        {
+            lines.push(format!(
+                "input|{track_index}|{}|{}",
+                u8::from(armed),
+                u8::from(monitoring)
+            ));
+        }
+    }
+    lines
+}
+
+pub fn load_project_from_text(text: &str) -> Result<Project, String> {
+    let mut lines = text_lines(text).peekable();
+    let header = lines
+        .next()
+        .ok_or_else(|| "missing project header".to_string())?;
+    if header != "FF_PROJECT_V1" {
+        return Err(format!("unexpected project header: {header}"));
+    }
+
+    let mut project = Project::default();
+    let mut active_kit_raw: Option<isize> = None;
+    let mut active_pattern_raw: Option<isize> = None;
+    let mut program_changes = Vec::new();
+    let mut song = Vec::new();
+
+    while let Some(line) = lines.next() {
+        if let Some(name_hex) = line.strip_prefix("name=") {
+            project.name = decode_text(name_hex)?;
+            continue;
+        }
+
+        if let Some(value) = line.strip_prefix("active_kit=") {
+            active_kit_raw = Some(
+                value
+                    .parse::<isize>()
+                    .map_err(|_| format!("invalid active_kit value: {value}"))?,
+            );
+            continue;
+        }
+
+        if let Some(value) = line.strip_prefix("active_pattern=") {
+            active_pattern_raw = Some(
+                value
+                    .parse::<isize>()
+                    .map_err(|_| format!("invalid active_pattern value: {value}"))?,
+            );
+            continue;
+        }
+
+        if let Some(value) = line.strip_prefix("seed=") {
+            project.random_seed = value
+                .parse::<u64>()
+                .map_err(|_| format!("invalid seed value: {value}"))?;
+            continue;
+        }
+
+        if let Some(value) = line.strip_prefix("humanize=") {
+            let (timing_samples, velocity) = value
+                .split_once('|')
+                .ok_or_else(|| format!("invalid project humanize: {value}"))?;
+            let timing_samples = parse_u64(timing_samples, "humanize.timing_samples")?;
+            let humanize = HumanizeDepth {
+                timing_samples: u32::try_from(timing_samples).unwrap_or(u32::MAX),
+                velocity: parse_u8(velocity, "humanize.velocity")?,
+            };
+            if !humanize.is_valid() {
+                return Err(format!("project humanize out of range: {value}"));
+            }
+            project.humanize = humanize;
+            continue;
+        }
+
+        if let Some(value) = line.strip_prefix("audio_hints=") {
+            let (sample_rate_hz, block_size) = value
+                .split_once('|')
+                .ok_or_else(|| format!("invalid audio hints: {value}"))?;
+            let hint = |field: &str, name: &str| -> Result<Option<u32>, String> {
+                if field == "-" {
+                    return Ok(None);
+                }
+                let parsed = parse_u64(field, name)?;
+                Ok(Some(u32::try_from(parsed).unwrap_or(u32::MAX)))
+            };
+            let hints = AudioHints {
+                sample_rate_hz: hint(sample_rate_hz, "audio_hints.sample_rate_hz")?,
+                block_size: hint(block_size, "audio_hints.block_size")?,
+            };
+            if !hints.is_valid() {
+                return Err(format!("audio hints out of range: {value}"));
+            }
+            project.audio_hints = hints;
+            continue;
+        }
+
+        if let Some(value) = line.strip_prefix("key=") {
+            project.key = MusicalKey::from_text(value)?;
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("metronome|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 6 {
+                return Err(format!("invalid metronome line: {line}"));
+            }
+
+            let flag = |value: &str, field: &str| match value {
+                "0" => Ok(false),
+                "1" => Ok(true),
+                _ => Err(format!("invalid {field}: {value}")),
+            };
+            let sample = |value: &str| {
+                if value == "-" {
+                    Ok(String::new())
+                } else {
+                    decode_text(value)
+                }
+            };
+            project.metronome = Metronome {
+                enabled: flag(fields[0], "metronome.enabled")?,
+                accent_sample_id: sample(fields[1])?,
+                click_sample_id: sample(fields[2])?,
+                gain_db: parse_f32(fields[3], "metronome.gain_db")?,
+                output_pair: parse_u8(fields[4], "metronome.output")?,
+                include_in_render: flag(fields[5], "metronome.render")?,
+            };
+            if !project.metronome.is_valid() {
+                return Err(format!("invalid metronome: {line}"));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("input|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 3 {
+                return Err(format!("invalid input line: {line}"));
+            }
+
+            let flag = |value: &str, field: &str| match value {
+                "0" => Ok(false),
+                "1" => Ok(true),
+                _ => Err(format!("invalid {field}: {value}")),
+            };
+            let track_index = parse_usize(fields[0], "input.track_index")?;
+            if !project
+                .track_inputs
+                .set_armed(track_index, flag(fields[1], "input.armed")?)
+                || !project
+                    .track_inputs
+                    .set_monitoring(track_index, flag(fields[2], "input.monitor")?)
+            {
+                return Err(format!("input track out of range: {line}"));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("program|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 3 {
+                return Err(format!("invalid program line: {line}"));
+            }
+
+            let index = |value: &str, field: &str| {
+                if value == "-" {
+                    Ok(None)
+                } else {
+                    parse_usize(value, field).map(Some)
+                }
+            };
+            program_changes.push(ProgramChange {
+                program: parse_u8(fields[0], "program.number")?,
+                pattern_index: index(fields[1], "program.pattern")?,
+                kit_index: index(fields[2], "program.kit")?,
+            });
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("groove|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 3 {
+                return Err(format!("invalid groove line: {line}"));
+            }
+
+            let mut groove = GrooveTemplate::new(&decode_text(fields[0])?);
+            let timing: Vec<&str> = fields[1].split(',').collect();
+            let velocity: Vec<&str> = fields[2].split(',').collect();
+            if timing.len() != GROOVE_SLOT_COUNT || velocity.len() != GROOVE_SLOT_COUNT {
+                return Err(format!(
+                    "groove must have {GROOVE_SLOT_COUNT} timing and velocity slots: {line}"
+                ));
+            }
+            for slot in 0..GROOVE_SLOT_COUNT {
+                groove.timing[slot] = parse_f32(timing[slot], "groove.timing")?;
+                groove.velocity[slot] = velocity[slot]
+                    .parse::<i8>()
+                    .map_err(|_| format!("invalid i8 for groove.velocity: {}", velocity[slot]))?;
+            }
+            if !project.set_groove(groove) {
+                return Err(format!("invalid groove: {line}"));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("song|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 2 {
+                return Err(format!("invalid song line: {line}"));
+            }
+
+            song.push(SongEntry {
+                pattern_index: parse_usize(fields[0], "song.pattern")?,
+                repeats: parse_u8(fields[1], "song.repeats")?,
+            });
+            continue;
+        }
+
+        if let Some(value) = line.strip_prefix("song_loop=") {
+            project.song_loop = match value {
+                "0" => false,
+                "1" => true,
+                _ => return Err(format!("invalid song_loop value: {value}")),
+            };
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("output|") {
+            let fields: Vec<&str> = rest.split('|').collect();
+            if fields.len() != 2 {
+                return Err(format!("invalid output line: {line}"));
+            }
+
+            let track_index = parse_usize(fields[0], "output.track_index")?;
+            let output_pair = parse_usize(fields[1], "output.pair")?;
+            if !project
+                .output_routing
+                .set_track_output(track_index, output_pair)
+            {
+                return Err(format!("output route out of range: {line}"));
+            }
+            continue;
+        }
+
+        if line == "BEGIN_KIT" {
+            let mut block = Vec::new();
+            loop {
+                let next_line = lines
+                    .next()
+                    .ok_or_else(|| "unterminated kit block".to_string())?;
+                if next_line == "END_KIT" {
+                    break;
+                }
+                block.push(next_line.to_string());
+            }
+            project.kits.push(deserialize_kit_body(&block)?);
+            continue;
+        }
+
+        if line == "BEGIN_PATTERN" {
+            let mut block = Vec::new();
+            loop {
+                let next_line = lines
+                    .next()
+                    .ok_or_else(|| "unterminated pattern block".to_string())?;
+                if next_line == "END_PATTERN" {
+                    break;
+                }
+                block.push(next_line.to_string());
+            }
+            project.patterns.push(deserialize_pattern_body(&block)?);
+            continue;
+        }
+
+        if line == "BEGIN_FREEZE" {
+            let mut block = Vec::new();
+            loop {
+                let next_line = lines
+                    .next()
+                    .ok_or_else(|| "unterminated freeze block".to_string())?;
+                if next_line == "END_FREEZE" {
+                    break;
+                }
+                block.push(next_line.to_string());
+            }
+            project.frozen_tracks.push(deserialize_freeze_body(&block)?);
+            continue;
+        }
+
+        return Err(format!("unknown project line: {line}"));
+    }
+
+    if let Some(raw) = active_kit_raw {
+        if raw >= 0 {
+            let index = usize::try_from(raw).map_err(|_| "invalid active_kit index".to_string())?;
+            if index >= project.kits.len() {
+                return Err(format!("active_kit out of range: {index}"));
+            }
+            project.active_kit = Some(index);
+        }
+    }
+
+    if let Some(raw) = active_pattern_raw {
+        if raw >= 0 {
+            let index =
+                usize::try_from(raw).map_err(|_| "invalid active_pattern index".to_string())?;
+            if index >= project.patterns.len() {
+                return Err(format!("active_pattern out of range: {index}"));
+            }
+            project.active_pattern = Some(index);
+        }
+    }
+
+    if !project.set_song(&song) {
+        return Err("song entry out of range".to_string());
+    }
+
+    for change in program_changes {
+        if !project.set_program_change(change) {
+            return Err(format!("invalid program change: {}", change.program));
+        }
+    }
+
+    Ok(project)
+}
+
+pub fn save_setlist_to_text(setlist: &SetList) -> String {
+    let mut lines = Vec::new();
+    lines.push("FF_SETLIST_V1".to_string());
+    lines.push(format!("name={}", encode_text(&setlist.name)));
+    for entry in &setlist.entries {
+        lines.push(format!(
+            "entry|{}|{}|{}",
+            encode_text(&entry.project_path),
+            entry
+                .tempo_bpm
+                .map(format_f32)
+                .unwrap_or_else(|| "-".to_string()),
+            entry
+                .start_pattern
+                .map(|index| index.to_string())
+                .unwrap_or_else(|| "-".to_string())
+        ));
+    }
+    lines.join("\n")
+}
+
+pub fn load_setlist_from_text(text: &str) -> Result<SetList, String> {
+    let mut lines = text_lines(text);
+    let header = lines
+        .next()
+        .ok_or_else(|| "missing setlist header".to_string())?;
+    if header != "FF_SETLIST_V1" {
+        return Err(format!("unexpected setlist header: {header}"));
+    }
+
+    let mut setlist = SetList::default();
+    for line in lines {
+        if let Some(name_hex) = line.strip_prefix("name=") {
+            setlist.name = decode_text(name_hex)?;
+            continue;
+        }
+        let parts: Vec<&str> = line.split('|').collect();
+        if parts.len() != 4 || parts[0] != "entry" {
+            return Err(format!("invalid setlist line: {line}"));
+        }
+        let entry = SetListEntry {
+            project_path: decode_text(parts[1])?,
+            tempo_bpm: match parts[2] {
+                "-" => None,
+                value => Some(parse_f32(value, "entry tempo")?),
+            },
+            start_pattern: match parts[3] {
+                "-" => None,
+                value => Some(parse_usize(value, "entry pattern")?),
+            },
+        };
+        if !setlist.add_entry(entry) {
+            return Err(format!("invalid setlist entry: {line}"));
+        }
+    }
+    Ok(setlist)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        format_f32, gain_db_from_linear, load_kit_from_text, load_pattern_from_text,
+        load_project_from_text, load_setlist_from_text, parse_f32, save_kit_to_text,
+        save_pattern_to_text, save_project_to_text, save_setlist_to_text, variation_name,
+        AccentClip, AccentCurve, AudioHints, GrooveTemplate, HumanizeDepth, IncrementalProjectSave,
+        Kit, LockTarget, MasterBus, Metronome, MusicalKey, MusicalScale, MuteSpan, ParameterLock,
+        Pattern, PatternScale, PatternStep, PatternTempo, PlaybackDirection, ProgramChange,
+        Project, SetList, SetListEntry, SongEntry, StepResolution, SwingGrid, TempoChange,
+        TrackAssignment, TrackChokeTargets, TrackControls, TrackDucking, TrackGroup, TrackHumanize,
+        TrackLabel, TrackMidiOutput, TrackSampleTrim, TrigCondition, ACCENT_CURVE_POINTS,
+        BUILTIN_GROOVE_NAMES, DEFAULT_ACCENT_AMOUNT, GAIN_DB_MAX, GAIN_DB_MIN,
+        LIMITER_CEILING_DB_MIN, MAX_ACCENT_AMOUNT, MAX_HUMANIZE_TIMING_SAMPLES, MAX_OUTPUT_PAIRS,
+        MAX_PATTERN_BARS, MAX_PATTERN_STEPS, MAX_TRACK_COLOR, MAX_TRACK_NAME_CHARS,
+        MIDI_PROGRAM_COUNT, PATTERN_VARIATION_COUNT, RESOLUTION_TICKS_PER_BEAT, STEPS_PER_PATTERN,
+        TRACK_COUNT,
+    };
+    use std::collections::BTreeMap;
+
+    fn fuzz_text(seed: u64, len: usize) -> String {
+        let mut state = seed;
+        let mut bytes = Vec::with_capacity(len);
+        for _ in 0..len {
+            // Deterministic LCG for lightweight fuzz-style coverage.
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            bytes.push((state >> 32) as u8);
+        }
+
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+
+    #[test]
+    fn duplicate_track_assignment_is_rejected() {
+        let mut kit = Kit::default();
+        assert!(kit.add_assignment(TrackAssignment {
+            track_index: 0,
+            sample_id: "kick.01".to_string(),
+        }));
+        assert!(!kit.add_assignment(TrackAssignment {
+            track_index: 0,
+            sample_id: "kick.02".to_string(),
+        }));
+    }
+
+    #[test]
+    fn track_controls_roundtrip_in_kit() {
+        let mut kit = Kit::default();
+        kit.set_track_controls(
+            3,
+            TrackControls {
+                gain_db: -3.0,
+                pan: -0.25,
+                filter_cutoff: 0.4,
+                envelope_decay: 0.7,
+                pitch_semitones: 3.0,
+                choke_group: Some(1),
+            },
+        );
+
+        let encoded = save_kit_to_text(&kit);
+        let decoded = load_kit_from_text(&encoded).expect("kit decode");
+        assert_eq!(kit, decoded);
+    }
+
+    #[test]
+    fn legacy_linear_kit_gains_migrate_to_db() {
+        let text = "FF_KIT_V1\nname=\ncontrol|0|1.000000|0.000000|1.000000|1.000000|0.000000|-1\ncontrol|1|0.500000|0.000000|1.000000|1.000000|0.000000|-1\ncontrol|2|0.000000|0.000000|1.000000|1.000000|0.000000|-1\ncontrol|3|4.000000|0.000000|1.000000|1.000000|0.000000|-1";
+        let kit = load_kit_from_text(text).expect("legacy kit");
+        let gains: Vec<f32> = kit
+            .controls
+            .iter()
+            .map(|control| control.controls.gain_db)
+            .collect();
+        assert_eq!(gains[0], 0.0);
+        assert!((gains[1] + 6.0206).abs() < 1e-3);
+        assert_eq!(gains[2], GAIN_DB_MIN);
+        assert_eq!(gains[3], GAIN_DB_MAX);
+
+        let saved = save_kit_to_text(&kit);
+        assert!(saved.contains("\ngain_units=db\n"));
+        let reloaded = load_kit_from_text(&saved).expect("migrated kit");
+        assert_eq!(save_kit_to_text(&reloaded), saved);
+        assert!(load_kit_from_text("FF_KIT_V1\ngain_units=linear").is_err());
+    }
+
+    #[test]
+    fn starter_kit_gains_are_the_migrated_linear_gains() {
+        let project = load_project_from_text(include_str!(
+            "../../../assets/starter-kit/default.ffproject"
+        ))
+        .expect("starter project");
+        let gains: Vec<f32> = project.kits[0]
+            .controls
+            .iter()
+            .map(|control| control.controls.gain_db)
+            .collect();
+        let migrated: Vec<f32> = [1.0, 1.0, 0.9, 0.85, 0.8, 1.0, 0.95, 0.8]
+            .into_iter()
+            .map(gain_db_from_linear)
+            .collect();
+        assert_eq!(gains, migrated);
+    }
+
+    #[test]
+    fn musical_key_membership_wraps_around_the_root() {
+        let key = MusicalKey {
+            root: 2,
+            scale: MusicalScale::MajorPentatonic,
+        };
+        let members: Vec<i32> = (0..12)
+            .filter(|semitone| key.contains_semitone(*semitone))
+            .collect();
+        assert_eq!(members, vec![2, 4, 6, 9, 11]);
+        assert!(key.contains_semitone(-10));
+        assert!(MusicalKey::default().contains_semitone(5));
+        assert_eq!(MusicalKey::from_text("D|major_pentatonic"), Ok(key));
+        assert!(MusicalKey::from_text("D").is_err());
+    }
+
+    #[test]
+    fn track_regions_roundtrip_in_kit() {
+        let mut kit = Kit::default();
+        assert!(kit.set_track_region(2, 1_000, 4_000));
+        assert!(kit.set_track_region(2, 2_000, 5_000));
+        assert!(!kit.set_track_region(1, 10, 10));
+        assert!(!kit.set_track_region(8, 0, 10));
+        assert_eq!(kit.regions.len(), 1);
+
+        let encoded = save_kit_to_text(&kit);
+        assert!(encoded.contains("region|2|2000|5000"));
+        assert_eq!(load_kit_from_text(&encoded).expect("kit decode"), kit);
+        assert!(load_kit_from_text("FF_KIT_V1\nregion|0|9|3").is_err());
+    }
+
+    #[test]
+    fn track_trims_roundtrip_in_kit() {
+        let mut kit = Kit::default();
+        let trim = TrackSampleTrim {
+            track_index: 5,
+            start: 0.25,
+            end: 0.75,
+            reverse: true,
+        };
+        assert!(kit.set_track_trim(trim));
+        assert!(!kit.set_track_trim(TrackSampleTrim {
+            start: 0.75,
+            end: 0.25,
+            ..trim
+        }));
+        assert!(!kit.set_track_trim(TrackSampleTrim {
+            track_index: 8,
+            ..trim
+        }));
+        assert_eq!(kit.track_trim(5), Some(trim));
+
+        let encoded = save_kit_to_text(&kit);
+        assert!(encoded.contains("trim|5|0.250000|0.750000|1"));
+        assert_eq!(load_kit_from_text(&encoded).expect("kit decode"), kit);
+        assert!(load_kit_from_text("FF_KIT_V1\ntrim|0|0.5|1.0|2").is_err());
+        kit.clear_track_trim(5);
+        assert_eq!(kit.track_trim(5), None);
+    }
+
+    #[test]
+    fn track_groups_roundtrip_with_exclusive_membership() {
+        let mut kit = Kit::default();
+        let drums = TrackGroup {
+            members: vec![2, 0, 1],
+            gain_db: -3.0,
+            choke: true,
+            ..TrackGroup::new(0, "drums")
+        };
+        assert!(kit.set_track_group(drums));
+        assert!(kit.set_track_group(TrackGroup {
+            members: vec![1, 5],
+            soloed: true,
+            ..TrackGroup::new(1, "perc")
+        }));
+        assert!(!kit.set_track_group(TrackGroup::new(4, "bus")));
+        assert!(!kit.set_track_group(TrackGroup {
+            members: vec![8],
+            ..TrackGroup::new(2, "bus")
+        }));
+        assert_eq!(kit.track_group(0).expect("drums").members, vec![0, 2]);
+        assert_eq!(kit.group_of_track(1), Some(1));
+        assert_eq!(kit.group_of_track(7), None);
+
+        let encoded = save_kit_to_text(&kit);
+        assert!(encoded.contains("group|0|6472756D73|0,2|-3.000000|0|0|1"));
+        assert_eq!(load_kit_from_text(&encoded).expect("decode"), kit);
+        assert!(load_kit_from_text("FF_KIT_V1\ngroup|0|00|-|0|2|0|0").is_err());
+    }
+
+    #[test]
+    fn track_ducking_roundtrips_in_kit() {
+        let mut kit = Kit::default();
+        let ducking = TrackDucking {
+            track_index: 1,
+            source_track: 0,
+            depth: 0.75,
+            attack_ms: 4.0,
+            release_ms: 180.0,
+        };
+        assert!(kit.set_track_ducking(ducking));
+        assert!(!kit.set_track_ducking(TrackDucking {
+            source_track: 1,
+            ..ducking
+        }));
+        assert!(!kit.set_track_ducking(TrackDucking {
+            depth: 1.5,
+            ..ducking
+        }));
+        assert_eq!(kit.track_ducking(1), Some(ducking));
+
+        let encoded = save_kit_to_text(&kit);
+        assert!(encoded.contains("duck|1|0|0.750000|4.000000|180.000000"));
+        assert_eq!(load_kit_from_text(&encoded).expect("kit decode"), kit);
+        assert!(load_kit_from_text("FF_KIT_V1\nduck|2|2|0.5|1|1").is_err());
+    }
+
+    #[test]
+    fn track_midi_output_roundtrips_in_kit() {
+        let mut kit = Kit::default();
+        let output = TrackMidiOutput {
+            track_index: 5,
+            channel: 9,
+            note: 42,
+            length_steps: 0.5,
+        };
+        assert!(kit.set_track_midi_output(output));
+        assert!(!kit.set_track_midi_output(TrackMidiOutput {
+            channel: 16,
+            ..output
+        }));
+        assert!(!kit.set_track_midi_output(TrackMidiOutput {
+            length_steps: 0.0,
+            ..output
+        }));
+        assert_eq!(kit.track_midi_output(5), Some(output));
+
+        let encoded = save_kit_to_text(&kit);
+        assert!(encoded.contains("midi_out|5|9|42|0.500000"));
+        assert_eq!(load_kit_from_text(&encoded).expect("kit decode"), kit);
+        kit.clear_track_midi_output(5);
+        assert_eq!(kit.track_midi_output(5), None);
+    }
+
+    #[test]
+    fn track_humanize_roundtrips_in_kit() {
+        let mut kit = Kit::default();
+        let humanize = TrackHumanize {
+            track_index: 6,
+            timing_samples: 240,
+            velocity: 12,
+        };
+        assert!(kit.set_track_humanize(humanize));
+        assert!(!kit.set_track_humanize(TrackHumanize {
+            timing_samples: MAX_HUMANIZE_TIMING_SAMPLES + 1,
+            ..humanize
+        }));
+        assert!(!kit.set_track_humanize(TrackHumanize {
+            track_index: TRACK_COUNT as u8,
+            ..humanize
+        }));
+        assert_eq!(kit.track_humanize(6), Some(humanize));
+        assert_eq!(kit.track_humanize(0), None);
+
+        let encoded = save_kit_to_text(&kit);
+        assert!(encoded.contains("humanize|6|240|12"));
+        assert_eq!(load_kit_from_text(&encoded).expect("kit decode"), kit);
+        assert!(load_kit_from_text("FF_KIT_V1\nhumanize|1|10|65").is_err());
+        kit.clear_track_humanize(6);
+        assert_eq!(kit.track_humanize(6), None);
+    }
+
+    #[test]
+    fn project_humanize_roundtrips_and_rejects_out_of_range_depths() {
+        let project = Project {
+            humanize: HumanizeDepth {
+                timing_samples: 96,
+                velocity: 10,
+            },
+            ..Project::default()
+        };
+        let encoded = save_project_to_text(&project);
+        assert!(encoded.contains("\nhumanize=96|10"));
+        assert_eq!(load_project_from_text(&encoded), Ok(project));
+        assert!(!save_project_to_text(&Project::default()).contains("humanize="));
+        assert!(load_project_from_text("FF_PROJECT_V1\nhumanize=10|65").is_err());
+        assert!(load_project_from_text("FF_PROJECT_V1\nhumanize=4801|0").is_err());
+        assert!(load_project_from_text("FF_PROJECT_V1\nhumanize=10").is_err());
+    }
+
+    #[test]
+    fn project_audio_hints_roundtrip_and_reject_out_of_range_values() {
+        let mut project = Project {
+            audio_hints: AudioHints {
+                sample_rate_hz: Some(44_100),
+                block_size: Some(256),
+            },
+            ..Project::default()
+        };
+        let encoded = save_project_to_text(&project);
+        assert!(encoded.contains("\naudio_hints=44100|256"));
+        assert_eq!(load_project_from_text(&encoded), Ok(project.clone()));
+
+        project.audio_hints.block_size = None;
+        let encoded = save_project_to_text(&project);
+        assert!(encoded.contains("\naudio_hints=44100|-"));
+        assert_eq!(load_project_from_text(&encoded), Ok(project));
+        assert!(!save_project_to_text(&Project::default()).contains("audio_hints="));
+        assert!(load_project_from_text("FF_PROJECT_V1\naudio_hints=4000|-").is_err());
+        assert!(load_project_from_text("FF_PROJECT_V1\naudio_hints=-|0").is_err());
+        assert!(load_project_from_text("FF_PROJECT_V1\naudio_hints=-|16384").is_err());
+        assert!(load_project_from_text("FF_PROJECT_V1\naudio_hints=48000").is_err());
+    }
+
+    #[test]
+    fn asymmetric_choke_targets_roundtrip_in_kit() {
+        let mut kit = Kit::default();
+        let open_hat_cut = TrackChokeTargets {
+            track_index: 2,
+            targets: 0b1000,
+        };
+        assert!(kit.set_track_choke_targets(open_hat_cut));
+        assert!(!kit.set_track_choke_targets(TrackChokeTargets {
+            track_index: 3,
+            targets: 0b1000,
+        }));
+        assert!(!kit.set_track_choke_targets(TrackChokeTargets {
+            track_index: TRACK_COUNT as u8,
+            targets: 1,
+        }));
+        assert!(kit.chokes(2, 3));
+        assert!(!kit.chokes(3, 2));
+        assert!(!kit.chokes(2, 8));
+
+        let encoded = save_kit_to_text(&kit);
+        assert!(encoded.contains("choke|2|8"));
+        assert_eq!(load_kit_from_text(&encoded).expect("kit decode"), kit);
+        assert!(load_kit_from_text("FF_KIT_V1\nchoke|1|2").is_err());
+
+        assert!(kit.set_track_choke_targets(TrackChokeTargets {
+            track_index: 2,
+            targets: 0,
+        }));
+        assert_eq!(kit.track_choke_targets(2), None);
+    }
+
+    #[test]
+    fn master_bus_settings_roundtrip_in_kit() {
+        let mut kit = Kit::default();
+        assert!(!save_kit_to_text(&kit).contains("master|"));
+        let master = MasterBus {
+            compressor_amount: 0.4,
+            filter_cutoff: 0.75,
+            limiter_ceiling_db: -1.5,
+        };
+        assert!(kit.set_master_bus(master));
+        assert!(!kit.set_master_bus(MasterBus {
+            limiter_ceiling_db: LIMITER_CEILING_DB_MIN - 1.0,
+            ..master
+        }));
+        assert!(!kit.set_master_bus(MasterBus {
+            compressor_amount: f32::NAN,
+            ..master
+        }));
+
+        let encoded = save_kit_to_text(&kit);
+        assert!(encoded.contains("master|0.400000|0.750000|-1.500000"));
+        assert_eq!(
+            load_kit_from_text(&encoded).expect("kit decode").master,
+            master
+        );
+        assert!(load_kit_from_text("FF_KIT_V1\nmaster|0|1|3").is_err());
+        assert!(load_kit_from_text("FF_KIT_V1\nmaster|0|1").is_err());
+    }
+
+    #[test]
+    fn track_labels_roundtrip_in_kit() {
+        let mut kit = Kit::default();
+        let percussion = TrackLabel {
+            track_index: 5,
+            name: "Conga | Lo".to_string(),
+            color: Some(0x33_CC99),
+        };
+        assert!(kit.set_track_label(percussion.clone()));
+        assert!(kit.set_track_label(TrackLabel {
+            track_index: 0,
+            name: "Kick".to_string(),
+            color: None,
+        }));
+        assert!(!kit.set_track_label(TrackLabel {
+            color: Some(MAX_TRACK_COLOR + 1),
+            ..percussion.clone()
+        }));
+        assert!(!kit.set_track_label(TrackLabel {
+            name: "n".repeat(MAX_TRACK_NAME_CHARS + 1),
+            ..percussion.clone()
+        }));
+        assert_eq!(kit.track_label(5), Some(&percussion));
+        assert_eq!(kit.labels[0].name, "Kick");
+
+        let encoded = save_kit_to_text(&kit);
+        assert!(encoded.contains("label|0|4B69636B|-"));
+        assert!(encoded.contains("label|5|436F6E6761207C204C6F|33CC99"));
+        assert_eq!(load_kit_from_text(&encoded).expect("kit decode"), kit);
+        assert!(load_kit_from_text("FF_KIT_V1\nlabel|1|4B|GG0000").is_err());
+        kit.clear_track_label(5);
+        assert_eq!(kit.track_label(5), None);
+    }
+
+    #[test]
+    fn pattern_steps_and_swing_are_mutable() {
+        let mut pattern = Pattern::default();
+        assert!(pattern.set_step(
+            2,
+            4,
+            PatternStep {
+                active: true,
+                velocity: 127,
+                ..PatternStep::default()
+            },
+        ));
+        pattern.set_swing(0.3);
+
+        let encoded = save_pattern_to_text(&pattern);
+        let decoded = load_pattern_from_text(&encoded).expect("pattern decode");
+        assert_eq!(pattern, decoded);
+    }
+
+    #[test]
+    fn multi_bar_patterns_roundtrip_with_markers() {
+        let mut pattern = Pattern::default();
+        let hit = PatternStep {
+            active: true,
+            velocity: 90,
+            ..PatternStep::default()
+        };
+        assert!(!pattern.set_step(0, STEPS_PER_PATTERN, hit));
+        assert!(!pattern.set_bar_count(MAX_PATTERN_BARS + 1));
+        assert!(pattern.set_bar_count(MAX_PATTERN_BARS));
+        assert!(pattern.set_step(0, MAX_PATTERN_STEPS - 1, hit));
+        assert!(pattern.set_bar_marker(2, "break"));
+        assert!(pattern.set_bar_marker(0, "intro"));
+        assert_eq!(pattern.bar_marker(2), Some("break"));
+
+        let encoded = save_pattern_to_text(&pattern);
+        assert!(encoded.contains("\nbars=4\n"));
+        assert_eq!(load_pattern_from_text(&encoded).expect("decode"), pattern);
+
+        assert!(pattern.set_bar_count(2));
+        assert_eq!(pattern.step(0, MAX_PATTERN_STEPS - 1), None);
+        assert!(!pattern.steps[0][MAX_PATTERN_STEPS - 1].active);
+        assert_eq!(pattern.bar_marker(2), None);
+        assert!(load_pattern_from_text("FF_PATTERN_V1\nmarker|1|41").is_err());
+    }
+
+    #[test]
+    fn stored_floats_roundtrip_exactly_and_reject_non_finite_input() {
+        for value in [
+            0.25,
+            -0.0,
+            1.0 / 3.0,
+            0.1,
+            1.0e-9,
+            -57.5,
+            f32::EPSILON,
+            299.99997,
+        ] {
+            let text = format_f32(value);
+            assert_eq!(
+                parse_f32(&text, "test").map(f32::to_bits),
+                Ok(value.to_bits())
+            );
+        }
+        assert_eq!(format_f32(0.25), "0.250000");
+        assert_eq!(format_f32(1.0 / 3.0), "0.33333334");
+
+        for (text, error) in [
+            ("NaN", "non-finite float for test: NaN"),
+            ("-inf", "non-finite float for test: -inf"),
+            ("1e39", "non-finite float for test: 1e39"),
+            ("2e6", "float out of range for test: 2e6"),
+            ("1.0x", "invalid float for test: 1.0x"),
+        ] {
+            assert_eq!(parse_f32(text, "test"), Err(error.to_string()));
+        }
+        assert_eq!(
+            load_pattern_from_text("FF_PATTERN_V1\nswing=nan"),
+            Err("non-finite float for pattern.swing: nan".to_string())
+        );
+
+        let mut pattern = Pattern::default();
+        pattern.set_swing(1.0 / 7.0);
+        let encoded = save_pattern_to_text(&pattern);
+        assert_eq!(
+            load_pattern_from_text(&encoded).map(|decoded| decoded.swing.to_bits()),
+            Ok(pattern.swing.to_bits())
+        );
+    }
+
+    #[test]
+    fn accent_curves_interpolate_clip_and_roundtrip_with_patterns() {
+        let flat = AccentCurve::default();
+        assert_eq!(flat.apply(50), 50 + DEFAULT_ACCENT_AMOUNT);
+        assert_eq!(flat.apply(120), 127);
+
+        let curve = AccentCurve {
+            boosts: [40, 40, 20, 0, 0],
+            clip: AccentClip::Clamp,
+        };
+        assert_eq!(curve.boost_at(0), 40);
+        assert_eq!(curve.boost_at(127 / 4 + 16), 30);
+        assert_eq!(curve.boost_at(127), 0);
+        assert_eq!(curve.apply(100), 100);
+
+        let headroom = AccentCurve {
+            boosts: [64; ACCENT_CURVE_POINTS],
+            clip: AccentClip::Headroom,
+        };
+        assert_eq!(headroom.apply(0), 64);
+        assert_eq!(headroom.apply(127), 127);
+        assert!(headroom.apply(120) < 127);
+        assert!(!AccentCurve::flat(MAX_ACCENT_AMOUNT + 1).is_valid());
+
+        let pattern = Pattern {
+            accent: headroom,
+            ..Pattern::default()
+        };
+        let encoded = save_pattern_to_text(&pattern);
+        assert!(encoded.contains("\naccent=64,64,64,64,64|headroom"));
+        assert_eq!(load_pattern_from_text(&encoded).expect("decode"), pattern);
+        assert!(!save_pattern_to_text(&Pattern::default()).contains("accent="));
+        assert!(load_pattern_from_text("FF_PATTERN_V1\naccent=1,2,3|clamp").is_err());
+        assert!(load_pattern_from_text("FF_PATTERN_V1\naccent=1,2,3,4,200|clamp").is_err());
+        assert!(load_pattern_from_text("FF_PATTERN_V1\naccent=1,2,3,4,5|fold").is_err());
+    }
+
+    #[test]
+    fn odd_length_patterns_end_part_way_through_their_last_bar() {
+        let mut pattern = Pattern::default();
+        let hit = PatternStep {
+            active: true,
+            velocity: 90,
+            ..PatternStep::default()
+        };
+        assert!(!pattern.set_step_count(0));
+        assert!(!pattern.set_step_count(MAX_PATTERN_STEPS + 1));
+        assert!(pattern.set_bar_count(2));
+        assert!(pattern.set_step(1, 30, hit));
+        assert!(pattern.set_step_count(24));
+        assert_eq!((pattern.bar_count, pattern.step_count()), (2, 24));
+        assert!(!pattern.steps[1][30].active);
+        assert!(!pattern.set_step(1, 24, hit));
+        assert!(pattern.set_step(1, 23, hit));
+
+        let encoded = save_pattern_to_text(&pattern);
+        assert!(encoded.contains("\nbars=2\nlength=24\n"));
+        assert_eq!(load_pattern_from_text(&encoded).expect("decode"), pattern);
+        assert!(load_pattern_from_text("FF_PATTERN_V1\nbars=2\nlength=12").is_err());
+        assert!(load_pattern_from_text("FF_PATTERN_V1\nbars=2\nlength=32").is_err());
+
+        assert!(pattern.set_step_count(32));
+        assert_eq!(pattern.length_steps, None);
+        assert!(pattern.set_step_count(12));
+        assert_eq!((pattern.bar_count, pattern.step_count()), (1, 12));
+        assert!(pattern.set_bar_count(3));
+        assert_eq!(pattern.step_count(), 3 * STEPS_PER_PATTERN);
+    }
+
+    #[test]
+    fn mute_spans_merge_roundtrip_and_follow_bar_count() {
+        let mut pattern = Pattern::default();
+        assert!(!pattern.set_mute_span(1, 0, 1));
+        assert!(pattern.set_bar_count(MAX_PATTERN_BARS));
+        assert!(pattern.set_mute_span(1, 2, 2));
+        assert!(pattern.set_mute_span(1, 1, 1));
+        assert!(pattern.set_mute_span(0, 3, 3));
+        assert!(!pattern.set_mute_span(1, 3, 2));
+        assert!(!pattern.set_mute_span(TRACK_COUNT, 0, 0));
+        assert_eq!(
+            pattern.mutes,
+            vec![
+                MuteSpan {
+                    track_index: 0,
+                    first_bar: 3,
+                    last_bar: 3,
+                },
+                MuteSpan {
+                    track_index: 1,
+                    first_bar: 1,
+                    last_bar: 2,
+                },
+            ]
+        );
+        assert!(pattern.track_muted_at(1, 2));
+        assert!(!pattern.track_muted_at(1, 3));
+
+        let encoded = save_pattern_to_text(&pattern);
+        assert!(encoded.lines().any(|line| line == "mute|1|1|2"));
+        assert!(!encoded.contains("step|"));
+        assert_eq!(load_pattern_from_text(&encoded).expect("decode"), pattern);
+        assert!(load_pattern_from_text("FF_PATTERN_V1\nmute|0|0|1").is_err());
+
+        assert!(pattern.set_bar_count(2));
+        assert_eq!(pattern.mutes.len(), 1);
+        assert_eq!(pattern.mutes[0].last_bar, 1);
+        pattern.clear_mute_spans(1);
+        assert!(pattern.mutes.is_empty());
+    }
+
+    #[test]
+    fn parameter_locks_roundtrip_and_follow_bar_count() {
+        let lock = |step_index: u8, target: LockTarget, value: f32| ParameterLock {
+            track_index: 2,
+            step_index,
+            target,
+            value,
+        };
+        let mut pattern = Pattern::default();
+        assert!(pattern.set_bar_count(2));
+        assert!(pattern.set_parameter_lock(lock(20, LockTarget::Pitch, 0.75)));
+        assert!(pattern.set_parameter_lock(lock(4, LockTarget::FilterCutoff, 0.25)));
+        assert!(pattern.set_parameter_lock(lock(4, LockTarget::FilterCutoff, 0.5)));
+        assert!(!pattern.set_parameter_lock(lock(32, LockTarget::Gain, 0.5)));
+        assert!(!pattern.set_parameter_lock(lock(4, LockTarget::Gain, 1.5)));
+        assert_eq!(pattern.locks.len(), 2);
+        assert_eq!(
+            pattern.parameter_lock(2, 4, LockTarget::FilterCutoff),
+            Some(0.5)
+        );
+
+        let encoded = save_pattern_to_text(&pattern);
+        assert!(encoded.contains("\nlock|2|4|cutoff|0.500000\n"));
+        assert_eq!(load_pattern_from_text(&encoded), Ok(pattern.clone()));
+        assert!(load_pattern_from_text("FF_PATTERN_V1\nlock|2|4|drive|0.5").is_err());
+        assert!(load_pattern_from_text("FF_PATTERN_V1\nlock|2|16|gain|0.5").is_err());
+
+        assert!(pattern.set_bar_count(1));
+        assert_eq!(pattern.parameter_lock(2, 20, LockTarget::Pitch), None);
+        assert!(pattern.clear_parameter_lock(2, 4, LockTarget::FilterCutoff));
+        assert!(!pattern.clear_parameter_lock(2, 4, LockTarget::FilterCutoff));
+        assert!(pattern.locks.is_empty());
+    }
+
+    #[test]
+    fn track_playback_directions_roundtrip_in_pattern() {
+        let mut pattern = Pattern::default();
+        assert!(!save_pattern_to_text(&pattern).contains("direction|"));
+        assert!(pattern.set_track_direction(1, PlaybackDirection::PingPong));
+        assert!(pattern.set_track_direction(7, PlaybackDirection::Random));
+        assert!(!pattern.set_track_direction(TRACK_COUNT, PlaybackDirection::Reverse));
 
-        return Err(format!("unknown project line: {line}"));
+        let encoded = save_pattern_to_text(&pattern);
+        assert!(encoded.contains("direction|1|pingpong"));
+        assert_eq!(load_pattern_from_text(&encoded), Ok(pattern));
+        assert!(load_pattern_from_text("FF_PATTERN_V1\ndirection|1|sideways").is_err());
+        assert!(load_pattern_from_text("FF_PATTERN_V1\ndirection|8|reverse").is_err());
     }
 
-    if let Some(raw) = active_kit_raw {
-        if raw >= 0 {
-            let index = usize::try_from(raw).map_err(|_| "invalid active_kit index".to_string())?;
-            if index >= project.kits.len() {
-                return Err(format!("active_kit out of range: {index}"));
-            }
-            project.active_kit = Some(index);
-        }
-    }
+    #[test]
+    fn track_step_resolutions_roundtrip_in_pattern() {
+        let mut pattern = Pattern::default();
+        assert!(!save_pattern_to_text(&pattern).contains("resolution|"));
+        assert!(pattern.set_track_resolution(2, StepResolution::SixteenthTriplet));
+        assert!(pattern.set_track_resolution(5, StepResolution::ThirtySecond));
+        assert!(!pattern.set_track_resolution(TRACK_COUNT, StepResolution::EighthTriplet));
 
-    if let Some(raw) = active_pattern_raw {
-        if raw >= 0 {
-            let index =
-                usize::try_from(raw).map_err(|_| "invalid active_pattern index".to_string())?;
-            if index >= project.patterns.len() {
-                return Err(format!("active_pattern out of range: {index}"));
-            }
-            project.active_pattern = Some(index);
+        let encoded = save_pattern_to_text(&pattern);
+        assert!(encoded.contains("resolution|2|1/16t"));
+        assert!(encoded.contains("resolution|5|1/32"));
+        assert_eq!(load_pattern_from_text(&encoded), Ok(pattern.clone()));
+        assert!(load_pattern_from_text("FF_PATTERN_V1\nresolution|1|1/12").is_err());
+        assert!(load_pattern_from_text("FF_PATTERN_V1\nresolution|8|1/8t").is_err());
+
+        assert!(!encoded.contains("swing_grid="));
+        pattern.swing_grid = SwingGrid::Sixteenth;
+        let encoded = save_pattern_to_text(&pattern);
+        assert!(encoded.contains("\nswing_grid=sixteenth"));
+        assert_eq!(load_pattern_from_text(&encoded), Ok(pattern));
+        assert!(load_pattern_from_text("FF_PATTERN_V1\nswing_grid=bar").is_err());
+        for resolution in StepResolution::ALL {
+            assert_eq!(RESOLUTION_TICKS_PER_BEAT % resolution.ticks_per_step(), 0);
         }
     }
 
-    Ok(project)
-}
+    #[test]
+    fn track_default_steps_roundtrip_in_pattern() {
+        let mut pattern = Pattern::default();
+        assert!(!save_pattern_to_text(&pattern).contains("default|"));
+        let template = PatternStep {
+            active: true,
+            velocity: 70,
+            probability: 80,
+            gate: 50,
+            ..PatternStep::default()
+        };
+        assert!(pattern.set_track_default_step(3, template));
+        assert!(!pattern.set_track_default_step(TRACK_COUNT, template));
+        assert!(!pattern.set_track_default_step(
+            2,
+            PatternStep {
+                gate: 0,
+                ..template
+            }
+        ));
+        assert!(!pattern.default_steps[3].active);
 
-#[cfg(test)]
-mod tests {
-    use super::{
-        load_kit_from_text, load_pattern_from_text, load_project_from_text, save_kit_to_text,
-        save_pattern_to_text, save_project_to_text, Kit, Pattern, PatternStep, Project,
-        TrackAssignment, TrackControls,
-    };
+        let encoded = save_pattern_to_text(&pattern);
+        assert!(encoded.contains("\ndefault|3|0|70|probability=80|gate=50"));
+        assert_eq!(load_pattern_from_text(&encoded), Ok(pattern));
+        assert!(load_pattern_from_text("FF_PATTERN_V1\ndefault|8|0|70").is_err());
+    }
 
-    fn fuzz_text(seed: u64, len: usize) -> String {
-        let mut state = seed;
-        let mut bytes = Vec::with_capacity(len);
-        for _ in 0..len {
-            // Deterministic LCG for lightweight fuzz-style coverage.
-            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
-            bytes.push((state >> 32) as u8);
-        }
+    #[test]
+    fn pattern_scale_roundtrips_when_not_normal() {
+        let mut pattern = Pattern::default();
+        assert!(!save_pattern_to_text(&pattern).contains("scale="));
 
-        String::from_utf8_lossy(&bytes).into_owned()
+        pattern.scale = PatternScale::Half;
+        let encoded = save_pattern_to_text(&pattern);
+        assert!(encoded.contains("\nscale=half"));
+        assert_eq!(load_pattern_from_text(&encoded).expect("decode"), pattern);
+        assert_eq!(
+            PatternScale::from_name("double"),
+            Some(PatternScale::Double)
+        );
+        assert!(load_pattern_from_text("FF_PATTERN_V1\nscale=3x").is_err());
     }
 
     #[test]
-    fn duplicate_track_assignment_is_rejected() {
-        let mut kit = Kit::default();
-        assert!(kit.add_assignment(TrackAssignment {
-            track_index: 0,
-            sample_id: "kick.01".to_string(),
-        }));
-        assert!(!kit.add_assignment(TrackAssignment {
-            track_index: 0,
-            sample_id: "kick.02".to_string(),
-        }));
+    fn pattern_tempo_override_roundtrips_with_its_change_mode() {
+        let mut pattern = Pattern::default();
+        assert!(!save_pattern_to_text(&pattern).contains("tempo="));
+
+        pattern.tempo = Some(PatternTempo {
+            bpm: 132.5,
+            change: TempoChange::Smooth,
+        });
+        let encoded = save_pattern_to_text(&pattern);
+        assert!(encoded.contains("\ntempo=132.500000|smooth"));
+        assert_eq!(load_pattern_from_text(&encoded).expect("decode"), pattern);
+        assert!(load_pattern_from_text("FF_PATTERN_V1\ntempo=120").is_err());
+        assert!(load_pattern_from_text("FF_PATTERN_V1\ntempo=120|jump").is_err());
+        assert!(load_pattern_from_text("FF_PATTERN_V1\ntempo=900|immediate").is_err());
     }
 
     #[test]
-    fn track_controls_roundtrip_in_kit() {
-        let mut kit = Kit::default();
-        kit.set_track_controls(
-            3,
-            TrackControls {
-                gain: 0.8,
-                pan: -0.25,
-                filter_cutoff: 0.4,
-                envelope_decay: 0.7,
-                pitch_semitones: 3.0,
-                choke_group: Some(1),
-            },
+    fn pattern_variations_store_only_differences_from_the_base() {
+        let hit = PatternStep {
+            active: true,
+            velocity: 90,
+            ..PatternStep::default()
+        };
+        let mut pattern = Pattern::default();
+        pattern.set_step(0, 0, hit);
+        let mut edited = pattern.clone();
+        edited.set_step(0, 0, PatternStep::default());
+        edited.set_step(2, 6, hit);
+
+        assert!(pattern.capture_variation(1, &edited));
+        assert!(!pattern.capture_variation(0, &edited));
+        assert!(!pattern.set_variation_step(PATTERN_VARIATION_COUNT as u8, 0, 0, Some(hit)));
+        assert_eq!(pattern.variations.len(), 2);
+        assert_eq!(pattern.variation_step(1, 2, 6), Some(hit));
+        assert_eq!(
+            pattern.variation_step(1, 0, 0),
+            Some(PatternStep::default())
         );
+        assert_eq!(pattern.variation_step(2, 0, 0), Some(hit));
+        assert_eq!(variation_name(1), Some('B'));
 
-        let encoded = save_kit_to_text(&kit);
-        let decoded = load_kit_from_text(&encoded).expect("kit decode");
-        assert_eq!(kit, decoded);
+        let encoded = save_pattern_to_text(&pattern);
+        assert!(encoded.contains("\nvariation|1|2|6|1|90"));
+        assert_eq!(load_pattern_from_text(&encoded).expect("decode"), pattern);
+        assert!(load_pattern_from_text("FF_PATTERN_V1\nvariation|1|0|16|1|90").is_err());
+
+        assert!(pattern.set_variation_step(1, 2, 6, None));
+        assert_eq!(pattern.variations.len(), 1);
+        pattern.clear_variation(1);
+        assert!(pattern.variations.is_empty());
     }
 
     #[test]
-    fn pattern_steps_and_swing_are_mutable() {
+    fn step_options_are_written_only_when_they_differ_from_the_default() {
         let mut pattern = Pattern::default();
-        assert!(pattern.set_step(
+        let hit = PatternStep {
+            active: true,
+            velocity: 90,
+            ..PatternStep::default()
+        };
+        pattern.set_step(0, 0, hit);
+        pattern.set_step(
+            1,
+            2,
+            PatternStep {
+                probability: 40,
+                condition: TrigCondition::Cycle { hit: 2, of: 4 },
+                ..hit
+            },
+        );
+        pattern.set_step(
             2,
+            3,
+            PatternStep {
+                condition: TrigCondition::NotPre,
+                accent: true,
+                ..hit
+            },
+        );
+        pattern.set_step(
+            3,
             4,
             PatternStep {
-                active: true,
-                velocity: 127,
+                flam: true,
+                flam_spacing_ms: 35,
+                ..hit
             },
-        ));
-        pattern.set_swing(0.3);
+        );
 
         let encoded = save_pattern_to_text(&pattern);
-        let decoded = load_pattern_from_text(&encoded).expect("pattern decode");
-        assert_eq!(pattern, decoded);
+        assert!(encoded.contains("\nstep|0|0|1|90\n"));
+        assert!(encoded.contains("\nstep|1|2|1|90|probability=40|condition=2:4"));
+        assert!(encoded.contains("\nstep|2|3|1|90|condition=not_pre|accent=1"));
+        assert!(encoded.contains("\nstep|3|4|1|90|flam=1|flam_spacing=35"));
+        assert_eq!(load_pattern_from_text(&encoded).expect("decode"), pattern);
+        for invalid in [
+            "step|0|0|1|90|probability=101",
+            "step|0|0|1|90|probability",
+            "step|0|0|1|90|unknown=1",
+            "step|0|0|1|90|condition=5:4",
+            "step|0|0|1|90|condition=sometimes",
+            "step|0|0|1|90|accent=yes",
+            "step|0|0|1|90|flam=1|flam_spacing=0",
+            "step|0|0|1|90|flam_spacing=61",
+            "step|0|0|1|90|gate=0",
+            "step|0|0|1|90|gate=101",
+        ] {
+            assert!(load_pattern_from_text(&format!("FF_PATTERN_V1\n{invalid}")).is_err());
+        }
     }
 
     #[test]
@@ -635,6 +4508,7 @@ mod tests {
             active_kit: None,
             patterns: vec![Pattern::default()],
             active_pattern: None,
+            ..Project::default()
         };
 
         assert!(project.set_active_kit(0));
@@ -643,6 +4517,110 @@ mod tests {
         assert!(!project.set_active_pattern(2));
     }
 
+    #[test]
+    fn loaders_tolerate_crlf_trailing_whitespace_and_blank_lines() {
+        let mut project = Project {
+            name: "windows".to_string(),
+            kits: vec![Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![Pattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        project.kits[0].add_assignment(TrackAssignment {
+            track_index: 1,
+            sample_id: "snare".to_string(),
+        });
+        project.patterns[0].set_swing(0.25);
+        project.patterns[0].set_step(
+            1,
+            4,
+            PatternStep {
+                active: true,
+                velocity: 90,
+                ..PatternStep::default()
+            },
+        );
+
+        // The way a Windows editor saves it: a byte-order mark, CRLF endings,
+        // stray spaces and tabs, and blank lines between sections.
+        let mangle = |text: &str| {
+            let mut mangled = String::from("\u{feff}");
+            for (index, line) in text.lines().enumerate() {
+                mangled.push_str(line);
+                mangled.push_str(if index % 2 == 0 {
+                    " \t\r\n"
+                } else {
+                    "\r\n\r\n"
+                });
+            }
+            mangled
+        };
+        assert_eq!(
+            load_project_from_text(&mangle(&save_project_to_text(&project))),
+            Ok(project.clone())
+        );
+        assert_eq!(
+            load_kit_from_text(&mangle(&save_kit_to_text(&project.kits[0]))),
+            Ok(project.kits[0].clone())
+        );
+        assert_eq!(
+            load_pattern_from_text(&mangle(&save_pattern_to_text(&project.patterns[0]))),
+            Ok(project.patterns[0].clone())
+        );
+        let setlist = SetList {
+            name: "tour".to_string(),
+            entries: vec![SetListEntry::new("windows.ffp")],
+        };
+        assert_eq!(
+            load_setlist_from_text(&mangle(&save_setlist_to_text(&setlist))),
+            Ok(setlist)
+        );
+
+        // Floats are written with a decimal point whatever the system locale.
+        assert!(save_pattern_to_text(&project.patterns[0]).contains("\nswing=0.250000"));
+        assert!(load_pattern_from_text("FF_PATTERN_V1\nswing=0,25").is_err());
+        assert!(load_pattern_from_text("FF_PATTERN_V1\n  swing=0.25").is_err());
+    }
+
+    #[test]
+    fn incremental_saves_rewrite_only_changed_blocks() {
+        let mut project = Project {
+            kits: vec![Kit::default(); 2],
+            active_kit: Some(0),
+            patterns: vec![Pattern::default(); 3],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        project.kits[1].add_assignment(TrackAssignment {
+            track_index: 2,
+            sample_id: "hat".to_string(),
+        });
+
+        let mut saver = IncrementalProjectSave::new();
+        assert_eq!(saver.dirty_kits(&project), vec![0, 1]);
+        assert_eq!(saver.dirty_patterns(&project), vec![0, 1, 2]);
+        assert_eq!(saver.save(&project), save_project_to_text(&project));
+        assert!(saver.dirty_kits(&project).is_empty());
+        assert!(saver.dirty_patterns(&project).is_empty());
+        assert!(saver.dirty_frozen_tracks(&project).is_empty());
+
+        project.name = "autosaved".to_string();
+        project.patterns[1].set_swing(0.4);
+        project.patterns.push(Pattern::default());
+        assert!(saver.dirty_kits(&project).is_empty());
+        assert_eq!(saver.dirty_patterns(&project), vec![1, 3]);
+        let text = saver.save(&project);
+        assert_eq!(text, save_project_to_text(&project));
+        assert_eq!(load_project_from_text(&text), Ok(project.clone()));
+
+        project.kits.truncate(1);
+        project.patterns.remove(0);
+        assert_eq!(saver.dirty_patterns(&project), vec![0, 1]);
+        assert_eq!(saver.save(&project), save_project_to_text(&project));
+        assert!(saver.dirty_patterns(&project).is_empty());
+    }
+
     #[test]
     fn project_text_roundtrip_is_deterministic() {
         let mut project = Project {
@@ -651,6 +4629,7 @@ mod tests {
             active_kit: Some(0),
             patterns: vec![Pattern::default()],
             active_pattern: Some(0),
+            ..Project::default()
         };
 
         project.kits[0].name = "kit-a".to_string();
@@ -661,7 +4640,7 @@ mod tests {
         project.kits[0].set_track_controls(
             0,
             TrackControls {
-                gain: 1.2,
+                gain_db: 1.5,
                 pan: 0.1,
                 filter_cutoff: 0.6,
                 envelope_decay: 0.8,
@@ -669,6 +4648,22 @@ mod tests {
                 choke_group: Some(1),
             },
         );
+        assert!(project.output_routing.set_track_output(3, 2));
+        assert!(!project.output_routing.set_track_output(3, MAX_OUTPUT_PAIRS));
+        project.random_seed = u64::MAX - 5;
+        project.key = MusicalKey {
+            root: 9,
+            scale: MusicalScale::Minor,
+        };
+        project.track_inputs.set_armed(2, true);
+        project.track_inputs.set_monitoring(5, false);
+        project.metronome = Metronome {
+            enabled: true,
+            accent_sample_id: "hi".to_string(),
+            gain_db: -6.0,
+            output_pair: 3,
+            ..Metronome::default()
+        };
         project.patterns[0].name = "main".to_string();
         project.patterns[0].set_swing(0.2);
         project.patterns[0].set_step(
@@ -677,6 +4672,7 @@ mod tests {
             PatternStep {
                 active: true,
                 velocity: 120,
+                ..PatternStep::default()
             },
         );
 
@@ -686,6 +4682,119 @@ mod tests {
 
         assert_eq!(project, decoded);
         assert_eq!(encoded_1, encoded_2);
+        assert!(encoded_1.contains("\noutput|3|2\n"));
+        assert!(encoded_1.contains(&format!("\nseed={}\n", u64::MAX - 5)));
+        assert!(encoded_1.contains("\nkey=A|minor\n"));
+        assert!(load_project_from_text("FF_PROJECT_V1\nkey=H|minor").is_err());
+        assert!(encoded_1.contains("\nmetronome|1|6869|-|-6.000000|3|0\n"));
+        assert!(encoded_1.contains("\ninput|2|1|1\ninput|5|0|0\n"));
+        assert_eq!(decoded.track_inputs.armed_tracks(), vec![2]);
+        assert!(load_project_from_text("FF_PROJECT_V1\ninput|8|1|1").is_err());
+        assert!(load_project_from_text("FF_PROJECT_V1\nmetronome|1|-|-|0|8|0").is_err());
+        assert_eq!(decoded.output_routing.output_pair_count(), 3);
+        assert_eq!(decoded.output_routing.tracks_on_output(2), vec![3]);
+        assert!(load_project_from_text("FF_PROJECT_V1\noutput|8|1").is_err());
+    }
+
+    #[test]
+    fn program_changes_select_patterns_and_kits_and_roundtrip_in_project() {
+        let mut project = Project {
+            kits: vec![Kit::default(); 2],
+            patterns: vec![Pattern::default(); 3],
+            ..Project::default()
+        };
+        let change =
+            |program: u8, pattern_index: Option<usize>, kit_index: Option<usize>| ProgramChange {
+                program,
+                pattern_index,
+                kit_index,
+            };
+        assert!(project.set_program_change(change(9, Some(2), Some(1))));
+        assert!(project.set_program_change(change(4, Some(1), None)));
+        assert!(project.set_program_change(change(4, None, Some(0))));
+        assert!(!project.set_program_change(change(5, None, None)));
+        assert!(!project.set_program_change(change(5, Some(3), None)));
+        assert!(!project.set_program_change(change(MIDI_PROGRAM_COUNT, Some(0), None)));
+        assert_eq!(project.program_changes.len(), 2);
+        assert_eq!(project.program_change(4), Some(&change(4, None, Some(0))));
+
+        assert!(project.apply_program_change(9));
+        assert_eq!(project.active_pattern, Some(2));
+        assert_eq!(project.active_kit, Some(1));
+        assert!(project.apply_program_change(4));
+        assert_eq!(project.active_pattern, Some(2));
+        assert_eq!(project.active_kit, Some(0));
+        assert!(!project.apply_program_change(5));
+
+        let encoded = save_project_to_text(&project);
+        assert!(encoded.contains("\nprogram|4|-|0\nprogram|9|2|1\n"));
+        assert_eq!(load_project_from_text(&encoded), Ok(project.clone()));
+        assert!(project.clear_program_change(4));
+        assert!(!project.clear_program_change(4));
+        assert!(load_project_from_text("FF_PROJECT_V1\nprogram|1|0|-").is_err());
+    }
+
+    #[test]
+    fn song_chain_roundtrips_in_project_and_rejects_bad_entries() {
+        let mut project = Project {
+            patterns: vec![Pattern::default(); 2],
+            song_loop: true,
+            ..Project::default()
+        };
+        let entry = |pattern_index: usize, repeats: u8| SongEntry {
+            pattern_index,
+            repeats,
+        };
+        assert!(project.set_song(&[entry(0, 2), entry(1, 1), entry(0, 4)]));
+        assert!(!project.set_song(&[entry(2, 1)]));
+        assert!(!project.set_song(&[entry(1, 0)]));
+        assert_eq!(project.song.len(), 3);
+
+        let encoded = save_project_to_text(&project);
+        assert!(encoded.contains("\nsong|0|2\nsong|1|1\nsong|0|4\nsong_loop=1\n"));
+        assert_eq!(load_project_from_text(&encoded), Ok(project));
+        assert!(load_project_from_text("FF_PROJECT_V1\nsong|0|1").is_err());
+        assert!(load_project_from_text("FF_PROJECT_V1\nsong_loop=2").is_err());
+    }
+
+    #[test]
+    fn groove_templates_resolve_builtins_and_roundtrip_user_grooves() {
+        let mpc = GrooveTemplate::builtin("mpc58").expect("builtin groove");
+        assert_eq!(mpc.timing_offset(0), 0.0);
+        assert!((mpc.timing_offset(17) - 0.16).abs() < 1e-6);
+        assert!(BUILTIN_GROOVE_NAMES
+            .iter()
+            .all(|name| GrooveTemplate::builtin(name).is_some_and(|groove| groove.is_valid())));
+        assert_eq!(GrooveTemplate::builtin("polka"), None);
+
+        let mut project = Project::default();
+        let mut lazy = GrooveTemplate::new("lazy");
+        lazy.timing[3] = 0.2;
+        lazy.velocity[3] = -20;
+        lazy.velocity[4] = 10;
+        assert!(project.set_groove(lazy.clone()));
+        assert!(!project.set_groove(GrooveTemplate::new("shuffle")));
+        let mut early = GrooveTemplate::new("early");
+        early.timing[1] = -0.1;
+        assert!(!project.set_groove(early));
+        assert_eq!(project.groove("lazy"), Some(lazy.clone()));
+        assert_eq!(project.groove("mpc58"), Some(mpc));
+        assert_eq!(project.groove("missing"), None);
+
+        let mut pattern = Pattern {
+            groove: Some("lazy".to_string()),
+            ..Pattern::default()
+        };
+        pattern.set_swing(0.1);
+        project.patterns.push(pattern);
+        let encoded = save_project_to_text(&project);
+        assert!(encoded.contains("\ngroove|6C617A79|"));
+        assert!(encoded.contains("\ngroove=6C617A79\n"));
+        assert_eq!(load_project_from_text(&encoded), Ok(project.clone()));
+
+        assert!(project.clear_groove("lazy"));
+        assert!(!project.clear_groove("lazy"));
+        assert!(load_project_from_text("FF_PROJECT_V1\ngroove|6C617A79|0|0").is_err());
     }
 
     #[test]
@@ -716,6 +4825,143 @@ mod tests {
         assert!(error.contains("track assignment out of range"));
     }
 
+    #[test]
+    fn freeze_swaps_track_to_bounce_and_unfreeze_restores_it() {
+        let mut kit = Kit::default();
+        assert!(kit.add_assignment(TrackAssignment {
+            track_index: 2,
+            sample_id: "snare.wav".to_string(),
+        }));
+        kit.set_track_controls(
+            2,
+            TrackControls {
+                gain_db: -6.0,
+                ..TrackControls::default()
+            },
+        );
+        assert!(kit.set_track_region(2, 10, 400));
+        let mut pattern = Pattern::default();
+        assert!(pattern.set_step(
+            2,
+            4,
+            PatternStep {
+                active: true,
+                velocity: 90,
+                ..PatternStep::default()
+            },
+        ));
+        let mut project = Project {
+            kits: vec![kit],
+            patterns: vec![pattern],
+            ..Project::default()
+        };
+        let original = project.clone();
+
+        assert!(project.freeze_track(2, "bounces/track-3.wav"));
+        assert!(!project.freeze_track(2, "again.wav"));
+        let kit = &project.kits[0];
+        assert_eq!(kit.tracks[0].sample_id, "bounces/track-3.wav");
+        assert_eq!(kit.track_controls(2), None);
+        assert_eq!(kit.track_region(2), None);
+        assert!(project.patterns[0].steps[2][0].active);
+        assert!(!project.patterns[0].steps[2][4].active);
+        assert_eq!(
+            project
+                .track_freeze(2)
+                .map(|freeze| freeze.original_steps[4].velocity),
+            Some(90)
+        );
+
+        let restored = load_project_from_text(&save_project_to_text(&project))
+            .expect("frozen project should roundtrip");
+        assert_eq!(restored, project);
+
+        assert!(project.unfreeze_track(2));
+        assert!(!project.unfreeze_track(2));
+        assert_eq!(project, original);
+    }
+
+    #[test]
+    fn remap_sample_ids_rewrites_every_reference_and_reports_unresolved() {
+        let mut kit = Kit::default();
+        for (track_index, sample_id) in [(0, "old/kick.wav"), (1, "old/snare.wav"), (2, "hat.wav")]
+        {
+            assert!(kit.add_assignment(TrackAssignment {
+                track_index,
+                sample_id: sample_id.to_string(),
+            }));
+        }
+        let mut project = Project {
+            kits: vec![kit.clone(), kit],
+            patterns: vec![Pattern::default()],
+            metronome: Metronome {
+                accent_sample_id: "old/click.wav".to_string(),
+                ..Metronome::default()
+            },
+            ..Project::default()
+        };
+        assert!(project.freeze_track(1, "bounce.wav"));
+
+        let map: BTreeMap<String, String> = [
+            ("old/kick.wav", "new/kick.wav"),
+            ("old/snare.wav", "new/snare.wav"),
+            ("old/click.wav", "new/click.wav"),
+            ("bounce.wav", "bounce.wav"),
+        ]
+        .into_iter()
+        .map(|(from, to)| (from.to_string(), to.to_string()))
+        .collect();
+        let report = project.remap_sample_ids(&map);
+        assert_eq!(report.replaced, 5);
+        assert_eq!(report.unresolved, vec!["hat.wav".to_string()]);
+        assert_eq!(project.kits[1].tracks[0].sample_id, "new/kick.wav");
+        assert_eq!(project.metronome.accent_sample_id, "new/click.wav");
+
+        assert!(project.unfreeze_track(1));
+        assert_eq!(project.kits[0].tracks[1].sample_id, "new/snare.wav");
+    }
+
+    #[test]
+    fn setlist_roundtrip_preserves_entry_defaults() {
+        let mut setlist = SetList {
+            name: "Friday gig".to_string(),
+            ..SetList::default()
+        };
+        assert!(setlist.add_entry(SetListEntry {
+            tempo_bpm: Some(124.0),
+            ..SetListEntry::new("opener/project.ffp")
+        }));
+        assert!(setlist.add_entry(SetListEntry {
+            start_pattern: Some(2),
+            ..SetListEntry::new("closer|final.ffp")
+        }));
+        assert!(!setlist.add_entry(SetListEntry::new("")));
+        assert!(!setlist.add_entry(SetListEntry {
+            tempo_bpm: Some(900.0),
+            ..SetListEntry::new("too-fast.ffp")
+        }));
+
+        let text = save_setlist_to_text(&setlist);
+        assert_eq!(load_setlist_from_text(&text), Ok(setlist.clone()));
+        assert!(setlist.move_entry(1, 0));
+        assert_eq!(setlist.entries[0].project_path, "closer|final.ffp");
+        assert!(!setlist.move_entry(0, 2));
+        assert!(load_setlist_from_text("FF_SETLIST_V1\nentry|00|-").is_err());
+        assert!(load_setlist_from_text("FF_SETLIST_V1\nentry|61|900.0|-").is_err());
+
+        let mut project = Project {
+            patterns: vec![Pattern::default(); 3],
+            ..Project::default()
+        };
+        assert!(setlist.entries[0].apply_defaults(&mut project));
+        assert_eq!(project.active_pattern, Some(2));
+        assert!(!SetListEntry {
+            start_pattern: Some(5),
+            ..SetListEntry::new("x")
+        }
+        .apply_defaults(&mut project));
+    }
+
     #[test]
     fn parser_fuzz_inputs_do_not_panic() {
         for seed in 0..256u64 {
@@ -725,8 +4971,119 @@ mod tests {
                 let _ = load_kit_from_text(&candidate);
                 let _ = load_pattern_from_text(&candidate);
                 let _ = load_project_from_text(&candidate);
+                let _ = load_setlist_from_text(&candidate);
             });
             assert!(parse_result.is_ok(), "parser panicked at seed {seed}");
         }
     }
+
+    #[test]
+    fn interop_step_fixture_matches_the_saved_project() {
+        let mut kit = Kit::default();
+        for (track_index, sample_id) in [(0, "kick.wav"), (1, "snare.wav"), (2, "clap.wav")] {
+            assert!(kit.add_assignment(TrackAssignment {
+                track_index,
+                sample_id: sample_id.to_string(),
+            }));
+        }
+        kit.set_track_controls(
+            1,
+            TrackControls {
+                gain_db: -6.0,
+                pan: 0.25,
+                ..TrackControls::default()
+            },
+        );
+
+        let mut pattern = Pattern {
+            name: "Interop".to_string(),
+            ..Pattern::default()
+        };
+        pattern.set_swing(0.1);
+        let steps = [
+            (0, 0, PatternStep::default()),
+            (
+                0,
+                8,
+                PatternStep {
+                    probability: 50,
+                    ..PatternStep::default()
+                },
+            ),
+            (
+                1,
+                4,
+                PatternStep {
+                    condition: TrigCondition::Cycle { hit: 1, of: 2 },
+                    accent: true,
+                    ..PatternStep::default()
+                },
+            ),
+            (
+                2,
+                12,
+                PatternStep {
+                    flam: true,
+                    flam_spacing_ms: 30,
+                    gate: 50,
+                    ..PatternStep::default()
+                },
+            ),
+        ];
+        for (track_index, step_index, step) in steps {
+            assert!(pattern.set_step(
+                track_index,
+                step_index,
+                PatternStep {
+                    active: true,
+                    velocity: 110,
+                    ..step
+                },
+            ));
+        }
+
+        let project = Project {
+            name: "Interop Steps|FF_BPM=128.000000".to_string(),
+            kits: vec![kit],
+            active_kit: Some(0),
+            patterns: vec![pattern],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        let fixture = include_str!("../../../fixtures/interop/step_fields.ffproject");
+        assert_eq!(save_project_to_text(&project), fixture);
+        assert_eq!(load_project_from_text(fixture), Ok(project));
+    }
+
+    #[test]
+    fn interop_long_pattern_fixture_matches_the_saved_project() {
+        let mut pattern = Pattern {
+            name: "Long".to_string(),
+            ..Pattern::default()
+        };
+        assert!(pattern.set_step_count(40));
+        for (track_index, step_index) in [(0, 0), (0, 16), (1, 20), (2, 39)] {
+            assert!(pattern.set_step(
+                track_index,
+                step_index,
+                PatternStep {
+                    active: true,
+                    velocity: 100,
+                    ..PatternStep::default()
+                },
+            ));
+        }
+
+        let project = Project {
+            name: "Long Pattern".to_string(),
+            kits: vec![Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![pattern],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        let fixture = include_str!("../../../fixtures/interop/long_pattern.ffproject");
+        assert_eq!(save_project_to_text(&project), fixture);
+        assert_eq!(load_project_from_text(fixture), Ok(project));
+    }
 }
@@ -0,0 +1,140 @@
+use abi_rs::FfLatencyReport;
+use control_rs::TRACK_COUNT;
+
+pub const MAX_LOOKAHEAD_SAMPLES: u32 = 1 << 16;
+
+#[derive(Clone, Debug, Default)]
+struct DelayLine {
+    buffer: Vec<f32>,
+    position: usize,
+}
+
+impl DelayLine {
+    fn resize(&mut self, length: usize) {
+        if self.buffer.len() != length {
+            self.buffer = vec![0.0; length];
+            self.position = 0;
+        }
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        for sample in samples {
+            std::mem::swap(&mut self.buffer[self.position], sample);
+            self.position = (self.position + 1) % self.buffer.len();
+        }
+    }
+
+    fn clear(&mut self) {
+        self.buffer.fill(0.0);
+        self.position = 0;
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LatencyCompensation {
+    track_lookahead: [u32; TRACK_COUNT],
+    master_lookahead: u32,
+    lines: [DelayLine; TRACK_COUNT],
+}
+
+impl LatencyCompensation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn track_lookahead_samples(&self, track_index: usize) -> Option<u32> {
+        self.track_lookahead.get(track_index).copied()
+    }
+
+    pub fn set_track_lookahead_samples(&mut self, track_index: usize, samples: u32) -> bool {
+        if track_index >= TRACK_COUNT || samples > MAX_LOOKAHEAD_SAMPLES {
+            return false;
+        }
+        self.track_lookahead[track_index] = samples;
+        self.resize_lines();
+        true
+    }
+
+    pub fn master_lookahead_samples(&self) -> u32 {
+        self.master_lookahead
+    }
+
+    pub fn set_master_lookahead_samples(&mut self, samples: u32) -> bool {
+        if samples > MAX_LOOKAHEAD_SAMPLES {
+            return false;
+        }
+        self.master_lookahead = samples;
+        self.resize_lines();
+        true
+    }
+
+    pub fn report(&self) -> FfLatencyReport {
+        FfLatencyReport::new(
+            self.track_lookahead.iter().copied().max().unwrap_or(0),
+            self.master_lookahead,
+        )
+    }
+
+    pub fn track_delay_samples(&self, track_index: usize) -> Option<u32> {
+        let lookahead = self.track_lookahead_samples(track_index)?;
+        Some(self.report().total_samples - lookahead)
+    }
+
+    pub fn process_track(&mut self, track_index: usize, samples: &mut [f32]) {
+        if let Some(line) = self.lines.get_mut(track_index) {
+            line.process(samples);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for line in &mut self.lines {
+            line.clear();
+        }
+    }
+
+    fn resize_lines(&mut self) {
+        for track_index in 0..TRACK_COUNT {
+            let delay = self.track_delay_samples(track_index).unwrap_or(0);
+            self.lines[track_index].resize(delay as usize);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LatencyCompensation, MAX_LOOKAHEAD_SAMPLES};
+
+    #[test]
+    fn report_sums_track_and_master_lookahead() {
+        let mut latency = LatencyCompensation::new();
+        assert_eq!(latency.report().total_samples, 0);
+        assert!(latency.set_track_lookahead_samples(2, 64));
+        assert!(latency.set_track_lookahead_samples(5, 16));
+        assert!(latency.set_master_lookahead_samples(32));
+        assert!(!latency.set_track_lookahead_samples(8, 1));
+        assert!(!latency.set_master_lookahead_samples(MAX_LOOKAHEAD_SAMPLES + 1));
+
+        let report = latency.report();
+        assert_eq!(report.track_lookahead_samples, 64);
+        assert_eq!(report.master_lookahead_samples, 32);
+        assert_eq!(report.total_samples, 96);
+        assert_eq!(latency.track_delay_samples(2), Some(32));
+        assert_eq!(latency.track_delay_samples(5), Some(80));
+        assert_eq!(latency.track_delay_samples(0), Some(96));
+    }
+
+    #[test]
+    fn delay_lines_shift_each_track_by_its_compensation() {
+        let mut latency = LatencyCompensation::new();
+        latency.set_track_lookahead_samples(1, 2);
+        let mut pre_triggered = [1.0, 0.0, 0.0, 0.0];
+        let mut regular = [1.0, 0.0, 0.0, 0.0];
+        latency.process_track(1, &mut pre_triggered);
+        latency.process_track(0, &mut regular);
+        assert_eq!(pre_triggered, [1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(regular, [0.0, 0.0, 1.0, 0.0]);
+    }
+}
@@ -0,0 +1,2050 @@
+use abi_rs::FfParameterUpdate;
+use std::time::Instant;
+
+use control_rs::telemetry::{EngineMetrics, TrackMeterReading};
+use control_rs::{RecallState, Sequencer, StepTriggerEvent, TRACK_COUNT};
+use presets_rs::{OutputRouting, Project, MAX_OUTPUT_PAIRS};
+
+pub mod latency;
+pub mod master_fx;
+pub mod metronome;
+pub mod performance;
+pub mod preview;
+pub mod setlist;
+pub mod slice;
+pub mod tempo;
+pub mod wav;
+
+use latency::LatencyCompensation;
+use master_fx::MasterEffects;
+use metronome::MetronomeSounds;
+
+pub const DEFAULT_RENDER_BLOCK_FRAMES: u32 = 256;
+pub const MAX_RENDER_THREADS: usize = TRACK_COUNT;
+const PARALLEL_RENDER_MIN_FRAMES: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrackParameters {
+    pub gain: f32,
+    pub pan: f32,
+    pub filter_cutoff: f32,
+    pub envelope_decay: f32,
+    pub pitch_semitones: f32,
+    pub choke_group: Option<u8>,
+    pub duck_source: Option<u8>,
+    pub duck_depth: f32,
+    pub duck_attack_ms: f32,
+    pub duck_release_ms: f32,
+    pub group: Option<u8>,
+    pub sample_start: f32,
+    pub sample_end: f32,
+    pub reverse: bool,
+    pub choke_targets: u8,
+}
+
+impl Default for TrackParameters {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            pan: 0.0,
+            filter_cutoff: 1.0,
+            envelope_decay: 1.0,
+            pitch_semitones: 0.0,
+            choke_group: None,
+            duck_source: None,
+            duck_depth: 0.0,
+            duck_attack_ms: 5.0,
+            duck_release_ms: 200.0,
+            group: None,
+            choke_targets: 0,
+            sample_start: 0.0,
+            sample_end: 1.0,
+            reverse: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GroupParameters {
+    pub gain: f32,
+    pub muted: bool,
+    pub soloed: bool,
+    pub choke: bool,
+}
+
+impl Default for GroupParameters {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            muted: false,
+            soloed: false,
+            choke: false,
+        }
+    }
+}
+
+const GROUP_COUNT: usize = abi_rs::FF_TRACK_GROUP_COUNT as usize;
+
+#[derive(Clone, Copy, Debug, Default)]
+struct ParameterRamp {
+    target: f32,
+    remaining: u32,
+}
+
+const RAMPED_SLOT_COUNT: usize = 5;
+
+#[derive(Clone, Debug, Default)]
+struct TrackVoice {
+    sample: Vec<f32>,
+    playhead: f64,
+    trigger_velocity: f32,
+    envelope_value: f32,
+    filter_state: f32,
+    active: bool,
+    parameters: TrackParameters,
+    ramps: [ParameterRamp; RAMPED_SLOT_COUNT],
+    fade_remaining: u32,
+    fade_total: u32,
+    duck_level: f32,
+    duck_attacking: bool,
+    frozen: bool,
+}
+
+fn ramped_parameter(parameters: &mut TrackParameters, slot: u32) -> Option<&mut f32> {
+    match slot {
+        abi_rs::FF_PARAM_SLOT_GAIN => Some(&mut parameters.gain),
+        abi_rs::FF_PARAM_SLOT_PAN => Some(&mut parameters.pan),
+        abi_rs::FF_PARAM_SLOT_FILTER_CUTOFF => Some(&mut parameters.filter_cutoff),
+        abi_rs::FF_PARAM_SLOT_ENVELOPE_DECAY => Some(&mut parameters.envelope_decay),
+        abi_rs::FF_PARAM_SLOT_PITCH => Some(&mut parameters.pitch_semitones),
+        _ => None,
+    }
+}
+
+fn advance_duck(voice: &mut TrackVoice, sample_rate: f32) -> f32 {
+    let samples_per_ms = sample_rate / 1_000.0;
+    if voice.duck_attacking {
+        let attack_samples = (voice.parameters.duck_attack_ms * samples_per_ms).max(1.0);
+        voice.duck_level = (voice.duck_level + 1.0 / attack_samples).min(1.0);
+        voice.duck_attacking = voice.duck_level < 1.0;
+    } else if voice.duck_level > 0.0 {
+        let release_samples = (voice.parameters.duck_release_ms * samples_per_ms).max(1.0);
+        voice.duck_level = (voice.duck_level - 1.0 / release_samples).max(0.0);
+    }
+    1.0 - voice.parameters.duck_depth * voice.duck_level
+}
+
+fn advance_ramps(voice: &mut TrackVoice) {
+    for (index, ramp) in voice.ramps.iter_mut().enumerate() {
+        if ramp.remaining == 0 {
+            continue;
+        }
+        if let Some(value) = ramped_parameter(&mut voice.parameters, index as u32 + 1) {
+            *value += (ramp.target - *value) / ramp.remaining as f32;
+        }
+        ramp.remaining -= 1;
+    }
+}
+
+#[derive(Debug)]
+pub struct OfflineEngine {
+    sample_rate_hz: u32,
+    master_gain: f32,
+    tracks: [TrackVoice; TRACK_COUNT],
+    tails: [TrackVoice; TRACK_COUNT],
+    audition: TrackVoice,
+    audition_gain: f32,
+    metronome: TrackVoice,
+    metronome_sounds: Option<MetronomeSounds>,
+    metronome_gain: f32,
+    metronome_output: usize,
+    groups: [GroupParameters; GROUP_COUNT],
+    render_threads: usize,
+    voice_buffers: Vec<Vec<f32>>,
+    track_outputs: [usize; TRACK_COUNT],
+    master_effects: MasterEffects,
+    master_bus: presets_rs::MasterBus,
+    latency: LatencyCompensation,
+    meter_peaks: [f32; TRACK_COUNT],
+    meter_energy: [f64; TRACK_COUNT],
+    meter_frames: usize,
+}
+
+impl OfflineEngine {
+    pub fn new(sample_rate_hz: u32) -> Self {
+        Self {
+            sample_rate_hz: sample_rate_hz.max(1),
+            master_gain: 1.0,
+            tracks: std::array::from_fn(|_| TrackVoice::default()),
+            tails: std::array::from_fn(|_| TrackVoice::default()),
+            audition: TrackVoice::default(),
+            audition_gain: 1.0,
+            metronome: TrackVoice::default(),
+            metronome_sounds: None,
+            metronome_gain: 1.0,
+            metronome_output: 0,
+            groups: [GroupParameters::default(); GROUP_COUNT],
+            render_threads: 1,
+            voice_buffers: vec![Vec::new(); TRACK_COUNT * 2],
+            track_outputs: [0; TRACK_COUNT],
+            master_effects: MasterEffects::new(sample_rate_hz, control_rs::DEFAULT_BPM),
+            master_bus: presets_rs::MasterBus::default(),
+            latency: LatencyCompensation::new(),
+            meter_peaks: [0.0; TRACK_COUNT],
+            meter_energy: [0.0; TRACK_COUNT],
+            meter_frames: 0,
+        }
+    }
+
+    pub fn sample_rate_hz(&self) -> u32 {
+        self.sample_rate_hz
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate_hz: u32) -> bool {
+        if sample_rate_hz == 0 {
+            return false;
+        }
+        let ratio = f64::from(sample_rate_hz) / f64::from(self.sample_rate_hz);
+        let rescale = |frames: u32| (f64::from(frames) * ratio).round() as u32;
+        for voice in self
+            .tracks
+            .iter_mut()
+            .chain(self.tails.iter_mut())
+            .chain([&mut self.audition, &mut self.metronome])
+        {
+            for ramp in &mut voice.ramps {
+                ramp.remaining = rescale(ramp.remaining);
+            }
+            if voice.fade_remaining > 0 {
+                voice.fade_remaining = rescale(voice.fade_remaining).max(1);
+                voice.fade_total = rescale(voice.fade_total).max(voice.fade_remaining);
+            }
+        }
+        self.sample_rate_hz = sample_rate_hz;
+        self.master_effects.set_sample_rate(sample_rate_hz);
+        true
+    }
+
+    pub fn render_threads(&self) -> usize {
+        self.render_threads
+    }
+
+    pub fn set_render_threads(&mut self, threads: usize) -> bool {
+        if !(1..=MAX_RENDER_THREADS).contains(&threads) {
+            return false;
+        }
+        self.render_threads = threads;
+        true
+    }
+
+    pub fn track_output(&self, track_index: usize) -> Option<usize> {
+        self.track_outputs.get(track_index).copied()
+    }
+
+    pub fn set_track_output(&mut self, track_index: usize, output_pair: usize) -> bool {
+        if track_index >= TRACK_COUNT || output_pair >= MAX_OUTPUT_PAIRS {
+            return false;
+        }
+        self.track_outputs[track_index] = output_pair;
+        true
+    }
+
+    pub fn set_output_routing(&mut self, routing: &OutputRouting) {
+        for (track_index, output) in self.track_outputs.iter_mut().enumerate() {
+            *output = routing.track_output(track_index).unwrap_or(0);
+        }
+    }
+
+    pub fn master_effects(&self) -> &MasterEffects {
+        &self.master_effects
+    }
+
+    pub fn master_effects_mut(&mut self) -> &mut MasterEffects {
+        &mut self.master_effects
+    }
+
+    pub fn master_bus(&self) -> presets_rs::MasterBus {
+        self.master_bus
+    }
+
+    pub fn latency(&self) -> &LatencyCompensation {
+        &self.latency
+    }
+
+    pub fn latency_mut(&mut self) -> &mut LatencyCompensation {
+        &mut self.latency
+    }
+
+    pub fn latency_report(&self) -> abi_rs::FfLatencyReport {
+        self.latency.report()
+    }
+
+    pub fn set_master_gain(&mut self, gain: f32) {
+        self.master_gain = gain.max(0.0);
+    }
+
+    pub fn set_track_sample(&mut self, track_index: usize, sample: Vec<f32>) -> bool {
+        if track_index >= TRACK_COUNT || sample.is_empty() {
+            return false;
+        }
+
+        let track = &mut self.tracks[track_index];
+        track.sample = sample;
+        track.playhead = 0.0;
+        track.active = false;
+        track.trigger_velocity = 0.0;
+        track.envelope_value = 0.0;
+        track.filter_state = 0.0;
+        true
+    }
+
+    pub fn release_track_sample(&mut self, track_index: usize, fade_frames: u32) -> bool {
+        if track_index >= TRACK_COUNT {
+            return false;
+        }
+
+        let track = &mut self.tracks[track_index];
+        let mut previous = std::mem::take(track);
+        track.parameters = previous.parameters;
+        track.ramps = previous.ramps;
+        if fade_frames > 0 && previous.active {
+            previous.fade_remaining = fade_frames;
+            previous.fade_total = fade_frames;
+            self.tails[track_index] = previous;
+        }
+        true
+    }
+
+    pub fn set_track_sample_crossfade(
+        &mut self,
+        track_index: usize,
+        sample: Vec<f32>,
+        fade_frames: u32,
+    ) -> bool {
+        if sample.is_empty() || !self.release_track_sample(track_index, fade_frames) {
+            return false;
+        }
+        self.set_track_sample(track_index, sample)
+    }
+
+    pub fn set_track_frozen(&mut self, track_index: usize, frozen: bool) -> bool {
+        if track_index >= TRACK_COUNT {
+            return false;
+        }
+
+        self.tracks[track_index].frozen = frozen;
+        true
+    }
+
+    pub fn track_frozen(&self, track_index: usize) -> bool {
+        self.tracks
+            .get(track_index)
+            .is_some_and(|track| track.frozen)
+    }
+
+    pub fn active_voice_count(&self) -> usize {
+        self.tracks
+            .iter()
+            .chain(&self.tails)
+            .filter(|track| track.active)
+            .count()
+    }
+
+    pub fn track_parameters(&self, track_index: usize) -> Option<TrackParameters> {
+        self.tracks.get(track_index).map(|track| track.parameters)
+    }
+
+    pub fn group_parameters(&self, group_index: usize) -> Option<GroupParameters> {
+        self.groups.get(group_index).copied()
+    }
+
+    pub fn trigger_track(&mut self, track_index: usize, velocity: f32) -> bool {
+        if track_index >= TRACK_COUNT || self.tracks[track_index].sample.is_empty() {
+            return false;
+        }
+
+        let choke_group = self.tracks[track_index].parameters.choke_group;
+        let choke_targets = self.tracks[track_index].parameters.choke_targets;
+        let choking_group = self.tracks[track_index]
+            .parameters
+            .group
+            .filter(|group| self.groups[usize::from(*group)].choke);
+        if choke_group.is_some() || choking_group.is_some() || choke_targets != 0 {
+            for (other_index, other) in self.tracks.iter_mut().enumerate() {
+                let same_choke =
+                    choke_group.is_some() && other.parameters.choke_group == choke_group;
+                let same_group = choking_group.is_some() && other.parameters.group == choking_group;
+                let targeted = choke_targets & (1 << other_index) != 0;
+                if other_index != track_index
+                    && other.active
+                    && (same_choke || same_group || targeted)
+                {
+                    other.active = false;
+                }
+            }
+        }
+
+        for (other_index, other) in self.tracks.iter_mut().enumerate() {
+            if other_index != track_index && other.parameters.duck_source == Some(track_index as u8)
+            {
+                other.duck_attacking = true;
+            }
+        }
+
+        let track = &mut self.tracks[track_index];
+        track.playhead = 0.0;
+        track.trigger_velocity = velocity.clamp(0.0, 1.0);
+        track.envelope_value = 1.0;
+        track.filter_state = 0.0;
+        track.active = track.trigger_velocity > 0.0;
+        track.active
+    }
+
+    pub fn apply_parameter_update(&mut self, update: &FfParameterUpdate) -> bool {
+        let value = update.normalized_value.clamp(0.0, 1.0);
+        match update.parameter_id {
+            abi_rs::FF_PARAM_GLOBAL_MASTER_COMPRESSOR => {
+                self.master_bus.compressor_amount = value;
+                return true;
+            }
+            abi_rs::FF_PARAM_GLOBAL_MASTER_FILTER => {
+                self.master_bus.filter_cutoff = value;
+                return true;
+            }
+            abi_rs::FF_PARAM_GLOBAL_LIMITER_CEILING => {
+                self.master_bus.limiter_ceiling_db =
+                    abi_rs::ff_limiter_ceiling_db_from_normalized(value);
+                return true;
+            }
+            _ => {}
+        }
+        if let Some(descriptor) = abi_rs::ff_group_parameter_descriptor(update.parameter_id) {
+            let value = update.normalized_value.clamp(0.0, 1.0);
+            let group = &mut self.groups[usize::from(descriptor.group_index)];
+            match descriptor.parameter_slot {
+                abi_rs::FF_PARAM_GROUP_SLOT_GAIN => {
+                    group.gain =
+                        abi_rs::ff_gain_db_to_linear(abi_rs::ff_gain_db_from_normalized(value));
+                }
+                abi_rs::FF_PARAM_GROUP_SLOT_MUTE => group.muted = value >= 0.5,
+                abi_rs::FF_PARAM_GROUP_SLOT_SOLO => group.soloed = value >= 0.5,
+                abi_rs::FF_PARAM_GROUP_SLOT_CHOKE => group.choke = value >= 0.5,
+                _ => return false,
+            }
+            return true;
+        }
+        let Some(descriptor) = abi_rs::ff_parameter_descriptor(update.parameter_id) else {
+            return false;
+        };
+
+        let value = update.normalized_value.clamp(0.0, 1.0);
+        let slot = descriptor.parameter_slot;
+        let track = &mut self.tracks[usize::from(descriptor.track_index)];
+        let target = match slot {
+            abi_rs::FF_PARAM_SLOT_GAIN => {
+                abi_rs::ff_gain_db_to_linear(abi_rs::ff_gain_db_from_normalized(value))
+            }
+            abi_rs::FF_PARAM_SLOT_PAN => value * 2.0 - 1.0,
+            abi_rs::FF_PARAM_SLOT_FILTER_CUTOFF => value,
+            abi_rs::FF_PARAM_SLOT_ENVELOPE_DECAY => value,
+            abi_rs::FF_PARAM_SLOT_PITCH => value * 48.0 - 24.0,
+            abi_rs::FF_PARAM_SLOT_CHOKE_GROUP => {
+                track.parameters.choke_group = if value <= 0.0001 {
+                    None
+                } else {
+                    Some(((value * 16.0).round() as u8).clamp(1, 16) - 1)
+                };
+                return true;
+            }
+            abi_rs::FF_PARAM_SLOT_DUCK_SOURCE => {
+                track.parameters.duck_source = if value <= 0.0001 {
+                    None
+                } else {
+                    Some(((value * 8.0).round() as u8).clamp(1, 8) - 1)
+                };
+                return true;
+            }
+            abi_rs::FF_PARAM_SLOT_DUCK_DEPTH => {
+                track.parameters.duck_depth = value;
+                return true;
+            }
+            abi_rs::FF_PARAM_SLOT_DUCK_ATTACK => {
+                track.parameters.duck_attack_ms = value * 100.0;
+                return true;
+            }
+            abi_rs::FF_PARAM_SLOT_DUCK_RELEASE => {
+                track.parameters.duck_release_ms = value * 1_000.0;
+                return true;
+            }
+            abi_rs::FF_PARAM_SLOT_TRACK_GROUP => {
+                track.parameters.group = if value <= 0.0001 {
+                    None
+                } else {
+                    Some(
+                        ((value * GROUP_COUNT as f32).round() as u8).clamp(1, GROUP_COUNT as u8)
+                            - 1,
+                    )
+                };
+                return true;
+            }
+            abi_rs::FF_PARAM_SLOT_SAMPLE_START => {
+                track.parameters.sample_start = value;
+                return true;
+            }
+            abi_rs::FF_PARAM_SLOT_SAMPLE_END => {
+                track.parameters.sample_end = value;
+                return true;
+            }
+            abi_rs::FF_PARAM_SLOT_REVERSE => {
+                track.parameters.reverse = value >= 0.5;
+                return true;
+            }
+            abi_rs::FF_PARAM_SLOT_CHOKE_TARGETS => {
+                track.parameters.choke_targets = (value * 255.0).round() as u8;
+                return true;
+            }
+            _ => return false,
+        };
+
+        let Some(current) = ramped_parameter(&mut track.parameters, slot) else {
+            return false;
+        };
+        let ramp = &mut track.ramps[slot as usize - 1];
+        if update.ramp_samples == 0 {
+            *current = target;
+            ramp.remaining = 0;
+        } else {
+            ramp.target = target;
+            ramp.remaining = update.ramp_samples;
+        }
+        true
+    }
+
+    pub fn apply_parameter_updates(&mut self, updates: &[FfParameterUpdate]) -> bool {
+        let mut all_applied = true;
+        for update in updates {
+            all_applied &= self.apply_parameter_update(update);
+        }
+        all_applied
+    }
+
+    pub fn audition_sample(&mut self, sample: Vec<f32>, velocity: f32) -> bool {
+        if sample.is_empty() {
+            return false;
+        }
+
+        let voice = &mut self.audition;
+        voice.sample = sample;
+        voice.playhead = 0.0;
+        voice.trigger_velocity = velocity.clamp(0.0, 1.0);
+        voice.envelope_value = 1.0;
+        voice.filter_state = 0.0;
+        voice.active = voice.trigger_velocity > 0.0;
+        voice.active
+    }
+
+    pub fn stop_audition(&mut self) {
+        self.audition.active = false;
+    }
+
+    pub fn is_auditioning(&self) -> bool {
+        self.audition.active
+    }
+
+    pub fn set_audition_gain(&mut self, gain: f32) {
+        self.audition_gain = gain.clamp(0.0, 2.0);
+    }
+
+    pub fn metronome_enabled(&self) -> bool {
+        self.metronome_sounds.is_some()
+    }
+
+    pub fn set_metronome_sounds(&mut self, sounds: Option<MetronomeSounds>) {
+        self.metronome_sounds = sounds;
+        self.metronome.active = false;
+    }
+
+    pub fn set_metronome_routing(&mut self, output_pair: usize, gain_db: f32) -> bool {
+        if output_pair >= MAX_OUTPUT_PAIRS || gain_db.is_nan() {
+            return false;
+        }
+        self.metronome_output = output_pair;
+        self.metronome_gain = abi_rs::ff_gain_db_to_linear(gain_db);
+        true
+    }
+
+    pub fn trigger_metronome(&mut self, accent: bool) -> bool {
+        let Some(sounds) = &self.metronome_sounds else {
+            return false;
+        };
+        let voice = &mut self.metronome;
+        voice.sample = sounds.sample(accent).to_vec();
+        voice.playhead = 0.0;
+        voice.trigger_velocity = 1.0;
+        voice.envelope_value = 1.0;
+        voice.filter_state = 0.0;
+        voice.active = !voice.sample.is_empty();
+        voice.active
+    }
+
+    pub fn take_track_meters(&mut self) -> [TrackMeterReading; TRACK_COUNT] {
+        let frames = self.meter_frames.max(1) as f64;
+        let meters = std::array::from_fn(|track_index| TrackMeterReading {
+            peak: self.meter_peaks[track_index],
+            rms: (self.meter_energy[track_index] / frames).sqrt() as f32,
+        });
+        self.meter_peaks = [0.0; TRACK_COUNT];
+        self.meter_energy = [0.0; TRACK_COUNT];
+        self.meter_frames = 0;
+        meters
+    }
+
+    pub fn process(&mut self, output: &mut [f32]) {
+        self.process_outputs(&mut [output]);
+    }
+
+    pub fn process_outputs(&mut self, outputs: &mut [&mut [f32]]) {
+        let Some(frames) = outputs.iter().map(|output| output.len()).min() else {
+            return;
+        };
+        self.render_voices(frames);
+        self.compensate_latency(frames);
+        self.accumulate_meters(frames);
+
+        let group_gains = self.track_group_gains();
+        let bus_count = outputs.len();
+        let track_outputs = self.track_outputs;
+        let voice_bus = |voice_index: usize| {
+            let output_pair = track_outputs[voice_index % TRACK_COUNT];
+            if output_pair < bus_count {
+                output_pair
+            } else {
+                0
+            }
+        };
+        let sample_rate = self.sample_rate_hz as f32;
+        for frame_index in 0..frames {
+            for (bus_index, output) in outputs.iter_mut().enumerate() {
+                let mixed: f32 = self
+                    .voice_buffers
+                    .iter()
+                    .enumerate()
+                    .filter(|(voice_index, _)| voice_bus(*voice_index) == bus_index)
+                    .map(|(voice_index, buffer)| {
+                        buffer[frame_index] * group_gains[voice_index % TRACK_COUNT]
+                    })
+                    .sum();
+                output[frame_index] = mixed * self.master_gain;
+            }
+        }
+        self.master_effects.process(&mut outputs[0][..frames]);
+        for sample in &mut outputs[0][..frames] {
+            *sample += render_voice(&mut self.audition, sample_rate) * self.audition_gain;
+        }
+        if self.metronome.active {
+            let bus_index = if self.metronome_output < bus_count {
+                self.metronome_output
+            } else {
+                0
+            };
+            for sample in &mut outputs[bus_index][..frames] {
+                *sample += render_voice(&mut self.metronome, sample_rate) * self.metronome_gain;
+            }
+        }
+    }
+
+    fn track_group_gains(&self) -> [f32; TRACK_COUNT] {
+        let any_soloed = self.groups.iter().any(|group| group.soloed);
+        std::array::from_fn(
+            |track_index| match self.tracks[track_index].parameters.group {
+                Some(group) => {
+                    let group = self.groups[usize::from(group)];
+                    if group.muted || (any_soloed && !group.soloed) {
+                        0.0
+                    } else {
+                        group.gain
+                    }
+                }
+                None if any_soloed => 0.0,
+                None => 1.0,
+            },
+        )
+    }
+
+    fn compensate_latency(&mut self, frames: usize) {
+        if self.latency.report().total_samples == 0 {
+            return;
+        }
+        let (tracks, tails) = self.voice_buffers.split_at_mut(TRACK_COUNT);
+        for (track_index, (track, tail)) in tracks.iter_mut().zip(tails).enumerate() {
+            for (voice, tail) in track[..frames].iter_mut().zip(&mut tail[..frames]) {
+                *voice += std::mem::take(tail);
+            }
+            self.latency
+                .process_track(track_index, &mut track[..frames]);
+        }
+    }
+
+    fn accumulate_meters(&mut self, frames: usize) {
+        let (tracks, tails) = self.voice_buffers.split_at(TRACK_COUNT);
+        for (track_index, (track, tail)) in tracks.iter().zip(tails).enumerate() {
+            for (voice, tail) in track[..frames].iter().zip(&tail[..frames]) {
+                let level = voice + tail;
+                self.meter_peaks[track_index] = self.meter_peaks[track_index].max(level.abs());
+                self.meter_energy[track_index] += f64::from(level * level);
+            }
+        }
+        self.meter_frames += frames;
+    }
+
+    fn render_voices(&mut self, frames: usize) {
+        let sample_rate = self.sample_rate_hz as f32;
+        let mut jobs: Vec<(&mut TrackVoice, &mut [f32])> = self
+            .tracks
+            .iter_mut()
+            .chain(&mut self.tails)
+            .zip(&mut self.voice_buffers)
+            .map(|(voice, buffer)| {
+                buffer.resize(frames, 0.0);
+                (voice, &mut buffer[..frames])
+            })
+            .collect();
+
+        if self.render_threads > 1 && frames >= PARALLEL_RENDER_MIN_FRAMES {
+            let chunk_len = jobs.len().div_ceil(self.render_threads);
+            std::thread::scope(|scope| {
+                for chunk in jobs.chunks_mut(chunk_len) {
+                    scope.spawn(move || {
+                        for (voice, buffer) in chunk {
+                            render_voice_block(voice, buffer, sample_rate);
+                        }
+                    });
+                }
+            });
+        } else {
+            for (voice, buffer) in &mut jobs {
+                render_voice_block(voice, buffer, sample_rate);
+            }
+        }
+    }
+}
+
+fn render_voice_block(voice: &mut TrackVoice, buffer: &mut [f32], sample_rate: f32) {
+    if !voice.active
+        && !voice.duck_attacking
+        && voice.duck_level == 0.0
+        && voice.ramps.iter().all(|ramp| ramp.remaining == 0)
+    {
+        buffer.fill(0.0);
+        return;
+    }
+    for sample in buffer {
+        *sample = render_voice(voice, sample_rate);
+    }
+}
+
+fn render_voice(voice: &mut TrackVoice, sample_rate: f32) -> f32 {
+    advance_ramps(voice);
+    let duck_gain = advance_duck(voice, sample_rate);
+    if !voice.active {
+        return 0.0;
+    }
+    if voice.sample.is_empty() {
+        voice.active = false;
+        return 0.0;
+    }
+
+    let (start_frame, end_frame) = trimmed_bounds(&voice.parameters, voice.sample.len());
+    let position = if voice.parameters.reverse {
+        end_frame - 1.0 - voice.playhead
+    } else {
+        start_frame + voice.playhead
+    };
+    let input = sample_at(&voice.sample, position);
+    voice.playhead += if voice.frozen {
+        1.0
+    } else {
+        f64::from(2.0f32.powf(voice.parameters.pitch_semitones / 12.0))
+    };
+    if voice.playhead >= end_frame - start_frame {
+        voice.active = false;
+    }
+
+    if voice.frozen {
+        voice.filter_state = input;
+    } else {
+        let alpha = 0.01 + voice.parameters.filter_cutoff * 0.99;
+        voice.filter_state += alpha * (input - voice.filter_state);
+    }
+    let pan_gain = 1.0 - voice.parameters.pan.abs() * 0.5;
+    let mix_gain = if voice.frozen {
+        1.0
+    } else {
+        voice.parameters.gain * voice.envelope_value * pan_gain * duck_gain
+    };
+    let mut output = voice.filter_state * voice.trigger_velocity * mix_gain;
+    if voice.fade_total > 0 {
+        output *= voice.fade_remaining as f32 / voice.fade_total as f32;
+        voice.fade_remaining = voice.fade_remaining.saturating_sub(1);
+        if voice.fade_remaining == 0 {
+            voice.active = false;
+        }
+    }
+
+    if voice.frozen {
+        return output;
+    }
+    let decay_seconds = 0.02 + voice.parameters.envelope_decay * 3.0;
+    voice.envelope_value *= (-1.0 / (decay_seconds * sample_rate)).exp();
+    if voice.envelope_value < 0.0001 {
+        voice.active = false;
+    }
+    output
+}
+
+fn trimmed_bounds(parameters: &TrackParameters, length: usize) -> (f64, f64) {
+    let length = length as f64;
+    let start = (f64::from(parameters.sample_start.clamp(0.0, 1.0)) * length).floor();
+    let end = (f64::from(parameters.sample_end.clamp(0.0, 1.0)) * length).ceil();
+    let start = start.min(length - 1.0);
+    (start, end.max(start + 1.0))
+}
+
+fn sample_at(sample: &[f32], playhead: f64) -> f32 {
+    let clamped = playhead.clamp(0.0, (sample.len() - 1) as f64);
+    let lower = clamped as usize;
+    let upper = (lower + 1).min(sample.len() - 1);
+    let fraction = (clamped - lower as f64) as f32;
+    sample[lower] + (sample[upper] - sample[lower]) * fraction
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderOutput {
+    pub audio: Vec<f32>,
+    pub events: Vec<StepTriggerEvent>,
+    pub missing_samples: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RoutedRenderOutput {
+    pub outputs: Vec<Vec<f32>>,
+    pub events: Vec<StepTriggerEvent>,
+    pub missing_samples: Vec<String>,
+}
+
+pub fn render_project<F>(
+    project: &Project,
+    sample_rate_hz: u32,
+    total_frames: u32,
+    block_frames: u32,
+    load_sample: F,
+) -> Result<RenderOutput, String>
+where
+    F: FnMut(&str) -> Option<Vec<f32>>,
+{
+    render_project_metered(
+        project,
+        sample_rate_hz,
+        total_frames,
+        block_frames,
+        &EngineMetrics::new(),
+        load_sample,
+    )
+}
+
+pub fn render_project_metered<F>(
+    project: &Project,
+    sample_rate_hz: u32,
+    total_frames: u32,
+    block_frames: u32,
+    metrics: &EngineMetrics,
+    load_sample: F,
+) -> Result<RenderOutput, String>
+where
+    F: FnMut(&str) -> Option<Vec<f32>>,
+{
+    render_project_threaded(
+        project,
+        sample_rate_hz,
+        total_frames,
+        block_frames,
+        1,
+        metrics,
+        load_sample,
+    )
+}
+
+pub fn render_project_threaded<F>(
+    project: &Project,
+    sample_rate_hz: u32,
+    total_frames: u32,
+    block_frames: u32,
+    render_threads: usize,
+    metrics: &EngineMetrics,
+    load_sample: F,
+) -> Result<RenderOutput, String>
+where
+    F: FnMut(&str) -> Option<Vec<f32>>,
+{
+    let mut routed = render_project_buses(
+        project,
+        sample_rate_hz,
+        total_frames,
+        block_frames,
+        render_threads,
+        1,
+        metrics,
+        load_sample,
+    )?;
+    Ok(RenderOutput {
+        audio: routed.outputs.remove(0),
+        events: routed.events,
+        missing_samples: routed.missing_samples,
+    })
+}
+
+pub fn render_project_outputs<F>(
+    project: &Project,
+    sample_rate_hz: u32,
+    total_frames: u32,
+    block_frames: u32,
+    load_sample: F,
+) -> Result<RoutedRenderOutput, String>
+where
+    F: FnMut(&str) -> Option<Vec<f32>>,
+{
+    render_project_buses(
+        project,
+        sample_rate_hz,
+        total_frames,
+        block_frames,
+        1,
+        project_output_count(project),
+        &EngineMetrics::new(),
+        load_sample,
+    )
+}
+
+fn project_output_count(project: &Project) -> usize {
+    let metronome_outputs = if project.metronome.renders() {
+        usize::from(project.metronome.output_pair) + 1
+    } else {
+        0
+    };
+    project
+        .output_routing
+        .output_pair_count()
+        .max(metronome_outputs)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_project_buses<F>(
+    project: &Project,
+    sample_rate_hz: u32,
+    total_frames: u32,
+    block_frames: u32,
+    render_threads: usize,
+    output_count: usize,
+    metrics: &EngineMetrics,
+    mut load_sample: F,
+) -> Result<RoutedRenderOutput, String>
+where
+    F: FnMut(&str) -> Option<Vec<f32>>,
+{
+    let mut recall = control_rs::recall_state_from_project(project, sample_rate_hz)?;
+    let (mut engine, missing_samples) =
+        prepare_engine(project, &recall, sample_rate_hz, &mut load_sample);
+    if !engine.set_render_threads(render_threads) {
+        return Err(format!("render threads out of range: {render_threads}"));
+    }
+
+    let block_frames = block_frames.max(1);
+    let mut outputs = vec![vec![0.0; total_frames as usize]; output_count.max(1)];
+    let mut events = Vec::new();
+    recall.sequencer_mut().start();
+
+    let mut block_start = 0u32;
+    while block_start < total_frames {
+        let frames = block_frames.min(total_frames - block_start);
+        let range = block_start as usize..(block_start + frames) as usize;
+        let mut blocks: Vec<&mut [f32]> = outputs
+            .iter_mut()
+            .map(|output| &mut output[range.clone()])
+            .collect();
+        events.extend(render_block_outputs(
+            recall.sequencer_mut(),
+            &mut engine,
+            &mut blocks,
+            metrics,
+        ));
+        block_start += frames;
+    }
+
+    Ok(RoutedRenderOutput {
+        outputs,
+        events,
+        missing_samples,
+    })
+}
+
+fn prepare_engine<F>(
+    project: &Project,
+    recall: &RecallState,
+    sample_rate_hz: u32,
+    load_sample: &mut F,
+) -> (OfflineEngine, Vec<String>)
+where
+    F: FnMut(&str) -> Option<Vec<f32>>,
+{
+    let mut engine = OfflineEngine::new(sample_rate_hz);
+    let kit_index = project.active_kit.unwrap_or(0);
+    let kit = project.kits.get(kit_index);
+    let missing_samples = load_recall(&mut engine, kit, &recall.to_engine_recall(), 0, load_sample);
+    apply_frozen_tracks(&mut engine, project, kit_index);
+    engine.set_output_routing(&project.output_routing);
+    let mut missing_samples = missing_samples;
+    if project.metronome.renders() {
+        let (sounds, missing_clicks) =
+            MetronomeSounds::load(&project.metronome, sample_rate_hz, load_sample);
+        missing_samples.extend(missing_clicks);
+        engine.set_metronome_sounds(Some(sounds));
+        engine.set_metronome_routing(
+            usize::from(project.metronome.output_pair),
+            project.metronome.gain_db,
+        );
+    }
+    (engine, missing_samples)
+}
+
+fn apply_frozen_tracks(engine: &mut OfflineEngine, project: &Project, kit_index: usize) {
+    for track_index in 0..TRACK_COUNT {
+        let frozen = project.frozen_tracks.iter().any(|freeze| {
+            freeze.kit_index == kit_index && usize::from(freeze.track_index) == track_index
+        });
+        engine.set_track_frozen(track_index, frozen);
+    }
+}
+
+pub fn render_track_bounce<F>(
+    project: &Project,
+    track_index: usize,
+    sample_rate_hz: u32,
+    mut load_sample: F,
+) -> Result<RenderOutput, String>
+where
+    F: FnMut(&str) -> Option<Vec<f32>>,
+{
+    if track_index >= TRACK_COUNT {
+        return Err(format!("track out of range: {track_index}"));
+    }
+
+    let mut recall = control_rs::recall_state_from_project(project, sample_rate_hz)?;
+    let (mut engine, missing_samples) =
+        prepare_engine(project, &recall, sample_rate_hz, &mut load_sample);
+    let mute_others: Vec<FfParameterUpdate> = (0..TRACK_COUNT)
+        .filter(|other_index| *other_index != track_index)
+        .filter_map(|other_index| {
+            abi_rs::ff_track_parameter_id(other_index as u8, abi_rs::FF_PARAM_SLOT_GAIN)
+        })
+        .map(|parameter_id| FfParameterUpdate {
+            parameter_id,
+            normalized_value: 0.0,
+            ramp_samples: 0,
+            reserved: 0,
+        })
+        .collect();
+    engine.apply_parameter_updates(&mute_others);
+
+    let sequencer = recall.sequencer_mut();
+    let loop_frames = (f64::from(sample_rate_hz) * 60.0
+        / f64::from(sequencer.transport().bpm())
+        / control_rs::STEPS_PER_BEAT as f64
+        / sequencer.pattern().scale().speed()
+        * sequencer.pattern().step_count() as f64)
+        .round() as usize;
+    sequencer.start();
+
+    let metrics = EngineMetrics::new();
+    let mut audio = vec![0.0; loop_frames * 2];
+    let mut events = Vec::new();
+    for (block_index, block) in audio
+        .chunks_mut(DEFAULT_RENDER_BLOCK_FRAMES as usize)
+        .enumerate()
+    {
+        let block_start = block_index * DEFAULT_RENDER_BLOCK_FRAMES as usize;
+        let block_events = render_block(sequencer, &mut engine, block, &metrics);
+        events.extend(block_events.into_iter().filter(|event| {
+            usize::from(event.track_index) == track_index
+                && block_start + event.block_offset as usize >= loop_frames
+        }));
+    }
+
+    Ok(RenderOutput {
+        audio: audio.split_off(loop_frames),
+        events,
+        missing_samples,
+    })
+}
+
+pub fn freeze_track<F>(
+    project: &mut Project,
+    track_index: usize,
+    bounce_sample_id: &str,
+    sample_rate_hz: u32,
+    load_sample: F,
+) -> Result<RenderOutput, String>
+where
+    F: FnMut(&str) -> Option<Vec<f32>>,
+{
+    let bounce = render_track_bounce(project, track_index, sample_rate_hz, load_sample)?;
+    if !project.freeze_track(track_index as u8, bounce_sample_id) {
+        return Err(format!("track {track_index} cannot be frozen"));
+    }
+    Ok(bounce)
+}
+
+fn load_recall<F>(
+    engine: &mut OfflineEngine,
+    kit: Option<&presets_rs::Kit>,
+    engine_recall: &control_rs::EngineRecall,
+    fade_frames: u32,
+    load_sample: &mut F,
+) -> Vec<String>
+where
+    F: FnMut(&str) -> Option<Vec<f32>>,
+{
+    let mut missing_samples = Vec::new();
+    for track_index in 0..TRACK_COUNT {
+        let assignment = engine_recall
+            .sample_assignments
+            .iter()
+            .find(|assignment| usize::from(assignment.track_index) == track_index);
+        let Some(assignment) = assignment else {
+            engine.release_track_sample(track_index, fade_frames);
+            continue;
+        };
+
+        let region = kit.and_then(|kit| kit.track_region(assignment.track_index));
+        let loaded = load_sample(&assignment.sample_id).is_some_and(|sample| {
+            let sample = match region {
+                Some(region) => slice::apply_region(sample, region.start_frame, region.end_frame),
+                None => sample,
+            };
+            engine.set_track_sample_crossfade(track_index, sample, fade_frames)
+        });
+        if !loaded {
+            engine.release_track_sample(track_index, fade_frames);
+            missing_samples.push(assignment.sample_id.clone());
+        }
+    }
+    engine.apply_parameter_updates(&engine_recall.parameter_updates);
+    missing_samples
+}
+
+pub fn switch_kit<F>(
+    engine: &mut OfflineEngine,
+    project: &Project,
+    kit_index: usize,
+    crossfade_frames: u32,
+    mut load_sample: F,
+) -> Result<Vec<String>, String>
+where
+    F: FnMut(&str) -> Option<Vec<f32>>,
+{
+    let kit = project
+        .kits
+        .get(kit_index)
+        .ok_or_else(|| format!("kit out of range: {kit_index}"))?;
+    let target = Project {
+        active_kit: Some(kit_index),
+        ..project.clone()
+    };
+    let recall = control_rs::recall_state_from_project(&target, engine.sample_rate_hz())?;
+    let missing_samples = load_recall(
+        engine,
+        Some(kit),
+        &recall.to_engine_recall_ramped(crossfade_frames),
+        crossfade_frames,
+        &mut load_sample,
+    );
+    apply_frozen_tracks(engine, project, kit_index);
+    Ok(missing_samples)
+}
+
+fn render_block(
+    sequencer: &mut Sequencer,
+    engine: &mut OfflineEngine,
+    block: &mut [f32],
+    metrics: &EngineMetrics,
+) -> Vec<StepTriggerEvent> {
+    render_block_outputs(sequencer, engine, &mut [block], metrics)
+}
+
+fn render_block_outputs(
+    sequencer: &mut Sequencer,
+    engine: &mut OfflineEngine,
+    outputs: &mut [&mut [f32]],
+    metrics: &EngineMetrics,
+) -> Vec<StepTriggerEvent> {
+    let frames = outputs.first().map_or(0, |output| output.len()) as u32;
+    let started = Instant::now();
+    engine
+        .master_effects_mut()
+        .set_tempo_bpm(sequencer.transport().bpm());
+    metrics.record_queue_depth(sequencer.scheduled_trigger_count());
+    let block_events = sequencer.process_block(frames);
+    metrics.record_queue_depth(sequencer.scheduled_trigger_count());
+    metrics.record_dropped_events(sequencer.take_dropped_triggers() as usize);
+    let clicks = if engine.metronome_enabled() {
+        metronome::metronome_clicks(sequencer.step_changes())
+    } else {
+        Vec::new()
+    };
+
+    let mut process_range = |engine: &mut OfflineEngine, start: u32, end: u32| {
+        let mut segments: Vec<&mut [f32]> = outputs
+            .iter_mut()
+            .map(|output| &mut output[start as usize..end as usize])
+            .collect();
+        engine.process_outputs(&mut segments);
+    };
+    let mut rendered = 0u32;
+    let mut clicks = clicks.iter().peekable();
+    for event in &block_events {
+        let offset = event.block_offset.min(frames);
+        while let Some(click) = clicks.next_if(|click| click.block_offset <= offset) {
+            if click.block_offset > rendered {
+                process_range(engine, rendered, click.block_offset);
+                rendered = click.block_offset;
+            }
+            engine.trigger_metronome(click.accent);
+        }
+        if offset > rendered {
+            process_range(engine, rendered, offset);
+            rendered = offset;
+        }
+        engine.trigger_track(
+            usize::from(event.track_index),
+            f32::from(event.velocity) / 127.0,
+        );
+        metrics.record_voice_count(engine.active_voice_count());
+    }
+    for click in clicks {
+        let offset = click.block_offset.min(frames);
+        if offset > rendered {
+            process_range(engine, rendered, offset);
+            rendered = offset;
+        }
+        engine.trigger_metronome(click.accent);
+    }
+    process_range(engine, rendered, frames);
+    metrics.record_track_meters(&engine.take_track_meters());
+    metrics.record_block(started.elapsed(), frames, block_events.len());
+    metrics.record_voice_count(engine.active_voice_count());
+    block_events
+}
+
+#[cfg(test)]
+mod tests {
+    use presets_rs::{Kit, Pattern, PatternStep, Project, TrackAssignment, TrackDucking};
+
+    use super::performance::{
+        PerformanceEvent, PerformanceRecorder, PerformanceTake, RecordQuantize,
+    };
+    use super::slice::{slice_regions, slice_to_kit, SliceMode};
+    use super::tempo::{estimate_tempo, suggest_loop_tempo, LoopTempoAction};
+    use super::wav::{read_wav, write_wav_16};
+    use control_rs::telemetry::{EngineMetrics, TrackMeterReading};
+    use control_rs::Sequencer;
+
+    use super::{
+        freeze_track, render_block, render_project, render_project_metered, render_project_outputs,
+        render_project_threaded, switch_kit, OfflineEngine, MAX_RENDER_THREADS,
+    };
+
+    #[test]
+    fn wav_roundtrip_preserves_pcm_samples() {
+        let samples = [0.0, 0.5, -0.5, 0.25];
+        let decoded = read_wav(&write_wav_16(&samples, 1, 44_100)).expect("wav decode");
+        assert_eq!(decoded.sample_rate_hz, 44_100);
+        assert_eq!(decoded.channels, 1);
+        for (expected, actual) in samples.iter().zip(&decoded.samples) {
+            assert!((expected - actual).abs() < 0.001);
+        }
+    }
+
+    #[test]
+    fn choke_group_silences_other_voices() {
+        let mut engine = OfflineEngine::new(48_000);
+        assert!(engine.set_track_sample(0, vec![1.0; 64]));
+        assert!(engine.set_track_sample(1, vec![1.0; 64]));
+        for track_index in [0u8, 1u8] {
+            engine.apply_parameter_update(&abi_rs::FfParameterUpdate {
+                parameter_id: abi_rs::ff_track_parameter_id(
+                    track_index,
+                    abi_rs::FF_PARAM_SLOT_CHOKE_GROUP,
+                )
+                .expect("id"),
+                normalized_value: 0.125,
+                ramp_samples: 0,
+                reserved: 0,
+            });
+        }
+        assert_eq!(
+            engine.track_parameters(0).expect("track").choke_group,
+            Some(1)
+        );
+
+        assert!(engine.trigger_track(0, 1.0));
+        assert!(engine.trigger_track(1, 1.0));
+        let mut output = [0.0; 4];
+        engine.process(&mut output);
+        let single_voice_peak = output[3];
+
+        let mut solo = OfflineEngine::new(48_000);
+        assert!(solo.set_track_sample(1, vec![1.0; 64]));
+        assert!(solo.trigger_track(1, 1.0));
+        let mut solo_output = [0.0; 4];
+        solo.process(&mut solo_output);
+        assert!((single_voice_peak - solo_output[3]).abs() < 1e-6);
+    }
+
+    #[test]
+    fn sample_rate_changes_stretch_pending_parameter_ramps() {
+        let gain = |ramp_samples: u32| abi_rs::FfParameterUpdate {
+            parameter_id: abi_rs::ff_track_parameter_id(0, abi_rs::FF_PARAM_SLOT_GAIN).expect("id"),
+            normalized_value: 0.25,
+            ramp_samples,
+            reserved: 0,
+        };
+        let mut immediate = OfflineEngine::new(48_000);
+        assert!(immediate.apply_parameter_update(&gain(0)));
+        let target = immediate.track_parameters(0).expect("track").gain;
+
+        let mut engine = OfflineEngine::new(48_000);
+        assert!(engine.set_track_sample(0, vec![0.5; 1_000]));
+        assert!(engine.apply_parameter_update(&gain(100)));
+        assert!(!engine.set_sample_rate(0));
+        assert!(engine.set_sample_rate(96_000));
+        assert_eq!(engine.sample_rate_hz(), 96_000);
+
+        let mut output = [0.0; 100];
+        engine.process(&mut output);
+        assert!((engine.track_parameters(0).expect("track").gain - target).abs() > 1e-3);
+        engine.process(&mut output);
+        assert!((engine.track_parameters(0).expect("track").gain - target).abs() < 1e-6);
+    }
+
+    #[test]
+    fn choke_targets_silence_only_the_targeted_direction() {
+        let mut engine = OfflineEngine::new(48_000);
+        assert!(engine.set_track_sample(0, vec![1.0; 64]));
+        assert!(engine.set_track_sample(1, vec![1.0; 64]));
+        assert!(engine.apply_parameter_update(&abi_rs::FfParameterUpdate {
+            parameter_id: abi_rs::ff_track_parameter_id(0, abi_rs::FF_PARAM_SLOT_CHOKE_TARGETS)
+                .expect("id"),
+            normalized_value: 2.0 / 255.0,
+            ramp_samples: 0,
+            reserved: 0,
+        }));
+        assert_eq!(
+            engine.track_parameters(0).expect("track").choke_targets,
+            0b10
+        );
+
+        assert!(engine.trigger_track(1, 1.0));
+        assert!(engine.trigger_track(0, 1.0));
+        assert_eq!(engine.active_voice_count(), 1);
+
+        assert!(engine.trigger_track(1, 1.0));
+        assert_eq!(engine.active_voice_count(), 2);
+    }
+
+    #[test]
+    fn trim_and_reverse_parameters_bound_voice_playback() {
+        let update = |slot: u32, normalized_value: f32| abi_rs::FfParameterUpdate {
+            parameter_id: abi_rs::ff_track_parameter_id(0, slot).expect("id"),
+            normalized_value,
+            ramp_samples: 0,
+            reserved: 0,
+        };
+        let mut engine = OfflineEngine::new(48_000);
+        let ramp: Vec<f32> = (0..8).map(|frame| frame as f32 / 10.0).collect();
+        assert!(engine.set_track_sample(0, ramp));
+        assert!(engine.apply_parameter_update(&update(abi_rs::FF_PARAM_SLOT_SAMPLE_START, 0.25)));
+        assert!(engine.apply_parameter_update(&update(abi_rs::FF_PARAM_SLOT_SAMPLE_END, 0.5)));
+
+        let render = |engine: &mut OfflineEngine| {
+            assert!(engine.trigger_track(0, 1.0));
+            let mut output = [0.0; 6];
+            engine.process(&mut output);
+            output
+        };
+        let forward = render(&mut engine);
+        for (actual, expected) in forward.iter().zip([0.2, 0.3, 0.0, 0.0, 0.0, 0.0]) {
+            assert!((actual - expected).abs() < 1e-3, "{forward:?}");
+        }
+
+        assert!(engine.apply_parameter_update(&update(abi_rs::FF_PARAM_SLOT_SAMPLE_START, 0.5)));
+        assert!(engine.apply_parameter_update(&update(abi_rs::FF_PARAM_SLOT_SAMPLE_END, 1.0)));
+        assert!(engine.apply_parameter_update(&update(abi_rs::FF_PARAM_SLOT_REVERSE, 1.0)));
+        assert!(engine.track_parameters(0).expect("track").reverse);
+        let reversed = render(&mut engine);
+        for (actual, expected) in reversed.iter().zip([0.7, 0.6, 0.5, 0.4, 0.0, 0.0]) {
+            assert!((actual - expected).abs() < 1e-3, "{reversed:?}");
+        }
+    }
+
+    #[test]
+    fn track_groups_scale_mute_solo_and_choke_their_members() {
+        let update = |parameter_id: Option<u32>, normalized_value: f32| abi_rs::FfParameterUpdate {
+            parameter_id: parameter_id.expect("id"),
+            normalized_value,
+            ramp_samples: 0,
+            reserved: 0,
+        };
+        let mut engine = OfflineEngine::new(48_000);
+        for (track_index, group) in [(0u8, 0.25), (1, 0.25), (2, 0.5)] {
+            assert!(engine.set_track_sample(usize::from(track_index), vec![1.0; 64]));
+            assert!(engine.apply_parameter_update(&update(
+                abi_rs::ff_track_parameter_id(track_index, abi_rs::FF_PARAM_SLOT_TRACK_GROUP),
+                group,
+            )));
+        }
+        assert_eq!(engine.track_parameters(2).expect("track").group, Some(1));
+        let group_id = |group, slot| abi_rs::ff_group_parameter_id(group, slot);
+        assert!(engine.apply_parameter_update(&update(
+            group_id(0, abi_rs::FF_PARAM_GROUP_SLOT_GAIN),
+            103.0 / 127.0,
+        )));
+
+        assert!(engine.trigger_track(0, 1.0));
+        assert!(engine.trigger_track(2, 1.0));
+        let mut output = [0.0; 1];
+        engine.process(&mut output);
+        assert!((output[0] - (10.0f32.powf(-6.0 / 20.0) + 1.0)).abs() < 1e-4);
+
+        engine.apply_parameter_update(&update(group_id(0, abi_rs::FF_PARAM_GROUP_SLOT_CHOKE), 1.0));
+        assert!(engine.trigger_track(1, 1.0));
+        assert_eq!(engine.active_voice_count(), 2);
+
+        engine.apply_parameter_update(&update(group_id(1, abi_rs::FF_PARAM_GROUP_SLOT_SOLO), 1.0));
+        engine.process(&mut output);
+        assert!((output[0] - 1.0).abs() < 1e-3);
+        engine.apply_parameter_update(&update(group_id(1, abi_rs::FF_PARAM_GROUP_SLOT_MUTE), 1.0));
+        engine.process(&mut output);
+        assert_eq!(output[0], 0.0);
+        assert!(engine.group_parameters(1).expect("group").muted);
+    }
+
+    #[test]
+    fn pre_triggered_tracks_lead_the_latency_compensated_mix() {
+        let mut engine = OfflineEngine::new(48_000);
+        assert!(engine.set_track_sample(0, vec![1.0]));
+        assert!(engine.set_track_sample(1, vec![1.0]));
+        assert!(engine.latency_mut().set_track_lookahead_samples(1, 3));
+        assert!(engine.latency_mut().set_master_lookahead_samples(2));
+        assert_eq!(engine.latency_report().total_samples, 5);
+
+        assert!(engine.trigger_track(0, 1.0));
+        assert!(engine.trigger_track(1, 1.0));
+        let mut output = [0.0; 8];
+        engine.process(&mut output);
+        let onsets: Vec<usize> = (0..output.len())
+            .filter(|frame| output[*frame] > 0.0)
+            .collect();
+        assert_eq!(onsets, vec![2, 5]);
+    }
+
+    #[test]
+    fn audition_voice_ignores_choke_groups_and_master_gain() {
+        let mut engine = OfflineEngine::new(48_000);
+        assert!(engine.set_track_sample(0, vec![1.0; 2]));
+        engine.apply_parameter_update(&abi_rs::FfParameterUpdate {
+            parameter_id: abi_rs::ff_track_parameter_id(0, abi_rs::FF_PARAM_SLOT_CHOKE_GROUP)
+                .expect("id"),
+            normalized_value: 0.0625,
+            ramp_samples: 0,
+            reserved: 0,
+        });
+        engine.set_master_gain(0.0);
+
+        assert!(engine.trigger_track(0, 1.0));
+        assert!(engine.audition_sample(vec![0.5; 2], 1.0));
+        assert_eq!(engine.active_voice_count(), 1);
+        let mut output = [0.0; 1];
+        engine.process(&mut output);
+        assert!((output[0] - 0.5).abs() < 1e-4);
+
+        engine.set_master_gain(1.0);
+        engine.process(&mut output);
+        assert!((output[0] - 1.5).abs() < 1e-4);
+
+        engine.stop_audition();
+        assert!(!engine.is_auditioning());
+        assert!(!engine.audition_sample(Vec::new(), 1.0));
+    }
+
+    #[test]
+    fn project_render_places_hits_at_event_offsets() {
+        let mut project = Project {
+            name: "render".to_string(),
+            kits: vec![Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![Pattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        project.kits[0].add_assignment(TrackAssignment {
+            track_index: 0,
+            sample_id: "click".to_string(),
+        });
+        project.kits[0].add_assignment(TrackAssignment {
+            track_index: 1,
+            sample_id: "missing".to_string(),
+        });
+        project.patterns[0].set_step(
+            0,
+            1,
+            PatternStep {
+                active: true,
+                velocity: 127,
+                ..PatternStep::default()
+            },
+        );
+
+        let metrics = EngineMetrics::new();
+        let output = render_project_metered(&project, 48_000, 12_000, 512, &metrics, |sample_id| {
+            (sample_id == "click").then(|| vec![1.0; 32])
+        })
+        .expect("render");
+
+        assert_eq!(output.missing_samples, vec!["missing".to_string()]);
+        assert_eq!(metrics.snapshot().blocks_processed, 24);
+        assert_eq!(metrics.snapshot().peak_voices, 1);
+        assert_eq!(output.events.len(), 1);
+        let hit = output.events[0].timeline_sample as usize;
+        assert_eq!(hit, 6_000);
+        assert_eq!(output.audio[hit - 1], 0.0);
+        assert!(output.audio[hit + 1] > 0.0);
+    }
+
+    #[test]
+    fn frozen_track_plays_back_its_bounce() {
+        let mut project = Project {
+            kits: vec![Kit::default()],
+            patterns: vec![Pattern::default()],
+            ..Project::default()
+        };
+        for (track_index, step_index) in [(0u8, 2usize), (1, 6)] {
+            project.kits[0].add_assignment(TrackAssignment {
+                track_index,
+                sample_id: format!("tone-{track_index}"),
+            });
+            project.patterns[0].set_step(
+                usize::from(track_index),
+                step_index,
+                PatternStep {
+                    active: true,
+                    velocity: 100,
+                    ..PatternStep::default()
+                },
+            );
+        }
+        let load = |sample_id: &str| match sample_id {
+            "tone-0" => Some(vec![0.5; 400]),
+            "tone-1" => Some(vec![-0.25; 400]),
+            _ => None,
+        };
+        let loop_frames = 96_000;
+        let dry = render_project(&project, 48_000, loop_frames, 256, load).expect("dry render");
+
+        let mut frozen = project.clone();
+        let bounce = freeze_track(&mut frozen, 0, "bounce-0", 48_000, load).expect("bounce");
+        assert_eq!(bounce.audio.len(), loop_frames as usize);
+        assert_eq!(bounce.events.len(), 1);
+        assert!(bounce.audio[12_010] > 0.0);
+        assert_eq!(bounce.audio[36_010], 0.0);
+
+        let bounce_audio = bounce.audio.clone();
+        let wet = render_project(&frozen, 48_000, loop_frames, 256, |sample_id| {
+            if sample_id == "bounce-0" {
+                Some(bounce_audio.clone())
+            } else {
+                load(sample_id)
+            }
+        })
+        .expect("frozen render");
+        for (dry, wet) in dry.audio.iter().zip(&wet.audio) {
+            assert!((dry - wet).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn threaded_render_matches_serial_render() {
+        let mut project = Project {
+            name: "parallel".to_string(),
+            kits: vec![Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![Pattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        for track_index in 0..8u8 {
+            project.kits[0].add_assignment(TrackAssignment {
+                track_index,
+                sample_id: format!("tone-{track_index}"),
+            });
+            project.patterns[0].set_step(
+                usize::from(track_index),
+                usize::from(track_index) * 2,
+                PatternStep {
+                    active: true,
+                    velocity: 100,
+                    ..PatternStep::default()
+                },
+            );
+        }
+        let load = |sample_id: &str| {
+            let seed = sample_id.len() as f32 + sample_id.as_bytes()[5] as f32;
+            Some(
+                (0..4_000)
+                    .map(|i| (i as f32 * seed * 0.001).sin())
+                    .collect(),
+            )
+        };
+
+        let metrics = EngineMetrics::new();
+        let serial = render_project_metered(&project, 48_000, 24_000, 256, &metrics, load)
+            .expect("serial render");
+        let parallel = render_project_threaded(&project, 48_000, 24_000, 256, 4, &metrics, load)
+            .expect("parallel render");
+        assert_eq!(serial.audio, parallel.audio);
+        assert!(serial.audio.iter().any(|sample| *sample != 0.0));
+        assert!(render_project_threaded(
+            &project,
+            48_000,
+            256,
+            256,
+            MAX_RENDER_THREADS + 1,
+            &metrics,
+            load
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn routed_tracks_render_to_separate_outputs() {
+        let mut project = Project {
+            kits: vec![Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![Pattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        for track_index in 0..2u8 {
+            project.kits[0].add_assignment(TrackAssignment {
+                track_index,
+                sample_id: "click".to_string(),
+            });
+            project.patterns[0].set_step(
+                usize::from(track_index),
+                usize::from(track_index) * 4,
+                PatternStep {
+                    active: true,
+                    velocity: 127,
+                    ..PatternStep::default()
+                },
+            );
+        }
+        assert!(project.output_routing.set_track_output(1, 2));
+        let load = |_: &str| Some(vec![1.0; 64]);
+
+        let routed = render_project_outputs(&project, 48_000, 48_000, 256, load).expect("render");
+        assert_eq!(routed.outputs.len(), 3);
+        let track_1_hit = routed.events[1].timeline_sample as usize;
+        assert!(routed.outputs[0][1] > 0.0);
+        assert_eq!(routed.outputs[0][track_1_hit + 1], 0.0);
+        assert!(routed.outputs[1].iter().all(|sample| *sample == 0.0));
+        assert!(routed.outputs[2][track_1_hit + 1] > 0.0);
+
+        let mixdown = render_project(&project, 48_000, 48_000, 256, load).expect("mixdown");
+        for (frame, sample) in mixdown.audio.iter().enumerate() {
+            let summed: f32 = routed.outputs.iter().map(|output| output[frame]).sum();
+            assert!((sample - summed).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn track_meters_cover_each_processed_block() {
+        let mut engine = OfflineEngine::new(48_000);
+        assert!(engine.set_track_sample(2, vec![0.5; 4_096]));
+        assert!(engine.trigger_track(2, 1.0));
+        let mut block = vec![0.0; 256];
+        engine.process(&mut block);
+        engine.process(&mut block);
+
+        let meters = engine.take_track_meters();
+        assert!(meters[2].peak > 0.0 && meters[2].peak <= 0.5);
+        assert!(meters[2].rms > 0.0 && meters[2].rms <= meters[2].peak);
+        assert_eq!(meters[0], TrackMeterReading::default());
+        assert_eq!(engine.take_track_meters()[2], TrackMeterReading::default());
+
+        let metrics = EngineMetrics::new();
+        let mut sequencer = Sequencer::new(48_000);
+        assert!(engine.trigger_track(2, 1.0));
+        render_block(&mut sequencer, &mut engine, &mut block, &metrics);
+        assert!(metrics.track_meters()[2].peak > 0.0);
+    }
+
+    #[test]
+    fn ducked_track_dips_on_source_triggers() {
+        let mut project = Project {
+            kits: vec![Kit::default()],
+            patterns: vec![Pattern::default()],
+            ..Project::default()
+        };
+        for (track_index, sample_id) in [(0u8, "kick"), (1u8, "bass")] {
+            project.kits[0].add_assignment(TrackAssignment {
+                track_index,
+                sample_id: sample_id.to_string(),
+            });
+        }
+        assert!(project.kits[0].set_track_ducking(TrackDucking {
+            track_index: 1,
+            source_track: 0,
+            depth: 1.0,
+            attack_ms: 0.0,
+            release_ms: 50.0,
+        }));
+        for (track_index, step_index) in [(1, 0), (0, 4)] {
+            project.patterns[0].set_step(
+                track_index,
+                step_index,
+                PatternStep {
+                    active: true,
+                    velocity: 127,
+                    ..PatternStep::default()
+                },
+            );
+        }
+
+        let output = render_project(&project, 48_000, 36_000, 256, |sample_id| {
+            Some(vec![if sample_id == "bass" { 1.0 } else { 0.0 }; 48_000])
+        })
+        .expect("render");
+        let kick = output
+            .events
+            .iter()
+            .find(|event| event.track_index == 0)
+            .expect("kick event")
+            .timeline_sample as usize;
+        assert!(output.audio[kick - 1] > 0.5);
+        assert!(output.audio[kick + 1].abs() < 0.001);
+        assert!(output.audio[kick + 1_200] > 0.1);
+        assert!(output.audio[kick + 3_000] > 0.5);
+    }
+
+    #[test]
+    fn slicer_finds_transients_and_builds_kit() {
+        let mut loop_sample = vec![0.0f32; 48_000];
+        for onset in [0usize, 12_000, 24_000, 36_000] {
+            for (offset, value) in loop_sample[onset..onset + 2_000].iter_mut().enumerate() {
+                *value = 0.9 * (-(offset as f32) / 400.0).exp();
+            }
+        }
+
+        let regions = slice_regions(
+            &loop_sample,
+            48_000,
+            SliceMode::Transients {
+                max_slices: 8,
+                sensitivity: 0.5,
+            },
+        );
+        let starts: Vec<u64> = regions.iter().map(|region| region.start_frame).collect();
+        assert_eq!(starts, vec![0, 11_776, 23_808, 35_840]);
+        assert_eq!(regions.last().expect("region").end_frame, 48_000);
+        assert_eq!(
+            slice_regions(&loop_sample, 48_000, SliceMode::Even(3)).len(),
+            3
+        );
+
+        let (kit, pattern) =
+            slice_to_kit("loop", "loop.wav", &loop_sample, 48_000, SliceMode::Even(4));
+        assert_eq!(kit.tracks.len(), 4);
+        assert_eq!(
+            kit.track_region(2).map(|region| region.start_frame),
+            Some(24_000)
+        );
+        assert!(pattern.steps[1][4].active);
+        assert!(pattern.steps[3][12].active);
+
+        let project = Project {
+            name: "slices".to_string(),
+            kits: vec![kit],
+            active_kit: Some(0),
+            patterns: vec![pattern],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        let output =
+            render_project_metered(&project, 48_000, 24_100, 256, &EngineMetrics::new(), |_| {
+                Some(loop_sample.clone())
+            })
+            .expect("render");
+        assert!(output.audio[23_999].abs() < 0.01);
+        assert!(output.audio[24_001] > 0.5);
+    }
+
+    #[test]
+    fn tempo_estimator_detects_loop_bpm_and_suggests_action() {
+        let beat_frames = 23_040usize;
+        let mut break_loop = vec![0.0f32; beat_frames * 8];
+        for eighth in 0..16 {
+            let onset = eighth * beat_frames / 2;
+            let level = if eighth % 2 == 0 { 0.9 } else { 0.3 };
+            for (offset, value) in break_loop[onset..onset + 3_000].iter_mut().enumerate() {
+                *value = level * (-(offset as f32) / 500.0).exp();
+            }
+        }
+
+        let estimate = estimate_tempo(&break_loop, 48_000).expect("tempo");
+        assert!((estimate.bpm - 125.0).abs() < 0.01, "bpm {}", estimate.bpm);
+        assert_eq!(estimate.bars, Some(2));
+        assert_eq!(
+            suggest_loop_tempo(Some(estimate), 120.0, false),
+            LoopTempoAction::SetProjectTempo(estimate.bpm)
+        );
+        match suggest_loop_tempo(Some(estimate), 120.0, true) {
+            LoopTempoAction::TimeStretch { ratio } => assert!((ratio - 0.96).abs() < 0.001),
+            other => panic!("unexpected action {other:?}"),
+        }
+        assert_eq!(estimate_tempo(&vec![0.0; 96_000], 48_000), None);
+    }
+
+    #[test]
+    fn recorded_take_roundtrips_bounces_and_quantizes() {
+        let mut project = Project {
+            name: "jam".to_string(),
+            kits: vec![Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![Pattern::default(), Pattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        project.kits[0].add_assignment(TrackAssignment {
+            track_index: 0,
+            sample_id: "kick".to_string(),
+        });
+        let held = PatternStep {
+            active: true,
+            velocity: 90,
+            ..PatternStep::default()
+        };
+        project.patterns[1].set_step(4, 0, held);
+
+        let mut recorder = PerformanceRecorder::new(48_000, 120.0);
+        assert!(!recorder.record_pad_hit(0, 0, 100));
+        recorder.start(1_000);
+        assert!(recorder.record_pad_hit(7_100, 0, 110));
+        assert!(recorder.record_pattern_switch(97_000, 1));
+        assert!(recorder.record_pad_hit(108_900, 2, 80));
+        assert!(recorder.record_master_effect(
+            150_000,
+            super::master_fx::MasterEffect::Stutter,
+            true
+        ));
+        assert!(!recorder.record_pad_hit(500, 0, 100));
+        let take = recorder.stop(193_000).expect("take");
+        assert_eq!(take.length_frames, 192_000);
+        assert_eq!(take.bar_count(), 2);
+        assert_eq!(
+            PerformanceTake::from_text(&take.to_text()).expect("parse"),
+            take
+        );
+
+        let patterns = take.quantize_to_patterns(&project, true);
+        assert_eq!(patterns.len(), 2);
+        assert_eq!(patterns[0].steps[0][1].velocity, 110);
+        assert!(patterns[0].steps[0][1].active);
+        assert_eq!(patterns[1].steps[4][0], held);
+        assert!(patterns[1].steps[2][2].active);
+        assert!(!take.quantize_to_patterns(&project, false)[1].steps[4][0].active);
+
+        let output = take
+            .bounce(&project, |_| Some(vec![1.0; 64]))
+            .expect("bounce");
+        assert_eq!(output.audio.len(), 192_000);
+        assert_eq!(output.audio[6_099], 0.0);
+        assert!(output.audio[6_100] > 0.0);
+    }
+
+    #[test]
+    fn metronome_renders_on_its_own_output_only_when_requested() {
+        let mut project = Project {
+            kits: vec![Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![Pattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        project.metronome = presets_rs::Metronome {
+            enabled: true,
+            accent_sample_id: "accent".to_string(),
+            output_pair: 1,
+            ..presets_rs::Metronome::default()
+        };
+        let load = |sample_id: &str| (sample_id == "accent").then(|| vec![0.5; 16]);
+
+        let silent = render_project(&project, 48_000, 48_000, 256, load).expect("render");
+        assert!(silent.audio.iter().all(|sample| *sample == 0.0));
+
+        project.metronome.include_in_render = true;
+        let routed = render_project_outputs(&project, 48_000, 48_000, 64, load).expect("render");
+        assert_eq!(routed.outputs.len(), 2);
+        assert!(routed.outputs[0].iter().all(|sample| *sample == 0.0));
+        assert!(routed.outputs[1][0] > 0.0);
+        assert_eq!(routed.outputs[1][16], 0.0);
+        assert!(routed.outputs[1][23_000..24_000]
+            .iter()
+            .all(|sample| *sample == 0.0));
+        assert!(routed.outputs[1][24_000..24_100]
+            .iter()
+            .any(|sample| sample.abs() > 0.1));
+        assert!(routed.missing_samples.is_empty());
+    }
+
+    #[test]
+    fn recorder_only_captures_pad_hits_on_armed_tracks() {
+        let mut inputs = presets_rs::TrackInputs::default();
+        inputs.set_armed(3, true);
+        let mut recorder = PerformanceRecorder::new(48_000, 120.0);
+        recorder.set_track_inputs(inputs);
+        recorder.start(0);
+        assert!(!recorder.record_pad_hit(100, 0, 100));
+        assert!(recorder.record_pad_hit(200, 3, 100));
+        assert!(recorder.record_pattern_switch(300, 1));
+        let take = recorder.stop(1_000).expect("take");
+        assert_eq!(take.events.len(), 2);
+        assert_eq!(take.events[0].at_sample, 200);
+    }
+
+    #[test]
+    fn recorded_events_at_one_sample_apply_parameters_before_pad_hits() {
+        let mut recorder = PerformanceRecorder::new(48_000, 120.0);
+        recorder.start(0);
+        assert!(recorder.record_pad_hit(500, 4, 100));
+        assert!(recorder.record_pad_hit(500, 2, 100));
+        assert!(recorder.record(
+            500,
+            PerformanceEvent::Parameter {
+                parameter_id: 0x1021,
+                value: 0.25,
+            },
+        ));
+        assert!(recorder.record_pattern_switch(500, 1));
+        let take = recorder.stop(1_000).expect("take");
+        let order: Vec<PerformanceEvent> = take.events.iter().map(|timed| timed.event).collect();
+        assert_eq!(
+            order,
+            vec![
+                PerformanceEvent::Parameter {
+                    parameter_id: 0x1021,
+                    value: 0.25,
+                },
+                PerformanceEvent::PatternSwitch { pattern_index: 1 },
+                PerformanceEvent::PadHit {
+                    track_index: 2,
+                    velocity: 100,
+                },
+                PerformanceEvent::PadHit {
+                    track_index: 4,
+                    velocity: 100,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn partial_quantize_pulls_hits_toward_a_swung_grid() {
+        let quantize = RecordQuantize::new(0.5, 0.25);
+        assert_eq!(
+            RecordQuantize::new(1.5, 0.9),
+            RecordQuantize::new(1.0, 0.45)
+        );
+        let step_frames = 6_000.0;
+        assert_eq!(quantize.grid_frame(1, step_frames), 7_500.0);
+        assert_eq!(quantize.nearest_step(6_900, step_frames), 1);
+        assert_eq!(quantize.nearest_step(10_000, step_frames), 2);
+        assert_eq!(quantize.quantize_frame(6_800, step_frames), 7_150);
+        assert_eq!(
+            RecordQuantize::default().quantize_frame(6_800, step_frames),
+            6_000
+        );
+
+        let mut recorder = PerformanceRecorder::new(48_000, 120.0);
+        recorder.set_quantize(Some(quantize));
+        recorder.start(0);
+        assert!(recorder.record_pad_hit(6_800, 1, 100));
+        assert!(recorder.record_pad_hit(11_000, 2, 90));
+        let take = recorder.stop(96_000).expect("take");
+        let hits: Vec<u64> = take.events.iter().map(|timed| timed.at_sample).collect();
+        assert_eq!(hits, vec![7_150, 11_500]);
+
+        let project = Project {
+            patterns: vec![Pattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        let patterns = take.quantize_to_patterns_with(&project, false, quantize);
+        assert_eq!(patterns[0].swing, 0.25);
+        assert!(patterns[0].steps[1][1].active);
+        assert!(patterns[0].steps[2][2].active);
+    }
+
+    #[test]
+    fn kit_switch_ramps_parameters_and_lets_old_voices_ring_out() {
+        let mut engine = OfflineEngine::new(48_000);
+        assert!(engine.set_track_sample(0, vec![1.0; 1_000]));
+        engine.apply_parameter_update(&abi_rs::FfParameterUpdate {
+            parameter_id: abi_rs::ff_track_parameter_id(0, abi_rs::FF_PARAM_SLOT_GAIN).expect("id"),
+            normalized_value: 1.0,
+            ramp_samples: 4,
+            reserved: 0,
+        });
+        let max_gain = abi_rs::ff_gain_db_to_linear(abi_rs::FF_GAIN_DB_MAX);
+        let mut output = [0.0; 2];
+        engine.process(&mut output);
+        let gain = engine.track_parameters(0).expect("track").gain;
+        assert!((gain - (1.0 + max_gain) * 0.5).abs() < 1e-5);
+        engine.process(&mut output);
+        assert!((engine.track_parameters(0).expect("track").gain - max_gain).abs() < 1e-5);
+
+        let mut project = Project {
+            name: "switch".to_string(),
+            kits: vec![Kit::default(), Kit::default()],
+            active_kit: Some(0),
+            patterns: vec![Pattern::default()],
+            active_pattern: Some(0),
+            ..Project::default()
+        };
+        project.kits[1].add_assignment(TrackAssignment {
+            track_index: 1,
+            sample_id: "b".to_string(),
+        });
+
+        assert!(engine.trigger_track(0, 1.0));
+        let missing =
+            switch_kit(&mut engine, &project, 1, 100, |_| Some(vec![0.5; 8])).expect("switch");
+        assert!(missing.is_empty());
+        assert_eq!(engine.active_voice_count(), 1);
+        assert!(!engine.trigger_track(0, 1.0));
+
+        let mut tail = [0.0; 100];
+        engine.process(&mut tail);
+        assert!(tail[0] > tail[50] && tail[50] > tail[99] && tail[99] > 0.0);
+        assert_eq!(engine.active_voice_count(), 0);
+        assert!(switch_kit(&mut engine, &project, 5, 100, |_| None).is_err());
+    }
+}
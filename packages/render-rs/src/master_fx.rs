@@ -0,0 +1,343 @@
+use control_rs::STEPS_PER_BEAT;
+use presets_rs::STEPS_PER_PATTERN;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MasterEffect {
+    Stutter,
+    FilterSweep,
+    TapeStop,
+}
+
+impl MasterEffect {
+    pub const ALL: [MasterEffect; 3] = [
+        MasterEffect::Stutter,
+        MasterEffect::FilterSweep,
+        MasterEffect::TapeStop,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MasterEffect::Stutter => "stutter",
+            MasterEffect::FilterSweep => "filter_sweep",
+            MasterEffect::TapeStop => "tape_stop",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|effect| effect.name() == name)
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn default_steps(self) -> u32 {
+        match self {
+            MasterEffect::Stutter => 1,
+            MasterEffect::FilterSweep => STEPS_PER_PATTERN as u32,
+            MasterEffect::TapeStop => STEPS_PER_BEAT as u32,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct EffectSlot {
+    engaged: bool,
+    pending: Option<(bool, f64)>,
+    length_steps: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct MasterEffects {
+    sample_rate_hz: u32,
+    bpm: f32,
+    quantize_steps: u32,
+    step_phase: f64,
+    slots: [EffectSlot; 3],
+    stutter_buffer: Vec<f32>,
+    stutter_length: usize,
+    stutter_position: usize,
+    sweep_progress: f32,
+    filter_state: f32,
+    tape: Vec<f32>,
+    tape_position: f64,
+    tape_speed: f32,
+}
+
+impl MasterEffects {
+    pub fn new(sample_rate_hz: u32, bpm: f32) -> Self {
+        Self {
+            sample_rate_hz: sample_rate_hz.max(1),
+            bpm: bpm.clamp(control_rs::MIN_BPM, control_rs::MAX_BPM),
+            quantize_steps: 1,
+            step_phase: 0.0,
+            slots: MasterEffect::ALL.map(|effect| EffectSlot {
+                length_steps: effect.default_steps(),
+                ..EffectSlot::default()
+            }),
+            stutter_buffer: Vec::new(),
+            stutter_length: 0,
+            stutter_position: 0,
+            sweep_progress: 0.0,
+            filter_state: 0.0,
+            tape: Vec::new(),
+            tape_position: 0.0,
+            tape_speed: 1.0,
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate_hz: u32) -> bool {
+        if sample_rate_hz == 0 {
+            return false;
+        }
+        self.sample_rate_hz = sample_rate_hz;
+        if self.is_engaged(MasterEffect::Stutter) {
+            self.stutter_length = self.effect_frames(MasterEffect::Stutter) as usize;
+            self.stutter_buffer.truncate(self.stutter_length);
+            self.stutter_position %= self.stutter_length;
+        }
+        true
+    }
+
+    pub fn set_tempo_bpm(&mut self, bpm: f32) {
+        self.bpm = bpm.clamp(control_rs::MIN_BPM, control_rs::MAX_BPM);
+    }
+
+    pub fn quantize_steps(&self) -> u32 {
+        self.quantize_steps
+    }
+
+    pub fn set_quantize_steps(&mut self, steps: u32) -> bool {
+        if steps == 0 || steps as usize > STEPS_PER_PATTERN {
+            return false;
+        }
+        self.quantize_steps = steps;
+        true
+    }
+
+    pub fn effect_steps(&self, effect: MasterEffect) -> u32 {
+        self.slots[effect.index()].length_steps
+    }
+
+    pub fn set_effect_steps(&mut self, effect: MasterEffect, steps: u32) -> bool {
+        if steps == 0 || steps as usize > STEPS_PER_PATTERN {
+            return false;
+        }
+        self.slots[effect.index()].length_steps = steps;
+        true
+    }
+
+    pub fn is_engaged(&self, effect: MasterEffect) -> bool {
+        self.slots[effect.index()].engaged
+    }
+
+    pub fn engage(&mut self, effect: MasterEffect) {
+        self.schedule(effect, true);
+    }
+
+    pub fn release(&mut self, effect: MasterEffect) {
+        self.schedule(effect, false);
+    }
+
+    pub fn reset(&mut self) {
+        for slot in &mut self.slots {
+            slot.engaged = false;
+            slot.pending = None;
+        }
+        self.step_phase = 0.0;
+    }
+
+    fn schedule(&mut self, effect: MasterEffect, engaged: bool) {
+        let quantize = f64::from(self.quantize_steps);
+        let boundary = (self.step_phase / quantize - 1e-9).ceil() * quantize;
+        let slot = &mut self.slots[effect.index()];
+        slot.pending = if slot.engaged == engaged {
+            None
+        } else {
+            Some((engaged, boundary))
+        };
+    }
+
+    fn step_frames(&self) -> f64 {
+        f64::from(self.sample_rate_hz) * 60.0 / f64::from(self.bpm) / STEPS_PER_BEAT as f64
+    }
+
+    fn effect_frames(&self, effect: MasterEffect) -> f64 {
+        (self.step_frames() * f64::from(self.slots[effect.index()].length_steps)).max(1.0)
+    }
+
+    fn apply_pending(&mut self) {
+        for effect in MasterEffect::ALL {
+            let slot = self.slots[effect.index()];
+            let Some((engaged, at_phase)) = slot.pending else {
+                continue;
+            };
+            if self.step_phase + 1e-9 < at_phase {
+                continue;
+            }
+            self.slots[effect.index()] = EffectSlot {
+                engaged,
+                pending: None,
+                ..slot
+            };
+            if !engaged {
+                continue;
+            }
+            match effect {
+                MasterEffect::Stutter => {
+                    self.stutter_length = self.effect_frames(effect) as usize;
+                    self.stutter_buffer.clear();
+                    self.stutter_position = 0;
+                }
+                MasterEffect::FilterSweep => self.sweep_progress = 0.0,
+                MasterEffect::TapeStop => {
+                    self.tape.clear();
+                    self.tape_position = 0.0;
+                    self.tape_speed = 1.0;
+                }
+            }
+        }
+    }
+
+    pub fn process(&mut self, buffer: &mut [f32]) {
+        let phase_per_frame = 1.0 / self.step_frames();
+        let sweep_rate = (1.0 / self.effect_frames(MasterEffect::FilterSweep)) as f32;
+        let tape_rate = (1.0 / self.effect_frames(MasterEffect::TapeStop)) as f32;
+        for sample in buffer {
+            self.apply_pending();
+            let mut value = *sample;
+
+            if self.is_engaged(MasterEffect::Stutter) {
+                if self.stutter_buffer.len() < self.stutter_length {
+                    self.stutter_buffer.push(value);
+                } else {
+                    value = self.stutter_buffer[self.stutter_position];
+                    self.stutter_position = (self.stutter_position + 1) % self.stutter_length;
+                }
+            }
+
+            if self.is_engaged(MasterEffect::TapeStop) {
+                self.tape.push(value);
+                value = if self.tape_speed > 0.0 {
+                    let lower = (self.tape_position as usize).min(self.tape.len() - 1);
+                    let upper = (lower + 1).min(self.tape.len() - 1);
+                    let fraction = (self.tape_position - lower as f64) as f32;
+                    self.tape[lower] + (self.tape[upper] - self.tape[lower]) * fraction
+                } else {
+                    0.0
+                };
+                self.tape_position += f64::from(self.tape_speed);
+                self.tape_speed = (self.tape_speed - tape_rate).max(0.0);
+            }
+
+            if self.is_engaged(MasterEffect::FilterSweep) {
+                let alpha = 1.0 - 0.99 * self.sweep_progress;
+                self.filter_state += alpha * (value - self.filter_state);
+                value = self.filter_state;
+                self.sweep_progress = (self.sweep_progress + sweep_rate).min(1.0);
+            } else {
+                self.filter_state = value;
+            }
+
+            *sample = value;
+            self.step_phase += phase_per_frame;
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MasterEffectNotes {
+    bindings: Vec<(u8, MasterEffect)>,
+}
+
+impl MasterEffectNotes {
+    pub fn bind_note(&mut self, note: u8, effect: MasterEffect) -> bool {
+        if note > 127 {
+            return false;
+        }
+        self.bindings.retain(|(bound, _)| *bound != note);
+        self.bindings.push((note, effect));
+        true
+    }
+
+    pub fn resolve(&self, note: u8) -> Option<MasterEffect> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == note)
+            .map(|(_, effect)| *effect)
+    }
+
+    pub fn handle_note(
+        &self,
+        effects: &mut MasterEffects,
+        note: u8,
+        velocity: u8,
+    ) -> Option<MasterEffect> {
+        let effect = self.resolve(note)?;
+        if velocity > 0 {
+            effects.engage(effect);
+        } else {
+            effects.release(effect);
+        }
+        Some(effect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MasterEffect, MasterEffectNotes, MasterEffects};
+
+    #[test]
+    fn engage_waits_for_the_next_quantized_step() {
+        let mut effects = MasterEffects::new(48_000, 120.0);
+        let notes = {
+            let mut notes = MasterEffectNotes::default();
+            assert!(notes.bind_note(36, MasterEffect::TapeStop));
+            notes
+        };
+        let mut block = vec![1.0; 100];
+        effects.process(&mut block);
+
+        assert_eq!(
+            notes.handle_note(&mut effects, 36, 100),
+            Some(MasterEffect::TapeStop)
+        );
+        assert_eq!(notes.handle_note(&mut effects, 37, 100), None);
+        let mut block = vec![1.0; 5_900];
+        effects.process(&mut block);
+        assert!(!effects.is_engaged(MasterEffect::TapeStop));
+
+        let mut block = vec![1.0; 100];
+        effects.process(&mut block);
+        assert!(effects.is_engaged(MasterEffect::TapeStop));
+
+        notes.handle_note(&mut effects, 36, 0);
+        let mut block = vec![1.0; 6_000];
+        effects.process(&mut block);
+        assert!(!effects.is_engaged(MasterEffect::TapeStop));
+        assert_eq!(block[5_999], 1.0);
+    }
+
+    #[test]
+    fn stutter_repeats_the_captured_step() {
+        let mut effects = MasterEffects::new(48_000, 120.0);
+        effects.engage(MasterEffect::Stutter);
+        let mut block: Vec<f32> = (0..12_000).map(|index| index as f32).collect();
+        effects.process(&mut block);
+
+        assert_eq!(block[5_999], 5_999.0);
+        assert_eq!(block[6_000], 0.0);
+        assert_eq!(block[11_999], 5_999.0);
+    }
+
+    #[test]
+    fn tape_stop_winds_down_to_silence() {
+        let mut effects = MasterEffects::new(48_000, 120.0);
+        effects.engage(MasterEffect::TapeStop);
+        let mut block = vec![0.5; 30_000];
+        effects.process(&mut block);
+
+        assert!(block[100] > 0.4);
+        assert_eq!(block[29_999], 0.0);
+    }
+}
@@ -0,0 +1,146 @@
+use control_rs::{StepChange, STEPS_PER_BEAT, STEPS_PER_PATTERN};
+use presets_rs::Metronome;
+
+pub const CLICK_LENGTH_MS: f32 = 30.0;
+pub const ACCENT_CLICK_HZ: f32 = 1_760.0;
+pub const CLICK_HZ: f32 = 880.0;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MetronomeClick {
+    pub block_offset: u32,
+    pub accent: bool,
+}
+
+pub fn metronome_clicks(step_changes: &[StepChange]) -> Vec<MetronomeClick> {
+    step_changes
+        .iter()
+        .filter(|change| change.step_index.is_multiple_of(STEPS_PER_BEAT))
+        .map(|change| MetronomeClick {
+            block_offset: change.block_offset,
+            accent: change.step_index.is_multiple_of(STEPS_PER_PATTERN),
+        })
+        .collect()
+}
+
+pub fn synthesized_click(sample_rate_hz: u32, accent: bool) -> Vec<f32> {
+    let sample_rate = sample_rate_hz.max(1) as f32;
+    let frequency = if accent { ACCENT_CLICK_HZ } else { CLICK_HZ };
+    let length = ((sample_rate * CLICK_LENGTH_MS / 1_000.0) as usize).max(1);
+    (0..length)
+        .map(|frame| {
+            let envelope = 1.0 - frame as f32 / length as f32;
+            let phase = std::f32::consts::TAU * frequency * frame as f32 / sample_rate;
+            phase.sin() * envelope * envelope
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MetronomeSounds {
+    pub accent: Vec<f32>,
+    pub click: Vec<f32>,
+}
+
+impl MetronomeSounds {
+    pub fn synthesized(sample_rate_hz: u32) -> Self {
+        Self {
+            accent: synthesized_click(sample_rate_hz, true),
+            click: synthesized_click(sample_rate_hz, false),
+        }
+    }
+
+    pub fn load<F>(
+        metronome: &Metronome,
+        sample_rate_hz: u32,
+        load_sample: &mut F,
+    ) -> (Self, Vec<String>)
+    where
+        F: FnMut(&str) -> Option<Vec<f32>>,
+    {
+        let mut missing_samples = Vec::new();
+        let mut load = |sample_id: &str, accent: bool| {
+            if sample_id.is_empty() {
+                return synthesized_click(sample_rate_hz, accent);
+            }
+            match load_sample(sample_id).filter(|sample| !sample.is_empty()) {
+                Some(sample) => sample,
+                None => {
+                    missing_samples.push(sample_id.to_string());
+                    synthesized_click(sample_rate_hz, accent)
+                }
+            }
+        };
+        let sounds = Self {
+            accent: load(&metronome.accent_sample_id, true),
+            click: load(&metronome.click_sample_id, false),
+        };
+        (sounds, missing_samples)
+    }
+
+    pub fn sample(&self, accent: bool) -> &[f32] {
+        if accent {
+            &self.accent
+        } else {
+            &self.click
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use control_rs::StepChange;
+    use presets_rs::Metronome;
+
+    use super::{metronome_clicks, synthesized_click, MetronomeClick, MetronomeSounds};
+
+    #[test]
+    fn clicks_fall_on_beats_with_an_accent_on_the_bar() {
+        let changes: Vec<StepChange> = [0, 1, 4, 16, 20]
+            .into_iter()
+            .enumerate()
+            .map(|(index, step_index)| StepChange {
+                bar: 0,
+                pattern_bar: step_index / 16,
+                step_index,
+                block_offset: index as u32 * 10,
+            })
+            .collect();
+        assert_eq!(
+            metronome_clicks(&changes),
+            vec![
+                MetronomeClick {
+                    block_offset: 0,
+                    accent: true,
+                },
+                MetronomeClick {
+                    block_offset: 20,
+                    accent: false,
+                },
+                MetronomeClick {
+                    block_offset: 30,
+                    accent: true,
+                },
+                MetronomeClick {
+                    block_offset: 40,
+                    accent: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn configured_samples_load_and_fall_back_to_synthesized_clicks() {
+        let metronome = Metronome {
+            accent_sample_id: "wood-hi".to_string(),
+            click_sample_id: "missing".to_string(),
+            ..Metronome::default()
+        };
+        let (sounds, missing) = MetronomeSounds::load(&metronome, 48_000, &mut |sample_id| {
+            (sample_id == "wood-hi").then(|| vec![0.5; 8])
+        });
+        assert_eq!(sounds.sample(true), &[0.5; 8]);
+        assert_eq!(sounds.sample(false), synthesized_click(48_000, false));
+        assert_eq!(missing, vec!["missing".to_string()]);
+        assert_eq!(synthesized_click(48_000, true).len(), 1_440);
+    }
+}
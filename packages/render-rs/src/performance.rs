@@ -0,0 +1,527 @@
+use abi_rs::{
+    FfParameterUpdate, FF_EVENT_PRIORITY_NOTE_ON, FF_EVENT_PRIORITY_PARAMETER,
+    FF_EVENT_PRIORITY_TRANSPORT,
+};
+use control_rs::smoothing::{ParameterSmoother, SmoothingConfig};
+use control_rs::telemetry::EngineMetrics;
+use control_rs::{Sequencer, Step, MAX_SWING, STEPS_PER_BEAT};
+use presets_rs::{Pattern, PatternStep, Project, TrackInputs, STEPS_PER_PATTERN, TRACK_COUNT};
+
+use crate::master_fx::MasterEffect;
+use crate::{prepare_engine, render_block, RenderOutput, DEFAULT_RENDER_BLOCK_FRAMES};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PerformanceEvent {
+    PadHit { track_index: u8, velocity: u8 },
+    Parameter { parameter_id: u32, value: f32 },
+    PatternSwitch { pattern_index: usize },
+    MasterEffect { effect: MasterEffect, engaged: bool },
+}
+
+impl PerformanceEvent {
+    pub fn priority(&self) -> u32 {
+        match self {
+            PerformanceEvent::Parameter { .. } => FF_EVENT_PRIORITY_PARAMETER,
+            PerformanceEvent::PatternSwitch { .. } | PerformanceEvent::MasterEffect { .. } => {
+                FF_EVENT_PRIORITY_TRANSPORT
+            }
+            PerformanceEvent::PadHit { .. } => FF_EVENT_PRIORITY_NOTE_ON,
+        }
+    }
+
+    fn track_index(&self) -> u8 {
+        match self {
+            PerformanceEvent::PadHit { track_index, .. } => *track_index,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimedPerformanceEvent {
+    pub at_sample: u64,
+    pub event: PerformanceEvent,
+}
+
+impl TimedPerformanceEvent {
+    pub fn order_key(&self) -> (u64, u32, u8) {
+        (
+            self.at_sample,
+            self.event.priority(),
+            self.event.track_index(),
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RecordQuantize {
+    pub strength: f32,
+    pub swing: f32,
+}
+
+impl Default for RecordQuantize {
+    fn default() -> Self {
+        Self {
+            strength: 1.0,
+            swing: 0.0,
+        }
+    }
+}
+
+impl RecordQuantize {
+    pub fn new(strength: f32, swing: f32) -> Self {
+        let finite_or = |value: f32, fallback: f32| {
+            if value.is_finite() {
+                value
+            } else {
+                fallback
+            }
+        };
+        Self {
+            strength: finite_or(strength, 1.0).clamp(0.0, 1.0),
+            swing: finite_or(swing, 0.0).clamp(0.0, MAX_SWING),
+        }
+    }
+
+    pub fn grid_frame(&self, step: usize, step_frames: f64) -> f64 {
+        let swing_offset = if !step.is_multiple_of(2) {
+            f64::from(self.swing) * step_frames
+        } else {
+            0.0
+        };
+        step as f64 * step_frames + swing_offset
+    }
+
+    pub fn nearest_step(&self, at_sample: u64, step_frames: f64) -> usize {
+        let at = at_sample as f64;
+        let pair = (at / (step_frames * 2.0)).floor() as usize * 2;
+        (pair..=pair + 2)
+            .min_by(|left, right| {
+                let left = (self.grid_frame(*left, step_frames) - at).abs();
+                let right = (self.grid_frame(*right, step_frames) - at).abs();
+                left.total_cmp(&right)
+            })
+            .unwrap_or(pair)
+    }
+
+    pub fn quantize_frame(&self, at_sample: u64, step_frames: f64) -> u64 {
+        let at = at_sample as f64;
+        let target = self.grid_frame(self.nearest_step(at_sample, step_frames), step_frames);
+        (at + (target - at) * f64::from(self.strength))
+            .round()
+            .max(0.0) as u64
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PerformanceTake {
+    pub sample_rate_hz: u32,
+    pub bpm: f32,
+    pub length_frames: u64,
+    pub events: Vec<TimedPerformanceEvent>,
+}
+
+impl PerformanceTake {
+    pub fn new(sample_rate_hz: u32, bpm: f32) -> Self {
+        Self {
+            sample_rate_hz: sample_rate_hz.max(1),
+            bpm: bpm.clamp(control_rs::MIN_BPM, control_rs::MAX_BPM),
+            length_frames: 0,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn step_frames(&self) -> f64 {
+        f64::from(self.sample_rate_hz) * 60.0 / f64::from(self.bpm) / STEPS_PER_BEAT as f64
+    }
+
+    pub fn bar_count(&self) -> usize {
+        let bar_frames = self.step_frames() * STEPS_PER_PATTERN as f64;
+        ((self.length_frames as f64 / bar_frames).ceil() as usize).max(1)
+    }
+
+    fn insert(&mut self, event: TimedPerformanceEvent) {
+        let index = self
+            .events
+            .partition_point(|existing| existing.order_key() <= event.order_key());
+        self.events.insert(index, event);
+        self.length_frames = self.length_frames.max(event.at_sample + 1);
+    }
+
+    pub fn quantized(&self, quantize: RecordQuantize) -> PerformanceTake {
+        let step_frames = self.step_frames();
+        let mut take = self.clone();
+        for timed in &mut take.events {
+            if matches!(timed.event, PerformanceEvent::PadHit { .. }) {
+                timed.at_sample = quantize.quantize_frame(timed.at_sample, step_frames);
+            }
+        }
+        take.events.sort_by_key(TimedPerformanceEvent::order_key);
+        if let Some(last) = take.events.last() {
+            take.length_frames = take.length_frames.max(last.at_sample + 1);
+        }
+        take
+    }
+
+    pub fn quantize_to_patterns(&self, project: &Project, overdub: bool) -> Vec<Pattern> {
+        self.quantize_to_patterns_with(project, overdub, RecordQuantize::default())
+    }
+
+    pub fn quantize_to_patterns_with(
+        &self,
+        project: &Project,
+        overdub: bool,
+        quantize: RecordQuantize,
+    ) -> Vec<Pattern> {
+        let base_pattern = |index: Option<usize>| -> Pattern {
+            match index.and_then(|index| project.patterns.get(index)) {
+                Some(pattern) if overdub => pattern.clone(),
+                _ => Pattern::default(),
+            }
+        };
+
+        let step_frames = self.step_frames();
+        let mut playing = project
+            .active_pattern
+            .or_else(|| (!project.patterns.is_empty()).then_some(0));
+        let mut next_event = 0;
+        let mut patterns = Vec::with_capacity(self.bar_count());
+        for bar in 0..self.bar_count() {
+            let bar_start = (bar * STEPS_PER_PATTERN) as f64 * step_frames;
+            while let Some(timed) = self.events.get(next_event) {
+                if timed.at_sample as f64 > bar_start {
+                    break;
+                }
+                if let PerformanceEvent::PatternSwitch { pattern_index } = timed.event {
+                    playing = Some(pattern_index);
+                }
+                next_event += 1;
+            }
+            let mut pattern = base_pattern(playing);
+            pattern.name = format!("take bar {}", bar + 1);
+            if quantize.swing > 0.0 {
+                pattern.set_swing(quantize.swing);
+            }
+            patterns.push(pattern);
+        }
+
+        for timed in &self.events {
+            let PerformanceEvent::PadHit {
+                track_index,
+                velocity,
+            } = timed.event
+            else {
+                continue;
+            };
+            let step = quantize.nearest_step(timed.at_sample, step_frames);
+            let bar = (step / STEPS_PER_PATTERN).min(patterns.len() - 1);
+            patterns[bar].set_step(
+                usize::from(track_index),
+                step % STEPS_PER_PATTERN,
+                PatternStep {
+                    active: velocity > 0,
+                    velocity: velocity.min(127),
+                    ..PatternStep::default()
+                },
+            );
+        }
+        patterns
+    }
+
+    pub fn bounce<F>(&self, project: &Project, mut load_sample: F) -> Result<RenderOutput, String>
+    where
+        F: FnMut(&str) -> Option<Vec<f32>>,
+    {
+        let mut recall = control_rs::recall_state_from_project(project, self.sample_rate_hz)?;
+        let (mut engine, missing_samples) =
+            prepare_engine(project, &recall, self.sample_rate_hz, &mut load_sample);
+        let metrics = EngineMetrics::new();
+        let mut smoother = ParameterSmoother::new(
+            self.sample_rate_hz,
+            DEFAULT_RENDER_BLOCK_FRAMES,
+            SmoothingConfig::default(),
+        );
+        let sequencer = recall.sequencer_mut();
+        sequencer.set_tempo_bpm(self.bpm);
+        sequencer.start();
+
+        let total_frames = self.length_frames;
+        let mut audio = vec![0.0; total_frames as usize];
+        let mut events = Vec::new();
+        let mut next_event = 0;
+        let mut position = 0u64;
+        while position < total_frames {
+            while let Some(timed) = self.events.get(next_event) {
+                if timed.at_sample > position {
+                    break;
+                }
+                match timed.event {
+                    PerformanceEvent::PadHit {
+                        track_index,
+                        velocity,
+                    } => {
+                        engine.trigger_track(usize::from(track_index), f32::from(velocity) / 127.0);
+                    }
+                    PerformanceEvent::Parameter {
+                        parameter_id,
+                        value,
+                    } => {
+                        let update = FfParameterUpdate {
+                            parameter_id,
+                            normalized_value: value,
+                            ramp_samples: 0,
+                            reserved: 0,
+                        };
+                        if !sequencer.apply_parameter_update(&update) {
+                            smoother.push(update);
+                        }
+                    }
+                    PerformanceEvent::PatternSwitch { pattern_index } => {
+                        if let Some(pattern) = project.patterns.get(pattern_index) {
+                            load_pattern(sequencer, pattern);
+                        }
+                    }
+                    PerformanceEvent::MasterEffect { effect, engaged } => {
+                        if engaged {
+                            engine.master_effects_mut().engage(effect);
+                        } else {
+                            engine.master_effects_mut().release(effect);
+                        }
+                    }
+                }
+                next_event += 1;
+            }
+            engine.apply_parameter_updates(&smoother.drain());
+
+            let limit = self
+                .events
+                .get(next_event)
+                .map_or(total_frames, |timed| timed.at_sample.min(total_frames));
+            let frames = (limit - position).min(u64::from(DEFAULT_RENDER_BLOCK_FRAMES));
+            let block = &mut audio[position as usize..(position + frames) as usize];
+            events.extend(render_block(sequencer, &mut engine, block, &metrics));
+            position += frames;
+        }
+
+        Ok(RenderOutput {
+            audio,
+            events,
+            missing_samples,
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![
+            "FF_TAKE_V1".to_string(),
+            format!("sample_rate={}", self.sample_rate_hz),
+            format!("bpm={:.6}", self.bpm),
+            format!("length={}", self.length_frames),
+        ];
+        for timed in &self.events {
+            lines.push(match timed.event {
+                PerformanceEvent::PadHit {
+                    track_index,
+                    velocity,
+                } => format!("pad|{}|{track_index}|{velocity}", timed.at_sample),
+                PerformanceEvent::Parameter {
+                    parameter_id,
+                    value,
+                } => format!("param|{}|{parameter_id}|{value:.6}", timed.at_sample),
+                PerformanceEvent::PatternSwitch { pattern_index } => {
+                    format!("pattern|{}|{pattern_index}", timed.at_sample)
+                }
+                PerformanceEvent::MasterEffect { effect, engaged } => format!(
+                    "fx|{}|{}|{}",
+                    timed.at_sample,
+                    effect.name(),
+                    u8::from(engaged)
+                ),
+            });
+        }
+        lines.join("\n")
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines();
+        let header = lines
+            .next()
+            .ok_or_else(|| "missing take header".to_string())?;
+        if header != "FF_TAKE_V1" {
+            return Err(format!("unexpected take header: {header}"));
+        }
+
+        let mut take = Self::new(48_000, control_rs::DEFAULT_BPM);
+        let mut length_frames = 0;
+        for line in lines {
+            if let Some(value) = line.strip_prefix("sample_rate=") {
+                take.sample_rate_hz = parse_field(value, "sample_rate")?;
+                continue;
+            }
+            if let Some(value) = line.strip_prefix("bpm=") {
+                take.bpm = parse_field(value, "bpm")?;
+                continue;
+            }
+            if let Some(value) = line.strip_prefix("length=") {
+                length_frames = parse_field(value, "length")?;
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split('|').collect();
+            let event = match fields.as_slice() {
+                ["pad", _, track, velocity] => {
+                    let track_index: u8 = parse_field(track, "track_index")?;
+                    if usize::from(track_index) >= TRACK_COUNT {
+                        return Err(format!("pad track out of range: {line}"));
+                    }
+                    PerformanceEvent::PadHit {
+                        track_index,
+                        velocity: parse_field(velocity, "velocity")?,
+                    }
+                }
+                ["param", _, parameter_id, value] => PerformanceEvent::Parameter {
+                    parameter_id: parse_field(parameter_id, "parameter_id")?,
+                    value: parse_field(value, "value")?,
+                },
+                ["pattern", _, pattern_index] => PerformanceEvent::PatternSwitch {
+                    pattern_index: parse_field(pattern_index, "pattern_index")?,
+                },
+                ["fx", _, name, engaged] => PerformanceEvent::MasterEffect {
+                    effect: MasterEffect::from_name(name)
+                        .ok_or_else(|| format!("unknown master effect: {line}"))?,
+                    engaged: parse_field::<u8>(engaged, "engaged")? != 0,
+                },
+                _ => return Err(format!("unknown take line: {line}")),
+            };
+            take.insert(TimedPerformanceEvent {
+                at_sample: parse_field(fields[1], "at_sample")?,
+                event,
+            });
+        }
+        take.length_frames = take.length_frames.max(length_frames);
+        Ok(take)
+    }
+}
+
+fn parse_field<T: std::str::FromStr>(value: &str, field: &str) -> Result<T, String> {
+    value
+        .parse::<T>()
+        .map_err(|_| format!("invalid value for {field}: {value}"))
+}
+
+fn load_pattern(sequencer: &mut Sequencer, pattern: &Pattern) {
+    sequencer.set_swing(pattern.swing);
+    for (track_index, steps) in pattern.steps.iter().enumerate() {
+        for (step_index, step) in steps.iter().enumerate() {
+            sequencer
+                .pattern_mut()
+                .set_step(track_index, step_index, Step::from(*step));
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PerformanceRecorder {
+    take: PerformanceTake,
+    origin_sample: Option<u64>,
+    quantize: Option<RecordQuantize>,
+    track_inputs: TrackInputs,
+}
+
+impl PerformanceRecorder {
+    pub fn new(sample_rate_hz: u32, bpm: f32) -> Self {
+        Self {
+            take: PerformanceTake::new(sample_rate_hz, bpm),
+            origin_sample: None,
+            quantize: None,
+            track_inputs: TrackInputs::all_armed(),
+        }
+    }
+
+    pub fn track_inputs(&self) -> TrackInputs {
+        self.track_inputs
+    }
+
+    pub fn set_track_inputs(&mut self, inputs: TrackInputs) {
+        self.track_inputs = inputs;
+    }
+
+    pub fn quantize(&self) -> Option<RecordQuantize> {
+        self.quantize
+    }
+
+    pub fn set_quantize(&mut self, quantize: Option<RecordQuantize>) {
+        self.quantize = quantize;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.origin_sample.is_some()
+    }
+
+    pub fn start(&mut self, at_sample: u64) {
+        self.take.events.clear();
+        self.take.length_frames = 0;
+        self.origin_sample = Some(at_sample);
+    }
+
+    pub fn stop(&mut self, at_sample: u64) -> Option<PerformanceTake> {
+        let origin = self.origin_sample.take()?;
+        let mut take = self.take.clone();
+        take.length_frames = take.length_frames.max(at_sample.saturating_sub(origin));
+        Some(match self.quantize {
+            Some(quantize) => take.quantized(quantize),
+            None => take,
+        })
+    }
+
+    pub fn record(&mut self, at_sample: u64, event: PerformanceEvent) -> bool {
+        let Some(origin) = self.origin_sample else {
+            return false;
+        };
+        if at_sample < origin {
+            return false;
+        }
+        self.take.insert(TimedPerformanceEvent {
+            at_sample: at_sample - origin,
+            event,
+        });
+        true
+    }
+
+    pub fn record_pad_hit(&mut self, at_sample: u64, track_index: u8, velocity: u8) -> bool {
+        usize::from(track_index) < TRACK_COUNT
+            && self.track_inputs.is_armed(usize::from(track_index))
+            && self.record(
+                at_sample,
+                PerformanceEvent::PadHit {
+                    track_index,
+                    velocity,
+                },
+            )
+    }
+
+    pub fn record_parameter(&mut self, at_sample: u64, update: &FfParameterUpdate) -> bool {
+        self.record(
+            at_sample,
+            PerformanceEvent::Parameter {
+                parameter_id: update.parameter_id,
+                value: update.normalized_value,
+            },
+        )
+    }
+
+    pub fn record_pattern_switch(&mut self, at_sample: u64, pattern_index: usize) -> bool {
+        self.record(at_sample, PerformanceEvent::PatternSwitch { pattern_index })
+    }
+
+    pub fn record_master_effect(
+        &mut self,
+        at_sample: u64,
+        effect: MasterEffect,
+        engaged: bool,
+    ) -> bool {
+        self.record(
+            at_sample,
+            PerformanceEvent::MasterEffect { effect, engaged },
+        )
+    }
+}
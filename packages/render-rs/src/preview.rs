@@ -0,0 +1,145 @@
+use control_rs::{DEFAULT_BPM, STEPS_PER_BEAT, STEPS_PER_PATTERN, TRACK_COUNT};
+use presets_rs::{Kit, Pattern, PatternStep, Project, SongEntry};
+
+use crate::{render_project, RenderOutput, DEFAULT_RENDER_BLOCK_FRAMES};
+
+pub const KIT_PREVIEW_BARS: usize = 2;
+
+// Steps of the groove bar, laid out for the auto-assign track order: kick on
+// track 0, snare on track 1 and closed hat on track 3.
+const GROOVE_HITS: [(usize, &[usize], u8); 3] = [
+    (0, &[0, 7, 10], 120),
+    (1, &[4, 12], 110),
+    (3, &[0, 2, 4, 6, 8, 10, 12, 14], 80),
+];
+
+// A short hover demo for the library browser: the first bar plays each
+// assigned track once on successive eighths, the second a simple groove, and
+// one more beat lets the last hits ring out. Tracks without a sample rest.
+pub fn render_kit_preview<F>(
+    kit: &Kit,
+    sample_rate_hz: u32,
+    load_sample: F,
+) -> Result<RenderOutput, String>
+where
+    F: FnMut(&str) -> Option<Vec<f32>>,
+{
+    let assigned = |track_index: usize| {
+        kit.tracks
+            .iter()
+            .any(|track| usize::from(track.track_index) == track_index)
+    };
+    let mut pattern = Pattern {
+        name: "preview".to_string(),
+        ..Pattern::default()
+    };
+    pattern.set_bar_count(KIT_PREVIEW_BARS);
+    for track_index in (0..TRACK_COUNT).filter(|track_index| assigned(*track_index)) {
+        pattern.set_step(
+            track_index,
+            track_index * 2,
+            PatternStep {
+                active: true,
+                velocity: 110,
+                ..PatternStep::default()
+            },
+        );
+    }
+    for (track_index, steps, velocity) in GROOVE_HITS {
+        if !assigned(track_index) {
+            continue;
+        }
+        for step_index in steps {
+            pattern.set_step(
+                track_index,
+                STEPS_PER_PATTERN + step_index,
+                PatternStep {
+                    active: true,
+                    velocity,
+                    ..PatternStep::default()
+                },
+            );
+        }
+    }
+
+    // A one-entry song stops the sequencer after the groove bar instead of
+    // looping back into the audition bar during the tail.
+    let project = Project {
+        name: kit.name.clone(),
+        kits: vec![kit.clone()],
+        active_kit: Some(0),
+        patterns: vec![pattern],
+        active_pattern: Some(0),
+        song: vec![SongEntry {
+            pattern_index: 0,
+            repeats: 1,
+        }],
+        ..Project::default()
+    };
+    let beats = KIT_PREVIEW_BARS * STEPS_PER_PATTERN / STEPS_PER_BEAT + 1;
+    let total_frames =
+        (f64::from(sample_rate_hz) * 60.0 / f64::from(DEFAULT_BPM) * beats as f64).round() as u32;
+    render_project(
+        &project,
+        sample_rate_hz,
+        total_frames,
+        DEFAULT_RENDER_BLOCK_FRAMES,
+        load_sample,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use presets_rs::{Kit, TrackAssignment};
+
+    use super::render_kit_preview;
+
+    #[test]
+    fn preview_auditions_each_assigned_track_then_plays_a_groove() {
+        let mut kit = Kit::default();
+        for (track_index, sample_id) in [(0, "kick"), (1, "snare"), (3, "hat"), (6, "perc")] {
+            kit.add_assignment(TrackAssignment {
+                track_index,
+                sample_id: sample_id.to_string(),
+            });
+        }
+        kit.add_assignment(TrackAssignment {
+            track_index: 7,
+            sample_id: "missing".to_string(),
+        });
+
+        let preview = render_kit_preview(&kit, 48_000, |sample_id| {
+            (sample_id != "missing").then(|| vec![0.5; 64])
+        })
+        .expect("preview");
+
+        // 120 BPM: two bars plus a beat of tail, with eighths 12000 samples apart.
+        assert_eq!(preview.audio.len(), 216_000);
+        assert_eq!(preview.missing_samples, vec!["missing".to_string()]);
+        let audition: Vec<(u8, u64)> = preview
+            .events
+            .iter()
+            .filter(|event| event.timeline_sample < 96_000)
+            .map(|event| (event.track_index, event.timeline_sample))
+            .collect();
+        assert_eq!(
+            audition,
+            vec![(0, 0), (1, 12_000), (3, 36_000), (6, 72_000), (7, 84_000)]
+        );
+        let groove = |track_index: u8| {
+            preview
+                .events
+                .iter()
+                .filter(|event| event.track_index == track_index && event.timeline_sample >= 96_000)
+                .count()
+        };
+        assert_eq!((groove(0), groove(1), groove(3), groove(6)), (3, 2, 8, 0));
+        assert!(preview
+            .events
+            .iter()
+            .all(|event| event.timeline_sample < 192_000));
+        assert!(preview.audio[36_000..36_064]
+            .iter()
+            .any(|sample| *sample != 0.0));
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::BTreeMap;
+
+use presets_rs::{Project, SetList};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StagedProject {
+    pub entry_index: usize,
+    pub project: Project,
+    pub tempo_bpm: Option<f32>,
+    pub samples: BTreeMap<String, Vec<f32>>,
+    pub missing_samples: Vec<String>,
+}
+
+impl StagedProject {
+    pub fn sample(&self, sample_id: &str) -> Option<Vec<f32>> {
+        self.samples.get(sample_id).cloned()
+    }
+}
+
+pub fn project_sample_ids(project: &Project) -> Vec<String> {
+    let mut sample_ids: Vec<String> = project
+        .kits
+        .iter()
+        .flat_map(|kit| kit.tracks.iter().map(|track| track.sample_id.clone()))
+        .chain(
+            project
+                .frozen_tracks
+                .iter()
+                .map(|freeze| freeze.bounce_sample_id.clone()),
+        )
+        .chain([
+            project.metronome.accent_sample_id.clone(),
+            project.metronome.click_sample_id.clone(),
+        ])
+        .filter(|sample_id| !sample_id.is_empty())
+        .collect();
+    sample_ids.sort();
+    sample_ids.dedup();
+    sample_ids
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SetListLoader {
+    setlist: SetList,
+    current: Option<StagedProject>,
+    staged: Option<StagedProject>,
+}
+
+impl SetListLoader {
+    pub fn new(setlist: SetList) -> Self {
+        Self {
+            setlist,
+            ..Self::default()
+        }
+    }
+
+    pub fn setlist(&self) -> &SetList {
+        &self.setlist
+    }
+
+    pub fn current(&self) -> Option<&StagedProject> {
+        self.current.as_ref()
+    }
+
+    pub fn staged(&self) -> Option<&StagedProject> {
+        self.staged.as_ref()
+    }
+
+    pub fn next_index(&self) -> usize {
+        self.current
+            .as_ref()
+            .map(|current| current.entry_index + 1)
+            .unwrap_or(0)
+    }
+
+    pub fn stage<P, F>(
+        &mut self,
+        entry_index: usize,
+        load_project: &mut P,
+        load_sample: &mut F,
+    ) -> Result<&StagedProject, String>
+    where
+        P: FnMut(&str) -> Result<Project, String>,
+        F: FnMut(&str) -> Option<Vec<f32>>,
+    {
+        let entry = self
+            .setlist
+            .entries
+            .get(entry_index)
+            .ok_or_else(|| format!("setlist entry out of range: {entry_index}"))?;
+        let mut project = load_project(&entry.project_path)?;
+        if !entry.apply_defaults(&mut project) {
+            return Err(format!(
+                "setlist entry {entry_index} start pattern out of range for {}",
+                entry.project_path
+            ));
+        }
+
+        let mut samples = BTreeMap::new();
+        let mut missing_samples = Vec::new();
+        for sample_id in project_sample_ids(&project) {
+            match load_sample(&sample_id).filter(|sample| !sample.is_empty()) {
+                Some(sample) => {
+                    samples.insert(sample_id, sample);
+                }
+                None => missing_samples.push(sample_id),
+            }
+        }
+
+        Ok(self.staged.insert(StagedProject {
+            entry_index,
+            project,
+            tempo_bpm: entry.tempo_bpm,
+            samples,
+            missing_samples,
+        }))
+    }
+
+    pub fn stage_next<P, F>(
+        &mut self,
+        load_project: &mut P,
+        load_sample: &mut F,
+    ) -> Result<Option<&StagedProject>, String>
+    where
+        P: FnMut(&str) -> Result<Project, String>,
+        F: FnMut(&str) -> Option<Vec<f32>>,
+    {
+        let next_index = self.next_index();
+        if next_index >= self.setlist.entries.len() {
+            self.staged = None;
+            return Ok(None);
+        }
+        self.stage(next_index, load_project, load_sample).map(Some)
+    }
+
+    pub fn advance(&mut self) -> Option<&StagedProject> {
+        let staged = self.staged.take()?;
+        self.current = Some(staged);
+        self.current.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use presets_rs::{Kit, Pattern, Project, SetList, SetListEntry, TrackAssignment};
+
+    use super::SetListLoader;
+
+    fn project_with(sample_id: &str) -> Project {
+        let mut kit = Kit::default();
+        assert!(kit.add_assignment(TrackAssignment {
+            track_index: 0,
+            sample_id: sample_id.to_string(),
+        }));
+        Project {
+            name: sample_id.to_string(),
+            kits: vec![kit],
+            patterns: vec![Pattern::default(); 2],
+            ..Project::default()
+        }
+    }
+
+    #[test]
+    fn next_project_is_pre_staged_with_its_samples_before_advancing() {
+        let setlist = SetList {
+            name: "gig".to_string(),
+            entries: vec![
+                SetListEntry {
+                    tempo_bpm: Some(128.0),
+                    ..SetListEntry::new("a.ffp")
+                },
+                SetListEntry {
+                    start_pattern: Some(1),
+                    ..SetListEntry::new("b.ffp")
+                },
+            ],
+        };
+        let mut loader = SetListLoader::new(setlist);
+        let mut load_project = |path: &str| match path {
+            "a.ffp" => Ok(project_with("kick.wav")),
+            "b.ffp" => Ok(project_with("missing.wav")),
+            _ => Err(format!("no project {path}")),
+        };
+        let mut sample_loads = Vec::new();
+        let mut load_sample = |sample_id: &str| {
+            sample_loads.push(sample_id.to_string());
+            (sample_id == "kick.wav").then(|| vec![0.25; 4])
+        };
+
+        let staged = loader
+            .stage_next(&mut load_project, &mut load_sample)
+            .unwrap()
+            .unwrap();
+        assert_eq!(staged.entry_index, 0);
+        assert_eq!(staged.tempo_bpm, Some(128.0));
+        assert_eq!(staged.sample("kick.wav"), Some(vec![0.25; 4]));
+        assert!(loader.current().is_none());
+        assert_eq!(loader.advance().map(|current| current.entry_index), Some(0));
+        assert!(loader.staged().is_none());
+
+        let staged = loader
+            .stage_next(&mut load_project, &mut load_sample)
+            .unwrap()
+            .unwrap();
+        assert_eq!(staged.project.active_pattern, Some(1));
+        assert_eq!(staged.missing_samples, vec!["missing.wav".to_string()]);
+        assert_eq!(loader.current().map(|current| current.entry_index), Some(0));
+        loader.advance();
+        assert_eq!(
+            loader
+                .stage_next(&mut load_project, &mut load_sample)
+                .unwrap(),
+            None
+        );
+        assert_eq!(sample_loads, vec!["kick.wav", "missing.wav"]);
+    }
+
+    #[test]
+    fn staging_reports_missing_projects_and_invalid_start_patterns() {
+        let mut loader = SetListLoader::new(SetList {
+            name: String::new(),
+            entries: vec![
+                SetListEntry::new("gone.ffp"),
+                SetListEntry {
+                    start_pattern: Some(4),
+                    ..SetListEntry::new("short.ffp")
+                },
+            ],
+        });
+        let mut load_project = |path: &str| {
+            if path == "short.ffp" {
+                Ok(project_with("kick.wav"))
+            } else {
+                Err(format!("no project {path}"))
+            }
+        };
+        let mut load_sample = |_: &str| Some(vec![0.0]);
+        assert!(loader
+            .stage(0, &mut load_project, &mut load_sample)
+            .is_err());
+        assert!(loader
+            .stage(1, &mut load_project, &mut load_sample)
+            .is_err());
+        assert!(loader
+            .stage(2, &mut load_project, &mut load_sample)
+            .is_err());
+        assert!(loader.staged().is_none());
+    }
+}
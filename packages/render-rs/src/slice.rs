@@ -0,0 +1,181 @@
+use presets_rs::{Kit, Pattern, PatternStep, TrackAssignment, STEPS_PER_PATTERN, TRACK_COUNT};
+
+pub const TRANSIENT_HOP_FRAMES: usize = 256;
+pub const TRANSIENT_MIN_GAP_SECONDS: f32 = 0.05;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SliceMode {
+    Even(usize),
+    Transients { max_slices: usize, sensitivity: f32 },
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SliceRegion {
+    pub start_frame: u64,
+    pub end_frame: u64,
+}
+
+pub(crate) fn onset_envelope(samples: &[f32]) -> (Vec<f32>, Vec<f32>) {
+    let energy: Vec<f32> = samples
+        .chunks(TRANSIENT_HOP_FRAMES)
+        .map(|hop| (hop.iter().map(|value| value * value).sum::<f32>() / hop.len() as f32).sqrt())
+        .collect();
+    let flux: Vec<f32> = energy
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let previous = match index {
+                0 => 0.0,
+                1 => energy[0],
+                _ => energy[index - 1].min(energy[index - 2]),
+            };
+            (value - previous).max(0.0)
+        })
+        .collect();
+
+    (energy, flux)
+}
+
+pub fn detect_transients(
+    samples: &[f32],
+    sample_rate_hz: u32,
+    sensitivity: f32,
+    max_onsets: usize,
+) -> Vec<usize> {
+    if samples.is_empty() || max_onsets == 0 {
+        return Vec::new();
+    }
+
+    let (energy, flux) = onset_envelope(samples);
+    let peak_flux = flux.iter().copied().fold(0.0f32, f32::max);
+    if peak_flux <= f32::EPSILON {
+        return vec![0];
+    }
+    let threshold = peak_flux * (1.0 - sensitivity.clamp(0.0, 1.0)).max(0.02);
+    let min_gap_hops = ((TRANSIENT_MIN_GAP_SECONDS * sample_rate_hz as f32)
+        / TRANSIENT_HOP_FRAMES as f32)
+        .ceil()
+        .max(1.0) as usize;
+
+    let mut candidates: Vec<(usize, f32)> = Vec::new();
+    for (index, value) in flux.iter().enumerate() {
+        let is_peak = *value >= threshold
+            && (index == 0 || *value >= flux[index - 1])
+            && flux.get(index + 1).is_none_or(|next| *value > *next);
+        if !is_peak {
+            continue;
+        }
+        match candidates.last_mut() {
+            Some(last) if index - last.0 < min_gap_hops => {
+                if *value > last.1 {
+                    *last = (index, *value);
+                }
+            }
+            _ => candidates.push((index, *value)),
+        }
+    }
+
+    candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+    candidates.truncate(max_onsets);
+    let mut onsets: Vec<usize> = candidates
+        .into_iter()
+        .map(|(index, _)| rising_edge_start(&energy, index) * TRANSIENT_HOP_FRAMES)
+        .collect();
+    onsets.sort_unstable();
+    onsets.dedup();
+    onsets
+}
+
+fn rising_edge_start(energy: &[f32], peak_hop: usize) -> usize {
+    let mut start = peak_hop;
+    while start > 0 {
+        let before = if start >= 2 { energy[start - 2] } else { 0.0 };
+        if energy[start - 1] <= before + 1e-4 {
+            break;
+        }
+        start -= 1;
+    }
+    start
+}
+
+pub fn slice_regions(samples: &[f32], sample_rate_hz: u32, mode: SliceMode) -> Vec<SliceRegion> {
+    let length = samples.len();
+    if length == 0 {
+        return Vec::new();
+    }
+
+    let mut starts = match mode {
+        SliceMode::Even(count) => {
+            let count = count.clamp(1, length);
+            (0..count).map(|index| index * length / count).collect()
+        }
+        SliceMode::Transients {
+            max_slices,
+            sensitivity,
+        } => detect_transients(samples, sample_rate_hz, sensitivity, max_slices),
+    };
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+        if let SliceMode::Transients { max_slices, .. } = mode {
+            starts.truncate(max_slices.max(1));
+        }
+    }
+    starts.dedup();
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(index, start)| SliceRegion {
+            start_frame: *start as u64,
+            end_frame: starts.get(index + 1).copied().unwrap_or(length) as u64,
+        })
+        .collect()
+}
+
+pub fn slice_to_kit(
+    name: &str,
+    sample_id: &str,
+    samples: &[f32],
+    sample_rate_hz: u32,
+    mode: SliceMode,
+) -> (Kit, Pattern) {
+    let mut regions = slice_regions(samples, sample_rate_hz, mode);
+    regions.truncate(TRACK_COUNT);
+
+    let mut kit = Kit {
+        name: name.to_string(),
+        ..Kit::default()
+    };
+    let mut pattern = Pattern {
+        name: name.to_string(),
+        ..Pattern::default()
+    };
+    let slice_count = regions.len().max(1);
+    for (track_index, region) in regions.iter().enumerate() {
+        let track = track_index as u8;
+        kit.add_assignment(TrackAssignment {
+            track_index: track,
+            sample_id: sample_id.to_string(),
+        });
+        kit.set_track_region(track, region.start_frame, region.end_frame);
+        pattern.set_step(
+            track_index,
+            track_index * STEPS_PER_PATTERN / slice_count,
+            PatternStep {
+                active: true,
+                velocity: 100,
+                ..PatternStep::default()
+            },
+        );
+    }
+    (kit, pattern)
+}
+
+pub fn apply_region(sample: Vec<f32>, start_frame: u64, end_frame: u64) -> Vec<f32> {
+    let start = (start_frame as usize).min(sample.len());
+    let end = (end_frame as usize).clamp(start, sample.len());
+    if start == 0 && end == sample.len() {
+        return sample;
+    }
+    sample[start..end].to_vec()
+}
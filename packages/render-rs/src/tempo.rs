@@ -0,0 +1,120 @@
+use crate::slice::{onset_envelope, TRANSIENT_HOP_FRAMES};
+
+pub const TEMPO_DETECT_MIN_BPM: f32 = 80.0;
+pub const TEMPO_DETECT_MAX_BPM: f32 = 160.0;
+pub const TEMPO_MIN_CONFIDENCE: f32 = 0.2;
+pub const TEMPO_BAR_SNAP_TOLERANCE: f64 = 0.05;
+pub const TEMPO_MATCH_TOLERANCE_BPM: f32 = 0.5;
+const BEATS_PER_BAR: f64 = 4.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TempoEstimate {
+    pub bpm: f32,
+    pub confidence: f32,
+    pub bars: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoopTempoAction {
+    Matches,
+    SetProjectTempo(f32),
+    TimeStretch { ratio: f32 },
+    Undetected,
+}
+
+pub fn estimate_tempo(samples: &[f32], sample_rate_hz: u32) -> Option<TempoEstimate> {
+    if sample_rate_hz == 0 {
+        return None;
+    }
+    let (_, flux) = onset_envelope(samples);
+    let hops_per_minute = 60.0 * sample_rate_hz as f32 / TRANSIENT_HOP_FRAMES as f32;
+    let min_lag = (hops_per_minute / TEMPO_DETECT_MAX_BPM).floor().max(1.0) as usize;
+    let max_lag = (hops_per_minute / TEMPO_DETECT_MIN_BPM).ceil() as usize;
+    if flux.len() < max_lag * 2 {
+        return None;
+    }
+
+    let mean = flux.iter().sum::<f32>() / flux.len() as f32;
+    let centered: Vec<f32> = flux.iter().map(|value| value - mean).collect();
+    let autocorrelation = |lag: usize| -> f32 {
+        centered
+            .iter()
+            .zip(&centered[lag..])
+            .map(|(a, b)| a * b)
+            .sum::<f32>()
+            / (centered.len() - lag) as f32
+    };
+    let zero_lag = autocorrelation(0);
+    if zero_lag <= f32::EPSILON {
+        return None;
+    }
+
+    let scores: Vec<f32> = (min_lag..=max_lag + 1)
+        .map(|lag| {
+            let double = if lag * 2 < centered.len() {
+                autocorrelation(lag * 2)
+            } else {
+                0.0
+            };
+            autocorrelation(lag) + 0.5 * double
+        })
+        .collect();
+    let (best_offset, best_score) = scores[..scores.len() - 1]
+        .iter()
+        .copied()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    let previous = if best_offset > 0 {
+        scores[best_offset - 1]
+    } else {
+        best_score
+    };
+    let next = scores[best_offset + 1];
+    let curvature = previous - 2.0 * best_score + next;
+    let refinement = if curvature.abs() > f32::EPSILON {
+        (0.5 * (previous - next) / curvature).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    let lag = (min_lag + best_offset) as f32 + refinement;
+    let mut bpm = hops_per_minute / lag;
+    let confidence = (autocorrelation(min_lag + best_offset) / zero_lag).clamp(0.0, 1.0);
+
+    let beats = samples.len() as f64 * f64::from(bpm) / (60.0 * f64::from(sample_rate_hz));
+    let bar_count = (beats / BEATS_PER_BAR).round();
+    let mut bars = None;
+    if bar_count >= 1.0
+        && ((beats - bar_count * BEATS_PER_BAR).abs() / beats) <= TEMPO_BAR_SNAP_TOLERANCE
+    {
+        bpm = (bar_count * BEATS_PER_BAR * 60.0 * f64::from(sample_rate_hz) / samples.len() as f64)
+            as f32;
+        bars = Some(bar_count as u32);
+    }
+
+    Some(TempoEstimate {
+        bpm,
+        confidence,
+        bars,
+    })
+}
+
+pub fn suggest_loop_tempo(
+    estimate: Option<TempoEstimate>,
+    project_bpm: f32,
+    project_has_audio: bool,
+) -> LoopTempoAction {
+    let Some(estimate) = estimate.filter(|estimate| estimate.confidence >= TEMPO_MIN_CONFIDENCE)
+    else {
+        return LoopTempoAction::Undetected;
+    };
+    if (estimate.bpm - project_bpm).abs() <= TEMPO_MATCH_TOLERANCE_BPM {
+        LoopTempoAction::Matches
+    } else if project_has_audio {
+        LoopTempoAction::TimeStretch {
+            ratio: project_bpm / estimate.bpm,
+        }
+    } else {
+        LoopTempoAction::SetProjectTempo(estimate.bpm)
+    }
+}
@@ -0,0 +1,134 @@
+#[derive(Clone, Debug, PartialEq)]
+pub struct WavData {
+    pub sample_rate_hz: u32,
+    pub channels: u16,
+    pub bits_per_sample: u16,
+    pub samples: Vec<f32>,
+}
+
+impl WavData {
+    pub fn frame_count(&self) -> usize {
+        self.samples.len() / usize::from(self.channels.max(1))
+    }
+
+    pub fn to_mono(&self) -> Vec<f32> {
+        let channels = usize::from(self.channels.max(1));
+        self.samples
+            .chunks(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect()
+    }
+}
+
+pub fn read_wav(bytes: &[u8]) -> Result<WavData, String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("not a RIFF/WAVE file".to_string());
+    }
+
+    let mut format: Option<(u16, u16, u32, u16)> = None;
+    let mut data: Option<&[u8]> = None;
+    let mut cursor = 12;
+    while cursor + 8 <= bytes.len() {
+        let chunk_id = &bytes[cursor..cursor + 4];
+        let chunk_size = read_u32(bytes, cursor + 4)? as usize;
+        let body_start = cursor + 8;
+        let body_end = body_start
+            .checked_add(chunk_size)
+            .filter(|end| *end <= bytes.len())
+            .ok_or_else(|| "truncated wav chunk".to_string())?;
+        let body = &bytes[body_start..body_end];
+
+        if chunk_id == b"fmt " {
+            if body.len() < 16 {
+                return Err("wav fmt chunk too small".to_string());
+            }
+            format = Some((
+                read_u16(body, 0)?,
+                read_u16(body, 2)?,
+                read_u32(body, 4)?,
+                read_u16(body, 14)?,
+            ));
+        } else if chunk_id == b"data" {
+            data = Some(body);
+        }
+
+        cursor = body_end + (chunk_size % 2);
+    }
+
+    let (format_tag, channels, sample_rate_hz, bits_per_sample) =
+        format.ok_or_else(|| "wav file has no fmt chunk".to_string())?;
+    let data = data.ok_or_else(|| "wav file has no data chunk".to_string())?;
+    if channels == 0 || sample_rate_hz == 0 {
+        return Err("wav file has invalid channel count or sample rate".to_string());
+    }
+
+    let samples = match (format_tag, bits_per_sample) {
+        (1, 16) => data
+            .chunks_exact(2)
+            .map(|chunk| f32::from(i16::from_le_bytes([chunk[0], chunk[1]])) / 32_768.0)
+            .collect(),
+        (1, 24) => data
+            .chunks_exact(3)
+            .map(|chunk| {
+                let value = i32::from_le_bytes([0, chunk[0], chunk[1], chunk[2]]) >> 8;
+                value as f32 / 8_388_608.0
+            })
+            .collect(),
+        (3, 32) => data
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect(),
+        _ => {
+            return Err(format!(
+                "unsupported wav encoding: format={format_tag}, bits={bits_per_sample}"
+            ))
+        }
+    };
+
+    Ok(WavData {
+        sample_rate_hz,
+        channels,
+        bits_per_sample,
+        samples,
+    })
+}
+
+pub fn write_wav_16(samples: &[f32], channels: u16, sample_rate_hz: u32) -> Vec<u8> {
+    let channels = channels.max(1);
+    let data_size = (samples.len() * 2) as u32;
+    let block_align = channels * 2;
+
+    let mut bytes = Vec::with_capacity(44 + samples.len() * 2);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_size).to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+    bytes.extend_from_slice(b"fmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&channels.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate_hz.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate_hz * u32::from(block_align)).to_le_bytes());
+    bytes.extend_from_slice(&block_align.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_size.to_le_bytes());
+    for sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * 32_767.0).round() as i16;
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16, String> {
+    bytes
+        .get(offset..offset + 2)
+        .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
+        .ok_or_else(|| "truncated wav header".to_string())
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, String> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .ok_or_else(|| "truncated wav header".to_string())
+}
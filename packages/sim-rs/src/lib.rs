@@ -0,0 +1,474 @@
+use std::fmt;
+
+use control_rs::{Sequencer, StepTriggerEvent};
+use midi_rs::clock::{parse_midi_realtime, ClockTransportChange, MidiClockFollower};
+use midi_rs::{LearnTarget, MappingProfile, MidiMessage, NoteMap};
+
+pub const CLOCK_TEMPO_TOLERANCE_BPM: f32 = 0.05;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SimInput {
+    Midi(Vec<u8>),
+    BeginLearn(LearnTarget),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimedInput {
+    pub at_sample: u64,
+    pub input: SimInput,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SimTimeline {
+    block_sizes: Vec<u32>,
+    inputs: Vec<TimedInput>,
+}
+
+impl SimTimeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn fixed_blocks(block_frames: u32, block_count: usize) -> Self {
+        Self {
+            block_sizes: vec![block_frames; block_count],
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn block(mut self, frames: u32) -> Self {
+        self.block_sizes.push(frames);
+        self
+    }
+
+    pub fn blocks(mut self, frames: impl IntoIterator<Item = u32>) -> Self {
+        self.block_sizes.extend(frames);
+        self
+    }
+
+    pub fn midi(mut self, at_sample: u64, bytes: &[u8]) -> Self {
+        self.inputs.push(TimedInput {
+            at_sample,
+            input: SimInput::Midi(bytes.to_vec()),
+        });
+        self
+    }
+
+    pub fn begin_learn(mut self, at_sample: u64, target: LearnTarget) -> Self {
+        self.inputs.push(TimedInput {
+            at_sample,
+            input: SimInput::BeginLearn(target),
+        });
+        self
+    }
+
+    pub fn block_sizes(&self) -> &[u32] {
+        &self.block_sizes
+    }
+
+    pub fn total_frames(&self) -> u64 {
+        self.block_sizes
+            .iter()
+            .map(|frames| u64::from(*frames))
+            .sum()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SimEvent {
+    Trigger(StepTriggerEvent),
+    Pad {
+        at_sample: u64,
+        track_index: u8,
+        velocity: u8,
+    },
+    Recorded {
+        at_sample: u64,
+        track_index: u8,
+        velocity: u8,
+    },
+    Parameter {
+        at_sample: u64,
+        parameter_id: String,
+        value: u8,
+    },
+    Learned {
+        at_sample: u64,
+        cc: u8,
+        parameter_id: String,
+    },
+    Transport {
+        at_sample: u64,
+        change: ClockTransportChange,
+    },
+    Tempo {
+        at_sample: u64,
+        bpm: f32,
+    },
+    Ignored {
+        at_sample: u64,
+        bytes: Vec<u8>,
+    },
+}
+
+impl fmt::Display for SimEvent {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Trigger(event) => write!(
+                formatter,
+                "{} trigger track={} step={} velocity={}",
+                event.timeline_sample, event.track_index, event.step_index, event.velocity
+            ),
+            Self::Pad {
+                at_sample,
+                track_index,
+                velocity,
+            } => write!(
+                formatter,
+                "{at_sample} pad track={track_index} velocity={velocity}"
+            ),
+            Self::Recorded {
+                at_sample,
+                track_index,
+                velocity,
+            } => write!(
+                formatter,
+                "{at_sample} recorded track={track_index} velocity={velocity}"
+            ),
+            Self::Parameter {
+                at_sample,
+                parameter_id,
+                value,
+            } => write!(formatter, "{at_sample} parameter {parameter_id}={value}"),
+            Self::Learned {
+                at_sample,
+                cc,
+                parameter_id,
+            } => write!(formatter, "{at_sample} learned cc={cc} -> {parameter_id}"),
+            Self::Transport { at_sample, change } => {
+                write!(formatter, "{at_sample} transport {change:?}")
+            }
+            Self::Tempo { at_sample, bpm } => write!(formatter, "{at_sample} tempo {bpm:.3}"),
+            Self::Ignored { at_sample, bytes } => {
+                write!(formatter, "{at_sample} ignored {bytes:02X?}")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SimLog {
+    pub events: Vec<SimEvent>,
+}
+
+impl SimLog {
+    pub fn lines(&self) -> Vec<String> {
+        self.events.iter().map(ToString::to_string).collect()
+    }
+
+    pub fn triggers(&self) -> impl Iterator<Item = &StepTriggerEvent> {
+        self.events.iter().filter_map(|event| match event {
+            SimEvent::Trigger(trigger) => Some(trigger),
+            _ => None,
+        })
+    }
+
+    pub fn first_divergence(&self, other: &SimLog) -> Option<usize> {
+        let shared = self.events.len().min(other.events.len());
+        (0..shared)
+            .find(|index| self.events[*index] != other.events[*index])
+            .or_else(|| (self.events.len() != other.events.len()).then_some(shared))
+    }
+}
+
+#[derive(Debug)]
+pub struct Simulation {
+    sequencer: Sequencer,
+    mapping: MappingProfile,
+    note_map: NoteMap,
+    clock: MidiClockFollower,
+    follow_clock: bool,
+}
+
+impl Simulation {
+    pub fn new(sample_rate_hz: u32) -> Self {
+        Self {
+            sequencer: Sequencer::new(sample_rate_hz),
+            mapping: MappingProfile::default(),
+            note_map: NoteMap::default(),
+            clock: MidiClockFollower::new(sample_rate_hz),
+            follow_clock: true,
+        }
+    }
+
+    pub fn from_sequencer(sequencer: Sequencer) -> Self {
+        let sample_rate_hz = sequencer.sample_rate_hz();
+        Self {
+            sequencer,
+            ..Self::new(sample_rate_hz)
+        }
+    }
+
+    pub fn set_follow_clock(&mut self, follow_clock: bool) {
+        self.follow_clock = follow_clock;
+    }
+
+    pub fn sequencer(&self) -> &Sequencer {
+        &self.sequencer
+    }
+
+    pub fn sequencer_mut(&mut self) -> &mut Sequencer {
+        &mut self.sequencer
+    }
+
+    pub fn mapping_mut(&mut self) -> &mut MappingProfile {
+        &mut self.mapping
+    }
+
+    pub fn note_map_mut(&mut self) -> &mut NoteMap {
+        &mut self.note_map
+    }
+
+    pub fn run(&mut self, timeline: &SimTimeline) -> SimLog {
+        let mut inputs = timeline.inputs.clone();
+        inputs.sort_by_key(|input| input.at_sample);
+
+        let mut log = SimLog::default();
+        let mut next_input = 0;
+        let mut block_start = 0u64;
+        for frames in &timeline.block_sizes {
+            let block_end = block_start + u64::from(*frames);
+            while next_input < inputs.len() && inputs[next_input].at_sample < block_end {
+                self.apply_input(&inputs[next_input], &mut log);
+                next_input += 1;
+            }
+
+            log.events.extend(
+                self.sequencer
+                    .process_block(*frames)
+                    .into_iter()
+                    .map(SimEvent::Trigger),
+            );
+            block_start = block_end;
+        }
+        log
+    }
+
+    fn apply_input(&mut self, timed: &TimedInput, log: &mut SimLog) {
+        let at_sample = timed.at_sample;
+        let bytes = match &timed.input {
+            SimInput::BeginLearn(target) => {
+                self.mapping.begin_learn(*target);
+                return;
+            }
+            SimInput::Midi(bytes) => bytes,
+        };
+
+        if let Some(realtime) = parse_midi_realtime(bytes) {
+            match self.clock.handle(realtime, at_sample) {
+                Some(change) => {
+                    match change {
+                        ClockTransportChange::Start => {
+                            self.sequencer.stop();
+                            self.sequencer.reset();
+                            self.sequencer.start();
+                        }
+                        ClockTransportChange::Continue => self.sequencer.start(),
+                        ClockTransportChange::Stop => self.sequencer.stop(),
+                    }
+                    log.events.push(SimEvent::Transport { at_sample, change });
+                }
+                None => self.follow_clock_tempo(at_sample, log),
+            }
+            return;
+        }
+
+        match midi_rs::parse_midi_message(bytes) {
+            Some(MidiMessage::NoteOn { note, velocity, .. }) => {
+                let trigger = midi_rs::note_on_to_pad_trigger(&self.note_map, note, velocity);
+                let route = trigger
+                    .as_ref()
+                    .map(|trigger| {
+                        self.sequencer
+                            .route_pad_input(usize::from(trigger.track_index))
+                    })
+                    .unwrap_or_default();
+                match trigger {
+                    Some(trigger) if route.monitor || route.record => {
+                        if route.monitor {
+                            log.events.push(SimEvent::Pad {
+                                at_sample,
+                                track_index: trigger.track_index,
+                                velocity: trigger.velocity,
+                            });
+                        }
+                        if route.record {
+                            log.events.push(SimEvent::Recorded {
+                                at_sample,
+                                track_index: trigger.track_index,
+                                velocity: trigger.velocity,
+                            });
+                        }
+                    }
+                    _ => log.events.push(SimEvent::Ignored {
+                        at_sample,
+                        bytes: bytes.clone(),
+                    }),
+                }
+            }
+            Some(
+                message @ MidiMessage::ControlChange {
+                    controller, value, ..
+                },
+            ) => {
+                if let Some(binding) = self.mapping.handle_message_for_learn(message) {
+                    log.events.push(SimEvent::Learned {
+                        at_sample,
+                        cc: binding.cc,
+                        parameter_id: binding.parameter_id,
+                    });
+                } else if let Some(parameter_id) = self
+                    .mapping
+                    .handle_control_change(controller, value, at_sample)
+                {
+                    log.events.push(SimEvent::Parameter {
+                        at_sample,
+                        parameter_id: parameter_id.to_string(),
+                        value,
+                    });
+                } else {
+                    log.events.push(SimEvent::Ignored {
+                        at_sample,
+                        bytes: bytes.clone(),
+                    });
+                }
+            }
+            Some(MidiMessage::NoteOff { .. }) => {}
+            None => log.events.push(SimEvent::Ignored {
+                at_sample,
+                bytes: bytes.clone(),
+            }),
+        }
+    }
+
+    fn follow_clock_tempo(&mut self, at_sample: u64, log: &mut SimLog) {
+        if !self.follow_clock {
+            return;
+        }
+        let Some(bpm) = self.clock.stable_bpm() else {
+            return;
+        };
+
+        let previous = self.sequencer.transport().bpm();
+        self.sequencer.set_tempo_bpm(bpm);
+        let applied = self.sequencer.transport().bpm();
+        if (applied - previous).abs() > CLOCK_TEMPO_TOLERANCE_BPM {
+            log.events.push(SimEvent::Tempo {
+                at_sample,
+                bpm: applied,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use control_rs::Step;
+    use midi_rs::LearnTarget;
+
+    use super::{SimEvent, SimTimeline, Simulation};
+
+    fn simulation() -> Simulation {
+        let mut simulation = Simulation::new(48_000);
+        simulation.sequencer_mut().pattern_mut().set_step(
+            0,
+            0,
+            Step {
+                active: true,
+                velocity: 100,
+                ..Step::default()
+            },
+        );
+        simulation.note_map_mut().bind_note(36, 0);
+        simulation
+    }
+
+    fn clock_timeline(block_frames: u32, block_count: usize) -> SimTimeline {
+        let mut timeline = SimTimeline::fixed_blocks(block_frames, block_count).midi(0, &[0xFA]);
+        for tick in 0..96u64 {
+            timeline = timeline.midi(tick * 1_250, &[0xF8]);
+        }
+        timeline
+    }
+
+    #[test]
+    fn log_is_reproducible_and_tracks_clock_tempo() {
+        let timeline = clock_timeline(256, 500);
+        let first = simulation().run(&timeline);
+        let second = simulation().run(&timeline);
+        assert_eq!(first, second);
+        assert_eq!(first.first_divergence(&second), None);
+
+        let tempo = first
+            .events
+            .iter()
+            .find_map(|event| match event {
+                SimEvent::Tempo { bpm, .. } => Some(*bpm),
+                _ => None,
+            })
+            .expect("tempo event");
+        assert!((tempo - 96.0).abs() < 0.01);
+        assert_eq!(first.triggers().count(), 2);
+        assert!(first.lines()[0].starts_with("0 transport Start"));
+    }
+
+    #[test]
+    fn block_size_changes_are_reported_as_divergence() {
+        let small = simulation().run(&clock_timeline(64, 2_000));
+        let large = simulation().run(&clock_timeline(512, 250));
+        assert!(small.first_divergence(&large).is_some());
+        assert_eq!(small.triggers().count(), large.triggers().count());
+    }
+
+    #[test]
+    fn learn_then_control_change_maps_to_parameter() {
+        let timeline = SimTimeline::fixed_blocks(128, 4)
+            .begin_learn(0, LearnTarget::TrackGain(2))
+            .midi(10, &[0xB0, 20, 64])
+            .midi(200, &[0xB0, 20, 90])
+            .midi(300, &[0x90, 36, 110])
+            .midi(400, &[0x90, 40, 110]);
+        let log = simulation().run(&timeline);
+        assert_eq!(
+            log.lines(),
+            vec![
+                "10 learned cc=20 -> engine.track.2.gain".to_string(),
+                "200 parameter engine.track.2.gain=90".to_string(),
+                "300 pad track=0 velocity=110".to_string(),
+                "400 ignored [90, 28, 6E]".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn armed_tracks_record_pads_and_unmonitored_tracks_stay_silent() {
+        let mut simulation = simulation();
+        simulation.note_map_mut().bind_note(38, 1);
+        simulation.note_map_mut().bind_note(42, 2);
+        assert!(simulation.sequencer_mut().set_track_armed(0, true));
+        assert!(simulation.sequencer_mut().set_track_monitoring(1, false));
+        let timeline = SimTimeline::fixed_blocks(128, 2)
+            .midi(10, &[0x90, 36, 100])
+            .midi(20, &[0x90, 38, 100])
+            .midi(30, &[0x90, 42, 90]);
+        assert_eq!(
+            simulation.run(&timeline).lines(),
+            vec![
+                "10 pad track=0 velocity=100".to_string(),
+                "10 recorded track=0 velocity=100".to_string(),
+                "20 ignored [90, 26, 64]".to_string(),
+                "30 pad track=2 velocity=90".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use control_rs::{Sequencer, Step, StepTriggerEvent};
+use midi_rs::MidiMessage;
+use presets_rs::{PatternStep, Project};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct WasmTriggerEvent {
+    pub track_index: u8,
+    pub step_index: u8,
+    pub velocity: u8,
+    pub choke_group: i16,
+    pub block_offset: u32,
+    timeline_sample: u64,
+}
+
+#[wasm_bindgen]
+impl WasmTriggerEvent {
+    #[wasm_bindgen(getter)]
+    pub fn timeline_sample(&self) -> f64 {
+        self.timeline_sample as f64
+    }
+}
+
+impl From<StepTriggerEvent> for WasmTriggerEvent {
+    fn from(event: StepTriggerEvent) -> Self {
+        Self {
+            track_index: event.track_index,
+            step_index: event.step_index,
+            velocity: event.velocity,
+            choke_group: event.choke_group.map(i16::from).unwrap_or(-1),
+            block_offset: event.block_offset,
+            timeline_sample: event.timeline_sample,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct WasmSequencer {
+    inner: Sequencer,
+}
+
+#[wasm_bindgen]
+impl WasmSequencer {
+    #[wasm_bindgen(constructor)]
+    pub fn new(sample_rate_hz: u32) -> Self {
+        Self {
+            inner: Sequencer::new(sample_rate_hz),
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.inner.start();
+    }
+
+    pub fn stop(&mut self) {
+        self.inner.stop();
+    }
+
+    pub fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.inner.transport().is_playing()
+    }
+
+    pub fn set_tempo_bpm(&mut self, bpm: f32) {
+        self.inner.set_tempo_bpm(bpm);
+    }
+
+    pub fn tempo_bpm(&self) -> f32 {
+        self.inner.transport().bpm()
+    }
+
+    pub fn set_swing(&mut self, swing: f32) {
+        self.inner.set_swing(swing);
+    }
+
+    pub fn swing(&self) -> f32 {
+        self.inner.swing()
+    }
+
+    pub fn set_track_choke_group(&mut self, track_index: usize, choke_group: i16) -> bool {
+        self.inner
+            .set_track_choke_group(track_index, u8::try_from(choke_group).ok())
+    }
+
+    pub fn set_step(
+        &mut self,
+        track_index: usize,
+        step_index: usize,
+        active: bool,
+        velocity: u8,
+    ) -> bool {
+        self.inner.pattern_mut().set_step(
+            track_index,
+            step_index,
+            Step {
+                active,
+                velocity: velocity.min(127),
+                ..Step::default()
+            },
+        )
+    }
+
+    pub fn step_active(&self, track_index: usize, step_index: usize) -> bool {
+        self.inner
+            .pattern()
+            .step(track_index, step_index)
+            .is_some_and(|step| step.active)
+    }
+
+    pub fn step_velocity(&self, track_index: usize, step_index: usize) -> Option<u8> {
+        self.inner
+            .pattern()
+            .step(track_index, step_index)
+            .map(|step| step.velocity)
+    }
+
+    pub fn current_step(&self) -> usize {
+        self.inner.position().step_index
+    }
+
+    pub fn process_block(&mut self, frames: u32) -> Vec<WasmTriggerEvent> {
+        self.inner
+            .process_block(frames)
+            .into_iter()
+            .map(WasmTriggerEvent::from)
+            .collect()
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct WasmProject {
+    inner: Project,
+}
+
+#[wasm_bindgen]
+impl WasmProject {
+    pub fn load(text: &str) -> Result<WasmProject, String> {
+        presets_rs::load_project_from_text(text).map(|inner| Self { inner })
+    }
+
+    pub fn save(&self) -> String {
+        presets_rs::save_project_to_text(&self.inner)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.inner.name.clone()
+    }
+
+    pub fn kit_count(&self) -> usize {
+        self.inner.kits.len()
+    }
+
+    pub fn pattern_count(&self) -> usize {
+        self.inner.patterns.len()
+    }
+
+    pub fn set_active_pattern(&mut self, index: usize) -> bool {
+        self.inner.set_active_pattern(index)
+    }
+
+    pub fn set_pattern_step(
+        &mut self,
+        pattern_index: usize,
+        track_index: usize,
+        step_index: usize,
+        active: bool,
+        velocity: u8,
+    ) -> bool {
+        self.inner
+            .patterns
+            .get_mut(pattern_index)
+            .is_some_and(|pattern| {
+                pattern.set_step(
+                    track_index,
+                    step_index,
+                    PatternStep {
+                        active,
+                        velocity: velocity.min(127),
+                        ..PatternStep::default()
+                    },
+                )
+            })
+    }
+
+    pub fn set_pattern_swing(&mut self, pattern_index: usize, swing: f32) -> bool {
+        match self.inner.patterns.get_mut(pattern_index) {
+            Some(pattern) => {
+                pattern.set_swing(swing);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn sequencer(&self, sample_rate_hz: u32) -> Result<WasmSequencer, String> {
+        let inner =
+            control_rs::recall_state_from_project(&self.inner, sample_rate_hz)?.into_sequencer();
+        Ok(WasmSequencer { inner })
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WasmMidiMessage {
+    kind: String,
+    pub channel: u8,
+    pub data1: u8,
+    pub data2: u8,
+}
+
+#[wasm_bindgen]
+impl WasmMidiMessage {
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> String {
+        self.kind.clone()
+    }
+}
+
+#[wasm_bindgen]
+pub fn parse_midi(bytes: &[u8]) -> Option<WasmMidiMessage> {
+    let (kind, channel, data1, data2) = match midi_rs::parse_midi_message(bytes)? {
+        MidiMessage::NoteOn {
+            channel,
+            note,
+            velocity,
+        } => ("note_on", channel, note, velocity),
+        MidiMessage::NoteOff {
+            channel,
+            note,
+            velocity,
+        } => ("note_off", channel, note, velocity),
+        MidiMessage::ControlChange {
+            channel,
+            controller,
+            value,
+        } => ("control_change", channel, controller, value),
+    };
+
+    Some(WasmMidiMessage {
+        kind: kind.to_string(),
+        channel,
+        data1,
+        data2,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_midi, WasmProject, WasmSequencer};
+
+    const STARTER_PROJECT: &str = include_str!("../../../assets/starter-kit/default.ffproject");
+
+    #[test]
+    fn sequencer_binding_matches_native_sequencer() {
+        let mut binding = WasmSequencer::new(48_000);
+        assert!(binding.set_step(2, 0, true, 200));
+        assert_eq!(binding.step_velocity(2, 0), Some(127));
+        binding.start();
+
+        let events = binding.process_block(128);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].track_index, 2);
+        assert_eq!(events[0].choke_group, -1);
+        assert_eq!(events[0].timeline_sample(), 0.0);
+    }
+
+    #[test]
+    fn project_binding_loads_edits_and_builds_sequencer() {
+        let mut project = WasmProject::load(STARTER_PROJECT).expect("starter project");
+        assert_eq!(project.kit_count(), 1);
+        assert!(project.set_pattern_step(0, 7, 15, true, 90));
+        assert!(!project.set_pattern_step(3, 0, 0, true, 90));
+
+        let reloaded = WasmProject::load(&project.save()).expect("reload");
+        let sequencer = reloaded.sequencer(48_000).expect("sequencer");
+        assert!(sequencer.step_active(7, 15));
+        assert!(WasmProject::load("not a project").is_err());
+    }
+
+    #[test]
+    fn midi_binding_reports_message_kind() {
+        let message = parse_midi(&[0xB2, 74, 10]).expect("control change");
+        assert_eq!(message.kind(), "control_change");
+        assert_eq!(message.channel, 2);
+        assert_eq!(parse_midi(&[0xF8, 0, 0]), None);
+    }
+}